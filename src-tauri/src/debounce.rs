@@ -0,0 +1,92 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::AppError;
+
+// Longest a change waits before it is written, like the file watcher's default
+// debounce: short enough that a killed app loses at most that much, long enough
+// to coalesce a burst of changes into one write
+pub const FLUSH_DELAY: Duration = Duration::from_millis(500);
+
+type WriteFn = dyn Fn() -> Result<(), AppError> + Send + Sync;
+
+// Coalesces writes of in-memory state to disk. `changed` marks the state as
+// unsaved and runs `write` on a timer thread `FLUSH_DELAY` after the first of
+// a burst of changes; the delay doesn't restart with each change, so steady
+// updates are still written every `FLUSH_DELAY`.
+#[derive(Clone)]
+pub struct DebouncedWrite {
+    write: Arc<WriteFn>,
+    unsaved: Arc<AtomicBool>,
+    scheduled: Arc<AtomicBool>,
+}
+
+impl DebouncedWrite {
+    pub fn new(write: impl Fn() -> Result<(), AppError> + Send + Sync + 'static) -> Self {
+        DebouncedWrite {
+            write: Arc::new(write),
+            unsaved: Arc::new(AtomicBool::new(false)),
+            scheduled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // Note a change, to be written within `FLUSH_DELAY`. A write that fails
+    // is logged and tried again by the next change or `flush`.
+    pub fn changed(&self) {
+        self.unsaved.store(true, Ordering::Relaxed);
+        if self.scheduled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let timer = self.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(FLUSH_DELAY);
+            timer.scheduled.store(false, Ordering::Release);
+            if let Err(e) = timer.flush() {
+                log::warn!("{}", e);
+            }
+        });
+    }
+
+    // Write now if anything is unsaved, e.g. on exit
+    pub fn flush(&self) -> Result<(), AppError> {
+        if !self.unsaved.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
+        let written = (self.write)();
+        if written.is_err() {
+            self.unsaved.store(true, Ordering::Relaxed);
+        }
+        written
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn bursts_of_changes_are_written_once() {
+        let writes = Arc::new(AtomicUsize::new(0));
+        let counted = Arc::clone(&writes);
+        let debounced = DebouncedWrite::new(move || {
+            counted.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        });
+
+        for _ in 0..100 {
+            debounced.changed();
+        }
+        assert_eq!(writes.load(Ordering::Relaxed), 0);
+        std::thread::sleep(FLUSH_DELAY * 3);
+        assert_eq!(writes.load(Ordering::Relaxed), 1);
+
+        // Nothing left to write, until the next change
+        debounced.flush().unwrap();
+        assert_eq!(writes.load(Ordering::Relaxed), 1);
+        debounced.changed();
+        debounced.flush().unwrap();
+        assert_eq!(writes.load(Ordering::Relaxed), 2);
+    }
+}
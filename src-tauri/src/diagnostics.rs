@@ -0,0 +1,209 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::AppError;
+use crate::gds::cache::{CacheStats, ParseCache};
+use crate::logging::log_file;
+use crate::paths::write_atomic;
+use crate::settings::Settings;
+use crate::watcher::WatchedFiles;
+
+// How much of the end of the log file a bundle holds
+const LOG_TAIL_BYTES: u64 = 64 * 1024;
+
+// What a redacted path's directories are replaced with
+const REDACTED: &str = "<redacted>";
+
+// State of the app for a bug report, written as one JSON file. Layout file
+// contents are never included.
+#[derive(Debug, Clone, Serialize)]
+struct DiagnosticBundle {
+    // Seconds since the Unix epoch
+    created: u64,
+    version: String,
+    features: Vec<String>,
+    os: String,
+    arch: String,
+    // Whether the `redact_paths` setting left out the directories of paths
+    paths_redacted: bool,
+    watched_files: Vec<PathBuf>,
+    parse_cache: CacheStats,
+    settings: Map<String, Value>,
+    log_tail: Vec<String>,
+}
+
+// The name of `path` under `REDACTED` in place of its directories
+fn redact_path(path: &Path) -> PathBuf {
+    let redacted = Path::new(REDACTED);
+    path.file_name().map_or_else(|| redacted.to_path_buf(), |name| redacted.join(name))
+}
+
+// `value` with every string holding an absolute path redacted, along with the
+// directories of those paths added to `dirs`
+fn redact_value(value: &mut Value, dirs: &mut Vec<String>) {
+    match value {
+        Value::String(text) if Path::new(text.as_str()).is_absolute() => {
+            dirs.extend(Path::new(text.as_str()).parent().map(|dir| dir.display().to_string()));
+            *text = redact_path(Path::new(text.as_str())).display().to_string();
+        }
+        Value::Array(values) => values.iter_mut().for_each(|value| redact_value(value, dirs)),
+        Value::Object(values) => values.values_mut().for_each(|value| redact_value(value, dirs)),
+        _ => {}
+    }
+}
+
+// Leave the directories of every path in the bundle out of it, including where
+// the known ones and the home directory appear in the log
+fn redact(bundle: &mut DiagnosticBundle) {
+    let mut dirs: Vec<String> = bundle
+        .watched_files
+        .iter()
+        .chain(bundle.parse_cache.files.iter().map(|file| &file.path))
+        .filter_map(|path| path.parent())
+        .map(|dir| dir.display().to_string())
+        .collect();
+    for path in &mut bundle.watched_files {
+        *path = redact_path(path);
+    }
+    for file in &mut bundle.parse_cache.files {
+        file.path = redact_path(&file.path);
+    }
+    for value in bundle.settings.values_mut() {
+        redact_value(value, &mut dirs);
+    }
+    dirs.extend(["HOME", "USERPROFILE"].iter().filter_map(|name| std::env::var(name).ok()));
+    // Longest first, so a directory is replaced before any of its parents
+    dirs.retain(|dir| dir.len() > 1);
+    dirs.sort_by_key(|dir| std::cmp::Reverse(dir.len()));
+    dirs.dedup();
+    for line in &mut bundle.log_tail {
+        for dir in &dirs {
+            if line.contains(dir.as_str()) {
+                *line = line.replace(dir.as_str(), REDACTED);
+            }
+        }
+    }
+}
+
+// The complete lines of the last `LOG_TAIL_BYTES` of the log file, or none if
+// it can't be read
+fn log_tail(file: &Path) -> Vec<String> {
+    let read = || -> std::io::Result<Vec<u8>> {
+        let mut file = std::fs::File::open(file)?;
+        let start = file.metadata()?.len().saturating_sub(LOG_TAIL_BYTES);
+        file.seek(SeekFrom::Start(start))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        // Drop the line the tail starts in the middle of
+        if start > 0 {
+            let first_line_end = bytes.iter().position(|&b| b == b'\n').map_or(0, |i| i + 1);
+            bytes.drain(..first_line_end);
+        }
+        Ok(bytes)
+    };
+    match read() {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).lines().map(str::to_string).collect(),
+        Err(e) => {
+            log::warn!("Failed to read the log file {}: {}", file.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+// Command to write the version, watches, parse cache, settings and the end of
+// the log into a JSON file in the temp dir for attaching to bug reports.
+// Returns the file's path. With the `redact_paths` setting on, paths are
+// reduced to their file names.
+#[tauri::command]
+pub async fn create_diagnostic_bundle(
+    app: tauri::AppHandle,
+    settings: tauri::State<'_, Settings>,
+    cache: tauri::State<'_, ParseCache>,
+    watched_files: tauri::State<'_, WatchedFiles>,
+) -> Result<String, AppError> {
+    let created = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+    let log_tail = match log_file(&app) {
+        Ok(file) => log_tail(&file),
+        Err(e) => {
+            log::warn!("{}", e);
+            Vec::new()
+        }
+    };
+    let mut bundle = DiagnosticBundle {
+        created,
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        features: crate::compiled_features(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        paths_redacted: settings.redact_paths(),
+        watched_files: watched_files.paths(),
+        parse_cache: cache.stats(),
+        settings: settings.values(),
+        log_tail,
+    };
+    if bundle.paths_redacted {
+        redact(&mut bundle);
+    }
+
+    let contents = serde_json::to_string_pretty(&bundle)
+        .map_err(|e| AppError::io("Failed to serialize the diagnostic bundle", e))?;
+    let path = std::env::temp_dir().join(format!("gdsjam-diagnostics-{}.json", created));
+    write_atomic(&path, contents.as_bytes())
+        .map_err(|e| AppError::io("Failed to write the diagnostic bundle", e))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::cache::CachedFile;
+    use crate::gds::parser::ParseMode;
+    use serde_json::json;
+
+    #[test]
+    fn redacted_bundles_keep_only_file_names() {
+        let dir = Path::new("/home/someone/chips");
+        let mut bundle = DiagnosticBundle {
+            created: 0,
+            version: "1.0.0".to_string(),
+            features: Vec::new(),
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            paths_redacted: true,
+            watched_files: vec![dir.join("top.gds")],
+            parse_cache: CacheStats {
+                max_entries: 8,
+                files: vec![CachedFile {
+                    path: dir.join("top.gds"),
+                    mode: ParseMode::Full,
+                    cells: 3,
+                }],
+            },
+            settings: json!({
+                "last_dir": "/srv/secret/project",
+                "recent": ["/srv/secret/project/a.gds", "relative/b.gds"],
+                "theme": "dark",
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+            log_tail: vec![
+                "[INFO] Parsed /home/someone/chips/top.gds in 12 ms".to_string(),
+                "[WARN] Failed to read /srv/secret/project/a.gds".to_string(),
+            ],
+        };
+        redact(&mut bundle);
+
+        let redacted = Path::new(REDACTED);
+        assert_eq!(bundle.watched_files, [redacted.join("top.gds")]);
+        assert_eq!(bundle.parse_cache.files[0].path, redacted.join("top.gds"));
+        assert_eq!(bundle.settings["last_dir"], json!(redacted.join("project")));
+        assert_eq!(bundle.settings["recent"], json!([redacted.join("a.gds"), "relative/b.gds"]));
+        assert_eq!(bundle.settings["theme"], "dark");
+        assert_eq!(bundle.log_tail[0], "[INFO] Parsed <redacted>/top.gds in 12 ms");
+        assert_eq!(bundle.log_tail[1], "[WARN] Failed to read <redacted>/a.gds");
+    }
+}
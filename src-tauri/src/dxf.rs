@@ -0,0 +1,479 @@
+//! ASCII DXF reading: LINE, LWPOLYLINE, POLYLINE, CIRCLE and ARC entities from
+//! the ENTITIES section, grouped by layer.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::error::AppError;
+use crate::gds::jobs::{ParseJobs, ParseProgressPayload, WarningEmittedPayload};
+use crate::gds::parser::{ParseProgress, PROGRESS_INTERVAL_RECORDS};
+use crate::warning::{Warning, WarningCode};
+
+// Segments used to approximate a full circle; arcs use a proportional share
+const CIRCLE_SEGMENTS: usize = 64;
+
+// Read buffer for DXF files, which are read a line at a time
+const READ_BUFFER_BYTES: usize = 1024 * 1024;
+
+// Errors produced while reading a DXF file
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(tag = "kind")]
+pub enum DxfError {
+    Io { message: String },
+    InvalidPair { line: u64, message: String },
+}
+
+impl fmt::Display for DxfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DxfError::Io { message } => write!(f, "I/O error: {}", message),
+            DxfError::InvalidPair { line, message } => {
+                write!(f, "Invalid DXF group at line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DxfError {}
+
+impl From<std::io::Error> for DxfError {
+    fn from(error: std::io::Error) -> Self {
+        DxfError::Io { message: error.to_string() }
+    }
+}
+
+// A polyline (open) or polygon (closed) in drawing units
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct DxfShape {
+    pub closed: bool,
+    pub points: Vec<[f64; 2]>,
+}
+
+// All shapes on one DXF layer, shaped like the GDS `LayerInfo` plus geometry
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct DxfLayer {
+    pub name: String,
+    pub element_count: u64,
+    pub shapes: Vec<DxfShape>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct DxfDrawing {
+    // Sorted by layer name
+    pub layers: Vec<DxfLayer>,
+    // Entities of unsupported types, which are skipped
+    pub skipped_entities: u64,
+    // One `unsupported_entity` warning per skipped entity type, in the order
+    // first seen. DXF has no byte offsets, so `offset` is always null.
+    pub warnings: Vec<Warning>,
+}
+
+// A group code / value pair
+struct Pair {
+    code: i32,
+    value: String,
+}
+
+// Read group code / value line pairs one line at a time, tracking the line
+// number for errors and the bytes consumed for progress
+struct PairReader<R> {
+    reader: R,
+    line: u64,
+    bytes_read: u64,
+    buffer: Vec<u8>,
+}
+
+impl<R: BufRead> PairReader<R> {
+    fn new(reader: R) -> Self {
+        PairReader { reader, line: 0, bytes_read: 0, buffer: Vec::new() }
+    }
+
+    // The next line, trimmed. Text that isn't UTF-8, such as the code page
+    // encodings older tools write, is decoded lossily rather than rejected.
+    fn next_line(&mut self) -> Result<Option<String>, DxfError> {
+        self.buffer.clear();
+        let read = self.reader.read_until(b'\n', &mut self.buffer)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        self.line += 1;
+        self.bytes_read += read as u64;
+        Ok(Some(String::from_utf8_lossy(&self.buffer).trim().to_string()))
+    }
+
+    fn next_pair(&mut self) -> Result<Option<Pair>, DxfError> {
+        let Some(code) = self.next_line()? else {
+            return Ok(None);
+        };
+        let code_line = self.line;
+        let code = code.parse::<i32>().map_err(|_| DxfError::InvalidPair {
+            line: code_line,
+            message: format!("expected a group code, found {:?}", code),
+        })?;
+        let value = self.next_line()?.ok_or_else(|| DxfError::InvalidPair {
+            line: code_line,
+            message: format!("group code {} has no value", code),
+        })?;
+        Ok(Some(Pair { code, value }))
+    }
+}
+
+fn parse_f64(pair: &Pair, line: u64) -> Result<f64, DxfError> {
+    pair.value.parse::<f64>().map_err(|_| DxfError::InvalidPair {
+        line,
+        message: format!("group code {} expects a number, found {:?}", pair.code, pair.value),
+    })
+}
+
+fn parse_i64(pair: &Pair, line: u64) -> Result<i64, DxfError> {
+    pair.value.parse::<i64>().map_err(|_| DxfError::InvalidPair {
+        line,
+        message: format!("group code {} expects an integer, found {:?}", pair.code, pair.value),
+    })
+}
+
+// Points along a circular arc from `start` to `end` degrees (counterclockwise)
+fn arc_points(center: [f64; 2], radius: f64, start: f64, end: f64) -> Vec<[f64; 2]> {
+    let mut sweep = end - start;
+    while sweep <= 0.0 {
+        sweep += 360.0;
+    }
+    let segments = ((CIRCLE_SEGMENTS as f64 * sweep / 360.0).ceil() as usize).max(1);
+    (0..=segments)
+        .map(|i| {
+            let angle = (start + sweep * i as f64 / segments as f64).to_radians();
+            [center[0] + radius * angle.cos(), center[1] + radius * angle.sin()]
+        })
+        .collect()
+}
+
+// Group codes collected for the entity currently being read
+#[derive(Default)]
+struct EntityBuilder {
+    kind: String,
+    // Line of the entity's type name
+    line: u64,
+    layer: String,
+    // Successive 10/20 coordinate pairs
+    points: Vec<[f64; 2]>,
+    end: [f64; 2],
+    radius: f64,
+    start_angle: f64,
+    end_angle: f64,
+    flags: i64,
+}
+
+impl EntityBuilder {
+    fn add(&mut self, pair: &Pair, line: u64) -> Result<(), DxfError> {
+        match pair.code {
+            8 => self.layer = pair.value.clone(),
+            10 => self.points.push([parse_f64(pair, line)?, 0.0]),
+            20 => {
+                let y = parse_f64(pair, line)?;
+                if let Some(last) = self.points.last_mut() {
+                    last[1] = y;
+                }
+            }
+            11 => self.end[0] = parse_f64(pair, line)?,
+            21 => self.end[1] = parse_f64(pair, line)?,
+            40 => self.radius = parse_f64(pair, line)?,
+            50 => self.start_angle = parse_f64(pair, line)?,
+            51 => self.end_angle = parse_f64(pair, line)?,
+            70 => self.flags = parse_i64(pair, line)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn closed(&self) -> bool {
+        self.flags & 1 != 0
+    }
+}
+
+// Accumulates finished shapes per layer
+#[derive(Default)]
+struct DrawingBuilder {
+    layers: BTreeMap<String, DxfLayer>,
+    skipped_entities: u64,
+    skipped_kinds: HashSet<String>,
+    warnings: Vec<Warning>,
+}
+
+impl DrawingBuilder {
+    fn push(&mut self, layer: &str, shape: DxfShape) {
+        let entry = self.layers.entry(layer.to_string()).or_insert_with(|| DxfLayer {
+            name: layer.to_string(),
+            element_count: 0,
+            shapes: Vec::new(),
+        });
+        entry.element_count += 1;
+        entry.shapes.push(shape);
+    }
+
+    // Count a skipped entity, returning a warning the first time its type is seen
+    fn skip(&mut self, kind: &str, line: u64) -> Option<Warning> {
+        self.skipped_entities += 1;
+        if !self.skipped_kinds.insert(kind.to_string()) {
+            return None;
+        }
+        let message = format!("unsupported {} entity at line {}; skipping them all", kind, line);
+        let warning = Warning::new(WarningCode::UnsupportedEntity, None, message);
+        self.warnings.push(warning.clone());
+        Some(warning)
+    }
+
+    fn finish(self) -> DxfDrawing {
+        DxfDrawing {
+            layers: self.layers.into_values().collect(),
+            skipped_entities: self.skipped_entities,
+            warnings: self.warnings,
+        }
+    }
+}
+
+// Streaming state for the ENTITIES section
+struct EntitiesReader {
+    drawing: DrawingBuilder,
+    entity: Option<EntityBuilder>,
+    // An open POLYLINE collecting VERTEX entities until SEQEND
+    polyline: Option<EntityBuilder>,
+}
+
+impl EntitiesReader {
+    fn new() -> Self {
+        EntitiesReader { drawing: DrawingBuilder::default(), entity: None, polyline: None }
+    }
+
+    // Finish the entity in progress, if any, passing a warning about a skipped
+    // entity type to `on_warning`
+    fn flush(&mut self, on_warning: &mut dyn FnMut(&Warning)) {
+        let Some(entity) = self.entity.take() else {
+            return;
+        };
+        match entity.kind.as_str() {
+            "LINE" => {
+                if let Some(start) = entity.points.first() {
+                    let shape = DxfShape { closed: false, points: vec![*start, entity.end] };
+                    self.drawing.push(&entity.layer, shape);
+                }
+            }
+            "LWPOLYLINE" => {
+                let shape = DxfShape { closed: entity.closed(), points: entity.points.clone() };
+                self.drawing.push(&entity.layer, shape);
+            }
+            "CIRCLE" => {
+                if let Some(center) = entity.points.first() {
+                    let mut points = arc_points(*center, entity.radius, 0.0, 360.0);
+                    points.pop();
+                    self.drawing.push(&entity.layer, DxfShape { closed: true, points });
+                }
+            }
+            "ARC" => {
+                if let Some(center) = entity.points.first() {
+                    let points = arc_points(*center, entity.radius, entity.start_angle, entity.end_angle);
+                    self.drawing.push(&entity.layer, DxfShape { closed: false, points });
+                }
+            }
+            "POLYLINE" => self.polyline = Some(entity),
+            "VERTEX" => {
+                if let (Some(polyline), Some(point)) = (self.polyline.as_mut(), entity.points.first()) {
+                    polyline.points.push(*point);
+                }
+            }
+            "SEQEND" => {
+                if let Some(polyline) = self.polyline.take() {
+                    let shape = DxfShape { closed: polyline.closed(), points: polyline.points.clone() };
+                    self.drawing.push(&polyline.layer, shape);
+                }
+            }
+            kind => {
+                if let Some(warning) = self.drawing.skip(kind, entity.line) {
+                    on_warning(&warning);
+                }
+            }
+        }
+    }
+
+    fn start(&mut self, kind: &str, line: u64, on_warning: &mut dyn FnMut(&Warning)) {
+        self.flush(on_warning);
+        let entity = EntityBuilder { kind: kind.to_string(), line, ..EntityBuilder::default() };
+        self.entity = Some(entity);
+    }
+
+    fn add(&mut self, pair: &Pair, line: u64) -> Result<(), DxfError> {
+        match self.entity.as_mut() {
+            // POLYLINE headers carry a dummy 10/20/30 point that isn't a vertex
+            Some(entity) if entity.kind == "POLYLINE" && matches!(pair.code, 10 | 20) => Ok(()),
+            Some(entity) => entity.add(pair, line),
+            None => Ok(()),
+        }
+    }
+}
+
+// Parse DXF text as it is read from `reader`, so only the drawing is held in
+// memory rather than the file. `on_progress` is called every
+// `PROGRESS_INTERVAL_RECORDS` group pairs with the bytes read out of
+// `total_bytes`; a drawing has no cells, so `cells_parsed` stays 0. Warnings
+// are passed to `on_warning` as they are found.
+pub fn parse_dxf_reader<R: BufRead>(
+    reader: R,
+    total_bytes: u64,
+    on_progress: &mut dyn FnMut(ParseProgress),
+    on_warning: &mut dyn FnMut(&Warning),
+) -> Result<DxfDrawing, DxfError> {
+    let mut reader = PairReader::new(reader);
+    let mut in_entities = false;
+    let mut expect_section_name = false;
+    let mut entities = EntitiesReader::new();
+    let mut index = 0;
+
+    while let Some(pair) = reader.next_pair()? {
+        let line = reader.line;
+        if index % PROGRESS_INTERVAL_RECORDS == PROGRESS_INTERVAL_RECORDS - 1 {
+            let bytes_read = reader.bytes_read;
+            on_progress(ParseProgress { bytes_read, total_bytes, cells_parsed: 0 });
+        }
+        index += 1;
+        if expect_section_name {
+            expect_section_name = false;
+            in_entities = pair.code == 2 && pair.value == "ENTITIES";
+            continue;
+        }
+        if pair.code == 0 {
+            match pair.value.as_str() {
+                "SECTION" => expect_section_name = true,
+                "ENDSEC" => {
+                    if in_entities {
+                        entities.flush(on_warning);
+                    }
+                    in_entities = false;
+                }
+                "EOF" => break,
+                kind if in_entities => entities.start(kind, line, on_warning),
+                _ => {}
+            }
+            continue;
+        }
+        if in_entities {
+            entities.add(&pair, line)?;
+        }
+    }
+    entities.flush(on_warning);
+
+    Ok(entities.drawing.finish())
+}
+
+// Parse DXF text held in memory
+#[cfg(test)]
+pub fn parse_dxf_str(text: &str) -> Result<DxfDrawing, DxfError> {
+    parse_dxf_reader(text.as_bytes(), text.len() as u64, &mut |_| {}, &mut |_| {})
+}
+
+// Command to parse a DXF file into per-layer polylines and polygons. The file
+// is streamed, and progress and warnings are sent as `parse-progress` and
+// `warning-emitted` events like a GDSII parse job's, under a job id of its own.
+#[tauri::command]
+pub async fn parse_dxf(
+    path: String,
+    app: AppHandle,
+    jobs: State<'_, ParseJobs>,
+) -> Result<DxfDrawing, AppError> {
+    let file = File::open(&path).map_err(|e| AppError::for_path(&path, e))?;
+    let total_bytes = file.metadata().map_err(|e| AppError::for_path(&path, e))?.len();
+    let job_id = jobs.next_job_id();
+    let reader = BufReader::with_capacity(READ_BUFFER_BYTES, file);
+    let mut on_progress = |progress| {
+        if let Err(e) = app.emit("parse-progress", ParseProgressPayload { job_id, progress }) {
+            log::warn!("Failed to emit parse progress: {}", e);
+        }
+    };
+    let mut on_warning = |warning: &Warning| {
+        let payload = WarningEmittedPayload { job_id, warning: warning.clone() };
+        if let Err(e) = app.emit("warning-emitted", payload) {
+            log::warn!("Failed to emit parse warning: {}", e);
+        }
+    };
+    Ok(parse_dxf_reader(reader, total_bytes, &mut on_progress, &mut on_warning)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dxf(entities: &[&str]) -> String {
+        let mut text = String::from("0\nSECTION\n2\nHEADER\n9\n$ACADVER\n1\nAC1015\n0\nENDSEC\n");
+        text.push_str("0\nSECTION\n2\nENTITIES\n");
+        for entity in entities {
+            text.push_str(entity);
+        }
+        text.push_str("0\nENDSEC\n0\nEOF\n");
+        text
+    }
+
+    #[test]
+    fn reads_supported_entities_by_layer() {
+        let text = dxf(&[
+            "0\nLINE\n8\nMETAL\n10\n0.0\n20\n0.0\n11\n5.0\n21\n0.0\n",
+            "0\nLWPOLYLINE\n8\nMETAL\n90\n3\n70\n1\n10\n0\n20\n0\n10\n4\n20\n0\n10\n4\n20\n3\n",
+            "0\nCIRCLE\n8\nVIA\n10\n1\n20\n1\n40\n0.5\n",
+            "0\nPOLYLINE\n8\nVIA\n66\n1\n10\n0\n20\n0\n70\n0\n",
+            "0\nVERTEX\n8\nVIA\n10\n1\n20\n2\n",
+            "0\nVERTEX\n8\nVIA\n10\n3\n20\n4\n",
+            "0\nSEQEND\n8\nVIA\n",
+            "0\nINSERT\n8\nMETAL\n2\nBLOCK\n10\n0\n20\n0\n",
+        ]);
+        let drawing = parse_dxf_str(&text).unwrap();
+
+        assert_eq!(drawing.skipped_entities, 1);
+        assert_eq!(drawing.warnings.len(), 1);
+        assert_eq!(drawing.warnings[0].code, WarningCode::UnsupportedEntity);
+        assert!(drawing.warnings[0].message.contains("INSERT entity at line"));
+        assert_eq!(drawing.layers.len(), 2);
+        let metal = &drawing.layers[0];
+        assert_eq!(metal.name, "METAL");
+        assert_eq!(metal.element_count, 2);
+        assert_eq!(metal.shapes[0].points, vec![[0.0, 0.0], [5.0, 0.0]]);
+        assert!(metal.shapes[1].closed);
+        assert_eq!(metal.shapes[1].points.len(), 3);
+
+        let via = &drawing.layers[1];
+        assert_eq!(via.shapes[0].points.len(), CIRCLE_SEGMENTS);
+        assert_eq!(via.shapes[1], DxfShape { closed: false, points: vec![[1.0, 2.0], [3.0, 4.0]] });
+    }
+
+    #[test]
+    fn large_drawings_are_streamed_with_progress() {
+        let line = "0\nLINE\n8\nM\n10\n0\n20\n0\n11\n1\n21\n0\n";
+        let text = dxf(&vec![line; 20_000]);
+        let mut progress = Vec::new();
+        // A small buffer, so lines often straddle refills
+        let reader = BufReader::with_capacity(64, text.as_bytes());
+        let mut on_progress = |update: ParseProgress| progress.push(update.bytes_read);
+        let drawing =
+            parse_dxf_reader(reader, text.len() as u64, &mut on_progress, &mut |_| {}).unwrap();
+
+        assert_eq!(drawing.layers[0].element_count, 20_000);
+        // 6 pairs per LINE plus the sections around them
+        assert_eq!(progress.len(), 120_011 / PROGRESS_INTERVAL_RECORDS);
+        assert!(progress.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(progress.iter().all(|bytes| *bytes <= text.len() as u64));
+        // A Latin-1 layer name doesn't stop the read
+        let latin1: Vec<u8> = dxf(&["0\nLINE\n8\nM?TAL\n10\n0\n20\n0\n11\n1\n21\n0\n"])
+            .bytes()
+            .map(|byte| if byte == b'?' { 0xc9 } else { byte })
+            .collect();
+        let drawing = parse_dxf_reader(&latin1[..], 0, &mut |_| {}, &mut |_| {}).unwrap();
+        assert_eq!(drawing.layers[0].name, "M\u{fffd}TAL");
+    }
+
+    #[test]
+    fn reports_line_of_malformed_value() {
+        let text = dxf(&["0\nLINE\n8\nL\n10\nabc\n20\n0\n"]);
+        assert!(matches!(parse_dxf_str(&text), Err(DxfError::InvalidPair { line: 20, .. })));
+    }
+}
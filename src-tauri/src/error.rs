@@ -0,0 +1,198 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+use crate::dxf::DxfError;
+use crate::gds::GdsError;
+
+// Error returned by every command, tagged with `kind` so the frontend can tell
+// e.g. a missing file from a corrupt one and offer the right recovery action
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(tag = "kind")]
+pub enum AppError {
+    Io { message: String },
+    NotFound { path: String },
+    // Malformed file contents. For a binary stream `offset` is the byte offset
+    // of the failure, `expected` what should have been there and `found_bytes`
+    // up to 16 bytes of the file from `offset`, for a hex view.
+    ParseError {
+        offset: Option<u64>,
+        message: String,
+        expected: Option<String>,
+        found_bytes: Vec<u8>,
+    },
+    InvalidFormat { expected: String, found: String },
+    InvalidArgument { message: String },
+    CellNotFound { cell: String },
+    InvalidHierarchy { cell: String, message: String },
+    Watch { message: String },
+    // No clipboard could be written, e.g. no clipboard tool is installed
+    Clipboard { message: String },
+    Cancelled,
+    // A result of `count` polygons was refused for exceeding the caller's `limit`.
+    // A command that stops as soon as the limit is passed reports `limit + 1`.
+    TooLarge { count: usize, limit: usize },
+    // `path` is a symlink and the `follow_symlinks` setting is off
+    SymlinkRefused { path: String },
+    // A write was refused because `path` is open read-only, or, without a
+    // path, because read-only mode is on for every file
+    ReadOnly { path: Option<String> },
+}
+
+impl AppError {
+    // I/O failure with a description of what was being attempted
+    pub fn io(context: &str, error: impl fmt::Display) -> Self {
+        AppError::Io { message: format!("{}: {}", context, error) }
+    }
+
+    // I/O failure on a specific file, reported as `NotFound` if it doesn't exist
+    pub fn for_path(path: impl AsRef<Path>, error: std::io::Error) -> Self {
+        if error.kind() == std::io::ErrorKind::NotFound {
+            AppError::NotFound { path: path.as_ref().to_string_lossy().to_string() }
+        } else {
+            AppError::Io { message: error.to_string() }
+        }
+    }
+
+    // Malformed text contents, which have no byte context
+    pub fn parse_error(message: impl Into<String>) -> Self {
+        AppError::ParseError {
+            offset: None,
+            message: message.into(),
+            expected: None,
+            found_bytes: Vec::new(),
+        }
+    }
+
+    pub fn invalid_argument(message: impl Into<String>) -> Self {
+        AppError::InvalidArgument { message: message.into() }
+    }
+
+    // Convert a stream parser error for a file expected to be in `format`
+    pub fn from_stream(error: GdsError, format: &str) -> Self {
+        let description = error.to_string();
+        match error {
+            GdsError::Io { message } => AppError::Io { message },
+            GdsError::InvalidHeader { message } => {
+                AppError::InvalidFormat { expected: format.to_string(), found: message }
+            }
+            GdsError::Truncated { offset, expected, message, found_bytes }
+            | GdsError::InvalidRecord { offset, expected, message, found_bytes } => {
+                let expected = Some(expected);
+                AppError::ParseError { offset: Some(offset), message, expected, found_bytes }
+            }
+            GdsError::InvalidUnits { message } => AppError::parse_error(message),
+            GdsError::CellNotFound { cell } => AppError::CellNotFound { cell },
+            GdsError::CyclicReference { cell }
+            | GdsError::HierarchyTooDeep { cell, .. }
+            | GdsError::DegenerateArray { cell, .. } => {
+                AppError::InvalidHierarchy { cell, message: description }
+            }
+            GdsError::Cancelled => AppError::Cancelled,
+            GdsError::SymlinkRefused { path } => AppError::SymlinkRefused { path },
+            GdsError::Internal { .. } => AppError::parse_error(description),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Io { message } => write!(f, "{}", message),
+            AppError::NotFound { path } => write!(f, "File not found: {}", path),
+            AppError::ParseError { offset, message, expected, found_bytes } => {
+                match offset {
+                    Some(offset) => write!(f, "Parse error at byte {}: {}", offset, message)?,
+                    None => write!(f, "Parse error: {}", message)?,
+                }
+                if let Some(expected) = expected {
+                    write!(f, "; expected {}", expected)?;
+                }
+                if !found_bytes.is_empty() {
+                    let hex: Vec<String> =
+                        found_bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+                    write!(f, ", found {}", hex.join(" "))?;
+                }
+                Ok(())
+            }
+            AppError::InvalidFormat { expected, found } => {
+                write!(f, "Expected a {} file: {}", expected, found)
+            }
+            AppError::InvalidArgument { message } => write!(f, "Invalid argument: {}", message),
+            AppError::CellNotFound { cell } => write!(f, "Cell {} not found", cell),
+            AppError::InvalidHierarchy { message, .. } => write!(f, "{}", message),
+            AppError::Watch { message } => write!(f, "File watch error: {}", message),
+            AppError::Clipboard { message } => write!(f, "Clipboard unavailable: {}", message),
+            AppError::Cancelled => write!(f, "Cancelled"),
+            AppError::TooLarge { count, limit } => {
+                write!(f, "Result has {} polygons, more than the limit of {}", count, limit)
+            }
+            AppError::SymlinkRefused { path } => {
+                write!(f, "Not following the symlink {}: symlinks are turned off", path)
+            }
+            AppError::ReadOnly { path: Some(path) } => write!(f, "{} is open read-only", path),
+            AppError::ReadOnly { path: None } => write!(f, "Read-only mode is on"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<std::io::Error> for AppError {
+    fn from(error: std::io::Error) -> Self {
+        AppError::Io { message: error.to_string() }
+    }
+}
+
+impl From<GdsError> for AppError {
+    fn from(error: GdsError) -> Self {
+        AppError::from_stream(error, "GDSII")
+    }
+}
+
+impl From<DxfError> for AppError {
+    fn from(error: DxfError) -> Self {
+        match error {
+            DxfError::Io { message } => AppError::Io { message },
+            DxfError::InvalidPair { line, message } => {
+                AppError::parse_error(format!("line {}: {}", line, message))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_files_and_parse_errors_are_distinguished() {
+        let missing = std::fs::read("/definitely/not/here.gds").unwrap_err();
+        assert_eq!(
+            AppError::for_path("/definitely/not/here.gds", missing),
+            AppError::NotFound { path: "/definitely/not/here.gds".to_string() }
+        );
+
+        let stream = [0u8, 4, 0x04, 0x00, 0xff];
+        let truncated = GdsError::truncated(2, "an ENDLIB record", "missing ENDLIB");
+        let error = AppError::from(truncated.with_context(&stream));
+        assert_eq!(
+            error,
+            AppError::ParseError {
+                offset: Some(2),
+                message: "missing ENDLIB".to_string(),
+                expected: Some("an ENDLIB record".to_string()),
+                found_bytes: vec![0x04, 0x00, 0xff],
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Parse error at byte 2: missing ENDLIB; expected an ENDLIB record, found 04 00 ff"
+        );
+        let json = serde_json::to_value(AppError::NotFound { path: "a.gds".to_string() }).unwrap();
+        assert_eq!(json, serde_json::json!({ "kind": "NotFound", "path": "a.gds" }));
+        let json = serde_json::to_value(AppError::TooLarge { count: 12, limit: 10 }).unwrap();
+        assert_eq!(json, serde_json::json!({ "kind": "TooLarge", "count": 12, "limit": 10 }));
+    }
+}
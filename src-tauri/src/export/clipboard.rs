@@ -0,0 +1,112 @@
+use std::fs::File;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::error::AppError;
+use crate::gds::cache::ParseCache;
+
+use super::thumbnail::render_cell_png;
+
+// A command that puts the PNG file it is given (as `{}` in its arguments, or on
+// stdin) on the system clipboard
+struct ClipboardTool {
+    program: &'static str,
+    args: &'static [&'static str],
+}
+
+// Tools tried in order until one is installed and succeeds
+#[cfg(target_os = "macos")]
+const CLIPBOARD_TOOLS: &[ClipboardTool] = &[ClipboardTool {
+    program: "osascript",
+    args: &["-e", "set the clipboard to (read (POSIX file \"{}\") as «class PNGf»)"],
+}];
+
+#[cfg(windows)]
+const CLIPBOARD_TOOLS: &[ClipboardTool] = &[ClipboardTool {
+    program: "powershell",
+    args: &[
+        "-NoProfile",
+        "-Command",
+        "Add-Type -AssemblyName System.Windows.Forms; \
+         [System.Windows.Forms.Clipboard]::SetImage([System.Drawing.Image]::FromFile('{}'))",
+    ],
+}];
+
+#[cfg(not(any(target_os = "macos", windows)))]
+const CLIPBOARD_TOOLS: &[ClipboardTool] = &[
+    ClipboardTool { program: "wl-copy", args: &["--type", "image/png"] },
+    ClipboardTool { program: "xclip", args: &["-selection", "clipboard", "-t", "image/png", "-i"] },
+];
+
+// Run the first of `tools` that is installed on `png`. Output is discarded
+// rather than captured, since X11 and Wayland tools keep running in the
+// background to serve the clipboard.
+fn run_clipboard_tool(tools: &[ClipboardTool], png: &Path) -> Result<(), AppError> {
+    let png_path = png.to_string_lossy();
+    let mut failures = Vec::new();
+    for tool in tools {
+        let input = File::open(png).map_err(|e| AppError::io("Failed to read image", e))?;
+        let status = Command::new(tool.program)
+            .args(tool.args.iter().map(|arg| arg.replace("{}", &png_path)))
+            .stdin(input)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        match status {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(status) => failures.push(format!("{} exited with {}", tool.program, status)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                failures.push(format!("{} is not installed", tool.program))
+            }
+            Err(e) => failures.push(format!("{} failed to start: {}", tool.program, e)),
+        }
+    }
+    Err(AppError::Clipboard { message: failures.join("; ") })
+}
+
+// Put PNG bytes on the system clipboard, going through a temporary file that is
+// removed afterwards
+fn copy_png(png: &[u8]) -> Result<(), AppError> {
+    let file = std::env::temp_dir().join(format!("gdsjam-clipboard-{}.png", std::process::id()));
+    std::fs::write(&file, png).map_err(|e| AppError::io("Failed to write image", e))?;
+    let copied = run_clipboard_tool(CLIPBOARD_TOOLS, &file);
+    let _ = std::fs::remove_file(&file);
+    copied
+}
+
+// Command to render a cell like `render_thumbnail` and put the PNG on the
+// system clipboard, for pasting into chats and documents
+#[tauri::command]
+pub async fn copy_cell_to_clipboard(
+    path: String,
+    cell: String,
+    width_px: u32,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<(), AppError> {
+    let library = cache.library(&path)?;
+    copy_png(&render_cell_png(&library, &cell, width_px)?)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    const MISSING: ClipboardTool = ClipboardTool { program: "gdsjam-no-such-tool", args: &[] };
+    const FAILING: ClipboardTool = ClipboardTool { program: "false", args: &[] };
+    const WORKING: ClipboardTool = ClipboardTool { program: "test", args: &["-f", "{}"] };
+
+    #[test]
+    fn missing_tools_fall_through_to_a_clear_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let png = dir.path().join("cell.png");
+        std::fs::write(&png, b"png").unwrap();
+
+        assert_eq!(run_clipboard_tool(&[MISSING, WORKING], &png), Ok(()));
+        let Err(AppError::Clipboard { message }) = run_clipboard_tool(&[MISSING, FAILING], &png)
+        else {
+            panic!("expected a clipboard error");
+        };
+        assert!(message.contains("gdsjam-no-such-tool is not installed"), "{}", message);
+        assert!(message.contains("false exited with"), "{}", message);
+    }
+}
@@ -0,0 +1,201 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::error::AppError;
+use crate::gds::bbox::BboxCalculator;
+use crate::gds::cache::ParseCache;
+use crate::gds::GdsLibrary;
+
+use super::font::{draw_text, text_width, GLYPH_ADVANCE, GLYPH_HEIGHT};
+use super::thumbnail::{render_cell_canvas, Canvas};
+
+// Most cells on one sheet; the rest go on further pages
+const MAX_SHEET_CELLS: usize = 100;
+
+// Largest sheet produced, whatever the tile size and column count. Fewer rows
+// go on a page when they wouldn't fit.
+const MAX_SHEET_PIXELS: u64 = 16 * 1024 * 1024;
+
+// Space around and between tiles, in pixels
+const TILE_GAP: u32 = 8;
+
+// Space above and below a label, in pixels
+const LABEL_MARGIN: u32 = 3;
+
+const LABEL_HEIGHT: u32 = GLYPH_HEIGHT + 2 * LABEL_MARGIN;
+
+const LABEL_COLOR: [u8; 4] = [48, 48, 48, 255];
+const FRAME_COLOR: [u8; 4] = [200, 200, 200, 255];
+
+// One page of a contact sheet: the encoded PNG and the cells on it, in the
+// order they are laid out, left to right and then top to bottom. `page`
+// counts from 0.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ContactSheet {
+    pub png: Vec<u8>,
+    pub cells: Vec<String>,
+    pub page: u32,
+    pub page_count: u32,
+}
+
+// How many tiles `cell_width_px` wide fit on a page of `columns` within the
+// pixel budget, at most `MAX_SHEET_CELLS`
+fn cells_per_page(cell_width_px: u32, columns: u32) -> usize {
+    let sheet_width = columns as u64 * (cell_width_px as u64 + TILE_GAP as u64) + TILE_GAP as u64;
+    let row_height = cell_width_px as u64 + (LABEL_HEIGHT + TILE_GAP) as u64;
+    let rows = (MAX_SHEET_PIXELS / sheet_width).saturating_sub(TILE_GAP as u64) / row_height;
+    (rows.saturating_mul(columns as u64)).min(MAX_SHEET_CELLS as u64) as usize
+}
+
+// `name` cut to at most `max_chars` characters, ending in ".." when cut
+fn fit_label(name: &str, max_chars: usize) -> String {
+    if name.chars().count() <= max_chars {
+        return name.to_string();
+    }
+    if max_chars < 3 {
+        return name.chars().take(max_chars).collect();
+    }
+    name.chars().take(max_chars - 2).chain("..".chars()).collect()
+}
+
+// Outline the `width` by `height` area at (`x`, `y`) one pixel outside it
+fn frame(canvas: &mut Canvas, x: u32, y: u32, width: u32, height: u32) {
+    let (left, top) = (x - 1, y - 1);
+    canvas.fill_rect(left, top, width + 2, 1, FRAME_COLOR);
+    canvas.fill_rect(left, y + height, width + 2, 1, FRAME_COLOR);
+    canvas.fill_rect(left, top, 1, height + 2, FRAME_COLOR);
+    canvas.fill_rect(x + width, top, 1, height + 2, FRAME_COLOR);
+}
+
+// Render page `page` of a grid of `columns` thumbnails of every cell of the
+// library, sorted by name, each fitted into a square `cell_width_px` on a
+// side and labeled with its name underneath. A cell without geometry gets an
+// empty tile.
+pub fn render_contact_sheet_png(
+    library: &GdsLibrary,
+    cell_width_px: u32,
+    columns: u32,
+    page: u32,
+) -> Result<ContactSheet, AppError> {
+    if cell_width_px == 0 {
+        return Err(AppError::invalid_argument("Thumbnail width must be at least 1 pixel"));
+    }
+    if columns == 0 || columns as usize > MAX_SHEET_CELLS {
+        return Err(AppError::invalid_argument(format!(
+            "Columns must be between 1 and {}, got {}",
+            MAX_SHEET_CELLS, columns
+        )));
+    }
+    let per_page = cells_per_page(cell_width_px, columns);
+    if per_page == 0 {
+        return Err(AppError::invalid_argument(format!(
+            "A row of {} thumbnails {} pixels wide is larger than a contact sheet can be",
+            columns, cell_width_px
+        )));
+    }
+    let mut names: Vec<&str> = library.cells.iter().map(|cell| cell.name.as_str()).collect();
+    names.sort_unstable();
+    let page_count = names.len().div_ceil(per_page).max(1);
+    if page as usize >= page_count {
+        return Err(AppError::invalid_argument(format!(
+            "Contact sheet has {} pages, no page {}",
+            page_count, page
+        )));
+    }
+    let on_page: Vec<&str> =
+        names.iter().skip(page as usize * per_page).take(per_page).copied().collect();
+
+    let rows = (on_page.len() as u32).div_ceil(columns);
+    let (tile_width, tile_height) =
+        (cell_width_px + TILE_GAP, cell_width_px + LABEL_HEIGHT + TILE_GAP);
+    let mut sheet = Canvas::new(columns * tile_width + TILE_GAP, rows * tile_height + TILE_GAP);
+    let mut calculator = BboxCalculator::new(library);
+    let max_chars = ((cell_width_px + 1) / GLYPH_ADVANCE) as usize;
+    for (index, name) in on_page.iter().enumerate() {
+        let (column, row) = (index as u32 % columns, index as u32 / columns);
+        let (x, y) = (TILE_GAP + column * tile_width, TILE_GAP + row * tile_height);
+        frame(&mut sheet, x, y, cell_width_px, cell_width_px);
+        if let Some(bbox) = calculator.cell_bbox(name)? {
+            // A tall cell is narrowed so its height fits the tile
+            let width_px = if bbox.height() > bbox.width() {
+                (cell_width_px as f64 * bbox.width() / bbox.height()).floor().max(1.0) as u32
+            } else {
+                cell_width_px
+            };
+            if let Some(image) = render_cell_canvas(library, name, width_px, &mut |_| true)? {
+                let left = x + cell_width_px.saturating_sub(image.width()) / 2;
+                let top = y + cell_width_px.saturating_sub(image.height()) / 2;
+                sheet.draw_image(&image, left, top);
+            }
+        }
+        let label = fit_label(name, max_chars);
+        let left = x + cell_width_px.saturating_sub(text_width(&label)) / 2;
+        draw_text(&mut sheet, left, y + cell_width_px + LABEL_MARGIN, &label, LABEL_COLOR);
+    }
+
+    let png = sheet.encode_png().map_err(|e| AppError::io("Failed to encode contact sheet", e))?;
+    Ok(ContactSheet {
+        png,
+        cells: on_page.iter().map(|name| name.to_string()).collect(),
+        page,
+        page_count: page_count as u32,
+    })
+}
+
+// Command to render thumbnails of every cell in a file into one PNG, as a
+// visual index for browsing a cell library. Large libraries are split over
+// pages; `page` defaults to the first.
+#[tauri::command]
+pub async fn render_contact_sheet(
+    path: String,
+    cell_width_px: u32,
+    columns: u32,
+    page: Option<u32>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<ContactSheet, AppError> {
+    let library = cache.library(&path)?;
+    render_contact_sheet_png(&library, cell_width_px, columns, page.unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn cells_are_tiled_by_name_with_labels() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("WIDE").boundary(1, 0, &[[0, 0], [200, 0], [200, 100], [0, 100], [0, 0]]);
+        gds.end_cell();
+        gds.begin_cell("EMPTY").end_cell();
+        gds.begin_cell("TALL").boundary(2, 0, &[[0, 0], [10, 0], [10, 40], [0, 40], [0, 0]]);
+        gds.end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let sheet = render_contact_sheet_png(&library, 32, 2, 0).unwrap();
+        assert_eq!(sheet.cells, ["EMPTY", "TALL", "WIDE"]);
+        assert_eq!((sheet.page, sheet.page_count), (0, 1));
+        let mut reader = png::Decoder::new(&sheet.png[..]).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        // Two columns of 32 pixel tiles, two rows with their labels
+        assert_eq!((info.width, info.height), (88, 114));
+        let alpha = |x: u32, y: u32| pixels[((y * info.width + x) * 4 + 3) as usize];
+        // TALL, second on the first row, is 8 pixels wide in the middle of its tile
+        assert!(alpha(64, 24) > 0);
+        assert_eq!(alpha(50, 24), 0);
+        // EMPTY has a frame and a label but nothing inside
+        assert_eq!(alpha(24, 24), 0);
+        assert!(alpha(7, 24) > 0);
+        assert!((8..40).any(|x| (43..50).any(|y| alpha(x, y) > 0)));
+
+        assert!(matches!(
+            render_contact_sheet_png(&library, 32, 2, 1),
+            Err(AppError::InvalidArgument { .. })
+        ));
+        assert_eq!(cells_per_page(64, 10), MAX_SHEET_CELLS);
+        assert_eq!(cells_per_page(4000, 4), 0);
+        assert_eq!(fit_label("TRANSISTOR", 6), "TRAN..");
+    }
+}
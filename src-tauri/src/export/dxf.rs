@@ -0,0 +1,125 @@
+use std::fmt::Write;
+
+use crate::error::AppError;
+use crate::gds::cache::ParseCache;
+use crate::gds::flatten::cell_polygons;
+use crate::gds::{GdsError, GdsLibrary};
+use crate::paths::write_atomic;
+use crate::read_only::ensure_writable;
+use crate::settings::Settings;
+
+// $INSUNITS code for micrometres
+const INSUNITS_MICRONS: u32 = 13;
+
+// DXF layer name for a GDSII (layer, datatype) pair
+pub fn dxf_layer_name(layer: u16, datatype: u16) -> String {
+    format!("L{}D{}", layer, datatype)
+}
+
+fn pair(dxf: &mut String, code: u32, value: impl std::fmt::Display) {
+    // Writing to a String cannot fail
+    let _ = write!(dxf, "{}\n{}\n", code, value);
+}
+
+// Write the flattened boundaries of a cell as an ASCII DXF (AutoCAD 2000)
+// drawing, one closed LWPOLYLINE per boundary, with coordinates in microns
+pub fn cell_to_dxf(library: &GdsLibrary, cell: &str) -> Result<String, GdsError> {
+    let layers = cell_polygons(library, cell)?;
+    let scale = library.units.db_unit_meters * 1e6;
+
+    let mut dxf = String::new();
+    pair(&mut dxf, 0, "SECTION");
+    pair(&mut dxf, 2, "HEADER");
+    pair(&mut dxf, 9, "$ACADVER");
+    pair(&mut dxf, 1, "AC1015");
+    pair(&mut dxf, 9, "$INSUNITS");
+    pair(&mut dxf, 70, INSUNITS_MICRONS);
+    pair(&mut dxf, 0, "ENDSEC");
+
+    pair(&mut dxf, 0, "SECTION");
+    pair(&mut dxf, 2, "TABLES");
+    pair(&mut dxf, 0, "TABLE");
+    pair(&mut dxf, 2, "LAYER");
+    pair(&mut dxf, 70, layers.len());
+    for (layer, datatype) in layers.keys() {
+        pair(&mut dxf, 0, "LAYER");
+        pair(&mut dxf, 2, dxf_layer_name(*layer, *datatype));
+        pair(&mut dxf, 70, 0);
+        // ACI colors 1-255, cycled by layer number
+        pair(&mut dxf, 62, *layer as u32 % 255 + 1);
+        pair(&mut dxf, 6, "CONTINUOUS");
+    }
+    pair(&mut dxf, 0, "ENDTAB");
+    pair(&mut dxf, 0, "ENDSEC");
+
+    pair(&mut dxf, 0, "SECTION");
+    pair(&mut dxf, 2, "ENTITIES");
+    for ((layer, datatype), polygons) in &layers {
+        let name = dxf_layer_name(*layer, *datatype);
+        for polygon in polygons {
+            // The closed flag replaces GDSII's repeated first point
+            let points = match polygon.split_last() {
+                Some((last, rest)) if rest.first() == Some(last) => rest,
+                _ => polygon.as_slice(),
+            };
+            pair(&mut dxf, 0, "LWPOLYLINE");
+            pair(&mut dxf, 100, "AcDbEntity");
+            pair(&mut dxf, 8, &name);
+            pair(&mut dxf, 100, "AcDbPolyline");
+            pair(&mut dxf, 90, points.len());
+            pair(&mut dxf, 70, 1);
+            for [x, y] in points {
+                pair(&mut dxf, 10, x * scale);
+                pair(&mut dxf, 20, y * scale);
+            }
+        }
+    }
+    pair(&mut dxf, 0, "ENDSEC");
+    pair(&mut dxf, 0, "EOF");
+    Ok(dxf)
+}
+
+// Command to convert a cell of a GDSII file, flattened, into a DXF file at `dst`
+#[tauri::command]
+pub async fn convert_gds_to_dxf(
+    src: String,
+    dst: String,
+    cell: String,
+    settings: tauri::State<'_, Settings>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<(), AppError> {
+    ensure_writable(&settings, &[&src, &dst])?;
+    let library = cache.library(&src)?;
+    let dxf = cell_to_dxf(&library, &cell)?;
+    write_atomic(&dst, dxf.as_bytes()).map_err(|e| AppError::for_path(&dst, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dxf::parse_dxf_str;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn round_trips_polygon_count_through_the_dxf_reader() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("VIA").boundary(2, 0, &[[0, 0], [100, 0], [100, 100], [0, 100], [0, 0]]).end_cell();
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &[[0, 0], [5000, 0], [5000, 2000], [0, 0]])
+            .aref("VIA", [0, 0], 3, 2, [600, 0], [0, 400])
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let drawing = parse_dxf_str(&cell_to_dxf(&library, "TOP").unwrap()).unwrap();
+        let layers: Vec<(&str, usize)> =
+            drawing.layers.iter().map(|layer| (layer.name.as_str(), layer.shapes.len())).collect();
+        assert_eq!(layers, vec![("L1D0", 1), ("L2D0", 6)]);
+
+        // The fixture uses 1 nm database units, so 5000 units are 5 microns
+        let triangle = &drawing.layers[0].shapes[0];
+        assert!(triangle.closed);
+        assert_eq!(triangle.points.len(), 3);
+        assert!((triangle.points[1][0] - 5.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,802 @@
+use std::collections::{HashMap, HashSet};
+
+use super::parse_layer_key;
+use crate::error::AppError;
+use crate::gds::cache::ParseCache;
+use crate::gds::flatten::cell_polygons;
+use crate::gds::hierarchy::cell_graph;
+use crate::gds::transform::Transform;
+use crate::gds::writer::write_library;
+use crate::gds::{CellReference, GdsCell, GdsElement, GdsError, GdsLibrary, Strans};
+use crate::geometry::boolean::{boolean, BooleanOp};
+use crate::geometry::rtree::Rect;
+use crate::geometry::signed_area;
+use crate::paths::write_atomic;
+use crate::read_only::ensure_writable;
+use crate::settings::Settings;
+
+// Cells reachable from `name`, children before parents
+fn collect_cells<'a>(
+    library: &'a GdsLibrary,
+    name: &str,
+    visiting: &mut HashSet<String>,
+    done: &mut HashSet<String>,
+    order: &mut Vec<&'a GdsCell>,
+) -> Result<(), GdsError> {
+    if done.contains(name) {
+        return Ok(());
+    }
+    let cell = library
+        .cell(name)
+        .ok_or_else(|| GdsError::CellNotFound { cell: name.to_string() })?;
+    if !visiting.insert(name.to_string()) {
+        return Err(GdsError::CyclicReference { cell: name.to_string() });
+    }
+    for reference in &cell.references {
+        collect_cells(library, &reference.cell, visiting, done, order)?;
+    }
+    visiting.remove(name);
+    done.insert(name.to_string());
+    order.push(cell);
+    Ok(())
+}
+
+// The part of `library` under `cell` that is drawn on one of `layers` (any
+// datatype; BOX and TEXT elements count by their layer too). The hierarchy is
+// kept rather than flattened: every cell reachable from `cell` is copied with
+// its other-layer elements dropped, and cells left with nothing to draw are
+// removed along with the references to them. Library name and units carry over.
+pub fn layer_subset(
+    library: &GdsLibrary,
+    cell: &str,
+    layers: &[u16],
+) -> Result<GdsLibrary, GdsError> {
+    let mut order = Vec::new();
+    collect_cells(library, cell, &mut HashSet::new(), &mut HashSet::new(), &mut order)?;
+
+    // Children come first, so references only point at cells already decided
+    let mut kept: HashMap<&str, GdsCell> = HashMap::new();
+    for source in &order {
+        let elements: Vec<_> = source
+            .elements
+            .iter()
+            .filter(|element| layers.contains(&element.layer_key().0))
+            .cloned()
+            .collect();
+        let references: Vec<_> = source
+            .references
+            .iter()
+            .filter(|reference| kept.contains_key(reference.cell.as_str()))
+            .cloned()
+            .collect();
+        if !elements.is_empty() || !references.is_empty() || source.name == cell {
+            let subset = GdsCell {
+                name: source.name.clone(),
+                dates: source.dates.clone(),
+                references,
+                elements,
+            };
+            kept.insert(&source.name, subset);
+        }
+    }
+
+    // Cells are written in their original library order
+    let cells =
+        library.cells.iter().filter_map(|source| kept.remove(source.name.as_str())).collect();
+    Ok(GdsLibrary {
+        name: library.name.clone(),
+        version: library.version,
+        units: library.units,
+        dates: library.dates.clone(),
+        cells,
+        warnings: Vec::new(),
+    })
+}
+
+// Command to write the elements of `cell` on the given layers, with the cells
+// below it, to a new GDSII file at `dst`
+#[tauri::command]
+pub async fn export_gds_subset(
+    src: String,
+    dst: String,
+    cell: String,
+    layers: Vec<u16>,
+    settings: tauri::State<'_, Settings>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<(), AppError> {
+    ensure_writable(&settings, &[&src, &dst])?;
+    let library = cache.library(&src)?;
+    let bytes = write_library(&layer_subset(&library, &cell, &layers)?)?;
+    write_atomic(&dst, &bytes).map_err(|e| AppError::for_path(&dst, e))
+}
+
+// The pieces of a polygon inside `region`, as closed rings on the database grid
+fn clip_to_region(outline: &[[f64; 2]], region: &Rect) -> Vec<Vec<[i32; 2]>> {
+    let Some(bounds) = Rect::around(outline) else {
+        return Vec::new();
+    };
+    let rings = if region.contains(bounds.min) && region.contains(bounds.max) {
+        vec![outline.to_vec()]
+    } else if bounds.intersects(region) {
+        let window = vec![
+            region.min,
+            [region.max[0], region.min[1]],
+            region.max,
+            [region.min[0], region.max[1]],
+        ];
+        boolean(&[outline.to_vec()], &[window], BooleanOp::And)
+    } else {
+        Vec::new()
+    };
+    rings
+        .iter()
+        // Clipping a simple polygon to a rectangle leaves no holes; a
+        // self-intersecting one could, and BOUNDARY can't express them
+        .filter(|ring| signed_area(ring).abs() > 0.0)
+        .map(|ring| {
+            let mut points: Vec<[i32; 2]> =
+                ring.iter().map(|p| [p[0].round() as i32, p[1].round() as i32]).collect();
+            points.dedup();
+            if points.first() != points.last() {
+                points.push(points[0]);
+            }
+            points
+        })
+        // At least a triangle once snapped to the grid
+        .filter(|points| points.len() >= 4)
+        .collect()
+}
+
+// The geometry of `cell` inside `region` (database units) as a library with a
+// single flattened cell of the same name. Boundaries, path outlines and boxes
+// crossing the edge of the region are clipped to it and written as
+// boundaries; text is dropped. Library name and units carry over.
+pub fn cropped_to_region(
+    library: &GdsLibrary,
+    cell: &str,
+    region: &Rect,
+) -> Result<GdsLibrary, GdsError> {
+    let mut elements = Vec::new();
+    for ((layer, datatype), outlines) in cell_polygons(library, cell)? {
+        for outline in &outlines {
+            for points in clip_to_region(outline, region) {
+                elements.push(GdsElement::boundary(layer, datatype, points, Vec::new()));
+            }
+        }
+    }
+    let cropped = GdsCell {
+        name: cell.to_string(),
+        dates: Default::default(),
+        references: Vec::new(),
+        elements,
+    };
+    Ok(GdsLibrary {
+        name: library.name.clone(),
+        version: library.version,
+        units: library.units,
+        dates: library.dates.clone(),
+        cells: vec![cropped],
+        warnings: Vec::new(),
+    })
+}
+
+// Command to write the geometry of `cell` inside a rectangle, given in user
+// units, to a new GDSII file at `dst` as one flattened cell
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn crop_to_region(
+    src: String,
+    dst: String,
+    cell: String,
+    min_x: f64,
+    min_y: f64,
+    max_x: f64,
+    max_y: f64,
+    settings: tauri::State<'_, Settings>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<(), AppError> {
+    ensure_writable(&settings, &[&src, &dst])?;
+    let finite = [min_x, min_y, max_x, max_y].iter().all(|value| value.is_finite());
+    if !(finite && min_x < max_x && min_y < max_y) {
+        return Err(AppError::invalid_argument(format!(
+            "Region ({}, {}) - ({}, {}) must have its minimum below its maximum",
+            min_x, min_y, max_x, max_y
+        )));
+    }
+    let library = cache.library(&src)?;
+    let user_unit = library.units.user_unit;
+    let region = Rect {
+        min: [(min_x / user_unit).round(), (min_y / user_unit).round()],
+        max: [(max_x / user_unit).round(), (max_y / user_unit).round()],
+    };
+    let bytes = write_library(&cropped_to_region(&library, &cell, &region)?)?;
+    write_atomic(&dst, &bytes).map_err(|e| AppError::for_path(&dst, e))
+}
+
+// Longest structure name the GDSII spec allows
+const MAX_CELL_NAME_LEN: usize = 32;
+
+// Whether `name` is a structure name the GDSII spec allows: 1 to 32 of
+// `A-Z`, `a-z`, `0-9`, `_`, `?` and `$`
+fn is_valid_cell_name(name: &str) -> bool {
+    (1..=MAX_CELL_NAME_LEN).contains(&name.len())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '?' | '$'))
+}
+
+// A copy of `library` with cell `old_name` called `new_name`, in its own
+// STRNAME and in every SREF/AREF pointing at it. Fails if `new_name` is not a
+// valid structure name or already names a cell, since renaming onto an
+// existing cell would silently merge the two.
+pub fn renamed_cell(
+    library: &GdsLibrary,
+    old_name: &str,
+    new_name: &str,
+) -> Result<GdsLibrary, AppError> {
+    if !is_valid_cell_name(new_name) {
+        return Err(AppError::invalid_argument(format!(
+            "Cell names must be 1 to {} letters, digits, '_', '?' or '$', got {:?}",
+            MAX_CELL_NAME_LEN, new_name
+        )));
+    }
+    if library.cell(old_name).is_none() {
+        return Err(GdsError::CellNotFound { cell: old_name.to_string() }.into());
+    }
+    if library.cell(new_name).is_some() {
+        return Err(AppError::invalid_argument(format!("Cell {} already exists", new_name)));
+    }
+    let mut renamed = library.clone();
+    renamed.warnings.clear();
+    for cell in &mut renamed.cells {
+        if cell.name == old_name {
+            cell.name = new_name.to_string();
+        }
+        for reference in &mut cell.references {
+            if reference.cell == old_name {
+                reference.cell = new_name.to_string();
+            }
+        }
+    }
+    Ok(renamed)
+}
+
+// Command to write a copy of the GDSII file at `src` to `dst` with one cell
+// renamed throughout
+#[tauri::command]
+pub async fn rename_cell(
+    src_path: String,
+    dst_path: String,
+    old_name: String,
+    new_name: String,
+    settings: tauri::State<'_, Settings>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<(), AppError> {
+    ensure_writable(&settings, &[&src_path, &dst_path])?;
+    let library = cache.library(&src_path)?;
+    let bytes = write_library(&renamed_cell(&library, &old_name, &new_name)?)?;
+    write_atomic(&dst_path, &bytes).map_err(|e| AppError::for_path(&dst_path, e))
+}
+
+// Names of `roots` and of every cell they reference through `edges`, the
+// (parent, child) pairs of a cell graph
+fn reachable_names<'a>(edges: &'a [(String, String)], roots: &[&'a str]) -> HashSet<&'a str> {
+    let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (parent, child) in edges {
+        children.entry(parent.as_str()).or_default().push(child.as_str());
+    }
+    let mut reachable: HashSet<&str> = roots.iter().copied().collect();
+    let mut pending = roots.to_vec();
+    while let Some(parent) = pending.pop() {
+        for &child in children.get(parent).into_iter().flatten() {
+            if reachable.insert(child) {
+                pending.push(child);
+            }
+        }
+    }
+    reachable
+}
+
+// `library` cut down to `cell` and the cells it references, directly or
+// through others, in their original order and otherwise unchanged. References
+// to cells missing from the source stay as they were.
+pub fn minimized(library: &GdsLibrary, cell: &str) -> Result<GdsLibrary, GdsError> {
+    if library.cell(cell).is_none() {
+        return Err(GdsError::CellNotFound { cell: cell.to_string() });
+    }
+    let graph = cell_graph(library);
+    let reachable = reachable_names(&graph.edges, &[cell]);
+    let cells = library
+        .cells
+        .iter()
+        .filter(|source| reachable.contains(source.name.as_str()))
+        .cloned()
+        .collect();
+    Ok(GdsLibrary { cells, warnings: Vec::new(), ..library.clone() })
+}
+
+// Command to write `cell` and only the cells it needs from the GDSII file at
+// `src` to `dst`
+#[tauri::command]
+pub async fn save_minimized(
+    src: String,
+    dst: String,
+    cell: String,
+    settings: tauri::State<'_, Settings>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<(), AppError> {
+    ensure_writable(&settings, &[&src, &dst])?;
+    let library = cache.library(&src)?;
+    let bytes = write_library(&minimized(&library, &cell)?)?;
+    write_atomic(&dst, &bytes).map_err(|e| AppError::for_path(&dst, e))
+}
+
+// `library` cut down to `cells` and everything they reference, directly or
+// through others, with each shared cell copied once, in their original order
+// and otherwise unchanged. Unlike `minimized`, the result must stand on its
+// own: a listed cell that doesn't exist, or a reference to a cell missing from
+// the source, fails rather than being carried over.
+pub fn extracted(library: &GdsLibrary, cells: &[String]) -> Result<GdsLibrary, AppError> {
+    if cells.is_empty() {
+        return Err(AppError::invalid_argument("No cells to export"));
+    }
+    let mut missing: Vec<&str> = Vec::new();
+    for name in cells {
+        if library.cell(name).is_none() && !missing.contains(&name.as_str()) {
+            missing.push(name);
+        }
+    }
+    match missing.as_slice() {
+        [] => {}
+        [name] => return Err(GdsError::CellNotFound { cell: name.to_string() }.into()),
+        names => {
+            return Err(AppError::invalid_argument(format!("Cells {} not found", names.join(", "))))
+        }
+    }
+
+    let graph = cell_graph(library);
+    let roots: Vec<&str> = cells.iter().map(String::as_str).collect();
+    let reachable = reachable_names(&graph.edges, &roots);
+    let mut written: HashSet<&str> = HashSet::new();
+    let kept: Vec<GdsCell> = library
+        .cells
+        .iter()
+        // A name defined twice is written once, as the parser resolves it
+        .filter(|source| reachable.contains(source.name.as_str()))
+        .filter(|source| written.insert(source.name.as_str()))
+        .cloned()
+        .collect();
+    for cell in &kept {
+        let unresolved =
+            cell.references.iter().find(|reference| !written.contains(reference.cell.as_str()));
+        if let Some(reference) = unresolved {
+            return Err(AppError::invalid_argument(format!(
+                "Cell {} references {}, which isn't in the library",
+                cell.name, reference.cell
+            )));
+        }
+    }
+    Ok(GdsLibrary { cells: kept, warnings: Vec::new(), ..library.clone() })
+}
+
+// Command to write the listed cells and everything they need from the GDSII
+// file at `src` to a new library at `dst`
+#[tauri::command]
+pub async fn export_cells(
+    src: String,
+    dst: String,
+    cells: Vec<String>,
+    settings: tauri::State<'_, Settings>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<(), AppError> {
+    ensure_writable(&settings, &[&src, &dst])?;
+    let library = cache.library(&src)?;
+    let bytes = write_library(&extracted(&library, &cells)?)?;
+    write_atomic(&dst, &bytes).map_err(|e| AppError::for_path(&dst, e))
+}
+
+// `inner` placed under `outer`, a STRANS without magnification: reflections
+// cancel out, and a reflecting `outer` turns `inner`'s rotation the other way
+fn compose_strans(outer: &Strans, inner: &Strans) -> Strans {
+    let angle = if outer.reflect_x { outer.angle - inner.angle } else { outer.angle + inner.angle };
+    Strans {
+        reflect_x: outer.reflect_x != inner.reflect_x,
+        angle: angle.rem_euclid(360.0),
+        ..*inner
+    }
+}
+
+// `point` moved by `transform` and rounded to the database grid
+fn snapped(transform: &Transform, point: [i32; 2]) -> [i32; 2] {
+    let [x, y] = transform.apply_point(point);
+    [x.round() as i32, y.round() as i32]
+}
+
+// `element` moved by `transform`, the placement `strans` describes
+fn transformed_element(element: &GdsElement, transform: &Transform, strans: &Strans) -> GdsElement {
+    let snap_all = |points: &[[i32; 2]]| -> Vec<[i32; 2]> {
+        points.iter().map(|point| snapped(transform, *point)).collect()
+    };
+    let mut moved = element.clone();
+    match &mut moved {
+        GdsElement::Boundary { points, .. }
+        | GdsElement::Path { points, .. }
+        | GdsElement::Box { points, .. }
+        | GdsElement::Node { points, .. } => *points = snap_all(points),
+        GdsElement::Rect { .. } => {}
+        GdsElement::Text { position, strans: text_strans, .. } => {
+            *position = snapped(transform, *position);
+            *text_strans = compose_strans(strans, text_strans);
+        }
+    }
+    // Rectangles only stay rectangles when turned by a multiple of 90 degrees
+    match moved {
+        GdsElement::Rect { layer, datatype, rect, properties } => {
+            GdsElement::boundary(layer, datatype, snap_all(&rect.points()), properties)
+        }
+        GdsElement::Boundary { layer, datatype, points, properties } => {
+            GdsElement::boundary(layer, datatype, points, properties)
+        }
+        moved => moved,
+    }
+}
+
+fn transformed_reference(
+    reference: &CellReference,
+    transform: &Transform,
+    strans: &Strans,
+) -> CellReference {
+    let mut moved = reference.clone();
+    moved.origin = snapped(transform, reference.origin);
+    moved.strans = compose_strans(strans, &reference.strans);
+    if let Some(array) = &mut moved.array {
+        array.column_point = snapped(transform, array.column_point);
+        array.row_point = snapped(transform, array.row_point);
+    }
+    moved
+}
+
+// A copy of `library` with everything drawn in `cell` reflected about the X
+// axis if `reflect_x`, rotated counterclockwise by `rotation` degrees about
+// the origin, then moved by `offset` (database units). The transform is
+// applied to the cell's own elements and to its placements of other cells,
+// which are left unchanged, rather than wrapping the cell in a new SREF: the
+// cell keeps its name, and every placement of it elsewhere moves with it.
+// Points are rounded to the database grid, so rotations off multiples of 90
+// degrees may move vertices by up to half a unit.
+pub fn transformed_cell(
+    library: &GdsLibrary,
+    cell: &str,
+    rotation: f64,
+    reflect_x: bool,
+    offset: [i32; 2],
+) -> Result<GdsLibrary, GdsError> {
+    if library.cell(cell).is_none() {
+        return Err(GdsError::CellNotFound { cell: cell.to_string() });
+    }
+    let strans = Strans { reflect_x, angle: rotation, ..Strans::default() };
+    let transform = Transform::from_placement(&strans, offset);
+    let mut transformed = library.clone();
+    transformed.warnings.clear();
+    for target in transformed.cells.iter_mut().filter(|target| target.name == cell) {
+        for element in &mut target.elements {
+            *element = transformed_element(element, &transform, &strans);
+        }
+        for reference in &mut target.references {
+            *reference = transformed_reference(reference, &transform, &strans);
+        }
+    }
+    Ok(transformed)
+}
+
+// Command to write a copy of the GDSII file at `src` to `dst` with `cell`
+// rotated, reflected and moved, e.g. to align a block before handoff. `dx`
+// and `dy` are in user units.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn transform_cell(
+    src: String,
+    dst: String,
+    cell: String,
+    rotation_deg: f64,
+    reflect_x: bool,
+    dx: f64,
+    dy: f64,
+    settings: tauri::State<'_, Settings>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<(), AppError> {
+    ensure_writable(&settings, &[&src, &dst])?;
+    if ![rotation_deg, dx, dy].iter().all(|value| value.is_finite()) {
+        return Err(AppError::invalid_argument(format!(
+            "Rotation {} and offset ({}, {}) must be finite",
+            rotation_deg, dx, dy
+        )));
+    }
+    let library = cache.library(&src)?;
+    let user_unit = library.units.user_unit;
+    let offset = [(dx / user_unit).round() as i32, (dy / user_unit).round() as i32];
+    let transformed = transformed_cell(&library, &cell, rotation_deg, reflect_x, offset)?;
+    let bytes = write_library(&transformed)?;
+    write_atomic(&dst, &bytes).map_err(|e| AppError::for_path(&dst, e))
+}
+
+// Target (layer, datatype) by source (layer, datatype)
+pub type LayerMap = HashMap<(u16, u16), (u16, u16)>;
+
+// Parse a remap table of "layer/datatype" keys, e.g. "17/0" to "1/0"
+fn parse_layer_map(layer_map: &HashMap<String, String>) -> Result<LayerMap, AppError> {
+    layer_map
+        .iter()
+        .map(|(from, to)| match (parse_layer_key(from), parse_layer_key(to)) {
+            (Some(from), Some(to)) => Ok((from, to)),
+            _ => Err(AppError::invalid_argument(format!(
+                "layer mapping {:?} -> {:?} is not of the form \"layer/datatype\"",
+                from, to
+            ))),
+        })
+        .collect()
+}
+
+// A copy of `library` with every element on a source (layer, datatype) of
+// `layer_map` moved to its target; other elements keep their layer. Each
+// element is looked up once by its original layer, so mappings don't chain,
+// and sources sharing a target are merged into it with every element kept.
+pub fn remapped_layers(library: &GdsLibrary, layer_map: &LayerMap) -> GdsLibrary {
+    let mut remapped = library.clone();
+    remapped.warnings.clear();
+    for cell in &mut remapped.cells {
+        for element in &mut cell.elements {
+            if let Some(target) = layer_map.get(&element.layer_key()) {
+                element.set_layer_key(*target);
+            }
+        }
+    }
+    remapped
+}
+
+// Command to write a copy of the GDSII file at `src` to `dst` with its layers
+// renumbered by `layer_map`, keyed and valued by "layer/datatype"
+#[tauri::command]
+pub async fn remap_layers(
+    src: String,
+    dst: String,
+    layer_map: HashMap<String, String>,
+    settings: tauri::State<'_, Settings>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<(), AppError> {
+    ensure_writable(&settings, &[&src, &dst])?;
+    let layer_map = parse_layer_map(&layer_map)?;
+    let library = cache.library(&src)?;
+    let bytes = write_library(&remapped_layers(&library, &layer_map))?;
+    write_atomic(&dst, &bytes).map_err(|e| AppError::for_path(&dst, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::bbox::{Bbox, BboxCalculator};
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::layers::collect_layers;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn round_trip_keeps_only_requested_layers() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("METAL").boundary(2, 0, &[[0, 0], [10, 0], [10, 10], [0, 0]]).end_cell();
+        gds.begin_cell("VIA")
+            .boundary(3, 0, &[[0, 0], [5, 0], [5, 5], [0, 0]])
+            .text(2, 1, [1, 1], "via")
+            .end_cell();
+        gds.begin_cell("UNUSED").boundary(2, 0, &[[0, 0], [1, 0], [1, 1], [0, 0]]).end_cell();
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &[[0, 0], [100, 0], [100, 100], [0, 0]])
+            .path(2, 5, 0, 4, &[[0, 0], [50, 0]])
+            .sref("METAL", [0, 0], None)
+            .aref("VIA", [0, 0], 2, 2, [20, 0], [0, 20])
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let dst = dir.path().join("subset.gds");
+        let subset = layer_subset(&library, "TOP", &[2]).unwrap();
+        std::fs::write(&dst, write_library(&subset).unwrap()).unwrap();
+        let written = parse_library(&std::fs::read(&dst).unwrap()).unwrap();
+
+        assert_eq!(written.units, library.units);
+        let layers: Vec<u16> = collect_layers(&written).iter().map(|info| info.layer).collect();
+        assert!(layers.iter().all(|layer| *layer == 2), "{:?}", layers);
+        let names: Vec<&str> = written.cells.iter().map(|cell| cell.name.as_str()).collect();
+        assert_eq!(names, vec!["METAL", "VIA", "TOP"]);
+        assert_eq!(written.cell("TOP").unwrap().references.len(), 2);
+        assert_eq!(written.cell("VIA").unwrap().elements.len(), 1);
+    }
+
+    #[test]
+    fn remapped_layers_merge_without_chaining() {
+        let square = [[0, 0], [10, 0], [10, 10], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .boundary(17, 0, &square)
+            .boundary(18, 0, &square)
+            .boundary(1, 0, &square)
+            .text(17, 0, [1, 1], "VDD")
+            .boundary(17, 1, &square)
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+        let layer_map = HashMap::from([
+            ("17/0".to_string(), "1/0".to_string()),
+            ("18/0".to_string(), "1/0".to_string()),
+            ("1/0".to_string(), "2/0".to_string()),
+        ]);
+
+        let remapped = remapped_layers(&library, &parse_layer_map(&layer_map).unwrap());
+        let written = parse_library(&write_library(&remapped).unwrap()).unwrap();
+        let keys: Vec<(u16, u16)> =
+            written.cell("TOP").unwrap().elements.iter().map(GdsElement::layer_key).collect();
+        assert_eq!(keys, [(1, 0), (1, 0), (2, 0), (1, 0), (17, 1)]);
+
+        let bad = HashMap::from([("17".to_string(), "1/0".to_string())]);
+        assert!(matches!(parse_layer_map(&bad), Err(AppError::InvalidArgument { .. })));
+    }
+
+    #[test]
+    fn renamed_cells_are_still_referenced() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("VIA").boundary(3, 0, &[[0, 0], [5, 0], [5, 5], [0, 0]]).end_cell();
+        gds.begin_cell("ROW").aref("VIA", [0, 0], 4, 1, [40, 0], [0, 10]).end_cell();
+        gds.begin_cell("TOP").sref("VIA", [0, 0], None).sref("ROW", [0, 50], None).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let renamed = renamed_cell(&library, "VIA", "VIA_M1$2").unwrap();
+        let written = parse_library(&write_library(&renamed).unwrap()).unwrap();
+        let names: Vec<&str> = written.cells.iter().map(|cell| cell.name.as_str()).collect();
+        assert_eq!(names, vec!["VIA_M1$2", "ROW", "TOP"]);
+        let resolves = |cell: &GdsCell| {
+            cell.references.iter().all(|reference| written.cell(&reference.cell).is_some())
+        };
+        assert!(written.cells.iter().all(resolves));
+        assert_eq!(written.cell("TOP").unwrap().references[0].cell, "VIA_M1$2");
+
+        let invalid = |result: Result<GdsLibrary, AppError>| {
+            matches!(result, Err(AppError::InvalidArgument { .. }))
+        };
+        assert!(invalid(renamed_cell(&library, "VIA", "ROW")));
+        assert!(invalid(renamed_cell(&library, "VIA", "via-m1")));
+        assert!(invalid(renamed_cell(&library, "VIA", &"V".repeat(33))));
+        assert!(renamed_cell(&library, "GONE", "NEW").is_err());
+    }
+
+    #[test]
+    fn minimized_libraries_keep_only_what_the_cell_needs() {
+        let square = [[0, 0], [10, 0], [10, 10], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("VIA").boundary(3, 0, &square).end_cell();
+        gds.begin_cell("SPARE").boundary(1, 0, &square).end_cell();
+        gds.begin_cell("ROW").aref("VIA", [0, 0], 4, 1, [40, 0], [0, 10]).end_cell();
+        gds.begin_cell("BLOCK").sref("ROW", [0, 0], None).sref("VIA", [0, 50], None).end_cell();
+        gds.begin_cell("CHIP").sref("BLOCK", [0, 0], None).sref("SPARE", [0, 0], None).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let dst = dir.path().join("block.gds");
+        std::fs::write(&dst, write_library(&minimized(&library, "BLOCK").unwrap()).unwrap())
+            .unwrap();
+        let written = parse_library(&std::fs::read(&dst).unwrap()).unwrap();
+        let names: Vec<&str> = written.cells.iter().map(|cell| cell.name.as_str()).collect();
+        assert_eq!(names, ["VIA", "ROW", "BLOCK"]);
+        let resolves = |cell: &GdsCell| {
+            cell.references.iter().all(|reference| written.cell(&reference.cell).is_some())
+        };
+        assert!(written.cells.iter().all(resolves));
+        // Kept cells are copied as they were
+        let row = |library: &GdsLibrary| library.cell("ROW").unwrap().references.clone();
+        assert_eq!(row(&written), row(&library));
+        assert!(cell_polygons(&written, "BLOCK").is_ok());
+        assert!(minimized(&library, "GONE").is_err());
+    }
+
+    #[test]
+    fn exported_cells_share_their_dependencies_and_must_resolve() {
+        let square = [[0, 0], [10, 0], [10, 10], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("VIA").boundary(3, 0, &square).end_cell();
+        gds.begin_cell("INV").sref("VIA", [0, 0], None).end_cell();
+        gds.begin_cell("NAND").sref("VIA", [0, 0], None).sref("VIA", [20, 0], None).end_cell();
+        gds.begin_cell("SPARE").boundary(1, 0, &square).end_cell();
+        gds.begin_cell("BROKEN").sref("GONE", [0, 0], None).end_cell();
+        gds.begin_cell("CHIP").sref("INV", [0, 0], None).sref("NAND", [50, 0], None).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let cells = ["NAND", "INV", "NAND"].map(String::from);
+        let written = parse_library(&write_library(&extracted(&library, &cells).unwrap()).unwrap())
+            .unwrap();
+        let names: Vec<&str> = written.cells.iter().map(|cell| cell.name.as_str()).collect();
+        assert_eq!(names, ["VIA", "INV", "NAND"]);
+        let nand = |library: &GdsLibrary| library.cell("NAND").unwrap().references.clone();
+        assert_eq!(nand(&written), nand(&library));
+
+        let missing = ["INV", "NOR", "XOR"].map(String::from);
+        let error = extracted(&library, &missing).unwrap_err();
+        assert!(error.to_string().contains("NOR, XOR"), "{}", error);
+        assert!(extracted(&library, &["BROKEN".to_string()]).is_err());
+        assert!(extracted(&library, &[]).is_err());
+    }
+
+    #[test]
+    fn transformed_cells_move_their_extent() {
+        let rect = |w: i32, h: i32| [[0, 0], [w, 0], [w, h], [0, h], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("PAD").boundary(1, 0, &rect(10, 10)).end_cell();
+        gds.begin_cell("TOP")
+            .boundary(2, 0, &rect(20, 40))
+            .sref("PAD", [100, 0], Some((false, 90.0, 1.0)))
+            .text(3, 0, [5, 5], "top")
+            .end_cell();
+        gds.begin_cell("CHIP").sref("TOP", [0, 0], None).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let extent = |library: &GdsLibrary, cell: &str| {
+            BboxCalculator::new(library).cell_bbox(cell).unwrap().unwrap()
+        };
+        let bbox = |min_x, min_y, max_x, max_y| Bbox { min_x, min_y, max_x, max_y };
+        assert_eq!(extent(&library, "TOP"), bbox(0.0, 0.0, 100.0, 40.0));
+
+        // (x, y) -> (-y, x), then moved right by 5
+        let rotated = transformed_cell(&library, "TOP", 90.0, false, [5, 0]).unwrap();
+        let written = parse_library(&write_library(&rotated).unwrap()).unwrap();
+        assert_eq!(written.units, library.units);
+        assert_eq!(extent(&written, "TOP"), bbox(-35.0, 0.0, 5.0, 100.0));
+        // Placements of the transformed cell move with it; the cells it
+        // places are unchanged
+        assert_eq!(extent(&written, "CHIP"), extent(&written, "TOP"));
+        let pad = |library: &GdsLibrary| library.cell("PAD").unwrap().elements.clone();
+        assert_eq!(pad(&written), pad(&library));
+        let top = written.cell("TOP").unwrap();
+        assert_eq!(top.references[0].strans.angle, 180.0);
+        let GdsElement::Text { position, strans, .. } = &top.elements[1] else {
+            panic!("expected the label");
+        };
+        assert_eq!((*position, strans.angle), ([0, 5], 90.0));
+
+        // (x, y) -> (x, -y); a reflected parent flips the child's rotation
+        let mirrored = transformed_cell(&library, "TOP", 0.0, true, [0, 0]).unwrap();
+        assert_eq!(extent(&mirrored, "TOP"), bbox(0.0, -40.0, 100.0, 0.0));
+        let reference = &mirrored.cell("TOP").unwrap().references[0];
+        assert!(reference.strans.reflect_x);
+        assert_eq!(reference.strans.angle, 270.0);
+        assert!(transformed_cell(&library, "GONE", 0.0, false, [0, 0]).is_err());
+    }
+
+    #[test]
+    fn cropping_clips_flattened_geometry_to_the_region() {
+        let square = |x: i32, y: i32, size: i32| {
+            [[x, y], [x + size, y], [x + size, y + size], [x, y + size], [x, y]]
+        };
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("PAD").boundary(1, 0, &square(0, 0, 10)).end_cell();
+        gds.begin_cell("TOP")
+            // Crosses the right edge of the region
+            .boundary(2, 0, &square(80, 20, 40))
+            .sref("PAD", [10, 10], None)
+            .sref("PAD", [500, 500], None)
+            .text(3, 0, [50, 50], "label")
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let region = Rect { min: [0.0, 0.0], max: [100.0, 100.0] };
+        let cropped = cropped_to_region(&library, "TOP", &region).unwrap();
+        let written = parse_library(&write_library(&cropped).unwrap()).unwrap();
+        assert_eq!(written.units, library.units);
+        assert_eq!(written.cells.len(), 1);
+        let top = written.cell("TOP").unwrap();
+        assert!(top.references.is_empty());
+
+        let polygons = cell_polygons(&written, "TOP").unwrap();
+        let layers: Vec<(u16, u16)> = polygons.keys().copied().collect();
+        assert_eq!(layers, vec![(1, 0), (2, 0)]);
+        // The placed pad inside is kept whole, the one outside dropped
+        assert_eq!(polygons[&(1, 0)].len(), 1);
+        let clipped = &polygons[&(2, 0)];
+        assert_eq!(clipped.len(), 1);
+        assert_eq!(signed_area(&clipped[0]).abs(), 20.0 * 40.0);
+        assert!(clipped[0].iter().all(|point| point[0] <= 100.0));
+    }
+}
@@ -0,0 +1,243 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::error::AppError;
+use crate::sync::lock;
+
+// How many polygons are drawn between progress callbacks
+pub const PROGRESS_INTERVAL_POLYGONS: usize = 10_000;
+
+// Progress of a running export, as reported to the progress callback
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+pub struct ExportProgress {
+    pub polygons_done: usize,
+    pub total_polygons: usize,
+}
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct ExportProgressPayload {
+    pub job_id: u64,
+    #[serde(flatten)]
+    pub progress: ExportProgress,
+}
+
+// Outcome of an export job: `error` is set when it failed or was cancelled,
+// and `png` holds the image of a `render_thumbnail` that succeeded
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct ExportCompletePayload {
+    pub job_id: u64,
+    pub png: Option<Vec<u8>>,
+    pub error: Option<AppError>,
+}
+
+// Report progress to `on_progress` before the first polygon, every
+// `PROGRESS_INTERVAL_POLYGONS` polygons and after the last one. Returns false
+// once the callback asks to stop.
+pub(crate) fn polygon_progress(
+    polygons_done: usize,
+    total_polygons: usize,
+    on_progress: &mut dyn FnMut(ExportProgress) -> bool,
+) -> bool {
+    if polygons_done % PROGRESS_INTERVAL_POLYGONS != 0 && polygons_done != total_polygons {
+        return true;
+    }
+    on_progress(ExportProgress { polygons_done, total_polygons })
+}
+
+// A running background export. Dropping the handle without joining would
+// detach the worker, so cancellation always joins it.
+struct ExportJob {
+    cancelled: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+// State to hold the running export jobs, keyed by job id. Finished jobs
+// remove themselves.
+#[derive(Default)]
+pub struct ExportJobs {
+    next_id: AtomicU64,
+    jobs: Arc<Mutex<HashMap<u64, ExportJob>>>,
+}
+
+impl ExportJobs {
+    // Run `work` on a background thread and return the job id. `work` gets a
+    // progress callback that returns false once the job was cancelled, and
+    // should then stop with `AppError::Cancelled` without writing anything.
+    pub fn start<W, P, C>(&self, work: W, mut on_progress: P, on_complete: C) -> u64
+    where
+        W: FnOnce(&mut dyn FnMut(ExportProgress) -> bool) -> Result<Option<Vec<u8>>, AppError>
+            + Send
+            + 'static,
+        P: FnMut(u64, ExportProgress) + Send + 'static,
+        C: FnOnce(u64, Result<Option<Vec<u8>>, AppError>) + Send + 'static,
+    {
+        let job_id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let jobs = Arc::clone(&self.jobs);
+
+        // Hold the lock while spawning so the worker can't remove its entry
+        // before it was inserted
+        let mut running = lock(&self.jobs, "Export jobs");
+        let worker_cancelled = Arc::clone(&cancelled);
+        let thread = std::thread::spawn(move || {
+            let result = work(&mut |progress| {
+                // Nothing is reported once the job was cancelled
+                if worker_cancelled.load(Ordering::Relaxed) {
+                    return false;
+                }
+                on_progress(job_id, progress);
+                !worker_cancelled.load(Ordering::Relaxed)
+            });
+            lock(&jobs, "Export jobs").remove(&job_id);
+            on_complete(job_id, result);
+        });
+        running.insert(job_id, ExportJob { cancelled, thread: Some(thread) });
+        job_id
+    }
+
+    // Cancel a running job and wait for its worker to exit, which drops the
+    // partially built output. Returns false if no such job is running.
+    pub fn cancel(&self, job_id: u64) -> bool {
+        let job = lock(&self.jobs, "Export jobs").remove(&job_id);
+        let Some(mut job) = job else {
+            return false;
+        };
+        job.cancelled.store(true, Ordering::Relaxed);
+        if let Some(thread) = job.thread.take() {
+            if thread.join().is_err() {
+                log::warn!("Export job {} panicked", job_id);
+            }
+        }
+        true
+    }
+
+    // Cancel every running job, then wait for all of their workers to exit.
+    // Returns how many jobs were running.
+    pub fn cancel_all(&self) -> usize {
+        let mut jobs: Vec<(u64, ExportJob)> = lock(&self.jobs, "Export jobs").drain().collect();
+        for (_, job) in &jobs {
+            job.cancelled.store(true, Ordering::Relaxed);
+        }
+        for (job_id, job) in &mut jobs {
+            if let Some(thread) = job.thread.take() {
+                if thread.join().is_err() {
+                    log::warn!("Export job {} panicked", job_id);
+                }
+            }
+        }
+        jobs.len()
+    }
+}
+
+// Start an export job that reports through `export-progress` and
+// `export-complete` events
+pub(crate) fn start_export_job<W>(app: AppHandle, jobs: &ExportJobs, work: W) -> u64
+where
+    W: FnOnce(&mut dyn FnMut(ExportProgress) -> bool) -> Result<Option<Vec<u8>>, AppError>
+        + Send
+        + 'static,
+{
+    let progress_app = app.clone();
+    jobs.start(
+        work,
+        move |job_id, progress| {
+            let payload = ExportProgressPayload { job_id, progress };
+            if let Err(e) = progress_app.emit("export-progress", payload) {
+                log::warn!("Failed to emit export progress: {}", e);
+            }
+        },
+        move |job_id, result| {
+            let (png, error) = match result {
+                Ok(png) => (png, None),
+                Err(error) => (None, Some(error)),
+            };
+            let payload = ExportCompletePayload { job_id, png, error };
+            if let Err(e) = app.emit("export-complete", payload) {
+                log::warn!("Failed to emit export result: {}", e);
+            }
+        },
+    )
+}
+
+// Command to abort a running `export_svg` or `render_thumbnail` job
+#[tauri::command]
+pub async fn cancel_export(job_id: u64, jobs: State<'_, ExportJobs>) -> Result<bool, AppError> {
+    Ok(jobs.cancel(job_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn cancelled_jobs_complete_with_cancelled_error() {
+        let jobs = ExportJobs::default();
+        let (sender, receiver) = mpsc::channel();
+        let (progress_sender, progress_receiver) = mpsc::channel();
+
+        let job_id = jobs.start(
+            |on_progress| {
+                let mut done = 0;
+                while polygon_progress(done, usize::MAX, on_progress) {
+                    done += PROGRESS_INTERVAL_POLYGONS;
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                Err(AppError::Cancelled)
+            },
+            move |_, progress| {
+                let _ = progress_sender.send(progress);
+            },
+            move |job_id, result| sender.send((job_id, result)).unwrap(),
+        );
+        let first = progress_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(first, ExportProgress { polygons_done: 0, total_polygons: usize::MAX });
+
+        assert!(jobs.cancel(job_id));
+        let (finished_id, result) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(finished_id, job_id);
+        assert!(matches!(result, Err(AppError::Cancelled)));
+        assert!(!jobs.cancel(job_id));
+    }
+
+    #[test]
+    fn cancel_all_stops_every_running_job() {
+        let jobs = ExportJobs::default();
+        let (sender, receiver) = mpsc::channel();
+        let (started_sender, started) = mpsc::channel();
+        for _ in 0..3 {
+            let sender = sender.clone();
+            let started_sender = started_sender.clone();
+            jobs.start(
+                |on_progress| {
+                    let mut done = 0;
+                    while polygon_progress(done, usize::MAX, on_progress) {
+                        done += PROGRESS_INTERVAL_POLYGONS;
+                        std::thread::sleep(Duration::from_millis(1));
+                    }
+                    Err(AppError::Cancelled)
+                },
+                move |job_id, _| {
+                    let _ = started_sender.send(job_id);
+                },
+                move |job_id, result| sender.send((job_id, result)).unwrap(),
+            );
+        }
+        for _ in 0..3 {
+            started.recv_timeout(Duration::from_secs(5)).unwrap();
+        }
+
+        assert_eq!(jobs.cancel_all(), 3);
+        for _ in 0..3 {
+            let (_, result) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert!(matches!(result, Err(AppError::Cancelled)));
+        }
+        assert_eq!(jobs.cancel_all(), 0);
+    }
+}
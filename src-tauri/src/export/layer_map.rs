@@ -0,0 +1,169 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::fmt::Write;
+
+use super::layer_key_string;
+use super::measurements::escape_field;
+use super::svg::escape_attribute;
+use crate::error::AppError;
+use crate::gds::cache::ParseCache;
+use crate::gds::layers::{collect_layers, LayerInfo};
+use crate::layer_state::{file_layer_styles, LayerState};
+use crate::paths::write_atomic;
+use crate::read_only::ensure_writable;
+use crate::settings::Settings;
+
+// Columns of the exported CSV, in order. Only ever append to this list, so
+// scripts reading older exports keep working.
+const COLUMNS: &[&str] = &["layer", "datatype", "name", "color", "visible"];
+
+// What tool a layer map is written for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LayerMapFormat {
+    // One row per layer under a header of `COLUMNS`
+    Csv,
+    // A KLayout layer properties (.lyp) file
+    Klayout,
+}
+
+// One CSV row per layer in the order of `layers`, under a header of `COLUMNS`.
+// Layers without a name get an empty one.
+fn layer_map_csv(layers: &[LayerInfo], styles: &LayerState) -> String {
+    let mut csv = COLUMNS.join(",");
+    csv.push('\n');
+    for info in layers {
+        let Some(style) = styles.get(&layer_key_string(info.layer, info.datatype)) else {
+            continue;
+        };
+        let name = style.name.as_deref().unwrap_or_default();
+        // Writing to a String cannot fail
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{}",
+            info.layer,
+            info.datatype,
+            escape_field(name),
+            escape_field(&style.color),
+            style.visible
+        );
+    }
+    csv
+}
+
+// A KLayout layer properties file with one entry per layer in the order of
+// `layers`, which is also the order KLayout lists them in. Each entry is
+// filled and outlined in the layer's color and sourced from "layer/datatype"
+// of the first layout shown. KLayout names an entry without a name after its
+// source.
+fn layer_map_lyp(layers: &[LayerInfo], styles: &LayerState) -> String {
+    let mut lyp = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<layer-properties>\n");
+    for info in layers {
+        let Some(style) = styles.get(&layer_key_string(info.layer, info.datatype)) else {
+            continue;
+        };
+        let color = escape_attribute(&style.color);
+        let name = escape_attribute(style.name.as_deref().unwrap_or_default());
+        let _ = write!(
+            lyp,
+            concat!(
+                " <properties>\n",
+                "  <frame-color>{color}</frame-color>\n",
+                "  <fill-color>{color}</fill-color>\n",
+                "  <frame-brightness>0</frame-brightness>\n",
+                "  <fill-brightness>0</fill-brightness>\n",
+                "  <dither-pattern>I5</dither-pattern>\n",
+                "  <valid>true</valid>\n",
+                "  <visible>{visible}</visible>\n",
+                "  <transparent>false</transparent>\n",
+                "  <name>{name}</name>\n",
+                "  <source>{layer}/{datatype}@1</source>\n",
+                " </properties>\n",
+            ),
+            color = color,
+            visible = style.visible,
+            name = name,
+            layer = info.layer,
+            datatype = info.datatype,
+        );
+    }
+    lyp.push_str(" <name/>\n</layer-properties>\n");
+    lyp
+}
+
+pub fn layer_map(layers: &[LayerInfo], styles: &LayerState, format: LayerMapFormat) -> String {
+    match format {
+        LayerMapFormat::Csv => layer_map_csv(layers, styles),
+        LayerMapFormat::Klayout => layer_map_lyp(layers, styles),
+    }
+}
+
+// Command to write the layers of a file with the names, colors and visibility
+// the user gave them to a layer map at `out_path`, for carrying the styling
+// over to other tools
+#[tauri::command]
+pub async fn export_layer_map(
+    path: String,
+    out_path: String,
+    format: LayerMapFormat,
+    app: tauri::AppHandle,
+    settings: tauri::State<'_, Settings>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<(), AppError> {
+    ensure_writable(&settings, &[&path, &out_path])?;
+    let layers = collect_layers(&*cache.library(&path)?);
+    let styles = file_layer_styles(&app, &settings, &path, &layers)?;
+    let contents = layer_map(&layers, &styles, format);
+    write_atomic(&out_path, contents.as_bytes()).map_err(|e| AppError::for_path(&out_path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer_state::LayerStyle;
+
+    fn layers_and_styles() -> (Vec<LayerInfo>, LayerState) {
+        let layers = vec![
+            LayerInfo { layer: 2, datatype: 0, element_count: 4 },
+            LayerInfo { layer: 10, datatype: 5, element_count: 1 },
+        ];
+        let styles = LayerState::from([
+            (
+                "2/0".to_string(),
+                LayerStyle {
+                    visible: true,
+                    color: "#ff0000".to_string(),
+                    name: Some("M1 <drawing>, top".to_string()),
+                },
+            ),
+            (
+                "10/5".to_string(),
+                LayerStyle { visible: false, color: "#00ff00".to_string(), name: None },
+            ),
+        ]);
+        (layers, styles)
+    }
+
+    #[test]
+    fn layer_maps_list_layers_in_order_with_their_styles() {
+        let (layers, styles) = layers_and_styles();
+        assert_eq!(
+            layer_map(&layers, &styles, LayerMapFormat::Csv),
+            concat!(
+                "layer,datatype,name,color,visible\n",
+                "2,0,\"M1 <drawing>, top\",#ff0000,true\n",
+                "10,5,,#00ff00,false\n",
+            )
+        );
+
+        let lyp = layer_map(&layers, &styles, LayerMapFormat::Klayout);
+        assert!(lyp.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<layer-properties>"));
+        assert!(lyp.ends_with(" <name/>\n</layer-properties>\n"));
+        // Every entry is closed and names are escaped
+        assert_eq!(lyp.matches("<properties>").count(), 2);
+        assert_eq!(lyp.matches("</properties>").count(), 2);
+        assert!(lyp.contains("  <name>M1 &lt;drawing&gt;, top</name>\n  <source>2/0@1</source>\n"));
+        assert!(lyp.contains("  <visible>false</visible>\n"));
+        assert!(lyp.contains("  <name></name>\n  <source>10/5@1</source>\n"));
+    }
+}
@@ -0,0 +1,154 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::fmt::Write;
+
+use crate::error::AppError;
+use crate::geometry::{centroid, signed_area};
+use crate::paths::write_atomic;
+use crate::read_only::ensure_writable;
+use crate::settings::Settings;
+
+// Columns of the exported CSV, in order. Only ever append to this list, so
+// scripts reading older exports keep working.
+const COLUMNS: &[&str] = &["kind", "label", "x1", "y1", "x2", "y2", "value", "points"];
+
+// A measurement or annotation the user made in the viewer, with coordinates in
+// whatever unit the viewer shows, usually user units
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Measurement {
+    Distance { label: Option<String>, from: [f64; 2], to: [f64; 2] },
+    Area { label: Option<String>, points: Vec<[f64; 2]> },
+    Label { text: String, position: [f64; 2] },
+}
+
+// Quote a field when it holds a comma, a quote or a line break, doubling any
+// quotes, as RFC 4180 has it
+pub(crate) fn escape_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn check_points(points: &[[f64; 2]]) -> Result<(), AppError> {
+    match points.iter().find(|point| !point.iter().all(|value| value.is_finite())) {
+        Some(point) => Err(AppError::invalid_argument(format!(
+            "Measurement point {:?} is not finite",
+            point
+        ))),
+        None => Ok(()),
+    }
+}
+
+// One CSV row per measurement under a header of `COLUMNS`. A distance has its
+// end points in x1..y2 and its length as `value`; an area has its centroid in
+// x1/y1, its enclosed area as `value` and its outline as `x y` pairs separated
+// by `;` in `points`; a label has its text as `label` and its position in
+// x1/y1. Fields that don't apply are left empty.
+pub fn measurements_to_csv(measurements: &[Measurement]) -> Result<String, AppError> {
+    let mut csv = COLUMNS.join(",");
+    csv.push('\n');
+    for measurement in measurements {
+        let fields: [String; 8] = match measurement {
+            Measurement::Distance { label, from, to } => {
+                check_points(&[*from, *to])?;
+                let length = (to[0] - from[0]).hypot(to[1] - from[1]);
+                [
+                    "distance".to_string(),
+                    label.clone().unwrap_or_default(),
+                    from[0].to_string(),
+                    from[1].to_string(),
+                    to[0].to_string(),
+                    to[1].to_string(),
+                    length.to_string(),
+                    String::new(),
+                ]
+            }
+            Measurement::Area { label, points } => {
+                check_points(points)?;
+                if points.len() < 3 {
+                    return Err(AppError::invalid_argument(format!(
+                        "An area needs at least 3 points, got {}",
+                        points.len()
+                    )));
+                }
+                let [x, y] = centroid(std::slice::from_ref(points)).unwrap_or_default();
+                let outline: Vec<String> =
+                    points.iter().map(|[x, y]| format!("{} {}", x, y)).collect();
+                [
+                    "area".to_string(),
+                    label.clone().unwrap_or_default(),
+                    x.to_string(),
+                    y.to_string(),
+                    String::new(),
+                    String::new(),
+                    signed_area(points).abs().to_string(),
+                    outline.join(";"),
+                ]
+            }
+            Measurement::Label { text, position } => {
+                check_points(&[*position])?;
+                [
+                    "label".to_string(),
+                    text.clone(),
+                    position[0].to_string(),
+                    position[1].to_string(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                ]
+            }
+        };
+        let row: Vec<String> = fields.iter().map(|field| escape_field(field)).collect();
+        // Writing to a String cannot fail
+        let _ = writeln!(csv, "{}", row.join(","));
+    }
+    Ok(csv)
+}
+
+// Command to write the viewer's measurements to a CSV file at `out_path`,
+// e.g. one chosen with `save_file_dialog`, to hand off the checked dimensions
+#[tauri::command]
+pub async fn export_measurements(
+    measurements: Vec<Measurement>,
+    out_path: String,
+    settings: tauri::State<'_, Settings>,
+) -> Result<(), AppError> {
+    ensure_writable(&settings, &[&out_path])?;
+    let csv = measurements_to_csv(&measurements)?;
+    write_atomic(&out_path, csv.as_bytes()).map_err(|e| AppError::for_path(&out_path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measurements_become_escaped_csv_rows() {
+        let measurements: Vec<Measurement> = serde_json::from_value(serde_json::json!([
+            { "kind": "distance", "label": "gate, min", "from": [0.0, 0.0], "to": [3.0, 4.0] },
+            { "kind": "area", "label": null, "points": [[0.0, 0.0], [2.0, 0.0], [2.0, 2.0]] },
+            { "kind": "label", "text": "VDD \"core\"\nTOP", "position": [-1.5, 2.0] },
+        ]))
+        .unwrap();
+
+        let csv = measurements_to_csv(&measurements).unwrap();
+        assert_eq!(
+            csv,
+            concat!(
+                "kind,label,x1,y1,x2,y2,value,points\n",
+                "distance,\"gate, min\",0,0,3,4,5,\n",
+                "area,,1.3333333333333333,0.6666666666666666,,,2,0 0;2 0;2 2\n",
+                "label,\"VDD \"\"core\"\"\nTOP\",-1.5,2,,,,\n",
+            )
+        );
+
+        let line = Measurement::Area { label: None, points: vec![[0.0, 0.0], [1.0, 1.0]] };
+        assert!(measurements_to_csv(&[line]).is_err());
+        let nan = Measurement::Label { text: "X".to_string(), position: [f64::NAN, 0.0] };
+        assert!(measurements_to_csv(&[nan]).is_err());
+    }
+}
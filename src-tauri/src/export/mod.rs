@@ -0,0 +1,33 @@
+//! Writers that turn a cell into files for other tools
+
+pub mod clipboard;
+pub mod contact_sheet;
+pub mod dxf;
+mod font;
+pub mod gds;
+pub mod jobs;
+pub mod layer_map;
+pub mod measurements;
+pub mod svg;
+pub mod thumbnail;
+
+// Fill colors for layers the caller didn't assign one, cycled by layer number
+const DEFAULT_PALETTE: &[&str] = &[
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd",
+    "#8c564b", "#e377c2", "#7f7f7f", "#bcbd22", "#17becf",
+];
+
+// Key identifying a (layer, datatype) pair in caller-supplied maps, e.g. "1/0"
+pub fn layer_key_string(layer: u16, datatype: u16) -> String {
+    format!("{}/{}", layer, datatype)
+}
+
+// The (layer, datatype) pair of a "layer/datatype" key, `None` if malformed
+pub fn parse_layer_key(key: &str) -> Option<(u16, u16)> {
+    let (layer, datatype) = key.split_once('/')?;
+    Some((layer.parse().ok()?, datatype.parse().ok()?))
+}
+
+pub fn default_layer_color(layer: u16) -> &'static str {
+    DEFAULT_PALETTE[layer as usize % DEFAULT_PALETTE.len()]
+}
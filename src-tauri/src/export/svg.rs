@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use tauri::{AppHandle, State};
+
+use super::jobs::{polygon_progress, start_export_job, ExportJobs, ExportProgress};
+use super::{default_layer_color, layer_key_string};
+use crate::error::AppError;
+use crate::gds::bbox::{Bbox, BboxCalculator};
+use crate::gds::cache::ParseCache;
+use crate::gds::flatten::cell_polygons;
+use crate::gds::GdsLibrary;
+use crate::paths::write_atomic;
+use crate::read_only::ensure_writable;
+use crate::settings::Settings;
+
+pub(crate) fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Adding zero turns -0 into 0 so flipped coordinates don't print as "-0"
+fn coordinate(value: f64) -> f64 {
+    value + 0.0
+}
+
+// Render a flattened cell as an SVG document in database units. Each
+// (layer, datatype) becomes a `<g>` filled with its color from `layer_colors`
+// (keyed "layer/datatype"), falling back to a default palette. Y is negated
+// because SVG's Y axis points down. Progress is reported as polygons are
+// written; returning false from `on_progress` stops with `AppError::Cancelled`.
+pub fn cell_to_svg(
+    library: &GdsLibrary,
+    cell: &str,
+    layer_colors: &HashMap<String, String>,
+    on_progress: &mut dyn FnMut(ExportProgress) -> bool,
+) -> Result<String, AppError> {
+    let layers = cell_polygons(library, cell)?;
+    let total = layers.values().map(Vec::len).sum();
+    let mut done = 0;
+    let bbox = BboxCalculator::new(library).cell_bbox(cell)?.unwrap_or(Bbox::ZERO);
+    // A zero-size viewBox disables rendering, so keep at least one unit
+    let width = bbox.width().max(1.0);
+    let height = bbox.height().max(1.0);
+
+    let mut svg = String::new();
+    // Writing to a String cannot fail
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+        coordinate(bbox.min_x),
+        coordinate(-bbox.max_y),
+        width,
+        height
+    );
+    for ((layer, datatype), polygons) in &layers {
+        let key = layer_key_string(*layer, *datatype);
+        let color = layer_colors
+            .get(&key)
+            .map(String::as_str)
+            .unwrap_or_else(|| default_layer_color(*layer));
+        let _ = writeln!(
+            svg,
+            r#"  <g id="layer-{}-{}" fill="{}" stroke="none">"#,
+            layer,
+            datatype,
+            escape_attribute(color)
+        );
+        for polygon in polygons {
+            if !polygon_progress(done, total, on_progress) {
+                return Err(AppError::Cancelled);
+            }
+            done += 1;
+            let points: Vec<String> = polygon
+                .iter()
+                .map(|[x, y]| format!("{},{}", coordinate(*x), coordinate(-y)))
+                .collect();
+            let _ = writeln!(svg, r#"    <polygon points="{}"/>"#, points.join(" "));
+        }
+        svg.push_str("  </g>\n");
+    }
+    svg.push_str("</svg>\n");
+    if !polygon_progress(done, total, on_progress) {
+        return Err(AppError::Cancelled);
+    }
+    Ok(svg)
+}
+
+// Write the SVG of a cell to `out_path` once it is complete, replacing the
+// file atomically, so a failed or cancelled export leaves no partial file
+pub fn export_svg_file(
+    library: &GdsLibrary,
+    cell: &str,
+    out_path: &str,
+    layer_colors: &HashMap<String, String>,
+    on_progress: &mut dyn FnMut(ExportProgress) -> bool,
+) -> Result<(), AppError> {
+    let svg = cell_to_svg(library, cell, layer_colors, on_progress)?;
+    write_atomic(out_path, svg.as_bytes()).map_err(|e| AppError::for_path(out_path, e))
+}
+
+// Command to start exporting a cell, flattened, as an SVG file at `out_path`
+// in the background. Returns a job id immediately; progress arrives as
+// `export-progress` events and the outcome as an `export-complete` event.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_svg(
+    path: String,
+    cell: String,
+    out_path: String,
+    layer_colors: HashMap<String, String>,
+    app: AppHandle,
+    jobs: State<'_, ExportJobs>,
+    settings: State<'_, Settings>,
+    cache: State<'_, ParseCache>,
+) -> Result<u64, AppError> {
+    ensure_writable(&settings, &[&path, &out_path])?;
+    let cache = ParseCache::clone(&cache);
+    Ok(start_export_job(app, &jobs, move |on_progress| {
+        let library = cache.library(&path)?;
+        export_svg_file(&library, &cell, &out_path, &layer_colors, on_progress)?;
+        Ok(None)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+
+    #[test]
+    fn output_is_xml_with_one_polygon_per_boundary() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("LEAF").boundary(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 0]]).end_cell();
+        gds.begin_cell("TOP")
+            .boundary(2, 0, &[[0, 0], [50, 0], [50, 20], [0, 0]])
+            .aref("LEAF", [0, 0], 3, 1, [60, 0], [0, 10])
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+        let colors = HashMap::from([("1/0".to_string(), "#ff0000".to_string())]);
+
+        let svg = cell_to_svg(&library, "TOP", &colors, &mut |_| true).unwrap();
+        let mut reader = Reader::from_str(&svg);
+        let (mut groups, mut polygons) = (Vec::new(), 0);
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(tag) if tag.name().as_ref() == b"g" => {
+                    let fill = tag.try_get_attribute("fill").unwrap().unwrap();
+                    groups.push(String::from_utf8(fill.value.to_vec()).unwrap());
+                }
+                Event::Empty(tag) if tag.name().as_ref() == b"polygon" => polygons += 1,
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        assert_eq!(polygons, 4);
+        assert_eq!(groups, vec!["#ff0000".to_string(), default_layer_color(2).to_string()]);
+        // The bbox spans y 0..20, which flips to -20..0
+        assert!(svg.contains(r#"viewBox="0 -20 50 20""#));
+        assert!(svg.contains(r#"points="0,0 50,0 50,-20 0,0""#));
+    }
+
+    #[test]
+    fn cancelled_exports_leave_the_file_alone() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 0]])
+            .boundary(2, 0, &[[0, 0], [20, 0], [20, 20], [0, 0]])
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("top.svg");
+        let out_path = out.to_string_lossy().to_string();
+        let colors = HashMap::new();
+
+        let mut reported = Vec::new();
+        export_svg_file(&library, "TOP", &out_path, &colors, &mut |progress| {
+            reported.push(progress.polygons_done);
+            true
+        })
+        .unwrap();
+        assert_eq!(reported, [0, 2]);
+        let written = std::fs::read_to_string(&out).unwrap();
+
+        // Stopping after the last polygon still keeps the finished SVG off disk
+        let result = export_svg_file(&library, "TOP", &out_path, &colors, &mut |progress| {
+            progress.polygons_done < progress.total_polygons
+        });
+        assert!(matches!(result, Err(AppError::Cancelled)));
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), written);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
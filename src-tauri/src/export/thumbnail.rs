@@ -0,0 +1,290 @@
+use std::time::Instant;
+use tauri::{AppHandle, State};
+
+use crate::error::AppError;
+use crate::gds::bbox::BboxCalculator;
+use crate::gds::cache::ParseCache;
+use crate::gds::flatten::cell_polygons;
+use crate::gds::GdsLibrary;
+use crate::perf;
+
+use super::default_layer_color;
+use super::jobs::{polygon_progress, start_export_job, ExportJobs, ExportProgress};
+
+// Largest image produced, whatever the requested width. Keeps a very wide or
+// very tall cell from allocating an enormous buffer.
+const MAX_THUMBNAIL_PIXELS: u64 = 4 * 1024 * 1024;
+
+// Opacity of layer fills, so overlapping layers stay distinguishable
+const FILL_ALPHA: f64 = 0.7;
+
+// Parse a `#rrggbb` color
+pub(crate) fn parse_hex_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#').filter(|hex| hex.len() == 6 && hex.is_ascii())?;
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+// RGBA image with a transparent background
+pub(crate) struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub(crate) fn new(width: u32, height: u32) -> Self {
+        Canvas { width, height, pixels: vec![0; width as usize * height as usize * 4] }
+    }
+
+    pub(crate) fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub(crate) fn height(&self) -> u32 {
+        self.height
+    }
+
+    // Paint the rectangle with its top-left pixel at (`x`, `y`) in `color`,
+    // clipped to the canvas
+    pub(crate) fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: [u8; 4]) {
+        for row in y..y.saturating_add(height).min(self.height) {
+            for column in x..x.saturating_add(width).min(self.width) {
+                let index = (row as usize * self.width as usize + column as usize) * 4;
+                self.pixels[index..index + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    // Copy `image` onto the canvas with its top-left pixel at (`x`, `y`),
+    // clipped to the canvas
+    pub(crate) fn draw_image(&mut self, image: &Canvas, x: u32, y: u32) {
+        let columns = image.width.min(self.width.saturating_sub(x)) as usize;
+        for row in 0..image.height.min(self.height.saturating_sub(y)) {
+            let from = row as usize * image.width as usize * 4;
+            let to = ((y + row) as usize * self.width as usize + x as usize) * 4;
+            self.pixels[to..to + columns * 4]
+                .copy_from_slice(&image.pixels[from..from + columns * 4]);
+        }
+    }
+
+    // Blend `color` over the pixels in columns `start..end` of `row`
+    fn fill_span(&mut self, row: u32, start: u32, end: u32, color: [u8; 3]) {
+        for column in start..end {
+            let index = (row as usize * self.width as usize + column as usize) * 4;
+            let pixel = &mut self.pixels[index..index + 4];
+            for channel in 0..3 {
+                let blended = color[channel] as f64 * FILL_ALPHA
+                    + pixel[channel] as f64 * (1.0 - FILL_ALPHA);
+                pixel[channel] = blended.round() as u8;
+            }
+            let alpha = 255.0 * FILL_ALPHA + pixel[3] as f64 * (1.0 - FILL_ALPHA);
+            pixel[3] = alpha.round() as u8;
+        }
+    }
+
+    // Even-odd scanline fill of a polygon in pixel coordinates, sampling each
+    // pixel at its center
+    fn fill_polygon(&mut self, points: &[[f64; 2]], color: [u8; 3]) {
+        let n = points.len();
+        if n < 3 {
+            return;
+        }
+        let min_y = points.iter().map(|p| p[1]).fold(f64::INFINITY, f64::min);
+        let max_y = points.iter().map(|p| p[1]).fold(f64::NEG_INFINITY, f64::max);
+        let first_row = (min_y - 0.5).ceil().max(0.0) as u32;
+        let last_row = ((max_y - 0.5).floor().min(self.height as f64 - 1.0)).max(-1.0);
+        if last_row < 0.0 {
+            return;
+        }
+
+        let mut crossings = Vec::new();
+        for row in first_row..=last_row as u32 {
+            let y = row as f64 + 0.5;
+            crossings.clear();
+            for i in 0..n {
+                let [x0, y0] = points[i];
+                let [x1, y1] = points[(i + 1) % n];
+                // Half-open so a vertex on the scanline is counted once
+                if (y0 <= y) != (y1 <= y) {
+                    crossings.push(x0 + (y - y0) / (y1 - y0) * (x1 - x0));
+                }
+            }
+            crossings.sort_by(f64::total_cmp);
+            for span in crossings.chunks_exact(2) {
+                let start = (span[0] - 0.5).ceil().clamp(0.0, self.width as f64) as u32;
+                let end = ((span[1] - 0.5).floor() + 1.0).clamp(0.0, self.width as f64) as u32;
+                if start < end {
+                    self.fill_span(row, start, end, color);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn encode_png(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+        Ok(bytes)
+    }
+}
+
+// Image size for a cell of the given extent: `width_px` wide with the cell's
+// aspect ratio, shrunk to stay within the pixel budget
+fn thumbnail_size(width_px: u32, cell_width: f64, cell_height: f64) -> (u32, u32) {
+    let aspect = if cell_width > 0.0 { cell_height / cell_width } else { 1.0 };
+    let mut width = width_px as f64;
+    let mut height = (width * aspect).max(1.0);
+    let pixels = width * height;
+    if pixels > MAX_THUMBNAIL_PIXELS as f64 {
+        let shrink = (MAX_THUMBNAIL_PIXELS as f64 / pixels).sqrt();
+        width *= shrink;
+        height *= shrink;
+    }
+    (width.floor().max(1.0) as u32, height.floor().max(1.0) as u32)
+}
+
+// Rasterize the flattened boundaries of a cell into a PNG, coloring each layer
+// from the default palette. Fails for unknown cells and cells without geometry.
+pub fn render_cell_png(library: &GdsLibrary, cell: &str, width_px: u32) -> Result<Vec<u8>, AppError> {
+    render_cell_png_with_progress(library, cell, width_px, &mut |_| true)
+}
+
+// `render_cell_png`, reporting progress as polygons are filled. Returning false
+// from the callback stops with `AppError::Cancelled`, dropping the canvas.
+pub fn render_cell_png_with_progress(
+    library: &GdsLibrary,
+    cell: &str,
+    width_px: u32,
+    on_progress: &mut dyn FnMut(ExportProgress) -> bool,
+) -> Result<Vec<u8>, AppError> {
+    if width_px == 0 {
+        return Err(AppError::invalid_argument("Thumbnail width must be at least 1 pixel"));
+    }
+    let Some(canvas) = render_cell_canvas(library, cell, width_px, on_progress)? else {
+        return Err(AppError::invalid_argument(format!("Cell {} has no geometry to render", cell)));
+    };
+    canvas.encode_png().map_err(|e| AppError::io("Failed to encode thumbnail", e))
+}
+
+// The image `render_cell_png_with_progress` encodes, `None` for a cell without
+// geometry. `width_px` must be at least 1.
+pub(crate) fn render_cell_canvas(
+    library: &GdsLibrary,
+    cell: &str,
+    width_px: u32,
+    on_progress: &mut dyn FnMut(ExportProgress) -> bool,
+) -> Result<Option<Canvas>, AppError> {
+    let layers = cell_polygons(library, cell)?;
+    let bbox = BboxCalculator::new(library).cell_bbox(cell)?;
+    let Some(bbox) = bbox.filter(|_| !layers.is_empty()) else {
+        return Ok(None);
+    };
+
+    let (width, height) = thumbnail_size(width_px, bbox.width(), bbox.height());
+    let scale = if bbox.width() > 0.0 {
+        width as f64 / bbox.width()
+    } else if bbox.height() > 0.0 {
+        height as f64 / bbox.height()
+    } else {
+        1.0
+    };
+    let total = layers.values().map(Vec::len).sum();
+    let mut done = 0;
+    let mut canvas = Canvas::new(width, height);
+    for ((layer, _), polygons) in &layers {
+        let color = parse_hex_color(default_layer_color(*layer)).unwrap_or([128, 128, 128]);
+        for polygon in polygons {
+            if !polygon_progress(done, total, on_progress) {
+                return Err(AppError::Cancelled);
+            }
+            done += 1;
+            // Image rows run top to bottom, so Y is flipped
+            let points: Vec<[f64; 2]> = polygon
+                .iter()
+                .map(|[x, y]| [(x - bbox.min_x) * scale, (bbox.max_y - y) * scale])
+                .collect();
+            canvas.fill_polygon(&points, color);
+        }
+    }
+    if !polygon_progress(done, total, on_progress) {
+        return Err(AppError::Cancelled);
+    }
+    Ok(Some(canvas))
+}
+
+// Command to start rendering a cell as a PNG thumbnail `width_px` wide in the
+// background. Returns a job id immediately; progress arrives as
+// `export-progress` events and the encoded file bytes with `export-complete`.
+#[tauri::command]
+pub async fn render_thumbnail(
+    path: String,
+    cell: String,
+    width_px: u32,
+    app: AppHandle,
+    jobs: State<'_, ExportJobs>,
+    cache: State<'_, ParseCache>,
+) -> Result<u64, AppError> {
+    let started = Instant::now();
+    let cache = ParseCache::clone(&cache);
+    let perf_app = app.clone();
+    Ok(start_export_job(app, &jobs, move |on_progress| {
+        let library = cache.library(&path)?;
+        let png = render_cell_png_with_progress(&library, &cell, width_px, on_progress)?;
+        perf::report(&perf_app, "render_thumbnail", &path, started);
+        Ok(Some(png))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    fn decode(bytes: &[u8]) -> (u32, u32, Vec<u8>) {
+        let mut reader = png::Decoder::new(bytes).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        (info.width, info.height, pixels)
+    }
+
+    #[test]
+    fn thumbnail_keeps_the_cell_aspect_ratio() {
+        let mut gds = GdsBuilder::library("LIB");
+        // Lower half of a 200x100 cell is filled
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &[[0, 0], [200, 0], [200, 50], [0, 50], [0, 0]])
+            .boundary(2, 0, &[[0, 99], [1, 99], [1, 100], [0, 100], [0, 99]])
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let (width, height, pixels) = decode(&render_cell_png(&library, "TOP", 64).unwrap());
+        assert_eq!((width, height), (64, 32));
+        let alpha = |x: usize, y: usize| pixels[(y * 64 + x) * 4 + 3];
+        assert_eq!(alpha(10, 10), 0);
+        assert!(alpha(10, 25) > 0);
+
+        // Huge widths are capped by the pixel budget
+        let (width, height) = thumbnail_size(1_000_000, 200.0, 100.0);
+        assert!(width as u64 * height as u64 <= MAX_THUMBNAIL_PIXELS);
+    }
+
+    #[test]
+    fn empty_and_unknown_cells_are_errors() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("EMPTY").end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        assert!(matches!(
+            render_cell_png(&library, "EMPTY", 64),
+            Err(AppError::InvalidArgument { .. })
+        ));
+        assert_eq!(
+            render_cell_png(&library, "MISSING", 64),
+            Err(AppError::CellNotFound { cell: "MISSING".to_string() })
+        );
+    }
+}
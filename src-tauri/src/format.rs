@@ -0,0 +1,203 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::io::Read;
+
+use crate::error::AppError;
+use crate::gds::{gzip, record};
+
+// Bytes read from the start of a file when sniffing its format
+const SNIFF_LEN: u64 = 256;
+
+const OASIS_MAGIC: &[u8] = b"%SEMI-OASIS\r\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+    Gds,
+    Oasis,
+    Dxf,
+    Unknown,
+}
+
+// A file type the backend handles, by its dialog filter name and extensions:
+// whether `format` files are parsed by a reader command, and whether some
+// command writes such files
+pub struct FormatEntry {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+    pub format: Option<FileFormat>,
+    pub read: bool,
+    pub write: bool,
+}
+
+// The one list of file types, which the open dialogs, dropped files and
+// `get_supported_formats` all derive from. Add a format here along with its
+// reader or writer command.
+pub const FORMATS: &[FormatEntry] = &[
+    // `parse_gds`; `export_gds_subset`, `crop_to_region` and the other GDSII writers
+    FormatEntry {
+        name: "GDSII",
+        extensions: &["gds", "gdsii"],
+        format: Some(FileFormat::Gds),
+        read: true,
+        write: true,
+    },
+    // `parse_oasis`
+    FormatEntry {
+        name: "OASIS",
+        extensions: &["oas", "oasis"],
+        format: Some(FileFormat::Oasis),
+        read: true,
+        write: false,
+    },
+    // `parse_dxf`; `convert_gds_to_dxf`
+    FormatEntry {
+        name: "DXF",
+        extensions: &["dxf"],
+        format: Some(FileFormat::Dxf),
+        read: true,
+        write: true,
+    },
+    // `export_svg`
+    FormatEntry { name: "SVG", extensions: &["svg"], format: None, read: false, write: true },
+    // `export_measurements`, `export_layer_map`
+    FormatEntry { name: "CSV", extensions: &["csv"], format: None, read: false, write: true },
+    // `export_layer_map`
+    FormatEntry {
+        name: "KLayout layer properties",
+        extensions: &["lyp"],
+        format: None,
+        read: false,
+        write: true,
+    },
+];
+
+// Extension of gzip-compressed layouts such as `top.gds.gz`, which every
+// layout reader inflates. Dialog filters only match the last extension, so
+// they offer every `.gz` file.
+pub const GZIP_EXTENSION: &str = "gz";
+
+// Extensions of the layout formats that can be opened
+pub fn layout_extensions() -> impl Iterator<Item = &'static str> {
+    FORMATS
+        .iter()
+        .filter(|entry| entry.read && entry.format.is_some())
+        .flat_map(|entry| entry.extensions.iter().copied())
+}
+
+// One supported extension and whether files with it can be opened or exported
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct SupportedFormat {
+    pub extension: String,
+    pub read: bool,
+    pub write: bool,
+}
+
+// Every extension of `FORMATS`, then `.gz` for compressed layouts
+pub fn supported_formats() -> Vec<SupportedFormat> {
+    let format = |extension: &str, read, write| SupportedFormat {
+        extension: extension.to_string(),
+        read,
+        write,
+    };
+    let listed = FORMATS.iter().flat_map(|entry| {
+        entry.extensions.iter().map(move |extension| format(extension, entry.read, entry.write))
+    });
+    listed.chain([format(GZIP_EXTENSION, true, false)]).collect()
+}
+
+// Command to list the file extensions the backend can open and export, for
+// building dialog filters and enabling export menu items
+#[tauri::command]
+pub async fn get_supported_formats() -> Result<Vec<SupportedFormat>, AppError> {
+    Ok(supported_formats())
+}
+
+// GDSII starts with a 6-byte HEADER record holding one INT16 version
+fn is_gds(bytes: &[u8]) -> bool {
+    bytes.len() >= 6 && bytes[0..4] == [0x00, 0x06, record::HEADER, record::INT16]
+}
+
+// ASCII DXF starts with `0` / `SECTION`, optionally after `999` comment pairs
+fn is_dxf(bytes: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(bytes);
+    let mut lines = text.lines().map(str::trim);
+    while let Some(code) = lines.next() {
+        match (code, lines.next()) {
+            ("999", Some(_)) => continue,
+            ("0", Some(value)) => return value == "SECTION",
+            _ => return false,
+        }
+    }
+    false
+}
+
+// Identify a layout file from its leading bytes
+pub fn detect_format_bytes(bytes: &[u8]) -> FileFormat {
+    if bytes.starts_with(OASIS_MAGIC) {
+        FileFormat::Oasis
+    } else if is_gds(bytes) {
+        FileFormat::Gds
+    } else if is_dxf(bytes) {
+        FileFormat::Dxf
+    } else {
+        FileFormat::Unknown
+    }
+}
+
+// Command to detect a file's format from its content, regardless of extension.
+// A gzip-compressed file is identified by what it inflates to.
+#[tauri::command]
+pub async fn detect_format(path: String) -> Result<FileFormat, AppError> {
+    let for_path = |e| AppError::for_path(&path, e);
+    let mut file = std::fs::File::open(&path).map_err(for_path)?;
+    let reader: Box<dyn Read> = if gzip::is_gzip(&mut file, &path).map_err(for_path)? {
+        Box::new(gzip::decoder(file).map_err(for_path)?.0)
+    } else {
+        Box::new(file)
+    };
+    let mut bytes = Vec::new();
+    reader
+        .take(SNIFF_LEN)
+        .read_to_end(&mut bytes)
+        .map_err(|e| AppError::io("Failed to read file", e))?;
+    Ok(detect_format_bytes(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+
+    #[test]
+    fn recognizes_each_format_from_its_header() {
+        let mut gds = GdsBuilder::library("LIB");
+        assert_eq!(detect_format_bytes(&gds.finish()), FileFormat::Gds);
+        assert_eq!(detect_format_bytes(b"%SEMI-OASIS\r\n\x01\x031.0"), FileFormat::Oasis);
+        assert_eq!(detect_format_bytes(b"  0\r\nSECTION\r\n  2\r\nHEADER\r\n"), FileFormat::Dxf);
+        assert_eq!(detect_format_bytes(b"999\ncomment\n0\nSECTION\n"), FileFormat::Dxf);
+    }
+
+    #[test]
+    fn every_detected_format_can_be_opened() {
+        for format in [FileFormat::Gds, FileFormat::Oasis, FileFormat::Dxf] {
+            let entry = FORMATS.iter().find(|entry| entry.format == Some(format)).unwrap();
+            assert!(entry.read, "{:?}", format);
+        }
+        let formats = supported_formats();
+        let gds = formats.iter().find(|format| format.extension == "gds").unwrap();
+        assert!(gds.read && gds.write);
+        let svg = formats.iter().find(|format| format.extension == "svg").unwrap();
+        assert!(!svg.read && svg.write);
+        let layouts: Vec<&str> = layout_extensions().collect();
+        assert_eq!(layouts, ["gds", "gdsii", "oas", "oasis", "dxf"]);
+    }
+
+    #[test]
+    fn unrecognized_content_is_unknown() {
+        assert_eq!(detect_format_bytes(b""), FileFormat::Unknown);
+        assert_eq!(detect_format_bytes(b"hello world"), FileFormat::Unknown);
+        assert_eq!(detect_format_bytes(b"0\nLINE\n"), FileFormat::Unknown);
+        assert_eq!(detect_format_bytes(&[0x00, 0x06, 0x01, 0x02, 0, 0]), FileFormat::Unknown);
+    }
+}
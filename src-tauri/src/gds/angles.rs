@@ -0,0 +1,193 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::cache::ParseCache;
+use super::flatten::{element_outline, Flattener};
+use super::{GdsElement, GdsError, GdsLibrary};
+use crate::error::AppError;
+
+// Most non-Manhattan elements listed for one cell
+const MAX_LISTED_ELEMENTS: usize = 1000;
+
+// How far off an axis or diagonal an edge may be, in database units across
+// its length, and still count as on it. Covers the rounding of rotated and
+// magnified placements; an edge written off-axis is at least a whole
+// database unit off.
+const EDGE_TOLERANCE: f64 = 1e-3;
+
+// The edge directions of a polygon, from most to least restricted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GeometryClass {
+    // Every edge horizontal or vertical
+    Manhattan,
+    // Every edge horizontal, vertical or at 45 degrees, and at least one at 45
+    FortyFive,
+    // At least one edge at any other angle
+    AllAngle,
+}
+
+// An element of a cell whose outline isn't Manhattan in at least one of its
+// placements. `id` is its index into the cell's elements, as
+// `get_layer_elements` reports, and `class` the least restricted class of any
+// of its placements.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ClassifiedElement {
+    pub cell: String,
+    pub id: u64,
+    pub layer: u16,
+    pub datatype: u16,
+    pub class: GeometryClass,
+}
+
+// Flattened polygons of a cell counted by class. `elements` is only filled in
+// when asked for.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, JsonSchema)]
+pub struct GeometryClasses {
+    pub manhattan_polygons: u64,
+    pub forty_five_degree: u64,
+    pub all_angle_polygons: u64,
+    pub elements: Option<Vec<ClassifiedElement>>,
+}
+
+fn edge_class(from: [f64; 2], to: [f64; 2]) -> GeometryClass {
+    let (dx, dy) = ((to[0] - from[0]).abs(), (to[1] - from[1]).abs());
+    if dx <= EDGE_TOLERANCE || dy <= EDGE_TOLERANCE {
+        GeometryClass::Manhattan
+    } else if (dx - dy).abs() <= EDGE_TOLERANCE {
+        GeometryClass::FortyFive
+    } else {
+        GeometryClass::AllAngle
+    }
+}
+
+// The class of a closed outline, its closing edge included whether or not the
+// first point is repeated. Repeated points make edges of no length, which
+// count as Manhattan.
+pub fn polygon_class(points: &[[f64; 2]]) -> GeometryClass {
+    let closing = points.last().zip(points.first());
+    points
+        .windows(2)
+        .map(|pair| (pair[0], pair[1]))
+        .chain(closing.map(|(&last, &first)| (last, first)))
+        .map(|(from, to)| edge_class(from, to))
+        .max()
+        .unwrap_or(GeometryClass::Manhattan)
+}
+
+// Count the boundaries, path outlines and boxes reachable from `cell` by
+// class, each placement separately and classified as placed, so a Manhattan
+// cell placed at 30 degrees counts as all-angle. With `list_elements`, also
+// name the elements behind the non-Manhattan ones, each once, in the order
+// they are first met.
+pub fn classify_cell_geometry(
+    library: &GdsLibrary,
+    cell: &str,
+    list_elements: bool,
+) -> Result<GeometryClasses, GdsError> {
+    // The flattener hands out elements without their cell, so they are told
+    // apart by address
+    let origins: HashMap<*const GdsElement, (&str, usize)> = if list_elements {
+        let elements = library.cells.iter().flat_map(|cell| {
+            let name = cell.name.as_str();
+            cell.elements
+                .iter()
+                .enumerate()
+                .map(move |(id, element)| (element as *const GdsElement, (name, id)))
+        });
+        elements.collect()
+    } else {
+        HashMap::new()
+    };
+    let mut classes = GeometryClasses::default();
+    let mut listed: Vec<ClassifiedElement> = Vec::new();
+    let mut listed_at: HashMap<*const GdsElement, usize> = HashMap::new();
+    Flattener::new(library).visit(cell, &mut |element, transform| {
+        let Some(outline) = element_outline(element, transform) else {
+            return;
+        };
+        let class = polygon_class(&outline);
+        match class {
+            GeometryClass::Manhattan => {
+                classes.manhattan_polygons += 1;
+                return;
+            }
+            GeometryClass::FortyFive => classes.forty_five_degree += 1,
+            GeometryClass::AllAngle => classes.all_angle_polygons += 1,
+        }
+        let key = element as *const GdsElement;
+        if let Some(&at) = listed_at.get(&key) {
+            listed[at].class = listed[at].class.max(class);
+        } else if let Some(&(cell, id)) = origins.get(&key) {
+            if listed.len() < MAX_LISTED_ELEMENTS {
+                let (layer, datatype) = element.layer_key();
+                listed_at.insert(key, listed.len());
+                let cell = cell.to_string();
+                listed.push(ClassifiedElement { cell, id: id as u64, layer, datatype, class });
+            }
+        }
+    })?;
+    classes.elements = list_elements.then_some(listed);
+    Ok(classes)
+}
+
+// Command to tell whether a cell's geometry is Manhattan, 45-degree or
+// all-angle, e.g. before handing it to a tool that only takes Manhattan
+// shapes, optionally with the elements that aren't
+#[tauri::command]
+pub async fn classify_geometry(
+    path: String,
+    cell: String,
+    list_elements: Option<bool>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<GeometryClasses, AppError> {
+    let library = cache.library(&path)?;
+    Ok(classify_cell_geometry(&library, &cell, list_elements.unwrap_or(false))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn polygons_are_classified_as_placed() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("SHAPES")
+            .boundary(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]])
+            .boundary(2, 0, &[[0, 0], [20, 0], [30, 10], [0, 10], [0, 0]])
+            .boundary(3, 0, &[[0, 0], [20, 0], [25, 10], [0, 0]])
+            // A diagonal wire: its square-ended outline runs at 45 degrees
+            .path(4, 0, 0, 4, &[[0, 0], [50, 50]])
+            .text(5, 0, [0, 0], "A")
+            .end_cell();
+        gds.begin_cell("TOP")
+            .sref("SHAPES", [0, 0], None)
+            // Rotated by 90 degrees in floating point, still the same classes
+            .sref("SHAPES", [1000, 0], Some((true, 90.0, 1.0)))
+            .sref("SHAPES", [0, 1000], Some((false, 30.0, 1.0)))
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let shapes = classify_cell_geometry(&library, "SHAPES", false).unwrap();
+        assert_eq!((shapes.manhattan_polygons, shapes.forty_five_degree), (1, 2));
+        assert_eq!((shapes.all_angle_polygons, shapes.elements), (1, None));
+
+        let top = classify_cell_geometry(&library, "TOP", true).unwrap();
+        assert_eq!((top.manhattan_polygons, top.forty_five_degree), (2, 4));
+        assert_eq!(top.all_angle_polygons, 6);
+        let listed: Vec<_> = top
+            .elements
+            .unwrap()
+            .into_iter()
+            .map(|element| (element.cell, element.id, element.class))
+            .collect();
+        // Every element is all-angle once placed at 30 degrees, the square too
+        assert_eq!(
+            listed,
+            [1, 2, 3, 0].map(|id| ("SHAPES".to_string(), id, GeometryClass::AllAngle))
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::cache::ParseCache;
+use super::transform::instance_transforms;
+use super::{ArrayParams, CellReference, GdsError, GdsLibrary};
+use crate::error::AppError;
+
+// One instance of an array, placed in the parent cell. The position is in user
+// units; rotation (degrees, counterclockwise), magnification and reflection
+// about the X axis are shared by every instance of the array. `abs_angle` and
+// `abs_mag` mean the rotation or magnification hold in the top cell's frame,
+// rather than adding to those of the references above this cell.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct InstancePlacement {
+    pub cell: String,
+    pub x: f64,
+    pub y: f64,
+    pub rotation: f64,
+    pub mag: f64,
+    pub reflect: bool,
+    pub abs_angle: bool,
+    pub abs_mag: bool,
+}
+
+// Reject arrays whose instances can't all be told apart: COLROW counts of zero
+// or beyond the INT16 range (negative counts as written), a lattice vector of
+// zero length for a repeated axis, or parallel vectors for a 2D array
+fn validate_array(
+    parent: &str,
+    reference: &CellReference,
+    array: &ArrayParams,
+) -> Result<(), GdsError> {
+    let degenerate = |message: String| GdsError::DegenerateArray {
+        cell: parent.to_string(),
+        referenced: reference.cell.clone(),
+        message,
+    };
+    for (axis, count) in [("column", array.columns), ("row", array.rows)] {
+        if count == 0 || count > i16::MAX as u16 {
+            return Err(degenerate(format!("{} count {} is out of range", axis, count as i16)));
+        }
+    }
+    let delta = |point: [i32; 2]| {
+        [
+            point[0] as i64 - reference.origin[0] as i64,
+            point[1] as i64 - reference.origin[1] as i64,
+        ]
+    };
+    let (column_delta, row_delta) = (delta(array.column_point), delta(array.row_point));
+    if array.columns > 1 && column_delta == [0, 0] {
+        return Err(degenerate("column vector has zero length".to_string()));
+    }
+    if array.rows > 1 && row_delta == [0, 0] {
+        return Err(degenerate("row vector has zero length".to_string()));
+    }
+    if array.columns > 1
+        && array.rows > 1
+        && column_delta[0] * row_delta[1] == column_delta[1] * row_delta[0]
+    {
+        return Err(degenerate("column and row vectors are parallel".to_string()));
+    }
+    Ok(())
+}
+
+// Every instance of the AREFs placed directly in `cell`, array by array in
+// file order and row by row within an array. SREFs are not included.
+pub fn expand_arrays(library: &GdsLibrary, cell: &str) -> Result<Vec<InstancePlacement>, GdsError> {
+    let parent = library
+        .cells
+        .iter()
+        .find(|candidate| candidate.name == cell)
+        .ok_or_else(|| GdsError::CellNotFound { cell: cell.to_string() })?;
+    let scale = library.units.user_unit;
+
+    let mut placements = Vec::new();
+    for reference in &parent.references {
+        let Some(array) = &reference.array else {
+            continue;
+        };
+        validate_array(cell, reference, array)?;
+        placements.extend(instance_transforms(reference).map(|transform| InstancePlacement {
+            cell: reference.cell.clone(),
+            x: transform.tx * scale,
+            y: transform.ty * scale,
+            rotation: reference.strans.angle,
+            mag: reference.strans.mag,
+            reflect: reference.strans.reflect_x,
+            abs_angle: reference.strans.abs_angle,
+            abs_mag: reference.strans.abs_mag,
+        }));
+    }
+    Ok(placements)
+}
+
+// Command to list the individual instances of the arrays placed in a cell
+#[tauri::command]
+pub async fn expand_aref(
+    path: String,
+    cell: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<InstancePlacement>, AppError> {
+    Ok(expand_arrays(&*cache.library(&path)?, &cell)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    fn library_with_array(
+        columns: i16,
+        rows: i16,
+        column_point: [i32; 2],
+        row_point: [i32; 2],
+    ) -> GdsLibrary {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("UNIT").boundary(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 0]]).end_cell();
+        gds.begin_cell("TOP")
+            .sref("UNIT", [0, 0], None)
+            .aref("UNIT", [1000, 0], columns, rows, column_point, row_point)
+            .end_cell();
+        parse_library(&gds.finish()).unwrap()
+    }
+
+    #[test]
+    fn arrays_expand_row_by_row() {
+        // 3 columns 100 apart and 2 rows 200 apart; 1 nm units are 0.001 µm
+        let library = library_with_array(3, 2, [1300, 0], [1000, 400]);
+        let placements = expand_arrays(&library, "TOP").unwrap();
+
+        let positions: Vec<[f64; 2]> = placements.iter().map(|p| [p.x, p.y]).collect();
+        let expected = [[1.0, 0.0], [1.1, 0.0], [1.2, 0.0], [1.0, 0.2], [1.1, 0.2], [1.2, 0.2]];
+        assert_eq!(positions.len(), expected.len());
+        for (position, expected) in positions.iter().zip(expected) {
+            assert!((position[0] - expected[0]).abs() < 1e-12);
+            assert!((position[1] - expected[1]).abs() < 1e-12);
+        }
+        assert!(placements.iter().all(|p| p.cell == "UNIT" && p.mag == 1.0 && !p.reflect));
+    }
+
+    #[test]
+    fn degenerate_arrays_are_rejected() {
+        let degenerate = |library: GdsLibrary| {
+            matches!(expand_arrays(&library, "TOP"), Err(GdsError::DegenerateArray { .. }))
+        };
+        assert!(degenerate(library_with_array(0, 1, [1000, 0], [1000, 0])));
+        assert!(degenerate(library_with_array(-2, 1, [1200, 0], [1000, 0])));
+        assert!(degenerate(library_with_array(3, 1, [1000, 0], [1000, 0])));
+        assert!(degenerate(library_with_array(2, 2, [1200, 0], [1400, 0])));
+        // A single row may leave the unused row vector at the origin
+        let single_row = library_with_array(2, 1, [1200, 0], [1000, 0]);
+        assert_eq!(expand_arrays(&single_row, "TOP").unwrap().len(), 2);
+    }
+}
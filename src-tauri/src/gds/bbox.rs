@@ -0,0 +1,253 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::transform::{array_steps, Transform};
+use super::cache::ParseCache;
+use super::flatten::{fold_cells, max_hierarchy_depth};
+use crate::error::AppError;
+use super::{CellReference, GdsCell, GdsElement, GdsError, GdsLibrary};
+
+// Axis-aligned bounding box in database units
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+pub struct Bbox {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl Bbox {
+    // Degenerate zero-area box at the origin, used for cells without geometry
+    pub const ZERO: Bbox = Bbox { min_x: 0.0, min_y: 0.0, max_x: 0.0, max_y: 0.0 };
+
+    pub fn from_point(point: [f64; 2]) -> Self {
+        Bbox { min_x: point[0], min_y: point[1], max_x: point[0], max_y: point[1] }
+    }
+
+    pub fn include_point(&mut self, point: [f64; 2]) {
+        self.min_x = self.min_x.min(point[0]);
+        self.min_y = self.min_y.min(point[1]);
+        self.max_x = self.max_x.max(point[0]);
+        self.max_y = self.max_y.max(point[1]);
+    }
+
+    pub fn union(&self, other: &Bbox) -> Bbox {
+        Bbox {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+
+    pub fn expanded(&self, margin: f64) -> Bbox {
+        Bbox {
+            min_x: self.min_x - margin,
+            min_y: self.min_y - margin,
+            max_x: self.max_x + margin,
+            max_y: self.max_y + margin,
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max_y - self.min_y
+    }
+
+    // Box around the four transformed corners. Exact for Manhattan transforms,
+    // a conservative bound for arbitrary rotations.
+    pub fn transformed(&self, transform: &Transform) -> Bbox {
+        let mut result = Bbox::from_point(transform.apply(self.min_x, self.min_y));
+        result.include_point(transform.apply(self.max_x, self.min_y));
+        result.include_point(transform.apply(self.min_x, self.max_y));
+        result.include_point(transform.apply(self.max_x, self.max_y));
+        result
+    }
+}
+
+// Grow an optional box to include another
+pub fn merge(target: &mut Option<Bbox>, other: Bbox) {
+    *target = Some(match target {
+        Some(existing) => existing.union(&other),
+        None => other,
+    });
+}
+
+fn points_bbox(points: &[[i32; 2]]) -> Option<Bbox> {
+    let (first, rest) = points.split_first()?;
+    let mut bbox = Bbox::from_point([first[0] as f64, first[1] as f64]);
+    for point in rest {
+        bbox.include_point([point[0] as f64, point[1] as f64]);
+    }
+    Some(bbox)
+}
+
+// Extent of a single element in its cell's coordinates. Paths are padded by half
+// their width on all sides, which covers flush, round and square ends, or by
+// their custom extension if that reaches further.
+pub fn element_bbox(element: &GdsElement) -> Option<Bbox> {
+    match element {
+        GdsElement::Boundary { points, .. }
+        | GdsElement::Box { points, .. }
+        | GdsElement::Node { points, .. } => points_bbox(points),
+        GdsElement::Rect { rect, .. } => Some(Bbox {
+            min_x: rect.x as f64,
+            min_y: rect.y as f64,
+            max_x: rect.x as f64 + rect.w as f64,
+            max_y: rect.y as f64 + rect.h as f64,
+        }),
+        GdsElement::Path { points, width, pathtype, begin_extension, end_extension, .. } => {
+            let mut margin = width.unsigned_abs() as f64 / 2.0;
+            if *pathtype == 4 {
+                margin = margin.max((*begin_extension).max(*end_extension) as f64);
+            }
+            points_bbox(points).map(|bbox| bbox.expanded(margin))
+        }
+        GdsElement::Text { position, .. } => {
+            Some(Bbox::from_point([position[0] as f64, position[1] as f64]))
+        }
+    }
+}
+
+// Extent of a reference given the referenced cell's own extent. For arrays only
+// the four corner instances can contribute to the outer extent.
+fn reference_bbox(reference: &CellReference, child: &Bbox) -> Bbox {
+    let placement = Transform::from_placement(&reference.strans, reference.origin);
+    let placed = child.transformed(&placement);
+    match &reference.array {
+        None => placed,
+        Some(array) => {
+            let (column_step, row_step) = array_steps(reference.origin, array);
+            let last_column = array.columns.max(1) as f64 - 1.0;
+            let last_row = array.rows.max(1) as f64 - 1.0;
+            let mut bbox = placed;
+            for (i, j) in [(last_column, 0.0), (0.0, last_row), (last_column, last_row)] {
+                let offset = Transform::translation(
+                    i * column_step[0] + j * row_step[0],
+                    i * column_step[1] + j * row_step[1],
+                );
+                bbox = bbox.union(&placed.transformed(&offset));
+            }
+            bbox
+        }
+    }
+}
+
+// Computes flattened cell extents, memoizing each cell so shared sub-cells are
+// only visited once
+pub struct BboxCalculator<'a> {
+    cells: HashMap<&'a str, &'a GdsCell>,
+    cache: HashMap<&'a str, Option<Bbox>>,
+    max_depth: usize,
+}
+
+// Extent of a cell's own elements, without its references
+fn own_bbox(cell: &GdsCell) -> Option<Bbox> {
+    let mut bbox = None;
+    for element_bbox in cell.elements.iter().filter_map(element_bbox) {
+        merge(&mut bbox, element_bbox);
+    }
+    bbox
+}
+
+impl<'a> BboxCalculator<'a> {
+    pub fn new(library: &'a GdsLibrary) -> Self {
+        BboxCalculator {
+            cells: library.cells.iter().map(|cell| (cell.name.as_str(), cell)).collect(),
+            cache: HashMap::new(),
+            max_depth: max_hierarchy_depth(),
+        }
+    }
+
+    // Flattened extent of a cell, or `None` if it has no geometry. References to
+    // cells missing from the library are ignored. Nesting deeper than the
+    // `max_hierarchy_depth` setting fails, and so does a reference cycle.
+    pub fn cell_bbox(&mut self, name: &str) -> Result<Option<Bbox>, GdsError> {
+        let Some(&cell) = self.cells.get(name) else {
+            return Ok(None);
+        };
+        let add = |bbox: &mut Option<Bbox>, reference: &CellReference, child: &Option<Bbox>| {
+            if let Some(child) = child {
+                merge(bbox, reference_bbox(reference, child));
+            }
+        };
+        fold_cells(&self.cells, &mut self.cache, cell, self.max_depth, own_bbox, add)
+    }
+}
+
+// Command to compute the flattened bounding box of a cell in database units.
+// Returns `None` for unknown cells and a zero-area box at the origin for cells
+// without geometry.
+#[tauri::command]
+pub async fn get_cell_bbox(
+    path: String,
+    cell: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Option<Bbox>, AppError> {
+    let library = cache.library(&path)?;
+    if library.cell(&cell).is_none() {
+        return Ok(None);
+    }
+    let bbox = BboxCalculator::new(&library).cell_bbox(&cell)?;
+    Ok(Some(bbox.unwrap_or(Bbox::ZERO)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::flatten::MAX_HIERARCHY_DEPTH;
+    use crate::gds::parse_library;
+
+    const SQUARE: [[i32; 2]; 5] = [[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]];
+
+    #[test]
+    fn nested_and_arrayed_references_expand_the_bbox() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("LEAF").boundary(1, 0, &SQUARE).end_cell();
+        gds.begin_cell("ROW").aref("LEAF", [0, 0], 4, 2, [80, 0], [0, 40]).end_cell();
+        gds.begin_cell("TOP").sref("ROW", [1000, 0], Some((false, 90.0, 1.0))).end_cell();
+        gds.begin_cell("EMPTY").end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+        let mut calculator = BboxCalculator::new(&library);
+
+        // 4 columns at 20 pitch and 2 rows at 20 pitch of a 10x10 square
+        assert_eq!(
+            calculator.cell_bbox("ROW").unwrap(),
+            Some(Bbox { min_x: 0.0, min_y: 0.0, max_x: 70.0, max_y: 30.0 })
+        );
+        // Rotated by 90 degrees and moved
+        assert_eq!(
+            calculator.cell_bbox("TOP").unwrap(),
+            Some(Bbox { min_x: 970.0, min_y: 0.0, max_x: 1000.0, max_y: 70.0 })
+        );
+        assert_eq!(calculator.cell_bbox("EMPTY").unwrap(), None);
+    }
+
+    #[test]
+    fn cyclic_and_too_deep_references_are_reported() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("A").sref("B", [0, 0], None).end_cell();
+        gds.begin_cell("B").sref("A", [0, 0], None).end_cell();
+        // A chain far deeper than any real design
+        for level in 0..1000 {
+            gds.begin_cell(&format!("D{}", level))
+                .sref(&format!("D{}", level + 1), [0, 0], None)
+                .end_cell();
+        }
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let mut calculator = BboxCalculator::new(&library);
+        assert!(matches!(calculator.cell_bbox("A"), Err(GdsError::CyclicReference { .. })));
+        assert!(matches!(
+            calculator.cell_bbox("D0"),
+            Err(GdsError::HierarchyTooDeep { max_depth: MAX_HIERARCHY_DEPTH, .. })
+        ));
+        // The failed walks leave no stale path behind
+        assert_eq!(calculator.cell_bbox("D999"), Ok(None));
+    }
+}
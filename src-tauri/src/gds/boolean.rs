@@ -0,0 +1,253 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::bbox::BboxCalculator;
+use super::cache::ParseCache;
+use super::flatten::{cell_polygons, check_polygon_count, layer_polygons};
+use super::units::{CoordConvention, CoordMapping};
+use super::{GdsError, GdsLibrary};
+use crate::error::AppError;
+use crate::geometry::boolean::{boolean, BooleanOp};
+use crate::geometry::signed_area;
+
+// Combine the flattened geometry of two (layer, datatype) pairs of `cell`,
+// reported through `mapping`: `and` keeps what is on both, `or` what is on either, `not` what is
+// on A but not B, and `xor` what is on exactly one. Overlapping shapes within a
+// layer merge first. The result uses the ring convention of the boolean engine:
+// closed counter-clockwise outlines, with each hole as a separate clockwise ring.
+pub fn layer_boolean(
+    library: &GdsLibrary,
+    cell: &str,
+    a: (u16, u16),
+    b: (u16, u16),
+    op: BooleanOp,
+    mapping: &CoordMapping,
+) -> Result<Vec<Vec<[f64; 2]>>, GdsError> {
+    let polygons_a = layer_polygons(library, cell, a.0, a.1, 1.0)?;
+    let polygons_b = layer_polygons(library, cell, b.0, b.1, 1.0)?;
+    let rings = boolean(&polygons_a, &polygons_b, op);
+    Ok(rings.iter().map(|ring| mapping.ring(ring)).collect())
+}
+
+// Merge the flattened geometry of one (layer, datatype) pair of `cell` into
+// non-overlapping outlines, reported through `mapping`. Touching and
+// overlapping shapes become one outline; rings follow the boolean engine
+// convention described at `layer_boolean`.
+pub fn layer_union(
+    library: &GdsLibrary,
+    cell: &str,
+    layer: (u16, u16),
+    mapping: &CoordMapping,
+) -> Result<Vec<Vec<[f64; 2]>>, GdsError> {
+    let polygons = layer_polygons(library, cell, layer.0, layer.1, 1.0)?;
+    let rings = boolean(&polygons, &[], BooleanOp::Or);
+    Ok(rings.iter().map(|ring| mapping.ring(ring)).collect())
+}
+
+// Area covered by one (layer, datatype) pair of a cell, in square user units,
+// and that area as a fraction of the cell's bounding box
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct LayerCoverage {
+    pub layer: u16,
+    pub datatype: u16,
+    pub area: f64,
+    pub fraction_of_bbox: f64,
+}
+
+// Coverage of every layer of `cell`, ordered by layer and datatype. Each layer
+// is merged as in `layer_union` first, so overlapping shapes count once. Areas
+// are summed in f64 square database units, which stays exact far beyond the
+// size of any chip. `fraction_of_bbox` is 0 for a cell without area.
+pub fn layer_coverage(library: &GdsLibrary, cell: &str) -> Result<Vec<LayerCoverage>, GdsError> {
+    let layers = cell_polygons(library, cell)?;
+    let bbox_area = BboxCalculator::new(library)
+        .cell_bbox(cell)?
+        .map_or(0.0, |bbox| (bbox.max_x - bbox.min_x) * (bbox.max_y - bbox.min_y));
+    let square_user_unit = library.units.user_unit * library.units.user_unit;
+    Ok(layers
+        .into_iter()
+        .map(|((layer, datatype), polygons)| {
+            let merged = boolean(&polygons, &[], BooleanOp::Or);
+            let area: f64 = merged.iter().map(|ring| signed_area(ring)).sum();
+            LayerCoverage {
+                layer,
+                datatype,
+                area: area * square_user_unit,
+                fraction_of_bbox: if bbox_area > 0.0 { area / bbox_area } else { 0.0 },
+            }
+        })
+        .collect())
+}
+
+// Command to compute a boolean between two layers of a cell. Layers are given as
+// [layer, datatype] pairs; the result is in user units unless `coord_convention`
+// asks otherwise. More than `max_polygons` rings fails with `TooLarge`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn boolean_layers(
+    path: String,
+    cell: String,
+    layer_a: (u16, u16),
+    layer_b: (u16, u16),
+    op: BooleanOp,
+    coord_convention: Option<CoordConvention>,
+    max_polygons: Option<usize>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<Vec<[f64; 2]>>, AppError> {
+    let library = cache.library(&path)?;
+    let mapping = CoordMapping::new(coord_convention.as_ref(), &library.units);
+    let rings = layer_boolean(&library, &cell, layer_a, layer_b, op, &mapping)?;
+    check_polygon_count(rings.len(), max_polygons)?;
+    Ok(rings)
+}
+
+// Command to merge the shapes on one layer of a cell into outlines, with holes
+// as separate clockwise rings. The result is in user units unless
+// `coord_convention` asks otherwise. More than `max_polygons` rings fails with
+// `TooLarge`.
+#[tauri::command]
+pub async fn union_layer(
+    path: String,
+    cell: String,
+    layer: u16,
+    datatype: u16,
+    coord_convention: Option<CoordConvention>,
+    max_polygons: Option<usize>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<Vec<[f64; 2]>>, AppError> {
+    let library = cache.library(&path)?;
+    let mapping = CoordMapping::new(coord_convention.as_ref(), &library.units);
+    let rings = layer_union(&library, &cell, (layer, datatype), &mapping)?;
+    check_polygon_count(rings.len(), max_polygons)?;
+    Ok(rings)
+}
+
+// Command to get the area each layer of a cell covers, for density checks
+#[tauri::command]
+pub async fn get_layer_coverage(
+    path: String,
+    cell: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<LayerCoverage>, AppError> {
+    Ok(layer_coverage(&*cache.library(&path)?, &cell)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+    use crate::geometry::signed_area;
+
+    const METAL: (u16, u16) = (1, 0);
+    const VIA: (u16, u16) = (2, 0);
+
+    fn square(x: i32, y: i32, size: i32) -> [[i32; 2]; 5] {
+        [[x, y], [x + size, y], [x + size, y + size], [x, y + size], [x, y]]
+    }
+
+    fn area(rings: &[Vec<[f64; 2]>]) -> f64 {
+        rings.iter().map(|ring| signed_area(ring)).sum()
+    }
+
+    #[test]
+    fn overlapping_squares_on_two_layers() {
+        // 1000 x 1000 nm squares overlapping by half; 1 nm units are 0.001 µm,
+        // so each square is 1 µm² and the overlap 0.5 µm²
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &square(0, 0, 1000))
+            .boundary(2, 0, &square(500, 0, 1000))
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let user_units = CoordMapping::new(None, &library.units);
+        let result = |op| layer_boolean(&library, "TOP", METAL, VIA, op, &user_units).unwrap();
+        let and = result(BooleanOp::And);
+        assert_eq!(and.len(), 1);
+        assert!((area(&and) - 0.5).abs() < 1e-9);
+        assert_eq!(and[0][0], [0.5, 0.0]);
+        let or = result(BooleanOp::Or);
+        assert_eq!(or.len(), 1);
+        assert!((area(&or) - 1.5).abs() < 1e-9);
+        let not = result(BooleanOp::Not);
+        assert_eq!(not.len(), 1);
+        assert!((area(&not) - 0.5).abs() < 1e-9);
+        assert!(not[0].iter().all(|p| p[0] <= 0.5));
+        let xor = result(BooleanOp::Xor);
+        assert_eq!(xor.len(), 2);
+        assert!((area(&xor) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn enclosed_shapes_leave_holes() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &square(0, 0, 3000))
+            .boundary(2, 0, &square(1000, 1000, 1000))
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let user_units = CoordMapping::new(None, &library.units);
+        let rings =
+            layer_boolean(&library, "TOP", METAL, VIA, BooleanOp::Not, &user_units).unwrap();
+        let mut areas: Vec<f64> = rings.iter().map(|ring| signed_area(ring)).collect();
+        areas.sort_by(f64::total_cmp);
+        assert_eq!(areas.len(), 2);
+        assert!((areas[0] + 1.0).abs() < 1e-9 && (areas[1] - 9.0).abs() < 1e-9);
+
+        assert!(matches!(
+            layer_boolean(&library, "MISSING", METAL, VIA, BooleanOp::And, &user_units),
+            Err(GdsError::CellNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn touching_squares_merge_into_outlines_with_holes() {
+        let db_units = CoordMapping { scale: 1.0, flip_y: false };
+        // A 3 x 3 grid of abutting squares, and next to it the same grid
+        // without its middle square
+        let mut gds = GdsBuilder::library("LIB");
+        let top = gds.begin_cell("TOP");
+        for (row, column) in (0..3).flat_map(|row| (0..3).map(move |column| (row, column))) {
+            top.boundary(1, 0, &square(column * 100, row * 100, 100));
+            if (row, column) != (1, 1) {
+                top.boundary(2, 0, &square(column * 100, row * 100, 100));
+            }
+        }
+        top.end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let grid = layer_union(&library, "TOP", METAL, &db_units).unwrap();
+        assert_eq!(
+            grid,
+            vec![vec![[0.0, 0.0], [300.0, 0.0], [300.0, 300.0], [0.0, 300.0], [0.0, 0.0]]]
+        );
+
+        let mut frame = layer_union(&library, "TOP", VIA, &db_units).unwrap();
+        frame.sort_by(|a, b| signed_area(b).total_cmp(&signed_area(a)));
+        assert_eq!(frame.len(), 2);
+        assert_eq!(signed_area(&frame[0]), 90_000.0);
+        // The hole is clockwise
+        assert_eq!(signed_area(&frame[1]), -10_000.0);
+    }
+
+    #[test]
+    fn coverage_counts_overlaps_once() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &square(0, 0, 1000))
+            .boundary(1, 0, &square(500, 0, 1000))
+            .boundary(2, 0, &square(0, 0, 2000))
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let coverage = layer_coverage(&library, "TOP").unwrap();
+        assert_eq!(coverage.len(), 2);
+        // 1.5 µm² of the 4 µm² bounding box, not the 2 µm² drawn
+        assert_eq!((coverage[0].layer, coverage[0].datatype), METAL);
+        assert!((coverage[0].area - 1.5).abs() < 1e-9);
+        assert!((coverage[0].fraction_of_bbox - 0.375).abs() < 1e-12);
+        assert!((coverage[1].fraction_of_bbox - 1.0).abs() < 1e-12);
+    }
+}
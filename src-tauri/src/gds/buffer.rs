@@ -0,0 +1,103 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::cache::ParseCache;
+use super::flatten::output_layer_polygons;
+use super::units::CoordConvention;
+use super::GdsLibrary;
+use crate::error::AppError;
+
+// The polygons of one layer packed back to back for a GPU buffer. `vertices`
+// holds x0, y0, x1, y1, ... of every ring in turn. Ring `i` is made of points
+// `ring_offsets[i]` up to `ring_offsets[i + 1]`, counted in points rather than
+// numbers; the last offset is the total point count, so there is always one
+// more offset than rings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, JsonSchema)]
+pub struct LayerBuffer {
+    pub vertices: Vec<f64>,
+    pub ring_offsets: Vec<u32>,
+}
+
+// The polygons `get_layer_polygons` returns without `kinds` or simplification,
+// in the same order, as a `LayerBuffer`. Fails with `TooLarge` when the points
+// don't fit the 32-bit offsets.
+pub fn layer_buffer(
+    library: &GdsLibrary,
+    cell: &str,
+    layer: u16,
+    datatype: u16,
+    coord_convention: Option<&CoordConvention>,
+) -> Result<LayerBuffer, AppError> {
+    let polygons =
+        output_layer_polygons(library, cell, layer, datatype, None, None, coord_convention, None)?;
+    let points: usize = polygons.iter().map(|polygon| polygon.points.len()).sum();
+    let limit = u32::MAX as usize;
+    if points > limit {
+        return Err(AppError::TooLarge { count: points, limit });
+    }
+    let mut buffer = LayerBuffer {
+        vertices: Vec::with_capacity(points * 2),
+        ring_offsets: Vec::with_capacity(polygons.len() + 1),
+    };
+    buffer.ring_offsets.push(0);
+    for polygon in &polygons {
+        buffer.vertices.extend(polygon.points.iter().flatten());
+        buffer.ring_offsets.push((buffer.vertices.len() / 2) as u32);
+    }
+    Ok(buffer)
+}
+
+// Command to get the flattened polygons of one layer of a cell as one flat
+// coordinate array, so the frontend can upload it as a typed array without an
+// object per polygon. Coordinates are in user units unless `coord_convention`
+// asks otherwise.
+#[tauri::command]
+pub async fn get_layer_buffer(
+    path: String,
+    cell: String,
+    layer: u16,
+    datatype: u16,
+    coord_convention: Option<CoordConvention>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<LayerBuffer, AppError> {
+    let library = cache.library(&path)?;
+    layer_buffer(&library, &cell, layer, datatype, coord_convention.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::flatten::layer_polygons;
+    use crate::gds::parse_library;
+    use crate::gds::units::LengthUnit;
+
+    #[test]
+    fn buffers_pack_the_rings_of_get_layer_polygons() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("LEAF").boundary(1, 0, &[[0, 0], [10, 0], [0, 10], [0, 0]]).end_cell();
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &[[0, 0], [40, 0], [40, 20], [0, 20], [0, 0]])
+            .sref("LEAF", [100, 0], None)
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+        let convention = CoordConvention { unit: LengthUnit::Db, flip_y: false };
+
+        let buffer = layer_buffer(&library, "TOP", 1, 0, Some(&convention)).unwrap();
+        let rings = layer_polygons(&library, "TOP", 1, 0, 1.0).unwrap();
+        assert_eq!(buffer.ring_offsets.len(), rings.len() + 1);
+        for (i, ring) in rings.iter().enumerate() {
+            let offsets = &buffer.ring_offsets;
+            let (start, end) = (offsets[i] as usize * 2, offsets[i + 1] as usize * 2);
+            let points: Vec<[f64; 2]> = buffer.vertices[start..end]
+                .chunks_exact(2)
+                .map(|point| [point[0], point[1]])
+                .collect();
+            assert_eq!(&points, ring);
+        }
+        assert_eq!(*buffer.ring_offsets.last().unwrap() as usize * 2, buffer.vertices.len());
+
+        let empty = layer_buffer(&library, "TOP", 9, 0, None).unwrap();
+        assert_eq!(empty, LayerBuffer { vertices: Vec::new(), ring_offsets: vec![0] });
+    }
+}
@@ -0,0 +1,333 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use super::handles::FileHandles;
+use super::incremental::{parse_reusing, CellIndex};
+use super::mmap::{read_all, FileBytes};
+use super::parser::ParseMode;
+use super::{catch_reader_panic, GdsError, GdsLibrary};
+use crate::error::AppError;
+use crate::sync::lock;
+
+// What the cache holds, for diagnostics
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CacheStats {
+    pub max_entries: usize,
+    pub files: Vec<CachedFile>,
+}
+
+// A cached library: its file, the mode it was parsed in and its cell count
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CachedFile {
+    pub path: PathBuf,
+    pub mode: ParseMode,
+    pub cells: usize,
+}
+
+// Parsed libraries kept in memory when no other limit is configured
+pub const DEFAULT_MAX_ENTRIES: usize = 8;
+
+struct CacheEntry {
+    modified: SystemTime,
+    mode: ParseMode,
+    library: Arc<GdsLibrary>,
+    // Index of the stream `library` was parsed from, for re-parsing only what
+    // changed when the file is modified
+    cells: Option<Arc<CellIndex>>,
+    // Value of the access counter when the entry was last used
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<PathBuf, CacheEntry>,
+    // The library each entry replaced when its file was last re-read, kept while
+    // the entry is cached so reloads can be diffed
+    previous: HashMap<PathBuf, (Arc<GdsLibrary>, Option<Arc<CellIndex>>)>,
+    access_counter: u64,
+}
+
+impl CacheState {
+    // Drop the entry for `key`, remembering its library as the previous one
+    fn retire(&mut self, key: &Path) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.previous.insert(key.to_path_buf(), (entry.library, entry.cells));
+        }
+    }
+}
+
+// A library loaded by `ParseCache::get_or_reparse`, with the index of its
+// stream if it can be re-parsed from
+pub type Reparsed = (GdsLibrary, Option<CellIndex>);
+
+// In-memory cache of parsed libraries keyed by canonical path. An entry is used
+// only while the file's modification time is unchanged. When more than
+// `max_entries` libraries are cached, the least recently used one is dropped.
+// Files are read through `files`, which keeps as many of them open, and a
+// file's handle is closed along with its entry.
+#[derive(Clone)]
+pub struct ParseCache {
+    state: Arc<Mutex<CacheState>>,
+    files: FileHandles,
+    max_entries: usize,
+}
+
+impl Default for ParseCache {
+    fn default() -> Self {
+        ParseCache::with_max_entries(DEFAULT_MAX_ENTRIES)
+    }
+}
+
+pub(crate) fn cache_key(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl ParseCache {
+    pub fn with_max_entries(max_entries: usize) -> Self {
+        ParseCache {
+            state: Arc::default(),
+            files: FileHandles::with_max_open(max_entries),
+            max_entries: max_entries.max(1),
+        }
+    }
+
+    // The open files shared by everything reading through the cache
+    pub fn files(&self) -> &FileHandles {
+        &self.files
+    }
+
+    // The cached library for `path` if the file is unchanged and was parsed in
+    // a mode covering `mode`, otherwise the result of `load`, which is cached
+    // on success. A hierarchy-only entry is replaced by a full parse without
+    // becoming the file's `previous` library, as the file didn't change. The
+    // lock isn't held while loading, so other files can be served meanwhile.
+    pub fn get_or_load<F>(
+        &self,
+        path: &str,
+        mode: ParseMode,
+        load: F,
+    ) -> Result<Arc<GdsLibrary>, AppError>
+    where
+        F: FnOnce(&str) -> Result<GdsLibrary, GdsError>,
+    {
+        self.get_or_reparse(path, mode, |path, _| Ok((load(path)?, None)))
+    }
+
+    // `get_or_load` with a `load` that also gets the file's previous library
+    // and the index of its stream, when both are known, to re-parse only what
+    // changed since
+    pub fn get_or_reparse<F>(
+        &self,
+        path: &str,
+        mode: ParseMode,
+        load: F,
+    ) -> Result<Arc<GdsLibrary>, AppError>
+    where
+        F: FnOnce(&str, Option<(&GdsLibrary, &CellIndex)>) -> Result<Reparsed, GdsError>,
+    {
+        let key = cache_key(Path::new(path));
+        // Taken before loading, so a file modified mid-parse is re-read next time
+        let modified = std::fs::metadata(&key)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|e| AppError::for_path(path, e))?;
+        let previous = {
+            let mut state = lock(&self.state, "Parse cache");
+            state.access_counter += 1;
+            let counter = state.access_counter;
+            if let Some(entry) = state.entries.get_mut(&key) {
+                if entry.modified == modified && entry.mode.covers(mode) {
+                    entry.last_used = counter;
+                    return Ok(Arc::clone(&entry.library));
+                }
+                if entry.modified == modified {
+                    state.entries.remove(&key);
+                } else {
+                    state.retire(&key);
+                }
+            }
+            state.previous.get(&key).and_then(|(library, cells)| {
+                Some((Arc::clone(library), Arc::clone(cells.as_ref()?)))
+            })
+        };
+
+        let (library, cells) =
+            load(path, previous.as_ref().map(|(library, cells)| (&**library, &**cells)))?;
+        let library = Arc::new(library);
+        let cells = cells.map(Arc::new);
+        let mut state = lock(&self.state, "Parse cache");
+        state.access_counter += 1;
+        let last_used = state.access_counter;
+        state.entries.insert(
+            key,
+            CacheEntry { modified, mode, library: Arc::clone(&library), cells, last_used },
+        );
+        while state.entries.len() > self.max_entries {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(path, _)| path.clone());
+            if let Some(oldest) = oldest {
+                state.entries.remove(&oldest);
+                self.files.close(&oldest);
+            }
+        }
+        let CacheState { entries, previous, .. } = &mut *state;
+        previous.retain(|path, _| entries.contains_key(path));
+        Ok(library)
+    }
+
+    // Cached GDSII library for `path` with all its geometry, parsing the file
+    // (or what changed in it) if needed
+    pub fn library(&self, path: &str) -> Result<Arc<GdsLibrary>, AppError> {
+        self.get_or_reparse(path, ParseMode::Full, |path, previous| {
+            let bytes = self.files.read(path, read_all)?;
+            catch_reader_panic(|| parse_reusing(&bytes, previous, &mut |_| true, &mut |_| {}))
+        })
+    }
+
+    // The raw contents of `path`, for commands that scan the stream rather than
+    // the parsed library. A missing file fails with `NotFound`.
+    pub fn bytes(&self, path: &str) -> Result<Arc<FileBytes>, AppError> {
+        self.files.read(path, read_all).map_err(|e| match std::fs::metadata(path) {
+            Err(missing) => AppError::for_path(path, missing),
+            Ok(_) => e.into(),
+        })
+    }
+
+    // Drop the entry for a file and close its handle, e.g. after it changed on
+    // disk. Its library is kept as the file's `previous` one until the file is
+    // loaded again (or any other file is loaded first).
+    pub fn invalidate(&self, path: &Path) {
+        lock(&self.state, "Parse cache").retire(&cache_key(path));
+        self.files.close(path);
+    }
+
+    // The library the cached entry for `path` replaced when the file was last
+    // re-read, if it is still known
+    pub fn previous(&self, path: &Path) -> Option<Arc<GdsLibrary>> {
+        let state = lock(&self.state, "Parse cache");
+        state.previous.get(&cache_key(path)).map(|(library, _)| Arc::clone(library))
+    }
+
+    // Whether a library for `path` is cached, whether or not it is still current
+    pub fn contains(&self, path: &Path) -> bool {
+        lock(&self.state, "Parse cache").entries.contains_key(&cache_key(path))
+    }
+
+    // The cached libraries, by path
+    pub fn stats(&self) -> CacheStats {
+        let state = lock(&self.state, "Parse cache");
+        let mut files: Vec<CachedFile> = state
+            .entries
+            .iter()
+            .map(|(path, entry)| CachedFile {
+                path: path.clone(),
+                mode: entry.mode,
+                cells: entry.library.cells.len(),
+            })
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        CacheStats { max_entries: self.max_entries, files }
+    }
+
+    pub fn clear(&self) {
+        let mut state = lock(&self.state, "Parse cache");
+        state.entries.clear();
+        state.previous.clear();
+        self.files.clear();
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        lock(&self.state, "Parse cache").entries.len()
+    }
+}
+
+// Command to drop every cached library, e.g. under memory pressure
+#[tauri::command]
+pub async fn clear_parse_cache(cache: tauri::State<'_, ParseCache>) -> Result<(), AppError> {
+    cache.clear();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::read_library;
+    use std::cell::Cell;
+
+    fn write_library(dir: &Path, name: &str) -> String {
+        let mut gds = GdsBuilder::library(name);
+        gds.begin_cell("TOP").end_cell();
+        let path = dir.join(format!("{}.gds", name));
+        std::fs::write(&path, gds.finish()).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn unchanged_files_are_served_from_the_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_library(dir.path(), "A");
+        let cache = ParseCache::default();
+        let loads = Cell::new(0);
+        let load = |path: &str| {
+            loads.set(loads.get() + 1);
+            read_library(path)
+        };
+
+        let first = cache.get_or_load(&path, ParseMode::Full, load).unwrap();
+        let second = cache.get_or_load(&path, ParseMode::Full, load).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(loads.get(), 1);
+
+        assert!(cache.contains(Path::new(&path)));
+        cache.invalidate(Path::new(&path));
+        assert!(!cache.contains(Path::new(&path)));
+        let reloaded = cache.get_or_load(&path, ParseMode::Full, load).unwrap();
+        assert_eq!(loads.get(), 2);
+        // The dropped library is kept for diffing against the reload
+        assert!(Arc::ptr_eq(&cache.previous(Path::new(&path)).unwrap(), &first));
+        assert!(!Arc::ptr_eq(&reloaded, &first));
+    }
+
+    #[test]
+    fn hierarchy_only_entries_are_reparsed_for_geometry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_library(dir.path(), "A");
+        let cache = ParseCache::default();
+        let hierarchy = cache.get_or_load(&path, ParseMode::HierarchyOnly, read_library).unwrap();
+        let again = cache.get_or_load(&path, ParseMode::HierarchyOnly, read_library).unwrap();
+        assert!(Arc::ptr_eq(&hierarchy, &again));
+
+        let full = cache.library(&path).unwrap();
+        assert!(!Arc::ptr_eq(&full, &hierarchy));
+        // The file itself didn't change, so there is nothing to diff against
+        assert!(cache.previous(Path::new(&path)).is_none());
+        // A full parse serves hierarchy-only requests too
+        let served = cache.get_or_load(&path, ParseMode::HierarchyOnly, read_library).unwrap();
+        assert!(Arc::ptr_eq(&served, &full));
+    }
+
+    #[test]
+    fn least_recently_used_entry_is_evicted() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<String> =
+            ["A", "B", "C"].iter().map(|name| write_library(dir.path(), name)).collect();
+        let cache = ParseCache::with_max_entries(2);
+
+        cache.library(&paths[0]).unwrap();
+        cache.library(&paths[1]).unwrap();
+        // Touch A so B becomes the oldest
+        cache.library(&paths[0]).unwrap();
+        cache.library(&paths[2]).unwrap();
+
+        assert_eq!(cache.len(), 2);
+        let state = cache.state.lock().unwrap();
+        assert!(!state.entries.contains_key(&cache_key(Path::new(&paths[1]))));
+    }
+}
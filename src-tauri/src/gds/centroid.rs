@@ -0,0 +1,66 @@
+use super::cache::ParseCache;
+use super::flatten::cell_polygons;
+use super::{GdsError, GdsLibrary};
+use crate::error::AppError;
+use crate::geometry::centroid;
+
+// Area-weighted centroid of the flattened polygons of `cell`, on any datatype
+// of `layer` if given, in database units. `None` when there are no polygons.
+pub fn cell_centroid(
+    library: &GdsLibrary,
+    cell: &str,
+    layer: Option<u16>,
+) -> Result<Option<[f64; 2]>, GdsError> {
+    let polygons: Vec<Vec<[f64; 2]>> = cell_polygons(library, cell)?
+        .into_iter()
+        .filter(|((polygon_layer, _), _)| layer.map_or(true, |layer| *polygon_layer == layer))
+        .flat_map(|(_, outlines)| outlines)
+        .collect();
+    Ok(centroid(&polygons))
+}
+
+// Command to get a reference point for a cell, e.g. to place its label, in
+// user units
+#[tauri::command]
+pub async fn get_cell_centroid(
+    path: String,
+    cell: String,
+    layer: Option<u16>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Option<[f64; 2]>, AppError> {
+    let library = cache.library(&path)?;
+    let user_unit = library.units.user_unit;
+    let center = cell_centroid(&library, &cell, layer)?;
+    Ok(center.map(|[x, y]| [x * user_unit, y * user_unit]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn centroids_weight_polygons_by_area() {
+        let square =
+            |x: i32, size: i32| [[x, 0], [x + size, 0], [x + size, size], [x, size], [x, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &square(0, 20))
+            // Clockwise, but weighted the same as if it ran the other way
+            .boundary(1, 5, &[[100, 0], [100, 10], [110, 10], [110, 0], [100, 0]])
+            .boundary(2, 0, &[[0, 0], [50, 0], [100, 0], [0, 0]])
+            .end_cell();
+        gds.begin_cell("EMPTY").end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        // 400 of area centered at (10, 10) and 100 at (105, 5)
+        let [x, y] = cell_centroid(&library, "TOP", Some(1)).unwrap().unwrap();
+        assert!((x - 29.0).abs() < 1e-9 && (y - 9.0).abs() < 1e-9, "{} {}", x, y);
+        // Only a line without area: the center of its extent
+        assert_eq!(cell_centroid(&library, "TOP", Some(2)).unwrap(), Some([50.0, 0.0]));
+        assert_eq!(cell_centroid(&library, "TOP", Some(3)).unwrap(), None);
+        assert_eq!(cell_centroid(&library, "EMPTY", None).unwrap(), None);
+        assert!(cell_centroid(&library, "GONE", None).is_err());
+    }
+}
@@ -0,0 +1,280 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+use super::bbox::{merge, Bbox, BboxCalculator};
+use super::buffer::{layer_buffer, LayerBuffer};
+use super::cache::ParseCache;
+use super::hierarchy::reachable_cells;
+use super::layers::{cell_layers, LayerInfo};
+use super::units::CoordConvention;
+use super::{CellReference, GdsCell, GdsElement, GdsError, GdsLibrary};
+use crate::error::AppError;
+
+// A cell whose own elements or references differ between two parses. `bbox`
+// covers the cell's placed extent before and after, in database units, or is
+// `None` if it had no geometry either time.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ChangedCell {
+    pub cell: String,
+    pub bbox: Option<Bbox>,
+}
+
+// What changed between two parses of a file. With `everything` set the whole
+// layout has to be redrawn and `cells` is empty.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ChangedRegions {
+    pub everything: bool,
+    pub cells: Vec<ChangedCell>,
+}
+
+impl ChangedRegions {
+    pub const EVERYTHING: ChangedRegions = ChangedRegions { everything: true, cells: Vec::new() };
+}
+
+// Cells that differ between `previous` and `current`, including added and
+// removed ones, in `current`'s order followed by removed cells. Cells that only
+// changed through a child aren't listed; their redraw area is the child's.
+// Changed units, or a hierarchy whose extent can't be computed, change
+// everything.
+pub fn changed_regions(previous: &GdsLibrary, current: &GdsLibrary) -> ChangedRegions {
+    if previous.units != current.units {
+        return ChangedRegions::EVERYTHING;
+    }
+    let mut before = BboxCalculator::new(previous);
+    let mut after = BboxCalculator::new(current);
+    let mut seen = HashSet::new();
+    let names = current.cells.iter().chain(&previous.cells).map(|cell| cell.name.as_str());
+
+    let mut cells = Vec::new();
+    for name in names {
+        if !seen.insert(name) {
+            continue;
+        }
+        let (old, new) = (previous.cell(name), current.cell(name));
+        if old == new {
+            continue;
+        }
+        let mut bbox = None;
+        for (cell, calculator) in [(old, &mut before), (new, &mut after)] {
+            if cell.is_none() {
+                continue;
+            }
+            match calculator.cell_bbox(name) {
+                Ok(Some(extent)) => merge(&mut bbox, extent),
+                Ok(None) => {}
+                Err(_) => return ChangedRegions::EVERYTHING,
+            }
+        }
+        cells.push(ChangedCell { cell: name.to_string(), bbox });
+    }
+    ChangedRegions { everything: false, cells }
+}
+
+// Command to find the cells whose geometry changed the last time the file was
+// re-parsed, so only their areas need redrawing. Reports everything as changed
+// when the previous parse is no longer known.
+#[tauri::command]
+pub async fn diff_since_reload(
+    path: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<ChangedRegions, AppError> {
+    let current = cache.library(&path)?;
+    Ok(match cache.previous(Path::new(&path)) {
+        Some(previous) => changed_regions(&previous, &current),
+        None => ChangedRegions::EVERYTHING,
+    })
+}
+
+// The fresh buffer of a layer whose geometry changed. A layer that is gone
+// gets an empty buffer, so the renderer drops what it drew for it.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ChangedLayerBuffer {
+    pub layer: u16,
+    pub datatype: u16,
+    pub buffer: LayerBuffer,
+}
+
+fn layer_keys(layers: &[LayerInfo]) -> BTreeSet<(u16, u16)> {
+    layers.iter().map(|info| (info.layer, info.datatype)).collect()
+}
+
+// The elements of a cell grouped by layer, in file order
+fn by_layer(cell: Option<&GdsCell>) -> BTreeMap<(u16, u16), Vec<&GdsElement>> {
+    let mut elements: BTreeMap<(u16, u16), Vec<&GdsElement>> = BTreeMap::new();
+    for element in cell.into_iter().flat_map(|cell| &cell.elements) {
+        elements.entry(element.layer_key()).or_default().push(element);
+    }
+    elements
+}
+
+// The references of a cell grouped by the cell they place, in file order
+fn by_child(cell: Option<&GdsCell>) -> HashMap<&str, Vec<&CellReference>> {
+    let mut references: HashMap<&str, Vec<&CellReference>> = HashMap::new();
+    for reference in cell.into_iter().flat_map(|cell| &cell.references) {
+        references.entry(reference.cell.as_str()).or_default().push(reference);
+    }
+    references
+}
+
+// Layers whose flattened geometry under `cell` may differ between `previous`
+// and `current`: those of the elements that changed in any cell below it
+// either time, and every layer below a child whose placements changed. `None`
+// when that can't be told, for changed units or a cell that is new.
+fn changed_layers(
+    previous: &GdsLibrary,
+    current: &GdsLibrary,
+    cell: &str,
+) -> Result<Option<BTreeSet<(u16, u16)>>, GdsError> {
+    let now = reachable_cells(current, cell)?;
+    if previous.units != current.units {
+        return Ok(None);
+    }
+    let Ok(before) = reachable_cells(previous, cell) else {
+        return Ok(None);
+    };
+    let mut seen = HashSet::new();
+    let mut layers = BTreeSet::new();
+    for name in now.iter().chain(&before).map(|cell| cell.name.as_str()) {
+        if !seen.insert(name) {
+            continue;
+        }
+        let (old, new) = (previous.cell(name), current.cell(name));
+        if old == new {
+            continue;
+        }
+        let (old_elements, new_elements) = (by_layer(old), by_layer(new));
+        let keys = old_elements.keys().chain(new_elements.keys());
+        layers.extend(keys.filter(|key| old_elements.get(key) != new_elements.get(key)));
+
+        let (old_references, new_references) = (by_child(old), by_child(new));
+        for child in old_references.keys().chain(new_references.keys()) {
+            if old_references.get(child) != new_references.get(child) {
+                // A child missing from the library draws nothing
+                for library in [previous, current] {
+                    layers.extend(layer_keys(&cell_layers(library, child).unwrap_or_default()));
+                }
+            }
+        }
+    }
+    Ok(Some(layers))
+}
+
+// Buffers of the layers of `cell` whose geometry changed since `previous`, as
+// `layer_buffer` makes them, sorted by layer then datatype. Every layer of the
+// cell, then and now, when there is no previous parse or the change can't be
+// narrowed down.
+pub fn changed_layer_buffers(
+    previous: Option<&GdsLibrary>,
+    current: &GdsLibrary,
+    cell: &str,
+    coord_convention: Option<&CoordConvention>,
+) -> Result<Vec<ChangedLayerBuffer>, AppError> {
+    let changed = match previous {
+        Some(previous) => changed_layers(previous, current, cell)?,
+        None => None,
+    };
+    let layers = match changed {
+        Some(layers) => layers,
+        None => {
+            let mut layers = layer_keys(&cell_layers(current, cell)?);
+            if let Some(previous) = previous {
+                layers.extend(layer_keys(&cell_layers(previous, cell).unwrap_or_default()));
+            }
+            layers
+        }
+    };
+    layers
+        .into_iter()
+        .map(|(layer, datatype)| {
+            let buffer = layer_buffer(current, cell, layer, datatype, coord_convention)?;
+            Ok(ChangedLayerBuffer { layer, datatype, buffer })
+        })
+        .collect()
+}
+
+// Command to get fresh buffers for only the layers of a cell that changed the
+// last time the file was re-parsed, so the renderer can replace just those
+// after a `file-changed` instead of rebuilding every layer
+#[tauri::command]
+pub async fn get_changed_layer_buffers(
+    path: String,
+    cell: String,
+    coord_convention: Option<CoordConvention>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<ChangedLayerBuffer>, AppError> {
+    let current = cache.library(&path)?;
+    let previous = cache.previous(Path::new(&path));
+    changed_layer_buffers(previous.as_deref(), &current, &cell, coord_convention.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    const SQUARE: [[i32; 2]; 5] = [[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]];
+
+    #[test]
+    fn only_edited_cells_are_reported() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("KEPT").boundary(1, 0, &SQUARE).end_cell();
+        gds.begin_cell("EDITED").boundary(1, 0, &SQUARE).end_cell();
+        gds.begin_cell("DELETED").boundary(2, 0, &SQUARE).end_cell();
+        let previous = parse_library(&gds.finish()).unwrap();
+
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("KEPT").boundary(1, 0, &SQUARE).end_cell();
+        gds.begin_cell("EDITED")
+            .boundary(1, 0, &[[50, 0], [60, 0], [60, 10], [50, 0]])
+            .end_cell();
+        let current = parse_library(&gds.finish()).unwrap();
+
+        let changes = changed_regions(&previous, &current);
+        assert!(!changes.everything);
+        let square = Bbox { min_x: 0.0, min_y: 0.0, max_x: 10.0, max_y: 10.0 };
+        // Covers where the old square was and where the new triangle is
+        let edited = Bbox { max_x: 60.0, ..square };
+        assert_eq!(
+            changes.cells,
+            vec![
+                ChangedCell { cell: "EDITED".to_string(), bbox: Some(edited) },
+                ChangedCell { cell: "DELETED".to_string(), bbox: Some(square) },
+            ]
+        );
+        assert_eq!(changed_regions(&current, &current).cells, Vec::new());
+    }
+    #[test]
+    fn only_layers_that_changed_under_the_cell_get_buffers() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("LEAF").boundary(1, 0, &SQUARE).boundary(2, 0, &SQUARE).end_cell();
+        gds.begin_cell("PAD").boundary(4, 0, &SQUARE).end_cell();
+        gds.begin_cell("ELSEWHERE").boundary(5, 0, &SQUARE).end_cell();
+        gds.begin_cell("TOP").boundary(3, 0, &SQUARE).sref("LEAF", [0, 0], None).end_cell();
+        let previous = parse_library(&gds.finish()).unwrap();
+
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("LEAF")
+            .boundary(1, 0, &SQUARE)
+            .boundary(2, 0, &[[50, 0], [60, 0], [60, 10], [50, 0]])
+            .end_cell();
+        gds.begin_cell("PAD").boundary(4, 0, &SQUARE).end_cell();
+        gds.begin_cell("ELSEWHERE").boundary(5, 1, &SQUARE).end_cell();
+        gds.begin_cell("TOP").sref("LEAF", [0, 0], None).sref("PAD", [100, 0], None).end_cell();
+        let current = parse_library(&gds.finish()).unwrap();
+
+        let changed = changed_layer_buffers(Some(&previous), &current, "TOP", None).unwrap();
+        let keys: Vec<_> = changed.iter().map(|layer| (layer.layer, layer.datatype)).collect();
+        // The edited triangle, the deleted square and the newly placed pad
+        assert_eq!(keys, [(2, 0), (3, 0), (4, 0)]);
+        assert_eq!(changed[0].buffer, layer_buffer(&current, "TOP", 2, 0, None).unwrap());
+        assert_eq!(changed[1].buffer.ring_offsets, [0]);
+
+        let unchanged = changed_layer_buffers(Some(&current), &current, "TOP", None).unwrap();
+        assert_eq!(unchanged, Vec::new());
+        let everything = changed_layer_buffers(None, &current, "TOP", None).unwrap();
+        assert_eq!(everything.len(), 3);
+    }
+}
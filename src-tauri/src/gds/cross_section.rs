@@ -0,0 +1,105 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::cache::ParseCache;
+use super::hit_test::{HitIndex, HitIndexCache};
+use crate::error::AppError;
+use crate::geometry::rtree::Rect;
+use crate::geometry::section::{merge_intervals, segment_intervals};
+
+// Where a cut line runs through the geometry of one layer, as `(from, to)`
+// distances along the line from its start, sorted and not overlapping
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct LayerSection {
+    pub layer: u16,
+    pub segments: Vec<(f64, f64)>,
+}
+
+// The stretches of the segment from `a` to `b` (database units) inside the
+// polygons of each of `layers`, any datatype, as fractions of the segment.
+// Stretches along a polygon edge count as inside; single touching points don't.
+pub fn layer_sections(
+    index: &HitIndex,
+    a: [f64; 2],
+    b: [f64; 2],
+    layers: &[u16],
+) -> Vec<(u16, Vec<[f64; 2]>)> {
+    let candidates = Rect::around(&[a, b]).map(|rect| index.within(&rect)).unwrap_or_default();
+    layers
+        .iter()
+        .map(|&layer| {
+            let intervals = candidates
+                .iter()
+                .filter(|(element, _)| element.layer == layer)
+                .flat_map(|(_, outline)| segment_intervals(outline, a, b))
+                .collect();
+            (layer, merge_intervals(intervals))
+        })
+        .collect()
+}
+
+// Command to cut a cell along the line from `(x0, y0)` to `(x1, y1)` in user
+// units, giving for each of `layers`, in the order asked, where the line is
+// inside that layer's geometry as distances from the start in user units
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn cross_section(
+    path: String,
+    cell: String,
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+    layers: Vec<u16>,
+    cache: tauri::State<'_, ParseCache>,
+    indexes: tauri::State<'_, HitIndexCache>,
+) -> Result<Vec<LayerSection>, AppError> {
+    let length = (x1 - x0).hypot(y1 - y0);
+    if !(length > 0.0 && length.is_finite()) {
+        return Err(AppError::invalid_argument(format!(
+            "Cut line from ({}, {}) to ({}, {}) must have a finite, non-zero length",
+            x0, y0, x1, y1
+        )));
+    }
+    let library = cache.library(&path)?;
+    let index = indexes.index(&path, &library, &cell)?;
+    let user_unit = library.units.user_unit;
+    let (a, b) = ([x0 / user_unit, y0 / user_unit], [x1 / user_unit, y1 / user_unit]);
+    Ok(layer_sections(&index, a, b, &layers)
+        .into_iter()
+        .map(|(layer, intervals)| LayerSection {
+            layer,
+            segments: intervals
+                .into_iter()
+                .map(|[start, end]| (start * length, end * length))
+                .collect(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn sections_merge_the_polygons_of_each_layer() {
+        let rect = |x0: i32, x1: i32| [[x0, 0], [x1, 0], [x1, 10], [x0, 10], [x0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &rect(0, 40))
+            .boundary(1, 5, &rect(30, 60))
+            .boundary(1, 0, &rect(80, 90))
+            .boundary(2, 0, &rect(100, 120))
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+        let index = HitIndex::build(&library, "TOP").unwrap();
+
+        let sections = layer_sections(&index, [-100.0, 5.0], [100.0, 5.0], &[2, 1, 3]);
+        assert_eq!(sections, [(2, vec![]), (1, vec![[0.5, 0.8], [0.9, 0.95]]), (3, vec![])]);
+        // Running along the top edge still cuts the rectangles
+        let grazing = layer_sections(&index, [0.0, 10.0], [100.0, 10.0], &[1]);
+        assert_eq!(grazing, [(1, vec![[0.0, 0.6], [0.8, 0.9]])]);
+    }
+}
@@ -0,0 +1,95 @@
+use super::record::Record;
+use super::GdsDates;
+
+// Fields of one GDSII date: year, month, day, hour, minute and second
+const DATE_FIELDS: usize = 6;
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i32, month: i32) -> i32 {
+    match month {
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => 31,
+    }
+}
+
+// Format a GDSII date as ISO-8601 local time, e.g. `2024-03-05T14:07:00`.
+// GDSII records no time zone. Years are written in several ways in the wild:
+// in full, as years since 1900 (124 for 2024), or as two digits; the last two
+// are read as 1970-2069. All-zero dates, which many tools write, and fields
+// out of range give `None`.
+pub fn iso_date(fields: &[i16]) -> Option<String> {
+    let &[year, month, day, hour, minute, second] = fields else {
+        return None;
+    };
+    let year = match year as i32 {
+        year @ 0..=69 => year + 2000,
+        year @ 70..=999 => year + 1900,
+        year => year,
+    };
+    let (month, day) = (month as i32, day as i32);
+    let valid = (1..=12).contains(&month)
+        && (1..=days_in_month(year, month)).contains(&day)
+        && (0..24).contains(&hour)
+        && (0..60).contains(&minute)
+        && (0..60).contains(&second)
+        && (1..=9999).contains(&year);
+    valid.then(|| {
+        format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}", year, month, day, hour, minute, second)
+    })
+}
+
+// Seconds since the Unix epoch of a date `iso_date` formatted, taking it as
+// UTC since it has no time zone
+pub fn unix_seconds(date: &str) -> Option<i64> {
+    let field = |range: std::ops::Range<usize>| date.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (field(0..4)?, field(5..7)?, field(8..10)?);
+    let (hour, minute, second) = (field(11..13)?, field(14..16)?, field(17..19)?);
+    // Civil date to days, after Howard Hinnant's algorithm
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    Some(days * 86_400 + hour * 3600 + minute * 60 + second)
+}
+
+// The modification and access dates of a BGNLIB or BGNSTR record. A record
+// that is short or holds the wrong data type just has no dates.
+pub fn dates_from_record(record: &Record) -> GdsDates {
+    let values = record.i16_values().unwrap_or_default();
+    let date = |index: usize| {
+        values.get(index * DATE_FIELDS..(index + 1) * DATE_FIELDS).and_then(iso_date)
+    };
+    GdsDates { modified: date(0), accessed: date(1) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_are_normalized_and_garbage_is_dropped() {
+        let date = |fields: [i16; 6]| iso_date(&fields);
+        assert_eq!(date([2024, 3, 5, 14, 7, 0]).as_deref(), Some("2024-03-05T14:07:00"));
+        assert_eq!(date([124, 12, 31, 23, 59, 59]).as_deref(), Some("2024-12-31T23:59:59"));
+        assert_eq!(date([98, 1, 1, 0, 0, 0]).as_deref(), Some("1998-01-01T00:00:00"));
+        assert_eq!(date([5, 6, 7, 8, 9, 10]).as_deref(), Some("2005-06-07T08:09:10"));
+        assert_eq!(date([2024, 2, 29, 0, 0, 0]).as_deref(), Some("2024-02-29T00:00:00"));
+
+        assert_eq!(date([0; 6]), None);
+        assert_eq!(date([2023, 2, 29, 0, 0, 0]), None);
+        assert_eq!(date([2024, 13, 1, 0, 0, 0]), None);
+        assert_eq!(date([2024, 1, 1, 24, 0, 0]), None);
+        assert_eq!(date([-1, 1, 1, 0, 0, 0]), None);
+        assert_eq!(iso_date(&[2024, 1, 1]), None);
+        assert_eq!(unix_seconds("2024-03-05T14:07:00"), Some(1_709_647_620));
+        assert_eq!(unix_seconds("1969-12-31T23:59:59"), Some(-1));
+    }
+}
@@ -0,0 +1,115 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::cache::ParseCache;
+use super::flatten::layer_polygons;
+use super::{GdsError, GdsLibrary};
+use crate::error::AppError;
+use crate::geometry::boolean::{boolean, BooleanOp};
+use crate::geometry::{perimeter, signed_area};
+
+// Result rings thinner than this, in database units of file A, are dropped as
+// rounding noise. A ring's thickness is taken as 2 * area / perimeter, which is
+// its width for a long thin sliver. Geometry that differs by less than about
+// half a database unit therefore counts as the same.
+pub const DIFF_TOLERANCE: f64 = 0.5;
+
+// Geometry of one layer split by which of two files it appears in, in the user
+// units of file A. Each list holds closed rings as returned by the boolean
+// engine: counter-clockwise outlines and clockwise holes.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct GdsDiff {
+    pub only_a: Vec<Vec<[f64; 2]>>,
+    pub only_b: Vec<Vec<[f64; 2]>>,
+    pub both: Vec<Vec<[f64; 2]>>,
+}
+
+fn without_slivers(rings: Vec<Vec<[f64; 2]>>) -> Vec<Vec<[f64; 2]>> {
+    rings
+        .into_iter()
+        .filter(|ring| 2.0 * signed_area(ring).abs() / perimeter(ring) >= DIFF_TOLERANCE)
+        .collect()
+}
+
+fn to_user_units(rings: Vec<Vec<[f64; 2]>>, scale: f64) -> Vec<Vec<[f64; 2]>> {
+    rings
+        .into_iter()
+        .map(|ring| ring.into_iter().map(|[x, y]| [x * scale, y * scale]).collect())
+        .collect()
+}
+
+// Compare the flattened boundaries of `cell` on (layer, datatype) in two
+// libraries. B is rescaled into A's database units first, so files written with
+// different units compare by physical size.
+pub fn diff_libraries(
+    a: &GdsLibrary,
+    b: &GdsLibrary,
+    cell: &str,
+    layer: u16,
+    datatype: u16,
+) -> Result<GdsDiff, GdsError> {
+    let polygons_a = layer_polygons(a, cell, layer, datatype, 1.0)?;
+    let b_scale = b.units.db_unit_meters / a.units.db_unit_meters;
+    let polygons_b = layer_polygons(b, cell, layer, datatype, b_scale)?;
+
+    let compare = |first: &[Vec<[f64; 2]>], second: &[Vec<[f64; 2]>], op| {
+        to_user_units(without_slivers(boolean(first, second, op)), a.units.user_unit)
+    };
+    Ok(GdsDiff {
+        only_a: compare(&polygons_a, &polygons_b, BooleanOp::Not),
+        only_b: compare(&polygons_b, &polygons_a, BooleanOp::Not),
+        both: compare(&polygons_a, &polygons_b, BooleanOp::And),
+    })
+}
+
+// Command to compare one layer of a cell between two GDSII files
+#[tauri::command]
+pub async fn diff_gds(
+    a: String,
+    b: String,
+    cell: String,
+    layer: u16,
+    datatype: u16,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<GdsDiff, AppError> {
+    let library_a = cache.library(&a)?;
+    let library_b = cache.library(&b)?;
+    Ok(diff_libraries(&library_a, &library_b, &cell, layer, datatype)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    fn library(boundaries: &[[[i32; 2]; 5]]) -> GdsLibrary {
+        let mut gds = GdsBuilder::library("LIB");
+        let cell = gds.begin_cell("TOP");
+        for points in boundaries {
+            cell.boundary(1, 0, points);
+        }
+        cell.end_cell();
+        parse_library(&gds.finish()).unwrap()
+    }
+
+    #[test]
+    fn moved_and_unchanged_shapes_are_separated() {
+        let fixed = [[0, 0], [100, 0], [100, 100], [0, 100], [0, 0]];
+        let before = library(&[fixed, [[200, 0], [300, 0], [300, 100], [200, 100], [200, 0]]]);
+        // Second square shifted right by half its width
+        let after = library(&[fixed, [[250, 0], [350, 0], [350, 100], [250, 100], [250, 0]]]);
+
+        let diff = diff_libraries(&before, &after, "TOP", 1, 0).unwrap();
+        let area = |rings: &[Vec<[f64; 2]>]| -> f64 { rings.iter().map(|r| signed_area(r)).sum() };
+        let user_unit = before.units.user_unit;
+        assert_eq!(diff.only_a.len(), 1);
+        assert_eq!(diff.only_b.len(), 1);
+        assert!((area(&diff.only_a) - 5000.0 * user_unit * user_unit).abs() < 1e-9);
+        assert!((area(&diff.both) - 15000.0 * user_unit * user_unit).abs() < 1e-9);
+
+        let same = diff_libraries(&before, &before, "TOP", 1, 0).unwrap();
+        assert!(same.only_a.is_empty() && same.only_b.is_empty());
+        assert_eq!(same.both.len(), 2);
+    }
+}
@@ -0,0 +1,113 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::io::{Read, Seek, SeekFrom};
+
+use super::record::{
+    data_type_name, is_pad_byte, record_type_name, PAD_WINDOW_LEN, RECORD_HEADER_LEN,
+};
+use crate::error::AppError;
+
+// Records listed when the caller doesn't give a limit
+const DEFAULT_DUMP_LIMIT: usize = 10_000;
+
+// Header of one record: where it starts, what it is and the length it declares
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct RecordSummary {
+    pub offset: u64,
+    pub record_type: String,
+    pub data_type: String,
+    pub length: u16,
+}
+
+// The headers of the first `limit` records of a stream. Record data is skipped
+// rather than read, so this stays cheap on huge files. A record whose declared
+// length is shorter than its header or runs past the end of the stream is
+// listed and ends the dump, since nothing after it can be located. A pad byte a
+// writer put after a record declared with an odd length is skipped, as the
+// parser does.
+pub fn dump_records<R: Read + Seek>(
+    reader: &mut R,
+    limit: usize,
+) -> std::io::Result<Vec<RecordSummary>> {
+    let end = reader.seek(SeekFrom::End(0))?;
+    let mut offset = reader.seek(SeekFrom::Start(0))?;
+    let mut records = Vec::new();
+    let mut header = [0u8; RECORD_HEADER_LEN];
+    while records.len() < limit && end - offset >= RECORD_HEADER_LEN as u64 {
+        reader.read_exact(&mut header)?;
+        let length = u16::from_be_bytes([header[0], header[1]]);
+        records.push(RecordSummary {
+            offset,
+            record_type: record_type_name(header[2]).to_string(),
+            data_type: data_type_name(header[3]).to_string(),
+            length,
+        });
+        if (length as usize) < RECORD_HEADER_LEN || offset + length as u64 > end {
+            break;
+        }
+        let mut next = offset + length as u64;
+        if length % 2 != 0 && next < end {
+            let mut window = [0u8; PAD_WINDOW_LEN];
+            let window = &mut window[..PAD_WINDOW_LEN.min((end - next) as usize)];
+            reader.seek(SeekFrom::Start(next))?;
+            reader.read_exact(window)?;
+            if is_pad_byte(window, (end - next) as usize) {
+                next += 1;
+            }
+        }
+        offset = reader.seek(SeekFrom::Start(next))?;
+    }
+    Ok(records)
+}
+
+// Command to list the record headers of a GDSII file, for diagnosing files that
+// won't parse. At most `limit` records are returned (10000 by default).
+#[tauri::command]
+pub async fn dump_gds_records(
+    path: String,
+    limit: Option<usize>,
+) -> Result<Vec<RecordSummary>, AppError> {
+    let mut file = std::fs::File::open(&path).map_err(|e| AppError::for_path(&path, e))?;
+    dump_records(&mut file, limit.unwrap_or(DEFAULT_DUMP_LIMIT))
+        .map_err(|e| AppError::for_path(&path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::record;
+    use std::io::Cursor;
+
+    #[test]
+    fn headers_are_listed_up_to_the_limit_or_a_broken_record() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP").end_cell();
+        let bytes = gds.finish();
+
+        let records = dump_records(&mut Cursor::new(&bytes), 3).unwrap();
+        let summary = |offset, record_type: &str, data_type: &str, length| RecordSummary {
+            offset,
+            record_type: record_type.to_string(),
+            data_type: data_type.to_string(),
+            length,
+        };
+        assert_eq!(records[0], summary(0, "HEADER", "INT16", 6));
+        assert_eq!(records[1], summary(6, "BGNLIB", "INT16", 28));
+        assert_eq!(records.len(), 3);
+
+        let all = dump_records(&mut Cursor::new(&bytes), usize::MAX).unwrap();
+        assert_eq!(all.last().unwrap().record_type, "ENDLIB");
+
+        // Cut off in the middle of BGNLIB: it is still listed, then the dump ends
+        let cut = dump_records(&mut Cursor::new(&bytes[..16]), usize::MAX).unwrap();
+        assert_eq!(cut, records[..2]);
+
+        // An odd declared length with a pad byte after the record
+        let mut odd = GdsBuilder::empty();
+        odd.odd_string(record::LIBNAME, "ABC");
+        let padded = dump_records(&mut Cursor::new(odd.finish()), usize::MAX).unwrap();
+        let expected = [summary(0, "LIBNAME", "ASCII", 7), summary(8, "ENDLIB", "NO_DATA", 4)];
+        assert_eq!(padded, expected);
+    }
+}
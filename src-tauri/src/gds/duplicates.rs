@@ -0,0 +1,144 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::cache::ParseCache;
+use super::flatten::layer_polygons;
+use super::GdsLibrary;
+use crate::error::AppError;
+
+// How far apart (in database units, along either axis) matching vertices of
+// two polygons may be for them to count as duplicates, so copies that a
+// generator rounded differently are caught too
+const VERTEX_TOLERANCE: f64 = 1.0;
+
+// Two polygons of one layer covering the same outline; the indexes are
+// positions in `get_layer_polygons`, with `index_a` the earlier one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct DuplicatePair {
+    pub index_a: usize,
+    pub index_b: usize,
+}
+
+// The distinct vertices of a ring, without the closing point or points
+// repeated back to back
+fn ring_vertices(ring: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    let mut vertices = ring.to_vec();
+    vertices.dedup();
+    while vertices.len() > 1 && vertices.first() == vertices.last() {
+        vertices.pop();
+    }
+    vertices
+}
+
+fn near(a: [f64; 2], b: [f64; 2]) -> bool {
+    (a[0] - b[0]).abs() <= VERTEX_TOLERANCE && (a[1] - b[1]).abs() <= VERTEX_TOLERANCE
+}
+
+// Whether two vertex lists trace the same outline within the tolerance,
+// whichever vertex each starts at and whichever way each runs
+fn same_outline(a: &[[f64; 2]], b: &[[f64; 2]]) -> bool {
+    let n = a.len();
+    if n != b.len() || n == 0 {
+        return false;
+    }
+    (0..n).filter(|&shift| near(a[0], b[shift])).any(|shift| {
+        let forward = (0..n).all(|i| near(a[i], b[(shift + i) % n]));
+        forward || (0..n).all(|i| near(a[i], b[(shift + n - i) % n]))
+    })
+}
+
+// Candidate key of a vertex list: its length and the grid square of its
+// vertex mean. Moving every vertex by at most the tolerance moves the mean by
+// at most that much too, so a duplicate's key is in the same or a
+// neighbouring square.
+fn candidate_key(vertices: &[[f64; 2]]) -> (usize, i64, i64) {
+    let n = vertices.len() as f64;
+    let [sx, sy] = vertices.iter().fold([0.0, 0.0], |[sx, sy], [x, y]| [sx + x, sy + y]);
+    let square = |sum: f64| (sum / n / VERTEX_TOLERANCE).floor() as i64;
+    (vertices.len(), square(sx), square(sy))
+}
+
+// Polygons of `rings` that duplicate an earlier one, each paired with the
+// first earlier polygon it duplicates, in order of the later index
+pub fn duplicate_pairs(rings: &[Vec<[f64; 2]>]) -> Vec<DuplicatePair> {
+    let outlines: Vec<Vec<[f64; 2]>> = rings.iter().map(|ring| ring_vertices(ring)).collect();
+    let mut by_key: HashMap<(usize, i64, i64), Vec<usize>> = HashMap::new();
+    let mut pairs = Vec::new();
+    for (index_b, outline) in outlines.iter().enumerate() {
+        if outline.len() < 3 {
+            continue;
+        }
+        let (n, x, y) = candidate_key(outline);
+        let mut candidates: Vec<usize> = (-1..=1)
+            .flat_map(|dx| (-1..=1).map(move |dy| (n, x + dx, y + dy)))
+            .filter_map(|key| by_key.get(&key))
+            .flatten()
+            .copied()
+            .collect();
+        candidates.sort_unstable();
+        if let Some(&index_a) =
+            candidates.iter().find(|&&index_a| same_outline(&outlines[index_a], outline))
+        {
+            pairs.push(DuplicatePair { index_a, index_b });
+        }
+        by_key.entry((n, x, y)).or_default().push(index_b);
+    }
+    pairs
+}
+
+// `duplicate_pairs` among the flattened polygons of one layer of a cell
+pub fn duplicate_polygons(
+    library: &GdsLibrary,
+    cell: &str,
+    layer: u16,
+    datatype: u16,
+) -> Result<Vec<DuplicatePair>, AppError> {
+    Ok(duplicate_pairs(&layer_polygons(library, cell, layer, datatype, 1.0)?))
+}
+
+// Command to find polygons of one layer of a cell that repeat another one
+// exactly or within a database unit, for cleaning up generator output
+#[tauri::command]
+pub async fn find_duplicate_polygons(
+    path: String,
+    cell: String,
+    layer: u16,
+    datatype: u16,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<DuplicatePair>, AppError> {
+    let library = cache.library(&path)?;
+    duplicate_polygons(&library, &cell, layer, datatype)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn duplicates_are_found_whatever_their_start_and_direction() {
+        let square = [[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("SQUARE").boundary(1, 0, &square).end_cell();
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &square)
+            // The same square from another corner, clockwise
+            .boundary(1, 0, &[[10, 10], [10, 0], [0, 0], [0, 10], [10, 10]])
+            // Off by one unit at one corner
+            .boundary(1, 0, &[[0, 0], [10, 0], [11, 10], [0, 10], [0, 0]])
+            // Off by two: a different polygon
+            .boundary(1, 0, &[[0, 0], [12, 0], [12, 10], [0, 10], [0, 0]])
+            .sref("SQUARE", [0, 0], None)
+            .sref("SQUARE", [100, 0], None)
+            .boundary(2, 0, &square)
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let pair = |index_a, index_b| DuplicatePair { index_a, index_b };
+        let pairs = duplicate_polygons(&library, "TOP", 1, 0).unwrap();
+        assert_eq!(pairs, [pair(0, 1), pair(0, 2), pair(0, 4)]);
+        assert!(duplicate_polygons(&library, "TOP", 2, 0).unwrap().is_empty());
+    }
+}
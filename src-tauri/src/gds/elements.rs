@@ -0,0 +1,116 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::bbox::{element_bbox, Bbox};
+use super::cache::ParseCache;
+use super::{GdsCell, GdsElement, GdsError, GdsLibrary};
+use crate::error::AppError;
+
+// An element of a cell without its geometry, for an element list. `id` is its
+// index into the cell's geometric elements, the same as `get_element_properties`
+// reports, and `kind` its `type`. `bbox` is in database units in the cell's own
+// coordinates, `None` for an element without points.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ElementSummary {
+    pub id: u64,
+    pub kind: String,
+    pub bbox: Option<Bbox>,
+}
+
+fn find_cell<'a>(library: &'a GdsLibrary, cell: &str) -> Result<&'a GdsCell, GdsError> {
+    library.cell(cell).ok_or_else(|| GdsError::CellNotFound { cell: cell.to_string() })
+}
+
+// The elements of `cell` itself on (`layer`, `datatype`), in file order. BOX,
+// TEXT and NODE elements match by their boxtype/texttype/nodetype.
+pub fn layer_elements(
+    library: &GdsLibrary,
+    cell: &str,
+    layer: u16,
+    datatype: u16,
+) -> Result<Vec<ElementSummary>, GdsError> {
+    Ok(find_cell(library, cell)?
+        .elements
+        .iter()
+        .enumerate()
+        .filter(|(_, element)| element.layer_key() == (layer, datatype))
+        .map(|(id, element)| ElementSummary {
+            id: id as u64,
+            kind: element.kind().to_string(),
+            bbox: element_bbox(element),
+        })
+        .collect())
+}
+
+// The element of `cell` with the id `layer_elements` gave it
+pub fn element_by_id<'a>(
+    library: &'a GdsLibrary,
+    cell: &str,
+    id: u64,
+) -> Result<&'a GdsElement, AppError> {
+    let found = find_cell(library, cell)?;
+    usize::try_from(id)
+        .ok()
+        .and_then(|index| found.elements.get(index))
+        .ok_or_else(|| AppError::invalid_argument(format!("Cell {} has no element {}", cell, id)))
+}
+
+// Command to list the elements of a cell on one layer with their ids, kinds and
+// extents, for an element inspector that fetches geometry on demand
+#[tauri::command]
+pub async fn get_layer_elements(
+    path: String,
+    cell: String,
+    layer: u16,
+    datatype: u16,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<ElementSummary>, AppError> {
+    let library = cache.library(&path)?;
+    Ok(layer_elements(&library, &cell, layer, datatype)?)
+}
+
+// Command to get the full geometry of one element listed by `get_layer_elements`
+#[tauri::command]
+pub async fn get_element_geometry(
+    path: String,
+    cell: String,
+    id: u64,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<GdsElement, AppError> {
+    let library = cache.library(&path)?;
+    element_by_id(&library, &cell, id).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn listed_ids_fetch_the_same_elements() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]])
+            .boundary(2, 0, &[[0, 0], [5, 0], [0, 5], [0, 0]])
+            .path(1, 0, 0, 4, &[[0, 20], [30, 20]])
+            .text(1, 0, [7, 8], "VDD")
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let listed = layer_elements(&library, "TOP", 1, 0).unwrap();
+        let kinds: Vec<(u64, &str)> =
+            listed.iter().map(|element| (element.id, element.kind.as_str())).collect();
+        assert_eq!(kinds, [(0, "rect"), (2, "path"), (3, "text")]);
+        // Padded by half the width on all sides
+        let padded = Bbox { min_x: -2.0, min_y: 18.0, max_x: 32.0, max_y: 22.0 };
+        assert_eq!(listed[1].bbox, Some(padded));
+        for summary in &listed {
+            let element = element_by_id(&library, "TOP", summary.id).unwrap();
+            assert_eq!(element.kind(), summary.kind);
+            assert_eq!(element_bbox(element), summary.bbox);
+        }
+        assert!(element_by_id(&library, "TOP", 4).is_err());
+        assert!(layer_elements(&library, "GONE", 1, 0).is_err());
+    }
+}
@@ -0,0 +1,221 @@
+//! Builders for small GDSII byte streams used as test fixtures.
+
+use super::record::{self, encode_real8};
+
+pub struct GdsBuilder {
+    bytes: Vec<u8>,
+}
+
+impl GdsBuilder {
+    // Start an empty stream, for fixtures that need unusual leading records
+    pub fn empty() -> Self {
+        GdsBuilder { bytes: Vec::new() }
+    }
+
+    // Start a library with HEADER, BGNLIB, LIBNAME and 1nm/1µm UNITS
+    pub fn library(name: &str) -> Self {
+        Self::library_with_version(name, 600)
+    }
+
+    // Start a library like `library`, with `version` in its HEADER record
+    pub fn library_with_version(name: &str, version: i16) -> Self {
+        let mut builder = GdsBuilder { bytes: Vec::new() };
+        builder
+            .record(record::HEADER, record::INT16, &version.to_be_bytes())
+            .record(record::BGNLIB, record::INT16, &[0; 24])
+            .string(record::LIBNAME, name)
+            .reals(record::UNITS, &[0.001, 1e-9]);
+        builder
+    }
+
+    // Append a raw record
+    pub fn record(&mut self, record_type: u8, data_type: u8, data: &[u8]) -> &mut Self {
+        let length = (data.len() + record::RECORD_HEADER_LEN) as u16;
+        self.bytes.extend_from_slice(&length.to_be_bytes());
+        self.bytes.push(record_type);
+        self.bytes.push(data_type);
+        self.bytes.extend_from_slice(data);
+        self
+    }
+
+    // Append raw bytes, e.g. to damage the stream
+    pub fn raw(&mut self, bytes: &[u8]) -> &mut Self {
+        self.bytes.extend_from_slice(bytes);
+        self
+    }
+
+    pub fn string(&mut self, record_type: u8, value: &str) -> &mut Self {
+        let mut data = value.as_bytes().to_vec();
+        if data.len() % 2 != 0 {
+            data.push(0);
+        }
+        self.record(record_type, record::ASCII, &data)
+    }
+
+    // Append an ASCII record the way some writers do, declaring the odd length
+    // of `value` and putting the pad byte after the record
+    pub fn odd_string(&mut self, record_type: u8, value: &str) -> &mut Self {
+        assert!(value.len() % 2 != 0, "odd_string needs an odd-length value");
+        self.record(record_type, record::ASCII, value.as_bytes()).raw(&[0])
+    }
+
+    pub fn int16(&mut self, record_type: u8, values: &[i16]) -> &mut Self {
+        let data: Vec<u8> = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+        self.record(record_type, record::INT16, &data)
+    }
+
+    pub fn int32(&mut self, record_type: u8, values: &[i32]) -> &mut Self {
+        let data: Vec<u8> = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+        self.record(record_type, record::INT32, &data)
+    }
+
+    pub fn reals(&mut self, record_type: u8, values: &[f64]) -> &mut Self {
+        let data: Vec<u8> = values.iter().flat_map(|v| encode_real8(*v)).collect();
+        self.record(record_type, record::REAL8, &data)
+    }
+
+    pub fn xy(&mut self, points: &[[i32; 2]]) -> &mut Self {
+        let values: Vec<i32> = points.iter().flat_map(|p| [p[0], p[1]]).collect();
+        self.int32(record::XY, &values)
+    }
+
+    pub fn begin_cell(&mut self, name: &str) -> &mut Self {
+        self.record(record::BGNSTR, record::INT16, &[0; 24]).string(record::STRNAME, name)
+    }
+
+    pub fn end_cell(&mut self) -> &mut Self {
+        self.record(record::ENDSTR, record::NO_DATA, &[])
+    }
+
+    pub fn end_element(&mut self) -> &mut Self {
+        self.record(record::ENDEL, record::NO_DATA, &[])
+    }
+
+    pub fn boundary(&mut self, layer: i16, datatype: i16, points: &[[i32; 2]]) -> &mut Self {
+        self.record(record::BOUNDARY, record::NO_DATA, &[])
+            .int16(record::LAYER, &[layer])
+            .int16(record::DATATYPE, &[datatype])
+            .xy(points)
+            .end_element()
+    }
+
+    // A BOUNDARY carrying PROPATTR/PROPVALUE pairs
+    pub fn boundary_with_properties(
+        &mut self,
+        layer: i16,
+        datatype: i16,
+        points: &[[i32; 2]],
+        properties: &[(i16, &str)],
+    ) -> &mut Self {
+        self.record(record::BOUNDARY, record::NO_DATA, &[])
+            .int16(record::LAYER, &[layer])
+            .int16(record::DATATYPE, &[datatype])
+            .xy(points);
+        for (attribute, value) in properties {
+            self.int16(record::PROPATTR, &[*attribute]).string(record::PROPVALUE, value);
+        }
+        self.end_element()
+    }
+
+    pub fn path(&mut self, layer: i16, datatype: i16, pathtype: i16, width: i32, points: &[[i32; 2]]) -> &mut Self {
+        self.record(record::PATH, record::NO_DATA, &[])
+            .int16(record::LAYER, &[layer])
+            .int16(record::DATATYPE, &[datatype])
+            .int16(record::PATHTYPE, &[pathtype])
+            .int32(record::WIDTH, &[width])
+            .xy(points)
+            .end_element()
+    }
+
+    pub fn box_element(&mut self, layer: i16, boxtype: i16, points: &[[i32; 2]]) -> &mut Self {
+        self.record(record::BOX, record::NO_DATA, &[])
+            .int16(record::LAYER, &[layer])
+            .int16(record::BOXTYPE, &[boxtype])
+            .xy(points)
+            .end_element()
+    }
+
+    pub fn node(&mut self, layer: i16, nodetype: i16, points: &[[i32; 2]]) -> &mut Self {
+        self.record(record::NODE, record::NO_DATA, &[])
+            .int16(record::LAYER, &[layer])
+            .int16(record::NODETYPE, &[nodetype])
+            .xy(points)
+            .end_element()
+    }
+
+    pub fn text(&mut self, layer: i16, texttype: i16, position: [i32; 2], text: &str) -> &mut Self {
+        self.record(record::TEXT, record::NO_DATA, &[])
+            .int16(record::LAYER, &[layer])
+            .int16(record::TEXTTYPE, &[texttype])
+            .xy(&[position])
+            .string(record::STRING, text)
+            .end_element()
+    }
+
+    // STRANS/MAG/ANGLE for a reference given as (reflect_x, angle, mag)
+    fn strans(&mut self, strans: Option<(bool, f64, f64)>) -> &mut Self {
+        if let Some((reflect_x, angle, mag)) = strans {
+            let flags: u16 = if reflect_x { 0x8000 } else { 0 };
+            self.record(record::STRANS, record::BIT_ARRAY, &flags.to_be_bytes())
+                .reals(record::MAG, &[mag])
+                .reals(record::ANGLE, &[angle]);
+        }
+        self
+    }
+
+    pub fn sref(&mut self, cell: &str, origin: [i32; 2], strans: Option<(bool, f64, f64)>) -> &mut Self {
+        self.record(record::SREF, record::NO_DATA, &[]).string(record::SNAME, cell);
+        self.strans(strans).xy(&[origin]).end_element()
+    }
+
+    pub fn aref(
+        &mut self,
+        cell: &str,
+        origin: [i32; 2],
+        columns: i16,
+        rows: i16,
+        column_point: [i32; 2],
+        row_point: [i32; 2],
+    ) -> &mut Self {
+        self.record(record::AREF, record::NO_DATA, &[])
+            .string(record::SNAME, cell)
+            .int16(record::COLROW, &[columns, rows])
+            .xy(&[origin, column_point, row_point])
+            .end_element()
+    }
+
+    // Append ENDLIB and return the finished stream
+    pub fn finish(&mut self) -> Vec<u8> {
+        self.record(record::ENDLIB, record::NO_DATA, &[]);
+        std::mem::take(&mut self.bytes)
+    }
+}
+
+// A library of `count` large boundaries, each a 8000-vertex staircase taking up
+// a whole 64 KB XY record, so the file is about `count` × 64 KB
+pub fn large_boundary_library(count: usize) -> Vec<u8> {
+    let mut gds = GdsBuilder::library("LARGE");
+    gds.begin_cell("TOP");
+    for i in 0..count as i32 {
+        let mut points: Vec<[i32; 2]> =
+            (0..7998).map(|step| [i * 10 + step / 2 + step % 2, i * 10 + step / 2]).collect();
+        points.push([i * 10, i * 10 + 4000]);
+        points.push(points[0]);
+        gds.boundary(1, 0, &points);
+    }
+    gds.end_cell();
+    gds.finish()
+}
+
+// A fill-heavy library, like the dummy metal fill of a finished chip: one cell
+// holding `count` 1 µm squares on layer 1, in rows of 1000 at a 2 µm pitch
+pub fn fill_library(count: usize) -> Vec<u8> {
+    let mut gds = GdsBuilder::library("FILL");
+    gds.begin_cell("FILL");
+    for i in 0..count as i32 {
+        let (x, y) = (i % 1000 * 2000, i / 1000 * 2000);
+        gds.boundary(1, 0, &[[x, y], [x + 1000, y], [x + 1000, y + 1000], [x, y + 1000], [x, y]]);
+    }
+    gds.end_cell();
+    gds.finish()
+}
@@ -0,0 +1,621 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use super::transform::{instance_transforms, Transform};
+use super::cache::ParseCache;
+use super::units::{CoordConvention, CoordMapping};
+use crate::error::AppError;
+use crate::geometry::path::path_outline;
+use crate::geometry::simplify::simplify_polygons;
+use crate::perf;
+use super::{CellReference, GdsCell, GdsElement, GdsError, GdsLibrary, Strans};
+
+// Deepest reference nesting followed before giving up, unless the
+// `max_hierarchy_depth` setting says otherwise. Real designs rarely exceed a
+// couple dozen levels; anything deeper is almost certainly a cycle.
+pub const MAX_HIERARCHY_DEPTH: usize = 64;
+
+// Largest `max_hierarchy_depth` accepted. The walks keep their own stacks, so
+// this only bounds their memory, not the thread's stack.
+pub const HIERARCHY_DEPTH_LIMIT: usize = 10_000;
+
+// Nesting followed by every hierarchy walk, from the `max_hierarchy_depth`
+// setting. `MAX_HIERARCHY_DEPTH` until the settings are loaded.
+static MAX_DEPTH: AtomicUsize = AtomicUsize::new(MAX_HIERARCHY_DEPTH);
+
+pub(crate) fn set_max_hierarchy_depth(max_depth: usize) {
+    MAX_DEPTH.store(max_depth, Ordering::Relaxed);
+}
+
+pub fn max_hierarchy_depth() -> usize {
+    MAX_DEPTH.load(Ordering::Relaxed)
+}
+
+// What a walk does after entering a cell: go on into its references, leave
+// them out, or end the whole walk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Walk {
+    Enter,
+    Skip,
+    Stop,
+}
+
+// Walks a cell hierarchy, calling a visitor for every element with the transform
+// that maps it into the top cell's coordinates
+pub struct Flattener<'a> {
+    cells: HashMap<&'a str, &'a GdsCell>,
+    max_depth: usize,
+}
+
+// A cell being walked: its transform, and the child and placements of the
+// reference currently being followed
+struct Frame<'a> {
+    cell: &'a GdsCell,
+    transform: Transform,
+    next_reference: usize,
+    child: Option<(&'a GdsCell, &'a Strans)>,
+    instances: Box<dyn Iterator<Item = Transform> + 'a>,
+}
+
+impl<'a> Flattener<'a> {
+    // A flattener following references as deep as the `max_hierarchy_depth`
+    // setting allows
+    pub fn new(library: &'a GdsLibrary) -> Self {
+        Self::with_max_depth(library, max_hierarchy_depth())
+    }
+
+    pub fn with_max_depth(library: &'a GdsLibrary, max_depth: usize) -> Self {
+        Flattener {
+            cells: library.cells.iter().map(|cell| (cell.name.as_str(), cell)).collect(),
+            max_depth,
+        }
+    }
+
+    // Visit every element reachable from `cell`, depth first in file order.
+    // References to cells missing from the library are skipped, and nesting
+    // deeper than the maximum depth, e.g. a reference cycle, fails with
+    // `HierarchyTooDeep`.
+    pub fn visit<F>(&self, cell: &str, visitor: &mut F) -> Result<(), GdsError>
+    where
+        F: FnMut(&'a GdsElement, &Transform),
+    {
+        self.visit_while(cell, &mut |element, transform| {
+            visitor(element, transform);
+            true
+        })
+    }
+
+    // `visit`, ending the walk as soon as `visitor` returns false
+    pub fn visit_while<F>(&self, cell: &str, visitor: &mut F) -> Result<(), GdsError>
+    where
+        F: FnMut(&'a GdsElement, &Transform) -> bool,
+    {
+        self.walk(cell, |_| true, &mut |path, transform| {
+            let Some(cell) = path.last() else {
+                return Walk::Enter;
+            };
+            if cell.elements.iter().all(|element| visitor(element, transform)) {
+                Walk::Enter
+            } else {
+                Walk::Stop
+            }
+        })
+    }
+
+    // Enter every placement of every cell reachable from `cell`, depth first
+    // in file order and each array position separately, calling `visitor` with
+    // the cells from `cell` down to the entered one and the transform placing
+    // it. References are only followed into cells `follow` accepts, checked
+    // once per reference rather than per placement. The walk keeps its own
+    // stack rather than recursing, so no file can overflow the thread's stack.
+    pub fn walk<F>(
+        &self,
+        cell: &str,
+        follow: impl Fn(&'a GdsCell) -> bool,
+        visitor: &mut F,
+    ) -> Result<(), GdsError>
+    where
+        F: FnMut(&[&'a GdsCell], &Transform) -> Walk,
+    {
+        let root = *self
+            .cells
+            .get(cell)
+            .ok_or_else(|| GdsError::CellNotFound { cell: cell.to_string() })?;
+        let frame = |cell: &'a GdsCell, transform: Transform| Frame {
+            cell,
+            transform,
+            next_reference: 0,
+            child: None,
+            instances: Box::new(std::iter::empty()),
+        };
+
+        let mut path = vec![root];
+        let mut stack = Vec::new();
+        match visitor(&path, &Transform::IDENTITY) {
+            Walk::Enter => stack.push(frame(root, Transform::IDENTITY)),
+            Walk::Skip | Walk::Stop => return Ok(()),
+        }
+        loop {
+            let depth = stack.len();
+            let Some(top) = stack.last_mut() else {
+                return Ok(());
+            };
+            if let (Some((child, strans)), Some(instance)) = (top.child, top.instances.next()) {
+                if depth > self.max_depth {
+                    return Err(GdsError::HierarchyTooDeep {
+                        cell: child.name.clone(),
+                        max_depth: self.max_depth,
+                    });
+                }
+                let transform = top.transform.place(&instance, strans);
+                path.push(child);
+                match visitor(&path, &transform) {
+                    Walk::Enter => stack.push(frame(child, transform)),
+                    Walk::Skip => {
+                        path.pop();
+                    }
+                    Walk::Stop => return Ok(()),
+                }
+                continue;
+            }
+            let Some(reference) = top.cell.references.get(top.next_reference) else {
+                stack.pop();
+                path.pop();
+                continue;
+            };
+            top.next_reference += 1;
+            top.child = self
+                .cells
+                .get(reference.cell.as_str())
+                .filter(|&&child| follow(child))
+                .map(|&child| (child, &reference.strans));
+            top.instances = Box::new(instance_transforms(reference));
+        }
+    }
+}
+
+// Fold a value over every cell reachable from `root`, bottom up: each cell
+// starts from `own` of it, then `add` takes in the value of each of its
+// references in file order. Values end up in `memo`, so a shared cell is only
+// folded once, there or in an earlier call. References to cells missing from
+// the library are skipped. Works without recursion; a reference cycle fails
+// with `CyclicReference` and nesting deeper than `max_depth` with
+// `HierarchyTooDeep`.
+pub(crate) fn fold_cells<'a, T: Clone>(
+    cells: &HashMap<&'a str, &'a GdsCell>,
+    memo: &mut HashMap<&'a str, T>,
+    root: &'a GdsCell,
+    max_depth: usize,
+    own: impl Fn(&'a GdsCell) -> T,
+    add: impl Fn(&mut T, &'a CellReference, &T),
+) -> Result<T, GdsError> {
+    if let Some(value) = memo.get(root.name.as_str()) {
+        return Ok(value.clone());
+    }
+    // Cells whose references are still being taken in, with their value so far
+    // and the next reference
+    let mut stack: Vec<(&'a GdsCell, T, usize)> = vec![(root, own(root), 0)];
+    while let Some((cell, value, next)) = stack.last_mut() {
+        let cell = *cell;
+        match cell.references.get(*next) {
+            Some(reference) => {
+                let Some(&child) = cells.get(reference.cell.as_str()) else {
+                    *next += 1;
+                    continue;
+                };
+                if let Some(child_value) = memo.get(child.name.as_str()) {
+                    add(value, reference, child_value);
+                    *next += 1;
+                    continue;
+                }
+                if stack.len() > max_depth {
+                    return Err(GdsError::HierarchyTooDeep { cell: child.name.clone(), max_depth });
+                }
+                if stack.iter().any(|(cell, ..)| cell.name == child.name) {
+                    return Err(GdsError::CyclicReference { cell: child.name.clone() });
+                }
+                stack.push((child, own(child), 0));
+            }
+            None => {
+                let Some((cell, value, _)) = stack.pop() else {
+                    break;
+                };
+                if let Some((parent, parent_value, next)) = stack.last_mut() {
+                    add(parent_value, &parent.references[*next], &value);
+                    *next += 1;
+                }
+                memo.insert(cell.name.as_str(), value);
+            }
+        }
+    }
+    Ok(memo[root.name.as_str()].clone())
+}
+
+// Which kind of element a flattened polygon came from. BOX elements stay apart
+// from boundaries, as some flows give them their own meaning (e.g. wells). A
+// NODE has no area; its points are only returned as markers when asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PolygonKind {
+    Boundary,
+    Path,
+    Box,
+    Node,
+}
+
+impl PolygonKind {
+    // `None` for TEXT, which has no outline
+    pub fn of(element: &GdsElement) -> Option<Self> {
+        match element {
+            GdsElement::Boundary { .. } | GdsElement::Rect { .. } => Some(PolygonKind::Boundary),
+            GdsElement::Path { .. } => Some(PolygonKind::Path),
+            GdsElement::Box { .. } => Some(PolygonKind::Box),
+            GdsElement::Node { .. } => Some(PolygonKind::Node),
+            GdsElement::Text { .. } => None,
+        }
+    }
+}
+
+// A flattened polygon tagged with the kind of element it came from
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct LayerPolygon {
+    pub kind: PolygonKind,
+    pub points: Vec<[f64; 2]>,
+}
+
+// Filled outline of a BOUNDARY, PATH or BOX element in the coordinates given by
+// `transform`, or `None` for TEXT, NODE and paths without area
+pub fn element_outline(element: &GdsElement, transform: &Transform) -> Option<Vec<[f64; 2]>> {
+    match element {
+        GdsElement::Boundary { points, .. } | GdsElement::Box { points, .. } => {
+            Some(points.iter().map(|point| transform.apply_point(*point)).collect())
+        }
+        GdsElement::Rect { rect, .. } => {
+            Some(rect.points().into_iter().map(|point| transform.apply_point(point)).collect())
+        }
+        GdsElement::Path { pathtype, width, begin_extension, end_extension, points, .. } => {
+            let centerline: Vec<[f64; 2]> =
+                points.iter().map(|p| [p[0] as f64, p[1] as f64]).collect();
+            let outline = path_outline(
+                &centerline,
+                *width as f64,
+                *pathtype,
+                *begin_extension as f64,
+                *end_extension as f64,
+            );
+            (!outline.is_empty())
+                .then(|| outline.iter().map(|[x, y]| transform.apply(*x, *y)).collect())
+        }
+        GdsElement::Text { .. } | GdsElement::Node { .. } => None,
+    }
+}
+
+// All BOUNDARY polygons, PATH outlines and BOXes on (layer, datatype) reachable
+// from `cell`, flattened, scaled by `scale` and tagged with their kind
+pub fn tagged_layer_polygons(
+    library: &GdsLibrary,
+    cell: &str,
+    layer: u16,
+    datatype: u16,
+    scale: f64,
+) -> Result<Vec<LayerPolygon>, GdsError> {
+    let mut polygons = Vec::new();
+    Flattener::new(library).visit(cell, &mut |element, transform| {
+        if element.layer_key() != (layer, datatype) {
+            return;
+        }
+        let Some(kind) = PolygonKind::of(element) else {
+            return;
+        };
+        let Some(outline) = element_outline(element, transform) else {
+            return;
+        };
+        let points = outline.into_iter().map(|[x, y]| [x * scale, y * scale]).collect();
+        polygons.push(LayerPolygon { kind, points });
+    })?;
+    Ok(polygons)
+}
+
+// `tagged_layer_polygons` without the tags
+pub fn layer_polygons(
+    library: &GdsLibrary,
+    cell: &str,
+    layer: u16,
+    datatype: u16,
+    scale: f64,
+) -> Result<Vec<Vec<[f64; 2]>>, GdsError> {
+    let polygons = tagged_layer_polygons(library, cell, layer, datatype, scale)?;
+    Ok(polygons.into_iter().map(|polygon| polygon.points).collect())
+}
+
+// Flattened polygons keyed by (layer, datatype)
+pub type LayerPolygons = BTreeMap<(u16, u16), Vec<Vec<[f64; 2]>>>;
+
+// All BOUNDARY polygons, PATH outlines and BOXes reachable from `cell`,
+// flattened and grouped by (layer, datatype), in database units
+pub fn cell_polygons(library: &GdsLibrary, cell: &str) -> Result<LayerPolygons, GdsError> {
+    let mut layers = LayerPolygons::new();
+    Flattener::new(library).visit(cell, &mut |element, transform| {
+        if let Some(outline) = element_outline(element, transform) {
+            layers.entry(element.layer_key()).or_default().push(outline);
+        }
+    })?;
+    Ok(layers)
+}
+
+// Fail with `InvalidArgument` unless a simplify tolerance is usable
+pub fn check_simplify_tolerance(simplify_tolerance: Option<f64>) -> Result<(), AppError> {
+    match simplify_tolerance {
+        Some(tolerance) if !(tolerance >= 0.0 && tolerance.is_finite()) => {
+            Err(AppError::invalid_argument(format!(
+                "Simplify tolerance must be a non-negative number, got {}",
+                tolerance
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Fail with `TooLarge` when a command would return more than `max_polygons`
+// polygons, so the caller can fall back to tiles or simplification
+pub fn check_polygon_count(count: usize, max_polygons: Option<usize>) -> Result<(), AppError> {
+    match max_polygons {
+        Some(limit) if count > limit => Err(AppError::TooLarge { count, limit }),
+        _ => Ok(()),
+    }
+}
+
+// The polygons on (layer, datatype) reachable from `cell`, flattened, and
+// separately the points of its NODEs, one marker set per element, in database
+// units. Only `kinds` are included if given, and no NODEs otherwise. More than
+// `max_polygons` of them together fails with `TooLarge` as soon as the walk
+// gets there, so the rest of the hierarchy isn't flattened just to be refused.
+fn limited_layer_polygons(
+    library: &GdsLibrary,
+    cell: &str,
+    (layer, datatype): (u16, u16),
+    kinds: Option<&[PolygonKind]>,
+    max_polygons: Option<usize>,
+) -> Result<(Vec<LayerPolygon>, Vec<LayerPolygon>), AppError> {
+    let wanted = |kind: PolygonKind| match kinds {
+        Some(kinds) => kinds.contains(&kind),
+        None => kind != PolygonKind::Node,
+    };
+    let (mut polygons, mut nodes) = (Vec::new(), Vec::new());
+    Flattener::new(library).visit_while(cell, &mut |element, transform| {
+        if element.layer_key() != (layer, datatype) {
+            return true;
+        }
+        let Some(kind) = PolygonKind::of(element).filter(|&kind| wanted(kind)) else {
+            return true;
+        };
+        if let GdsElement::Node { points, .. } = element {
+            let points = points.iter().map(|point| transform.apply_point(*point)).collect();
+            nodes.push(LayerPolygon { kind, points });
+        } else if let Some(points) = element_outline(element, transform) {
+            polygons.push(LayerPolygon { kind, points });
+        }
+        max_polygons.map_or(true, |limit| polygons.len() + nodes.len() <= limit)
+    })?;
+    check_polygon_count(polygons.len() + nodes.len(), max_polygons)?;
+    Ok((polygons, nodes))
+}
+
+// The polygons `get_layer_polygons` returns: one layer of a cell, only of some
+// `kinds` if given, in the output coordinates of `coord_convention` and
+// decimated by `simplify_tolerance` in those units. NODE markers are only
+// included when `kinds` lists them, after the polygons and never simplified.
+// More than `max_polygons` fails with `TooLarge` without flattening the rest.
+#[allow(clippy::too_many_arguments)]
+pub fn output_layer_polygons(
+    library: &GdsLibrary,
+    cell: &str,
+    layer: u16,
+    datatype: u16,
+    kinds: Option<&[PolygonKind]>,
+    simplify_tolerance: Option<f64>,
+    coord_convention: Option<&CoordConvention>,
+    max_polygons: Option<usize>,
+) -> Result<Vec<LayerPolygon>, AppError> {
+    let mapping = CoordMapping::new(coord_convention, &library.units);
+    let (polygons, nodes) =
+        limited_layer_polygons(library, cell, (layer, datatype), kinds, max_polygons)?;
+    let mut polygons: Vec<LayerPolygon> = polygons
+        .into_iter()
+        .map(|polygon| LayerPolygon { points: mapping.ring(&polygon.points), ..polygon })
+        .collect();
+    if let Some(tolerance) = simplify_tolerance {
+        let rings: Vec<Vec<[f64; 2]>> =
+            polygons.iter_mut().map(|polygon| std::mem::take(&mut polygon.points)).collect();
+        for (polygon, ring) in polygons.iter_mut().zip(simplify_polygons(&rings, tolerance)) {
+            polygon.points = ring;
+        }
+    }
+    polygons.extend(
+        nodes.into_iter().map(|node| LayerPolygon { points: mapping.ring(&node.points), ..node }),
+    );
+    Ok(polygons)
+}
+
+// Command to get the flattened boundary polygons, path outlines and boxes of one
+// layer of a cell, tagged with their kind and optionally only of some `kinds`.
+// Coordinates are in user units (e.g. µm) unless `coord_convention` asks
+// otherwise. With `simplify_tolerance`, given in those output units, outlines
+// are decimated for low-zoom previews. More than `max_polygons` fails with
+// `TooLarge`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn get_layer_polygons(
+    path: String,
+    cell: String,
+    layer: u16,
+    datatype: u16,
+    kinds: Option<Vec<PolygonKind>>,
+    simplify_tolerance: Option<f64>,
+    coord_convention: Option<CoordConvention>,
+    max_polygons: Option<usize>,
+    app: tauri::AppHandle,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<LayerPolygon>, AppError> {
+    check_simplify_tolerance(simplify_tolerance)?;
+    let started = Instant::now();
+    let library = cache.library(&path)?;
+    let polygons = output_layer_polygons(
+        &library,
+        &cell,
+        layer,
+        datatype,
+        kinds.as_deref(),
+        simplify_tolerance,
+        coord_convention.as_ref(),
+        max_polygons,
+    )?;
+    perf::report(&app, "get_layer_polygons", &path, started);
+    Ok(polygons)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn flattens_transformed_instances() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("LEAF")
+            .boundary(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 0]])
+            .boundary(2, 0, &[[0, 0], [5, 0], [5, 5], [0, 0]])
+            .end_cell();
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &[[-1, -1], [1, -1], [1, 1], [-1, -1]])
+            .sref("LEAF", [100, 0], Some((true, 0.0, 1.0)))
+            .aref("LEAF", [0, 100], 2, 1, [40, 100], [0, 110])
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let polygons = layer_polygons(&library, "TOP", 1, 0, 1.0).unwrap();
+        assert_eq!(polygons.len(), 4);
+        // Reflected about the X axis, then moved
+        assert_eq!(polygons[1], vec![[100.0, 0.0], [110.0, 0.0], [110.0, -10.0], [100.0, 0.0]]);
+        // Second array column is 20 units to the right
+        assert_eq!(polygons[3][1], [30.0, 100.0]);
+    }
+
+    #[test]
+    fn absolute_angle_ignores_the_parent_rotation() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("LEAF").boundary(1, 0, &[[0, 0], [2, 0], [2, 1], [0, 0]]).end_cell();
+        gds.begin_cell("MID").sref("LEAF", [10, 0], Some((false, 0.0, 1.0))).end_cell();
+        gds.begin_cell("TOP").sref("MID", [0, 0], Some((false, 90.0, 1.0))).end_cell();
+        let mut library = parse_library(&gds.finish()).unwrap();
+
+        // Without ABS_ANGLE the leaf turns with MID
+        let polygons = layer_polygons(&library, "TOP", 1, 0, 1.0).unwrap();
+        assert_eq!(polygons[0], vec![[0.0, 10.0], [0.0, 12.0], [-1.0, 12.0], [0.0, 10.0]]);
+
+        library.cells[1].references[0].strans.abs_angle = true;
+        let polygons = layer_polygons(&library, "TOP", 1, 0, 1.0).unwrap();
+        // Only its position follows MID's rotation
+        assert_eq!(polygons[0], vec![[0.0, 10.0], [2.0, 10.0], [2.0, 11.0], [0.0, 10.0]]);
+    }
+
+    #[test]
+    fn self_reference_fails_instead_of_overflowing() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("LOOP")
+            .boundary(1, 0, &[[0, 0], [1, 0], [1, 1], [0, 0]])
+            .sref("LOOP", [0, 0], None)
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        assert!(matches!(
+            layer_polygons(&library, "LOOP", 1, 0, 1.0),
+            Err(GdsError::HierarchyTooDeep { .. })
+        ));
+    }
+
+    #[test]
+    fn deep_chains_stop_at_the_configured_depth() {
+        // C0 references C1, which references C2, and so on down to C200
+        let depth = 200;
+        let mut gds = GdsBuilder::library("LIB");
+        for level in 0..depth {
+            gds.begin_cell(&format!("C{}", level))
+                .boundary(1, 0, &[[0, 0], [1, 0], [1, 1], [0, 0]])
+                .sref(&format!("C{}", level + 1), [1, 0], None)
+                .end_cell();
+        }
+        gds.begin_cell(&format!("C{}", depth)).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let count = |flattener: Flattener| {
+            let mut count = 0;
+            flattener.visit("C0", &mut |_, _| count += 1).map(|()| count)
+        };
+        assert_eq!(
+            count(Flattener::with_max_depth(&library, MAX_HIERARCHY_DEPTH)),
+            Err(GdsError::HierarchyTooDeep { cell: "C65".to_string(), max_depth: 64 })
+        );
+        assert_eq!(count(Flattener::with_max_depth(&library, depth)), Ok(depth));
+    }
+
+    #[test]
+    fn paths_are_returned_as_filled_outlines() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("WIRE").path(3, 0, 2, 20, &[[0, 0], [100, 0]]).end_cell();
+        gds.begin_cell("TOP").sref("WIRE", [0, 0], Some((false, 90.0, 1.0))).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let polygons = layer_polygons(&library, "TOP", 3, 0, 1.0).unwrap();
+        assert_eq!(polygons.len(), 1);
+        // Square ends extend the 100-long wire by 10 on each side, then it is
+        // rotated to run along Y
+        assert_eq!(crate::geometry::signed_area(&polygons[0]), 120.0 * 20.0);
+        assert!(polygons[0].iter().all(|p| (-10.0..=10.0).contains(&p[0])));
+        assert!(polygons[0].contains(&[10.0, -10.0]) && polygons[0].contains(&[-10.0, 110.0]));
+    }
+
+    #[test]
+    fn boxes_are_tagged_apart_from_boundaries() {
+        let square = [[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .boundary(4, 0, &square)
+            .box_element(4, 0, &square)
+            .path(4, 0, 0, 2, &[[0, 0], [10, 0]])
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let polygons = tagged_layer_polygons(&library, "TOP", 4, 0, 1.0).unwrap();
+        let kinds: Vec<PolygonKind> = polygons.iter().map(|polygon| polygon.kind).collect();
+        assert_eq!(kinds, vec![PolygonKind::Boundary, PolygonKind::Box, PolygonKind::Path]);
+        assert_eq!(polygons[1].points, polygons[0].points);
+    }
+
+    #[test]
+    fn polygon_limit_stops_the_walk_early() {
+        let square = [[0, 0], [1, 0], [1, 1], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &square)
+            .node(1, 0, &[[5, 5]])
+            .boundary(1, 0, &square)
+            .sref("LOOP", [0, 0], None)
+            .end_cell();
+        // Only reached, and only failing, if the walk goes on past the limit
+        gds.begin_cell("LOOP").sref("LOOP", [0, 0], None).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let polygons = |kinds: Option<&[PolygonKind]>, max_polygons| {
+            output_layer_polygons(&library, "TOP", 1, 0, kinds, None, None, max_polygons)
+        };
+        assert_eq!(polygons(None, Some(1)), Err(AppError::TooLarge { count: 2, limit: 1 }));
+        let kinds = [PolygonKind::Boundary, PolygonKind::Node];
+        assert_eq!(polygons(Some(&kinds), Some(2)), Err(AppError::TooLarge { count: 3, limit: 2 }));
+        assert!(matches!(polygons(None, Some(2)), Err(AppError::InvalidHierarchy { .. })));
+    }
+}
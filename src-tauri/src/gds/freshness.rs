@@ -0,0 +1,106 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::cache::ParseCache;
+use super::dates::{dates_from_record, iso_date, unix_seconds};
+use super::record::{self, RecordReader};
+use super::writer::civil_time;
+use super::GdsError;
+use crate::error::AppError;
+
+// How far apart the two times may be and still match. GDSII dates are local
+// times without a zone, so a freshly written file can be up to 14 hours off
+// UTC; the rest covers a tool taking a while to write a large file.
+const FRESHNESS_TOLERANCE_SECONDS: i64 = 15 * 3600;
+
+// The file's modification time on disk next to the one its BGNLIB records.
+// `fs_mtime` is ISO-8601 UTC with a trailing `Z`, `gds_mtime` local time as in
+// `GdsDates`, and `mismatch` is set when they are further apart than
+// `FRESHNESS_TOLERANCE_SECONDS`.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct FileFreshness {
+    pub fs_mtime: String,
+    pub gds_mtime: Option<String>,
+    pub mismatch: bool,
+}
+
+// Compare `modified`, the file's modification time, with the BGNLIB date of
+// the stream `bytes`. A stream without a usable date, e.g. the all-zero one
+// many tools write, never mismatches.
+pub fn freshness(bytes: &[u8], modified: SystemTime) -> Result<FileFreshness, GdsError> {
+    let mut records = RecordReader::new(bytes);
+    match records.next() {
+        Some(Ok(first)) if first.record_type == record::HEADER => {}
+        _ => {
+            return Err(GdsError::InvalidHeader {
+                message: "file does not start with a HEADER record".to_string(),
+            })
+        }
+    }
+    let mut gds_mtime = None;
+    for record in records {
+        let record = record.map_err(|error| error.with_context(bytes))?;
+        match record.record_type {
+            record::BGNLIB => {
+                gds_mtime = dates_from_record(&record).modified;
+                break;
+            }
+            record::BGNSTR | record::ENDLIB => break,
+            _ => {}
+        }
+    }
+    let fs_seconds = modified.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+    let fs_mtime = iso_date(&civil_time(fs_seconds)).unwrap_or_default() + "Z";
+    let mismatch = gds_mtime.as_deref().and_then(unix_seconds).is_some_and(|gds_seconds| {
+        (fs_seconds as i64 - gds_seconds).abs() > FRESHNESS_TOLERANCE_SECONDS
+    });
+    Ok(FileFreshness { fs_mtime, gds_mtime, mismatch })
+}
+
+// Command to flag a GDSII file whose modification time on disk is far from the
+// date its library records, e.g. a copy that was touched but not regenerated
+#[tauri::command]
+pub async fn check_file_freshness(
+    path: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<FileFreshness, AppError> {
+    let bytes = cache.bytes(&path)?;
+    let modified = std::fs::metadata(&path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| AppError::for_path(&path, e))?;
+    Ok(freshness(&bytes, modified)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use std::time::Duration;
+
+    fn stream_dated(date: [i16; 6]) -> Vec<u8> {
+        let mut gds = GdsBuilder::empty();
+        let dates: Vec<i16> = date.iter().chain(&date).copied().collect();
+        gds.int16(record::HEADER, &[600]).int16(record::BGNLIB, &dates);
+        gds.string(record::LIBNAME, "LIB").reals(record::UNITS, &[0.001, 1e-9]);
+        gds.finish()
+    }
+
+    #[test]
+    fn copies_far_from_their_recorded_date_mismatch() {
+        // 2024-03-05T14:07:00Z
+        let modified = UNIX_EPOCH + Duration::from_secs(1_709_647_620);
+        let fresh = freshness(&stream_dated([2024, 3, 5, 14, 7, 0]), modified).unwrap();
+        assert_eq!(fresh.fs_mtime, "2024-03-05T14:07:00Z");
+        assert_eq!(fresh.gds_mtime.as_deref(), Some("2024-03-05T14:07:00"));
+        assert!(!fresh.mismatch);
+        // Written in a zone 9 hours ahead of UTC
+        assert!(!freshness(&stream_dated([2024, 3, 5, 23, 7, 0]), modified).unwrap().mismatch);
+
+        let stale = freshness(&stream_dated([2021, 11, 30, 9, 0, 0]), modified).unwrap();
+        assert!(stale.mismatch);
+        // The fixture's all-zero date
+        let undated = freshness(&GdsBuilder::library("LIB").finish(), modified).unwrap();
+        assert_eq!((undated.gds_mtime, undated.mismatch), (None, false));
+    }
+}
@@ -0,0 +1,222 @@
+//! Property test feeding malformed streams to every reader of raw GDSII bytes,
+//! and to the library walks that run right after a parse, checking none of
+//! them panics. Streams are the fixture below and the files in `fuzz/corpus`,
+//! mutated at random from a fixed seed. Set `GDSJAM_FUZZ_ITERATIONS` (and
+//! `GDSJAM_FUZZ_SEED`) to run longer, and save any stream that panics to the
+//! corpus once it is fixed.
+
+use std::io::Cursor;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use super::bbox::{element_bbox, BboxCalculator};
+use super::catch_reader_panic;
+use super::dump::dump_records;
+use super::fixtures::GdsBuilder;
+use super::flatten::element_outline;
+use super::freshness::freshness;
+use super::hierarchy::{cell_summaries, hierarchy_tree, orphan_cells};
+use super::incremental::{index_cells, parse_reusing};
+use super::layers::collect_layers;
+use super::parser::{header_version, parse_library, parse_library_with_progress, ParseMode};
+use super::record::{self, RecordReader};
+use super::stats::{scan_histogram, scan_stats};
+use super::transform::Transform;
+use super::units::scan_units;
+use super::validate::validate;
+use super::writer::write_library;
+use super::GdsError;
+
+// Mutated streams per run of the test suite
+const DEFAULT_ITERATIONS: u64 = 3000;
+
+const CORPUS_DIR: &str = "fuzz/corpus";
+
+// Record types worth putting in random records, every one the parser handles
+const RECORD_TYPES: &[u8] = &[
+    record::HEADER,
+    record::BGNLIB,
+    record::LIBNAME,
+    record::UNITS,
+    record::ENDLIB,
+    record::BGNSTR,
+    record::STRNAME,
+    record::ENDSTR,
+    record::BOUNDARY,
+    record::PATH,
+    record::SREF,
+    record::AREF,
+    record::TEXT,
+    record::LAYER,
+    record::DATATYPE,
+    record::WIDTH,
+    record::XY,
+    record::ENDEL,
+    record::SNAME,
+    record::COLROW,
+    record::NODE,
+    record::TEXTTYPE,
+    record::PRESENTATION,
+    record::STRING,
+    record::STRANS,
+    record::MAG,
+    record::ANGLE,
+    record::PATHTYPE,
+    record::NODETYPE,
+    record::PROPATTR,
+    record::PROPVALUE,
+    record::BOX,
+    record::BOXTYPE,
+    record::BGNEXTN,
+    record::ENDEXTN,
+];
+
+fn env_number(name: &str, default: u64) -> u64 {
+    std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+}
+
+// A library with one element of every kind, a transformed SREF, an AREF and a
+// reference to a missing cell
+fn fixture() -> Vec<u8> {
+    let mut gds = GdsBuilder::library("LIB");
+    gds.begin_cell("LEAF")
+        .boundary(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]])
+        .path(2, 0, 4, 5, &[[0, 0], [20, 0]])
+        .box_element(3, 0, &[[0, 0], [5, 0], [5, 5], [0, 5], [0, 0]])
+        .node(4, 0, &[[1, 1]])
+        .text(5, 0, [1, 1], "A")
+        .end_cell();
+    gds.begin_cell("TOP")
+        .sref("LEAF", [0, 0], Some((true, 90.0, 2.0)))
+        .aref("LEAF", [0, 0], 2, 3, [40, 0], [0, 60])
+        .sref("MISSING", [0, 0], None)
+        .end_cell();
+    gds.finish()
+}
+
+fn corpus() -> Vec<Vec<u8>> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(CORPUS_DIR);
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "gds"))
+        .collect();
+    paths.sort();
+    paths.iter().map(|path| std::fs::read(path).unwrap()).collect()
+}
+
+// Everything that reads `bytes`, then the walks over the library they parse
+// to. Results are dropped; only panics matter here.
+fn read_everything(bytes: &[u8]) {
+    let _ = RecordReader::new(bytes).collect::<Vec<_>>();
+    let _ = validate(bytes);
+    let _ = scan_stats(bytes);
+    let _ = scan_histogram(bytes, "TOP");
+    let _ = scan_units(bytes);
+    let _ = header_version(bytes);
+    let _ = freshness(bytes, UNIX_EPOCH);
+    let _ = dump_records(&mut Cursor::new(bytes), 1000);
+    let mode = ParseMode::HierarchyOnly;
+    let _ = parse_library_with_progress(bytes, mode, &mut |_| true, &mut |_| {});
+    let Ok(library) = parse_library(bytes) else {
+        return;
+    };
+    if let Some(index) = index_cells(bytes) {
+        let _ = parse_reusing(bytes, Some((&library, &index)), &mut |_| true, &mut |_| {});
+    }
+    let _ = cell_summaries(&library);
+    let _ = hierarchy_tree(&library);
+    let _ = orphan_cells(&library);
+    let _ = collect_layers(&library);
+    let _ = write_library(&library);
+    let mut calculator = BboxCalculator::new(&library);
+    for cell in &library.cells {
+        let _ = calculator.cell_bbox(&cell.name);
+        for element in &cell.elements {
+            let _ = element_bbox(element);
+            let _ = element_outline(element, &Transform::IDENTITY);
+        }
+    }
+}
+
+// Damage `bytes` in one of a few ways: overwrite bytes, cut the stream short,
+// insert garbage, or rewrite the length, type or data type of a record
+fn mutate(rng: &mut fastrand::Rng, bytes: &mut Vec<u8>) {
+    let offsets: Vec<usize> =
+        RecordReader::new(bytes).filter_map(|record| Some(record.ok()?.offset as usize)).collect();
+    match rng.u8(0..5) {
+        0 => {
+            for _ in 0..rng.usize(1..8) {
+                let at = rng.usize(..bytes.len());
+                bytes[at] = rng.u8(..);
+            }
+        }
+        1 => bytes.truncate(rng.usize(..bytes.len())),
+        2 => {
+            let at = rng.usize(..=bytes.len());
+            let garbage: Vec<u8> = (0..rng.usize(1..16)).map(|_| rng.u8(..)).collect();
+            bytes.splice(at..at, garbage);
+        }
+        _ if offsets.is_empty() => bytes.clear(),
+        3 => {
+            let at = offsets[rng.usize(..offsets.len())];
+            let lengths = [0, 1, 2, 3, 4, 5, 0x8000, 0xffff, rng.u16(..)];
+            let length: u16 = lengths[rng.usize(..lengths.len())];
+            bytes[at..at + 2].copy_from_slice(&length.to_be_bytes());
+        }
+        _ => {
+            for _ in 0..rng.usize(1..4) {
+                let at = offsets[rng.usize(..offsets.len())];
+                if rng.bool() {
+                    bytes[at + 2] = RECORD_TYPES[rng.usize(..RECORD_TYPES.len())];
+                } else {
+                    bytes[at + 3] = rng.u8(0..8);
+                }
+            }
+        }
+    }
+}
+
+// A library of random but well-framed records, so the parser gets past the
+// record reader and into the elements
+fn random_records(rng: &mut fastrand::Rng) -> Vec<u8> {
+    let mut gds = GdsBuilder::library("LIB");
+    for _ in 0..rng.usize(1..40) {
+        let data: Vec<u8> = (0..rng.usize(0..12) * 2).map(|_| rng.u8(..)).collect();
+        gds.record(RECORD_TYPES[rng.usize(..RECORD_TYPES.len())], rng.u8(0..8), &data);
+    }
+    gds.finish()
+}
+
+#[test]
+fn corpus_streams_read_without_panicking() {
+    for bytes in corpus() {
+        read_everything(&bytes);
+    }
+}
+
+#[test]
+fn mutated_streams_read_without_panicking() {
+    let seeds: Vec<Vec<u8>> = std::iter::once(fixture()).chain(corpus()).collect();
+    let mut rng = fastrand::Rng::with_seed(env_number("GDSJAM_FUZZ_SEED", 1));
+    for iteration in 0..env_number("GDSJAM_FUZZ_ITERATIONS", DEFAULT_ITERATIONS) {
+        let bytes = if iteration % 5 == 4 {
+            random_records(&mut rng)
+        } else {
+            let mut bytes = seeds[rng.usize(..seeds.len())].clone();
+            mutate(&mut rng, &mut bytes);
+            bytes
+        };
+        let read = catch_reader_panic(|| {
+            read_everything(&bytes);
+            Ok(())
+        });
+        assert!(read.is_ok(), "iteration {} panicked on {:02x?}", iteration, bytes);
+    }
+}
+
+#[test]
+fn reader_panics_become_internal_errors() {
+    let error = catch_reader_panic::<(), _>(|| panic!("index out of bounds")).unwrap_err();
+    assert_eq!(error, GdsError::Internal { message: "index out of bounds".to_string() });
+}
@@ -0,0 +1,135 @@
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+// Every gzip member starts with these two bytes
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// Deflate can't shrink data by more than about this factor, which bounds a
+// trailer's size hint for files whose inflated size overflowed it
+const MAX_DEFLATE_RATIO: u64 = 1032;
+
+// Most a gzip file may inflate to. The inflated stream is held in memory
+// whole, so without a limit a file of a few MB could expand to fill it.
+pub const MAX_INFLATED_BYTES: u64 = 4 << 30;
+
+// Whether `path` is gzip-compressed: it starts with the gzip magic or, for a
+// file too short to tell, has a `.gz` extension
+pub fn is_gzip(file: &mut File, path: &str) -> std::io::Result<bool> {
+    let mut magic = [0u8; 2];
+    let read = file.read(&mut magic)?;
+    file.seek(SeekFrom::Start(0))?;
+    if read == magic.len() {
+        return Ok(magic == GZIP_MAGIC);
+    }
+    let extension = Path::new(path).extension().and_then(|extension| extension.to_str());
+    Ok(extension.is_some_and(|extension| extension.eq_ignore_ascii_case("gz")))
+}
+
+// Inflated size recorded in the gzip trailer, as a capacity hint. The trailer
+// only holds the size modulo 4 GiB (of the last member, for concatenated
+// ones), so the hint is never trusted beyond what the file could hold.
+fn inflated_len_hint(file: &mut File, compressed_len: u64) -> std::io::Result<usize> {
+    if compressed_len < 4 {
+        return Ok(0);
+    }
+    let mut trailer = [0u8; 4];
+    file.seek(SeekFrom::End(-4))?;
+    file.read_exact(&mut trailer)?;
+    file.seek(SeekFrom::Start(0))?;
+    let hint = u64::from(u32::from_le_bytes(trailer)).min(compressed_len * MAX_DEFLATE_RATIO);
+    Ok(usize::try_from(hint).unwrap_or(0))
+}
+
+// Inflates a gzip file as it is read, failing with `InvalidData` once it has
+// produced more than `limit` bytes
+pub struct Inflater {
+    decoder: MultiGzDecoder<File>,
+    inflated: u64,
+    limit: u64,
+}
+
+impl Read for Inflater {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.decoder.read(buffer)?;
+        self.inflated += read as u64;
+        if self.inflated > self.limit {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "Gzip stream inflates to more than {} MiB; decompress the file to open it",
+                    self.limit >> 20
+                ),
+            ));
+        }
+        Ok(read)
+    }
+}
+
+// A reader inflating a gzip file as it is read, so the compressed data is
+// never held in memory, and a hint of the inflated size. Inflating more than
+// `MAX_INFLATED_BYTES` fails.
+pub fn decoder(file: File) -> std::io::Result<(Inflater, usize)> {
+    decoder_with_limit(file, MAX_INFLATED_BYTES)
+}
+
+fn decoder_with_limit(mut file: File, limit: u64) -> std::io::Result<(Inflater, usize)> {
+    let compressed_len = file.metadata()?.len();
+    let hint = inflated_len_hint(&mut file, compressed_len)?;
+    let hint = hint.min(usize::try_from(limit).unwrap_or(usize::MAX));
+    Ok((Inflater { decoder: MultiGzDecoder::new(file), inflated: 0, limit }, hint))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::read_library;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    #[test]
+    fn gzipped_files_parse_like_plain_ones() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP").boundary(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 0]]).end_cell();
+        let contents = gds.finish();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&contents).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, bytes: &[u8]| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, bytes).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let plain = write("top.gds", &contents);
+        // Recognized by content, whatever the extension
+        let gzipped = write("top.gds.bin", &compressed);
+
+        let mut file = File::open(&gzipped).unwrap();
+        assert!(is_gzip(&mut file, &gzipped).unwrap());
+        assert_eq!(decoder(file).unwrap().1, contents.len());
+        assert_eq!(read_library(&gzipped).unwrap(), read_library(&plain).unwrap());
+
+        // Inflating past the limit fails rather than filling memory
+        let limit = contents.len() as u64 - 1;
+        let (mut inflater, hint) =
+            decoder_with_limit(File::open(&gzipped).unwrap(), limit).unwrap();
+        assert_eq!(hint as u64, limit);
+        let error = inflater.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        let (mut inflater, _) =
+            decoder_with_limit(File::open(&gzipped).unwrap(), limit + 1).unwrap();
+        assert_eq!(inflater.read_to_end(&mut Vec::new()).unwrap(), contents.len());
+
+        let mut file = File::open(&plain).unwrap();
+        assert!(!is_gzip(&mut file, &plain).unwrap());
+        // The check leaves the file at its start
+        let mut start = [0u8; 4];
+        file.read_exact(&mut start).unwrap();
+        assert_eq!(start, contents[..4]);
+    }
+}
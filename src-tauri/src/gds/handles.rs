@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use super::cache::cache_key;
+use super::mmap::{map_or_read_open, FileBytes};
+use super::GdsError;
+use crate::paths::refuses_symlink;
+use crate::sync::lock;
+
+// A kept file handle. Only the handle is kept, never a mapping of the file:
+// a mapping outliving the command reading it would turn the file being
+// truncated in place by another program into a crash at any later access.
+struct OpenFile {
+    // Modification time and length of the file when it was opened
+    version: (SystemTime, u64),
+    file: File,
+    // Value of the access counter when the handle was last used
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct HandlesState {
+    files: HashMap<PathBuf, OpenFile>,
+    access_counter: u64,
+}
+
+// Open files keyed by canonical path, so commands reading the same file one
+// after another (stats, layers, then geometry per layer) don't each open it
+// again, which is slow on network filesystems. A large file is mapped afresh
+// from its handle on every read, and the mapping is dropped with the last use
+// of the bytes returned. A handle is used only
+// while the file's modification time and length are unchanged. When more than
+// `max_open` files are open, the least recently used one is closed.
+#[derive(Clone)]
+pub struct FileHandles {
+    state: Arc<Mutex<HandlesState>>,
+    max_open: usize,
+}
+
+impl FileHandles {
+    pub fn with_max_open(max_open: usize) -> Self {
+        FileHandles { state: Arc::default(), max_open: max_open.max(1) }
+    }
+
+    // The contents of `path` as `map_or_read` returns them, through the file's
+    // kept handle when it is still current. A handle being read from is taken
+    // out meanwhile, since reading moves its position, so a concurrent read of
+    // the same file opens it once more rather than waiting.
+    pub fn read<F>(&self, path: &str, read: F) -> Result<Arc<FileBytes>, GdsError>
+    where
+        F: FnOnce(&mut dyn Read, usize) -> Result<Vec<u8>, GdsError>,
+    {
+        if refuses_symlink(Path::new(path)) {
+            return Err(GdsError::SymlinkRefused { path: path.to_string() });
+        }
+        let key = cache_key(Path::new(path));
+        let metadata = std::fs::metadata(&key)?;
+        let version = (metadata.modified()?, metadata.len());
+        // The handle of a changed file is closed as it drops
+        let kept = lock(&self.state, "File handles")
+            .files
+            .remove(&key)
+            .filter(|open| open.version == version)
+            .map(|open| open.file);
+
+        let mut file = match kept {
+            Some(file) => file,
+            None => File::open(&key)?,
+        };
+        let bytes = Arc::new(map_or_read_open(path, &mut file, read)?);
+        let mut state = lock(&self.state, "File handles");
+        state.access_counter += 1;
+        let last_used = state.access_counter;
+        state.files.insert(key, OpenFile { version, file, last_used });
+        while state.files.len() > self.max_open {
+            let oldest = state
+                .files
+                .iter()
+                .min_by_key(|(_, open)| open.last_used)
+                .map(|(path, _)| path.clone());
+            if let Some(oldest) = oldest {
+                state.files.remove(&oldest);
+            }
+        }
+        Ok(bytes)
+    }
+
+    // Close the handle of a file, e.g. after it changed on disk or stopped
+    // being watched
+    pub fn close(&self, path: &Path) {
+        lock(&self.state, "File handles").files.remove(&cache_key(path));
+    }
+
+    pub fn clear(&self) {
+        lock(&self.state, "File handles").files.clear();
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        lock(&self.state, "File handles").files.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::mmap::read_all;
+
+    #[test]
+    fn handles_are_reused_until_the_file_changes_or_is_closed() {
+        let dir = tempfile::tempdir().unwrap();
+        let [first, second] = ["a.gds", "b.gds"].map(|name| dir.path().join(name));
+        std::fs::write(&first, b"first").unwrap();
+        std::fs::write(&second, b"second").unwrap();
+        let [first_str, second_str] = [&first, &second].map(|path| path.to_string_lossy());
+        let handles = FileHandles::with_max_open(1);
+
+        assert_eq!(&**handles.read(&first_str, read_all).unwrap(), b"first");
+        assert_eq!(&**handles.read(&first_str, read_all).unwrap(), b"first");
+        assert_eq!(handles.len(), 1);
+        // A different length is a different version, whatever the timestamp
+        std::fs::write(&first, b"first, edited").unwrap();
+        assert_eq!(&**handles.read(&first_str, read_all).unwrap(), b"first, edited");
+
+        // Opening a second file closes the first
+        assert_eq!(&**handles.read(&second_str, read_all).unwrap(), b"second");
+        assert_eq!(handles.len(), 1);
+        handles.close(&second);
+        assert_eq!(handles.len(), 0);
+    }
+}
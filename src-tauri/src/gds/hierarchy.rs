@@ -0,0 +1,795 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+
+use super::bbox::{Bbox, BboxCalculator};
+use super::cache::ParseCache;
+use super::flatten::max_hierarchy_depth;
+use super::{GdsCell, GdsDates, GdsError, GdsLibrary};
+use crate::error::AppError;
+
+// Cells that no other cell references, in definition order. If every cell is
+// referenced (a cyclic hierarchy), falls back to the last-defined cell.
+pub fn top_cells(library: &GdsLibrary) -> Vec<String> {
+    let mut referenced: HashSet<&str> = HashSet::new();
+    for cell in &library.cells {
+        for reference in &cell.references {
+            if reference.cell != cell.name {
+                referenced.insert(reference.cell.as_str());
+            }
+        }
+    }
+
+    let tops: Vec<String> = library
+        .cells
+        .iter()
+        .filter(|cell| !referenced.contains(cell.name.as_str()))
+        .map(|cell| cell.name.clone())
+        .collect();
+
+    if tops.is_empty() {
+        if let Some(last) = library.cells.last() {
+            log::warn!(
+                "Every cell in library {} is referenced; falling back to last cell {}",
+                library.name,
+                last.name
+            );
+            return vec![last.name.clone()];
+        }
+    }
+    tops
+}
+
+// Command to detect the top-level cell(s) of a GDSII file
+#[tauri::command]
+pub async fn get_top_cells(
+    path: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<String>, AppError> {
+    Ok(top_cells(&*cache.library(&path)?))
+}
+
+// Which cells reference which. `edges` are (parent, child) pairs, one per
+// distinct SREF/AREF target. `nodes` lists the defined cells in definition
+// order, followed by referenced cells missing from the library. Each entry of
+// `cycles` is a reference loop, starting and ending at the same cell.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct CellGraph {
+    pub nodes: Vec<String>,
+    pub edges: Vec<(String, String)>,
+    pub cycles: Vec<Vec<String>>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum VisitState {
+    Unvisited,
+    OnStack,
+    Done,
+}
+
+// Depth-first search for reference loops, using an explicit stack so deep
+// hierarchies can't overflow the thread's stack
+fn find_cycles(children: &[Vec<usize>], nodes: &[String]) -> Vec<Vec<String>> {
+    let mut state = vec![VisitState::Unvisited; nodes.len()];
+    let mut cycles = Vec::new();
+    for root in 0..nodes.len() {
+        if state[root] != VisitState::Unvisited {
+            continue;
+        }
+        // (node, index of the next child to visit)
+        let mut stack = vec![(root, 0)];
+        state[root] = VisitState::OnStack;
+        while let Some(&mut (node, ref mut next)) = stack.last_mut() {
+            let Some(&child) = children[node].get(*next) else {
+                state[node] = VisitState::Done;
+                stack.pop();
+                continue;
+            };
+            *next += 1;
+            match state[child] {
+                VisitState::Unvisited => {
+                    state[child] = VisitState::OnStack;
+                    stack.push((child, 0));
+                }
+                VisitState::OnStack => {
+                    let start = stack.iter().position(|&(n, _)| n == child).unwrap_or(0);
+                    let mut cycle: Vec<String> =
+                        stack[start..].iter().map(|&(n, _)| nodes[n].clone()).collect();
+                    cycle.push(nodes[child].clone());
+                    cycles.push(cycle);
+                }
+                VisitState::Done => {}
+            }
+        }
+    }
+    cycles
+}
+
+pub fn cell_graph(library: &GdsLibrary) -> CellGraph {
+    let mut nodes: Vec<String> = library.cells.iter().map(|cell| cell.name.clone()).collect();
+    let mut index: HashMap<String, usize> =
+        nodes.iter().enumerate().map(|(i, name)| (name.clone(), i)).collect();
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+    let mut edges = Vec::new();
+
+    for (parent, cell) in library.cells.iter().enumerate() {
+        for reference in &cell.references {
+            let child = *index.entry(reference.cell.clone()).or_insert_with(|| {
+                nodes.push(reference.cell.clone());
+                children.push(Vec::new());
+                nodes.len() - 1
+            });
+            if !children[parent].contains(&child) {
+                children[parent].push(child);
+                edges.push((cell.name.clone(), reference.cell.clone()));
+            }
+        }
+    }
+
+    let cycles = find_cycles(&children, &nodes);
+    if !cycles.is_empty() {
+        log::warn!("Library {} has {} reference cycle(s)", library.name, cycles.len());
+    }
+    CellGraph { nodes, edges, cycles }
+}
+
+// `cell` and the cells below it, each listed once however often it is placed,
+// breadth first from `cell`. References to missing cells are skipped.
+pub fn reachable_cells<'a>(
+    library: &'a GdsLibrary,
+    cell: &'a str,
+) -> Result<Vec<&'a GdsCell>, GdsError> {
+    let root = library.cell(cell).ok_or_else(|| GdsError::CellNotFound { cell: cell.to_string() })?;
+    let mut seen: HashSet<&str> = HashSet::from([cell]);
+    let mut cells = vec![root];
+    let mut next = 0;
+    while let Some(&current) = cells.get(next) {
+        next += 1;
+        for reference in &current.references {
+            if seen.insert(&reference.cell) {
+                cells.extend(library.cell(&reference.cell));
+            }
+        }
+    }
+    Ok(cells)
+}
+
+// Command to get the cell dependency graph of a GDSII file
+#[tauri::command]
+pub async fn get_cell_graph(
+    path: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<CellGraph, AppError> {
+    Ok(cell_graph(&*cache.library(&path)?))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HierarchyFormat {
+    Json,
+    Dot,
+}
+
+// One cell of the exported tree, with one child per distinct cell it
+// references, sorted by name. A `missing` cell is referenced but not defined;
+// a `cycle` node is already one of its ancestors and is not expanded again. A
+// `repeated` node is a cell already expanded earlier in the export, so shared
+// cells are listed in full only once.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct HierarchyNode {
+    pub name: String,
+    pub element_count: usize,
+    pub missing: bool,
+    pub cycle: bool,
+    pub repeated: bool,
+    pub children: Vec<HierarchyNode>,
+}
+
+// A node of the tree being filled in, with the children still to add
+struct Expansion<'a> {
+    name: &'a str,
+    node: HierarchyNode,
+    children: std::collections::btree_set::IntoIter<&'a str>,
+}
+
+struct TreeBuilder<'a> {
+    cells: HashMap<&'a str, &'a GdsCell>,
+    visited: HashSet<&'a str>,
+    max_depth: usize,
+}
+
+impl<'a> TreeBuilder<'a> {
+    // Start the node of `name` below the nodes being filled in on `stack`.
+    // Returns it when it has no children to add: a missing cell, a cycle back
+    // to one of the nodes on the stack, or a cell already expanded elsewhere.
+    fn enter(
+        &mut self,
+        name: &'a str,
+        stack: &mut Vec<Expansion<'a>>,
+    ) -> Result<Option<HierarchyNode>, GdsError> {
+        let cell = self.cells.get(name).copied();
+        let cycle = stack.iter().any(|expansion| expansion.name == name);
+        let repeated = !cycle && self.visited.contains(name);
+        let node = HierarchyNode {
+            name: name.to_string(),
+            element_count: cell.map_or(0, |cell| cell.elements.len()),
+            missing: cell.is_none(),
+            cycle,
+            repeated,
+            children: Vec::new(),
+        };
+        let Some(cell) = cell.filter(|_| !cycle && !repeated) else {
+            return Ok(Some(node));
+        };
+        if stack.len() >= self.max_depth {
+            return Err(GdsError::HierarchyTooDeep {
+                cell: name.to_string(),
+                max_depth: self.max_depth,
+            });
+        }
+        self.visited.insert(name);
+        let children: BTreeSet<&str> =
+            cell.references.iter().map(|reference| reference.cell.as_str()).collect();
+        stack.push(Expansion { name, node, children: children.into_iter() });
+        Ok(None)
+    }
+
+    // Add the tree below `root` to `trees`. Nodes being filled in are kept on
+    // a stack of their own rather than by recursing.
+    fn add_tree(&mut self, root: &'a str, trees: &mut Vec<HierarchyNode>) -> Result<(), GdsError> {
+        let mut stack = Vec::new();
+        let mut entering = Some(root);
+        loop {
+            let node = match entering.take() {
+                Some(name) => match self.enter(name, &mut stack)? {
+                    Some(node) => node,
+                    None => continue,
+                },
+                None => {
+                    if let Some(child) = stack.last_mut().and_then(|top| top.children.next()) {
+                        entering = Some(child);
+                        continue;
+                    }
+                    match stack.pop() {
+                        Some(expansion) => expansion.node,
+                        None => return Ok(()),
+                    }
+                }
+            };
+            match stack.last_mut() {
+                Some(parent) => parent.node.children.push(node),
+                None => trees.push(node),
+            }
+        }
+    }
+}
+
+// The hierarchy as nested trees, one per top cell, sorted by name. A shared
+// cell is expanded where it is first reached and is a `repeated` leaf under
+// every later parent, so the export stays as small as the library however
+// much the cells are reused. Cells only reachable through a reference loop get
+// a tree of their own, so every defined cell appears.
+pub fn hierarchy_tree(library: &GdsLibrary) -> Result<Vec<HierarchyNode>, GdsError> {
+    let mut builder = TreeBuilder {
+        cells: library.cells.iter().map(|cell| (cell.name.as_str(), cell)).collect(),
+        visited: HashSet::new(),
+        max_depth: max_hierarchy_depth(),
+    };
+    let mut trees = Vec::new();
+    let tops: BTreeSet<String> = top_cells(library).into_iter().collect();
+    for top in &tops {
+        builder.add_tree(top, &mut trees)?;
+    }
+    let names: BTreeSet<&str> = library.cells.iter().map(|cell| cell.name.as_str()).collect();
+    for name in names {
+        if !builder.visited.contains(name) {
+            builder.add_tree(name, &mut trees)?;
+        }
+    }
+    Ok(trees)
+}
+
+// `text` escaped for a Graphviz string literal
+fn dot_escaped(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// The cell graph in Graphviz DOT: one node per cell labelled with its element
+// count, with missing cells dashed, and one edge per distinct reference, both
+// sorted by name
+pub fn hierarchy_dot(library: &GdsLibrary) -> String {
+    let graph = cell_graph(library);
+    let element_counts: HashMap<&str, usize> =
+        library.cells.iter().map(|cell| (cell.name.as_str(), cell.elements.len())).collect();
+    let nodes: BTreeSet<&str> = graph.nodes.iter().map(String::as_str).collect();
+    let edges: BTreeSet<(&str, &str)> =
+        graph.edges.iter().map(|(parent, child)| (parent.as_str(), child.as_str())).collect();
+
+    let mut dot = format!("digraph \"{}\" {{\n", dot_escaped(&library.name));
+    for name in nodes {
+        let (detail, style) = match element_counts.get(name) {
+            Some(1) => ("1 element".to_string(), ""),
+            Some(count) => (format!("{} elements", count), ""),
+            None => ("missing".to_string(), ", style=dashed"),
+        };
+        let name = dot_escaped(name);
+        let _ = writeln!(dot, "  \"{}\" [label=\"{}\\n{}\"{}];", name, name, detail, style);
+    }
+    for (parent, child) in edges {
+        let _ = writeln!(dot, "  \"{}\" -> \"{}\";", dot_escaped(parent), dot_escaped(child));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+// Command to export the cell hierarchy as nested JSON or a Graphviz DOT graph,
+// for documenting a design. Both are sorted by name, so they diff cleanly.
+#[tauri::command]
+pub async fn export_hierarchy(
+    path: String,
+    format: HierarchyFormat,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<String, AppError> {
+    let library = cache.library(&path)?;
+    match format {
+        HierarchyFormat::Json => serde_json::to_string_pretty(&hierarchy_tree(&library)?)
+            .map_err(|e| AppError::io("Failed to serialize the hierarchy", e)),
+        HierarchyFormat::Dot => Ok(hierarchy_dot(&library)),
+    }
+}
+
+// Why an unreferenced cell is not a real top cell: it has no elements or
+// references at all, or only references that draw nothing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanKind {
+    Empty,
+    NoGeometry,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct OrphanCell {
+    pub name: String,
+    pub kind: OrphanKind,
+}
+
+// Cells no other cell references that draw nothing, in definition order.
+// Unreferenced cells with geometry are the library's top cells and are left
+// out, however many there are.
+pub fn orphan_cells(library: &GdsLibrary) -> Vec<OrphanCell> {
+    let graph = cell_graph(library);
+    let referenced: HashSet<&str> = graph
+        .edges
+        .iter()
+        .filter(|(parent, child)| parent != child)
+        .map(|(_, child)| child.as_str())
+        .collect();
+    let mut calculator = BboxCalculator::new(library);
+    library
+        .cells
+        .iter()
+        .filter(|cell| !referenced.contains(cell.name.as_str()))
+        .filter_map(|cell| {
+            let kind = if cell.elements.is_empty() && cell.references.is_empty() {
+                OrphanKind::Empty
+            } else if matches!(calculator.cell_bbox(&cell.name), Ok(None)) {
+                OrphanKind::NoGeometry
+            } else {
+                return None;
+            };
+            Some(OrphanCell { name: cell.name.clone(), kind })
+        })
+        .collect()
+}
+
+// Command to list the unreferenced cells that are cruft rather than top cells
+#[tauri::command]
+pub async fn get_orphan_cells(
+    path: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<OrphanCell>, AppError> {
+    Ok(orphan_cells(&*cache.library(&path)?))
+}
+
+// What a cell without elements of its own holds: nothing at all, as a marker
+// or a stub to be filled in later, or only references to other cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EmptyKind {
+    Empty,
+    Container,
+}
+
+impl EmptyKind {
+    // `None` for a cell with elements of its own
+    fn of(cell: &GdsCell) -> Option<Self> {
+        match (cell.elements.is_empty(), cell.references.is_empty()) {
+            (false, _) => None,
+            (true, true) => Some(EmptyKind::Empty),
+            (true, false) => Some(EmptyKind::Container),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct EmptyCell {
+    pub name: String,
+    pub kind: EmptyKind,
+}
+
+// Cells without elements of their own, in definition order, whether other
+// cells reference them or not
+pub fn empty_cells(library: &GdsLibrary) -> Vec<EmptyCell> {
+    library
+        .cells
+        .iter()
+        .filter_map(|cell| Some(EmptyCell { name: cell.name.clone(), kind: EmptyKind::of(cell)? }))
+        .collect()
+}
+
+// Command to list the empty and reference-only cells of a library, for
+// finding stubs to fill in or clean up
+#[tauri::command]
+pub async fn get_empty_cells(
+    path: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<EmptyCell>, AppError> {
+    Ok(empty_cells(&*cache.library(&path)?))
+}
+
+// A SREF or AREF placing a cell the library doesn't define
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct MissingReference {
+    pub referencing_cell: String,
+    pub missing_cell: String,
+}
+
+// References to undefined cells, one per referencing cell and missing target
+// however often it is placed, in the order of the dependency graph's edges
+pub fn missing_references(library: &GdsLibrary) -> Vec<MissingReference> {
+    let graph = cell_graph(library);
+    let missing: HashSet<&str> =
+        graph.nodes[library.cells.len()..].iter().map(String::as_str).collect();
+    graph
+        .edges
+        .into_iter()
+        .filter(|(_, child)| missing.contains(child.as_str()))
+        .map(|(referencing_cell, missing_cell)| MissingReference { referencing_cell, missing_cell })
+        .collect()
+}
+
+// Command to find references to cells missing from a library, e.g. after a
+// broken merge or a partial export, before the file goes downstream
+#[tauri::command]
+pub async fn check_references(
+    path: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<MissingReference>, AppError> {
+    Ok(missing_references(&*cache.library(&path)?))
+}
+
+// Weight of one cell for the hierarchy panel. `element_count` and
+// `child_reference_count` count the cell's own elements and SREF/AREF records
+// (an array counts once); `bbox` is its flattened extent in database units, or
+// `None` if it has no geometry or sits on a reference loop. `is_empty` is set
+// for a cell with neither, `is_container` for one with references only.
+// `dates` are the ones the writing tool recorded in BGNSTR.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct CellSummary {
+    pub name: String,
+    pub element_count: usize,
+    pub child_reference_count: usize,
+    pub bbox: Option<Bbox>,
+    pub is_empty: bool,
+    pub is_container: bool,
+    pub dates: GdsDates,
+}
+
+// Summaries of every cell, sorted by name. Extents are memoized across cells,
+// so shared sub-cells are only measured once.
+pub fn cell_summaries(library: &GdsLibrary) -> Vec<CellSummary> {
+    let mut calculator = BboxCalculator::new(library);
+    let mut summaries: Vec<CellSummary> = library
+        .cells
+        .iter()
+        .map(|cell| CellSummary {
+            name: cell.name.clone(),
+            element_count: cell.elements.len(),
+            child_reference_count: cell.references.len(),
+            bbox: calculator.cell_bbox(&cell.name).ok().flatten(),
+            is_empty: EmptyKind::of(cell) == Some(EmptyKind::Empty),
+            is_container: EmptyKind::of(cell) == Some(EmptyKind::Container),
+            dates: cell.dates.clone(),
+        })
+        .collect();
+    summaries.sort_by(|a, b| a.name.cmp(&b.name));
+    summaries
+}
+
+// Command to list every cell with its element and reference counts and extent
+#[tauri::command]
+pub async fn get_cell_summaries(
+    path: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<CellSummary>, AppError> {
+    Ok(cell_summaries(&*cache.library(&path)?))
+}
+
+// Names of the cells containing `query`, ignoring case: exact matches first,
+// then prefixes, then other substrings, each group by length and then name.
+// An empty query matches nothing.
+pub fn matching_cells(library: &GdsLibrary, query: &str) -> Vec<String> {
+    let query = query.to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let mut matches: Vec<(u8, &str)> = library
+        .cells
+        .iter()
+        .filter_map(|cell| {
+            let name = cell.name.to_lowercase();
+            let rank = if name == query {
+                0
+            } else if name.starts_with(&query) {
+                1
+            } else if name.contains(&query) {
+                2
+            } else {
+                return None;
+            };
+            Some((rank, cell.name.as_str()))
+        })
+        .collect();
+    matches.sort_by(|a, b| (a.0, a.1.len(), a.1).cmp(&(b.0, b.1.len(), b.1)));
+    matches.into_iter().map(|(_, name)| name.to_string()).collect()
+}
+
+// Command to search the cells of a library by name, for jumping to a cell
+#[tauri::command]
+pub async fn find_cells(
+    path: String,
+    query: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<String>, AppError> {
+    Ok(matching_cells(&*cache.library(&path)?, &query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn only_the_unreferenced_root_is_top() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("LEAF").boundary(1, 0, &[[0, 0], [1, 0], [1, 1], [0, 0]]).end_cell();
+        gds.begin_cell("MID").sref("LEAF", [0, 0], None).end_cell();
+        gds.begin_cell("ROOT").sref("MID", [0, 0], None).sref("LEAF", [5, 5], None).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        assert_eq!(top_cells(&library), vec!["ROOT".to_string()]);
+    }
+
+    #[test]
+    fn unreferenced_cells_without_geometry_are_orphans() {
+        let square = [[0, 0], [1, 0], [1, 1], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("LEAF").boundary(1, 0, &square).end_cell();
+        gds.begin_cell("TOP").sref("LEAF", [0, 0], None).end_cell();
+        // A second top cell, e.g. a test structure, is legitimate
+        gds.begin_cell("TEST").boundary(2, 0, &square).end_cell();
+        gds.begin_cell("STUB").end_cell();
+        gds.begin_cell("DANGLING").sref("GONE", [0, 0], None).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let orphan = |name: &str, kind| OrphanCell { name: name.to_string(), kind };
+        assert_eq!(
+            orphan_cells(&library),
+            vec![orphan("STUB", OrphanKind::Empty), orphan("DANGLING", OrphanKind::NoGeometry)]
+        );
+    }
+
+    #[test]
+    fn cyclic_library_falls_back_to_last_cell() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("A").sref("B", [0, 0], None).end_cell();
+        gds.begin_cell("B").sref("A", [0, 0], None).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        assert_eq!(top_cells(&library), vec!["B".to_string()]);
+    }
+
+    #[test]
+    fn graph_has_distinct_edges_and_reports_cycles() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("LEAF").end_cell();
+        gds.begin_cell("A")
+            .sref("LEAF", [0, 0], None)
+            .sref("LEAF", [5, 0], None)
+            .sref("B", [0, 0], None)
+            .end_cell();
+        gds.begin_cell("B").sref("A", [0, 0], None).sref("GONE", [0, 0], None).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let graph = cell_graph(&library);
+        assert_eq!(graph.nodes, vec!["LEAF", "A", "B", "GONE"]);
+        let edge = |a: &str, b: &str| (a.to_string(), b.to_string());
+        assert_eq!(
+            graph.edges,
+            vec![edge("A", "LEAF"), edge("A", "B"), edge("B", "A"), edge("B", "GONE")]
+        );
+        assert_eq!(graph.cycles, vec![vec!["A", "B", "A"]]);
+    }
+
+    #[test]
+    fn shared_cells_are_expanded_once() {
+        // Each level places the next one twice through two cells, so the
+        // fully expanded tree would have 2^30 leaves
+        let square = [[0, 0], [1, 0], [1, 1], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        for level in 0..30 {
+            let next = format!("L{}", level + 1);
+            gds.begin_cell(&format!("L{}", level))
+                .sref(&format!("A{}", level), [0, 0], None)
+                .sref(&format!("B{}", level), [0, 0], None)
+                .end_cell();
+            gds.begin_cell(&format!("A{}", level)).sref(&next, [0, 0], None).end_cell();
+            gds.begin_cell(&format!("B{}", level)).sref(&next, [0, 0], None).end_cell();
+        }
+        gds.begin_cell("L30").boundary(1, 0, &square).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        fn count(nodes: &[HierarchyNode]) -> (usize, usize) {
+            nodes.iter().fold((0, 0), |(all, repeated), node| {
+                let (below, repeated_below) = count(&node.children);
+                (all + 1 + below, repeated + node.repeated as usize + repeated_below)
+            })
+        }
+        let trees = hierarchy_tree(&library).unwrap();
+        assert_eq!(trees.len(), 1);
+        assert_eq!(count(&trees), (121, 30));
+    }
+
+    #[test]
+    fn hierarchy_exports_are_sorted_and_survive_loops() {
+        let square = [[0, 0], [1, 0], [1, 1], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .sref("MID", [0, 0], None)
+            .sref("LEAF", [0, 0], None)
+            .sref("LEAF", [5, 0], None)
+            .end_cell();
+        gds.begin_cell("MID").sref("LEAF", [0, 0], None).sref("GONE", [0, 0], None).end_cell();
+        gds.begin_cell("LEAF").boundary(1, 0, &square).end_cell();
+        // A loop nothing else reaches
+        gds.begin_cell("Y").sref("X", [0, 0], None).boundary(1, 0, &square).end_cell();
+        gds.begin_cell("X").sref("Y", [0, 0], None).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let names = |nodes: &[HierarchyNode]| -> Vec<String> {
+            nodes.iter().map(|node| node.name.clone()).collect()
+        };
+        let trees = hierarchy_tree(&library).unwrap();
+        assert_eq!(names(&trees), ["TOP", "X"]);
+        assert_eq!(names(&trees[0].children), ["LEAF", "MID"]);
+        let mid = &trees[0].children[1];
+        assert_eq!(names(&mid.children), ["GONE", "LEAF"]);
+        assert!(mid.children[0].missing);
+        assert!(!trees[0].children[0].repeated);
+        assert!(mid.children[1].repeated && mid.children[1].children.is_empty());
+        let y = &trees[1].children[0];
+        assert_eq!((y.name.as_str(), y.element_count), ("Y", 1));
+        assert!(y.children[0].cycle && y.children[0].children.is_empty());
+        assert!(!y.children[0].repeated);
+
+        assert_eq!(
+            hierarchy_dot(&library),
+            concat!(
+                "digraph \"LIB\" {\n",
+                "  \"GONE\" [label=\"GONE\\nmissing\", style=dashed];\n",
+                "  \"LEAF\" [label=\"LEAF\\n1 element\"];\n",
+                "  \"MID\" [label=\"MID\\n0 elements\"];\n",
+                "  \"TOP\" [label=\"TOP\\n0 elements\"];\n",
+                "  \"X\" [label=\"X\\n0 elements\"];\n",
+                "  \"Y\" [label=\"Y\\n1 element\"];\n",
+                "  \"MID\" -> \"GONE\";\n",
+                "  \"MID\" -> \"LEAF\";\n",
+                "  \"TOP\" -> \"LEAF\";\n",
+                "  \"TOP\" -> \"MID\";\n",
+                "  \"X\" -> \"Y\";\n",
+                "  \"Y\" -> \"X\";\n",
+                "}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn summaries_are_sorted_by_name_with_counts_and_extents() {
+        let square = [[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .sref("LEAF", [100, 0], None)
+            .aref("LEAF", [0, 0], 2, 2, [40, 0], [0, 40])
+            .end_cell();
+        gds.begin_cell("LEAF").boundary(1, 0, &square).boundary(2, 0, &square).end_cell();
+        gds.begin_cell("LOOP").sref("LOOP", [0, 0], None).boundary(1, 0, &square).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let summaries = cell_summaries(&library);
+        let names: Vec<&str> = summaries.iter().map(|summary| summary.name.as_str()).collect();
+        assert_eq!(names, ["LEAF", "LOOP", "TOP"]);
+        assert_eq!((summaries[0].element_count, summaries[0].child_reference_count), (2, 0));
+        assert_eq!((summaries[2].element_count, summaries[2].child_reference_count), (0, 2));
+        assert_eq!(
+            summaries[2].bbox,
+            Some(Bbox { min_x: 0.0, min_y: 0.0, max_x: 110.0, max_y: 30.0 })
+        );
+        assert_eq!(summaries[1].bbox, None);
+        assert!(summaries[2].is_container && !summaries[2].is_empty);
+        assert!(!summaries[0].is_container && !summaries[0].is_empty);
+    }
+
+    #[test]
+    fn empty_cells_are_told_apart_from_containers() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP").sref("LEAF", [0, 0], None).sref("STUB", [0, 0], None).end_cell();
+        gds.begin_cell("LEAF").boundary(1, 0, &[[0, 0], [1, 0], [1, 1], [0, 0]]).end_cell();
+        gds.begin_cell("STUB").end_cell();
+        gds.begin_cell("MIXED").sref("LEAF", [0, 0], None).text(5, 0, [0, 0], "A").end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let empty = |name: &str, kind| EmptyCell { name: name.to_string(), kind };
+        assert_eq!(
+            empty_cells(&library),
+            [empty("TOP", EmptyKind::Container), empty("STUB", EmptyKind::Empty)]
+        );
+    }
+
+    #[test]
+    fn references_to_undefined_cells_are_reported_once_per_parent() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("LEAF").boundary(1, 0, &[[0, 0], [1, 0], [1, 1], [0, 0]]).end_cell();
+        gds.begin_cell("MID").sref("GONE", [0, 0], None);
+        gds.aref("VANISHED", [0, 0], 2, 2, [10, 0], [0, 10]).end_cell();
+        gds.begin_cell("TOP")
+            .sref("LEAF", [0, 0], None)
+            .sref("GONE", [0, 0], None)
+            .sref("MID", [0, 0], None)
+            .sref("GONE", [5, 0], None)
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let missing = |parent: &str, child: &str| MissingReference {
+            referencing_cell: parent.to_string(),
+            missing_cell: child.to_string(),
+        };
+        assert_eq!(
+            missing_references(&library),
+            [missing("MID", "GONE"), missing("MID", "VANISHED"), missing("TOP", "GONE")]
+        );
+
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("LEAF").end_cell();
+        gds.begin_cell("TOP").sref("LEAF", [0, 0], None).end_cell();
+        assert_eq!(missing_references(&parse_library(&gds.finish()).unwrap()), []);
+    }
+
+    #[test]
+    fn cell_search_ranks_exact_then_prefix_then_substring() {
+        let mut gds = GdsBuilder::library("LIB");
+        for name in ["SRAM_BIT", "bitcell", "BIT", "BIT_ARRAY_64", "BIT_ARRAY", "INV"] {
+            gds.begin_cell(name).end_cell();
+        }
+        let library = parse_library(&gds.finish()).unwrap();
+
+        assert_eq!(
+            matching_cells(&library, "bit"),
+            ["BIT", "bitcell", "BIT_ARRAY", "BIT_ARRAY_64", "SRAM_BIT"]
+        );
+        assert_eq!(matching_cells(&library, "Array_6"), ["BIT_ARRAY_64"]);
+        assert!(matching_cells(&library, "").is_empty());
+        assert!(matching_cells(&library, "nand").is_empty());
+    }
+}
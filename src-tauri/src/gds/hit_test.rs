@@ -0,0 +1,239 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+
+use super::cache::{cache_key, ParseCache};
+use super::flatten::cell_polygons;
+use super::{GdsError, GdsLibrary};
+use crate::error::AppError;
+use crate::geometry::contains_point;
+use crate::geometry::rtree::{Rect, RTree};
+use crate::sync::lock;
+
+// A flattened polygon: `index` is its position in the `get_layer_polygons` list
+// for the same cell and (layer, datatype)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, JsonSchema)]
+pub struct ElementRef {
+    pub layer: u16,
+    pub datatype: u16,
+    pub index: usize,
+}
+
+// Flattened polygons of a cell (in database units) with an R-tree over their
+// bounding boxes
+pub struct HitIndex {
+    polygons: Vec<(ElementRef, Vec<[f64; 2]>)>,
+    tree: RTree<usize>,
+}
+
+impl HitIndex {
+    pub fn build(library: &GdsLibrary, cell: &str) -> Result<Self, GdsError> {
+        let mut polygons = Vec::new();
+        for ((layer, datatype), outlines) in cell_polygons(library, cell)? {
+            for (index, outline) in outlines.into_iter().enumerate() {
+                polygons.push((ElementRef { layer, datatype, index }, outline));
+            }
+        }
+        let tree = RTree::new(
+            polygons
+                .iter()
+                .enumerate()
+                .filter_map(|(i, (_, outline))| Some((Rect::around(outline)?, i)))
+                .collect(),
+        );
+        Ok(HitIndex { polygons, tree })
+    }
+
+    // Every indexed polygon, in layer, datatype and index order
+    pub fn polygons(&self) -> &[(ElementRef, Vec<[f64; 2]>)] {
+        &self.polygons
+    }
+
+    // Polygons containing `point` (database units), highest layer and datatype
+    // first and, within one, the last drawn first
+    pub fn hits(&self, point: [f64; 2]) -> Vec<ElementRef> {
+        let mut hits: Vec<ElementRef> = self
+            .tree
+            .query_point(point)
+            .into_iter()
+            .map(|&i| &self.polygons[i])
+            .filter(|(_, outline)| contains_point(outline, point))
+            .map(|(element, _)| *element)
+            .collect();
+        hits.sort_by_key(|element| Reverse(*element));
+        hits
+    }
+
+    // Whether `point` (database units) lies inside any polygon, only of `layer`
+    // if given. Each polygon is filled by the even-odd rule, so a hole cut
+    // into a boundary with a keyhole line is not inside it.
+    pub fn contains(&self, point: [f64; 2], layer: Option<u16>) -> bool {
+        self.tree.query_point(point).into_iter().map(|&i| &self.polygons[i]).any(
+            |(element, outline)| {
+                layer.map_or(true, |layer| element.layer == layer) && contains_point(outline, point)
+            },
+        )
+    }
+
+    // Polygons whose bounding boxes intersect `region` (database units), ordered
+    // by layer, datatype and index
+    pub fn within(&self, region: &Rect) -> Vec<(ElementRef, &[[f64; 2]])> {
+        let mut found: Vec<(ElementRef, &[[f64; 2]])> = self
+            .tree
+            .query_rect(region)
+            .into_iter()
+            .map(|&i| (self.polygons[i].0, self.polygons[i].1.as_slice()))
+            .collect();
+        found.sort_by_key(|(element, _)| *element);
+        found
+    }
+}
+
+// Hit indexes by file and cell. Each remembers the parsed library it was built
+// from; once the parse cache replaces that library (the file changed, or the
+// entry was evicted) the index is stale and gets rebuilt.
+struct CachedIndex {
+    source: Weak<GdsLibrary>,
+    index: Arc<HitIndex>,
+}
+
+#[derive(Default)]
+pub struct HitIndexCache(Mutex<HashMap<(PathBuf, String), CachedIndex>>);
+
+impl HitIndexCache {
+    // The index for `cell` if one was already built from this `library`
+    pub fn cached(
+        &self,
+        path: &str,
+        library: &Arc<GdsLibrary>,
+        cell: &str,
+    ) -> Option<Arc<HitIndex>> {
+        let key = (cache_key(Path::new(path)), cell.to_string());
+        let indexes = lock(&self.0, "Hit index cache");
+        let cached = indexes.get(&key)?;
+        (Weak::as_ptr(&cached.source) == Arc::as_ptr(library)).then(|| Arc::clone(&cached.index))
+    }
+
+    // The index for `cell`, building it if there is none for this `library`
+    pub fn index(
+        &self,
+        path: &str,
+        library: &Arc<GdsLibrary>,
+        cell: &str,
+    ) -> Result<Arc<HitIndex>, GdsError> {
+        if let Some(index) = self.cached(path, library, cell) {
+            return Ok(index);
+        }
+
+        // Built without the lock, so other cells can be queried meanwhile
+        let index = Arc::new(HitIndex::build(library, cell)?);
+        let key = (cache_key(Path::new(path)), cell.to_string());
+        let mut indexes = lock(&self.0, "Hit index cache");
+        indexes.retain(|_, cached| cached.source.strong_count() > 0);
+        let source = Arc::downgrade(library);
+        indexes.insert(key, CachedIndex { source, index: Arc::clone(&index) });
+        Ok(index)
+    }
+}
+
+// Command to find the polygons of a cell containing a point given in user
+// units, topmost layer first
+#[tauri::command]
+pub async fn hit_test(
+    path: String,
+    cell: String,
+    x: f64,
+    y: f64,
+    cache: tauri::State<'_, ParseCache>,
+    indexes: tauri::State<'_, HitIndexCache>,
+) -> Result<Vec<ElementRef>, AppError> {
+    let library = cache.library(&path)?;
+    let index = indexes.index(&path, &library, &cell)?;
+    let user_unit = library.units.user_unit;
+    Ok(index.hits([x / user_unit, y / user_unit]))
+}
+
+// Command to check whether a point given in user units lies inside a filled
+// polygon of a cell, on any datatype of `layer` if given
+#[tauri::command]
+pub async fn point_in_cell(
+    path: String,
+    cell: String,
+    x: f64,
+    y: f64,
+    layer: Option<u16>,
+    cache: tauri::State<'_, ParseCache>,
+    indexes: tauri::State<'_, HitIndexCache>,
+) -> Result<bool, AppError> {
+    let library = cache.library(&path)?;
+    let index = indexes.index(&path, &library, &cell)?;
+    let user_unit = library.units.user_unit;
+    Ok(index.contains([x / user_unit, y / user_unit], layer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn hits_come_back_topmost_first() {
+        let square =
+            |x: i32, size: i32| [[x, 0], [x + size, 0], [x + size, size], [x, size], [x, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("VIA").boundary(2, 0, &square(0, 10)).end_cell();
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &square(0, 100))
+            .boundary(1, 0, &square(200, 100))
+            .boundary(1, 0, &square(40, 40))
+            .sref("VIA", [45, 5], None)
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+        let index = HitIndex::build(&library, "TOP").unwrap();
+
+        let element = |layer, index| ElementRef { layer, datatype: 0, index };
+        assert_eq!(index.hits([50.0, 10.0]), vec![element(2, 0), element(1, 2), element(1, 0)]);
+        assert_eq!(index.hits([250.0, 50.0]), vec![element(1, 1)]);
+        assert!(index.hits([150.0, 50.0]).is_empty());
+    }
+
+    #[test]
+    fn points_in_holes_are_outside() {
+        // A 100 square with a 20 square hole at its center, joined to the
+        // outside by a zero-width cut along y = 50
+        let frame = [
+            [0, 0],
+            [100, 0],
+            [100, 100],
+            [0, 100],
+            [0, 50],
+            [40, 50],
+            [40, 60],
+            [60, 60],
+            [60, 40],
+            [40, 40],
+            [40, 50],
+            [0, 50],
+            [0, 0],
+        ];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &frame)
+            .boundary(2, 0, &[[45, 45], [55, 45], [55, 55], [45, 55], [45, 45]])
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+        let index = HitIndex::build(&library, "TOP").unwrap();
+
+        assert!(index.contains([10.0, 10.0], None));
+        assert!(!index.contains([42.0, 58.0], None));
+        // Inside the hole, but covered by layer 2
+        assert!(index.contains([50.0, 52.0], None));
+        assert!(!index.contains([50.0, 52.0], Some(1)));
+        assert!(!index.contains([10.0, 10.0], Some(2)));
+        assert!(!index.contains([150.0, 10.0], None));
+    }
+}
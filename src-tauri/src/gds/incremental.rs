@@ -0,0 +1,259 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hasher;
+
+use super::dates::dates_from_record;
+use super::parser::{parse_library_with_progress, ParseMode, ParseProgress};
+use super::record::{self, Record, RecordReader};
+use super::{GdsCell, GdsError, GdsLibrary};
+use crate::warning::Warning;
+
+// Above this share of the structure bytes in changed structures, re-parsing
+// only those saves too little over a full parse to be worth the bookkeeping
+const MAX_CHANGED_FRACTION: f64 = 0.5;
+
+// The ENDLIB closing the stream of changed structures
+const ENDLIB_RECORD: [u8; 4] =
+    [0, record::RECORD_HEADER_LEN as u8, record::ENDLIB, record::NO_DATA];
+
+// Fingerprint of a structure's records after its BGNSTR, which only holds the
+// dates tools often restamp on every save
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CellDigest {
+    hash: u64,
+    len: usize,
+}
+
+// The structures of a stream that parsed cleanly, by name, for telling which
+// of them a later version of the file left unchanged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellIndex {
+    cells: HashMap<String, CellDigest>,
+}
+
+// Where one structure sits in a stream
+struct CellSpan<'a> {
+    name: String,
+    bgnstr: Record<'a>,
+    // From its BGNSTR to the end of its ENDSTR
+    start: usize,
+    end: usize,
+    digest: CellDigest,
+}
+
+fn digest(bytes: &[u8]) -> CellDigest {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(bytes);
+    CellDigest { hash: hasher.finish(), len: bytes.len() }
+}
+
+// Offset just past `record`, not counting a pad byte after it
+fn record_end(record: &Record) -> usize {
+    record.offset as usize + record::RECORD_HEADER_LEN + record.data.len()
+}
+
+// The structures of a plain stream, found from the record headers alone, with
+// the offset of the first BGNSTR (or of ENDLIB without one). `None` for a
+// stream the parser would have to recover from, or with anything other than
+// structures between the first BGNSTR and ENDLIB.
+fn scan_cells(bytes: &[u8]) -> Option<(usize, Vec<CellSpan<'_>>)> {
+    let mut records = RecordReader::new(bytes);
+    let mut spans = Vec::new();
+    let mut prefix_end = None;
+    loop {
+        let record = records.next()?.ok()?;
+        match record.record_type {
+            record::BGNSTR => {
+                let start = record.offset as usize;
+                prefix_end.get_or_insert(start);
+                let name = records.next()?.ok()?;
+                if name.record_type != record::STRNAME {
+                    return None;
+                }
+                let end = loop {
+                    let inner = records.next()?.ok()?;
+                    match inner.record_type {
+                        record::ENDSTR => break record_end(&inner),
+                        record::BGNSTR | record::ENDLIB => return None,
+                        _ => {}
+                    }
+                };
+                spans.push(CellSpan {
+                    name: name.string(),
+                    bgnstr: record,
+                    start,
+                    end,
+                    digest: digest(&bytes[name.offset as usize..end]),
+                });
+            }
+            record::ENDLIB => {
+                return Some((prefix_end.unwrap_or(record.offset as usize), spans));
+            }
+            _ if prefix_end.is_some() => return None,
+            _ => {}
+        }
+    }
+}
+
+// Index of `spans`, unless two structures share a name
+fn index_spans(spans: &[CellSpan]) -> Option<CellIndex> {
+    let cells: HashMap<String, CellDigest> =
+        spans.iter().map(|span| (span.name.clone(), span.digest)).collect();
+    (cells.len() == spans.len()).then_some(CellIndex { cells })
+}
+
+// Index of the structures of `bytes`, if it is a stream `reparse` can work from
+pub fn index_cells(bytes: &[u8]) -> Option<CellIndex> {
+    index_spans(&scan_cells(bytes)?.1)
+}
+
+// Parse `bytes`, a new version of the file `previous` was fully parsed from
+// with `index` taken of it, decoding only the structures that changed and
+// copying the rest from `previous`. The library-level records before the
+// first structure are always re-read. Returns the library and an index of the
+// new stream, or `None` to have the caller parse the whole file: when it isn't
+// a plain stream, too much of it changed, or the changed part has warnings,
+// which a full parse reports with the right offsets. Only a cancelled parse
+// fails.
+pub fn reparse(
+    bytes: &[u8],
+    previous: &GdsLibrary,
+    index: &CellIndex,
+    on_progress: &mut dyn FnMut(ParseProgress) -> bool,
+) -> Result<Option<(GdsLibrary, CellIndex)>, GdsError> {
+    let Some((prefix_end, spans)) = scan_cells(bytes) else {
+        return Ok(None);
+    };
+    let Some(new_index) = index_spans(&spans) else {
+        return Ok(None);
+    };
+    let previous_cells: HashMap<&str, &GdsCell> =
+        previous.cells.iter().map(|cell| (cell.name.as_str(), cell)).collect();
+    let changed: HashSet<&str> = spans
+        .iter()
+        .filter(|span| {
+            index.cells.get(&span.name) != Some(&span.digest)
+                || !previous_cells.contains_key(span.name.as_str())
+        })
+        .map(|span| span.name.as_str())
+        .collect();
+    let span_bytes = |changed_only: bool| -> usize {
+        spans
+            .iter()
+            .filter(|span| !changed_only || changed.contains(span.name.as_str()))
+            .map(|span| span.end - span.start)
+            .sum()
+    };
+    let changed_bytes = span_bytes(true);
+    if spans.is_empty() || changed_bytes as f64 > span_bytes(false) as f64 * MAX_CHANGED_FRACTION {
+        return Ok(None);
+    }
+
+    // The library-level records and the changed structures, as a stream of
+    // their own
+    let mut stream = Vec::with_capacity(prefix_end + changed_bytes + record::RECORD_HEADER_LEN);
+    stream.extend_from_slice(&bytes[..prefix_end]);
+    for span in spans.iter().filter(|span| changed.contains(span.name.as_str())) {
+        stream.extend_from_slice(&bytes[span.start..span.end]);
+    }
+    stream.extend_from_slice(&ENDLIB_RECORD);
+    let result = parse_library_with_progress(&stream, ParseMode::Full, on_progress, &mut |_| {});
+    let parsed = match result {
+        Ok(parsed) if parsed.warnings.is_empty() && parsed.cells.len() == changed.len() => parsed,
+        Err(GdsError::Cancelled) => return Err(GdsError::Cancelled),
+        _ => return Ok(None),
+    };
+
+    let mut decoded = parsed.cells.into_iter();
+    let mut cells = Vec::with_capacity(spans.len());
+    for span in &spans {
+        if changed.contains(span.name.as_str()) {
+            match decoded.next() {
+                Some(cell) if cell.name == span.name => cells.push(cell),
+                _ => return Ok(None),
+            }
+        } else {
+            let mut cell = previous_cells[span.name.as_str()].clone();
+            cell.dates = dates_from_record(&span.bgnstr);
+            cells.push(cell);
+        }
+    }
+    log::debug!("Re-parsed {} of {} structures", changed.len(), spans.len());
+    Ok(Some((GdsLibrary { cells, ..parsed }, new_index)))
+}
+
+// Fully parse `bytes`, from `previous` (a library and the index of the stream
+// it was parsed from) where `reparse` can. The index of `bytes` is returned
+// along with the library when the stream parsed without warnings.
+pub fn parse_reusing(
+    bytes: &[u8],
+    previous: Option<(&GdsLibrary, &CellIndex)>,
+    on_progress: &mut dyn FnMut(ParseProgress) -> bool,
+    on_warning: &mut dyn FnMut(&Warning),
+) -> Result<(GdsLibrary, Option<CellIndex>), GdsError> {
+    if let Some((library, index)) = previous {
+        if let Some((library, index)) = reparse(bytes, library, index, on_progress)? {
+            return Ok((library, Some(index)));
+        }
+    }
+    let library = parse_library_with_progress(bytes, ParseMode::Full, on_progress, on_warning)?;
+    let index = if library.warnings.is_empty() { index_cells(bytes) } else { None };
+    Ok((library, index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    fn square(size: i32) -> [[i32; 2]; 5] {
+        [[0, 0], [size, 0], [size, size], [0, size], [0, 0]]
+    }
+
+    fn stream(leaf_size: i32, extra_cell: bool) -> Vec<u8> {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("LEAF").boundary(1, 0, &square(leaf_size)).end_cell();
+        gds.begin_cell("BIG").boundary(2, 0, &square(100)).boundary(2, 0, &square(200)).end_cell();
+        if extra_cell {
+            gds.begin_cell("NEW").boundary(3, 0, &square(5)).end_cell();
+        }
+        gds.begin_cell("TOP").sref("LEAF", [0, 0], None).sref("BIG", [10, 10], None).end_cell();
+        gds.finish()
+    }
+
+    #[test]
+    fn only_changed_structures_are_reparsed() {
+        let old = stream(10, false);
+        let previous = parse_library(&old).unwrap();
+        let index = index_cells(&old).unwrap();
+
+        let new = stream(20, true);
+        let (library, new_index) =
+            reparse(&new, &previous, &index, &mut |_| true).unwrap().unwrap();
+        assert_eq!(library, parse_library(&new).unwrap());
+        assert_eq!(new_index, index_cells(&new).unwrap());
+    }
+
+    #[test]
+    fn damaged_or_mostly_changed_streams_fall_back_to_a_full_parse() {
+        let old = stream(10, false);
+        let previous = parse_library(&old).unwrap();
+        let index = index_cells(&old).unwrap();
+
+        // Every structure changed
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP").boundary(1, 0, &square(1)).end_cell();
+        assert!(reparse(&gds.finish(), &previous, &index, &mut |_| true).unwrap().is_none());
+
+        // Cut off in the middle of a structure
+        let truncated = &old[..old.len() - 30];
+        assert!(index_cells(truncated).is_none());
+        assert!(reparse(truncated, &previous, &index, &mut |_| true).unwrap().is_none());
+        let (library, index) =
+            parse_reusing(truncated, Some((&previous, &index)), &mut |_| true, &mut |_| {})
+                .unwrap();
+        assert!(!library.warnings.is_empty());
+        assert!(index.is_none());
+    }
+}
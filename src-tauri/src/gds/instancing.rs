@@ -0,0 +1,132 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::cache::ParseCache;
+use super::flatten::{element_outline, Flattener, Walk};
+use super::region::Polygon;
+use super::transform::Transform;
+use super::{GdsCell, GdsError, GdsLibrary};
+use crate::error::AppError;
+
+// One placement of a cell's geometry: `transform` maps the cell's own
+// coordinates into the top cell's, composed through every level above it
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct CellPlacement {
+    pub cell: String,
+    pub transform: Transform,
+}
+
+// The geometry of each cell once, in the cell's own coordinates, and every
+// place it appears. Drawing `cells[p.cell]` under `p.transform` for each
+// placement gives the flattened cell.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct InstancedGeometry {
+    pub cells: HashMap<String, Vec<Polygon>>,
+    pub placements: Vec<CellPlacement>,
+}
+
+// A cell's own polygons, scaled, without those of its references
+fn own_polygons(cell: &GdsCell, scale: f64) -> Vec<Polygon> {
+    cell.elements
+        .iter()
+        .filter_map(|element| {
+            let outline = element_outline(element, &Transform::IDENTITY)?;
+            let (layer, datatype) = element.layer_key();
+            let points = outline.into_iter().map(|[x, y]| [x * scale, y * scale]).collect();
+            Some(Polygon { layer, datatype, points })
+        })
+        .collect()
+}
+
+// The geometry reachable from `cell`, one copy per cell and scaled by `scale`,
+// with the placement of every copy in depth-first order starting with `cell`
+// itself. Unlike flattening, memory grows with the number of placements
+// rather than with placements times polygons. Only cells with polygons of
+// their own appear; references to missing cells are skipped.
+pub fn instanced_geometry(
+    library: &GdsLibrary,
+    cell: &str,
+    scale: f64,
+) -> Result<InstancedGeometry, GdsError> {
+    let mut geometry = InstancedGeometry { cells: HashMap::new(), placements: Vec::new() };
+    Flattener::new(library).walk(cell, |_| true, &mut |path, transform| {
+        let Some(cell) = path.last() else {
+            return Walk::Enter;
+        };
+        if !geometry.cells.contains_key(&cell.name) {
+            geometry.cells.insert(cell.name.clone(), own_polygons(cell, scale));
+        }
+        // Cells that only hold references draw nothing of their own
+        if !geometry.cells[&cell.name].is_empty() {
+            let transform =
+                Transform { tx: transform.tx * scale, ty: transform.ty * scale, ..*transform };
+            geometry.placements.push(CellPlacement { cell: cell.name.clone(), transform });
+        }
+        Walk::Enter
+    })?;
+    geometry.cells.retain(|_, polygons| !polygons.is_empty());
+    Ok(geometry)
+}
+
+// Command to get a cell's geometry for instanced drawing: each sub-cell's
+// polygons once, plus the composed transform of every placement. Coordinates
+// and translations are in user units.
+#[tauri::command]
+pub async fn get_instanced_geometry(
+    path: String,
+    cell: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<InstancedGeometry, AppError> {
+    let library = cache.library(&path)?;
+    Ok(instanced_geometry(&library, &cell, library.units.user_unit)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::flatten::cell_polygons;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn placed_copies_match_the_flattened_cell() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("LEAF").boundary(1, 0, &[[0, 0], [10, 0], [10, 20], [0, 0]]).end_cell();
+        gds.begin_cell("PAIR")
+            .sref("LEAF", [0, 0], None)
+            .sref("LEAF", [50, 0], Some((true, 90.0, 2.0)))
+            .end_cell();
+        gds.begin_cell("TOP")
+            .boundary(2, 0, &[[0, 0], [1, 0], [1, 1], [0, 0]])
+            .aref("PAIR", [0, 100], 3, 1, [300, 100], [0, 100])
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let geometry = instanced_geometry(&library, "TOP", 1.0).unwrap();
+        let mut names: Vec<&str> = geometry.cells.keys().map(String::as_str).collect();
+        names.sort();
+        // PAIR only holds references, so it has neither geometry nor placements
+        assert_eq!(names, ["LEAF", "TOP"]);
+        assert_eq!(geometry.cells["LEAF"].len(), 1);
+        assert_eq!(geometry.placements.len(), 1 + 3 * 2);
+        assert_eq!(geometry.placements[0].cell, "TOP");
+
+        let mut drawn: Vec<Vec<[f64; 2]>> = geometry
+            .placements
+            .iter()
+            .filter(|placement| placement.cell == "LEAF")
+            .flat_map(|placement| {
+                geometry.cells["LEAF"].iter().map(move |polygon| {
+                    let t = placement.transform;
+                    polygon.points.iter().map(|&[x, y]| t.apply(x, y)).collect()
+                })
+            })
+            .collect();
+        let mut flattened = cell_polygons(&library, "TOP").unwrap().remove(&(1, 0)).unwrap();
+        let key = |ring: &Vec<[f64; 2]>| format!("{:?}", ring);
+        drawn.sort_by_key(key);
+        flattened.sort_by_key(key);
+        assert_eq!(drawn, flattened);
+    }
+}
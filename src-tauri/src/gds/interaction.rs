@@ -0,0 +1,198 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use super::cache::ParseCache;
+use super::hit_test::{HitIndex, HitIndexCache};
+use super::snap::closest_on_segment;
+use crate::error::AppError;
+use crate::geometry::boolean::{boolean, BooleanOp};
+use crate::geometry::measure::distance;
+use crate::geometry::rtree::Rect;
+use crate::geometry::signed_area;
+
+// Spacing, in user units, up to which shapes on two layers are compared when
+// the caller doesn't give one
+const DEFAULT_MAX_SPACING: f64 = 1.0;
+
+// Shared area, in square database units, below which two shapes only touch.
+// Integer coordinates keep real overlaps of orthogonal shapes at 1 or more;
+// this absorbs rounding along angled edges.
+const OVERLAP_TOLERANCE: f64 = 0.25;
+
+// How the shapes of two (layer, datatype) pairs relate. `min_spacing` is the
+// smallest gap between two shapes that don't overlap, or `None` if every
+// shape near the other layer overlaps it.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct LayerPair {
+    pub layer_a: (u16, u16),
+    pub layer_b: (u16, u16),
+    pub overlaps: bool,
+    pub min_spacing: Option<f64>,
+}
+
+fn segments(ring: &[[f64; 2]]) -> impl Iterator<Item = ([f64; 2], [f64; 2])> + '_ {
+    (0..ring.len()).map(move |i| (ring[i], ring[(i + 1) % ring.len()]))
+}
+
+fn cross(o: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64 {
+    (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+}
+
+// Whether the segments strictly cross; touching ones have a distance of 0 anyway
+fn segments_cross(a: ([f64; 2], [f64; 2]), b: ([f64; 2], [f64; 2])) -> bool {
+    let (d1, d2) = (cross(b.0, b.1, a.0), cross(b.0, b.1, a.1));
+    let (d3, d4) = (cross(a.0, a.1, b.0), cross(a.0, a.1, b.1));
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+fn segment_distance(a: ([f64; 2], [f64; 2]), b: ([f64; 2], [f64; 2])) -> f64 {
+    if segments_cross(a, b) {
+        return 0.0;
+    }
+    [
+        distance(a.0, closest_on_segment(a.0, b.0, b.1)),
+        distance(a.1, closest_on_segment(a.1, b.0, b.1)),
+        distance(b.0, closest_on_segment(b.0, a.0, a.1)),
+        distance(b.1, closest_on_segment(b.1, a.0, a.1)),
+    ]
+    .into_iter()
+    .fold(f64::INFINITY, f64::min)
+}
+
+// Smallest distance between the outlines of two polygons
+fn outline_distance(a: &[[f64; 2]], b: &[[f64; 2]]) -> f64 {
+    segments(a)
+        .flat_map(|sa| segments(b).map(move |sb| segment_distance(sa, sb)))
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn overlap(a: &[[f64; 2]], b: &[[f64; 2]]) -> bool {
+    let shared = boolean(&[a.to_vec()], &[b.to_vec()], BooleanOp::And);
+    shared.iter().map(|ring| signed_area(ring)).sum::<f64>() > OVERLAP_TOLERANCE
+}
+
+// Gap between two rectangles, 0 if they touch or intersect
+fn rect_gap(a: &Rect, b: &Rect) -> f64 {
+    let dx = (b.min[0] - a.max[0]).max(a.min[0] - b.max[0]).max(0.0);
+    let dy = (b.min[1] - a.max[1]).max(a.min[1] - b.max[1]).max(0.0);
+    dx.hypot(dy)
+}
+
+// Compare every shape with the shapes of other layers within `max_spacing`
+// (database units), using the index to find candidates. Only pairs with an
+// overlap or a gap within `max_spacing` are returned, ordered by layer.
+pub fn layer_interactions(index: &HitIndex, max_spacing: f64) -> Vec<LayerPair> {
+    let mut pairs = BTreeMap::new();
+    for (element, outline) in index.polygons() {
+        let Some(bounds) = Rect::around(outline) else {
+            continue;
+        };
+        let search = Rect {
+            min: [bounds.min[0] - max_spacing, bounds.min[1] - max_spacing],
+            max: [bounds.max[0] + max_spacing, bounds.max[1] + max_spacing],
+        };
+        let layer = (element.layer, element.datatype);
+        for (other, other_outline) in index.within(&search) {
+            let other_layer = (other.layer, other.datatype);
+            // Each pair of shapes on different layers is compared once
+            if other_layer <= layer {
+                continue;
+            }
+            let Some(other_bounds) = Rect::around(other_outline) else {
+                continue;
+            };
+            let gap = rect_gap(&bounds, &other_bounds);
+            if gap > max_spacing {
+                continue;
+            }
+            let pair = pairs.entry((layer, other_layer)).or_insert(LayerPair {
+                layer_a: layer,
+                layer_b: other_layer,
+                overlaps: false,
+                min_spacing: None,
+            });
+            // Only shapes whose bounding boxes meet can overlap
+            if gap == 0.0 && overlap(outline, other_outline) {
+                pair.overlaps = true;
+                continue;
+            }
+            let spacing = outline_distance(outline, other_outline);
+            if spacing <= max_spacing && pair.min_spacing.map_or(true, |min| spacing < min) {
+                pair.min_spacing = Some(spacing);
+            }
+        }
+    }
+    pairs.into_values().filter(|pair| pair.overlaps || pair.min_spacing.is_some()).collect()
+}
+
+// Command to report which layers of a cell overlap and how close the others
+// come, up to `max_spacing` user units (1 by default)
+#[tauri::command]
+pub async fn layer_interaction_report(
+    path: String,
+    cell: String,
+    max_spacing: Option<f64>,
+    cache: tauri::State<'_, ParseCache>,
+    indexes: tauri::State<'_, HitIndexCache>,
+) -> Result<Vec<LayerPair>, AppError> {
+    let max_spacing = max_spacing.unwrap_or(DEFAULT_MAX_SPACING);
+    if !(max_spacing >= 0.0 && max_spacing.is_finite()) {
+        return Err(AppError::invalid_argument(format!(
+            "Maximum spacing must be a non-negative number, got {}",
+            max_spacing
+        )));
+    }
+    let library = cache.library(&path)?;
+    let index = indexes.index(&path, &library, &cell)?;
+    let user_unit = library.units.user_unit;
+    let pairs = layer_interactions(&index, max_spacing / user_unit);
+    Ok(pairs
+        .into_iter()
+        .map(|pair| LayerPair {
+            min_spacing: pair.min_spacing.map(|spacing| spacing * user_unit),
+            ..pair
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    fn square(x: i32, y: i32, size: i32) -> [[i32; 2]; 5] {
+        [[x, y], [x + size, y], [x + size, y + size], [x, y + size], [x, y]]
+    }
+
+    #[test]
+    fn overlaps_and_spacings_between_nearby_layers() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &square(0, 0, 100))
+            // Overlaps layer 1, and sits 30 from the second layer 1 square
+            .boundary(2, 0, &square(50, 50, 100))
+            .boundary(1, 0, &square(180, 0, 100))
+            // Touches layer 1 along an edge without overlapping
+            .boundary(3, 0, &square(50, -50, 50))
+            // Far from everything
+            .boundary(4, 0, &square(5000, 5000, 10))
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+        let index = HitIndex::build(&library, "TOP").unwrap();
+
+        let pair = |a: u16, b: u16, overlaps, min_spacing| LayerPair {
+            layer_a: (a, 0),
+            layer_b: (b, 0),
+            overlaps,
+            min_spacing,
+        };
+        let nearby = layer_interactions(&index, 40.0);
+        assert_eq!(nearby, vec![pair(1, 2, true, Some(30.0)), pair(1, 3, false, Some(0.0))]);
+
+        // Gaps beyond the search range are not reported
+        let overlapping = layer_interactions(&index, 10.0);
+        assert_eq!(overlapping, vec![pair(1, 2, true, None), pair(1, 3, false, Some(0.0))]);
+    }
+}
@@ -0,0 +1,452 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, State};
+
+use super::cache::{ParseCache, Reparsed};
+use super::handles::FileHandles;
+use super::incremental::{parse_reusing, CellIndex};
+use super::parser::{parse_library_with_progress, ParseMode, ParseProgress};
+use super::{catch_reader_panic, GdsError, GdsLibrary};
+use crate::error::AppError;
+use crate::perf;
+use crate::sync::lock;
+use crate::warning::Warning;
+use crate::watcher::WatchedFiles;
+
+// Chunk size for reading the file, so a cancelled job stops between chunks
+// instead of after loading the whole file
+const READ_CHUNK_BYTES: usize = 8 * 1024 * 1024;
+
+// A running background parse. Dropping the handle without joining would
+// detach the worker, so cancellation always joins it.
+struct ParseJob {
+    cancelled: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+// State to hold the running parse jobs, keyed by job id. Finished jobs remove
+// themselves.
+#[derive(Default)]
+pub struct ParseJobs {
+    next_id: AtomicU64,
+    jobs: Arc<Mutex<HashMap<u64, ParseJob>>>,
+}
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct ParseProgressPayload {
+    pub job_id: u64,
+    #[serde(flatten)]
+    pub progress: ParseProgress,
+}
+
+// Sent as `warning-emitted` for each warning of a parse job as it is found,
+// before the job completes with them all in the library's `warnings`
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct WarningEmittedPayload {
+    pub job_id: u64,
+    #[serde(flatten)]
+    pub warning: Warning,
+}
+
+// Outcome of a parse job: exactly one of `library` and `error` is set
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct ParseCompletePayload {
+    pub job_id: u64,
+    pub library: Option<Arc<GdsLibrary>>,
+    pub error: Option<AppError>,
+}
+
+// Result of parsing one file of a batch: exactly one of `library` and `error`
+// is set
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct ParseOutcome {
+    pub path: String,
+    pub library: Option<Arc<GdsLibrary>>,
+    pub error: Option<AppError>,
+}
+
+// Sent as `batch-progress` each time a file of a batch finishes. The library
+// itself only comes with the command's result.
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct BatchProgressPayload {
+    pub path: String,
+    pub completed: usize,
+    pub total: usize,
+    pub error: Option<AppError>,
+}
+
+// Read a file in chunks, giving up early once `cancelled` is set
+fn read_cancellable(
+    file: &mut dyn Read,
+    len: usize,
+    cancelled: &AtomicBool,
+) -> Result<Vec<u8>, GdsError> {
+    let mut bytes = Vec::with_capacity(len);
+    let mut chunk = vec![0u8; READ_CHUNK_BYTES.min(len.max(1))];
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(GdsError::Cancelled);
+        }
+        let read = file.read(&mut chunk)?;
+        if read == 0 {
+            return Ok(bytes);
+        }
+        bytes.extend_from_slice(&chunk[..read]);
+    }
+}
+
+// Read (or map) and parse a file in `mode` through `files`, reporting progress
+// and warnings and stopping once `cancelled` is set. A full parse only decodes
+// what changed since `previous` where it can, and returns the index to re-parse
+// from next. A parser panic fails the parse with `GdsError::Internal`.
+pub fn run_parse(
+    files: &FileHandles,
+    path: &str,
+    mode: ParseMode,
+    previous: Option<(&GdsLibrary, &CellIndex)>,
+    cancelled: &AtomicBool,
+    on_progress: &mut dyn FnMut(ParseProgress),
+    on_warning: &mut dyn FnMut(&Warning),
+) -> Result<Reparsed, GdsError> {
+    let bytes = files.read(path, |file, len| read_cancellable(file, len, cancelled))?;
+    let mut on_progress = |progress| {
+        on_progress(progress);
+        !cancelled.load(Ordering::Relaxed)
+    };
+    catch_reader_panic(|| match mode {
+        ParseMode::Full => parse_reusing(&bytes, previous, &mut on_progress, on_warning),
+        ParseMode::HierarchyOnly => {
+            let library = parse_library_with_progress(&bytes, mode, &mut on_progress, on_warning)?;
+            Ok((library, None))
+        }
+    })
+}
+
+impl ParseJobs {
+    // A fresh job id, also used by parses that don't run as jobs so their
+    // progress events can't be mistaken for a job's
+    pub fn next_job_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    // Start parsing `path` in `mode` on a background thread and return the job
+    // id. An unchanged file already in `cache` in a covering mode completes
+    // without re-parsing, and so without reporting its warnings again.
+    pub fn start<P, W, C>(
+        &self,
+        path: String,
+        mode: ParseMode,
+        cache: ParseCache,
+        mut on_progress: P,
+        mut on_warning: W,
+        on_complete: C,
+    ) -> u64
+    where
+        P: FnMut(u64, ParseProgress) + Send + 'static,
+        W: FnMut(u64, &Warning) + Send + 'static,
+        C: FnOnce(u64, Result<Arc<GdsLibrary>, AppError>) + Send + 'static,
+    {
+        let job_id = self.next_job_id();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let jobs = Arc::clone(&self.jobs);
+
+        // Hold the lock while spawning so the worker can't remove its entry
+        // before it was inserted
+        let mut running = lock(&self.jobs, "Parse jobs");
+        let worker_cancelled = Arc::clone(&cancelled);
+        let thread = std::thread::spawn(move || {
+            let result = cache.get_or_reparse(&path, mode, |path, previous| {
+                run_parse(
+                    cache.files(),
+                    path,
+                    mode,
+                    previous,
+                    &worker_cancelled,
+                    // Nothing is reported once the job was cancelled
+                    &mut |progress| {
+                        if !worker_cancelled.load(Ordering::Relaxed) {
+                            on_progress(job_id, progress)
+                        }
+                    },
+                    &mut |warning| {
+                        if !worker_cancelled.load(Ordering::Relaxed) {
+                            on_warning(job_id, warning)
+                        }
+                    },
+                )
+            });
+            lock(&jobs, "Parse jobs").remove(&job_id);
+            on_complete(job_id, result);
+        });
+        running.insert(job_id, ParseJob { cancelled, thread: Some(thread) });
+        job_id
+    }
+
+    // Cancel a running job and wait for its worker to exit, which drops any
+    // partially parsed data. Returns false if no such job is running.
+    pub fn cancel(&self, job_id: u64) -> bool {
+        let job = lock(&self.jobs, "Parse jobs").remove(&job_id);
+        let Some(mut job) = job else {
+            return false;
+        };
+        job.cancelled.store(true, Ordering::Relaxed);
+        if let Some(thread) = job.thread.take() {
+            if thread.join().is_err() {
+                log::warn!("Parse job {} panicked", job_id);
+            }
+        }
+        true
+    }
+
+    // Cancel every running job, then wait for all of their workers to exit.
+    // Returns how many jobs were running.
+    pub fn cancel_all(&self) -> usize {
+        let mut jobs: Vec<(u64, ParseJob)> = lock(&self.jobs, "Parse jobs").drain().collect();
+        for (_, job) in &jobs {
+            job.cancelled.store(true, Ordering::Relaxed);
+        }
+        for (job_id, job) in &mut jobs {
+            if let Some(thread) = job.thread.take() {
+                if thread.join().is_err() {
+                    log::warn!("Parse job {} panicked", job_id);
+                }
+            }
+        }
+        jobs.len()
+    }
+}
+
+// Parse `paths` on up to one worker thread per CPU, returning an outcome per
+// path in the same order. A file that fails to parse only fails its own
+// outcome. `on_done` is called from the workers, one file at a time, as each
+// file finishes.
+pub fn parse_batch<F>(paths: &[String], cache: &ParseCache, on_done: F) -> Vec<ParseOutcome>
+where
+    F: FnMut(&ParseOutcome) + Send,
+{
+    let workers = std::thread::available_parallelism().map_or(1, |n| n.get()).min(paths.len());
+    let next = AtomicUsize::new(0);
+    let outcomes = Mutex::new(vec![None; paths.len()]);
+    let on_done = Mutex::new(on_done);
+    let not_cancelled = AtomicBool::new(false);
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(index) else {
+                    return;
+                };
+                let result = cache.get_or_reparse(path, ParseMode::Full, |path, previous| {
+                    run_parse(
+                        cache.files(),
+                        path,
+                        ParseMode::Full,
+                        previous,
+                        &not_cancelled,
+                        &mut |_| {},
+                        &mut |_| {},
+                    )
+                });
+                let (library, error) = match result {
+                    Ok(library) => (Some(library), None),
+                    Err(error) => (None, Some(error)),
+                };
+                let outcome = ParseOutcome { path: path.clone(), library, error };
+                (*lock(&on_done, "Batch progress"))(&outcome);
+                lock(&outcomes, "Batch outcomes")[index] = Some(outcome);
+            });
+        }
+    });
+
+    let outcomes = outcomes.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner());
+    outcomes.into_iter().flatten().collect()
+}
+
+// Start a parse job that reports through `parse-progress`, `warning-emitted`
+// and `parse-complete` events, and its duration as a `perf` event for `command`
+fn start_parse_job(
+    command: &'static str,
+    path: String,
+    mode: ParseMode,
+    app: AppHandle,
+    jobs: &ParseJobs,
+    cache: &ParseCache,
+) -> u64 {
+    let progress_app = app.clone();
+    let warning_app = app.clone();
+    let started = Instant::now();
+    let timed_path = path.clone();
+    jobs.start(
+        path,
+        mode,
+        ParseCache::clone(cache),
+        move |job_id, progress| {
+            let payload = ParseProgressPayload { job_id, progress };
+            if let Err(e) = progress_app.emit("parse-progress", payload) {
+                log::warn!("Failed to emit parse progress: {}", e);
+            }
+        },
+        move |job_id, warning| {
+            let payload = WarningEmittedPayload { job_id, warning: warning.clone() };
+            if let Err(e) = warning_app.emit("warning-emitted", payload) {
+                log::warn!("Failed to emit parse warning: {}", e);
+            }
+        },
+        move |job_id, result| {
+            let (library, error) = match result {
+                Ok(library) => {
+                    perf::report(&app, command, &timed_path, started);
+                    (Some(library), None)
+                }
+                Err(error) => (None, Some(error)),
+            };
+            if let Err(e) = app.emit("parse-complete", ParseCompletePayload { job_id, library, error }) {
+                log::warn!("Failed to emit parse result: {}", e);
+            }
+        },
+    )
+}
+
+// Command to start parsing a GDSII file in the background, in full unless
+// `parse_mode` says otherwise. Returns a job id immediately; progress arrives
+// as `parse-progress` events and the result as a `parse-complete` event.
+#[tauri::command]
+pub async fn parse_gds(
+    path: String,
+    parse_mode: Option<ParseMode>,
+    app: AppHandle,
+    jobs: State<'_, ParseJobs>,
+    cache: State<'_, ParseCache>,
+) -> Result<u64, AppError> {
+    let mode = parse_mode.unwrap_or_default();
+    Ok(start_parse_job("parse_gds", path, mode, app, &jobs, &cache))
+}
+
+// Command to parse several files concurrently, resolving once all are done
+// with one outcome per path, in order. Each finished file is reported in a
+// `batch-progress` event.
+#[tauri::command]
+pub async fn parse_gds_batch(
+    paths: Vec<String>,
+    app: AppHandle,
+    cache: State<'_, ParseCache>,
+) -> Result<Vec<ParseOutcome>, AppError> {
+    let cache = ParseCache::clone(&cache);
+    let total = paths.len();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut completed = 0;
+        parse_batch(&paths, &cache, |outcome| {
+            completed += 1;
+            let payload = BatchProgressPayload {
+                path: outcome.path.clone(),
+                completed,
+                total,
+                error: outcome.error.clone(),
+            };
+            if let Err(e) = app.emit("batch-progress", payload) {
+                log::warn!("Failed to emit batch progress: {}", e);
+            }
+        })
+    })
+    .await
+    .map_err(|e| AppError::io("Batch parse failed", e))
+}
+
+// Command to re-parse a file that is open (cached or watched), bypassing the
+// cache and the watcher's debounce. Returns the job id, reporting like
+// `parse_gds`, or `None` (with a logged warning) for a file that isn't open.
+#[tauri::command]
+pub async fn reload_file(
+    path: String,
+    app: AppHandle,
+    jobs: State<'_, ParseJobs>,
+    cache: State<'_, ParseCache>,
+    watched_files: State<'_, WatchedFiles>,
+) -> Result<Option<u64>, AppError> {
+    if !cache.contains(Path::new(&path)) && !watched_files.is_watched(&path) {
+        log::warn!("Not reloading {}: the file is not open", path);
+        return Ok(None);
+    }
+    cache.invalidate(Path::new(&path));
+    Ok(Some(start_parse_job("reload_file", path, ParseMode::Full, app, &jobs, &cache)))
+}
+
+// Command to abort a running parse job
+#[tauri::command]
+pub async fn cancel_parse(job_id: u64, jobs: State<'_, ParseJobs>) -> Result<bool, AppError> {
+    Ok(jobs.cancel(job_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    fn write_library(dir: &tempfile::TempDir) -> String {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP").boundary(1, 0, &[[0, 0], [1, 0], [1, 1], [0, 0]]).end_cell();
+        let path = dir.path().join("lib.gds");
+        std::fs::write(&path, gds.finish()).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn background_job_reports_its_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_library(&dir);
+        let jobs = ParseJobs::default();
+        let (sender, receiver) = mpsc::channel();
+
+        let cache = ParseCache::default();
+        let on_complete = move |job_id, result| sender.send((job_id, result)).unwrap();
+        let job_id = jobs.start(path, ParseMode::Full, cache, |_, _| {}, |_, _| {}, on_complete);
+        let (finished_id, result) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert_eq!(finished_id, job_id);
+        assert_eq!(result.unwrap().cells[0].name, "TOP");
+        // The finished job is gone, so there is nothing left to cancel
+        assert!(!jobs.cancel(job_id));
+    }
+
+    #[test]
+    fn corrupt_file_fails_only_its_own_outcome() {
+        let dir = tempfile::tempdir().unwrap();
+        let good = write_library(&dir);
+        let corrupt = dir.path().join("corrupt.gds");
+        std::fs::write(&corrupt, b"not a gds file").unwrap();
+        let paths = vec![corrupt.to_string_lossy().to_string(), good.clone(), good];
+
+        let mut reported = Vec::new();
+        let outcomes = parse_batch(&paths, &ParseCache::default(), |outcome| {
+            reported.push(outcome.path.clone());
+        });
+
+        assert_eq!(reported.len(), 3);
+        let paths_back: Vec<&String> = outcomes.iter().map(|outcome| &outcome.path).collect();
+        assert_eq!(paths_back, paths.iter().collect::<Vec<_>>());
+        assert!(outcomes[0].library.is_none() && outcomes[0].error.is_some());
+        assert!(outcomes[1..].iter().all(|outcome| outcome.library.is_some()));
+    }
+
+    #[test]
+    fn cancelled_parse_stops_with_cancelled_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_library(&dir);
+        let cancelled = AtomicBool::new(true);
+
+        let files = FileHandles::with_max_open(1);
+        let result =
+            run_parse(&files, &path, ParseMode::Full, None, &cancelled, &mut |_| {}, &mut |_| {});
+        assert_eq!(result, Err(GdsError::Cancelled));
+    }
+}
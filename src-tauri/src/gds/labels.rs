@@ -0,0 +1,183 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+use super::cache::ParseCache;
+use super::flatten::Flattener;
+use super::hierarchy::reachable_cells;
+use super::transform::Transform;
+use super::{GdsElement, GdsError, GdsLibrary};
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HorizontalAlign {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum VerticalAlign {
+    Top,
+    Middle,
+    Bottom,
+}
+
+// A TEXT element placed in the top cell's coordinates. (x, y) is the anchor
+// point, in user units, and the alignments say which part of the rendered
+// text sits on it.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Label {
+    pub text: String,
+    pub layer: u16,
+    pub texttype: u16,
+    pub x: f64,
+    pub y: f64,
+    // Counterclockwise rotation in degrees, in [0, 360)
+    pub rotation: f64,
+    pub mag: f64,
+    // Mirrored about the text's own baseline
+    pub reflected: bool,
+    pub halign: HorizontalAlign,
+    pub valign: VerticalAlign,
+}
+
+// Decode the justification bits of a PRESENTATION value: bits 0-1 horizontal,
+// bits 2-3 vertical. The unused value 3 falls back to left/top.
+pub fn justification(presentation: u16) -> (HorizontalAlign, VerticalAlign) {
+    let horizontal = match presentation & 0b11 {
+        1 => HorizontalAlign::Center,
+        2 => HorizontalAlign::Right,
+        _ => HorizontalAlign::Left,
+    };
+    let vertical = match (presentation >> 2) & 0b11 {
+        1 => VerticalAlign::Middle,
+        2 => VerticalAlign::Bottom,
+        _ => VerticalAlign::Top,
+    };
+    (horizontal, vertical)
+}
+
+fn place_label(element: &GdsElement, parent: &Transform, scale: f64) -> Option<Label> {
+    let GdsElement::Text { layer, texttype, presentation, strans, position, text, .. } = element
+    else {
+        return None;
+    };
+    let placed = parent.compose(&Transform::from_placement(strans, *position));
+    let determinant = placed.a * placed.d - placed.b * placed.c;
+    // Absolute magnification and angle ignore the enclosing references
+    let mag = if strans.abs_mag { strans.mag } else { determinant.abs().sqrt() };
+    let rotation = if strans.abs_angle {
+        strans.angle
+    } else {
+        placed.c.atan2(placed.a).to_degrees()
+    };
+    let (halign, valign) = justification(*presentation);
+    Some(Label {
+        text: text.clone(),
+        layer: *layer,
+        texttype: *texttype,
+        x: placed.tx * scale,
+        y: placed.ty * scale,
+        rotation: rotation.rem_euclid(360.0),
+        mag,
+        reflected: determinant < 0.0,
+        halign,
+        valign,
+    })
+}
+
+// All TEXT elements reachable from `cell`, placed through the hierarchy
+pub fn collect_labels(library: &GdsLibrary, cell: &str) -> Result<Vec<Label>, GdsError> {
+    let scale = library.units.user_unit;
+    let mut labels = Vec::new();
+    Flattener::new(library).visit(cell, &mut |element, transform| {
+        labels.extend(place_label(element, transform, scale));
+    })?;
+    Ok(labels)
+}
+
+// The distinct TEXT strings of `cell` and the cells below it, sorted, only from
+// `layers` if given. Each cell is read once, without placing anything.
+pub fn text_strings(
+    library: &GdsLibrary,
+    cell: &str,
+    layers: Option<&[u16]>,
+) -> Result<Vec<String>, GdsError> {
+    let mut strings = BTreeSet::new();
+    for cell in reachable_cells(library, cell)? {
+        for element in &cell.elements {
+            if let GdsElement::Text { layer, text, .. } = element {
+                if layers.map_or(true, |layers| layers.contains(layer)) {
+                    strings.insert(text.as_str());
+                }
+            }
+        }
+    }
+    Ok(strings.into_iter().map(str::to_string).collect())
+}
+
+// Command to get the text labels of a cell with their positions in user units
+#[tauri::command]
+pub async fn get_labels(
+    path: String,
+    cell: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<Label>, AppError> {
+    Ok(collect_labels(&*cache.library(&path)?, &cell)?)
+}
+
+// Command to list the distinct label strings of a cell, e.g. net and port names,
+// without their positions
+#[tauri::command]
+pub async fn get_text_strings(
+    path: String,
+    cell: String,
+    layers: Option<Vec<u16>>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<String>, AppError> {
+    Ok(text_strings(&*cache.library(&path)?, &cell, layers.as_deref())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn labels_follow_enclosing_references() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("PAD").text(10, 0, [100, 0], "VDD").end_cell();
+        gds.begin_cell("TOP")
+            .text(10, 1, [0, 0], "TOP")
+            .sref("PAD", [1000, 2000], Some((false, 90.0, 2.0)))
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let labels = collect_labels(&library, "TOP").unwrap();
+        assert_eq!(labels.len(), 2);
+        let pad = &labels[1];
+        assert_eq!(pad.text, "VDD");
+        // (100, 0) scaled by 2 and rotated by 90 degrees, then moved; 1 nm units
+        // are 0.001 µm
+        assert!((pad.x - 1.0).abs() < 1e-9 && (pad.y - 2.2).abs() < 1e-9);
+        assert_eq!((pad.rotation, pad.mag, pad.reflected), (90.0, 2.0, false));
+        assert_eq!((pad.halign, pad.valign), (HorizontalAlign::Left, VerticalAlign::Top));
+
+        assert_eq!(text_strings(&library, "TOP", None).unwrap(), ["TOP", "VDD"]);
+        assert_eq!(text_strings(&library, "TOP", Some(&[10])).unwrap(), ["TOP", "VDD"]);
+        assert!(text_strings(&library, "TOP", Some(&[11])).unwrap().is_empty());
+        assert_eq!(text_strings(&library, "PAD", None).unwrap(), ["VDD"]);
+    }
+
+    #[test]
+    fn presentation_bits_select_justification() {
+        assert_eq!(justification(0b0101), (HorizontalAlign::Center, VerticalAlign::Middle));
+        assert_eq!(justification(0b1010), (HorizontalAlign::Right, VerticalAlign::Bottom));
+        // Font bits are ignored
+        assert_eq!(justification(0b11_0000), (HorizontalAlign::Left, VerticalAlign::Top));
+    }
+}
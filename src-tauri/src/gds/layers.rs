@@ -0,0 +1,163 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::cache::ParseCache;
+use super::hierarchy::reachable_cells;
+use super::{GdsCell, GdsError, GdsLibrary};
+use crate::error::AppError;
+
+// A (layer, datatype) pair present in a library and how many elements use it
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct LayerInfo {
+    pub layer: u16,
+    pub datatype: u16,
+    pub element_count: u64,
+}
+
+// Tally elements per (layer, datatype) across all cells, sorted by layer then
+// datatype. Text layers are included so labels can be toggled like geometry.
+pub fn collect_layers(library: &GdsLibrary) -> Vec<LayerInfo> {
+    tally(&library.cells.iter().collect::<Vec<_>>())
+}
+
+fn tally(cells: &[&GdsCell]) -> Vec<LayerInfo> {
+    let mut counts: BTreeMap<(u16, u16), u64> = BTreeMap::new();
+    for cell in cells {
+        for element in &cell.elements {
+            *counts.entry(element.layer_key()).or_insert(0) += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .map(|((layer, datatype), element_count)| LayerInfo { layer, datatype, element_count })
+        .collect()
+}
+
+// Command to list the layers and datatypes present in a GDSII file
+#[tauri::command]
+pub async fn get_gds_layers(
+    path: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<LayerInfo>, AppError> {
+    Ok(collect_layers(&*cache.library(&path)?))
+}
+
+// `collect_layers` for `cell` and the cells below it, each counted once however
+// often it is placed. References to missing cells are skipped.
+pub fn cell_layers(library: &GdsLibrary, cell: &str) -> Result<Vec<LayerInfo>, GdsError> {
+    Ok(tally(&reachable_cells(library, cell)?))
+}
+
+// (layer, datatype) pairs used by only one of two cells, or by both, each list
+// sorted by layer then datatype
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct LayerComparison {
+    pub only_a: Vec<(u16, u16)>,
+    pub only_b: Vec<(u16, u16)>,
+    pub both: Vec<(u16, u16)>,
+}
+
+pub fn compare_layers(a: &[LayerInfo], b: &[LayerInfo]) -> LayerComparison {
+    let keys = |layers: &[LayerInfo]| -> BTreeSet<(u16, u16)> {
+        layers.iter().map(|info| (info.layer, info.datatype)).collect()
+    };
+    let (a, b) = (keys(a), keys(b));
+    LayerComparison {
+        only_a: a.difference(&b).copied().collect(),
+        only_b: b.difference(&a).copied().collect(),
+        both: a.intersection(&b).copied().collect(),
+    }
+}
+
+// Command to compare the layers two cells draw on, possibly in different files,
+// e.g. to check no layer was dropped in a process migration
+#[tauri::command]
+pub async fn compare_cell_layers(
+    path_a: String,
+    cell_a: String,
+    path_b: String,
+    cell_b: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<LayerComparison, AppError> {
+    let a = cell_layers(&*cache.library(&path_a)?, &cell_a)?;
+    let b = cell_layers(&*cache.library(&path_b)?, &cell_b)?;
+    Ok(compare_layers(&a, &b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+    use crate::gds::record;
+    use crate::gds::writer::write_library;
+
+    #[test]
+    fn tallies_all_element_kinds_sorted() {
+        let square = [[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("A")
+            .boundary(2, 0, &square)
+            .boundary(1, 5, &square)
+            .path(1, 0, 0, 4, &[[0, 0], [100, 0]])
+            .end_cell();
+        gds.begin_cell("B")
+            .boundary(1, 0, &square)
+            .box_element(3, 1, &square)
+            .text(10, 0, [5, 5], "VDD")
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let layers: Vec<(u16, u16, u64)> = collect_layers(&library)
+            .into_iter()
+            .map(|info| (info.layer, info.datatype, info.element_count))
+            .collect();
+        assert_eq!(layers, vec![(1, 0, 2), (1, 5, 1), (2, 0, 1), (3, 1, 1), (10, 0, 1)]);
+    }
+
+    #[test]
+    fn cell_layers_follow_references_and_compare_sorted() {
+        let square = [[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("VIA").boundary(5, 0, &square).end_cell();
+        gds.begin_cell("OLD").boundary(1, 0, &square).boundary(2, 0, &square);
+        gds.sref("VIA", [0, 0], None).sref("GONE", [0, 0], None).end_cell();
+        gds.begin_cell("NEW").boundary(2, 0, &square).boundary(1, 1, &square).end_cell();
+        gds.begin_cell("UNUSED").boundary(9, 0, &square).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let old = cell_layers(&library, "OLD").unwrap();
+        let new = cell_layers(&library, "NEW").unwrap();
+        let comparison = compare_layers(&old, &new);
+        assert_eq!(comparison.only_a, [(1, 0), (5, 0)]);
+        assert_eq!(comparison.only_b, [(1, 1)]);
+        assert_eq!(comparison.both, [(2, 0)]);
+        assert!(cell_layers(&library, "GONE").is_err());
+    }
+
+    #[test]
+    fn wide_layer_and_datatype_numbers_are_not_truncated() {
+        let square = [[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            // 40000 is stored as the INT16 bit pattern of -25536
+            .boundary(1000, 40000u16 as i16, &square)
+            .text(65535u16 as i16, 300, [5, 5], "MARK");
+        // Some tools write the numbers as INT32
+        gds.record(record::BOUNDARY, record::NO_DATA, &[])
+            .int32(record::LAYER, &[2000])
+            .int32(record::DATATYPE, &[50000])
+            .xy(&square)
+            .end_element()
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+        let layers: Vec<(u16, u16)> =
+            collect_layers(&library).into_iter().map(|info| (info.layer, info.datatype)).collect();
+        assert_eq!(layers, vec![(1000, 40000), (2000, 50000), (65535, 300)]);
+
+        // Written back as INT16 and read again unchanged
+        let written = parse_library(&write_library(&library).unwrap()).unwrap();
+        assert_eq!(collect_layers(&written), collect_layers(&library));
+    }
+}
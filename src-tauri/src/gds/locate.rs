@@ -0,0 +1,173 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use super::bbox::{Bbox, BboxCalculator};
+use super::cache::ParseCache;
+use super::flatten::{Flattener, Walk};
+use super::transform::Transform;
+use super::{GdsCell, GdsError, GdsLibrary};
+use crate::error::AppError;
+
+// Placements returned when the caller doesn't set a limit
+const DEFAULT_LOCATION_LIMIT: usize = 10_000;
+
+// One placement of a cell in a top cell. The position, the origin of the
+// placed cell, and `bbox` are in user units in the top cell's coordinates;
+// rotation is in degrees counterclockwise and `reflect` is about the X axis,
+// composed through every level. `path` names the cells from the top cell down
+// to the placed one, both included.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct CellLocation {
+    pub x: f64,
+    pub y: f64,
+    pub rotation: f64,
+    pub mag: f64,
+    pub reflect: bool,
+    pub bbox: Option<Bbox>,
+    pub path: Vec<String>,
+}
+
+// The placement of `target_bbox`, a cell's extent in its own coordinates,
+// through `path` under `transform`, scaled to user units by `scale`
+fn location(
+    path: &[&GdsCell],
+    transform: &Transform,
+    target_bbox: Option<Bbox>,
+    scale: f64,
+) -> CellLocation {
+    let determinant = transform.a * transform.d - transform.b * transform.c;
+    let bbox = target_bbox.map(|bbox| {
+        let placed = bbox.transformed(transform);
+        Bbox {
+            min_x: placed.min_x * scale,
+            min_y: placed.min_y * scale,
+            max_x: placed.max_x * scale,
+            max_y: placed.max_y * scale,
+        }
+    });
+    CellLocation {
+        x: transform.tx * scale,
+        y: transform.ty * scale,
+        rotation: transform.c.atan2(transform.a).to_degrees().rem_euclid(360.0),
+        mag: determinant.abs().sqrt(),
+        reflect: determinant < 0.0,
+        bbox,
+        path: path.iter().map(|cell| cell.name.clone()).collect(),
+    }
+}
+
+// Every placement of `target` reachable from `top`, depth first in file order
+// and each array position separately, at most `limit` of them. The walk stops
+// at the limit, so a cell placed millions of times costs no more than `limit`
+// placements. `top` itself being the target gives its one untransformed
+// placement.
+pub fn locate_cells(
+    library: &GdsLibrary,
+    top: &str,
+    target: &str,
+    limit: usize,
+) -> Result<Vec<CellLocation>, GdsError> {
+    let cells: HashMap<&str, &GdsCell> =
+        library.cells.iter().map(|cell| (cell.name.as_str(), cell)).collect();
+    if !cells.contains_key(top) {
+        return Err(GdsError::CellNotFound { cell: top.to_string() });
+    }
+    let (&target, _) = cells
+        .get_key_value(target)
+        .ok_or_else(|| GdsError::CellNotFound { cell: target.to_string() })?;
+
+    let mut parents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for cell in &library.cells {
+        for reference in &cell.references {
+            parents.entry(reference.cell.as_str()).or_default().push(cell.name.as_str());
+        }
+    }
+    let mut leads = HashSet::from([target]);
+    let mut pending = vec![target];
+    while let Some(name) = pending.pop() {
+        for &parent in parents.get(name).into_iter().flatten() {
+            if leads.insert(parent) {
+                pending.push(parent);
+            }
+        }
+    }
+
+    // Only references that can lead to the target are followed
+    let target_bbox = BboxCalculator::new(library).cell_bbox(target)?;
+    let scale = library.units.user_unit;
+    let mut locations = Vec::new();
+    let follow = |cell: &GdsCell| leads.contains(cell.name.as_str());
+    Flattener::new(library).walk(top, follow, &mut |path, transform| {
+        if locations.len() >= limit {
+            return Walk::Stop;
+        }
+        match path.last() {
+            Some(cell) if cell.name == target => {
+                locations.push(location(path, transform, target_bbox, scale));
+                Walk::Skip
+            }
+            _ => Walk::Enter,
+        }
+    })?;
+    Ok(locations)
+}
+
+// Command to find every instance of a cell under a top cell, with where it
+// lands and through which cells, e.g. to zoom to each one in turn. Returns at
+// most `limit` placements, 10,000 without one.
+#[tauri::command]
+pub async fn locate_cell(
+    path: String,
+    top_cell: String,
+    target_cell: String,
+    limit: Option<usize>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<CellLocation>, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_LOCATION_LIMIT);
+    if limit == 0 {
+        return Err(AppError::invalid_argument("Limit must be at least 1"));
+    }
+    let library = cache.library(&path)?;
+    Ok(locate_cells(&library, &top_cell, &target_cell, limit)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn placements_are_found_through_arrays_and_nesting() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("VIA").boundary(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]]);
+        gds.end_cell();
+        gds.begin_cell("UNRELATED").boundary(2, 0, &[[0, 0], [5, 0], [5, 5], [0, 0]]).end_cell();
+        gds.begin_cell("ROW").aref("VIA", [0, 0], 3, 1, [300, 0], [0, 100]).end_cell();
+        gds.begin_cell("TOP")
+            .sref("UNRELATED", [0, 0], None)
+            .sref("ROW", [1000, 0], Some((false, 90.0, 1.0)))
+            .sref("VIA", [-50, -50], None)
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let locations = locate_cells(&library, "TOP", "VIA", 100).unwrap();
+        assert_eq!(locations.len(), 4);
+        // The second array position, rotated with its row about x = 1000
+        let second = &locations[1];
+        assert!((second.x - 1.0).abs() < 1e-12 && (second.y - 0.1).abs() < 1e-12);
+        assert_eq!((second.rotation, second.mag, second.reflect), (90.0, 1.0, false));
+        assert_eq!(second.path, ["TOP", "ROW", "VIA"]);
+        let bbox = second.bbox.unwrap();
+        assert!((bbox.min_x - 0.99).abs() < 1e-12 && (bbox.max_y - 0.11).abs() < 1e-12);
+        assert_eq!(locations[3].path, ["TOP", "VIA"]);
+
+        assert_eq!(locate_cells(&library, "TOP", "VIA", 2).unwrap().len(), 2);
+        assert_eq!(locate_cells(&library, "VIA", "VIA", 2).unwrap()[0].path, ["VIA"]);
+        assert!(matches!(
+            locate_cells(&library, "TOP", "MISSING", 2),
+            Err(GdsError::CellNotFound { .. })
+        ));
+    }
+}
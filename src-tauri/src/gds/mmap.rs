@@ -0,0 +1,191 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Deref;
+use std::path::Path;
+
+use super::gzip;
+use super::GdsError;
+use crate::paths::refuses_symlink;
+
+// Files at least this large are memory-mapped rather than read into a buffer
+pub const MMAP_THRESHOLD_BYTES: u64 = 64 * 1024 * 1024;
+
+// Contents of a file, either mapped into memory or read into a buffer
+pub enum FileBytes {
+    #[cfg(all(unix, feature = "mmap"))]
+    Mapped(mapped::MappedFile),
+    Read(Vec<u8>),
+}
+
+impl Deref for FileBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            #[cfg(all(unix, feature = "mmap"))]
+            FileBytes::Mapped(mapped) => mapped,
+            FileBytes::Read(bytes) => bytes,
+        }
+    }
+}
+
+#[cfg(all(unix, feature = "mmap"))]
+mod mapped {
+    use memmap2::{Advice, Mmap, MmapOptions};
+    use std::fs::File;
+    use std::ops::Deref;
+
+    // A read-only mapping of a whole file, unmapped on drop. Pages are read
+    // lazily by the OS as the parser walks the records and can be dropped
+    // again under memory pressure, so the file never has to fit in the heap.
+    pub struct MappedFile(Mmap);
+
+    impl MappedFile {
+        pub fn map(file: &File, len: usize) -> std::io::Result<Self> {
+            if len == 0 {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "empty file"));
+            }
+            // SAFETY: the mapping is only read. Another process truncating the
+            // file while it is mapped makes reads past the new end fault, the
+            // risk every mapping carries (see the `mmap` feature in the README).
+            let mmap = unsafe { MmapOptions::new().len(len).map(file)? };
+            // The parser reads front to back, so ask for aggressive read-ahead;
+            // failing to is harmless
+            let _ = mmap.advise(Advice::Sequential);
+            Ok(MappedFile(mmap))
+        }
+    }
+
+    impl Deref for MappedFile {
+        type Target = [u8];
+
+        fn deref(&self) -> &[u8] {
+            &self.0
+        }
+    }
+}
+
+// Read everything `reader` holds, reserving `len_hint` bytes up front
+pub fn read_all(reader: &mut dyn Read, len_hint: usize) -> Result<Vec<u8>, GdsError> {
+    let mut bytes = Vec::with_capacity(len_hint);
+    reader.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+// Map `path` if it is at least `MMAP_THRESHOLD_BYTES` long and mapping is
+// supported, falling back to `read` for smaller files and wherever mapping
+// fails (some network and FUSE filesystems refuse it). `read` gets the file and
+// its length. A gzip-compressed file is never mapped: `read` gets a reader
+// inflating it on the fly and a hint of the inflated length instead, so only
+// the inflated bytes are ever held in memory. A symlink is refused while the
+// `follow_symlinks` setting is off.
+pub fn map_or_read<F>(path: &str, read: F) -> Result<FileBytes, GdsError>
+where
+    F: FnOnce(&mut dyn Read, usize) -> Result<Vec<u8>, GdsError>,
+{
+    if refuses_symlink(Path::new(path)) {
+        return Err(GdsError::SymlinkRefused { path: path.to_string() });
+    }
+    let mut file = File::open(path)?;
+    map_or_read_open(path, &mut file, read)
+}
+
+// `map_or_read` for `file`, already open at `path`, from its start wherever
+// earlier reads left it. The symlink check is up to the caller.
+pub fn map_or_read_open<F>(path: &str, file: &mut File, read: F) -> Result<FileBytes, GdsError>
+where
+    F: FnOnce(&mut dyn Read, usize) -> Result<Vec<u8>, GdsError>,
+{
+    file.seek(SeekFrom::Start(0))?;
+    if gzip::is_gzip(file, path)? {
+        // The clone shares the file position, which the decoder moves
+        let (mut decoder, len_hint) = gzip::decoder(file.try_clone()?)?;
+        return read(&mut decoder, len_hint).map(FileBytes::Read);
+    }
+    let len = file.metadata()?.len();
+    #[cfg(all(unix, feature = "mmap"))]
+    {
+        if len >= MMAP_THRESHOLD_BYTES {
+            match usize::try_from(len).map_err(std::io::Error::other) {
+                Ok(len) => match mapped::MappedFile::map(file, len) {
+                    Ok(mapped) => return Ok(FileBytes::Mapped(mapped)),
+                    Err(e) => log::warn!("Reading {} instead of mapping it: {}", path, e),
+                },
+                Err(e) => log::warn!("Reading {} instead of mapping it: {}", path, e),
+            }
+        }
+    }
+    read(file, usize::try_from(len).unwrap_or(0)).map(FileBytes::Read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+
+    #[test]
+    fn mapped_and_read_files_parse_the_same() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP").boundary(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 0]]).end_cell();
+        let contents = gds.finish();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("top.gds");
+        std::fs::write(&path, &contents).unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        // Small files are read through the fallback
+        let read = map_or_read(&path, read_all).unwrap();
+        assert!(matches!(read, FileBytes::Read(_)));
+        assert_eq!(&*read, contents.as_slice());
+
+        #[cfg(all(unix, feature = "mmap"))]
+        {
+            use crate::gds::parse_library;
+
+            let file = std::fs::File::open(&path).unwrap();
+            let mapped = mapped::MappedFile::map(&file, contents.len()).unwrap();
+            assert_eq!(parse_library(&mapped).unwrap(), parse_library(&contents).unwrap());
+            assert!(mapped::MappedFile::map(&file, 0).is_err());
+        }
+    }
+
+    // The fixture behind the mapped-vs-read figures in the README, too big for
+    // the regular run. On Linux, `cargo test --release mmap_fixture_memory --
+    // --ignored --nocapture` prints the time each path takes to parse it and
+    // the heap (anonymous memory) the process holds once it has.
+    #[cfg(all(target_os = "linux", feature = "mmap"))]
+    #[test]
+    #[ignore]
+    fn mmap_fixture_memory() {
+        use crate::gds::fixtures::large_boundary_library;
+        use crate::gds::parse_library;
+        use std::time::Instant;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("large.gds");
+        std::fs::write(&path, large_boundary_library(8192)).unwrap();
+        let path = path.to_string_lossy().to_string();
+        let heap = || {
+            let status = std::fs::read_to_string("/proc/self/status").unwrap();
+            status.lines().find(|line| line.starts_with("RssAnon:")).unwrap().to_string()
+        };
+
+        let size = std::fs::metadata(&path).unwrap().len() >> 20;
+        println!("{} MiB file, before parsing {}", size, heap());
+        for mapped in [false, true] {
+            let started = Instant::now();
+            let bytes = if mapped {
+                map_or_read(&path, read_all).unwrap()
+            } else {
+                let mut file = File::open(&path).unwrap();
+                FileBytes::Read(read_all(&mut file, 0).unwrap())
+            };
+            assert_eq!(matches!(bytes, FileBytes::Mapped(_)), mapped);
+            let library = parse_library(&bytes).unwrap();
+            let elapsed = started.elapsed().as_secs_f64();
+            let how = if mapped { "mapped" } else { "read" };
+            println!("{}: {:.2} s, once parsed {}", how, elapsed, heap());
+            assert_eq!(library.cells[0].elements.len(), 8192);
+        }
+    }
+}
@@ -0,0 +1,459 @@
+//! GDSII stream parsing.
+//!
+//! `record` walks the raw record stream, `parser` turns it into a [`GdsLibrary`]
+//! and `writer` encodes one back into a stream.
+
+pub mod angles;
+pub mod aref;
+pub mod bbox;
+pub mod boolean;
+pub mod buffer;
+pub mod cache;
+pub mod centroid;
+pub mod changes;
+pub mod cross_section;
+pub mod dates;
+pub mod diff;
+pub mod duplicates;
+pub mod dump;
+pub mod elements;
+pub mod flatten;
+pub mod freshness;
+pub mod gzip;
+pub mod handles;
+pub mod hierarchy;
+pub mod hit_test;
+pub mod incremental;
+pub mod instancing;
+pub mod interaction;
+pub mod jobs;
+pub mod labels;
+pub mod layers;
+pub mod locate;
+pub mod mmap;
+pub mod offgrid;
+pub mod outline;
+pub mod path_lengths;
+pub mod parser;
+pub mod problems;
+pub mod properties;
+pub mod record;
+pub mod rects;
+pub mod region;
+pub mod render_plan;
+pub mod snap;
+pub mod stats;
+pub mod streaming;
+pub mod tiles;
+pub mod transform;
+pub mod units;
+pub mod validate;
+pub mod writer;
+
+#[cfg(test)]
+pub mod fixtures;
+#[cfg(test)]
+mod fuzz;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::fmt;
+
+use crate::warning::Warning;
+
+pub use parser::parse_library;
+
+// Errors produced while reading or decoding a GDSII file
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(tag = "kind")]
+pub enum GdsError {
+    Io { message: String },
+    InvalidHeader { message: String },
+    // A record or value cut off at `offset`. `expected` names what should have
+    // been there; `found_bytes` is filled by `with_context`.
+    Truncated { offset: u64, expected: String, message: String, found_bytes: Vec<u8> },
+    InvalidRecord { offset: u64, expected: String, message: String, found_bytes: Vec<u8> },
+    InvalidUnits { message: String },
+    CyclicReference { cell: String },
+    CellNotFound { cell: String },
+    HierarchyTooDeep { cell: String, max_depth: usize },
+    DegenerateArray { cell: String, referenced: String, message: String },
+    Cancelled,
+    // `path` is a symlink and the `follow_symlinks` setting is off
+    SymlinkRefused { path: String },
+    // A reader panicked on the stream; `message` is what it panicked with
+    Internal { message: String },
+}
+
+impl fmt::Display for GdsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GdsError::Io { message } => write!(f, "I/O error: {}", message),
+            GdsError::InvalidHeader { message } => write!(f, "Unrecognized file header: {}", message),
+            GdsError::Truncated { offset, message, .. } => {
+                write!(f, "Truncated file at byte {}: {}", offset, message)
+            }
+            GdsError::InvalidRecord { offset, message, .. } => {
+                write!(f, "Invalid record at byte {}: {}", offset, message)
+            }
+            GdsError::InvalidUnits { message } => write!(f, "Invalid library units: {}", message),
+            GdsError::CyclicReference { cell } => {
+                write!(f, "Cell {} references itself through its hierarchy", cell)
+            }
+            GdsError::CellNotFound { cell } => write!(f, "Cell {} not found", cell),
+            GdsError::HierarchyTooDeep { cell, max_depth } => write!(
+                f,
+                "Hierarchy below {} is nested more than {} levels deep (possibly cyclic)",
+                cell, max_depth
+            ),
+            GdsError::DegenerateArray { cell, referenced, message } => {
+                write!(f, "Degenerate array of {} in cell {}: {}", referenced, cell, message)
+            }
+            GdsError::Cancelled => write!(f, "Parsing was cancelled"),
+            GdsError::SymlinkRefused { path } => {
+                write!(f, "Not following the symlink {}: symlinks are turned off", path)
+            }
+            GdsError::Internal { message } => {
+                write!(f, "Internal error while reading the stream: {}", message)
+            }
+        }
+    }
+}
+
+// Most bytes of the stream `with_context` attaches to an error
+const CONTEXT_BYTES: usize = 16;
+
+impl GdsError {
+    pub fn truncated(offset: u64, expected: impl Into<String>, message: impl Into<String>) -> Self {
+        GdsError::Truncated {
+            offset,
+            expected: expected.into(),
+            message: message.into(),
+            found_bytes: Vec::new(),
+        }
+    }
+
+    pub fn invalid_record(
+        offset: u64,
+        expected: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        GdsError::InvalidRecord {
+            offset,
+            expected: expected.into(),
+            message: message.into(),
+            found_bytes: Vec::new(),
+        }
+    }
+
+    // Attach up to 16 bytes of `bytes`, the stream being decoded, starting at
+    // the error's offset. Called once where a decode fails, so the steps
+    // below it don't each need the stream.
+    pub fn with_context(mut self, bytes: &[u8]) -> Self {
+        if let GdsError::Truncated { offset, found_bytes, .. }
+        | GdsError::InvalidRecord { offset, found_bytes, .. } = &mut self
+        {
+            let start = usize::try_from(*offset).unwrap_or(usize::MAX).min(bytes.len());
+            let end = bytes.len().min(start + CONTEXT_BYTES);
+            *found_bytes = bytes[start..end].to_vec();
+        }
+        self
+    }
+}
+
+impl std::error::Error for GdsError {}
+
+// Run the reader `read`, turning a panic into `GdsError::Internal`. A bug hit
+// by one malformed file then fails reading that file like any other bad
+// stream, rather than taking down the thread and, for a parse job, the
+// `parse-complete` event the frontend is waiting for.
+pub fn catch_reader_panic<T, F>(read: F) -> Result<T, GdsError>
+where
+    F: FnOnce() -> Result<T, GdsError>,
+{
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(read)).unwrap_or_else(|payload| {
+        let message = match payload.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => payload.downcast_ref::<String>().cloned().unwrap_or_default(),
+        };
+        log::error!("GDSII reader panicked: {}", message);
+        Err(GdsError::Internal { message })
+    })
+}
+
+impl From<std::io::Error> for GdsError {
+    fn from(error: std::io::Error) -> Self {
+        GdsError::Io { message: error.to_string() }
+    }
+}
+
+// Library units from the UNITS record
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+pub struct GdsUnits {
+    // Size of a database unit in user units (typically 0.001, i.e. 1nm in µm)
+    pub user_unit: f64,
+    // Size of a database unit in meters (typically 1e-9)
+    pub db_unit_meters: f64,
+}
+
+// Modification and access dates the writing tool recorded in BGNLIB or BGNSTR,
+// as ISO-8601 local times. `None` when the tool left them zero or invalid.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, JsonSchema)]
+pub struct GdsDates {
+    pub modified: Option<String>,
+    pub accessed: Option<String>,
+}
+
+// STRANS reflection/magnification/rotation of a reference
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+pub struct Strans {
+    // Reflect about the X axis before rotating
+    pub reflect_x: bool,
+    pub abs_mag: bool,
+    pub abs_angle: bool,
+    pub mag: f64,
+    // Counterclockwise rotation in degrees
+    pub angle: f64,
+}
+
+impl Default for Strans {
+    fn default() -> Self {
+        Strans { reflect_x: false, abs_mag: false, abs_angle: false, mag: 1.0, angle: 0.0 }
+    }
+}
+
+// Array parameters of an AREF: `columns` x `rows` instances spanning from the
+// reference origin to `column_point` and `row_point` (the raw AREF XY points)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+pub struct ArrayParams {
+    pub columns: u16,
+    pub rows: u16,
+    pub column_point: [i32; 2],
+    pub row_point: [i32; 2],
+}
+
+// An SREF (or AREF, when `array` is set) placing another cell
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct CellReference {
+    pub cell: String,
+    pub origin: [i32; 2],
+    pub strans: Strans,
+    pub array: Option<ArrayParams>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub properties: Vec<Property>,
+}
+
+// A PROPATTR/PROPVALUE pair attached to an element. The attribute number is kept
+// as written, including values outside the usual 1-127 range.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Property {
+    pub attribute: u16,
+    pub value: String,
+}
+
+// An axis-aligned rectangle: lower-left corner (`x`, `y`), width and height,
+// all positive sizes. Most boundaries in real designs are rectangles, and this
+// takes no heap allocation where a general polygon needs five points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+impl Rect {
+    // The rectangle a closed 5-point outline traces, in either direction and
+    // from any corner, or `None` for any other shape
+    pub fn from_points(points: &[[i32; 2]]) -> Option<Rect> {
+        let [first, b, c, d, last] = points else {
+            return None;
+        };
+        if first != last {
+            return None;
+        }
+        let corners = [*first, *b, *c, *d];
+        // Each edge must be horizontal or vertical, alternating
+        let horizontal = |i: usize| corners[i][1] == corners[(i + 1) % 4][1];
+        let vertical = |i: usize| corners[i][0] == corners[(i + 1) % 4][0];
+        let alternates = (0..4).all(|i| if i % 2 == 0 { horizontal(i) } else { vertical(i) })
+            || (0..4).all(|i| if i % 2 == 0 { vertical(i) } else { horizontal(i) });
+        if !alternates {
+            return None;
+        }
+        let (min_x, max_x) = (first[0].min(c[0]), first[0].max(c[0]));
+        let (min_y, max_y) = (first[1].min(c[1]), first[1].max(c[1]));
+        let w = max_x.checked_sub(min_x).filter(|&w| w > 0)?;
+        let h = max_y.checked_sub(min_y).filter(|&h| h > 0)?;
+        Some(Rect { x: min_x, y: min_y, w, h })
+    }
+
+    // Closed counterclockwise outline starting at the lower-left corner
+    pub fn points(&self) -> Vec<[i32; 2]> {
+        let (x1, y1) = (self.x + self.w, self.y + self.h);
+        vec![[self.x, self.y], [x1, self.y], [x1, y1], [self.x, y1], [self.x, self.y]]
+    }
+}
+
+// A geometric element of a cell. Coordinates are in database units.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum GdsElement {
+    Boundary {
+        layer: u16,
+        datatype: u16,
+        points: Vec<[i32; 2]>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        properties: Vec<Property>,
+    },
+    // A BOUNDARY that is an axis-aligned rectangle. Its outline is normalized
+    // to `Rect::points`, so the starting corner and direction aren't kept.
+    Rect {
+        layer: u16,
+        datatype: u16,
+        rect: Rect,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        properties: Vec<Property>,
+    },
+    Path {
+        layer: u16,
+        datatype: u16,
+        pathtype: i16,
+        width: i32,
+        // BGNEXTN/ENDEXTN: how far a pathtype 4 path extends past its end points
+        begin_extension: i32,
+        end_extension: i32,
+        points: Vec<[i32; 2]>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        properties: Vec<Property>,
+    },
+    Box {
+        layer: u16,
+        boxtype: u16,
+        points: Vec<[i32; 2]>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        properties: Vec<Property>,
+    },
+    Text {
+        layer: u16,
+        texttype: u16,
+        presentation: u16,
+        strans: Strans,
+        position: [i32; 2],
+        text: String,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        properties: Vec<Property>,
+    },
+    // A NODE: electrical connection points with no area, used by some
+    // connectivity flows
+    Node {
+        layer: u16,
+        nodetype: u16,
+        points: Vec<[i32; 2]>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        properties: Vec<Property>,
+    },
+}
+
+impl GdsElement {
+    // A BOUNDARY element, stored as `Rect` when it is a rectangle
+    pub fn boundary(
+        layer: u16,
+        datatype: u16,
+        points: Vec<[i32; 2]>,
+        properties: Vec<Property>,
+    ) -> GdsElement {
+        match Rect::from_points(&points) {
+            Some(rect) => GdsElement::Rect { layer, datatype, rect, properties },
+            None => GdsElement::Boundary { layer, datatype, points, properties },
+        }
+    }
+
+    // The element's `type` when serialized, e.g. "boundary" or "rect"
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GdsElement::Boundary { .. } => "boundary",
+            GdsElement::Rect { .. } => "rect",
+            GdsElement::Path { .. } => "path",
+            GdsElement::Box { .. } => "box",
+            GdsElement::Text { .. } => "text",
+            GdsElement::Node { .. } => "node",
+        }
+    }
+
+    // The (layer, datatype) pair the element is drawn on. BOX, TEXT and NODE
+    // elements use their boxtype/texttype/nodetype as the datatype.
+    pub fn layer_key(&self) -> (u16, u16) {
+        match self {
+            GdsElement::Boundary { layer, datatype, .. } => (*layer, *datatype),
+            GdsElement::Rect { layer, datatype, .. } => (*layer, *datatype),
+            GdsElement::Path { layer, datatype, .. } => (*layer, *datatype),
+            GdsElement::Box { layer, boxtype, .. } => (*layer, *boxtype),
+            GdsElement::Text { layer, texttype, .. } => (*layer, *texttype),
+            GdsElement::Node { layer, nodetype, .. } => (*layer, *nodetype),
+        }
+    }
+
+    // Move the element to another (layer, datatype), or (layer, boxtype) and
+    // so on, the counterpart of `layer_key`
+    pub fn set_layer_key(&mut self, (new_layer, new_datatype): (u16, u16)) {
+        let (layer, datatype) = match self {
+            GdsElement::Boundary { layer, datatype, .. }
+            | GdsElement::Rect { layer, datatype, .. }
+            | GdsElement::Path { layer, datatype, .. } => (layer, datatype),
+            GdsElement::Box { layer, boxtype, .. } => (layer, boxtype),
+            GdsElement::Text { layer, texttype, .. } => (layer, texttype),
+            GdsElement::Node { layer, nodetype, .. } => (layer, nodetype),
+        };
+        *layer = new_layer;
+        *datatype = new_datatype;
+    }
+
+    pub fn properties(&self) -> &[Property] {
+        match self {
+            GdsElement::Boundary { properties, .. }
+            | GdsElement::Rect { properties, .. }
+            | GdsElement::Path { properties, .. }
+            | GdsElement::Box { properties, .. }
+            | GdsElement::Text { properties, .. }
+            | GdsElement::Node { properties, .. } => properties,
+        }
+    }
+}
+
+// A structure (cell) definition
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct GdsCell {
+    pub name: String,
+    pub dates: GdsDates,
+    pub references: Vec<CellReference>,
+    // Geometry is fetched through the per-layer commands rather than sent with
+    // the hierarchy
+    #[serde(skip_serializing)]
+    pub elements: Vec<GdsElement>,
+}
+
+// A parsed GDSII library
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct GdsLibrary {
+    pub name: String,
+    // Stream format version from the HEADER record, e.g. 600 or 5
+    pub version: i16,
+    pub units: GdsUnits,
+    pub dates: GdsDates,
+    pub cells: Vec<GdsCell>,
+    // Problems in an out-of-spec stream that the parser recovered from
+    pub warnings: Vec<Warning>,
+}
+
+impl GdsLibrary {
+    pub fn cell(&self, name: &str) -> Option<&GdsCell> {
+        self.cells.iter().find(|cell| cell.name == name)
+    }
+}
+
+// Read and parse a GDSII file from disk, memory-mapping large files
+pub fn read_library(path: &str) -> Result<GdsLibrary, GdsError> {
+    let bytes = mmap::map_or_read(path, mmap::read_all)?;
+    parse_library(&bytes)
+}
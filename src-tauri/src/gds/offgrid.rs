@@ -0,0 +1,137 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::cache::ParseCache;
+use super::flatten::Flattener;
+use super::{GdsElement, GdsError, GdsLibrary};
+use crate::error::AppError;
+
+// Most off-grid vertices listed for one cell; the count of the rest is still
+// reported
+const MAX_OFFGRID_VERTICES: usize = 1000;
+
+// How far from a grid line a vertex may be, as a fraction of the grid, and
+// still count as on it. Covers the rounding of rotated and magnified
+// placements, not real offsets, which are at least a database unit.
+const GRID_TOLERANCE: f64 = 1e-6;
+
+// A vertex off the grid, in user units in top-cell coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+pub struct OffGridVertex {
+    pub layer: u16,
+    pub datatype: u16,
+    pub x: f64,
+    pub y: f64,
+}
+
+// Off-grid vertices of a cell. `count` includes vertices beyond the ones
+// listed.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct OffGridReport {
+    pub count: usize,
+    pub vertices: Vec<OffGridVertex>,
+}
+
+// The points an element is drawn from: boundary, rectangle and box corners
+// without the closing repeat of the first, and path centerline points. Path
+// outlines aren't checked, since an odd width puts their edges half a grid
+// step off even when the centerline is on it. Texts and nodes have none.
+fn defining_points(element: &GdsElement) -> Vec<[i32; 2]> {
+    let mut points = match element {
+        GdsElement::Boundary { points, .. } | GdsElement::Box { points, .. } => points.clone(),
+        GdsElement::Rect { rect, .. } => rect.points(),
+        GdsElement::Path { points, .. } => return points.clone(),
+        GdsElement::Text { .. } | GdsElement::Node { .. } => return Vec::new(),
+    };
+    if points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    points
+}
+
+fn on_grid(value: f64, grid: f64) -> bool {
+    let steps = value / grid;
+    (steps - steps.round()).abs() <= GRID_TOLERANCE
+}
+
+// Vertices of the geometry reachable from `cell` that don't lie on a grid of
+// `grid` user units, after placement, in file order. Each placement of a cell
+// is checked separately, so a cell that is on the grid by itself can still be
+// reported when it is placed at an off-grid origin.
+pub fn find_offgrid_vertices(
+    library: &GdsLibrary,
+    cell: &str,
+    grid: f64,
+) -> Result<OffGridReport, GdsError> {
+    let user_unit = library.units.user_unit;
+    let grid_dbu = grid / user_unit;
+    let mut report = OffGridReport { count: 0, vertices: Vec::new() };
+    Flattener::new(library).visit(cell, &mut |element, transform| {
+        let (layer, datatype) = element.layer_key();
+        for point in defining_points(element) {
+            let [x, y] = transform.apply_point(point);
+            if on_grid(x, grid_dbu) && on_grid(y, grid_dbu) {
+                continue;
+            }
+            report.count += 1;
+            if report.vertices.len() < MAX_OFFGRID_VERTICES {
+                let (x, y) = (x * user_unit, y * user_unit);
+                report.vertices.push(OffGridVertex { layer, datatype, x, y });
+            }
+        }
+    })?;
+    Ok(report)
+}
+
+// Command to list the vertices of a cell that are off a manufacturing grid
+// given in user units, e.g. 0.005 for a 5 nm grid, before running DRC
+#[tauri::command]
+pub async fn find_offgrid(
+    path: String,
+    cell: String,
+    grid: f64,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<OffGridReport, AppError> {
+    if !(grid.is_finite() && grid > 0.0) {
+        return Err(AppError::invalid_argument(format!("Grid must be positive, got {}", grid)));
+    }
+    let library = cache.library(&path)?;
+    Ok(find_offgrid_vertices(&library, &cell, grid)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn placements_and_rotations_are_checked_against_the_grid() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("PAD")
+            .boundary(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]])
+            // One corner 1 nm off a 5 nm grid
+            .boundary(2, 0, &[[0, 0], [10, 0], [11, 10], [0, 0]])
+            .path(3, 0, 0, 5, &[[0, 0], [20, 0]])
+            .end_cell();
+        gds.begin_cell("TOP")
+            .sref("PAD", [0, 0], None)
+            .sref("PAD", [100, 0], Some((false, 90.0, 1.0)))
+            // Every point of this one moves off the grid
+            .sref("PAD", [3, 0], None)
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let report = find_offgrid_vertices(&library, "TOP", 0.005).unwrap();
+        // The bad corner of the first two placements and all 9 points of the last
+        assert_eq!(report.count, 11);
+        let first = report.vertices[0];
+        assert_eq!((first.layer, first.datatype), (2, 0));
+        assert!((first.x - 0.011).abs() < 1e-12 && (first.y - 0.010).abs() < 1e-12);
+        // Rotated about the origin at x = 100
+        let rotated = report.vertices[1];
+        assert!((rotated.x - 0.090).abs() < 1e-12 && (rotated.y - 0.011).abs() < 1e-12);
+
+        assert_eq!(find_offgrid_vertices(&library, "PAD", 0.001).unwrap().count, 0);
+    }
+}
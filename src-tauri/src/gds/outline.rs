@@ -0,0 +1,99 @@
+use super::cache::ParseCache;
+use super::flatten::cell_polygons;
+use super::{GdsError, GdsLibrary};
+use crate::error::AppError;
+use crate::geometry::hull::{concave_hull, convex_hull};
+use crate::geometry::rtree::Rect;
+
+// Cells of the grid vertices are merged on for concave outlines, along the
+// longer side of the cell's extent
+const CONCAVE_GRID_CELLS: f64 = 256.0;
+
+// Vertices snapped to a grid of `CONCAVE_GRID_CELLS` across their extent, one
+// per grid point, so the concave hull's work stays bounded however much
+// geometry a cell holds
+fn thinned(vertices: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    let Some(extent) = Rect::around(vertices) else {
+        return Vec::new();
+    };
+    let side = (extent.max[0] - extent.min[0]).max(extent.max[1] - extent.min[1]);
+    if side == 0.0 {
+        return vertices.to_vec();
+    }
+    let pitch = side / CONCAVE_GRID_CELLS;
+    let snap = |value: f64, min: f64| min + ((value - min) / pitch).round() * pitch;
+    let mut snapped: Vec<[f64; 2]> = vertices
+        .iter()
+        .map(|point| [snap(point[0], extent.min[0]), snap(point[1], extent.min[1])])
+        .collect();
+    snapped.sort_by(|a, b| a[0].total_cmp(&b[0]).then(a[1].total_cmp(&b[1])));
+    snapped.dedup();
+    snapped
+}
+
+// The outline around all flattened polygons of `cell`, in database units: the
+// convex hull of their vertices, or with `concavity` a concave hull of them
+// merged onto a grid of 1/256 of the cell's longer side. Empty for a cell
+// without polygons.
+pub fn cell_outline(
+    library: &GdsLibrary,
+    cell: &str,
+    concavity: Option<f64>,
+) -> Result<Vec<[f64; 2]>, GdsError> {
+    let vertices: Vec<[f64; 2]> =
+        cell_polygons(library, cell)?.into_values().flatten().flatten().collect();
+    Ok(match concavity {
+        Some(concavity) => concave_hull(&thinned(&vertices), concavity),
+        None => convex_hull(&vertices),
+    })
+}
+
+// Command to get a simplified outline of a cell for floorplanning overviews,
+// in user units
+#[tauri::command]
+pub async fn get_cell_outline(
+    path: String,
+    cell: String,
+    concavity: Option<f64>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<[f64; 2]>, AppError> {
+    if let Some(concavity) = concavity.filter(|value| !(value.is_finite() && *value > 0.0)) {
+        return Err(AppError::invalid_argument(format!(
+            "Concavity must be a positive number, got {}",
+            concavity
+        )));
+    }
+    let library = cache.library(&path)?;
+    let user_unit = library.units.user_unit;
+    let outline = cell_outline(&library, &cell, concavity)?;
+    Ok(outline.into_iter().map(|[x, y]| [x * user_unit, y * user_unit]).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+    use crate::geometry::signed_area;
+
+    #[test]
+    fn outlines_hug_the_flattened_geometry() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("L")
+            .boundary(1, 0, &[[0, 0], [100, 0], [100, 10], [10, 10], [10, 100], [0, 100], [0, 0]])
+            .end_cell();
+        gds.begin_cell("TOP").sref("L", [0, 0], None).end_cell();
+        gds.begin_cell("EMPTY").end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let convex = cell_outline(&library, "TOP", None).unwrap();
+        assert_eq!(convex, [[0.0, 0.0], [100.0, 0.0], [100.0, 10.0], [10.0, 100.0], [0.0, 100.0]]);
+        // The concave outline cuts into the corner the L leaves open
+        let concave = cell_outline(&library, "TOP", Some(1.0)).unwrap();
+        assert!(signed_area(&concave) < signed_area(&convex) / 2.0, "{:?}", concave);
+
+        assert!(cell_outline(&library, "EMPTY", None).unwrap().is_empty());
+        assert!(cell_outline(&library, "EMPTY", Some(2.0)).unwrap().is_empty());
+        assert!(cell_outline(&library, "GONE", None).is_err());
+    }
+}
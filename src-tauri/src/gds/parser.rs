@@ -0,0 +1,1020 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use super::dates::dates_from_record;
+use super::record::{self, Record, RecordReader};
+use super::units::units_from_record;
+use super::{
+    ArrayParams, CellReference, GdsCell, GdsDates, GdsElement, GdsError, GdsLibrary, GdsUnits,
+    Property, Strans,
+};
+use crate::warning::{Warning, WarningCode};
+
+// STRANS flag bits
+pub const STRANS_REFLECT: u16 = 0x8000;
+pub const STRANS_ABS_MAG: u16 = 0x0004;
+pub const STRANS_ABS_ANGLE: u16 = 0x0002;
+
+// The kind of element currently being read, from its opening record
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ElementKind {
+    Boundary,
+    Path,
+    Sref,
+    Aref,
+    Text,
+    Node,
+    Box,
+}
+
+impl ElementKind {
+    fn from_record_type(record_type: u8) -> Option<Self> {
+        match record_type {
+            record::BOUNDARY => Some(ElementKind::Boundary),
+            record::PATH => Some(ElementKind::Path),
+            record::SREF => Some(ElementKind::Sref),
+            record::AREF => Some(ElementKind::Aref),
+            record::TEXT => Some(ElementKind::Text),
+            record::NODE => Some(ElementKind::Node),
+            record::BOX => Some(ElementKind::Box),
+            _ => None,
+        }
+    }
+}
+
+// The single INT32 value of a WIDTH-like record
+fn first_i32(record: &Record, name: &str) -> Result<i32, GdsError> {
+    record.i32_values()?.first().copied().ok_or_else(|| {
+        let message = format!("{} record has no data", name);
+        GdsError::invalid_record(record.offset, "an INT32 value", message)
+    })
+}
+
+// Layer or datatype-like number of a LAYER, DATATYPE, BOXTYPE, TEXTTYPE or
+// NODETYPE record. These are unsigned 16-bit values, so INT16 data above 32767
+// is read back as such. Some tools write them as INT32 instead; those are
+// accepted as long as they fit in 16 bits.
+fn layer_number(record: &Record) -> Result<u16, GdsError> {
+    if record.data_type != record::INT32 {
+        return Ok(record.i16_value()? as u16);
+    }
+    let name = record::record_type_name(record.record_type);
+    let value = first_i32(record, name)?;
+    u16::try_from(value).map_err(|_| {
+        GdsError::invalid_record(
+            record.offset,
+            "a value from 0 to 65535",
+            format!("{} value {} is outside 0 to 65535", name, value),
+        )
+    })
+}
+
+// Fields accumulated between an element's opening record and its ENDEL
+#[derive(Debug)]
+struct ElementBuilder {
+    kind: ElementKind,
+    offset: u64,
+    sname: Option<String>,
+    xy: Vec<[i32; 2]>,
+    colrow: Option<(u16, u16)>,
+    strans: Strans,
+    layer: u16,
+    datatype: u16,
+    pathtype: i16,
+    width: i32,
+    begin_extension: i32,
+    end_extension: i32,
+    presentation: u16,
+    text: String,
+    properties: Vec<Property>,
+    // PROPATTR waiting for its PROPVALUE
+    property_attribute: Option<u16>,
+    // Set when a record of the element was unusable; the element is dropped at
+    // its ENDEL, with the warning already given
+    damaged: bool,
+    // Set for geometry in a hierarchy-only parse: the element's records are
+    // passed over without being decoded and it is dropped at its ENDEL
+    skipped: bool,
+}
+
+// A finished element: either geometry or a reference to another cell
+enum ParsedElement {
+    Geometry(GdsElement),
+    Reference(CellReference),
+}
+
+impl ElementBuilder {
+    fn new(kind: ElementKind, offset: u64) -> Self {
+        ElementBuilder {
+            kind,
+            offset,
+            sname: None,
+            xy: Vec::new(),
+            colrow: None,
+            strans: Strans::default(),
+            layer: 0,
+            datatype: 0,
+            pathtype: 0,
+            width: 0,
+            begin_extension: 0,
+            end_extension: 0,
+            presentation: 0,
+            text: String::new(),
+            properties: Vec::new(),
+            property_attribute: None,
+            damaged: false,
+            skipped: false,
+        }
+    }
+
+    fn add_record(&mut self, record: &Record) -> Result<(), GdsError> {
+        match record.record_type {
+            // Some writers emit optional fields without a value; those keep
+            // their defaults rather than costing the element
+            record::STRANS
+            | record::MAG
+            | record::ANGLE
+            | record::PATHTYPE
+            | record::WIDTH
+            | record::BGNEXTN
+            | record::ENDEXTN
+            | record::PRESENTATION
+                if record.data.is_empty() => {}
+            record::SNAME => self.sname = Some(record.string()),
+            record::XY => {
+                let values = record.i32_values()?;
+                if values.len() % 2 != 0 {
+                    return Err(GdsError::invalid_record(
+                        record.offset,
+                        "an even number of coordinates",
+                        "XY record has an odd number of coordinates",
+                    ));
+                }
+                self.xy = values.chunks_exact(2).map(|c| [c[0], c[1]]).collect();
+            }
+            record::COLROW => {
+                let values = record.i16_values()?;
+                if values.len() < 2 {
+                    return Err(GdsError::invalid_record(
+                        record.offset,
+                        "a column and a row count",
+                        "COLROW record needs a column and a row count",
+                    ));
+                }
+                self.colrow = Some((values[0] as u16, values[1] as u16));
+            }
+            record::STRANS => {
+                let flags = record.bit_array()?;
+                self.strans.reflect_x = flags & STRANS_REFLECT != 0;
+                self.strans.abs_mag = flags & STRANS_ABS_MAG != 0;
+                self.strans.abs_angle = flags & STRANS_ABS_ANGLE != 0;
+            }
+            record::MAG => self.strans.mag = record.real8_value()?,
+            record::ANGLE => self.strans.angle = record.real8_value()?,
+            record::LAYER => self.layer = layer_number(record)?,
+            record::DATATYPE | record::BOXTYPE | record::TEXTTYPE | record::NODETYPE => {
+                self.datatype = layer_number(record)?
+            }
+            record::PATHTYPE => self.pathtype = record.i16_value()?,
+            record::WIDTH => self.width = first_i32(record, "WIDTH")?,
+            record::BGNEXTN => self.begin_extension = first_i32(record, "BGNEXTN")?,
+            record::ENDEXTN => self.end_extension = first_i32(record, "ENDEXTN")?,
+            record::PRESENTATION => self.presentation = record.bit_array()?,
+            record::STRING => self.text = record.string(),
+            record::PROPATTR => self.property_attribute = Some(record.i16_value()? as u16),
+            record::PROPVALUE => match self.property_attribute.take() {
+                Some(attribute) => {
+                    self.properties.push(Property { attribute, value: record.string() })
+                }
+                None => log::warn!("Ignoring PROPVALUE without PROPATTR at byte {}", record.offset),
+            },
+            // Other element fields are not needed yet
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Turn the accumulated fields into an element
+    fn finish(self) -> Result<ParsedElement, GdsError> {
+        let invalid = |expected: &str, message: &str| {
+            GdsError::invalid_record(self.offset, expected, message)
+        };
+        Ok(match self.kind {
+            ElementKind::Sref | ElementKind::Aref => {
+                let cell = self
+                    .sname
+                    .clone()
+                    .ok_or_else(|| invalid("an SNAME record", "reference without SNAME"))?;
+                let origin = self
+                    .xy
+                    .first()
+                    .copied()
+                    .ok_or_else(|| invalid("an XY record", "reference without XY"))?;
+                let array = if self.kind == ElementKind::Aref {
+                    let (columns, rows) = self
+                        .colrow
+                        .ok_or_else(|| invalid("a COLROW record", "AREF without COLROW"))?;
+                    if self.xy.len() < 3 {
+                        return Err(invalid("three XY points", "AREF XY needs three points"));
+                    }
+                    Some(ArrayParams {
+                        columns,
+                        rows,
+                        column_point: self.xy[1],
+                        row_point: self.xy[2],
+                    })
+                } else {
+                    None
+                };
+                ParsedElement::Reference(CellReference {
+                    cell,
+                    origin,
+                    strans: self.strans,
+                    array,
+                    properties: self.properties,
+                })
+            }
+            ElementKind::Boundary => ParsedElement::Geometry(GdsElement::boundary(
+                self.layer,
+                self.datatype,
+                self.xy,
+                self.properties,
+            )),
+            ElementKind::Path => ParsedElement::Geometry(GdsElement::Path {
+                layer: self.layer,
+                datatype: self.datatype,
+                pathtype: self.pathtype,
+                width: self.width,
+                begin_extension: self.begin_extension,
+                end_extension: self.end_extension,
+                points: self.xy,
+                properties: self.properties,
+            }),
+            ElementKind::Box => ParsedElement::Geometry(GdsElement::Box {
+                layer: self.layer,
+                boxtype: self.datatype,
+                points: self.xy,
+                properties: self.properties,
+            }),
+            ElementKind::Text => {
+                let position =
+                    *self.xy.first().ok_or_else(|| invalid("an XY record", "TEXT without XY"))?;
+                ParsedElement::Geometry(GdsElement::Text {
+                    layer: self.layer,
+                    texttype: self.datatype,
+                    presentation: self.presentation,
+                    strans: self.strans,
+                    position,
+                    text: self.text,
+                    properties: self.properties,
+                })
+            }
+            ElementKind::Node => ParsedElement::Geometry(GdsElement::Node {
+                layer: self.layer,
+                nodetype: self.datatype,
+                points: self.xy,
+                properties: self.properties,
+            }),
+        })
+    }
+}
+
+fn unexpected(record: &Record, context: &str) -> Warning {
+    Warning::new(
+        WarningCode::UnexpectedRecord,
+        Some(record.offset),
+        format!(
+            "unexpected {} record at byte {} {}",
+            record::record_type_name(record.record_type),
+            record.offset,
+            context
+        ),
+    )
+}
+
+// Byte offset a record error points at, if it has one
+fn error_offset(error: &GdsError) -> Option<u64> {
+    match error {
+        GdsError::Truncated { offset, .. } | GdsError::InvalidRecord { offset, .. } => {
+            Some(*offset)
+        }
+        _ => None,
+    }
+}
+
+// Most warnings kept for one parse; a badly damaged file would otherwise
+// produce one per record
+pub const MAX_WARNINGS: usize = 100;
+
+// Highest layer and datatype number the GDSII spec allows; larger ones are
+// read, but some tools reject them
+pub const MAX_SPEC_LAYER: u16 = 255;
+
+// Problems the parser recovered from, in file order. Each kept warning is
+// also passed to `on_warning` as it is found.
+struct Warnings<'a> {
+    kept: Vec<Warning>,
+    dropped: usize,
+    // Layer/datatype pairs already warned about as out of range
+    reported_layers: HashSet<(u16, u16)>,
+    on_warning: &'a mut dyn FnMut(&Warning),
+}
+
+impl<'a> Warnings<'a> {
+    fn new(on_warning: &'a mut dyn FnMut(&Warning)) -> Self {
+        Warnings { kept: Vec::new(), dropped: 0, reported_layers: HashSet::new(), on_warning }
+    }
+
+    fn push(&mut self, warning: Warning) {
+        log::debug!("GDSII parse warning: {}", warning);
+        if self.kept.len() < MAX_WARNINGS {
+            (self.on_warning)(&warning);
+            self.kept.push(warning);
+        } else {
+            self.dropped += 1;
+        }
+    }
+
+    // Warn once per layer/datatype pair that is outside the spec's range
+    fn check_layer(&mut self, (layer, datatype): (u16, u16), offset: u64) {
+        if (layer > MAX_SPEC_LAYER || datatype > MAX_SPEC_LAYER)
+            && self.reported_layers.insert((layer, datatype))
+        {
+            self.push(Warning::new(
+                WarningCode::LayerOutOfRange,
+                Some(offset),
+                format!(
+                    "layer {}/{} at byte {} is outside 0 to {}",
+                    layer, datatype, offset, MAX_SPEC_LAYER
+                ),
+            ));
+        }
+    }
+
+    fn finish(mut self) -> Vec<Warning> {
+        if self.dropped > 0 {
+            let message = format!("{} more warnings not shown", self.dropped);
+            let warning = Warning::new(WarningCode::WarningsTruncated, None, message);
+            (self.on_warning)(&warning);
+            self.kept.push(warning);
+        }
+        self.kept
+    }
+}
+
+// Add a finished element to `cell`, or warn and drop it if it is incomplete
+fn finish_element(builder: ElementBuilder, cell: Option<&mut GdsCell>, warnings: &mut Warnings) {
+    if builder.damaged || builder.skipped {
+        return;
+    }
+    let offset = builder.offset;
+    match (builder.finish(), cell) {
+        (Ok(ParsedElement::Geometry(geometry)), Some(cell)) => {
+            warnings.check_layer(geometry.layer_key(), offset);
+            cell.elements.push(geometry)
+        }
+        (Ok(ParsedElement::Reference(reference)), Some(cell)) => {
+            cell.references.push(reference)
+        }
+        (Ok(_), _) => {}
+        (Err(error), _) => warnings.push(Warning::new(
+            WarningCode::InvalidElement,
+            error_offset(&error),
+            format!("{}; dropping the element", error),
+        )),
+    }
+}
+
+// Add a structure to the library once it is closed, warning if it has no
+// ENDSTR or, in a full parse, nothing in it. `offset` is that of its BGNSTR.
+fn close_cell(
+    library: &mut GdsLibrary,
+    cell: GdsCell,
+    offset: u64,
+    ended: bool,
+    mode: ParseMode,
+    warnings: &mut Warnings,
+) {
+    if !ended {
+        let message = format!("structure {} has no ENDSTR", cell.name);
+        warnings.push(Warning::new(WarningCode::MissingEndstr, Some(offset), message));
+    }
+    if mode == ParseMode::Full && cell.elements.is_empty() && cell.references.is_empty() {
+        let message = format!("structure {} at byte {} is empty", cell.name, offset);
+        warnings.push(Warning::new(WarningCode::EmptyCell, Some(offset), message));
+    }
+    library.cells.push(cell);
+}
+
+// HEADER versions written by known tools: 0 for streams older than release 3.0,
+// 3 to 7 for releases 3.0 to 7.0, and 600 for release 6.0 as most current
+// tools write it
+pub const KNOWN_GDS_VERSIONS: &[i16] = &[0, 3, 4, 5, 6, 7, 600];
+
+// How many records are read between progress callbacks
+pub const PROGRESS_INTERVAL_RECORDS: usize = 50_000;
+
+// Progress of a running parse, as reported to the progress callback
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+pub struct ParseProgress {
+    pub bytes_read: u64,
+    pub total_bytes: u64,
+    pub cells_parsed: usize,
+}
+
+// How much of a library to parse. `HierarchyOnly` reads the cells and their
+// SREF/AREF references but leaves every cell's `elements` empty, for showing
+// the cell tree of a large file quickly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ParseMode {
+    #[default]
+    Full,
+    HierarchyOnly,
+}
+
+impl ParseMode {
+    // Whether a library parsed in this mode has everything one parsed in
+    // `wanted` has
+    pub fn covers(self, wanted: ParseMode) -> bool {
+        self == ParseMode::Full || wanted == ParseMode::HierarchyOnly
+    }
+}
+
+// Parse a complete GDSII byte stream into a library
+pub fn parse_library(bytes: &[u8]) -> Result<GdsLibrary, GdsError> {
+    parse_library_with_progress(bytes, ParseMode::Full, &mut |_| true, &mut |_| {})
+}
+
+// Parse a GDSII byte stream in `mode`, calling `on_progress` every
+// `PROGRESS_INTERVAL_RECORDS` records. Returning `false` from the callback
+// aborts the parse with `GdsError::Cancelled`.
+//
+// Out-of-spec streams are read as far as possible, with each problem recorded
+// in the library's `warnings` and passed to `on_warning` as it is found, up to
+// `MAX_WARNINGS`. A damaged record is skipped up to the next
+// plausible record header, an element that can't be completed is dropped, a
+// structure missing its ENDSTR ends at the next BGNSTR or at the end of the
+// stream, and a missing ENDLIB is tolerated. Only a stream that doesn't start
+// with a HEADER record or has no usable UNITS fails.
+pub fn parse_library_with_progress(
+    bytes: &[u8],
+    mode: ParseMode,
+    on_progress: &mut dyn FnMut(ParseProgress) -> bool,
+    on_warning: &mut dyn FnMut(&Warning),
+) -> Result<GdsLibrary, GdsError> {
+    parse_records(bytes, mode, on_progress, on_warning).map_err(|error| error.with_context(bytes))
+}
+
+// Read the HEADER record a stream must start with
+fn header_record<'a>(records: &mut RecordReader<'a>) -> Result<Record<'a>, GdsError> {
+    match records.next() {
+        Some(Ok(first)) if first.record_type == record::HEADER => Ok(first),
+        Some(Ok(first)) => Err(GdsError::InvalidHeader {
+            message: format!(
+                "expected HEADER record, found {}",
+                record::record_type_name(first.record_type)
+            ),
+        }),
+        Some(Err(_)) | None => Err(GdsError::InvalidHeader {
+            message: "file does not start with a GDSII record".to_string(),
+        }),
+    }
+}
+
+// Stream format version from the HEADER record at the start of `bytes`, without
+// reading further
+pub fn header_version(bytes: &[u8]) -> Result<i16, GdsError> {
+    header_record(&mut RecordReader::new(bytes))?.i16_value()
+}
+
+fn missing_endel(builder: &ElementBuilder) -> Warning {
+    Warning::new(
+        WarningCode::MissingEndel,
+        Some(builder.offset),
+        format!("{:?} element at byte {} has no ENDEL; dropping it", builder.kind, builder.offset),
+    )
+}
+
+fn parse_records(
+    bytes: &[u8],
+    mode: ParseMode,
+    on_progress: &mut dyn FnMut(ParseProgress) -> bool,
+    on_warning: &mut dyn FnMut(&Warning),
+) -> Result<GdsLibrary, GdsError> {
+    let mut records = RecordReader::new(bytes);
+    let mut warnings = Warnings::new(on_warning);
+    let version = match header_record(&mut records)?.i16_value() {
+        Ok(version) => {
+            if !KNOWN_GDS_VERSIONS.contains(&version) {
+                let message = format!("unrecognized GDSII version {}", version);
+                warnings.push(Warning::new(WarningCode::UnrecognizedVersion, Some(0), message));
+            }
+            version
+        }
+        Err(error) => {
+            let message = format!("{}; assuming version 0", error);
+            warnings.push(Warning::new(WarningCode::UnrecognizedVersion, Some(0), message));
+            0
+        }
+    };
+
+    let mut library = GdsLibrary {
+        name: String::new(),
+        version,
+        units: GdsUnits { user_unit: 0.0, db_unit_meters: 0.0 },
+        dates: GdsDates::default(),
+        cells: Vec::new(),
+        warnings: Vec::new(),
+    };
+    let mut units = None;
+    let mut cell: Option<GdsCell> = None;
+    // Offset of the open structure's BGNSTR
+    let mut cell_offset = 0;
+    let mut element: Option<ElementBuilder> = None;
+    let mut ended = false;
+    let mut index = 0;
+
+    while let Some(result) = records.next() {
+        let record = match result {
+            Ok(record) => record,
+            Err(error) => {
+                let offset = error_offset(&error).unwrap_or(bytes.len() as u64);
+                warnings.push(Warning::new(
+                    WarningCode::DamagedRecord,
+                    Some(offset),
+                    format!("{}; skipping to the next record", error),
+                ));
+                if records.resync(offset) {
+                    continue;
+                }
+                break;
+            }
+        };
+        if index % PROGRESS_INTERVAL_RECORDS == PROGRESS_INTERVAL_RECORDS - 1 {
+            let progress = ParseProgress {
+                bytes_read: record.offset,
+                total_bytes: bytes.len() as u64,
+                cells_parsed: library.cells.len(),
+            };
+            if !on_progress(progress) {
+                return Err(GdsError::Cancelled);
+            }
+        }
+        index += 1;
+
+        if let Some(builder) = element.as_mut() {
+            if record.record_type == record::ENDEL {
+                let builder = element.take().unwrap();
+                finish_element(builder, cell.as_mut(), &mut warnings);
+                continue;
+            }
+            if ElementKind::from_record_type(record.record_type).is_none()
+                && !matches!(record.record_type, record::ENDSTR | record::BGNSTR | record::ENDLIB)
+            {
+                if builder.skipped {
+                    continue;
+                }
+                if let Err(error) = builder.add_record(&record) {
+                    warnings.push(Warning::new(
+                        WarningCode::InvalidElement,
+                        error_offset(&error),
+                        format!("{}; dropping the element", error),
+                    ));
+                    builder.damaged = true;
+                }
+                continue;
+            }
+            // The element never got its ENDEL; the record is handled below
+            if !builder.damaged {
+                warnings.push(missing_endel(builder));
+            }
+            element = None;
+        }
+
+        match record.record_type {
+            record::BGNLIB => library.dates = dates_from_record(&record),
+            record::LIBNAME => library.name = record.string(),
+            record::UNITS => units = Some(units_from_record(&record)?),
+            record::BGNSTR => {
+                if let Some(open) = cell.take() {
+                    close_cell(&mut library, open, cell_offset, false, mode, &mut warnings);
+                }
+                cell_offset = record.offset;
+                cell = Some(GdsCell {
+                    name: String::new(),
+                    dates: dates_from_record(&record),
+                    references: Vec::new(),
+                    elements: Vec::new(),
+                });
+            }
+            record::STRNAME => match cell.as_mut() {
+                Some(cell) => cell.name = record.string(),
+                None => warnings.push(unexpected(&record, "outside a structure")),
+            },
+            record::ENDSTR => match cell.take() {
+                Some(cell) => {
+                    close_cell(&mut library, cell, cell_offset, true, mode, &mut warnings)
+                }
+                None => warnings.push(unexpected(&record, "outside a structure")),
+            },
+            record::ENDLIB => {
+                if let Some(open) = cell.take() {
+                    close_cell(&mut library, open, cell_offset, false, mode, &mut warnings);
+                }
+                ended = true;
+                break;
+            }
+            record_type => {
+                // Library-level records we don't need (REFLIBS, FONTS, ...) are skipped
+                if let Some(kind) = ElementKind::from_record_type(record_type) {
+                    let mut builder = ElementBuilder::new(kind, record.offset);
+                    builder.skipped = mode == ParseMode::HierarchyOnly
+                        && !matches!(kind, ElementKind::Sref | ElementKind::Aref);
+                    if cell.is_none() {
+                        warnings.push(unexpected(&record, "outside a structure; dropping it"));
+                        builder.damaged = true;
+                    }
+                    element = Some(builder);
+                } else if record::record_type_name(record_type) == "UNKNOWN" {
+                    warnings.push(Warning::new(
+                        WarningCode::UnknownRecord,
+                        Some(record.offset),
+                        format!(
+                            "unknown record type 0x{:02X} at byte {}; skipping it",
+                            record_type, record.offset
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    if !ended {
+        if let Some(builder) = element.filter(|builder| !builder.damaged) {
+            warnings.push(missing_endel(&builder));
+        }
+        if let Some(open) = cell.take() {
+            close_cell(&mut library, open, cell_offset, false, mode, &mut warnings);
+        }
+        let message = "missing ENDLIB record";
+        warnings.push(Warning::new(WarningCode::MissingEndlib, Some(bytes.len() as u64), message));
+    }
+
+    library.units = units.ok_or_else(|| GdsError::InvalidUnits {
+        message: "library has no UNITS record".to_string(),
+    })?;
+    library.warnings = warnings.finish();
+
+    Ok(library)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::Rect;
+
+    #[test]
+    fn parses_hierarchy_with_references() {
+        let mut gds = GdsBuilder::library("TESTLIB");
+        gds.begin_cell("LEAF").boundary(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]]).end_cell();
+        gds.begin_cell("TOP")
+            .sref("LEAF", [100, 200], Some((true, 90.0, 2.0)))
+            .aref("LEAF", [0, 0], 3, 2, [30, 0], [0, 20])
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        assert_eq!(library.name, "TESTLIB");
+        assert_eq!(library.units, GdsUnits { user_unit: 0.001, db_unit_meters: 1e-9 });
+        assert_eq!(library.cells.len(), 2);
+        let leaf = library.cell("LEAF").unwrap();
+        assert!(leaf.references.is_empty());
+        assert_eq!(
+            leaf.elements,
+            vec![GdsElement::Rect {
+                layer: 1,
+                datatype: 0,
+                rect: Rect { x: 0, y: 0, w: 10, h: 10 },
+                properties: Vec::new(),
+            }]
+        );
+
+        let top = library.cell("TOP").unwrap();
+        assert_eq!(top.references.len(), 2);
+        let sref = &top.references[0];
+        assert_eq!(sref.cell, "LEAF");
+        assert_eq!(sref.origin, [100, 200]);
+        assert!(sref.strans.reflect_x);
+        assert_eq!(sref.strans.angle, 90.0);
+        assert_eq!(sref.strans.mag, 2.0);
+        assert_eq!(
+            top.references[1].array,
+            Some(ArrayParams { columns: 3, rows: 2, column_point: [30, 0], row_point: [0, 20] })
+        );
+    }
+
+    #[test]
+    fn latin1_cell_names_resolve_like_ascii_ones() {
+        // "µ" in latin-1 is 0xB5, never valid UTF-8 on its own
+        let name = b"R_5\xB5M";
+        let mut gds = GdsBuilder::library("LIB");
+        gds.record(record::BGNSTR, record::INT16, &[0; 24])
+            .record(record::STRNAME, record::ASCII, name)
+            .boundary(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 0]])
+            .end_cell();
+        // Padded with a NUL here, unlike the STRNAME
+        gds.begin_cell("TOP")
+            .record(record::SREF, record::NO_DATA, &[])
+            .record(record::SNAME, record::ASCII, &[&name[..], &[0]].concat())
+            .xy(&[[5, 5]])
+            .end_element()
+            .text(2, 0, [0, 0], "caf\u{e9}")
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        assert!(library.warnings.is_empty(), "{:?}", library.warnings);
+        assert_eq!(library.cells[0].name, "R_5\u{b5}M");
+        let top = library.cell("TOP").unwrap();
+        assert_eq!(top.references[0].cell, library.cells[0].name);
+        // UTF-8 text stays UTF-8
+        assert!(matches!(&top.elements[0], GdsElement::Text { text, .. } if text == "caf\u{e9}"));
+    }
+
+    #[test]
+    fn odd_length_and_empty_records_parse_cleanly() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.record(record::BGNSTR, record::INT16, &[0; 24]).odd_string(record::STRNAME, "INV");
+        gds.boundary(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 0]]).end_cell();
+        gds.record(record::BGNSTR, record::INT16, &[0; 24]).odd_string(record::STRNAME, "TOP");
+        gds.record(record::SREF, record::NO_DATA, &[])
+            .odd_string(record::SNAME, "INV")
+            // Optional fields written without a value keep their defaults
+            .record(record::STRANS, record::BIT_ARRAY, &[])
+            .record(record::MAG, record::REAL8, &[])
+            .xy(&[[5, 5]])
+            .end_element();
+        gds.record(record::PATH, record::NO_DATA, &[])
+            .int16(record::LAYER, &[2])
+            .int16(record::DATATYPE, &[0])
+            .record(record::WIDTH, record::INT32, &[])
+            .xy(&[[0, 0], [20, 0]])
+            .end_element();
+        gds.text(3, 0, [1, 1], "").end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        assert!(library.warnings.is_empty(), "{:?}", library.warnings);
+        let top = library.cell("TOP").unwrap();
+        assert_eq!(top.references[0].cell, "INV");
+        assert_eq!(top.references[0].strans, Strans::default());
+        assert_eq!(top.elements.len(), 2);
+        assert_eq!(library.cell("INV").unwrap().elements.len(), 1);
+    }
+
+    #[test]
+    fn hierarchy_only_parses_skip_geometry() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("LEAF")
+            .boundary(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]])
+            .text(2, 0, [5, 5], "PIN")
+            .end_cell();
+        gds.begin_cell("TOP")
+            .sref("LEAF", [100, 200], None)
+            .path(3, 0, 0, 4, &[[0, 0], [50, 0]])
+            .aref("LEAF", [0, 0], 3, 2, [30, 0], [0, 20])
+            .end_cell();
+        let bytes = gds.finish();
+        let full = parse_library(&bytes).unwrap();
+        let mode = ParseMode::HierarchyOnly;
+        let hierarchy =
+            parse_library_with_progress(&bytes, mode, &mut |_| true, &mut |_| {}).unwrap();
+
+        assert!(hierarchy.cells.iter().all(|cell| cell.elements.is_empty()));
+        assert!(hierarchy.warnings.is_empty());
+        let names = |library: &GdsLibrary| -> Vec<String> {
+            library.cells.iter().map(|cell| cell.name.clone()).collect()
+        };
+        assert_eq!(names(&hierarchy), names(&full));
+        assert_eq!(hierarchy.cell("TOP").unwrap().references, full.cell("TOP").unwrap().references);
+        assert!(ParseMode::Full.covers(ParseMode::HierarchyOnly));
+        assert!(!ParseMode::HierarchyOnly.covers(ParseMode::Full));
+    }
+
+    #[test]
+    fn box_records_stay_box_elements() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("WELL")
+            .box_element(5, 2, &[[0, 0], [40, 0], [40, 20], [0, 20], [0, 0]])
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        assert_eq!(
+            library.cells[0].elements,
+            vec![GdsElement::Box {
+                layer: 5,
+                boxtype: 2,
+                points: vec![[0, 0], [40, 0], [40, 20], [0, 20], [0, 0]],
+                properties: Vec::new(),
+            }]
+        );
+    }
+
+    #[test]
+    fn node_records_keep_their_nodetype_and_points() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("NET").node(7, 3, &[[0, 0], [25, 40]]).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let node = &library.cells[0].elements[0];
+        assert_eq!(
+            *node,
+            GdsElement::Node {
+                layer: 7,
+                nodetype: 3,
+                points: vec![[0, 0], [25, 40]],
+                properties: Vec::new(),
+            }
+        );
+        assert_eq!(node.layer_key(), (7, 3));
+        assert!(library.warnings.is_empty());
+    }
+
+    #[test]
+    fn bgnlib_and_bgnstr_dates_are_exposed() {
+        let mut gds = GdsBuilder::empty();
+        gds.int16(record::HEADER, &[600])
+            // Modified in 2023, accessed with a years-since-1900 date
+            .int16(record::BGNLIB, &[2023, 11, 2, 9, 30, 0, 124, 1, 15, 8, 0, 5])
+            .string(record::LIBNAME, "LIB")
+            .reals(record::UNITS, &[0.001, 1e-9])
+            .int16(record::BGNSTR, &[2023, 10, 1, 12, 0, 0, 0, 0, 0, 0, 0, 0])
+            .string(record::STRNAME, "TOP")
+            .end_cell()
+            // A tool that writes garbage dates
+            .int16(record::BGNSTR, &[2023, 2, 30, 99, 0, 0])
+            .string(record::STRNAME, "JUNK")
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let dates = |modified: Option<&str>, accessed: Option<&str>| GdsDates {
+            modified: modified.map(str::to_string),
+            accessed: accessed.map(str::to_string),
+        };
+        assert_eq!(
+            library.dates,
+            dates(Some("2023-11-02T09:30:00"), Some("2024-01-15T08:00:05"))
+        );
+        assert_eq!(library.cells[0].dates, dates(Some("2023-10-01T12:00:00"), None));
+        assert_eq!(library.cells[1].dates, GdsDates::default());
+    }
+
+    #[test]
+    fn rejects_non_gds_input() {
+        assert!(matches!(
+            parse_library(b"%SEMI-OASIS\r\n"),
+            Err(GdsError::InvalidHeader { .. })
+        ));
+        assert!(matches!(parse_library(&[]), Err(GdsError::InvalidHeader { .. })));
+    }
+
+    #[test]
+    fn header_versions_are_kept_and_unknown_ones_warned_about() {
+        let square = [[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]];
+        let mut gds = GdsBuilder::library_with_version("LIB", 5);
+        gds.begin_cell("TOP").boundary(1, 0, &square).end_cell();
+        let bytes = gds.finish();
+        let library = parse_library(&bytes).unwrap();
+        assert_eq!((library.version, library.warnings), (5, vec![]));
+        assert_eq!(header_version(&bytes[..6]), Ok(5));
+
+        let mut gds = GdsBuilder::library_with_version("LIB", 42);
+        gds.begin_cell("TOP").boundary(1, 0, &square).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+        assert_eq!(library.version, 42);
+        let warning = Warning::new(
+            WarningCode::UnrecognizedVersion,
+            Some(0),
+            "unrecognized GDSII version 42",
+        );
+        assert_eq!(library.warnings, [warning]);
+        assert!(matches!(header_version(b"%SEMI-OASIS\r\n"), Err(GdsError::InvalidHeader { .. })));
+    }
+
+    #[test]
+    fn missing_endlib_is_a_warning() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP").boundary(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 0]]).end_cell();
+        let mut bytes = gds.finish();
+        bytes.truncate(bytes.len() - 4);
+        let library = parse_library(&bytes).unwrap();
+        assert_eq!(library.cells.len(), 1);
+        let end = bytes.len() as u64;
+        let warning = Warning::new(WarningCode::MissingEndlib, Some(end), "missing ENDLIB record");
+        assert_eq!(library.warnings, [warning]);
+    }
+
+    #[test]
+    fn questionable_contents_are_warned_about_as_they_are_found() {
+        let square = [[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        // A record type no GDSII release defines
+        gds.record(0x70, record::NO_DATA, &[]);
+        gds.begin_cell("EMPTY").end_cell();
+        gds.begin_cell("TOP")
+            .boundary(300, 0, &square)
+            .boundary(300, 0, &square)
+            .boundary(1, 256, &square)
+            .boundary(255, 255, &square)
+            .end_cell();
+        let bytes = gds.finish();
+        let mut emitted = Vec::new();
+        let library =
+            parse_library_with_progress(&bytes, ParseMode::Full, &mut |_| true, &mut |warning| {
+                emitted.push(warning.clone())
+            })
+            .unwrap();
+
+        let codes: Vec<WarningCode> = library.warnings.iter().map(|warning| warning.code).collect();
+        assert_eq!(
+            codes,
+            [
+                WarningCode::UnknownRecord,
+                WarningCode::EmptyCell,
+                WarningCode::LayerOutOfRange,
+                WarningCode::LayerOutOfRange,
+            ]
+        );
+        assert!(library.warnings.iter().all(|warning| warning.offset.is_some()));
+        assert!(library.warnings[2].message.starts_with("layer 300/0 at byte"));
+        assert_eq!(emitted, library.warnings);
+        let json = serde_json::to_value(&library.warnings[1]).unwrap();
+        assert_eq!(json["code"], "empty_cell");
+    }
+
+    #[test]
+    fn out_of_spec_streams_keep_what_parsed() {
+        let square = [[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        // An element before any BGNSTR
+        gds.boundary(1, 0, &square);
+        gds.begin_cell("A").boundary(1, 0, &square).end_cell();
+        // Zero padding between structures
+        gds.raw(&[0; 6]);
+        // An element cut off before its ENDEL
+        gds.begin_cell("B")
+            .boundary(2, 0, &square)
+            .record(record::BOUNDARY, record::NO_DATA, &[])
+            .int16(record::LAYER, &[3])
+            .end_cell();
+        // A structure left open at ENDLIB
+        gds.begin_cell("C").boundary(4, 0, &square);
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let names: Vec<&str> = library.cells.iter().map(|cell| cell.name.as_str()).collect();
+        assert_eq!(names, ["A", "B", "C"]);
+        assert!(library.cells.iter().all(|cell| cell.elements.len() == 1));
+        assert_eq!(library.cell("B").unwrap().elements[0].layer_key(), (2, 0));
+        assert_eq!(library.warnings.len(), 4, "{:?}", library.warnings);
+        let codes: Vec<WarningCode> = library.warnings.iter().map(|warning| warning.code).collect();
+        assert_eq!(
+            codes,
+            [
+                WarningCode::UnexpectedRecord,
+                WarningCode::DamagedRecord,
+                WarningCode::MissingEndel,
+                WarningCode::MissingEndstr,
+            ]
+        );
+        assert!(library.warnings[0].message.contains("outside a structure"));
+        assert!(library.warnings[1].message.contains("skipping to the next record"));
+        assert!(library.warnings[2].message.contains("has no ENDEL"));
+        assert!(library.warnings[3].message.contains("structure C has no ENDSTR"));
+    }
+
+    #[test]
+    fn failures_report_their_offset_and_bytes() {
+        // A UNITS record cut short after one and a half values
+        let mut gds = GdsBuilder::empty();
+        gds.record(record::HEADER, record::INT16, &600i16.to_be_bytes())
+            .record(record::BGNLIB, record::INT16, &[0; 24])
+            .string(record::LIBNAME, "LIB");
+        let units = record::encode_real8(0.001);
+        gds.record(record::UNITS, record::REAL8, &[&units[..], &units[..4]].concat());
+        // After the 6-byte HEADER, 28-byte BGNLIB and 8-byte LIBNAME
+        let units_offset = 42;
+
+        let bytes = gds.finish();
+        let error = parse_library(&bytes).unwrap_err();
+        match &error {
+            GdsError::InvalidRecord { offset, expected, found_bytes, .. } => {
+                assert_eq!(*offset, units_offset);
+                assert_eq!(expected, "REAL8 data in whole 8-byte values");
+                // The 16-byte UNITS record, header included
+                assert_eq!(found_bytes[..], bytes[42..58]);
+            }
+            other => panic!("expected an invalid record, got {:?}", other),
+        }
+        assert!(error.to_string().starts_with(&format!("Invalid record at byte {}", units_offset)));
+    }
+}
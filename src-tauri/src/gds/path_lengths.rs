@@ -0,0 +1,102 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::cache::ParseCache;
+use super::flatten::Flattener;
+use super::units::{CoordConvention, CoordMapping, LengthUnit};
+use super::{GdsElement, GdsError, GdsLibrary};
+use crate::error::AppError;
+
+// Summed centerline length of the PATH elements of a layer, and how many
+// segments of non-zero length it is made of
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+pub struct PathLengths {
+    pub total_length: f64,
+    pub segment_count: u64,
+}
+
+// Centerline lengths of the paths on `layer` (and `datatype`, if given) that
+// are reachable from `cell`, in database units multiplied by `scale`. Each
+// placement counts separately, with its magnification. Segments between
+// repeated points add nothing, so a path of a single point, or of the same
+// point over and over, isn't counted at all.
+pub fn path_lengths(
+    library: &GdsLibrary,
+    cell: &str,
+    layer: u16,
+    datatype: Option<u16>,
+    scale: f64,
+) -> Result<PathLengths, GdsError> {
+    let mut lengths = PathLengths { total_length: 0.0, segment_count: 0 };
+    Flattener::new(library).visit(cell, &mut |element, transform| {
+        let GdsElement::Path { layer: path_layer, datatype: path_datatype, points, .. } = element
+        else {
+            return;
+        };
+        if *path_layer != layer || datatype.is_some_and(|datatype| datatype != *path_datatype) {
+            return;
+        }
+        let placed: Vec<[f64; 2]> =
+            points.iter().map(|&point| transform.apply_point(point)).collect();
+        for pair in placed.windows(2) {
+            let length = (pair[1][0] - pair[0][0]).hypot(pair[1][1] - pair[0][1]);
+            if length > 0.0 {
+                lengths.total_length += length * scale;
+                lengths.segment_count += 1;
+            }
+        }
+    })?;
+    Ok(lengths)
+}
+
+// Command to sum the centerline lengths of a layer's paths through the
+// hierarchy, for wire length and resistance estimates. Lengths are in `unit`,
+// or user units without one.
+#[tauri::command]
+pub async fn get_path_lengths(
+    path: String,
+    cell: String,
+    layer: u16,
+    datatype: Option<u16>,
+    unit: Option<LengthUnit>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<PathLengths, AppError> {
+    let library = cache.library(&path)?;
+    let convention = unit.map(|unit| CoordConvention { unit, flip_y: false });
+    let scale = CoordMapping::new(convention.as_ref(), &library.units).scale;
+    Ok(path_lengths(&library, &cell, layer, datatype, scale)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn lengths_follow_placements_and_skip_zero_length_segments() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("WIRE")
+            // 3-4-5 triangle legs and a repeated point: two segments, 7 long
+            .path(1, 0, 0, 10, &[[0, 0], [3, 0], [3, 0], [3, 4]])
+            // A single point and a path of one point repeated
+            .path(1, 0, 0, 10, &[[5, 5]])
+            .path(1, 0, 0, 10, &[[5, 5], [5, 5]])
+            .path(1, 1, 0, 10, &[[0, 0], [0, 100]])
+            .path(2, 0, 0, 10, &[[0, 0], [0, 1000]])
+            .end_cell();
+        gds.begin_cell("TOP")
+            .sref("WIRE", [0, 0], None)
+            .sref("WIRE", [100, 0], Some((false, 90.0, 2.0)))
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let lengths = path_lengths(&library, "TOP", 1, Some(0), 1.0).unwrap();
+        assert_eq!(lengths.segment_count, 4);
+        assert!((lengths.total_length - (7.0 + 14.0)).abs() < 1e-9);
+        // Every datatype of the layer, in µm
+        let lengths = path_lengths(&library, "TOP", 1, None, 0.001).unwrap();
+        assert_eq!(lengths.segment_count, 6);
+        assert!((lengths.total_length - 0.321).abs() < 1e-12);
+    }
+}
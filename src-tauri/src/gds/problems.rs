@@ -0,0 +1,76 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::cache::ParseCache;
+use super::hit_test::{ElementRef, HitIndex, HitIndexCache};
+use crate::error::AppError;
+use crate::geometry::check::{polygon_issue, PolygonIssue};
+
+// A flattened polygon of a cell with an issue; `layer`, `datatype` and `index`
+// identify it as in `hit_test`
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ProblemPolygon {
+    #[serde(flatten)]
+    pub element: ElementRef,
+    pub issue: PolygonIssue,
+}
+
+// Every polygon of the cell `index` was built for that has an issue, in layer,
+// datatype and index order
+pub fn problem_polygons(index: &HitIndex) -> Vec<ProblemPolygon> {
+    index
+        .polygons()
+        .iter()
+        .filter_map(|(element, outline)| {
+            Some(ProblemPolygon { element: *element, issue: polygon_issue(outline)? })
+        })
+        .collect()
+}
+
+// Command to check a cell's flattened geometry for self-intersecting,
+// zero-area and degenerate polygons before running boolean operations or DRC
+#[tauri::command]
+pub async fn find_problem_polygons(
+    path: String,
+    cell: String,
+    cache: tauri::State<'_, ParseCache>,
+    indexes: tauri::State<'_, HitIndexCache>,
+) -> Result<Vec<ProblemPolygon>, AppError> {
+    let library = cache.library(&path)?;
+    let index = indexes.index(&path, &library, &cell)?;
+    Ok(problem_polygons(&index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn problem_polygons_are_found_through_references() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("BAD")
+            .boundary(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]])
+            .boundary(1, 0, &[[0, 0], [10, 10], [10, 0], [0, 10], [0, 0]])
+            .boundary(2, 3, &[[0, 0], [5, 0], [10, 0], [0, 0]])
+            .end_cell();
+        gds.begin_cell("TOP").sref("BAD", [100, 0], None).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+        let index = HitIndex::build(&library, "TOP").unwrap();
+
+        let element = |layer, datatype, index| ElementRef { layer, datatype, index };
+        assert_eq!(
+            problem_polygons(&index),
+            [
+                ProblemPolygon { element: element(1, 0, 1), issue: PolygonIssue::SelfIntersection },
+                ProblemPolygon { element: element(2, 3, 0), issue: PolygonIssue::ZeroArea },
+            ]
+        );
+        let json = serde_json::to_value(&problem_polygons(&index)[1]).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "layer": 2, "datatype": 3, "index": 0, "issue": "ZeroArea" })
+        );
+    }
+}
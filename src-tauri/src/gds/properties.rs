@@ -0,0 +1,116 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::cache::ParseCache;
+use super::{GdsCell, GdsError, GdsLibrary, Property};
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PropertyOwner {
+    // Index into the cell's geometric elements
+    Element,
+    // Index into the cell's SREF/AREF references
+    Reference,
+}
+
+// The properties of one element of a cell
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ElementProperties {
+    pub owner: PropertyOwner,
+    pub index: usize,
+    pub layer: Option<u16>,
+    pub datatype: Option<u16>,
+    // Referenced cell, for references
+    pub cell: Option<String>,
+    pub properties: Vec<Property>,
+}
+
+// Properties of the elements and references defined directly in `cell`, in file
+// order. Elements without properties are left out.
+pub fn cell_properties(cell: &GdsCell) -> Vec<ElementProperties> {
+    let elements = cell
+        .elements
+        .iter()
+        .enumerate()
+        .filter(|(_, element)| !element.properties().is_empty())
+        .map(|(index, element)| {
+            let (layer, datatype) = element.layer_key();
+            ElementProperties {
+                owner: PropertyOwner::Element,
+                index,
+                layer: Some(layer),
+                datatype: Some(datatype),
+                cell: None,
+                properties: element.properties().to_vec(),
+            }
+        });
+    let references = cell
+        .references
+        .iter()
+        .enumerate()
+        .filter(|(_, reference)| !reference.properties.is_empty())
+        .map(|(index, reference)| ElementProperties {
+            owner: PropertyOwner::Reference,
+            index,
+            layer: None,
+            datatype: None,
+            cell: Some(reference.cell.clone()),
+            properties: reference.properties.clone(),
+        });
+    elements.chain(references).collect()
+}
+
+pub fn library_cell_properties(
+    library: &GdsLibrary,
+    cell: &str,
+) -> Result<Vec<ElementProperties>, GdsError> {
+    let cell =
+        library.cell(cell).ok_or_else(|| GdsError::CellNotFound { cell: cell.to_string() })?;
+    Ok(cell_properties(cell))
+}
+
+// Command to get the PROPATTR/PROPVALUE pairs of a cell's elements
+#[tauri::command]
+pub async fn get_element_properties(
+    path: String,
+    cell: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<ElementProperties>, AppError> {
+    Ok(library_cell_properties(&*cache.library(&path)?, &cell)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn properties_are_grouped_per_element() {
+        let square = [[0, 0], [1, 0], [1, 1], [0, 1], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &square)
+            // Attribute numbers outside 1-127 are kept as they are
+            .boundary_with_properties(2, 5, &square, &[(1, "net=VDD"), (300, "w=0.5")])
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let properties = library_cell_properties(&library, "TOP").unwrap();
+        assert_eq!(properties.len(), 1);
+        assert_eq!((properties[0].owner, properties[0].index), (PropertyOwner::Element, 1));
+        assert_eq!((properties[0].layer, properties[0].datatype), (Some(2), Some(5)));
+        assert_eq!(
+            properties[0].properties,
+            vec![
+                Property { attribute: 1, value: "net=VDD".to_string() },
+                Property { attribute: 300, value: "w=0.5".to_string() },
+            ]
+        );
+        assert!(matches!(
+            library_cell_properties(&library, "NOPE"),
+            Err(GdsError::CellNotFound { .. })
+        ));
+    }
+}
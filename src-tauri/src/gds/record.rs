@@ -0,0 +1,427 @@
+use super::GdsError;
+
+// GDSII record types
+pub const HEADER: u8 = 0x00;
+pub const BGNLIB: u8 = 0x01;
+pub const LIBNAME: u8 = 0x02;
+pub const UNITS: u8 = 0x03;
+pub const ENDLIB: u8 = 0x04;
+pub const BGNSTR: u8 = 0x05;
+pub const STRNAME: u8 = 0x06;
+pub const ENDSTR: u8 = 0x07;
+pub const BOUNDARY: u8 = 0x08;
+pub const PATH: u8 = 0x09;
+pub const SREF: u8 = 0x0A;
+pub const AREF: u8 = 0x0B;
+pub const TEXT: u8 = 0x0C;
+pub const LAYER: u8 = 0x0D;
+pub const DATATYPE: u8 = 0x0E;
+pub const WIDTH: u8 = 0x0F;
+pub const XY: u8 = 0x10;
+pub const ENDEL: u8 = 0x11;
+pub const SNAME: u8 = 0x12;
+pub const COLROW: u8 = 0x13;
+pub const NODE: u8 = 0x15;
+pub const TEXTTYPE: u8 = 0x16;
+pub const PRESENTATION: u8 = 0x17;
+pub const STRING: u8 = 0x19;
+pub const STRANS: u8 = 0x1A;
+pub const MAG: u8 = 0x1B;
+pub const ANGLE: u8 = 0x1C;
+pub const PATHTYPE: u8 = 0x21;
+pub const NODETYPE: u8 = 0x2A;
+pub const PROPATTR: u8 = 0x2B;
+pub const PROPVALUE: u8 = 0x2C;
+pub const BOX: u8 = 0x2D;
+pub const BOXTYPE: u8 = 0x2E;
+pub const BGNEXTN: u8 = 0x30;
+pub const ENDEXTN: u8 = 0x31;
+
+// GDSII data types
+pub const NO_DATA: u8 = 0x00;
+pub const BIT_ARRAY: u8 = 0x01;
+pub const INT16: u8 = 0x02;
+pub const INT32: u8 = 0x03;
+pub const REAL4: u8 = 0x04;
+pub const REAL8: u8 = 0x05;
+pub const ASCII: u8 = 0x06;
+
+// Size of the length + record type + data type header preceding every record
+pub const RECORD_HEADER_LEN: usize = 4;
+
+// Human-readable name of a record type, for error messages and diagnostics
+pub fn record_type_name(record_type: u8) -> &'static str {
+    match record_type {
+        HEADER => "HEADER",
+        BGNLIB => "BGNLIB",
+        LIBNAME => "LIBNAME",
+        UNITS => "UNITS",
+        ENDLIB => "ENDLIB",
+        BGNSTR => "BGNSTR",
+        STRNAME => "STRNAME",
+        ENDSTR => "ENDSTR",
+        BOUNDARY => "BOUNDARY",
+        PATH => "PATH",
+        SREF => "SREF",
+        AREF => "AREF",
+        TEXT => "TEXT",
+        LAYER => "LAYER",
+        DATATYPE => "DATATYPE",
+        WIDTH => "WIDTH",
+        XY => "XY",
+        ENDEL => "ENDEL",
+        SNAME => "SNAME",
+        COLROW => "COLROW",
+        0x14 => "TEXTNODE",
+        NODE => "NODE",
+        TEXTTYPE => "TEXTTYPE",
+        PRESENTATION => "PRESENTATION",
+        0x18 => "SPACING",
+        STRING => "STRING",
+        STRANS => "STRANS",
+        MAG => "MAG",
+        ANGLE => "ANGLE",
+        0x1D => "UINTEGER",
+        0x1E => "USTRING",
+        0x1F => "REFLIBS",
+        0x20 => "FONTS",
+        PATHTYPE => "PATHTYPE",
+        0x22 => "GENERATIONS",
+        0x23 => "ATTRTABLE",
+        0x24 => "STYPTABLE",
+        0x25 => "STRTYPE",
+        0x26 => "ELFLAGS",
+        0x27 => "ELKEY",
+        0x28 => "LINKTYPE",
+        0x29 => "LINKKEYS",
+        NODETYPE => "NODETYPE",
+        PROPATTR => "PROPATTR",
+        PROPVALUE => "PROPVALUE",
+        BOX => "BOX",
+        BOXTYPE => "BOXTYPE",
+        0x2F => "PLEX",
+        BGNEXTN => "BGNEXTN",
+        ENDEXTN => "ENDEXTN",
+        0x32 => "TAPENUM",
+        0x33 => "TAPECODE",
+        0x34 => "STRCLASS",
+        0x35 => "RESERVED",
+        0x36 => "FORMAT",
+        0x37 => "MASK",
+        0x38 => "ENDMASKS",
+        0x39 => "LIBDIRSIZE",
+        0x3A => "SRFNAME",
+        0x3B => "LIBSECUR",
+        _ => "UNKNOWN",
+    }
+}
+
+// Human-readable name of a data type
+pub fn data_type_name(data_type: u8) -> &'static str {
+    match data_type {
+        NO_DATA => "NO_DATA",
+        BIT_ARRAY => "BIT_ARRAY",
+        INT16 => "INT16",
+        INT32 => "INT32",
+        REAL4 => "REAL4",
+        REAL8 => "REAL8",
+        ASCII => "ASCII",
+        _ => "UNKNOWN",
+    }
+}
+
+// A single record borrowed from the underlying byte stream
+#[derive(Debug, Clone, Copy)]
+pub struct Record<'a> {
+    pub offset: u64,
+    pub record_type: u8,
+    pub data_type: u8,
+    pub data: &'a [u8],
+}
+
+impl<'a> Record<'a> {
+    fn invalid(&self, expected: String, message: String) -> GdsError {
+        GdsError::invalid_record(self.offset, expected, message)
+    }
+
+    fn chunks(&self, size: usize, what: &str) -> Result<std::slice::ChunksExact<'a, u8>, GdsError> {
+        if self.data.len() % size != 0 {
+            return Err(self.invalid(
+                format!("{} data in whole {}-byte values", what, size),
+                format!(
+                    "{} data length {} is not a multiple of {} for {}",
+                    record_type_name(self.record_type),
+                    self.data.len(),
+                    size,
+                    what
+                ),
+            ));
+        }
+        Ok(self.data.chunks_exact(size))
+    }
+
+    pub fn i16_values(&self) -> Result<Vec<i16>, GdsError> {
+        Ok(self
+            .chunks(2, "INT16")?
+            .map(|c| i16::from_be_bytes([c[0], c[1]]))
+            .collect())
+    }
+
+    pub fn i32_values(&self) -> Result<Vec<i32>, GdsError> {
+        Ok(self
+            .chunks(4, "INT32")?
+            .map(|c| i32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+            .collect())
+    }
+
+    pub fn real8_values(&self) -> Result<Vec<f64>, GdsError> {
+        Ok(self
+            .chunks(8, "REAL8")?
+            .map(|c| decode_real8(c.try_into().unwrap()))
+            .collect())
+    }
+
+    // First INT16 value of the record
+    pub fn i16_value(&self) -> Result<i16, GdsError> {
+        self.i16_values()?.first().copied().ok_or_else(|| self.no_data("an INT16 value"))
+    }
+
+    // First REAL8 value of the record
+    pub fn real8_value(&self) -> Result<f64, GdsError> {
+        self.real8_values()?.first().copied().ok_or_else(|| self.no_data("a REAL8 value"))
+    }
+
+    fn no_data(&self, expected: &str) -> GdsError {
+        let name = record_type_name(self.record_type);
+        self.invalid(expected.to_string(), format!("{} record has no data", name))
+    }
+
+    // BIT_ARRAY data as a single 16-bit word
+    pub fn bit_array(&self) -> Result<u16, GdsError> {
+        if self.data.len() < 2 {
+            let name = record_type_name(self.record_type);
+            return Err(self.invalid(
+                "a 2-byte bit array".to_string(),
+                format!("{} record is too short for a bit array", name),
+            ));
+        }
+        Ok(u16::from_be_bytes([self.data[0], self.data[1]]))
+    }
+
+    // ASCII data with trailing NUL padding removed, see `decode_string`
+    pub fn string(&self) -> String {
+        decode_string(self.data)
+    }
+}
+
+// A GDSII string without its trailing NUL padding. The format only allows
+// ASCII, but tools write UTF-8 or, especially older ones, latin-1. Valid UTF-8
+// is read as such and anything else as latin-1, so every byte decodes to the
+// same character wherever the string appears and an SNAME always matches the
+// STRNAME it was copied from.
+pub fn decode_string(data: &[u8]) -> String {
+    let end = data.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    match std::str::from_utf8(&data[..end]) {
+        Ok(text) => text.to_string(),
+        Err(_) => data[..end].iter().map(|&b| b as char).collect(),
+    }
+}
+
+// Decode a GDSII 8-byte real: sign bit, 7-bit excess-64 base-16 exponent,
+// 56-bit mantissa
+pub fn decode_real8(bytes: [u8; 8]) -> f64 {
+    let sign = if bytes[0] & 0x80 != 0 { -1.0 } else { 1.0 };
+    let exponent = (bytes[0] & 0x7F) as i32 - 64;
+    let mut mantissa: u64 = 0;
+    for &b in &bytes[1..] {
+        mantissa = (mantissa << 8) | b as u64;
+    }
+    sign * (mantissa as f64 / 2f64.powi(56)) * 16f64.powi(exponent)
+}
+
+// Encode an f64 as a GDSII 8-byte real
+pub fn encode_real8(value: f64) -> [u8; 8] {
+    if value == 0.0 || !value.is_finite() {
+        return [0; 8];
+    }
+    let sign: u8 = if value < 0.0 { 0x80 } else { 0 };
+    let mut magnitude = value.abs();
+    let mut exponent: i32 = 64;
+    while magnitude >= 1.0 {
+        magnitude /= 16.0;
+        exponent += 1;
+    }
+    while magnitude < 1.0 / 16.0 {
+        magnitude *= 16.0;
+        exponent -= 1;
+    }
+    let exponent = exponent.clamp(0, 127);
+    let mantissa = (magnitude * 2f64.powi(56)).round() as u64;
+    let mut bytes = [0u8; 8];
+    bytes[0] = sign | exponent as u8;
+    bytes[1..].copy_from_slice(&mantissa.to_be_bytes()[1..]);
+    bytes
+}
+
+// The declared length of `header` if it looks like a record header: a known
+// record and data type and a length that fits in the `remaining` bytes of the
+// stream from the header on
+pub fn plausible_header(header: [u8; RECORD_HEADER_LEN], remaining: usize) -> Option<usize> {
+    let length = u16::from_be_bytes([header[0], header[1]]) as usize;
+    let plausible = (RECORD_HEADER_LEN..=remaining).contains(&length)
+        && record_type_name(header[2]) != "UNKNOWN"
+        && data_type_name(header[3]) != "UNKNOWN";
+    plausible.then_some(length)
+}
+
+// Bytes `is_pad_byte` looks at: the pad and the record header after it
+pub const PAD_WINDOW_LEN: usize = 1 + RECORD_HEADER_LEN;
+
+// Whether the first byte of `window`, the first `PAD_WINDOW_LEN` (or fewer)
+// of the `remaining` bytes after a record declared with an odd length, is the
+// pad byte a writer put after it: a NUL followed by the end of the stream or a
+// plausible header, where reading the NUL as the start of a header would not
+// be plausible
+pub fn is_pad_byte(window: &[u8], remaining: usize) -> bool {
+    let header_at = |start: usize| {
+        let header = window.get(start..start + RECORD_HEADER_LEN)?;
+        plausible_header(header.try_into().ok()?, remaining - start)
+    };
+    window.first() == Some(&0)
+        && (remaining == 1 || header_at(1).is_some())
+        && header_at(0).is_none()
+}
+
+// Iterator over the records of a GDSII byte stream. Stops after the first error.
+pub struct RecordReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> RecordReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        RecordReader { bytes, pos: 0 }
+    }
+
+    fn plausible_length(&self, pos: usize) -> Option<usize> {
+        let header = self.bytes.get(pos..pos + RECORD_HEADER_LEN)?;
+        plausible_header(header.try_into().ok()?, self.bytes.len() - pos)
+    }
+
+    // Continue after the damaged record at `offset`, from the next byte past it
+    // holding a plausible record header with an even length. Returns whether
+    // one was found.
+    pub fn resync(&mut self, offset: u64) -> bool {
+        let start = (offset as usize + 1).min(self.bytes.len());
+        let found = (start..self.bytes.len())
+            .find(|&pos| self.plausible_length(pos).is_some_and(|length| length % 2 == 0));
+        self.pos = found.unwrap_or(self.bytes.len());
+        found.is_some()
+    }
+
+    fn fail(&mut self, error: GdsError) -> Option<Result<Record<'a>, GdsError>> {
+        self.pos = self.bytes.len();
+        Some(Err(error))
+    }
+}
+
+impl<'a> Iterator for RecordReader<'a> {
+    type Item = Result<Record<'a>, GdsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining = self.bytes.len() - self.pos;
+        if remaining == 0 {
+            return None;
+        }
+        let offset = self.pos as u64;
+        if remaining < RECORD_HEADER_LEN {
+            return self.fail(GdsError::truncated(
+                offset,
+                "a 4-byte record header",
+                format!("{} trailing bytes are too short for a record header", remaining),
+            ));
+        }
+
+        let header = &self.bytes[self.pos..self.pos + RECORD_HEADER_LEN];
+        let length = u16::from_be_bytes([header[0], header[1]]) as usize;
+        let record_type = header[2];
+        let data_type = header[3];
+
+        if length < RECORD_HEADER_LEN {
+            return self.fail(GdsError::invalid_record(
+                offset,
+                "a record length of at least 4",
+                format!("record length {} is shorter than its header", length),
+            ));
+        }
+        if length > remaining {
+            return self.fail(GdsError::truncated(
+                offset,
+                format!("{} bytes of {} record", length, record_type_name(record_type)),
+                format!(
+                    "{} record declares {} bytes but only {} remain",
+                    record_type_name(record_type),
+                    length,
+                    remaining
+                ),
+            ));
+        }
+
+        let data = &self.bytes[self.pos + RECORD_HEADER_LEN..self.pos + length];
+        self.pos += length;
+        // The spec pads records to an even length, but some writers declare the
+        // odd length of the data and put the pad byte after the record
+        let window = &self.bytes[self.pos..(self.pos + PAD_WINDOW_LEN).min(self.bytes.len())];
+        if length % 2 != 0 && is_pad_byte(window, self.bytes.len() - self.pos) {
+            self.pos += 1;
+        }
+        Some(Ok(Record { offset, record_type, data_type, data }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn real8_round_trips() {
+        for value in [1.0, 0.001, 1e-9, -2.5, 90.0, 123456.789] {
+            let decoded = decode_real8(encode_real8(value));
+            assert!((decoded - value).abs() <= value.abs() * 1e-12, "{} != {}", decoded, value);
+        }
+        assert_eq!(decode_real8(encode_real8(0.0)), 0.0);
+    }
+
+    #[test]
+    fn reader_reports_truncated_record_offset() {
+        // A valid 6-byte HEADER followed by a record claiming 8 bytes with only 4 present
+        let bytes = [0x00, 0x06, HEADER, INT16, 0x02, 0x58, 0x00, 0x08, BGNLIB, INT16];
+        let mut reader = RecordReader::new(&bytes);
+        assert!(reader.next().unwrap().is_ok());
+        match reader.next().unwrap() {
+            Err(GdsError::Truncated { offset, .. }) => assert_eq!(offset, 6),
+            other => panic!("expected truncation error, got {:?}", other),
+        }
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn reader_skips_pad_bytes_after_odd_length_records() {
+        let bytes = [
+            // LIBNAME "ABC" declared as 7 bytes, with the pad byte after it
+            &[0x00, 0x07, LIBNAME, ASCII, b'A', b'B', b'C', 0x00][..],
+            // A record without data
+            &[0x00, 0x04, ENDSTR, NO_DATA],
+            // STRNAME "X" declared as 5 bytes, with the pad as the last byte
+            &[0x00, 0x05, STRNAME, ASCII, b'X', 0x00],
+        ]
+        .concat();
+        let records: Vec<Record> = RecordReader::new(&bytes).map(Result::unwrap).collect();
+
+        let offsets: Vec<u64> = records.iter().map(|record| record.offset).collect();
+        assert_eq!(offsets, [0, 8, 12]);
+        assert_eq!(records[0].string(), "ABC");
+        assert!(records[1].data.is_empty());
+        assert_eq!(records[2].string(), "X");
+    }
+}
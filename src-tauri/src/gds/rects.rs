@@ -0,0 +1,147 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::cache::ParseCache;
+use super::flatten::{element_outline, Flattener};
+use super::transform::Transform;
+use super::units::{CoordConvention, CoordMapping};
+use super::{GdsElement, GdsError, GdsLibrary};
+use crate::error::AppError;
+
+// A flattened axis-aligned rectangle: lower-left corner, width and height
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+pub struct LayerRect {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+// The shapes of one layer split for a rectangle-only renderer: `rects` for
+// every rectangle that stays axis-aligned once placed, `polygons` for the rest
+#[derive(Debug, Clone, Default, PartialEq, Serialize, JsonSchema)]
+pub struct LayerRects {
+    pub rects: Vec<LayerRect>,
+    pub polygons: Vec<Vec<[f64; 2]>>,
+}
+
+// Whether the transform maps axis-aligned rectangles to axis-aligned ones,
+// i.e. its rotation is a multiple of 90 degrees
+fn keeps_axes(transform: &Transform) -> bool {
+    (transform.b == 0.0 && transform.c == 0.0) || (transform.a == 0.0 && transform.d == 0.0)
+}
+
+// Flattened shapes on (layer, datatype) reachable from `cell`, reported through
+// `mapping`. Rectangle boundaries placed at multiples of 90 degrees come back as
+// rectangles; everything else, including rectangles under other rotations,
+// paths and boxes, as polygon outlines.
+pub fn layer_rects(
+    library: &GdsLibrary,
+    cell: &str,
+    layer: u16,
+    datatype: u16,
+    mapping: &CoordMapping,
+) -> Result<LayerRects, GdsError> {
+    let mut shapes = LayerRects::default();
+    Flattener::new(library).visit(cell, &mut |element, transform| {
+        if element.layer_key() != (layer, datatype) {
+            return;
+        }
+        if let GdsElement::Rect { rect, .. } = element {
+            if keeps_axes(transform) {
+                let corner = |x: i32, y: i32| mapping.point(transform.apply_point([x, y]));
+                let [x0, y0] = corner(rect.x, rect.y);
+                let [x1, y1] = corner(rect.x + rect.w, rect.y + rect.h);
+                shapes.rects.push(LayerRect {
+                    x: x0.min(x1),
+                    y: y0.min(y1),
+                    w: (x1 - x0).abs(),
+                    h: (y1 - y0).abs(),
+                });
+                return;
+            }
+        }
+        if let Some(outline) = element_outline(element, transform) {
+            shapes.polygons.push(mapping.ring(&outline));
+        }
+    })?;
+    Ok(shapes)
+}
+
+// Command to get the flattened shapes of one layer of a cell as rectangles
+// where possible, with polygons for whatever isn't one. Coordinates are in user
+// units unless `coord_convention` asks otherwise.
+#[tauri::command]
+pub async fn get_layer_rects(
+    path: String,
+    cell: String,
+    layer: u16,
+    datatype: u16,
+    coord_convention: Option<CoordConvention>,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<LayerRects, AppError> {
+    let library = cache.library(&path)?;
+    let mapping = CoordMapping::new(coord_convention.as_ref(), &library.units);
+    Ok(layer_rects(&library, &cell, layer, datatype, &mapping)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::{fill_library, GdsBuilder};
+    use crate::gds::units::LengthUnit;
+    use crate::gds::{parse_library, read_library};
+
+    #[test]
+    fn fill_rectangles_stay_compact_until_rotated_off_axis() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("FILL");
+        for i in 0..100 {
+            let x = i * 20;
+            // Alternate the winding and starting corner
+            if i % 2 == 0 {
+                gds.boundary(1, 0, &[[x, 0], [x + 10, 0], [x + 10, 5], [x, 5], [x, 0]]);
+            } else {
+                gds.boundary(1, 0, &[[x + 10, 5], [x + 10, 0], [x, 0], [x, 5], [x + 10, 5]]);
+            }
+        }
+        gds.boundary(1, 0, &[[0, 0], [10, 0], [0, 10], [0, 0]]).end_cell();
+        gds.begin_cell("TOP")
+            .sref("FILL", [0, 0], Some((false, 90.0, 1.0)))
+            .sref("FILL", [0, 0], Some((false, 45.0, 1.0)))
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let fill = &library.cell("FILL").unwrap().elements;
+        let rects = fill.iter().filter(|e| matches!(e, GdsElement::Rect { .. })).count();
+        assert_eq!(rects, 100);
+
+        let convention = CoordConvention { unit: LengthUnit::Db, flip_y: false };
+        let mapping = CoordMapping::new(Some(&convention), &library.units);
+        let shapes = layer_rects(&library, "TOP", 1, 0, &mapping).unwrap();
+        // The 90° copy keeps its rectangles, the 45° copy and the triangle don't
+        assert_eq!(shapes.rects.len(), 100);
+        assert_eq!(shapes.polygons.len(), 100 + 2);
+        assert_eq!(shapes.rects[1], LayerRect { x: -5.0, y: 20.0, w: 5.0, h: 10.0 });
+    }
+
+    // The fixture behind the memory figures in the README, too big for the
+    // regular run. On Linux, `cargo test --release fill_fixture_memory --
+    // --ignored --nocapture` prints the peak resident size of the process.
+    #[test]
+    #[ignore]
+    fn fill_fixture_memory() {
+        let count = 2_000_000;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fill.gds");
+        std::fs::write(&path, fill_library(count)).unwrap();
+        let library = read_library(&path.to_string_lossy()).unwrap();
+
+        let fill = &library.cell("FILL").unwrap().elements;
+        assert_eq!(fill.iter().filter(|e| matches!(e, GdsElement::Rect { .. })).count(), count);
+        let status = std::fs::read_to_string("/proc/self/status").unwrap_or_default();
+        if let Some(peak) = status.lines().find(|line| line.starts_with("VmHWM:")) {
+            println!("{} rectangles, {}", count, peak);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+use super::cache::ParseCache;
+use super::flatten::{cell_polygons, check_polygon_count};
+use super::hit_test::{HitIndex, HitIndexCache};
+use super::units::{CoordConvention, CoordMapping};
+use super::{GdsError, GdsLibrary};
+use crate::error::AppError;
+use crate::geometry::rtree::Rect;
+
+// A flattened polygon with its layer, in the coordinates the query asked for
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Polygon {
+    pub layer: u16,
+    pub datatype: u16,
+    pub points: Vec<[f64; 2]>,
+}
+
+fn wanted(layers: Option<&[u16]>, layer: u16) -> bool {
+    layers.map_or(true, |layers| layers.contains(&layer))
+}
+
+fn overlaps(outline: &[[f64; 2]], region: &Rect) -> bool {
+    Rect::around(outline).is_some_and(|rect| rect.intersects(region))
+}
+
+// Polygons of `cell` whose bounding boxes intersect `region` (database units),
+// optionally only on `layers`, ordered by layer and datatype and reported
+// through `mapping`. Uses `index` when one is at hand, and flattens and scans
+// the cell otherwise.
+pub fn region_polygons(
+    library: &GdsLibrary,
+    index: Option<&HitIndex>,
+    cell: &str,
+    region: &Rect,
+    layers: Option<&[u16]>,
+    mapping: &CoordMapping,
+) -> Result<Vec<Polygon>, GdsError> {
+    let polygon = |layer, datatype, points: &[[f64; 2]]| Polygon {
+        layer,
+        datatype,
+        points: mapping.ring(points),
+    };
+
+    if let Some(index) = index {
+        return Ok(index
+            .within(region)
+            .into_iter()
+            .filter(|(element, _)| wanted(layers, element.layer))
+            .map(|(element, points)| polygon(element.layer, element.datatype, points))
+            .collect());
+    }
+    let mut polygons = Vec::new();
+    for ((layer, datatype), outlines) in cell_polygons(library, cell)? {
+        if !wanted(layers, layer) {
+            continue;
+        }
+        polygons.extend(
+            outlines
+                .iter()
+                .filter(|outline| overlaps(outline, region))
+                .map(|outline| polygon(layer, datatype, outline)),
+        );
+    }
+    Ok(polygons)
+}
+
+// Sorted (layer, datatype) pairs with a polygon of `cell` whose bounding box
+// intersects `region` (database units), from `index` when given
+pub fn region_layers(
+    library: &GdsLibrary,
+    index: Option<&HitIndex>,
+    cell: &str,
+    region: &Rect,
+) -> Result<Vec<(u16, u16)>, GdsError> {
+    let layers: BTreeSet<(u16, u16)> = match index {
+        Some(index) => index
+            .within(region)
+            .into_iter()
+            .map(|(element, _)| (element.layer, element.datatype))
+            .collect(),
+        None => cell_polygons(library, cell)?
+            .into_iter()
+            .filter(|(_, outlines)| outlines.iter().any(|outline| overlaps(outline, region)))
+            .map(|(key, _)| key)
+            .collect(),
+    };
+    Ok(layers.into_iter().collect())
+}
+
+// The database-unit rectangle of a viewport given in `mapping`'s coordinates
+fn viewport_rect(
+    min_x: f64,
+    min_y: f64,
+    max_x: f64,
+    max_y: f64,
+    mapping: &CoordMapping,
+) -> Result<Rect, AppError> {
+    if !(min_x <= max_x && min_y <= max_y) {
+        return Err(AppError::invalid_argument(format!(
+            "Region ({}, {}) - ({}, {}) has its minimum above its maximum",
+            min_x, min_y, max_x, max_y
+        )));
+    }
+    // A flipped Y axis swaps which corner is lowest
+    let (low, high) = (mapping.to_db([min_x, min_y]), mapping.to_db([max_x, max_y]));
+    Ok(Rect {
+        min: [low[0].min(high[0]), low[1].min(high[1])],
+        max: [low[0].max(high[0]), low[1].max(high[1])],
+    })
+}
+
+// Command to get the polygons of a cell overlapping a viewport rectangle,
+// optionally only on some layers. The rectangle and the result are in user
+// units unless `coord_convention` asks otherwise. More than `max_polygons`
+// fails with `TooLarge`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn query_region(
+    path: String,
+    cell: String,
+    min_x: f64,
+    min_y: f64,
+    max_x: f64,
+    max_y: f64,
+    layers: Option<Vec<u16>>,
+    coord_convention: Option<CoordConvention>,
+    max_polygons: Option<usize>,
+    cache: tauri::State<'_, ParseCache>,
+    indexes: tauri::State<'_, HitIndexCache>,
+) -> Result<Vec<Polygon>, AppError> {
+    let library = cache.library(&path)?;
+    let mapping = CoordMapping::new(coord_convention.as_ref(), &library.units);
+    let region = viewport_rect(min_x, min_y, max_x, max_y, &mapping)?;
+    let index = indexes.cached(&path, &library, &cell);
+    let layers = layers.as_deref();
+    let polygons = region_polygons(&library, index.as_deref(), &cell, &region, layers, &mapping)?;
+    check_polygon_count(polygons.len(), max_polygons)?;
+    Ok(polygons)
+}
+
+// Command to list the layers with geometry in a viewport rectangle in user
+// units, as sorted (layer, datatype) pairs, so the layer panel can dim the
+// ones with nothing on screen. Builds and caches the `hit_test` index, as the
+// view asks again on every pan and zoom.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn layers_in_region(
+    path: String,
+    cell: String,
+    min_x: f64,
+    min_y: f64,
+    max_x: f64,
+    max_y: f64,
+    cache: tauri::State<'_, ParseCache>,
+    indexes: tauri::State<'_, HitIndexCache>,
+) -> Result<Vec<(u16, u16)>, AppError> {
+    let library = cache.library(&path)?;
+    let mapping = CoordMapping::new(None, &library.units);
+    let region = viewport_rect(min_x, min_y, max_x, max_y, &mapping)?;
+    let index = indexes.index(&path, &library, &cell)?;
+    Ok(region_layers(&library, Some(&index), &cell, &region)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn indexed_and_scanned_queries_agree() {
+        let square =
+            |x: i32, size: i32| [[x, 0], [x + size, 0], [x + size, size], [x, size], [x, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &square(0, 100))
+            .boundary(1, 0, &square(1000, 100))
+            .boundary(2, 0, &square(50, 100))
+            .path(3, 0, 0, 10, &[[0, 500], [2000, 500]])
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+        let index = HitIndex::build(&library, "TOP").unwrap();
+        let region = Rect { min: [90.0, 90.0], max: [200.0, 600.0] };
+        let user_units = CoordMapping::new(None, &library.units);
+
+        for index in [None, Some(&index)] {
+            let found =
+                region_polygons(&library, index, "TOP", &region, None, &user_units).unwrap();
+            let layers: Vec<u16> = found.iter().map(|polygon| polygon.layer).collect();
+            assert_eq!(layers, vec![1, 2, 3]);
+            // 1 nm units are 0.001 µm
+            assert_eq!(found[0].points[0], [0.0, 0.0]);
+            assert_eq!(found[0].points[2], [0.1, 0.1]);
+
+            let layers = Some(&[2, 3][..]);
+            let filtered =
+                region_polygons(&library, index, "TOP", &region, layers, &user_units).unwrap();
+            assert_eq!(filtered.len(), 2);
+
+            let layers = region_layers(&library, index, "TOP", &region).unwrap();
+            assert_eq!(layers, [(1, 0), (2, 0), (3, 0)]);
+            let corner = Rect { min: [1050.0, 0.0], max: [1060.0, 10.0] };
+            assert_eq!(region_layers(&library, index, "TOP", &corner).unwrap(), [(1, 0)]);
+        }
+    }
+}
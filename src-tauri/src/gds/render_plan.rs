@@ -0,0 +1,206 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::bbox::BboxCalculator;
+use super::cache::ParseCache;
+use super::flatten::{fold_cells, max_hierarchy_depth};
+use super::hierarchy::reachable_cells;
+use super::tiles::MAX_TILES;
+use super::{CellReference, GdsCell, GdsElement, GdsError, GdsLibrary};
+use crate::error::AppError;
+
+// Up to this many flattened polygons, a cell is drawn flattened in one go
+pub const FLATTENED_POLYGON_BUDGET: u64 = 200_000;
+
+// Above the budget, a cell whose placed polygons each stand for at least this
+// many flattened ones is drawn instanced
+pub const MIN_INSTANCING_REUSE: f64 = 4.0;
+
+// Flattened polygons a tile should hold on average
+pub const TILE_POLYGONS: u64 = 20_000;
+
+// Width in pixels of a full view of the cell that the suggested simplify
+// tolerance keeps outlines accurate to, one pixel at that zoom
+const PREVIEW_PIXELS: f64 = 4096.0;
+
+// How the frontend should draw a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderStrategy {
+    // Each cell's polygons once plus its placements, from `get_instanced_geometry`
+    Instanced,
+    // Every polygon in top-cell coordinates, from `get_layer_polygons` or, with
+    // a `tile_size`, `get_cell_tiles`
+    Flattened,
+}
+
+// The suggested way to draw a cell, with the numbers it was chosen from so a
+// caller can apply its own thresholds instead. Polygon counts are estimates:
+// every boundary, path and box counts as one polygon, and texts and nodes as
+// none. `tile_size` and `simplify_tolerance` are in user units and only set
+// for a flattened cell over `FLATTENED_POLYGON_BUDGET`.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct RenderPlan {
+    pub strategy: RenderStrategy,
+    pub tile_size: Option<f64>,
+    pub simplify_tolerance: Option<f64>,
+    // Placements of cells with polygons of their own, `cell` included
+    pub placements: u64,
+    // Polygons of the cells below `cell`, counting each cell once
+    pub unique_polygons: u64,
+    pub flattened_polygons: u64,
+    // `flattened_polygons / unique_polygons`, or 1 without polygons
+    pub reuse: f64,
+    // Why `strategy` was picked, for display
+    pub reason: String,
+}
+
+fn is_polygon(element: &GdsElement) -> bool {
+    !matches!(element, GdsElement::Text { .. } | GdsElement::Node { .. })
+}
+
+fn instance_count(reference: &CellReference) -> u64 {
+    reference
+        .array
+        .as_ref()
+        .map_or(1, |array| array.columns.max(1) as u64 * array.rows.max(1) as u64)
+}
+
+// Flattened polygon and placement counts of `cell`, (0, 0) for a missing one,
+// memoized so shared sub-cells are only counted once
+fn flattened_counts(library: &GdsLibrary, cell: &str) -> Result<(u64, u64), GdsError> {
+    let cells: HashMap<&str, &GdsCell> =
+        library.cells.iter().map(|cell| (cell.name.as_str(), cell)).collect();
+    let Some(&root) = cells.get(cell) else {
+        return Ok((0, 0));
+    };
+    let own = |cell: &GdsCell| {
+        let own = cell.elements.iter().filter(|element| is_polygon(element)).count() as u64;
+        (own, u64::from(own > 0))
+    };
+    let add = |counts: &mut (u64, u64), reference: &CellReference, child: &(u64, u64)| {
+        let instances = instance_count(reference);
+        counts.0 = counts.0.saturating_add(child.0.saturating_mul(instances));
+        counts.1 = counts.1.saturating_add(child.1.saturating_mul(instances));
+    };
+    fold_cells(&cells, &mut HashMap::new(), root, max_hierarchy_depth(), own, add)
+}
+
+// `RenderPlan` for `cell`: flattened while it fits in
+// `FLATTENED_POLYGON_BUDGET`, otherwise instanced when each placed polygon is
+// repeated `MIN_INSTANCING_REUSE` times on average, and otherwise flattened in
+// tiles of about `TILE_POLYGONS` polygons with outlines decimated to a pixel of
+// a `PREVIEW_PIXELS` wide view for zoomed-out previews
+pub fn render_plan(library: &GdsLibrary, cell: &str) -> Result<RenderPlan, GdsError> {
+    let unique_polygons: u64 = reachable_cells(library, cell)?
+        .iter()
+        .map(|cell| cell.elements.iter().filter(|element| is_polygon(element)).count() as u64)
+        .sum();
+    let (flattened_polygons, placements) = flattened_counts(library, cell)?;
+    let reuse =
+        if unique_polygons == 0 { 1.0 } else { flattened_polygons as f64 / unique_polygons as f64 };
+    let mut plan = RenderPlan {
+        strategy: RenderStrategy::Flattened,
+        tile_size: None,
+        simplify_tolerance: None,
+        placements,
+        unique_polygons,
+        flattened_polygons,
+        reuse,
+        reason: String::new(),
+    };
+
+    if flattened_polygons <= FLATTENED_POLYGON_BUDGET {
+        plan.reason = format!(
+            "{} polygons when flattened, within the {} drawn at once",
+            flattened_polygons, FLATTENED_POLYGON_BUDGET
+        );
+        return Ok(plan);
+    }
+    if reuse >= MIN_INSTANCING_REUSE {
+        plan.strategy = RenderStrategy::Instanced;
+        plan.reason = format!(
+            "{} polygons when flattened, but only {} to place {} times",
+            flattened_polygons, unique_polygons, placements
+        );
+        return Ok(plan);
+    }
+
+    plan.reason = format!(
+        "{} polygons when flattened, with too little reuse ({:.1}x) to instance",
+        flattened_polygons, reuse
+    );
+    if let Some(bbox) = BboxCalculator::new(library).cell_bbox(cell)? {
+        let user_unit = library.units.user_unit;
+        let (width, height) = (bbox.width() * user_unit, bbox.height() * user_unit);
+        let tiles = (flattened_polygons / TILE_POLYGONS).clamp(1, MAX_TILES as u64);
+        let tile_size = (width * height / tiles as f64).sqrt();
+        if tile_size > 0.0 {
+            plan.tile_size = Some(tile_size);
+        }
+        let tolerance = width.max(height) / PREVIEW_PIXELS;
+        if tolerance > 0.0 {
+            plan.simplify_tolerance = Some(tolerance);
+        }
+    }
+    Ok(plan)
+}
+
+// Command to suggest whether to draw a cell instanced or flattened, with a
+// tile size and simplify tolerance for large flattened cells
+#[tauri::command]
+pub async fn get_render_plan(
+    path: String,
+    cell: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<RenderPlan, AppError> {
+    let library = cache.library(&path)?;
+    Ok(render_plan(&library, &cell)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn repeated_cells_are_instanced_and_unique_geometry_is_tiled() {
+        let square = |x: i32| [[x, 0], [x + 10, 0], [x + 10, 10], [x, 10], [x, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("BIT").boundary(1, 0, &square(0)).boundary(2, 0, &square(2)).end_cell();
+        gds.begin_cell("SMALL").aref("BIT", [0, 0], 10, 10, [200, 0], [0, 200]).end_cell();
+        gds.begin_cell("ARRAY")
+            .aref("BIT", [0, 0], 1000, 1000, [20_000, 0], [0, 20_000])
+            .end_cell();
+        // 70,000 squares in a 14 by 2 µm grid, placed three times side by side
+        let field = gds.begin_cell("FIELD");
+        for i in 0..70_000 {
+            let [x, y] = [i % 700 * 20, i / 700 * 20];
+            field.boundary(1, 0, &square(x).map(|[px, py]| [px, py + y]));
+        }
+        field.end_cell();
+        gds.begin_cell("FIELDS").aref("FIELD", [0, 0], 3, 1, [60_000, 0], [0, 0]).end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let small = render_plan(&library, "SMALL").unwrap();
+        assert_eq!(small.strategy, RenderStrategy::Flattened);
+        assert_eq!((small.flattened_polygons, small.placements), (200, 100));
+        assert_eq!(small.tile_size, None);
+
+        let array = render_plan(&library, "ARRAY").unwrap();
+        assert_eq!(array.strategy, RenderStrategy::Instanced);
+        assert_eq!((array.unique_polygons, array.flattened_polygons), (2, 2_000_000));
+        assert_eq!(array.placements, 1_000_000);
+
+        // 210,000 polygons reused only 3 times: ten tiles over 53.99 by 1.99 µm
+        let fields = render_plan(&library, "FIELDS").unwrap();
+        assert_eq!(fields.strategy, RenderStrategy::Flattened);
+        assert_eq!(fields.reuse, 3.0);
+        assert!((fields.tile_size.unwrap() - (53.99f64 * 1.99 / 10.0).sqrt()).abs() < 1e-9);
+        assert!((fields.simplify_tolerance.unwrap() - 53.99 / 4096.0).abs() < 1e-9);
+
+        assert!(matches!(render_plan(&library, "MISSING"), Err(GdsError::CellNotFound { .. })));
+    }
+}
@@ -0,0 +1,136 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::cache::ParseCache;
+use super::hit_test::{ElementRef, HitIndex, HitIndexCache};
+use crate::error::AppError;
+use crate::geometry::rtree::Rect;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapKind {
+    Vertex,
+    Edge,
+}
+
+// The point snapped to and the polygon it lies on
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct SnapResult {
+    pub x: f64,
+    pub y: f64,
+    pub kind: SnapKind,
+    pub distance: f64,
+    pub element: ElementRef,
+}
+
+// Closest point to `point` on the segment from `a` to `b`
+pub(super) fn closest_on_segment(point: [f64; 2], a: [f64; 2], b: [f64; 2]) -> [f64; 2] {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let length_squared = dx * dx + dy * dy;
+    if length_squared == 0.0 {
+        return a;
+    }
+    let t = (((point[0] - a[0]) * dx + (point[1] - a[1]) * dy) / length_squared).clamp(0.0, 1.0);
+    [a[0] + t * dx, a[1] + t * dy]
+}
+
+fn distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+    (a[0] - b[0]).hypot(a[1] - b[1])
+}
+
+// Nearest polygon vertex, or failing that nearest point on a polygon edge,
+// within `radius` of `point` (all in database units). A vertex in range always
+// wins over a closer edge point, so endpoints are easy to pick. Ties go to the
+// polygon `HitIndex::within` lists first.
+pub fn snap(index: &HitIndex, point: [f64; 2], radius: f64) -> Option<SnapResult> {
+    let search = Rect {
+        min: [point[0] - radius, point[1] - radius],
+        max: [point[0] + radius, point[1] + radius],
+    };
+    let mut vertex: Option<(f64, [f64; 2], ElementRef)> = None;
+    let mut edge: Option<(f64, [f64; 2], ElementRef)> = None;
+    let closer = |best: &Option<(f64, [f64; 2], ElementRef)>, d: f64| {
+        d <= radius && best.map_or(true, |(best, _, _)| d < best)
+    };
+    for (element, outline) in index.within(&search) {
+        for (i, &a) in outline.iter().enumerate() {
+            let d = distance(point, a);
+            if closer(&vertex, d) {
+                vertex = Some((d, a, element));
+            }
+            if let Some(&b) = outline.get(i + 1) {
+                let on_edge = closest_on_segment(point, a, b);
+                let d = distance(point, on_edge);
+                if closer(&edge, d) {
+                    edge = Some((d, on_edge, element));
+                }
+            }
+        }
+    }
+
+    let (kind, (distance, [x, y], element)) = match (vertex, edge) {
+        (Some(vertex), _) => (SnapKind::Vertex, vertex),
+        (None, Some(edge)) => (SnapKind::Edge, edge),
+        (None, None) => return None,
+    };
+    Some(SnapResult { x, y, kind, distance, element })
+}
+
+// Command to snap a point of a cell to the nearest polygon vertex or edge
+// within `radius`. The point, radius and result are in user units.
+#[tauri::command]
+pub async fn snap_point(
+    path: String,
+    cell: String,
+    x: f64,
+    y: f64,
+    radius: f64,
+    cache: tauri::State<'_, ParseCache>,
+    indexes: tauri::State<'_, HitIndexCache>,
+) -> Result<Option<SnapResult>, AppError> {
+    if !(radius >= 0.0 && radius.is_finite()) {
+        return Err(AppError::invalid_argument(format!(
+            "Snap radius must be a non-negative number, got {}",
+            radius
+        )));
+    }
+    let library = cache.library(&path)?;
+    let index = indexes.index(&path, &library, &cell)?;
+    let user_unit = library.units.user_unit;
+    let snapped = snap(&index, [x / user_unit, y / user_unit], radius / user_unit);
+    Ok(snapped.map(|result| SnapResult {
+        x: result.x * user_unit,
+        y: result.y * user_unit,
+        distance: result.distance * user_unit,
+        ..result
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn vertices_win_over_edges_within_the_radius() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &[[0, 0], [100, 0], [100, 100], [0, 100], [0, 0]])
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+        let index = HitIndex::build(&library, "TOP").unwrap();
+
+        let near_edge = snap(&index, [50.0, 3.0], 5.0).unwrap();
+        assert_eq!(near_edge.kind, SnapKind::Edge);
+        assert_eq!([near_edge.x, near_edge.y], [50.0, 0.0]);
+        assert_eq!(near_edge.element, ElementRef { layer: 1, datatype: 0, index: 0 });
+
+        // The edge is 1 away but the corner, 4 away, is still in range
+        let near_corner = snap(&index, [97.0, -1.0], 5.0).unwrap();
+        assert_eq!(near_corner.kind, SnapKind::Vertex);
+        assert_eq!([near_corner.x, near_corner.y], [100.0, 0.0]);
+
+        assert_eq!(snap(&index, [50.0, 50.0], 5.0), None);
+    }
+}
@@ -0,0 +1,281 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use super::bbox::{element_bbox, merge, Bbox};
+use super::cache::ParseCache;
+use super::record::{self, RecordReader};
+use super::{GdsElement, GdsError, GdsLibrary};
+use crate::error::AppError;
+
+// Summary counts of a GDSII file. `total_elements` counts every element
+// (boundaries, paths, texts, boxes, nodes and references); `polygon_count`
+// counts boundaries only. `layer_count` is the number of distinct layer numbers.
+// `bbox` bounds the coordinates of the geometry as written in each cell, in
+// database units, without following references; `None` if there is none.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct GdsStats {
+    pub cell_count: u64,
+    pub total_elements: u64,
+    pub polygon_count: u64,
+    pub path_count: u64,
+    pub text_count: u64,
+    pub layer_count: u64,
+    pub bbox: Option<Bbox>,
+    pub file_size_bytes: u64,
+}
+
+// Shape count and size of one layer of a cell. Boundaries, paths and boxes
+// count as polygons; their vertices are the XY points as written, including a
+// boundary's closing point.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct LayerHistogram {
+    pub layer: u16,
+    pub datatype: u16,
+    pub polygon_count: u64,
+    pub total_vertices: u64,
+    pub max_vertices: u64,
+}
+
+// The shape being read in the cell a histogram is collected for
+#[derive(Default)]
+struct PendingShape {
+    layer: u16,
+    datatype: u16,
+    vertices: u64,
+}
+
+// Tally a GDSII stream in a single pass over its records, without building the
+// library
+pub fn scan_stats(bytes: &[u8]) -> Result<GdsStats, GdsError> {
+    Ok(scan(bytes, None)?.0)
+}
+
+// `GdsStats` of an already parsed library, whatever format it was read from.
+// `bbox` covers the full width of paths like `get_cell_bbox` does.
+pub fn library_stats(library: &GdsLibrary, file_size_bytes: u64) -> GdsStats {
+    let mut stats = GdsStats {
+        cell_count: library.cells.len() as u64,
+        total_elements: 0,
+        polygon_count: 0,
+        path_count: 0,
+        text_count: 0,
+        layer_count: 0,
+        bbox: None,
+        file_size_bytes,
+    };
+    let mut layers = HashSet::new();
+    for cell in &library.cells {
+        stats.total_elements += (cell.elements.len() + cell.references.len()) as u64;
+        for element in &cell.elements {
+            match element {
+                GdsElement::Boundary { .. } | GdsElement::Rect { .. } => stats.polygon_count += 1,
+                GdsElement::Path { .. } => stats.path_count += 1,
+                GdsElement::Text { .. } => stats.text_count += 1,
+                GdsElement::Box { .. } | GdsElement::Node { .. } => {}
+            }
+            layers.insert(element.layer_key().0);
+            if let Some(bbox) = element_bbox(element) {
+                merge(&mut stats.bbox, bbox);
+            }
+        }
+    }
+    stats.layer_count = layers.len() as u64;
+    stats
+}
+
+// Per-layer shape counts of `cell` as written, without following references,
+// largest total vertex count first
+pub fn scan_histogram(bytes: &[u8], cell: &str) -> Result<Vec<LayerHistogram>, GdsError> {
+    scan(bytes, Some(cell))?.1.ok_or_else(|| GdsError::CellNotFound { cell: cell.to_string() })
+}
+
+// The stats of a stream and, in the same pass, the histogram of
+// `histogram_cell` if given and found
+fn scan(
+    bytes: &[u8],
+    histogram_cell: Option<&str>,
+) -> Result<(GdsStats, Option<Vec<LayerHistogram>>), GdsError> {
+    let mut records = RecordReader::new(bytes);
+    match records.next() {
+        Some(Ok(first)) if first.record_type == record::HEADER => {}
+        _ => {
+            return Err(GdsError::InvalidHeader {
+                message: "file does not start with a HEADER record".to_string(),
+            })
+        }
+    }
+
+    let mut stats = GdsStats {
+        cell_count: 0,
+        total_elements: 0,
+        polygon_count: 0,
+        path_count: 0,
+        text_count: 0,
+        layer_count: 0,
+        bbox: None,
+        file_size_bytes: bytes.len() as u64,
+    };
+    let mut layers = HashSet::new();
+    // Whether the current element's XY is geometry (rather than a placement)
+    let mut geometry = false;
+    let mut histogram: Option<HashMap<(u16, u16), LayerHistogram>> = None;
+    // Whether the current structure is `histogram_cell`
+    let mut in_histogram_cell = false;
+    let mut shape: Option<PendingShape> = None;
+    for record in records {
+        let record = record?;
+        match record.record_type {
+            record::BGNSTR => stats.cell_count += 1,
+            record::STRNAME => {
+                in_histogram_cell = histogram_cell == Some(record.string().as_str());
+                if in_histogram_cell {
+                    histogram.get_or_insert_with(HashMap::new);
+                }
+            }
+            record::BOUNDARY | record::PATH | record::TEXT | record::BOX | record::NODE => {
+                stats.total_elements += 1;
+                match record.record_type {
+                    record::BOUNDARY => stats.polygon_count += 1,
+                    record::PATH => stats.path_count += 1,
+                    record::TEXT => stats.text_count += 1,
+                    _ => {}
+                }
+                geometry = true;
+                let is_shape =
+                    matches!(record.record_type, record::BOUNDARY | record::PATH | record::BOX);
+                shape = (in_histogram_cell && is_shape).then(PendingShape::default);
+            }
+            record::SREF | record::AREF => {
+                stats.total_elements += 1;
+                geometry = false;
+            }
+            record::LAYER => {
+                let layer = record.i16_value()? as u16;
+                layers.insert(layer);
+                if let Some(shape) = shape.as_mut() {
+                    shape.layer = layer;
+                }
+            }
+            record::DATATYPE | record::BOXTYPE => {
+                if let Some(shape) = shape.as_mut() {
+                    shape.datatype = record.i16_value()? as u16;
+                }
+            }
+            record::XY if geometry => {
+                let values = record.i32_values()?;
+                for point in values.chunks_exact(2) {
+                    merge(&mut stats.bbox, Bbox::from_point([point[0] as f64, point[1] as f64]));
+                }
+                if let Some(shape) = shape.as_mut() {
+                    shape.vertices = (values.len() / 2) as u64;
+                }
+            }
+            record::ENDEL => {
+                geometry = false;
+                if let (Some(shape), Some(histogram)) = (shape.take(), histogram.as_mut()) {
+                    let entry = histogram.entry((shape.layer, shape.datatype)).or_insert(
+                        LayerHistogram {
+                            layer: shape.layer,
+                            datatype: shape.datatype,
+                            polygon_count: 0,
+                            total_vertices: 0,
+                            max_vertices: 0,
+                        },
+                    );
+                    entry.polygon_count += 1;
+                    entry.total_vertices += shape.vertices;
+                    entry.max_vertices = entry.max_vertices.max(shape.vertices);
+                }
+            }
+            record::ENDSTR => in_histogram_cell = false,
+            record::ENDLIB => break,
+            _ => {}
+        }
+    }
+    stats.layer_count = layers.len() as u64;
+    let histogram = histogram.map(|histogram| {
+        let mut layers: Vec<LayerHistogram> = histogram.into_values().collect();
+        layers.sort_by(|a, b| {
+            b.total_vertices
+                .cmp(&a.total_vertices)
+                .then((a.layer, a.datatype).cmp(&(b.layer, b.datatype)))
+        });
+        layers
+    });
+    Ok((stats, histogram))
+}
+
+// Command to summarize the contents of a GDSII file
+#[tauri::command]
+pub async fn get_gds_stats(
+    path: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<GdsStats, AppError> {
+    let bytes = cache.bytes(&path)?;
+    Ok(scan_stats(&bytes)?)
+}
+
+// Command to list how many polygons and vertices each layer of a cell holds, to
+// find the layers that make it slow to render
+#[tauri::command]
+pub async fn get_geometry_histogram(
+    path: String,
+    cell: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<LayerHistogram>, AppError> {
+    let bytes = cache.bytes(&path)?;
+    Ok(scan_histogram(&bytes, &cell)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+
+    #[test]
+    fn counts_elements_and_extent() {
+        let square = [[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("A")
+            .boundary(1, 0, &square)
+            .boundary(1, 5, &square)
+            .path(2, 0, 0, 4, &[[-50, 0], [100, 0]])
+            .end_cell();
+        gds.begin_cell("TOP")
+            .text(10, 0, [5, 300], "VDD")
+            .box_element(3, 0, &square)
+            // Placements don't extend the extent
+            .sref("A", [5000, 5000], None)
+            .end_cell();
+        let bytes = gds.finish();
+
+        let stats = scan_stats(&bytes).unwrap();
+        assert_eq!(stats.cell_count, 2);
+        assert_eq!(stats.total_elements, 6);
+        assert_eq!((stats.polygon_count, stats.path_count, stats.text_count), (2, 1, 1));
+        assert_eq!(stats.layer_count, 4);
+        assert_eq!(
+            stats.bbox,
+            Some(Bbox { min_x: -50.0, min_y: 0.0, max_x: 100.0, max_y: 300.0 })
+        );
+        assert_eq!(stats.file_size_bytes, bytes.len() as u64);
+
+        assert!(matches!(scan_stats(b"not a gds"), Err(GdsError::InvalidHeader { .. })));
+
+        // The same counts from the parsed library; only the path's width differs
+        let library = crate::gds::parse_library(&bytes).unwrap();
+        let from_library = library_stats(&library, bytes.len() as u64);
+        assert_eq!(from_library.bbox.unwrap().min_y, -2.0);
+        assert_eq!(GdsStats { bbox: stats.bbox, ..from_library }, stats);
+
+        let histogram = scan_histogram(&bytes, "A").unwrap();
+        let rows: Vec<(u16, u16, u64, u64, u64)> = histogram
+            .iter()
+            .map(|h| (h.layer, h.datatype, h.polygon_count, h.total_vertices, h.max_vertices))
+            .collect();
+        assert_eq!(rows, [(1, 0, 1, 5, 5), (1, 5, 1, 5, 5), (2, 0, 1, 2, 2)]);
+        assert_eq!(scan_histogram(&bytes, "TOP").unwrap().len(), 1);
+        assert!(matches!(scan_histogram(&bytes, "B"), Err(GdsError::CellNotFound { .. })));
+    }
+}
@@ -0,0 +1,200 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, State};
+
+use super::cache::ParseCache;
+use super::flatten::{check_simplify_tolerance, output_layer_polygons, LayerPolygon, PolygonKind};
+use super::units::CoordConvention;
+use crate::error::AppError;
+use crate::sync::lock;
+
+// Polygons per `layer-geometry-chunk` event when the caller doesn't give a size
+const DEFAULT_CHUNK_POLYGONS: usize = 5_000;
+
+// State to hold the cancel flags of the geometry streams still sending, keyed by
+// request id. Finished streams remove themselves.
+#[derive(Default)]
+pub struct GeometryStreams {
+    next_id: AtomicU64,
+    running: Arc<Mutex<HashMap<u64, Arc<AtomicBool>>>>,
+}
+
+// One batch of a stream; `sequence` counts the chunks of a request from 0
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct GeometryChunkPayload<'a> {
+    pub request_id: u64,
+    pub sequence: usize,
+    pub polygons: &'a [LayerPolygon],
+}
+
+// Sent as `layer-geometry-done` once a stream stops, whether it sent every
+// chunk, was cancelled or failed before the first one
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct GeometryDonePayload {
+    pub request_id: u64,
+    pub chunk_count: usize,
+    pub polygon_count: usize,
+    pub cancelled: bool,
+    pub error: Option<AppError>,
+}
+
+impl GeometryStreams {
+    // Register a new stream, returning its request id and cancel flag
+    fn start(&self) -> (u64, Arc<AtomicBool>) {
+        let request_id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        lock(&self.running, "Geometry streams").insert(request_id, Arc::clone(&cancelled));
+        (request_id, cancelled)
+    }
+
+    // Ask a stream to stop before its next chunk. Returns false if no such
+    // stream is still sending.
+    pub fn cancel(&self, request_id: u64) -> bool {
+        match lock(&self.running, "Geometry streams").remove(&request_id) {
+            Some(cancelled) => {
+                cancelled.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Ask every stream still sending to stop before its next chunk. Returns
+    // how many there were.
+    pub fn cancel_all(&self) -> usize {
+        let running: Vec<_> = lock(&self.running, "Geometry streams").drain().collect();
+        for (_, cancelled) in &running {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+        running.len()
+    }
+}
+
+// Hand `polygons` to `send` in chunks of `chunk_size`, stopping once `cancelled`
+// is set. Returns the number of chunks sent.
+pub fn send_in_chunks(
+    polygons: &[LayerPolygon],
+    chunk_size: usize,
+    cancelled: &AtomicBool,
+    send: &mut dyn FnMut(usize, &[LayerPolygon]),
+) -> usize {
+    let mut sent = 0;
+    for chunk in polygons.chunks(chunk_size.max(1)) {
+        if cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+        send(sent, chunk);
+        sent += 1;
+    }
+    sent
+}
+
+// Command to stream the polygons `get_layer_polygons` would return in batches,
+// so a huge layer doesn't stall IPC. Returns a request id immediately; the
+// polygons arrive as `layer-geometry-chunk` events of `chunk_size` polygons
+// (5000 by default), then a `layer-geometry-done` event.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn stream_layer_polygons(
+    path: String,
+    cell: String,
+    layer: u16,
+    datatype: u16,
+    kinds: Option<Vec<PolygonKind>>,
+    simplify_tolerance: Option<f64>,
+    coord_convention: Option<CoordConvention>,
+    chunk_size: Option<usize>,
+    app: AppHandle,
+    cache: State<'_, ParseCache>,
+    streams: State<'_, GeometryStreams>,
+) -> Result<u64, AppError> {
+    check_simplify_tolerance(simplify_tolerance)?;
+    let chunk_size = chunk_size.unwrap_or(DEFAULT_CHUNK_POLYGONS);
+    if chunk_size == 0 {
+        return Err(AppError::invalid_argument("Chunk size must be at least 1"));
+    }
+    let (request_id, cancelled) = streams.start();
+    let running = Arc::clone(&streams.running);
+    let cache = ParseCache::clone(&cache);
+    std::thread::spawn(move || {
+        let polygons = cache.library(&path).and_then(|library| {
+            output_layer_polygons(
+                &library,
+                &cell,
+                layer,
+                datatype,
+                kinds.as_deref(),
+                simplify_tolerance,
+                coord_convention.as_ref(),
+                None,
+            )
+        });
+        let (polygons, error) = match polygons {
+            Ok(polygons) => (polygons, None),
+            Err(error) => (Vec::new(), Some(error)),
+        };
+        let chunk_count = send_in_chunks(&polygons, chunk_size, &cancelled, &mut |sequence, chunk| {
+            let payload = GeometryChunkPayload { request_id, sequence, polygons: chunk };
+            if let Err(e) = app.emit("layer-geometry-chunk", payload) {
+                log::warn!("Failed to emit layer geometry: {}", e);
+            }
+        });
+        lock(&running, "Geometry streams").remove(&request_id);
+        let payload = GeometryDonePayload {
+            request_id,
+            chunk_count,
+            polygon_count: polygons.len().min(chunk_count * chunk_size),
+            cancelled: cancelled.load(Ordering::Relaxed),
+            error,
+        };
+        if let Err(e) = app.emit("layer-geometry-done", payload) {
+            log::warn!("Failed to emit end of layer geometry: {}", e);
+        }
+    });
+    Ok(request_id)
+}
+
+// Command to stop a geometry stream before its next chunk. Returns false if it
+// already finished.
+#[tauri::command]
+pub async fn cancel_layer_polygons(
+    request_id: u64,
+    streams: State<'_, GeometryStreams>,
+) -> Result<bool, AppError> {
+    Ok(streams.cancel(request_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_are_numbered_and_stop_once_cancelled() {
+        let square = |x: f64| LayerPolygon {
+            kind: PolygonKind::Boundary,
+            points: vec![[x, 0.0], [x + 1.0, 0.0], [x + 1.0, 1.0], [x, 1.0]],
+        };
+        let polygons: Vec<LayerPolygon> = (0..5).map(|x| square(x as f64)).collect();
+        let not_cancelled = AtomicBool::new(false);
+        let mut chunks = Vec::new();
+        let sent = send_in_chunks(&polygons, 2, &not_cancelled, &mut |sequence, chunk| {
+            chunks.push((sequence, chunk.to_vec()));
+        });
+        assert_eq!(sent, 3);
+        let sequences: Vec<usize> = chunks.iter().map(|(sequence, _)| *sequence).collect();
+        assert_eq!(sequences, [0, 1, 2]);
+        assert_eq!(chunks.into_iter().flat_map(|(_, chunk)| chunk).collect::<Vec<_>>(), polygons);
+
+        // Cancelling from the receiving side stops before the next chunk
+        let streams = GeometryStreams::default();
+        let (request_id, cancelled) = streams.start();
+        let sent = send_in_chunks(&polygons, 2, &cancelled, &mut |_, _| {
+            assert!(streams.cancel(request_id));
+        });
+        assert_eq!(sent, 1);
+        assert!(!streams.cancel(request_id));
+    }
+}
@@ -0,0 +1,247 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+use super::bbox::Bbox;
+use super::cache::ParseCache;
+use super::flatten::check_polygon_count;
+use super::hit_test::{ElementRef, HitIndex, HitIndexCache};
+use crate::error::AppError;
+use crate::geometry::rtree::Rect;
+
+// Most tiles one grid may have, so a tiny tile size can't run away
+pub(crate) const MAX_TILES: usize = 1 << 20;
+
+// A tile of a cell holding at least one polygon. Tiles are numbered row by row
+// from the bottom left, so `index` is `row * columns + column`. `bbox` is in
+// database units like `get_cell_bbox`.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct CellTile {
+    pub index: usize,
+    pub column: usize,
+    pub row: usize,
+    pub bbox: Bbox,
+    pub elements: Vec<ElementRef>,
+}
+
+// A flattened polygon of a tile, in user units
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct TilePolygon {
+    pub element: ElementRef,
+    pub points: Vec<[f64; 2]>,
+}
+
+// Square tiles of `size` database units laid over the extent of a cell's
+// polygons, starting at its lower left corner
+struct TileGrid {
+    origin: [f64; 2],
+    size: f64,
+    columns: usize,
+    rows: usize,
+}
+
+impl TileGrid {
+    // The grid for `tile_size` user units, or `None` for a cell without polygons
+    fn new(index: &HitIndex, user_unit: f64, tile_size: f64) -> Result<Option<Self>, AppError> {
+        if !(tile_size.is_finite() && tile_size > 0.0) {
+            return Err(AppError::invalid_argument(format!(
+                "Tile size must be positive, got {}",
+                tile_size
+            )));
+        }
+        let extent = index
+            .polygons()
+            .iter()
+            .filter_map(|(_, outline)| Rect::around(outline))
+            .reduce(|a, b| Rect {
+                min: [a.min[0].min(b.min[0]), a.min[1].min(b.min[1])],
+                max: [a.max[0].max(b.max[0]), a.max[1].max(b.max[1])],
+            });
+        let Some(extent) = extent else {
+            return Ok(None);
+        };
+        let size = tile_size / user_unit;
+        let count = |span: f64| (span / size).floor() as usize + 1;
+        let columns = count(extent.max[0] - extent.min[0]);
+        let rows = count(extent.max[1] - extent.min[1]);
+        if columns.saturating_mul(rows) > MAX_TILES {
+            return Err(AppError::invalid_argument(format!(
+                "Tile size {} splits the cell into more than {} tiles",
+                tile_size, MAX_TILES
+            )));
+        }
+        Ok(Some(TileGrid { origin: extent.min, size, columns, rows }))
+    }
+
+    // Columns and rows of the tiles `rect` touches. A polygon ending exactly on
+    // a tile edge belongs to the tiles on both sides.
+    fn span(&self, rect: &Rect) -> (RangeInclusive<usize>, RangeInclusive<usize>) {
+        let tile = |value: f64, axis: usize, count: usize| {
+            (((value - self.origin[axis]) / self.size).floor().max(0.0) as usize).min(count - 1)
+        };
+        (
+            tile(rect.min[0], 0, self.columns)..=tile(rect.max[0], 0, self.columns),
+            tile(rect.min[1], 1, self.rows)..=tile(rect.max[1], 1, self.rows),
+        )
+    }
+
+    fn rect(&self, column: usize, row: usize) -> Rect {
+        let min = [
+            self.origin[0] + column as f64 * self.size,
+            self.origin[1] + row as f64 * self.size,
+        ];
+        Rect { min, max: [min[0] + self.size, min[1] + self.size] }
+    }
+}
+
+// The non-empty tiles of the cell `index` was built for, in index order. A
+// polygon is listed in every tile its bounding box touches.
+pub fn cell_tiles(
+    index: &HitIndex,
+    user_unit: f64,
+    tile_size: f64,
+) -> Result<Vec<CellTile>, AppError> {
+    let Some(grid) = TileGrid::new(index, user_unit, tile_size)? else {
+        return Ok(Vec::new());
+    };
+    let mut tiles: BTreeMap<usize, Vec<ElementRef>> = BTreeMap::new();
+    for (element, outline) in index.polygons() {
+        let Some(rect) = Rect::around(outline) else {
+            continue;
+        };
+        let (columns, rows) = grid.span(&rect);
+        for row in rows {
+            for column in columns.clone() {
+                tiles.entry(row * grid.columns + column).or_default().push(*element);
+            }
+        }
+    }
+    Ok(tiles
+        .into_iter()
+        .map(|(index, elements)| {
+            let (column, row) = (index % grid.columns, index / grid.columns);
+            let Rect { min, max } = grid.rect(column, row);
+            let bbox = Bbox { min_x: min[0], min_y: min[1], max_x: max[0], max_y: max[1] };
+            CellTile { index, column, row, bbox, elements }
+        })
+        .collect())
+}
+
+// The polygons `cell_tiles` lists for tile `tile_index`, in user units
+pub fn tile_geometry(
+    index: &HitIndex,
+    user_unit: f64,
+    tile_size: f64,
+    tile_index: usize,
+) -> Result<Vec<TilePolygon>, AppError> {
+    let grid = TileGrid::new(index, user_unit, tile_size)?;
+    let tile_count = grid.as_ref().map_or(0, |grid| grid.columns * grid.rows);
+    let Some(grid) = grid.filter(|_| tile_index < tile_count) else {
+        return Err(AppError::invalid_argument(format!(
+            "Tile {} is outside the grid of {} tiles",
+            tile_index, tile_count
+        )));
+    };
+    let (column, row) = (tile_index % grid.columns, tile_index / grid.columns);
+    Ok(index
+        .within(&grid.rect(column, row))
+        .into_iter()
+        .filter(|(_, outline)| {
+            Rect::around(outline).is_some_and(|rect| {
+                let (columns, rows) = grid.span(&rect);
+                columns.contains(&column) && rows.contains(&row)
+            })
+        })
+        .map(|(element, outline)| TilePolygon {
+            element,
+            points: outline.iter().map(|p| [p[0] * user_unit, p[1] * user_unit]).collect(),
+        })
+        .collect())
+}
+
+// Command to split a cell's flattened geometry into a grid of `tile_size`
+// (user units) tiles, listing the polygons of each, so the view can load only
+// the tiles on screen
+#[tauri::command]
+pub async fn get_cell_tiles(
+    path: String,
+    cell: String,
+    tile_size: f64,
+    cache: tauri::State<'_, ParseCache>,
+    indexes: tauri::State<'_, HitIndexCache>,
+) -> Result<Vec<CellTile>, AppError> {
+    let library = cache.library(&path)?;
+    let index = indexes.index(&path, &library, &cell)?;
+    cell_tiles(&index, library.units.user_unit, tile_size)
+}
+
+// Command to get the polygons of one tile of `get_cell_tiles`. More than
+// `max_polygons` fails with `TooLarge`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn get_tile_geometry(
+    path: String,
+    cell: String,
+    tile_size: f64,
+    tile_index: usize,
+    max_polygons: Option<usize>,
+    cache: tauri::State<'_, ParseCache>,
+    indexes: tauri::State<'_, HitIndexCache>,
+) -> Result<Vec<TilePolygon>, AppError> {
+    let library = cache.library(&path)?;
+    let index = indexes.index(&path, &library, &cell)?;
+    let polygons = tile_geometry(&index, library.units.user_unit, tile_size, tile_index)?;
+    check_polygon_count(polygons.len(), max_polygons)?;
+    Ok(polygons)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn tiles_list_every_polygon_they_touch() {
+        let square =
+            |x: i32, size: i32| [[x, 0], [x + size, 0], [x + size, size], [x, size], [x, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP")
+            .boundary(1, 0, &square(0, 100))
+            .boundary(1, 0, &square(250, 50))
+            // Spans the first two columns
+            .boundary(2, 0, &square(50, 150))
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+        let index = HitIndex::build(&library, "TOP").unwrap();
+        let user_unit = library.units.user_unit;
+        let tile_size = 120.0 * user_unit;
+
+        // A 3 x 2 grid from (0, 0); the top row only holds the layer 2 square
+        let tiles = cell_tiles(&index, user_unit, tile_size).unwrap();
+        let element = |layer, index| ElementRef { layer, datatype: 0, index };
+        let listed: Vec<(usize, Vec<ElementRef>)> =
+            tiles.iter().map(|tile| (tile.index, tile.elements.clone())).collect();
+        assert_eq!(
+            listed,
+            [
+                (0, vec![element(1, 0), element(2, 0)]),
+                (1, vec![element(2, 0)]),
+                (2, vec![element(1, 1)]),
+                (3, vec![element(2, 0)]),
+                (4, vec![element(2, 0)]),
+            ]
+        );
+        assert_eq!((tiles[4].column, tiles[4].row), (1, 1));
+        assert_eq!(tiles[4].bbox, Bbox { min_x: 120.0, min_y: 120.0, max_x: 240.0, max_y: 240.0 });
+
+        let geometry = tile_geometry(&index, user_unit, tile_size, 2).unwrap();
+        assert_eq!(geometry.len(), 1);
+        assert_eq!(geometry[0].element, element(1, 1));
+        assert_eq!(geometry[0].points[0], [250.0 * user_unit, 0.0]);
+        assert!(tile_geometry(&index, user_unit, tile_size, 5).unwrap().is_empty());
+        assert!(tile_geometry(&index, user_unit, tile_size, 6).is_err());
+        assert!(cell_tiles(&index, user_unit, 0.0).is_err());
+    }
+}
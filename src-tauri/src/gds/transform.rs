@@ -0,0 +1,233 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::{ArrayParams, CellReference, Strans};
+
+// A 2D affine transform: x' = a*x + b*y + tx, y' = c*x + d*y + ty
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+pub struct Transform {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub tx: f64,
+    pub ty: f64,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::IDENTITY
+    }
+}
+
+// Cosine and sine of an angle in degrees, exact for multiples of 90 so that
+// Manhattan placements don't pick up floating-point noise
+fn cos_sin_degrees(angle: f64) -> (f64, f64) {
+    let normalized = angle.rem_euclid(360.0);
+    if normalized == 0.0 {
+        (1.0, 0.0)
+    } else if normalized == 90.0 {
+        (0.0, 1.0)
+    } else if normalized == 180.0 {
+        (-1.0, 0.0)
+    } else if normalized == 270.0 {
+        (0.0, -1.0)
+    } else {
+        let radians = normalized.to_radians();
+        (radians.cos(), radians.sin())
+    }
+}
+
+// Apply the STRANS of a reference or text to a point in the child's
+// coordinates, in the order GDSII specifies: reflect about the X axis, then
+// rotate counterclockwise by `angle` degrees, then scale by `mag`. Every
+// placement goes through here; ABS_MAG and ABS_ANGLE only matter once it is
+// nested in another transform, see `Transform::place`.
+pub fn apply_strans(strans: &Strans, point: [f64; 2]) -> [f64; 2] {
+    let [x, y] = point;
+    let y = if strans.reflect_x { -y } else { y };
+    let (cos, sin) = cos_sin_degrees(strans.angle);
+    let (x, y) = (x * cos - y * sin, x * sin + y * cos);
+    [x * strans.mag, y * strans.mag]
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform { a: 1.0, b: 0.0, c: 0.0, d: 1.0, tx: 0.0, ty: 0.0 };
+
+    pub fn translation(dx: f64, dy: f64) -> Self {
+        Transform { tx: dx, ty: dy, ..Transform::IDENTITY }
+    }
+
+    // The placement transform of a reference: `apply_strans`, then translate to
+    // `origin`. Its matrix columns are the images of the unit vectors, so the
+    // order of the STRANS steps is only spelled out in `apply_strans`.
+    pub fn from_placement(strans: &Strans, origin: [i32; 2]) -> Self {
+        let [a, c] = apply_strans(strans, [1.0, 0.0]);
+        let [b, d] = apply_strans(strans, [0.0, 1.0]);
+        Transform { a, b, c, d, tx: origin[0] as f64, ty: origin[1] as f64 }
+    }
+
+    // The transform that applies `inner` first and then `self`
+    pub fn compose(&self, inner: &Transform) -> Transform {
+        Transform {
+            a: self.a * inner.a + self.b * inner.c,
+            b: self.a * inner.b + self.b * inner.d,
+            c: self.c * inner.a + self.d * inner.c,
+            d: self.c * inner.b + self.d * inner.d,
+            tx: self.a * inner.tx + self.b * inner.ty + self.tx,
+            ty: self.c * inner.tx + self.d * inner.ty + self.ty,
+        }
+    }
+
+    // Magnification, counterclockwise rotation in degrees and whether it
+    // reflects, for a transform built from placements. Rotations within a
+    // rounding error of a multiple of 90 degrees come out exact.
+    fn decompose(&self) -> (f64, f64, bool) {
+        let determinant = self.a * self.d - self.b * self.c;
+        let angle = self.c.atan2(self.a).to_degrees();
+        let quarter = (angle / 90.0).round() * 90.0;
+        let angle = if (angle - quarter).abs() < 1e-9 { quarter } else { angle };
+        (determinant.abs().sqrt(), angle, determinant < 0.0)
+    }
+
+    // The transform of an instance inside `self`, where `instance` places it
+    // in the parent with `strans`. The two normally compose. With ABS_MAG or
+    // ABS_ANGLE the instance keeps its own magnification or rotation whatever
+    // the enclosing references do; only its position and reflection follow them.
+    pub fn place(&self, instance: &Transform, strans: &Strans) -> Transform {
+        let composed = self.compose(instance);
+        if !strans.abs_mag && !strans.abs_angle {
+            return composed;
+        }
+        let (mag, angle, reflected) = self.decompose();
+        let absolute = Strans {
+            reflect_x: reflected != strans.reflect_x,
+            mag: if strans.abs_mag { strans.mag } else { mag * strans.mag },
+            // A reflected parent turns the child's rotation the other way
+            angle: if strans.abs_angle {
+                strans.angle
+            } else if reflected {
+                angle - strans.angle
+            } else {
+                angle + strans.angle
+            },
+            ..*strans
+        };
+        let linear = Transform::from_placement(&absolute, [0, 0]);
+        Transform { tx: composed.tx, ty: composed.ty, ..linear }
+    }
+
+    pub fn apply(&self, x: f64, y: f64) -> [f64; 2] {
+        [self.a * x + self.b * y + self.tx, self.c * x + self.d * y + self.ty]
+    }
+
+    pub fn apply_point(&self, point: [i32; 2]) -> [f64; 2] {
+        self.apply(point[0] as f64, point[1] as f64)
+    }
+}
+
+// Per-instance translation steps of an AREF lattice, in the parent's coordinates:
+// (column step, row step)
+pub fn array_steps(origin: [i32; 2], array: &ArrayParams) -> ([f64; 2], [f64; 2]) {
+    let columns = array.columns.max(1) as f64;
+    let rows = array.rows.max(1) as f64;
+    // In f64, since lattice points on opposite ends of the i32 range are
+    // further apart than an i32 can hold
+    let delta = |point: [i32; 2], axis: usize| point[axis] as f64 - origin[axis] as f64;
+    let column_step =
+        [delta(array.column_point, 0) / columns, delta(array.column_point, 1) / columns];
+    let row_step = [delta(array.row_point, 0) / rows, delta(array.row_point, 1) / rows];
+    (column_step, row_step)
+}
+
+// Transforms of every instance placed by a reference: one for an SREF, one per
+// array position for an AREF
+pub fn instance_transforms(reference: &CellReference) -> impl Iterator<Item = Transform> + '_ {
+    let placement = Transform::from_placement(&reference.strans, reference.origin);
+    let (columns, rows, column_step, row_step) = match &reference.array {
+        Some(array) => {
+            let (column_step, row_step) = array_steps(reference.origin, array);
+            (array.columns.max(1), array.rows.max(1), column_step, row_step)
+        }
+        None => (1, 1, [0.0, 0.0], [0.0, 0.0]),
+    };
+    (0..rows).flat_map(move |row| {
+        (0..columns).map(move |column| {
+            let (i, j) = (column as f64, row as f64);
+            Transform::translation(
+                i * column_step[0] + j * row_step[0],
+                i * column_step[1] + j * row_step[1],
+            )
+            .compose(&placement)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placement_reflects_before_rotating() {
+        let strans = Strans { reflect_x: true, angle: 90.0, mag: 2.0, ..Strans::default() };
+        let transform = Transform::from_placement(&strans, [10, 20]);
+        // (1, 2) -> reflect (1, -2) -> mag (2, -4) -> rotate 90 (4, 2) -> translate
+        assert_eq!(transform.apply(1.0, 2.0), [14.0, 22.0]);
+    }
+
+    #[test]
+    fn reflected_and_rotated_placements_land_exactly() {
+        let strans = |reflect_x, angle, mag| Strans { reflect_x, angle, mag, ..Strans::default() };
+        // (point, strans, origin, expected)
+        let cases = [
+            ([3.0, 1.0], strans(true, 0.0, 1.0), [0, 0], [3.0, -1.0]),
+            ([3.0, 1.0], strans(true, 90.0, 1.0), [0, 0], [1.0, 3.0]),
+            ([3.0, 1.0], strans(true, 180.0, 1.0), [0, 0], [-3.0, 1.0]),
+            ([3.0, 1.0], strans(true, 270.0, 3.0), [100, -50], [97.0, -59.0]),
+            ([3.0, 1.0], strans(false, -90.0, 0.5), [0, 0], [0.5, -1.5]),
+        ];
+        for (point, strans, origin, expected) in cases {
+            let placed = Transform::from_placement(&strans, origin).apply(point[0], point[1]);
+            assert_eq!(placed, expected, "{:?} at {:?}", strans, origin);
+        }
+
+        // A reflected child inside a rotated parent: the child's reflection
+        // happens in the child's frame, before the parent rotates it
+        let child = Transform::from_placement(&strans(true, 0.0, 1.0), [10, 0]);
+        let parent = Transform::from_placement(&strans(false, 90.0, 1.0), [0, 0]);
+        assert_eq!(parent.compose(&child).apply(1.0, 2.0), [2.0, 11.0]);
+    }
+
+    #[test]
+    fn absolute_flags_ignore_the_enclosing_transform() {
+        let strans = |angle, mag, abs_angle, abs_mag| Strans {
+            angle,
+            mag,
+            abs_angle,
+            abs_mag,
+            ..Strans::default()
+        };
+        let parent = Transform::from_placement(&strans(90.0, 2.0, false, false), [0, 0]);
+        let place = |child: Strans| {
+            let instance = Transform::from_placement(&child, [10, 0]);
+            parent.place(&instance, &child).apply(1.0, 0.0)
+        };
+        // Relative: rotated by 90 + 90 and scaled by 2 * 3, at the rotated origin
+        assert_eq!(place(strans(90.0, 3.0, false, false)), [-6.0, 20.0]);
+        assert_eq!(place(strans(90.0, 3.0, true, false)), [0.0, 26.0]);
+        assert_eq!(place(strans(90.0, 3.0, false, true)), [-3.0, 20.0]);
+        assert_eq!(place(strans(90.0, 3.0, true, true)), [0.0, 23.0]);
+        // At the top level the flags change nothing
+        let child = strans(90.0, 3.0, true, true);
+        let instance = Transform::from_placement(&child, [10, 0]);
+        assert_eq!(Transform::IDENTITY.place(&instance, &child), instance);
+    }
+
+    #[test]
+    fn composition_applies_inner_first() {
+        let inner = Transform::from_placement(&Strans { angle: 90.0, ..Strans::default() }, [5, 0]);
+        let outer = Transform::translation(100.0, 0.0);
+        let composed = outer.compose(&inner);
+        assert_eq!(composed.apply(1.0, 0.0), [105.0, 1.0]);
+    }
+}
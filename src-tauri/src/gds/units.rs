@@ -0,0 +1,188 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::cache::ParseCache;
+use super::record::{self, Record, RecordReader};
+use super::{GdsError, GdsUnits};
+use crate::error::AppError;
+
+// Length unit for geometry output: raw database units, the file's user units,
+// or a physical unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LengthUnit {
+    #[default]
+    Db,
+    User,
+    Nm,
+    #[serde(alias = "µm")]
+    Um,
+    Mm,
+}
+
+// How geometry commands should report coordinates. Defaults to raw database
+// units with Y pointing up, as stored in the file; `flip_y` negates Y for
+// Y-down targets such as SVG and canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+pub struct CoordConvention {
+    #[serde(default)]
+    pub unit: LengthUnit,
+    #[serde(default)]
+    pub flip_y: bool,
+}
+
+// Mapping from database-unit coordinates to the coordinates a command returns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoordMapping {
+    pub scale: f64,
+    pub flip_y: bool,
+}
+
+impl CoordMapping {
+    // Mapping for `convention` in a file with `units`. Without a convention,
+    // geometry commands keep returning user units with Y up.
+    pub fn new(convention: Option<&CoordConvention>, units: &GdsUnits) -> Self {
+        let Some(convention) = convention else {
+            return CoordMapping { scale: units.user_unit, flip_y: false };
+        };
+        let unit_meters = match convention.unit {
+            LengthUnit::Db => units.db_unit_meters,
+            LengthUnit::User => units.db_unit_meters / units.user_unit,
+            LengthUnit::Nm => 1e-9,
+            LengthUnit::Um => 1e-6,
+            LengthUnit::Mm => 1e-3,
+        };
+        CoordMapping { scale: units.db_unit_meters / unit_meters, flip_y: convention.flip_y }
+    }
+
+    pub fn point(&self, [x, y]: [f64; 2]) -> [f64; 2] {
+        let y = if self.flip_y { -y } else { y };
+        // Adding 0.0 turns a flipped -0.0 into 0.0
+        [x * self.scale + 0.0, y * self.scale + 0.0]
+    }
+
+    // Flipping Y negates signed areas, so rings change orientation
+    pub fn ring(&self, ring: &[[f64; 2]]) -> Vec<[f64; 2]> {
+        ring.iter().map(|&point| self.point(point)).collect()
+    }
+
+    // Inverse of `point`: back to database units
+    pub fn to_db(&self, [x, y]: [f64; 2]) -> [f64; 2] {
+        let y = if self.flip_y { -y } else { y };
+        [x / self.scale, y / self.scale]
+    }
+}
+
+// Decode and validate a UNITS record: two positive REAL8 values
+pub fn units_from_record(record: &Record) -> Result<GdsUnits, GdsError> {
+    let values = record.real8_values()?;
+    if values.len() < 2 {
+        return Err(GdsError::InvalidUnits {
+            message: format!("UNITS record at byte {} needs two values", record.offset),
+        });
+    }
+    let units = GdsUnits { user_unit: values[0], db_unit_meters: values[1] };
+    if !(units.user_unit > 0.0 && units.db_unit_meters > 0.0) {
+        return Err(GdsError::InvalidUnits {
+            message: format!(
+                "UNITS must be positive, got user unit {} and database unit {} m",
+                units.user_unit, units.db_unit_meters
+            ),
+        });
+    }
+    Ok(units)
+}
+
+// Read only as far as the UNITS record, which precedes the first structure
+pub fn scan_units(bytes: &[u8]) -> Result<GdsUnits, GdsError> {
+    let mut records = RecordReader::new(bytes);
+    match records.next() {
+        Some(Ok(first)) if first.record_type == record::HEADER => {}
+        _ => {
+            return Err(GdsError::InvalidHeader {
+                message: "file does not start with a HEADER record".to_string(),
+            })
+        }
+    }
+    for record in records {
+        let record = record.map_err(|error| error.with_context(bytes))?;
+        match record.record_type {
+            record::UNITS => return units_from_record(&record).map_err(|e| e.with_context(bytes)),
+            record::BGNSTR | record::ENDLIB => break,
+            _ => {}
+        }
+    }
+    Err(GdsError::InvalidUnits { message: "library has no UNITS record".to_string() })
+}
+
+// Command to read the database unit scale of a GDSII file
+#[tauri::command]
+pub async fn get_gds_units(
+    path: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<GdsUnits, AppError> {
+    let bytes = cache.bytes(&path)?;
+    Ok(scan_units(&bytes)?)
+}
+
+// Command to convert database-unit points (e.g. from `get_cell_bbox`) to a
+// coordinate convention
+#[tauri::command]
+pub async fn transform_coords(
+    path: String,
+    points: Vec<[f64; 2]>,
+    convention: CoordConvention,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<Vec<[f64; 2]>, AppError> {
+    let bytes = cache.bytes(&path)?;
+    let mapping = CoordMapping::new(Some(&convention), &scan_units(&bytes)?);
+    Ok(mapping.ring(&points))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+
+    #[test]
+    fn reads_units_and_rejects_non_positive_values() {
+        let mut gds = GdsBuilder::library("LIB");
+        let units = scan_units(&gds.finish()).unwrap();
+        assert!((units.user_unit - 0.001).abs() < 1e-15);
+        assert!((units.db_unit_meters - 1e-9).abs() < 1e-21);
+
+        let mut bad = GdsBuilder::empty();
+        bad.int16(record::HEADER, &[600]).reals(record::UNITS, &[0.001, 0.0]);
+        assert!(matches!(scan_units(&bad.finish()), Err(GdsError::InvalidUnits { .. })));
+
+        let mut missing = GdsBuilder::empty();
+        missing.int16(record::HEADER, &[600]).string(record::LIBNAME, "LIB");
+        assert!(matches!(scan_units(&missing.finish()), Err(GdsError::InvalidUnits { .. })));
+    }
+
+    #[test]
+    fn conventions_scale_and_flip() {
+        // 1 nm database units, 1 µm user units
+        let units = GdsUnits { user_unit: 0.001, db_unit_meters: 1e-9 };
+        let map = |convention: Option<CoordConvention>, point| {
+            let mapping = CoordMapping::new(convention.as_ref(), &units);
+            let mapped: [f64; 2] = mapping.point(point);
+            let back = mapping.to_db(mapped);
+            assert!((back[0] - point[0]).abs() < 1e-9 && (back[1] - point[1]).abs() < 1e-9);
+            mapped
+        };
+        let convention = |unit, flip_y| Some(CoordConvention { unit, flip_y });
+
+        assert_eq!(map(None, [1500.0, 0.0]), [1.5, 0.0]);
+        assert_eq!(map(Some(CoordConvention::default()), [1500.0, -20.0]), [1500.0, -20.0]);
+        assert_eq!(map(convention(LengthUnit::Db, true), [1500.0, 0.0]), [1500.0, 0.0]);
+        assert_eq!(map(convention(LengthUnit::Nm, true), [1500.0, 20.0]), [1500.0, -20.0]);
+        let mm = map(convention(LengthUnit::Mm, false), [1500.0, 20.0]);
+        assert!((mm[0] - 0.0015).abs() < 1e-15 && (mm[1] - 0.00002).abs() < 1e-15);
+        let user = map(convention(LengthUnit::User, true), [1500.0, 20.0]);
+        assert!((user[0] - 1.5).abs() < 1e-12 && (user[1] + 0.02).abs() < 1e-12);
+
+        let parsed: CoordConvention = serde_json::from_str(r#"{ "unit": "µm" }"#).unwrap();
+        assert_eq!(parsed, CoordConvention { unit: LengthUnit::Um, flip_y: false });
+    }
+}
@@ -0,0 +1,238 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::io::Read;
+
+use super::cache::ParseCache;
+use super::mmap::map_or_read;
+use super::parser::header_version;
+use super::record::{self, record_type_name, RecordReader, RECORD_HEADER_LEN};
+use super::{catch_reader_panic, GdsError};
+use crate::error::AppError;
+
+// A HEADER record holds a single INT16 version
+const HEADER_RECORD_LEN: u64 = RECORD_HEADER_LEN as u64 + 2;
+
+// Most issues listed for one file; the count of the rest is still reported
+const MAX_ISSUES: usize = 100;
+
+// A structural problem found at `offset`
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ValidationIssue {
+    pub offset: u64,
+    pub message: String,
+}
+
+// Outcome of checking a stream. `valid` is true when there are no issues;
+// `issue_count` includes issues beyond the ones listed.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub record_count: u64,
+    pub issue_count: usize,
+    pub issues: Vec<ValidationIssue>,
+}
+
+#[derive(Default)]
+struct Issues {
+    listed: Vec<ValidationIssue>,
+    count: usize,
+}
+
+impl Issues {
+    fn push(&mut self, offset: u64, message: impl Into<String>) {
+        self.count += 1;
+        if self.listed.len() < MAX_ISSUES {
+            self.listed.push(ValidationIssue { offset, message: message.into() });
+        }
+    }
+}
+
+// Check the record structure of a GDSII stream without decoding any geometry:
+// HEADER first, one BGNLIB matched by ENDLIB, BGNSTR/ENDSTR and element/ENDEL
+// pairs properly nested, and every record of even length within the stream.
+// After a damaged record the check resumes at the next plausible header, so
+// one bad record doesn't hide the rest.
+pub fn validate(bytes: &[u8]) -> ValidationReport {
+    let mut issues = Issues::default();
+    let mut records = RecordReader::new(bytes);
+    let mut record_count = 0;
+    let mut library_open = false;
+    let mut library_ended = false;
+    // Offsets of the open BGNSTR and element records
+    let mut structure: Option<u64> = None;
+    let mut element: Option<u64> = None;
+
+    while let Some(result) = records.next() {
+        let record = match result {
+            Ok(record) => record,
+            Err(error) => {
+                let offset = match &error {
+                    GdsError::Truncated { offset, .. }
+                    | GdsError::InvalidRecord { offset, .. } => *offset,
+                    _ => bytes.len() as u64,
+                };
+                issues.push(offset, error.to_string());
+                if records.resync(offset) {
+                    continue;
+                }
+                break;
+            }
+        };
+        let offset = record.offset;
+        let name = record_type_name(record.record_type);
+        record_count += 1;
+
+        if record_count == 1 && record.record_type != record::HEADER {
+            issues.push(offset, format!("stream starts with {} instead of HEADER", name));
+        }
+        let length = record.data.len() + RECORD_HEADER_LEN;
+        if length % 2 != 0 {
+            issues.push(offset, format!("{} record has odd length {}", name, length));
+        }
+        if name == "UNKNOWN" {
+            issues.push(offset, format!("unknown record type 0x{:02X}", record.record_type));
+        }
+
+        match record.record_type {
+            record::HEADER if record_count > 1 => {
+                issues.push(offset, "HEADER record after the start of the stream")
+            }
+            record::BGNLIB => {
+                if library_open || library_ended {
+                    issues.push(offset, "second BGNLIB record");
+                }
+                library_open = true;
+            }
+            record::ENDLIB => {
+                if !library_open {
+                    issues.push(offset, "ENDLIB without BGNLIB");
+                }
+                if let Some(start) = structure.take() {
+                    issues.push(start, "structure has no ENDSTR before ENDLIB");
+                }
+                library_ended = true;
+                break;
+            }
+            record::BGNSTR => {
+                if !library_open {
+                    issues.push(offset, "BGNSTR before BGNLIB");
+                }
+                if let Some(start) = element.take() {
+                    issues.push(start, "element has no ENDEL");
+                }
+                if let Some(start) = structure.replace(offset) {
+                    issues.push(start, "structure has no ENDSTR before the next BGNSTR");
+                }
+            }
+            record::ENDSTR => {
+                if let Some(start) = element.take() {
+                    issues.push(start, "element has no ENDEL");
+                }
+                if structure.take().is_none() {
+                    issues.push(offset, "ENDSTR without BGNSTR");
+                }
+            }
+            record::ENDEL if element.is_none() => issues.push(offset, "ENDEL without an element"),
+            record::ENDEL => element = None,
+            record::BOUNDARY
+            | record::PATH
+            | record::SREF
+            | record::AREF
+            | record::TEXT
+            | record::NODE
+            | record::BOX => {
+                if structure.is_none() {
+                    issues.push(offset, format!("{} element outside a structure", name));
+                }
+                if let Some(start) = element.replace(offset) {
+                    issues.push(start, "element has no ENDEL");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let end = bytes.len() as u64;
+    if let Some(start) = element {
+        issues.push(start, "element has no ENDEL");
+    }
+    if let Some(start) = structure {
+        issues.push(start, "structure has no ENDSTR");
+    }
+    if !library_ended {
+        issues.push(end, "missing ENDLIB record");
+    }
+
+    ValidationReport {
+        valid: issues.count == 0,
+        record_count,
+        issue_count: issues.count,
+        issues: issues.listed,
+    }
+}
+
+// Command to check whether a GDSII file is structurally sound, as a cheap
+// pre-check before opening it
+#[tauri::command]
+pub async fn validate_gds(
+    path: String,
+    cache: tauri::State<'_, ParseCache>,
+) -> Result<ValidationReport, AppError> {
+    let bytes = cache.bytes(&path)?;
+    Ok(catch_reader_panic(|| Ok(validate(&bytes)))?)
+}
+
+// Command to read a GDSII file's stream format version from its HEADER record,
+// without reading the rest of the file
+#[tauri::command]
+pub async fn get_gds_version(path: String) -> Result<i16, AppError> {
+    let header = map_or_read(&path, |file, _| {
+        let mut bytes = Vec::new();
+        file.take(HEADER_RECORD_LEN).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    })
+    .map_err(|e| AppError::from_stream(e, "GDSII"))?;
+    header_version(&header).map_err(|e| AppError::from_stream(e, "GDSII"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+
+    #[test]
+    fn well_formed_streams_pass_and_damage_is_located() {
+        let square = [[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]];
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("TOP").boundary(1, 0, &square).end_cell();
+        let bytes = gds.finish();
+        let report = validate(&bytes);
+        assert!(report.valid, "{:?}", report.issues);
+        assert_eq!(report.record_count, 13);
+
+        // An element outside any structure, a structure left open, then the
+        // stream cut off before ENDLIB
+        let mut gds = GdsBuilder::library("LIB");
+        gds.boundary(1, 0, &square);
+        gds.begin_cell("OPEN").boundary(1, 0, &square);
+        let mut bytes = gds.finish();
+        bytes.truncate(bytes.len() - 4);
+        let report = validate(&bytes);
+        assert!(!report.valid);
+        let messages: Vec<&str> =
+            report.issues.iter().map(|issue| issue.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "BOUNDARY element outside a structure",
+                "structure has no ENDSTR",
+                "missing ENDLIB record",
+            ]
+        );
+        assert_eq!(report.issues[2].offset, bytes.len() as u64);
+
+        // A record running past the end of the stream
+        let report = validate(&bytes[..bytes.len() - 2]);
+        assert!(report.issues.iter().any(|issue| issue.message.starts_with("Truncated file")));
+    }
+}
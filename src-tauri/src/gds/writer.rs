@@ -0,0 +1,292 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::parser::{STRANS_ABS_ANGLE, STRANS_ABS_MAG, STRANS_REFLECT};
+use super::record::{self, encode_real8, RECORD_HEADER_LEN};
+use super::{CellReference, GdsElement, GdsError, GdsLibrary, Property, Strans};
+
+// Stream format version written to HEADER
+pub const GDS_VERSION: i16 = 600;
+
+// Year, month, day, hour, minute and second of a Unix timestamp (UTC), for the
+// BGNLIB/BGNSTR modification and access dates
+pub(crate) fn civil_time(unix_seconds: u64) -> [i16; 6] {
+    let days = (unix_seconds / 86_400) as i64;
+    let seconds = unix_seconds % 86_400;
+    // Days to civil date, after Howard Hinnant's algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    [
+        year as i16,
+        month as i16,
+        day as i16,
+        (seconds / 3600) as i16,
+        (seconds / 60 % 60) as i16,
+        (seconds % 60) as i16,
+    ]
+}
+
+struct GdsWriter {
+    bytes: Vec<u8>,
+}
+
+impl GdsWriter {
+    fn record(&mut self, record_type: u8, data_type: u8, data: &[u8]) -> Result<(), GdsError> {
+        let length = data.len() + RECORD_HEADER_LEN;
+        if length > u16::MAX as usize {
+            return Err(GdsError::invalid_record(
+                self.bytes.len() as u64,
+                "a record of at most 65535 bytes",
+                format!(
+                    "{} record would be {} bytes, more than a record can hold",
+                    record::record_type_name(record_type),
+                    length
+                ),
+            ));
+        }
+        self.bytes.extend_from_slice(&(length as u16).to_be_bytes());
+        self.bytes.push(record_type);
+        self.bytes.push(data_type);
+        self.bytes.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn empty(&mut self, record_type: u8) -> Result<(), GdsError> {
+        self.record(record_type, record::NO_DATA, &[])
+    }
+
+    fn int16(&mut self, record_type: u8, values: &[i16]) -> Result<(), GdsError> {
+        let data: Vec<u8> = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+        self.record(record_type, record::INT16, &data)
+    }
+
+    fn int32(&mut self, record_type: u8, values: &[i32]) -> Result<(), GdsError> {
+        let data: Vec<u8> = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+        self.record(record_type, record::INT32, &data)
+    }
+
+    fn real8(&mut self, record_type: u8, values: &[f64]) -> Result<(), GdsError> {
+        let data: Vec<u8> = values.iter().flat_map(|v| encode_real8(*v)).collect();
+        self.record(record_type, record::REAL8, &data)
+    }
+
+    // ASCII data is padded with a NUL to an even length
+    fn string(&mut self, record_type: u8, value: &str) -> Result<(), GdsError> {
+        let mut data = value.as_bytes().to_vec();
+        if data.len() % 2 != 0 {
+            data.push(0);
+        }
+        self.record(record_type, record::ASCII, &data)
+    }
+
+    fn xy(&mut self, points: &[[i32; 2]]) -> Result<(), GdsError> {
+        let values: Vec<i32> = points.iter().flat_map(|p| [p[0], p[1]]).collect();
+        self.int32(record::XY, &values)
+    }
+
+    // STRANS with MAG and ANGLE, all omitted for the identity
+    fn strans(&mut self, strans: &Strans) -> Result<(), GdsError> {
+        if *strans == Strans::default() {
+            return Ok(());
+        }
+        let mut flags = 0;
+        if strans.reflect_x {
+            flags |= STRANS_REFLECT;
+        }
+        if strans.abs_mag {
+            flags |= STRANS_ABS_MAG;
+        }
+        if strans.abs_angle {
+            flags |= STRANS_ABS_ANGLE;
+        }
+        self.record(record::STRANS, record::BIT_ARRAY, &flags.to_be_bytes())?;
+        if strans.mag != 1.0 {
+            self.real8(record::MAG, &[strans.mag])?;
+        }
+        if strans.angle != 0.0 {
+            self.real8(record::ANGLE, &[strans.angle])?;
+        }
+        Ok(())
+    }
+
+    // Properties and the closing ENDEL
+    fn end_element(&mut self, properties: &[Property]) -> Result<(), GdsError> {
+        for property in properties {
+            self.int16(record::PROPATTR, &[property.attribute as i16])?;
+            self.string(record::PROPVALUE, &property.value)?;
+        }
+        self.empty(record::ENDEL)
+    }
+
+    fn element(&mut self, element: &GdsElement) -> Result<(), GdsError> {
+        match element {
+            GdsElement::Boundary { layer, datatype, points, properties } => {
+                self.empty(record::BOUNDARY)?;
+                self.int16(record::LAYER, &[*layer as i16])?;
+                self.int16(record::DATATYPE, &[*datatype as i16])?;
+                self.xy(points)?;
+                self.end_element(properties)
+            }
+            GdsElement::Rect { layer, datatype, rect, properties } => {
+                self.empty(record::BOUNDARY)?;
+                self.int16(record::LAYER, &[*layer as i16])?;
+                self.int16(record::DATATYPE, &[*datatype as i16])?;
+                self.xy(&rect.points())?;
+                self.end_element(properties)
+            }
+            GdsElement::Path {
+                layer,
+                datatype,
+                pathtype,
+                width,
+                begin_extension,
+                end_extension,
+                points,
+                properties,
+            } => {
+                self.empty(record::PATH)?;
+                self.int16(record::LAYER, &[*layer as i16])?;
+                self.int16(record::DATATYPE, &[*datatype as i16])?;
+                if *pathtype != 0 {
+                    self.int16(record::PATHTYPE, &[*pathtype])?;
+                }
+                self.int32(record::WIDTH, &[*width])?;
+                if *pathtype == 4 {
+                    self.int32(record::BGNEXTN, &[*begin_extension])?;
+                    self.int32(record::ENDEXTN, &[*end_extension])?;
+                }
+                self.xy(points)?;
+                self.end_element(properties)
+            }
+            GdsElement::Box { layer, boxtype, points, properties } => {
+                self.empty(record::BOX)?;
+                self.int16(record::LAYER, &[*layer as i16])?;
+                self.int16(record::BOXTYPE, &[*boxtype as i16])?;
+                self.xy(points)?;
+                self.end_element(properties)
+            }
+            GdsElement::Text {
+                layer,
+                texttype,
+                presentation,
+                strans,
+                position,
+                text,
+                properties,
+            } => {
+                self.empty(record::TEXT)?;
+                self.int16(record::LAYER, &[*layer as i16])?;
+                self.int16(record::TEXTTYPE, &[*texttype as i16])?;
+                if *presentation != 0 {
+                    let flags = presentation.to_be_bytes();
+                    self.record(record::PRESENTATION, record::BIT_ARRAY, &flags)?;
+                }
+                self.strans(strans)?;
+                self.xy(&[*position])?;
+                self.string(record::STRING, text)?;
+                self.end_element(properties)
+            }
+            GdsElement::Node { layer, nodetype, points, properties } => {
+                self.empty(record::NODE)?;
+                self.int16(record::LAYER, &[*layer as i16])?;
+                self.int16(record::NODETYPE, &[*nodetype as i16])?;
+                self.xy(points)?;
+                self.end_element(properties)
+            }
+        }
+    }
+
+    fn reference(&mut self, reference: &CellReference) -> Result<(), GdsError> {
+        match &reference.array {
+            None => {
+                self.empty(record::SREF)?;
+                self.string(record::SNAME, &reference.cell)?;
+                self.strans(&reference.strans)?;
+                self.xy(&[reference.origin])?;
+            }
+            Some(array) => {
+                self.empty(record::AREF)?;
+                self.string(record::SNAME, &reference.cell)?;
+                self.strans(&reference.strans)?;
+                self.int16(record::COLROW, &[array.columns as i16, array.rows as i16])?;
+                self.xy(&[reference.origin, array.column_point, array.row_point])?;
+            }
+        }
+        self.end_element(&reference.properties)
+    }
+}
+
+// Encode a library as a GDSII stream. Within each cell the elements are written
+// before the references.
+pub fn write_library(library: &GdsLibrary) -> Result<Vec<u8>, GdsError> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let date = civil_time(now);
+    let dates: Vec<i16> = date.iter().chain(&date).copied().collect();
+
+    let mut writer = GdsWriter { bytes: Vec::new() };
+    writer.int16(record::HEADER, &[GDS_VERSION])?;
+    writer.int16(record::BGNLIB, &dates)?;
+    writer.string(record::LIBNAME, &library.name)?;
+    writer.real8(record::UNITS, &[library.units.user_unit, library.units.db_unit_meters])?;
+    for cell in &library.cells {
+        writer.int16(record::BGNSTR, &dates)?;
+        writer.string(record::STRNAME, &cell.name)?;
+        for element in &cell.elements {
+            writer.element(element)?;
+        }
+        for reference in &cell.references {
+            writer.reference(reference)?;
+        }
+        writer.empty(record::ENDSTR)?;
+    }
+    writer.empty(record::ENDLIB)?;
+    Ok(writer.bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::fixtures::GdsBuilder;
+    use crate::gds::parse_library;
+
+    #[test]
+    fn written_libraries_parse_back_unchanged() {
+        let mut gds = GdsBuilder::library("LIB");
+        gds.begin_cell("UNIT")
+            .boundary_with_properties(1, 0, &[[0, 0], [10, 0], [10, 10], [0, 0]], &[(1, "net")])
+            .path(2, 3, 1, 4, &[[0, 0], [100, 0]])
+            .box_element(3, 1, &[[0, 0], [5, 0], [5, 5], [0, 5], [0, 0]])
+            .end_cell();
+        gds.begin_cell("TOP")
+            .text(10, 0, [5, 5], "VDD")
+            .sref("UNIT", [100, 200], Some((true, 90.0, 2.0)))
+            .aref("UNIT", [0, 0], 3, 2, [300, 0], [0, 200])
+            .end_cell();
+        let library = parse_library(&gds.finish()).unwrap();
+
+        let written = write_library(&library).unwrap();
+        let mut reparsed = parse_library(&written).unwrap();
+        // Written files are stamped with the time of writing
+        assert!(reparsed.dates.modified.is_some());
+        reparsed.dates = library.dates.clone();
+        for cell in &mut reparsed.cells {
+            assert!(cell.dates.modified.is_some());
+            cell.dates = Default::default();
+        }
+        assert_eq!(reparsed, library);
+    }
+
+    #[test]
+    fn dates_are_civil_utc() {
+        assert_eq!(civil_time(0), [1970, 1, 1, 0, 0, 0]);
+        // 2024-02-29 12:34:56 UTC
+        assert_eq!(civil_time(1_709_210_096), [2024, 2, 29, 12, 34, 56]);
+    }
+}
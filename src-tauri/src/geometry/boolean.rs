@@ -0,0 +1,408 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use super::signed_area;
+
+// Boolean operation between two polygon sets A and B
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BooleanOp {
+    And,
+    Or,
+    // A minus B
+    Not,
+    Xor,
+}
+
+impl BooleanOp {
+    fn apply(self, a: bool, b: bool) -> bool {
+        match self {
+            BooleanOp::And => a && b,
+            BooleanOp::Or => a || b,
+            BooleanOp::Not => a && !b,
+            BooleanOp::Xor => a != b,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    A,
+    B,
+}
+
+// A non-horizontal input edge, stored bottom to top
+struct Edge {
+    bottom: [f64; 2],
+    top: [f64; 2],
+    // Change in winding number when crossing the edge from left to right
+    winding: i32,
+    operand: Operand,
+}
+
+impl Edge {
+    // Endpoints are returned exactly so pieces of consecutive edges meet
+    fn x_at(&self, y: f64) -> f64 {
+        if y <= self.bottom[1] {
+            self.bottom[0]
+        } else if y >= self.top[1] {
+            self.top[0]
+        } else {
+            let t = (y - self.bottom[1]) / (self.top[1] - self.bottom[1]);
+            self.bottom[0] + t * (self.top[0] - self.bottom[0])
+        }
+    }
+}
+
+// A directed boundary segment of the result, with the result on its left
+type Segment = ([f64; 2], [f64; 2]);
+
+fn push_edges(polygons: &[Vec<[f64; 2]>], operand: Operand, edges: &mut Vec<Edge>) {
+    for polygon in polygons {
+        // Every polygon counts as filled whatever its orientation
+        let orientation = if signed_area(polygon) < 0.0 { -1 } else { 1 };
+        let n = polygon.len();
+        for i in 0..n {
+            // Adding zero turns -0 into 0 so equal points compare equal bitwise
+            let p = [polygon[i][0] + 0.0, polygon[i][1] + 0.0];
+            let q = [polygon[(i + 1) % n][0] + 0.0, polygon[(i + 1) % n][1] + 0.0];
+            if p[1] == q[1] {
+                continue;
+            }
+            // In a counter-clockwise ring the left side runs downward, so
+            // crossing a downward edge enters the polygon
+            let (bottom, top, winding) =
+                if p[1] < q[1] { (p, q, -orientation) } else { (q, p, orientation) };
+            edges.push(Edge { bottom, top, winding, operand });
+        }
+    }
+}
+
+// Is the span [x0, x1] covered by `bounds`, a sorted list of (start, end) pairs
+// flattened into one slice? `cursor` carries the scan position between calls
+// with increasing spans.
+fn covered(bounds: &[f64], cursor: &mut usize, x0: f64, x1: f64) -> bool {
+    while *cursor + 1 < bounds.len() && bounds[*cursor + 1] <= x0 {
+        *cursor += 2;
+    }
+    *cursor + 1 < bounds.len() && bounds[*cursor] <= x0 && bounds[*cursor + 1] >= x1
+}
+
+// Horizontal boundary segments along `y`, where the result just below (covering
+// `below`) differs from the result just above (covering `above`)
+fn horizontal_segments(below: &[f64], above: &[f64], y: f64, segments: &mut Vec<Segment>) {
+    let mut xs: Vec<f64> = below.iter().chain(above).copied().collect();
+    xs.sort_by(f64::total_cmp);
+    xs.dedup();
+    let (mut below_cursor, mut above_cursor) = (0, 0);
+    for span in xs.windows(2) {
+        let (x0, x1) = (span[0], span[1]);
+        let in_below = covered(below, &mut below_cursor, x0, x1);
+        let in_above = covered(above, &mut above_cursor, x0, x1);
+        if in_below && !in_above {
+            // Top side of the region below runs right to left
+            segments.push(([x1, y], [x0, y]));
+        } else if in_above && !in_below {
+            segments.push(([x0, y], [x1, y]));
+        }
+    }
+}
+
+// Split the plane into horizontal slabs in which no edges cross, and classify
+// each edge piece by whether the result lies on its left or right
+fn boundary_segments(edges: &mut [Edge], op: BooleanOp) -> Vec<Segment> {
+    edges.sort_by(|e, f| e.bottom[1].total_cmp(&f.bottom[1]));
+    let mut ys: Vec<f64> = edges.iter().flat_map(|e| [e.bottom[1], e.top[1]]).collect();
+    ys.sort_by(f64::total_cmp);
+    ys.dedup();
+
+    let mut segments = Vec::new();
+    let Some(&first_y) = ys.first() else {
+        return segments;
+    };
+    // Result boundaries along the top of the previous slab
+    let mut pending: Option<(f64, Vec<f64>)> = None;
+    let mut active: Vec<usize> = Vec::new();
+    let (mut next_edge, mut next_y) = (0, 0);
+    let mut y_lo = first_y;
+    loop {
+        while next_edge < edges.len() && edges[next_edge].bottom[1] <= y_lo {
+            active.push(next_edge);
+            next_edge += 1;
+        }
+        active.retain(|&e| edges[e].top[1] > y_lo);
+        while next_y < ys.len() && ys[next_y] <= y_lo {
+            next_y += 1;
+        }
+        let Some(&vertex_y) = ys.get(next_y) else {
+            break;
+        };
+        if active.is_empty() {
+            if let Some((y, below)) = pending.take() {
+                horizontal_segments(&below, &[], y, &mut segments);
+            }
+            y_lo = vertex_y;
+            continue;
+        }
+
+        active.sort_by(|&e, &f| {
+            let (e, f) = (&edges[e], &edges[f]);
+            e.x_at(y_lo)
+                .total_cmp(&f.x_at(y_lo))
+                .then(e.x_at(vertex_y).total_cmp(&f.x_at(vertex_y)))
+        });
+        // Lower the top of the slab to the first crossing. Edges sorted at the
+        // bottom stay sorted up to the top exactly when no neighbours swap.
+        let mut y_hi = vertex_y;
+        loop {
+            let mut crossing = y_hi;
+            for pair in active.windows(2) {
+                let (e, f) = (&edges[pair[0]], &edges[pair[1]]);
+                let (e_lo, f_lo) = (e.x_at(y_lo), f.x_at(y_lo));
+                let (e_hi, f_hi) = (e.x_at(y_hi), f.x_at(y_hi));
+                if e_hi > f_hi {
+                    let t = (f_lo - e_lo) / ((e_hi - e_lo) - (f_hi - f_lo));
+                    let y = y_lo + t * (y_hi - y_lo);
+                    if y > y_lo && y < crossing {
+                        crossing = y;
+                    }
+                }
+            }
+            if crossing >= y_hi {
+                break;
+            }
+            y_hi = crossing;
+        }
+
+        let (mut winding_a, mut winding_b) = (0, 0);
+        let mut inside = false;
+        let (mut bottom_bounds, mut top_bounds) = (Vec::new(), Vec::new());
+        let mut i = 0;
+        while i < active.len() {
+            let (lo, hi) = (edges[active[i]].x_at(y_lo), edges[active[i]].x_at(y_hi));
+            // Coincident edges are crossed together so they can cancel out
+            while i < active.len()
+                && edges[active[i]].x_at(y_lo) == lo
+                && edges[active[i]].x_at(y_hi) == hi
+            {
+                let edge = &edges[active[i]];
+                match edge.operand {
+                    Operand::A => winding_a += edge.winding,
+                    Operand::B => winding_b += edge.winding,
+                }
+                i += 1;
+            }
+            let now = op.apply(winding_a != 0, winding_b != 0);
+            if now != inside {
+                let (bottom, top) = ([lo, y_lo], [hi, y_hi]);
+                // Left sides of the result run downward, right sides upward
+                segments.push(if now { (top, bottom) } else { (bottom, top) });
+                bottom_bounds.push(lo);
+                top_bounds.push(hi);
+                inside = now;
+            }
+        }
+
+        match pending.take() {
+            Some((y, below)) if y == y_lo => {
+                horizontal_segments(&below, &bottom_bounds, y_lo, &mut segments)
+            }
+            Some((y, below)) => {
+                horizontal_segments(&below, &[], y, &mut segments);
+                horizontal_segments(&[], &bottom_bounds, y_lo, &mut segments);
+            }
+            None => horizontal_segments(&[], &bottom_bounds, y_lo, &mut segments),
+        }
+        pending = Some((y_hi, top_bounds));
+        y_lo = y_hi;
+    }
+    if let Some((y, below)) = pending {
+        horizontal_segments(&below, &[], y, &mut segments);
+    }
+    segments
+}
+
+fn point_key(point: [f64; 2]) -> (u64, u64) {
+    ((point[0] + 0.0).to_bits(), (point[1] + 0.0).to_bits())
+}
+
+// Angle turned going from direction `incoming` to `outgoing`, positive to the left
+fn turn_angle(incoming: [f64; 2], outgoing: [f64; 2]) -> f64 {
+    let cross = incoming[0] * outgoing[1] - incoming[1] * outgoing[0];
+    let dot = incoming[0] * outgoing[0] + incoming[1] * outgoing[1];
+    cross.atan2(dot)
+}
+
+fn collinear(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> bool {
+    let (u, v) = ([b[0] - a[0], b[1] - a[1]], [c[0] - b[0], c[1] - b[1]]);
+    let cross = u[0] * v[1] - u[1] * v[0];
+    cross.abs() <= 1e-9 * u[0].hypot(u[1]) * v[0].hypot(v[1])
+}
+
+// Drop repeated and collinear points (including the slab splits along straight
+// edges and zero-width spikes), or the whole ring if no area is left
+fn simplify(ring: Vec<[f64; 2]>) -> Option<Vec<[f64; 2]>> {
+    let mut points: Vec<[f64; 2]> = Vec::with_capacity(ring.len());
+    for point in ring {
+        if points.last() == Some(&point) {
+            continue;
+        }
+        while points.len() >= 2 && collinear(points[points.len() - 2], points[points.len() - 1], point) {
+            points.pop();
+        }
+        points.push(point);
+    }
+    loop {
+        let n = points.len();
+        if n < 3 {
+            return None;
+        }
+        if points[n - 1] == points[0] || collinear(points[n - 2], points[n - 1], points[0]) {
+            points.pop();
+        } else if collinear(points[n - 1], points[0], points[1]) {
+            points.remove(0);
+        } else {
+            break;
+        }
+    }
+    if signed_area(&points) == 0.0 {
+        return None;
+    }
+    // Start at the lowest, then leftmost point so output doesn't depend on
+    // which segment the ring was traced from
+    let lowest = (0..points.len())
+        .min_by(|&i, &j| {
+            let (p, q) = (points[i], points[j]);
+            p[1].total_cmp(&q[1]).then(p[0].total_cmp(&q[0]))
+        })
+        .unwrap_or(0);
+    points.rotate_left(lowest);
+    Some(points)
+}
+
+// Join directed segments into closed rings. Where several rings touch at a
+// vertex, the sharpest left turn is taken so the rings stay separate.
+fn chain_rings(segments: &[Segment]) -> Vec<Vec<[f64; 2]>> {
+    let mut outgoing: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+    for (i, (start, _)) in segments.iter().enumerate() {
+        outgoing.entry(point_key(*start)).or_default().push(i);
+    }
+    let mut used = vec![false; segments.len()];
+    let mut rings = Vec::new();
+    for first in 0..segments.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+        let (start, mut at) = segments[first];
+        let mut from = start;
+        let mut ring = vec![start];
+        let mut closed = true;
+        while point_key(at) != point_key(start) {
+            ring.push(at);
+            let incoming = [at[0] - from[0], at[1] - from[1]];
+            let direction = |s: usize| {
+                let (p, q) = segments[s];
+                turn_angle(incoming, [q[0] - p[0], q[1] - p[1]])
+            };
+            let next = outgoing.get(&point_key(at)).and_then(|candidates| {
+                candidates
+                    .iter()
+                    .copied()
+                    .filter(|&s| !used[s])
+                    .max_by(|&s, &t| direction(s).total_cmp(&direction(t)))
+            });
+            let Some(next) = next else {
+                closed = false;
+                break;
+            };
+            used[next] = true;
+            from = at;
+            at = segments[next].1;
+        }
+        if let Some(mut ring) = closed.then_some(ring).and_then(simplify) {
+            ring.push(ring[0]);
+            rings.push(ring);
+        }
+    }
+    rings
+}
+
+// Compute `a op b` for two sets of polygons. Each input polygon counts as filled
+// regardless of its orientation, and overlapping polygons within a set merge.
+//
+// The result is a list of closed rings (first point repeated at the end) with
+// the result region on their left: outer boundaries run counter-clockwise and
+// holes are separate clockwise rings, so a hole's area is negative under
+// `signed_area` and summing all rings gives the result's area. Rings touching
+// at a single vertex are kept apart.
+pub fn boolean(a: &[Vec<[f64; 2]>], b: &[Vec<[f64; 2]>], op: BooleanOp) -> Vec<Vec<[f64; 2]>> {
+    let mut edges = Vec::new();
+    push_edges(a, Operand::A, &mut edges);
+    push_edges(b, Operand::B, &mut edges);
+    chain_rings(&boundary_segments(&mut edges, op))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: f64, y: f64, size: f64) -> Vec<[f64; 2]> {
+        vec![[x, y], [x + size, y], [x + size, y + size], [x, y + size], [x, y]]
+    }
+
+    fn total_area(rings: &[Vec<[f64; 2]>]) -> f64 {
+        rings.iter().map(|ring| signed_area(ring)).sum()
+    }
+
+    #[test]
+    fn overlapping_squares_for_each_op() {
+        // Two 10x10 squares overlapping in a 5x5 corner, one given clockwise
+        let a = vec![square(0.0, 0.0, 10.0)];
+        let mut b = vec![square(5.0, 5.0, 10.0)];
+        b[0].reverse();
+
+        let and = boolean(&a, &b, BooleanOp::And);
+        assert_eq!(and.len(), 1);
+        assert_eq!(and[0].len(), 5);
+        assert_eq!(total_area(&and), 25.0);
+
+        let or = boolean(&a, &b, BooleanOp::Or);
+        assert_eq!(or.len(), 1);
+        assert_eq!(or[0].len(), 9);
+        assert_eq!(total_area(&or), 175.0);
+
+        let not = boolean(&a, &b, BooleanOp::Not);
+        assert_eq!(not.len(), 1);
+        assert_eq!(total_area(&not), 75.0);
+
+        // The two L-shapes touch at (5, 5) and (10, 10) but stay separate rings
+        let xor = boolean(&a, &b, BooleanOp::Xor);
+        assert_eq!(xor.len(), 2);
+        assert_eq!(total_area(&xor), 150.0);
+    }
+
+    #[test]
+    fn holes_and_diagonal_edges() {
+        // Cutting a square out of the middle leaves an outer ring and a hole
+        let frame = boolean(&[square(0.0, 0.0, 10.0)], &[square(3.0, 3.0, 4.0)], BooleanOp::Not);
+        assert_eq!(frame.len(), 2);
+        let mut areas: Vec<f64> = frame.iter().map(|ring| signed_area(ring)).collect();
+        areas.sort_by(f64::total_cmp);
+        assert_eq!(areas, vec![-16.0, 100.0]);
+
+        // Two triangles crossing each other in a star of David
+        let up = vec![vec![[0.0, 0.0], [6.0, 0.0], [3.0, 6.0]]];
+        let down = vec![vec![[0.0, 4.0], [3.0, -2.0], [6.0, 4.0]]];
+        let and = boolean(&up, &down, BooleanOp::And);
+        assert_eq!(and.len(), 1);
+        assert_eq!(and[0].len(), 7);
+        let or = boolean(&up, &down, BooleanOp::Or);
+        assert!((total_area(&or) - (18.0 + 18.0 - total_area(&and))).abs() < 1e-9);
+
+        // Squares sharing an edge merge into a single rectangle
+        let merged = boolean(&[square(0.0, 0.0, 1.0)], &[square(1.0, 0.0, 1.0)], BooleanOp::Or);
+        assert_eq!(merged, vec![vec![[0.0, 0.0], [2.0, 0.0], [2.0, 1.0], [0.0, 1.0], [0.0, 0.0]]]);
+    }
+}
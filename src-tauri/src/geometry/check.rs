@@ -0,0 +1,107 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::hull::segments_cross;
+use super::rtree::{Rect, RTree};
+use super::signed_area;
+
+// What is wrong with a polygon that commonly breaks boolean operations and DRC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub enum PolygonIssue {
+    // Two edges cross each other
+    SelfIntersection,
+    // At least three distinct vertices and no crossing edges, but the outline
+    // encloses no area, e.g. all of them on one line
+    ZeroArea,
+    // Fewer than three distinct vertices
+    Degenerate,
+}
+
+// The vertices of `ring` without repeats: consecutive duplicates and a closing
+// point equal to the first are dropped
+fn distinct_vertices(ring: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    let mut vertices = ring.to_vec();
+    vertices.dedup();
+    while vertices.len() > 1 && vertices.first() == vertices.last() {
+        vertices.pop();
+    }
+    vertices
+}
+
+// Whether two edges of the closed `ring` cross at a point inside both. Edges
+// that only touch or run along each other, like the two sides of a keyhole cut
+// into a boundary for a hole, don't count. Candidate pairs come from an R-tree
+// over the edges' bounding boxes, so long outlines stay fast.
+fn self_intersects(ring: &[[f64; 2]]) -> bool {
+    let n = ring.len();
+    let edge = |i: usize| (ring[i], ring[(i + 1) % n]);
+    let boxes = (0..n)
+        .filter_map(|i| {
+            let (a, b) = edge(i);
+            Some((Rect::around(&[a, b])?, i))
+        })
+        .collect();
+    let tree = RTree::new(boxes);
+    (0..n).any(|i| {
+        let (a, b) = edge(i);
+        let Some(rect) = Rect::around(&[a, b]) else {
+            return false;
+        };
+        tree.query_rect(&rect).into_iter().any(|&j| {
+            let (c, d) = edge(j);
+            j > i && segments_cross(a, b, c, d)
+        })
+    })
+}
+
+// The first issue of a polygon outline, checked in the order `Degenerate`,
+// `SelfIntersection`, `ZeroArea`, or `None` for a clean one. A bow tie's lobes
+// cancel out to no area, but its crossing is the issue to report. The outline
+// may or may not repeat its first point at the end.
+pub fn polygon_issue(ring: &[[f64; 2]]) -> Option<PolygonIssue> {
+    let vertices = distinct_vertices(ring);
+    if vertices.len() < 3 {
+        Some(PolygonIssue::Degenerate)
+    } else if self_intersects(&vertices) {
+        Some(PolygonIssue::SelfIntersection)
+    } else if signed_area(&vertices) == 0.0 {
+        Some(PolygonIssue::ZeroArea)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polygons_are_checked_for_each_issue() {
+        let square = [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0], [0.0, 0.0]];
+        assert_eq!(polygon_issue(&square), None);
+        // A square with a square hole, joined to it by a keyhole cut
+        let keyhole = [
+            [0.0, 0.0],
+            [10.0, 0.0],
+            [10.0, 10.0],
+            [0.0, 10.0],
+            [0.0, 5.0],
+            [3.0, 5.0],
+            [3.0, 7.0],
+            [7.0, 7.0],
+            [7.0, 3.0],
+            [3.0, 3.0],
+            [3.0, 5.0],
+            [0.0, 5.0],
+        ];
+        assert_eq!(polygon_issue(&keyhole), None);
+
+        let bow_tie = [[0.0, 0.0], [10.0, 10.0], [10.0, 0.0], [0.0, 10.0]];
+        assert_eq!(polygon_issue(&bow_tie), Some(PolygonIssue::SelfIntersection));
+        let line = [[0.0, 0.0], [5.0, 0.0], [10.0, 0.0], [0.0, 0.0]];
+        assert_eq!(polygon_issue(&line), Some(PolygonIssue::ZeroArea));
+        let repeated = [[1.0, 1.0], [1.0, 1.0], [4.0, 2.0], [1.0, 1.0]];
+        assert_eq!(polygon_issue(&repeated), Some(PolygonIssue::Degenerate));
+        assert_eq!(polygon_issue(&[]), Some(PolygonIssue::Degenerate));
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::{BinaryHeap, HashSet};
+
+fn cross(o: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64 {
+    (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+}
+
+fn distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+    (b[0] - a[0]).hypot(b[1] - a[1])
+}
+
+// Distance from `point` to the segment from `a` to `b`
+fn segment_distance(point: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64 {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let length_squared = dx * dx + dy * dy;
+    if length_squared == 0.0 {
+        return distance(point, a);
+    }
+    let t = (((point[0] - a[0]) * dx + (point[1] - a[1]) * dy) / length_squared).clamp(0.0, 1.0);
+    distance(point, [a[0] + t * dx, a[1] + t * dy])
+}
+
+// Whether `point` projects onto the segment from `a` to `b` strictly between
+// its ends
+fn beside(point: [f64; 2], a: [f64; 2], b: [f64; 2]) -> bool {
+    let along = (point[0] - a[0]) * (b[0] - a[0]) + (point[1] - a[1]) * (b[1] - a[1]);
+    0.0 < along && along < (b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)
+}
+
+// Whether the segments `a`-`b` and `c`-`d` cross at a point inside both.
+// Touching at an end point doesn't count.
+pub fn segments_cross(a: [f64; 2], b: [f64; 2], c: [f64; 2], d: [f64; 2]) -> bool {
+    let sides = |p, q, r, s| cross(p, q, r) * cross(p, q, s) < 0.0;
+    sides(a, b, c, d) && sides(c, d, a, b)
+}
+
+// One chain of a monotone chain hull: the points turning counterclockwise,
+// from the first point to the last
+fn hull_chain<'a>(points: impl Iterator<Item = &'a [f64; 2]>) -> Vec<[f64; 2]> {
+    let mut chain: Vec<[f64; 2]> = Vec::new();
+    for &point in points {
+        while let [.., a, b] = chain[..] {
+            if cross(a, b, point) > 0.0 {
+                break;
+            }
+            chain.pop();
+        }
+        chain.push(point);
+    }
+    chain
+}
+
+// Convex hull of `points`, counterclockwise from the leftmost (then lowest)
+// point, by Andrew's monotone chain. Collinear points are dropped, so a set of
+// points on one line gives its two ends, and a single distinct point gives one.
+pub fn convex_hull(points: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a[0].total_cmp(&b[0]).then(a[1].total_cmp(&b[1])));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    // Each chain ends where the other starts
+    let mut hull = hull_chain(sorted.iter());
+    hull.pop();
+    let mut upper = hull_chain(sorted.iter().rev());
+    upper.pop();
+    hull.extend(upper);
+    hull
+}
+
+// Whether `point` lies strictly inside the counterclockwise triangle `a`, `b`,
+// `c`
+fn in_triangle(point: [f64; 2], a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> bool {
+    cross(a, b, point) > 0.0 && cross(b, c, point) > 0.0 && cross(c, a, point) > 0.0
+}
+
+// Concave hull of `points` by edge digging (Park and Oh, 2012), as concaveman
+// does. Starting from the convex hull, longest edge first, an edge is replaced
+// by two edges through the nearest point not yet on the hull when the edge is
+// more than `concavity` times longer than that point's distance to the nearer
+// end of the edge. A point is only taken if the new edges cross no other edge,
+// it isn't on another edge already, and no point is left outside, so the
+// result stays a simple ring around every point. Smaller values follow the
+// points more closely; large ones give the convex hull. The ring is
+// counterclockwise, without a closing point. The work grows with the number of
+// points times the hull length, so large sets should be thinned first.
+pub fn concave_hull(points: &[[f64; 2]], concavity: f64) -> Vec<[f64; 2]> {
+    let hull = convex_hull(points);
+    if hull.len() < 3 {
+        return hull;
+    }
+    let on_hull: HashSet<[u64; 2]> =
+        hull.iter().map(|point| [point[0].to_bits(), point[1].to_bits()]).collect();
+    let mut inner: Vec<[f64; 2]> = points
+        .iter()
+        .filter(|point| !on_hull.contains(&[point[0].to_bits(), point[1].to_bits()]))
+        .copied()
+        .collect();
+    inner.sort_by(|a, b| a[0].total_cmp(&b[0]).then(a[1].total_cmp(&b[1])));
+    inner.dedup();
+
+    // The ring is a linked list over `nodes`: the convex hull first, which
+    // always stays on it, then the inner points as they are taken
+    let corners = hull.len();
+    let nodes: Vec<[f64; 2]> = hull.into_iter().chain(inner).collect();
+    let mut next: Vec<usize> = (0..nodes.len()).map(|i| (i + 1) % corners).collect();
+    let mut taken = vec![false; nodes.len()];
+    let free = |taken: &[bool]| (corners..nodes.len()).filter(|&i| !taken[i]).collect::<Vec<_>>();
+    // Edges from node `a` to node `b`, keyed by length. Lengths aren't
+    // negative, so their bit patterns sort like the lengths themselves.
+    let edge = |a: usize, b: usize| (distance(nodes[a], nodes[b]).to_bits(), a, b);
+    let mut pending: BinaryHeap<(u64, usize, usize)> =
+        (0..corners).map(|a| edge(a, next[a])).collect();
+    while let Some((_, a, b)) = pending.pop() {
+        let (start, end) = (nodes[a], nodes[b]);
+        let candidates = free(&taken);
+        if candidates.is_empty() {
+            break;
+        }
+        // A point in line with the edge but beyond an end would fold the ring
+        // back on itself
+        let in_reach = candidates.iter().copied().filter(|&i| {
+            cross(start, end, nodes[i]) != 0.0 || beside(nodes[i], start, end)
+        });
+        let nearest = in_reach.min_by(|&i, &j| {
+            let distance_to = |k: usize| segment_distance(nodes[k], start, end);
+            distance_to(i).total_cmp(&distance_to(j))
+        });
+        let Some(point) = nearest else {
+            continue;
+        };
+        let corner = nodes[point];
+        let reach = distance(corner, start).min(distance(corner, end));
+        if reach == 0.0 || distance(start, end) / reach <= concavity {
+            continue;
+        }
+        let leaves_a_point_out = candidates
+            .iter()
+            .any(|&other| other != point && in_triangle(nodes[other], start, corner, end));
+        let (mut crosses, mut on_ring) = (false, false);
+        let mut node = 0;
+        loop {
+            let following = next[node];
+            if node != a {
+                let (c, d) = (nodes[node], nodes[following]);
+                crosses |= segments_cross(start, corner, c, d) || segments_cross(corner, end, c, d);
+                on_ring |= segment_distance(corner, c, d) == 0.0;
+            }
+            node = following;
+            if node == 0 {
+                break;
+            }
+        }
+        if leaves_a_point_out || crosses || on_ring {
+            continue;
+        }
+        next[point] = b;
+        next[a] = point;
+        taken[point] = true;
+        pending.push(edge(a, point));
+        pending.push(edge(point, b));
+    }
+
+    let mut ring = vec![nodes[0]];
+    let mut node = next[0];
+    while node != 0 {
+        ring.push(nodes[node]);
+        node = next[node];
+    }
+    ring
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::signed_area;
+
+    #[test]
+    fn concave_hulls_follow_a_notch_the_convex_hull_spans() {
+        // A U shape: a 30 x 30 square with a 10 wide notch cut from the top
+        let mut points = Vec::new();
+        for x in 0..=30 {
+            for y in 0..=30 {
+                if !(10 < x && x < 20 && y > 5) {
+                    points.push([x as f64, y as f64]);
+                }
+            }
+        }
+        let convex = convex_hull(&points);
+        assert_eq!(convex, [[0.0, 0.0], [30.0, 0.0], [30.0, 30.0], [0.0, 30.0]]);
+
+        let concave = concave_hull(&points, 1.0);
+        let area = signed_area(&concave);
+        assert!(area > 0.0);
+        // The notch is mostly cut out, unlike the convex hull's 900
+        assert!(area < 900.0 - 100.0, "{}", area);
+        assert!(concave.iter().all(|point| points.contains(point)));
+        // A large concavity keeps the convex hull
+        assert_eq!(concave_hull(&points, 1e9), convex);
+
+        assert_eq!(convex_hull(&[[1.0, 1.0], [2.0, 2.0], [3.0, 3.0]]), [[1.0, 1.0], [3.0, 3.0]]);
+        assert!(concave_hull(&[], 1.0).is_empty());
+    }
+}
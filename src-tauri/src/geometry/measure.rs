@@ -0,0 +1,195 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::hull::convex_hull;
+use super::{perimeter, signed_area};
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+pub struct PolygonMeasurement {
+    // Positive for counter-clockwise points
+    pub area: f64,
+    pub perimeter: f64,
+}
+
+// Smallest rectangle around a set of points at any angle. `angle` is in degrees
+// counterclockwise from the X axis to the `width` side, from 0 up to 180.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+pub struct OrientedBox {
+    pub center: [f64; 2],
+    pub width: f64,
+    pub height: f64,
+    pub angle: f64,
+}
+
+pub fn distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+    (b[0] - a[0]).hypot(b[1] - a[1])
+}
+
+// Signed area and perimeter of a polygon. With fewer than three points there is
+// no area, and the perimeter is the length of the open line through the points.
+pub fn measure(points: &[[f64; 2]]) -> PolygonMeasurement {
+    if points.len() < 3 {
+        let length = points.windows(2).map(|pair| distance(pair[0], pair[1])).sum();
+        return PolygonMeasurement { area: 0.0, perimeter: length };
+    }
+    PolygonMeasurement { area: signed_area(points), perimeter: perimeter(points) }
+}
+
+// Minimum-area oriented bounding box of `points` by rotating calipers: the
+// best box has a side along one of the convex hull's edges, so each edge is
+// tried in turn. Points on one line give a box of zero height along it.
+pub fn oriented_bbox(points: &[[f64; 2]]) -> Option<OrientedBox> {
+    let hull = convex_hull(points);
+    let first = *hull.first()?;
+    let mut best: Option<(f64, OrientedBox)> = None;
+    for i in 0..hull.len() {
+        let (a, b) = (hull[i], hull[(i + 1) % hull.len()]);
+        let length = distance(a, b);
+        if length == 0.0 {
+            continue;
+        }
+        let u = [(b[0] - a[0]) / length, (b[1] - a[1]) / length];
+        let v = [-u[1], u[0]];
+        let (mut u_range, mut v_range) = ([f64::MAX, f64::MIN], [f64::MAX, f64::MIN]);
+        for point in &hull {
+            let along = point[0] * u[0] + point[1] * u[1];
+            let across = point[0] * v[0] + point[1] * v[1];
+            u_range = [u_range[0].min(along), u_range[1].max(along)];
+            v_range = [v_range[0].min(across), v_range[1].max(across)];
+        }
+        let (width, height) = (u_range[1] - u_range[0], v_range[1] - v_range[0]);
+        if best.as_ref().is_some_and(|(area, _)| *area <= width * height) {
+            continue;
+        }
+        let (mid_u, mid_v) = ((u_range[0] + u_range[1]) / 2.0, (v_range[0] + v_range[1]) / 2.0);
+        let center = [mid_u * u[0] + mid_v * v[0], mid_u * u[1] + mid_v * v[1]];
+        let angle = u[1].atan2(u[0]).to_degrees().rem_euclid(180.0);
+        best = Some((width * height, OrientedBox { center, width, height, angle }));
+    }
+    Some(best.map_or(
+        OrientedBox { center: first, width: 0.0, height: 0.0, angle: 0.0 },
+        |(_, found)| found,
+    ))
+}
+
+// Round `value` to the nearest multiple of `db_unit`. When the grid is an exact
+// fraction of one (0.001, 0.005, ...) the result is computed by division, so it
+// prints as written, e.g. 2 rather than 1.9999999999999998.
+pub fn snap_to_grid(value: f64, db_unit: f64) -> f64 {
+    let steps = (value / db_unit).round();
+    let per_unit = 1.0 / db_unit;
+    if (per_unit - per_unit.round()).abs() <= per_unit * 1e-9 {
+        steps / per_unit.round()
+    } else {
+        steps * db_unit
+    }
+}
+
+fn validate_grid(grid: Option<f64>) -> Result<Option<f64>, AppError> {
+    match grid {
+        Some(grid) if !(grid > 0.0 && grid.is_finite()) => Err(AppError::invalid_argument(
+            format!("Grid must be a positive number, got {}", grid),
+        )),
+        grid => Ok(grid),
+    }
+}
+
+// Command to measure a polygon given in real units (e.g. µm). With `grid`, the
+// perimeter is rounded to the grid and the area to half a grid cell, the
+// resolution of areas of grid-aligned polygons.
+#[tauri::command]
+pub async fn measure_polygon(
+    points: Vec<[f64; 2]>,
+    grid: Option<f64>,
+) -> Result<PolygonMeasurement, AppError> {
+    let measurement = measure(&points);
+    Ok(match validate_grid(grid)? {
+        Some(grid) => PolygonMeasurement {
+            area: snap_to_grid(measurement.area, grid * grid / 2.0),
+            perimeter: snap_to_grid(measurement.perimeter, grid),
+        },
+        None => measurement,
+    })
+}
+
+// Command to measure the distance between two points, optionally rounded to
+// `grid`
+#[tauri::command]
+pub async fn measure_distance(
+    a: [f64; 2],
+    b: [f64; 2],
+    grid: Option<f64>,
+) -> Result<f64, AppError> {
+    let length = distance(a, b);
+    Ok(validate_grid(grid)?.map_or(length, |grid| snap_to_grid(length, grid)))
+}
+
+// Command to get the smallest rotated rectangle around a selection, e.g. for
+// odd-angle shapes whose axis-aligned box overstates their size
+#[tauri::command]
+pub async fn min_bounding_box(points: Vec<[f64; 2]>) -> Result<OrientedBox, AppError> {
+    if points.iter().flatten().any(|value| !value.is_finite()) {
+        return Err(AppError::invalid_argument("Points must have finite coordinates"));
+    }
+    oriented_bbox(&points)
+        .ok_or_else(|| AppError::invalid_argument("At least one point is needed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_polygons_and_degenerate_input() {
+        // A closing point repeating the first adds nothing
+        let square = [[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0], [0.0, 0.0]];
+        assert_eq!(measure(&square), PolygonMeasurement { area: 4.0, perimeter: 8.0 });
+        let clockwise: Vec<[f64; 2]> = square.iter().rev().copied().collect();
+        assert_eq!(measure(&clockwise).area, -4.0);
+
+        assert_eq!(measure(&[[0.0, 0.0], [3.0, 4.0]]), PolygonMeasurement { area: 0.0, perimeter: 5.0 });
+        assert_eq!(measure(&[[1.0, 1.0]]), PolygonMeasurement { area: 0.0, perimeter: 0.0 });
+        assert_eq!(distance([1.0, 1.0], [4.0, 5.0]), 5.0);
+    }
+
+    #[test]
+    fn values_snap_to_the_grid() {
+        // 0.1 + 0.2 style noise disappears
+        assert_eq!(snap_to_grid(1.9999999, 0.001), 2.0);
+        assert_eq!(snap_to_grid(0.1 + 0.2, 0.001), 0.3);
+        assert_eq!(snap_to_grid(1.2345, 0.005), 1.235);
+        assert_eq!(snap_to_grid(-0.0004, 0.001), 0.0);
+        assert_eq!(snap_to_grid(7.4, 2.5), 7.5);
+        assert!(validate_grid(Some(0.0)).is_err());
+        assert_eq!(validate_grid(None), Ok(None));
+    }
+
+    #[test]
+    fn oriented_boxes_follow_the_shape() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        // A 4 x 1 bar rotated by 30 degrees about (10, 5), plus a point inside
+        let (sin, cos) = 30f64.to_radians().sin_cos();
+        let corner = |x: f64, y: f64| [10.0 + x * cos - y * sin, 5.0 + x * sin + y * cos];
+        let bar = [
+            corner(-2.0, -0.5),
+            corner(2.0, -0.5),
+            corner(2.0, 0.5),
+            corner(-2.0, 0.5),
+            [10.0, 5.0],
+        ];
+        let found = oriented_bbox(&bar).unwrap();
+        assert!(close(found.width, 4.0) && close(found.height, 1.0), "{:?}", found);
+        assert!(close(found.angle, 30.0));
+        assert!(close(found.center[0], 10.0) && close(found.center[1], 5.0));
+
+        // Collinear points give a zero-height box along their line
+        let line = oriented_bbox(&[[0.0, 0.0], [2.0, 2.0], [1.0, 1.0], [-1.0, -1.0]]).unwrap();
+        assert!(close(line.width, 3.0 * 2f64.sqrt()) && line.height == 0.0);
+        assert!(close(line.angle, 45.0) && close(line.center[0], 0.5));
+
+        let point = OrientedBox { center: [1.0, 2.0], width: 0.0, height: 0.0, angle: 0.0 };
+        assert_eq!(oriented_bbox(&[[1.0, 2.0], [1.0, 2.0]]), Some(point));
+        assert_eq!(oriented_bbox(&[]), None);
+    }
+}
@@ -0,0 +1,93 @@
+//! Planar geometry on flattened polygons, independent of the source file format
+
+pub mod boolean;
+pub mod check;
+pub mod hull;
+pub mod measure;
+pub mod path;
+pub mod rtree;
+pub mod section;
+pub mod simplify;
+
+// Signed area of a ring by the shoelace formula: positive for counter-clockwise
+// rings. The ring may or may not repeat its first point at the end.
+pub fn signed_area(ring: &[[f64; 2]]) -> f64 {
+    let n = ring.len();
+    if n < 3 {
+        return 0.0;
+    }
+    let mut twice_area = 0.0;
+    for i in 0..n {
+        let [x0, y0] = ring[i];
+        let [x1, y1] = ring[(i + 1) % n];
+        twice_area += x0 * y1 - x1 * y0;
+    }
+    twice_area / 2.0
+}
+
+// Length of a closed ring, including the edge back to the first point
+pub fn perimeter(ring: &[[f64; 2]]) -> f64 {
+    let n = ring.len();
+    if n < 2 {
+        return 0.0;
+    }
+    (0..n)
+        .map(|i| {
+            let [x0, y0] = ring[i];
+            let [x1, y1] = ring[(i + 1) % n];
+            (x1 - x0).hypot(y1 - y0)
+        })
+        .sum()
+}
+
+// Whether `point` lies inside a ring by the even-odd rule. Points exactly on an
+// edge may go either way.
+pub fn contains_point(ring: &[[f64; 2]], point: [f64; 2]) -> bool {
+    let n = ring.len();
+    let mut inside = false;
+    for i in 0..n {
+        let [x0, y0] = ring[i];
+        let [x1, y1] = ring[(i + 1) % n];
+        if (y0 > point[1]) != (y1 > point[1]) {
+            let x = x0 + (point[1] - y0) * (x1 - x0) / (y1 - y0);
+            if point[0] < x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+// Centroid of the area inside `polygons`, each weighted by its area whatever
+// its orientation, so overlapping polygons count twice. Moments are taken about
+// the first point to keep precision far from the origin. If no polygon encloses
+// any area, the center of the points' bounding box is used instead; `None` if
+// there are no points at all.
+pub fn centroid(polygons: &[Vec<[f64; 2]>]) -> Option<[f64; 2]> {
+    let origin = *polygons.iter().flatten().next()?;
+    let (mut area, mut moment) = (0.0, [0.0, 0.0]);
+    for ring in polygons {
+        let n = ring.len();
+        let (mut twice_area, mut sum) = (0.0, [0.0, 0.0]);
+        for i in 0..n {
+            let [x0, y0] = [ring[i][0] - origin[0], ring[i][1] - origin[1]];
+            let [x1, y1] = [ring[(i + 1) % n][0] - origin[0], ring[(i + 1) % n][1] - origin[1]];
+            let cross = x0 * y1 - x1 * y0;
+            twice_area += cross;
+            sum[0] += (x0 + x1) * cross;
+            sum[1] += (y0 + y1) * cross;
+        }
+        // Each polygon's own centroid is sum / (3 * twice_area); weighting it by
+        // |twice_area| / 2 flips the sign of a clockwise ring's moment
+        let sign = twice_area.signum();
+        area += twice_area.abs() / 2.0;
+        moment[0] += sign * sum[0] / 6.0;
+        moment[1] += sign * sum[1] / 6.0;
+    }
+    if area > 0.0 {
+        return Some([origin[0] + moment[0] / area, origin[1] + moment[1] / area]);
+    }
+    let points: Vec<[f64; 2]> = polygons.iter().flatten().copied().collect();
+    let extent = rtree::Rect::around(&points)?;
+    Some([(extent.min[0] + extent.max[0]) / 2.0, (extent.min[1] + extent.max[1]) / 2.0])
+}
@@ -0,0 +1,177 @@
+use std::f64::consts::PI;
+
+// Segments approximating each semicircular end of a round-ended path
+const ROUND_CAP_SEGMENTS: usize = 16;
+
+// Joins whose miter would reach further than this many half-widths from the
+// centerline are beveled instead, so sharp turns don't grow long spikes
+const MITER_LIMIT: f64 = 4.0;
+
+fn unit(from: [f64; 2], to: [f64; 2]) -> [f64; 2] {
+    let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+    let length = dx.hypot(dy);
+    [dx / length, dy / length]
+}
+
+// Normal pointing to the left of direction `d`
+fn left_normal(d: [f64; 2]) -> [f64; 2] {
+    [-d[1], d[0]]
+}
+
+fn offset(point: [f64; 2], direction: [f64; 2], distance: f64) -> [f64; 2] {
+    [point[0] + direction[0] * distance, point[1] + direction[1] * distance]
+}
+
+// Points strictly between the start and end of a half-turn of radius `radius`
+// around `center`, starting at `start_angle` and turning clockwise
+fn half_circle(center: [f64; 2], radius: f64, start_angle: f64) -> impl Iterator<Item = [f64; 2]> {
+    (1..ROUND_CAP_SEGMENTS).map(move |k| {
+        let angle = start_angle - PI * k as f64 / ROUND_CAP_SEGMENTS as f64;
+        [center[0] + radius * angle.cos(), center[1] + radius * angle.sin()]
+    })
+}
+
+// Filled outline of a GDSII path as a closed counter-clockwise ring (first point
+// repeated at the end). `pathtype` selects the ends: 0 flush, 1 round (a
+// half-circle of the half-width), 2 square (extended by the half-width), 4
+// extended by `begin_extension`/`end_extension`; other values are treated as
+// flush. Negative widths count by their magnitude. Joins are mitered up to
+// `MITER_LIMIT`. Returns an empty ring for zero-width paths and for single-point
+// paths that have no area.
+pub fn path_outline(
+    points: &[[f64; 2]],
+    width: f64,
+    pathtype: i16,
+    begin_extension: f64,
+    end_extension: f64,
+) -> Vec<[f64; 2]> {
+    let half = width.abs() / 2.0;
+    let mut centerline: Vec<[f64; 2]> = Vec::with_capacity(points.len());
+    for point in points {
+        if centerline.last() != Some(point) {
+            centerline.push(*point);
+        }
+    }
+    if half == 0.0 || centerline.is_empty() {
+        return Vec::new();
+    }
+
+    if centerline.len() == 1 {
+        let [x, y] = centerline[0];
+        let mut ring: Vec<[f64; 2]> = match pathtype {
+            1 => std::iter::once([x + half, y])
+                .chain(half_circle([x, y], half, 0.0))
+                .chain(std::iter::once([x - half, y]))
+                .chain(half_circle([x, y], half, PI))
+                .collect(),
+            2 => vec![
+                [x - half, y - half],
+                [x - half, y + half],
+                [x + half, y + half],
+                [x + half, y - half],
+            ],
+            _ => return Vec::new(),
+        };
+        ring.reverse();
+        ring.push(ring[0]);
+        return ring;
+    }
+
+    let (begin, end) = match pathtype {
+        2 => (half, half),
+        4 => (begin_extension, end_extension),
+        _ => (0.0, 0.0),
+    };
+    let n = centerline.len();
+    let directions: Vec<[f64; 2]> =
+        centerline.windows(2).map(|pair| unit(pair[0], pair[1])).collect();
+    centerline[0] = offset(centerline[0], directions[0], -begin);
+    centerline[n - 1] = offset(centerline[n - 1], directions[n - 2], end);
+
+    // Built clockwise: along the left side, around the end, back along the
+    // right side and around the start
+    let first_normal = left_normal(directions[0]);
+    let last_normal = left_normal(directions[n - 2]);
+    let mut left = vec![offset(centerline[0], first_normal, half)];
+    let mut right = vec![offset(centerline[0], first_normal, -half)];
+    for i in 1..n - 1 {
+        let (incoming, outgoing) = (left_normal(directions[i - 1]), left_normal(directions[i]));
+        let sum = [incoming[0] + outgoing[0], incoming[1] + outgoing[1]];
+        // |incoming + outgoing| is twice the cosine of half the turn angle
+        let cos_half_turn = sum[0].hypot(sum[1]) / 2.0;
+        if cos_half_turn * MITER_LIMIT >= 1.0 {
+            let miter = [sum[0] / (2.0 * cos_half_turn), sum[1] / (2.0 * cos_half_turn)];
+            let length = half / cos_half_turn;
+            left.push(offset(centerline[i], miter, length));
+            right.push(offset(centerline[i], miter, -length));
+        } else {
+            left.push(offset(centerline[i], incoming, half));
+            left.push(offset(centerline[i], outgoing, half));
+            right.push(offset(centerline[i], incoming, -half));
+            right.push(offset(centerline[i], outgoing, -half));
+        }
+    }
+    left.push(offset(centerline[n - 1], last_normal, half));
+    right.push(offset(centerline[n - 1], last_normal, -half));
+
+    let mut ring = left;
+    if pathtype == 1 {
+        ring.extend(half_circle(centerline[n - 1], half, last_normal[1].atan2(last_normal[0])));
+    }
+    ring.extend(right.into_iter().rev());
+    if pathtype == 1 {
+        ring.extend(half_circle(centerline[0], half, (-first_normal[1]).atan2(-first_normal[0])));
+    }
+    ring.reverse();
+    ring.push(ring[0]);
+    ring
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::signed_area;
+
+    const ELBOW: [[f64; 2]; 3] = [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0]];
+
+    fn touches(ring: &[[f64; 2]], point: [f64; 2]) -> bool {
+        ring.iter().any(|p| (p[0] - point[0]).abs() < 1e-9 && (p[1] - point[1]).abs() < 1e-9)
+    }
+
+    #[test]
+    fn end_types_change_the_outline_length() {
+        // An L of two 10-long legs, 2 wide: 2 * 10 * 2 area, as the miter's
+        // outer corner makes up for the inner overlap
+        let flush = path_outline(&ELBOW, 2.0, 0, 0.0, 0.0);
+        assert_eq!(flush.len(), 7);
+        assert!((signed_area(&flush) - 40.0).abs() < 1e-9);
+        assert!(touches(&flush, [11.0, -1.0]) && touches(&flush, [9.0, 1.0]));
+
+        // Square ends add a half-width square at each end
+        let square = path_outline(&ELBOW, 2.0, 2, 0.0, 0.0);
+        assert!((signed_area(&square) - 44.0).abs() < 1e-9);
+        assert!(touches(&square, [-1.0, -1.0]) && touches(&square, [11.0, 11.0]));
+
+        // Custom extensions, including a negative (shortening) one
+        let custom = path_outline(&ELBOW, 2.0, 4, 3.0, -1.0);
+        assert!((signed_area(&custom) - 44.0).abs() < 1e-9);
+        assert!(touches(&custom, [-3.0, 1.0]) && touches(&custom, [9.0, 9.0]));
+
+        // Round ends add two half-discs, approximated by polygons
+        let round = path_outline(&ELBOW, 2.0, 1, 0.0, 0.0);
+        let discs = signed_area(&round) - 40.0;
+        assert!(discs < PI && discs > PI * 0.97);
+    }
+
+    #[test]
+    fn degenerate_paths() {
+        assert!(path_outline(&ELBOW, 0.0, 0, 0.0, 0.0).is_empty());
+        assert!(path_outline(&[[5.0, 5.0], [5.0, 5.0]], 2.0, 0, 0.0, 0.0).is_empty());
+        let dot = path_outline(&[[5.0, 5.0]], -2.0, 2, 0.0, 0.0);
+        assert!((signed_area(&dot) - 4.0).abs() < 1e-9);
+
+        // Doubling back on itself bevels instead of producing a spike
+        let hairpin = path_outline(&[[0.0, 0.0], [10.0, 0.0], [0.0, 0.1]], 2.0, 0, 0.0, 0.0);
+        assert!(hairpin.iter().all(|p| p[0] <= 11.0 + MITER_LIMIT));
+    }
+}
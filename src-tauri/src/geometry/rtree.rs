@@ -0,0 +1,167 @@
+// Entries per node. Small enough that scanning a node is cheap, large enough to
+// keep the tree shallow.
+const NODE_CAPACITY: usize = 16;
+
+// Axis-aligned rectangle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub min: [f64; 2],
+    pub max: [f64; 2],
+}
+
+impl Rect {
+    // Smallest rectangle holding every point, or `None` for no points
+    pub fn around(points: &[[f64; 2]]) -> Option<Rect> {
+        let (first, rest) = points.split_first()?;
+        Some(rest.iter().fold(Rect { min: *first, max: *first }, |rect, p| Rect {
+            min: [rect.min[0].min(p[0]), rect.min[1].min(p[1])],
+            max: [rect.max[0].max(p[0]), rect.max[1].max(p[1])],
+        }))
+    }
+
+    fn union(&self, other: &Rect) -> Rect {
+        Rect {
+            min: [self.min[0].min(other.min[0]), self.min[1].min(other.min[1])],
+            max: [self.max[0].max(other.max[0]), self.max[1].max(other.max[1])],
+        }
+    }
+
+    fn center(&self) -> [f64; 2] {
+        [(self.min[0] + self.max[0]) / 2.0, (self.min[1] + self.max[1]) / 2.0]
+    }
+
+    // Rectangles sharing only an edge or corner count as intersecting
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.min[0] <= other.max[0]
+            && other.min[0] <= self.max[0]
+            && self.min[1] <= other.max[1]
+            && other.min[1] <= self.max[1]
+    }
+
+    // Points on the edge count as inside
+    pub fn contains(&self, point: [f64; 2]) -> bool {
+        (self.min[0]..=self.max[0]).contains(&point[0])
+            && (self.min[1]..=self.max[1]).contains(&point[1])
+    }
+}
+
+struct Node {
+    rect: Rect,
+    // Indices into `items` for leaves, into `nodes` otherwise
+    leaf: bool,
+    children: Vec<usize>,
+}
+
+// Static R-tree over rectangles tagged with values, bulk-loaded with the
+// sort-tile-recursive method: entries are sorted into vertical strips by x,
+// each strip by y, and consecutive runs become nodes, level by level.
+pub struct RTree<T> {
+    items: Vec<(Rect, T)>,
+    nodes: Vec<Node>,
+    root: Option<usize>,
+}
+
+// Group `entries` (rectangle, child index) into nodes, returning the entries of
+// the level above
+fn pack(mut entries: Vec<(Rect, usize)>, leaf: bool, nodes: &mut Vec<Node>) -> Vec<(Rect, usize)> {
+    let node_count = entries.len().div_ceil(NODE_CAPACITY);
+    let strip_len = NODE_CAPACITY * (node_count as f64).sqrt().ceil() as usize;
+    entries.sort_by(|a, b| a.0.center()[0].total_cmp(&b.0.center()[0]));
+
+    let mut parents = Vec::with_capacity(node_count);
+    for strip in entries.chunks_mut(strip_len) {
+        strip.sort_by(|a, b| a.0.center()[1].total_cmp(&b.0.center()[1]));
+        for group in strip.chunks(NODE_CAPACITY) {
+            let rect = group[1..].iter().fold(group[0].0, |rect, (child, _)| rect.union(child));
+            parents.push((rect, nodes.len()));
+            nodes.push(Node { rect, leaf, children: group.iter().map(|(_, i)| *i).collect() });
+        }
+    }
+    parents
+}
+
+impl<T> RTree<T> {
+    pub fn new(items: Vec<(Rect, T)>) -> Self {
+        let mut nodes = Vec::new();
+        let mut level: Vec<(Rect, usize)> =
+            items.iter().enumerate().map(|(i, (rect, _))| (*rect, i)).collect();
+        let mut leaf = true;
+        let mut root = None;
+        while !level.is_empty() {
+            level = pack(level, leaf, &mut nodes);
+            leaf = false;
+            if level.len() == 1 {
+                root = Some(level[0].1);
+                break;
+            }
+        }
+        RTree { items, nodes, root }
+    }
+
+    // Values whose rectangles contain `point`, in no particular order
+    pub fn query_point(&self, point: [f64; 2]) -> Vec<&T> {
+        self.query(|rect| rect.contains(point))
+    }
+
+    // Values whose rectangles intersect `region`, in no particular order
+    pub fn query_rect(&self, region: &Rect) -> Vec<&T> {
+        self.query(|rect| rect.intersects(region))
+    }
+
+    // Values whose rectangles pass `test`, which must also pass for any rectangle
+    // enclosing a passing one
+    fn query(&self, test: impl Fn(&Rect) -> bool) -> Vec<&T> {
+        let mut found = Vec::new();
+        let mut pending: Vec<usize> = self.root.into_iter().collect();
+        while let Some(index) = pending.pop() {
+            let node = &self.nodes[index];
+            if !test(&node.rect) {
+                continue;
+            }
+            if node.leaf {
+                found.extend(
+                    node.children
+                        .iter()
+                        .map(|&i| &self.items[i])
+                        .filter(|(rect, _)| test(rect))
+                        .map(|(_, value)| value),
+                );
+            } else {
+                pending.extend(&node.children);
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_queries_match_a_linear_scan() {
+        // A 40 x 40 grid of overlapping 3 x 3 squares, enough for three levels
+        let items: Vec<(Rect, usize)> = (0..1600)
+            .map(|i| {
+                let (x, y) = ((i % 40) as f64 * 2.0, (i / 40) as f64 * 2.0);
+                (Rect { min: [x, y], max: [x + 3.0, y + 3.0] }, i)
+            })
+            .collect();
+        let tree = RTree::new(items.clone());
+
+        for point in [[0.0, 0.0], [2.5, 2.5], [41.0, 17.9], [80.0, 80.0], [-1.0, 5.0]] {
+            let mut found: Vec<usize> = tree.query_point(point).into_iter().copied().collect();
+            found.sort();
+            let expected: Vec<usize> =
+                items.iter().filter(|(rect, _)| rect.contains(point)).map(|(_, i)| *i).collect();
+            assert_eq!(found, expected);
+        }
+        let region = Rect { min: [10.5, 10.5], max: [13.0, 11.0] };
+        let mut found: Vec<usize> = tree.query_rect(&region).into_iter().copied().collect();
+        found.sort();
+        let expected: Vec<usize> =
+            items.iter().filter(|(rect, _)| rect.intersects(&region)).map(|(_, i)| *i).collect();
+        assert_eq!(found, expected);
+        assert!(RTree::<usize>::new(Vec::new()).query_point([0.0, 0.0]).is_empty());
+    }
+}
@@ -0,0 +1,96 @@
+// Which side of the line through `a` and `b` a point is on: the cross product
+// of the line's direction with the point, relative to `a`
+fn side(a: [f64; 2], d: [f64; 2], p: [f64; 2]) -> f64 {
+    d[0] * (p[1] - a[1]) - d[1] * (p[0] - a[0])
+}
+
+// Where the line crosses a ring by the even-odd rule, as sorted parameters of
+// `a + t * (b - a)`, with points exactly on the line counted to the left of it
+// when `on_left` and to the right otherwise. Either way a vertex the ring only
+// touches gives no crossing or two, and one it passes through gives one.
+fn crossings(ring: &[[f64; 2]], a: [f64; 2], d: [f64; 2], on_left: bool) -> Vec<f64> {
+    let left = |p| if on_left { side(a, d, p) >= 0.0 } else { side(a, d, p) > 0.0 };
+    let n = ring.len();
+    let mut ts = Vec::new();
+    for i in 0..n {
+        let (p, q) = (ring[i], ring[(i + 1) % n]);
+        if left(p) == left(q) {
+            continue;
+        }
+        let e = [q[0] - p[0], q[1] - p[1]];
+        // The sides differ, so the edge isn't parallel to the line
+        let t = ((p[0] - a[0]) * e[1] - (p[1] - a[1]) * e[0]) / (d[0] * e[1] - d[1] * e[0]);
+        ts.push(t);
+    }
+    ts.sort_by(f64::total_cmp);
+    ts
+}
+
+// The parts of the segment from `a` to `b` inside a ring, as sorted, disjoint
+// `[start, end]` parameters of `a + t * (b - a)` within 0..=1. The ring is
+// filled by the even-odd rule and taken as closed: where the segment runs along
+// an edge that stretch counts as inside, whichever side the ring is on, while
+// touching a single vertex adds nothing.
+pub fn segment_intervals(ring: &[[f64; 2]], a: [f64; 2], b: [f64; 2]) -> Vec<[f64; 2]> {
+    let d = [b[0] - a[0], b[1] - a[1]];
+    if ring.len() < 3 || d == [0.0, 0.0] {
+        return Vec::new();
+    }
+    // Counting points on the line to one side and then the other is the same
+    // as nudging the line off either side of any edge it runs along, so the
+    // union of the two covers those edges whichever side the ring is on
+    let mut intervals = Vec::new();
+    for on_left in [true, false] {
+        for pair in crossings(ring, a, d, on_left).chunks_exact(2) {
+            let (start, end) = (pair[0].max(0.0), pair[1].min(1.0));
+            if start < end {
+                intervals.push([start, end]);
+            }
+        }
+    }
+    merge_intervals(intervals)
+}
+
+// Sort intervals and join those that overlap or touch
+pub fn merge_intervals(mut intervals: Vec<[f64; 2]>) -> Vec<[f64; 2]> {
+    intervals.sort_by(|x, y| x[0].total_cmp(&y[0]));
+    let mut merged: Vec<[f64; 2]> = Vec::with_capacity(intervals.len());
+    for [start, end] in intervals {
+        match merged.last_mut() {
+            Some(last) if start <= last[1] => last[1] = last[1].max(end),
+            _ => merged.push([start, end]),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: [[f64; 2]; 4] = [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]];
+
+    #[test]
+    fn segments_are_clipped_to_the_ring() {
+        assert_eq!(segment_intervals(&SQUARE, [-10.0, 5.0], [10.0, 5.0]), [[0.5, 1.0]]);
+        // A U open at the top is cut twice
+        let u = [[0.0, 0.0], [30.0, 0.0], [30.0, 20.0], [20.0, 20.0], [20.0, 10.0]];
+        let u = [&u[..], &[[10.0, 10.0], [10.0, 20.0], [0.0, 20.0]]].concat();
+        assert_eq!(
+            segment_intervals(&u, [0.0, 15.0], [30.0, 15.0]),
+            [[0.0, 1.0 / 3.0], [2.0 / 3.0, 1.0]]
+        );
+        assert!(segment_intervals(&SQUARE, [20.0, 0.0], [20.0, 10.0]).is_empty());
+    }
+
+    #[test]
+    fn grazing_edges_count_either_way_and_vertices_not_at_all() {
+        // Along the top edge, in both directions
+        assert_eq!(segment_intervals(&SQUARE, [-10.0, 10.0], [10.0, 10.0]), [[0.5, 1.0]]);
+        assert_eq!(segment_intervals(&SQUARE, [10.0, 10.0], [-10.0, 10.0]), [[0.0, 0.5]]);
+        // Through the corner only
+        assert!(segment_intervals(&SQUARE, [0.0, 20.0], [20.0, 0.0]).is_empty());
+        // Through two opposite corners
+        assert_eq!(segment_intervals(&SQUARE, [-5.0, -5.0], [15.0, 15.0]), [[0.25, 0.75]]);
+    }
+}
@@ -0,0 +1,113 @@
+use super::measure::distance;
+
+// Distance from `p` to the segment from `a` to `b`
+fn segment_distance(p: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64 {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared == 0.0 {
+        0.0
+    } else {
+        (((p[0] - a[0]) * dx + (p[1] - a[1]) * dy) / length_squared).clamp(0.0, 1.0)
+    };
+    (p[0] - a[0] - t * dx).hypot(p[1] - a[1] - t * dy)
+}
+
+// The vertex strictly between `start` and `end` farthest from their chord
+fn farthest_from_chord(points: &[[f64; 2]], start: usize, end: usize) -> Option<(usize, f64)> {
+    (start + 1..end)
+        .map(|i| (i, segment_distance(points[i], points[start], points[end])))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+// Douglas-Peucker between two kept vertices: mark every vertex that lies more
+// than `tolerance` from the simplified line. Uses an explicit stack, since
+// rings can have millions of vertices.
+fn mark_kept(points: &[[f64; 2]], start: usize, end: usize, tolerance: f64, keep: &mut [bool]) {
+    let mut spans = vec![(start, end)];
+    while let Some((start, end)) = spans.pop() {
+        if let Some((i, distance)) = farthest_from_chord(points, start, end) {
+            if distance > tolerance {
+                keep[i] = true;
+                spans.push((start, i));
+                spans.push((i, end));
+            }
+        }
+    }
+}
+
+// Simplify a closed ring with Douglas-Peucker, dropping vertices that move the
+// outline by at most `tolerance`. A ring that repeats its first point at the
+// end still does afterwards. At least three vertices are always kept, so a
+// small polygon shrinks to a triangle rather than vanishing; rings with three
+// or fewer vertices, and a tolerance of zero, leave the ring unchanged.
+pub fn simplify_ring(ring: &[[f64; 2]], tolerance: f64) -> Vec<[f64; 2]> {
+    let closed = ring.len() > 1 && ring.first() == ring.last();
+    let vertices = if closed { &ring[..ring.len() - 1] } else { ring };
+    let n = vertices.len();
+    if n <= 3 || tolerance <= 0.0 {
+        return ring.to_vec();
+    }
+
+    // Split the ring at the first vertex and the vertex farthest from it, then
+    // simplify both halves; index `n` stands for the first vertex again
+    let mut cycle = vertices.to_vec();
+    cycle.push(vertices[0]);
+    let far = (1..n)
+        .max_by(|&a, &b| distance(cycle[0], cycle[a]).total_cmp(&distance(cycle[0], cycle[b])))
+        .unwrap_or(1);
+    let mut keep = vec![false; n + 1];
+    keep[0] = true;
+    keep[far] = true;
+    mark_kept(&cycle, 0, far, tolerance, &mut keep);
+    mark_kept(&cycle, far, n, tolerance, &mut keep);
+
+    if keep[..n].iter().filter(|&&kept| kept).count() < 3 {
+        let third = [farthest_from_chord(&cycle, 0, far), farthest_from_chord(&cycle, far, n)]
+            .into_iter()
+            .flatten()
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, _)) = third {
+            keep[i] = true;
+        }
+    }
+
+    let mut simplified: Vec<[f64; 2]> =
+        (0..n).filter(|&i| keep[i]).map(|i| cycle[i]).collect();
+    if closed {
+        simplified.push(simplified[0]);
+    }
+    simplified
+}
+
+// `simplify_ring` applied to each polygon
+pub fn simplify_polygons(polys: &[Vec<[f64; 2]>], tolerance: f64) -> Vec<Vec<[f64; 2]>> {
+    polys.iter().map(|ring| simplify_ring(ring, tolerance)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jagged_edges_collapse_and_rings_stay_closed() {
+        // A 100 x 100 square whose bottom edge wobbles by 0.5
+        let mut ring: Vec<[f64; 2]> =
+            (0..100).map(|x| [x as f64, if x % 2 == 0 { 0.0 } else { 0.5 }]).collect();
+        ring.extend([[100.0, 0.0], [100.0, 100.0], [0.0, 100.0], [0.0, 0.0]]);
+
+        let simplified = simplify_ring(&ring, 1.0);
+        let square = vec![[0.0, 0.0], [100.0, 0.0], [100.0, 100.0], [0.0, 100.0], [0.0, 0.0]];
+        assert_eq!(simplified, square);
+        // Below the wobble, every vertex matters
+        assert_eq!(simplify_ring(&ring, 0.25), ring);
+    }
+
+    #[test]
+    fn small_polygons_keep_three_vertices() {
+        let square = vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0], [0.0, 0.0]];
+        let simplified = simplify_polygons(&[square], 10.0);
+        assert_eq!(simplified[0].len(), 4);
+        assert_eq!(simplified[0].first(), simplified[0].last());
+        assert!(crate::geometry::signed_area(&simplified[0]).abs() > 0.0);
+    }
+}
@@ -0,0 +1,115 @@
+use std::path::Path;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+use crate::error::AppError;
+use crate::sync::lock;
+
+#[derive(Default)]
+struct LaunchState {
+    frontend_ready: bool,
+    pending: Vec<String>,
+}
+
+// Files the OS asked us to open (command line, file associations). Until the
+// frontend reports that it listens for `open-file`, they are queued.
+#[derive(Default)]
+pub struct LaunchFiles(Mutex<LaunchState>);
+
+// A layout file the OS passed us, resolved to an absolute path, or `None` for
+// flags, unsupported extensions and paths that don't exist
+fn openable_file(argument: &Path) -> Option<String> {
+    if argument.to_string_lossy().starts_with('-') || !crate::has_layout_extension(argument) {
+        return None;
+    }
+    let path = std::fs::canonicalize(argument).ok()?;
+    path.is_file().then(|| path.to_string_lossy().to_string())
+}
+
+// The first openable file among the command-line arguments after the program
+// name
+pub fn file_from_args(args: impl IntoIterator<Item = String>) -> Option<String> {
+    args.into_iter().skip(1).find_map(|argument| openable_file(Path::new(&argument)))
+}
+
+fn emit_open_file(app: &tauri::AppHandle, path: &str) {
+    if let Err(e) = app.emit("open-file", path) {
+        log::warn!("Failed to emit open-file event: {}", e);
+    }
+}
+
+impl LaunchFiles {
+    // Emit `open-file` for `path`, or queue it until the frontend is ready
+    pub fn open(&self, app: &tauri::AppHandle, path: String) {
+        let mut state = lock(&self.0, "Launch files");
+        if state.frontend_ready {
+            emit_open_file(app, &path);
+        } else {
+            state.pending.push(path);
+        }
+    }
+
+    // Open the files the OS handed over as URLs (macOS "Open With", double-click)
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn open_urls(&self, app: &tauri::AppHandle, urls: &[tauri::Url]) {
+        for url in urls {
+            match url.to_file_path().ok().and_then(|path| openable_file(&path)) {
+                Some(path) => self.open(app, path),
+                None => log::info!("Ignoring opened URL that is not a layout file: {}", url),
+            }
+        }
+    }
+
+    fn take_pending(&self) -> Vec<String> {
+        let mut state = lock(&self.0, "Launch files");
+        state.frontend_ready = true;
+        std::mem::take(&mut state.pending)
+    }
+}
+
+// Queue the layout file given on the command line, if any
+pub fn open_from_args(app: &tauri::AppHandle) {
+    if let Some(path) = file_from_args(std::env::args()) {
+        app.state::<LaunchFiles>().open(app, path);
+    }
+}
+
+// Command for the frontend to call once it listens for `open-file`. Emits the
+// files queued since launch; later ones are emitted right away.
+#[tauri::command]
+pub async fn frontend_ready(
+    app: tauri::AppHandle,
+    launch_files: tauri::State<'_, LaunchFiles>,
+) -> Result<(), AppError> {
+    for path in launch_files.take_pending() {
+        emit_open_file(&app, &path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_existing_layout_argument_is_opened() {
+        let dir = tempfile::tempdir().unwrap();
+        let layout = dir.path().join("chip.gds");
+        let notes = dir.path().join("notes.txt");
+        std::fs::write(&layout, b"").unwrap();
+        std::fs::write(&notes, b"").unwrap();
+        let arg = |path: &Path| path.to_string_lossy().to_string();
+
+        let args = vec![
+            "gdsjam".to_string(),
+            "--verbose".to_string(),
+            arg(&notes),
+            arg(&dir.path().join("missing.gds")),
+            arg(&layout),
+        ];
+        let expected = std::fs::canonicalize(&layout).unwrap();
+        assert_eq!(file_from_args(args), Some(arg(&expected)));
+        // The program name itself is never opened
+        assert_eq!(file_from_args(vec![arg(&layout)]), None);
+    }
+}
@@ -0,0 +1,332 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+use crate::error::AppError;
+use crate::export::{layer_key_string, parse_layer_key};
+use crate::export::thumbnail::parse_hex_color;
+use crate::gds::cache::ParseCache;
+use crate::gds::layers::{collect_layers, LayerInfo};
+use crate::per_file_store::PerFileStore;
+use crate::settings::{Settings, LAYER_COLORS_KEY, LAYER_ORDER_KEY};
+
+const LAYER_STATES_NAME: &str = "layer_states.json";
+
+// How one (layer, datatype) is shown, with the name the user gave it, if any
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct LayerStyle {
+    pub visible: bool,
+    pub color: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+// Layer styles of one file, keyed by "layer/datatype" (e.g. "1/0")
+pub type LayerState = BTreeMap<String, LayerStyle>;
+
+fn layer_states(app: &tauri::AppHandle) -> std::result::Result<PerFileStore<LayerState>, AppError> {
+    PerFileStore::in_app_data(app, LAYER_STATES_NAME, "layer states")
+}
+
+// Fail with `InvalidArgument` unless every key of `state` is "layer/datatype"
+pub(crate) fn check_layer_keys(state: &LayerState) -> std::result::Result<(), AppError> {
+    match state.keys().find(|key| parse_layer_key(key).is_none()) {
+        Some(key) => Err(AppError::invalid_argument(format!(
+            "layer key {:?} is not of the form \"layer/datatype\"",
+            key
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn save_state(
+    store: &PerFileStore<LayerState>,
+    settings: &Settings,
+    path: &str,
+    state: LayerState,
+) -> std::result::Result<(), AppError> {
+    check_layer_keys(&state)?;
+    store.save(settings, path, state)
+}
+
+// A color of its own for every layer number, spreading hues by the golden
+// ratio so that neighbouring layers look clearly different
+fn hashed_layer_color(layer: u16) -> String {
+    let hue = (layer as f64 * 0.618_033_988_749_895).fract() * 6.0;
+    let (saturation, lightness) = (0.65, 0.5);
+    let chroma = (1.0 - (2.0 * lightness - 1.0_f64).abs()) * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let channel = |value: f64| ((value + lightness - chroma / 2.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", channel(r), channel(g), channel(b))
+}
+
+// The user's default colors by layer number, as stored in the settings
+fn default_colors(settings: &Settings) -> Map<String, Value> {
+    match settings.get(LAYER_COLORS_KEY) {
+        Some(Value::Object(colors)) => colors,
+        _ => Map::new(),
+    }
+}
+
+// The color a layer gets in every file without its own: the user's default
+// for the layer number, else the hashed color
+fn default_color(colors: &Map<String, Value>, layer: u16) -> String {
+    match colors.get(&layer.to_string()) {
+        Some(Value::String(color)) if parse_hex_color(color).is_some() => color.clone(),
+        _ => hashed_layer_color(layer),
+    }
+}
+
+// One layer of a file in drawing order: a layer with a higher `z_order` is
+// drawn over the ones below it, starting from 0 at the bottom
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct LayerStackEntry {
+    pub layer: u16,
+    pub datatype: u16,
+    pub z_order: u32,
+}
+
+// The user's preferred drawing order as stored in the settings, bottom layer
+// first. Entries that aren't "layer/datatype" keys are skipped.
+fn stored_order(settings: &Settings) -> Vec<(u16, u16)> {
+    match settings.get(LAYER_ORDER_KEY) {
+        Some(Value::Array(keys)) => {
+            keys.iter().filter_map(|key| key.as_str().and_then(parse_layer_key)).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+// The layers of a file from bottom to top: the ones in `order` as they appear
+// there, then the rest by layer and datatype, so layers the order doesn't know
+// of yet come out on top where they are noticed. Without an order this is
+// simply by layer number, which puts substrate and wells below the metals in
+// the usual numbering.
+fn layer_stack(layers: &[LayerInfo], order: &[(u16, u16)]) -> Vec<LayerStackEntry> {
+    let mut keys: Vec<(u16, u16)> = layers.iter().map(|info| (info.layer, info.datatype)).collect();
+    let rank = |key: &(u16, u16)| order.iter().position(|ordered| ordered == key);
+    keys.sort_by_key(|key| (rank(key).map_or((1, 0), |rank| (0, rank)), *key));
+    keys.into_iter()
+        .zip(0..)
+        .map(|((layer, datatype), z_order)| LayerStackEntry { layer, datatype, z_order })
+        .collect()
+}
+
+// Every layer visible in its default color
+fn default_state(layers: &[LayerInfo], colors: &Map<String, Value>) -> LayerState {
+    layers
+        .iter()
+        .map(|info| {
+            let color = default_color(colors, info.layer);
+            let style = LayerStyle { visible: true, color, name: None };
+            (layer_key_string(info.layer, info.datatype), style)
+        })
+        .collect()
+}
+
+// The style of each of `layers` in the file at `path`: the remembered one, else
+// the default. Remembered styles of layers not in `layers` are left out.
+pub(crate) fn file_layer_styles(
+    app: &tauri::AppHandle,
+    settings: &Settings,
+    path: &str,
+    layers: &[LayerInfo],
+) -> std::result::Result<LayerState, AppError> {
+    let saved = layer_states(app)?.load(path)?.unwrap_or_default();
+    let mut styles = default_state(layers, &default_colors(settings));
+    for (key, style) in styles.iter_mut() {
+        if let Some(saved) = saved.get(key) {
+            *style = saved.clone();
+        }
+    }
+    Ok(styles)
+}
+
+// Command to remember the layer visibility and colors for a file. Nothing is
+// remembered for a file open read-only.
+#[tauri::command]
+pub async fn save_layer_state(
+    path: String,
+    state: LayerState,
+    app: tauri::AppHandle,
+    settings: tauri::State<'_, Settings>,
+) -> std::result::Result<(), AppError> {
+    save_state(&layer_states(&app)?, &settings, &path, state)
+}
+
+// Command to get the remembered layer visibility and colors for a file. A file
+// without any gets every layer visible in its default color; `None` only if
+// nothing was saved and the file can't be parsed either.
+#[tauri::command]
+pub async fn load_layer_state(
+    path: String,
+    app: tauri::AppHandle,
+    settings: tauri::State<'_, Settings>,
+    cache: tauri::State<'_, ParseCache>,
+) -> std::result::Result<Option<LayerState>, AppError> {
+    if let Some(state) = layer_states(&app)?.load(&path)? {
+        return Ok(Some(state));
+    }
+    let Ok(library) = cache.library(&path) else {
+        return Ok(None);
+    };
+    Ok(Some(default_state(&collect_layers(&library), &default_colors(&settings))))
+}
+
+// Command to get the color a layer number gets in files without their own
+#[tauri::command]
+pub async fn get_default_layer_color(
+    layer: u16,
+    settings: tauri::State<'_, Settings>,
+) -> std::result::Result<String, AppError> {
+    Ok(default_color(&default_colors(&settings), layer))
+}
+
+// Command to set the user's default `#rrggbb` color for a layer number across
+// all files, or to go back to the built-in color with `None`
+#[tauri::command]
+pub async fn set_default_layer_color(
+    layer: u16,
+    color: Option<String>,
+    settings: tauri::State<'_, Settings>,
+) -> std::result::Result<(), AppError> {
+    let mut colors = default_colors(&settings);
+    match color {
+        Some(color) if parse_hex_color(&color).is_none() => {
+            return Err(AppError::invalid_argument(format!(
+                "Expected a color of the form #rrggbb, got {:?}",
+                color
+            )));
+        }
+        Some(color) => colors.insert(layer.to_string(), Value::String(color)),
+        None => colors.remove(&layer.to_string()),
+    };
+    settings.set(LAYER_COLORS_KEY, Value::Object(colors));
+    Ok(())
+}
+
+// Command to get the order the layers of a file are drawn in, for compositing
+// semi-transparent layers the same way every time
+#[tauri::command]
+pub async fn get_layer_stack(
+    path: String,
+    settings: tauri::State<'_, Settings>,
+    cache: tauri::State<'_, ParseCache>,
+) -> std::result::Result<Vec<LayerStackEntry>, AppError> {
+    let library = cache.library(&path)?;
+    Ok(layer_stack(&collect_layers(&library), &stored_order(&settings)))
+}
+
+// Command to set the user's drawing order across all files as "layer/datatype"
+// keys from bottom to top, or to go back to ordering by layer number with
+// `None`
+#[tauri::command]
+pub async fn set_layer_order(
+    order: Option<Vec<String>>,
+    settings: tauri::State<'_, Settings>,
+) -> std::result::Result<(), AppError> {
+    let Some(order) = order else {
+        settings.set(LAYER_ORDER_KEY, Value::Null);
+        return Ok(());
+    };
+    let mut seen = Vec::with_capacity(order.len());
+    for key in &order {
+        let Some(layer_key) = parse_layer_key(key) else {
+            return Err(AppError::invalid_argument(format!(
+                "Expected a \"layer/datatype\" key, got {:?}",
+                key
+            )));
+        };
+        if seen.contains(&layer_key) {
+            return Err(AppError::invalid_argument(format!("Layer {} is listed twice", key)));
+        }
+        seen.push(layer_key);
+    }
+    settings.set(LAYER_ORDER_KEY, Value::from(order));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(visible: bool, color: &str) -> LayerStyle {
+        LayerStyle { visible, color: color.to_string(), name: None }
+    }
+
+    #[test]
+    fn states_round_trip_and_missing_files_are_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PerFileStore::new(dir.path().join(LAYER_STATES_NAME), "layer states");
+        let settings = Settings::load(dir.path());
+        let kept = dir.path().join("kept.gds");
+        let gone = dir.path().join("gone.gds");
+        std::fs::write(&kept, b"").unwrap();
+        std::fs::write(&gone, b"").unwrap();
+        let (kept, gone) = (kept.to_string_lossy().to_string(), gone.to_string_lossy().to_string());
+
+        let state = LayerState::from([
+            ("1/0".to_string(), style(true, "#ff0000")),
+            ("2/5".to_string(), style(false, "#00ff00")),
+        ]);
+        save_state(&store, &settings, &kept, state.clone()).unwrap();
+        save_state(&store, &settings, &gone, state.clone()).unwrap();
+        std::fs::remove_file(&gone).unwrap();
+
+        assert_eq!(store.load(&kept).unwrap(), Some(state));
+        assert_eq!(store.load(&gone).unwrap(), None);
+        assert_eq!(store.read().unwrap().len(), 1);
+
+        let bad = LayerState::from([("metal1".to_string(), style(true, "#000000"))]);
+        assert!(matches!(
+            save_state(&store, &settings, &kept, bad),
+            Err(AppError::InvalidArgument { .. })
+        ));
+    }
+
+    #[test]
+    fn defaults_prefer_the_users_colors_over_hashed_ones() {
+        let colors = Map::from_iter([("1".to_string(), Value::String("#0000ff".to_string()))]);
+        let layers = [
+            LayerInfo { layer: 1, datatype: 0, element_count: 3 },
+            LayerInfo { layer: 2, datatype: 7, element_count: 1 },
+        ];
+        let state = default_state(&layers, &colors);
+        assert_eq!(state["1/0"], style(true, "#0000ff"));
+        assert_eq!(state["2/7"], style(true, &hashed_layer_color(2)));
+
+        // Hashed colors are stable, valid and differ between neighbouring layers
+        assert_eq!(hashed_layer_color(2), hashed_layer_color(2));
+        let hashed: Vec<String> = (0..16).map(hashed_layer_color).collect();
+        assert!(hashed.iter().all(|color| parse_hex_color(color).is_some()));
+        assert!(hashed.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn stored_layers_come_first_and_the_rest_by_layer_number() {
+        let layers = [(1, 0), (2, 0), (5, 1), (5, 0), (10, 0)];
+        let layers =
+            layers.map(|(layer, datatype)| LayerInfo { layer, datatype, element_count: 1 });
+        let stack = |order: &[(u16, u16)]| -> Vec<(u16, u16, u32)> {
+            layer_stack(&layers, order)
+                .iter()
+                .map(|entry| (entry.layer, entry.datatype, entry.z_order))
+                .collect()
+        };
+        assert_eq!(stack(&[]), [(1, 0, 0), (2, 0, 1), (5, 0, 2), (5, 1, 3), (10, 0, 4)]);
+        // Layers not in the file are ignored
+        assert_eq!(
+            stack(&[(10, 0), (7, 0), (1, 0)]),
+            [(10, 0, 0), (1, 0, 1), (2, 0, 2), (5, 0, 3), (5, 1, 4)]
+        );
+    }
+}
@@ -1,144 +1,364 @@
-use std::sync::{Arc, Mutex};
-use std::path::PathBuf;
-use std::time::Duration;
-use tauri::{Manager, State, Emitter};
-use notify_debouncer_full::{new_debouncer, notify::{RecursiveMode, Watcher}, DebounceEventResult, Debouncer, FileIdMap};
+use std::path::{Path, PathBuf};
+use tauri::{Emitter, Manager};
 
-// State to hold the current watched file path
-struct WatchedFile(Arc<Mutex<Option<PathBuf>>>);
+mod debounce;
+mod diagnostics;
+mod dxf;
+pub mod error;
+mod export;
+mod format;
+pub mod gds;
+mod geometry;
+mod launch;
+mod layer_state;
+mod logging;
+mod menu;
+pub mod oasis;
+mod paths;
+mod per_file_store;
+mod perf;
+mod read_only;
+mod recent_files;
+mod reveal;
+mod schema;
+mod session;
+mod settings;
+mod sync;
+mod view_state;
+pub mod warning;
+mod watcher;
+mod window_state;
 
-// State to hold the file watcher debouncer
-type DebouncerType = Debouncer<notify_debouncer_full::notify::RecommendedWatcher, FileIdMap>;
-struct FileWatcher(Arc<Mutex<Option<DebouncerType>>>);
+use error::AppError;
+use export::jobs::ExportJobs;
+use gds::cache::ParseCache;
+use gds::hit_test::HitIndexCache;
+use gds::jobs::ParseJobs;
+use gds::streaming::GeometryStreams;
+use launch::LaunchFiles;
+use recent_files::RecentFiles;
+use settings::Settings;
+use watcher::WatchedFiles;
 
-// Command to open file dialog and return the selected file path
+// Whether `path` has the extension of a layout format that can be opened,
+// ignoring case, optionally followed by `.gz`
+fn has_layout_extension(path: &Path) -> bool {
+    let is_layout = |path: &Path| {
+        path.extension().and_then(|extension| extension.to_str()).is_some_and(|extension| {
+            format::layout_extensions().any(|known| known.eq_ignore_ascii_case(extension))
+        })
+    };
+    let gzipped = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case(format::GZIP_EXTENSION));
+    if gzipped {
+        return is_layout(Path::new(path.file_stem().unwrap_or_default()));
+    }
+    is_layout(path)
+}
+
+// Emit an `open-file` event for every dropped layout file, skipping others
+fn open_dropped_files(window: &tauri::Window, paths: &[PathBuf]) {
+    for path in paths {
+        if !has_layout_extension(path) {
+            log::info!("Ignoring dropped file with unsupported extension: {}", path.display());
+            continue;
+        }
+        if let Err(e) = window.emit("open-file", path.to_string_lossy().to_string()) {
+            log::warn!("Failed to emit open-file event: {}", e);
+        }
+    }
+}
+
+// Run a blocking dialog on the blocking thread pool, so the async runtime keeps
+// serving other commands while the system dialog is open (or hung)
+async fn run_dialog<T, F>(show: F) -> std::result::Result<T, AppError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(show)
+        .await
+        .map_err(|e| AppError::io("File dialog failed", e))
+}
+
+// File type filters for the dialogs, as (name, extensions) pairs
+type DialogFilters = Vec<(String, Vec<String>)>;
+
+// Filters the open dialogs use when the caller doesn't pass any: every layout
+// format that can be opened, and compressed layouts
+fn default_open_filters() -> DialogFilters {
+    let extensions = format::layout_extensions()
+        .chain([format::GZIP_EXTENSION])
+        .map(str::to_string)
+        .collect();
+    vec![("Layout Files".to_string(), extensions)]
+}
+
+// Filters the save dialog uses when the caller doesn't pass any: one per
+// format that can be exported
+fn default_save_filters() -> DialogFilters {
+    format::FORMATS
+        .iter()
+        .filter(|entry| entry.write)
+        .map(|entry| {
+            let extensions = entry.extensions.iter().map(|extension| extension.to_string());
+            (format!("{} Files", entry.name), extensions.collect())
+        })
+        .collect()
+}
+
+fn add_filters<R: tauri::Runtime>(
+    mut dialog: tauri_plugin_dialog::FileDialogBuilder<R>,
+    filters: &DialogFilters,
+) -> tauri_plugin_dialog::FileDialogBuilder<R> {
+    for (name, extensions) in filters {
+        let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+        dialog = dialog.add_filter(name, &extensions);
+    }
+    dialog
+}
+
+// Command to open file dialog and return the selected file path. `filters`
+// replaces the layout file filter; an empty list shows all files. The dialog
+// starts in the directory of the last opened file when one is recorded.
 #[tauri::command]
-async fn open_file_dialog(app: tauri::AppHandle) -> std::result::Result<Option<String>, String> {
+async fn open_file_dialog(
+    filters: Option<DialogFilters>,
+    app: tauri::AppHandle,
+) -> std::result::Result<Option<String>, AppError> {
+    pick_file(filters.unwrap_or_else(default_open_filters), app).await
+}
+
+// Show the open dialog with `filters`, returning the chosen file or `None` on cancel
+async fn pick_file(
+    filters: DialogFilters,
+    app: tauri::AppHandle,
+) -> std::result::Result<Option<String>, AppError> {
     use tauri_plugin_dialog::DialogExt;
 
-    let file_path = app.dialog()
-        .file()
-        .add_filter("GDS Files", &["gds", "gdsii", "dxf"])
-        .blocking_pick_file();
+    let file_path = run_dialog(move || {
+        let mut dialog = add_filters(app.dialog().file(), &filters);
+        if let Some(dir) = last_directory(&app) {
+            dialog = dialog.set_directory(dir);
+        }
+        dialog.blocking_pick_file()
+    })
+    .await?;
 
-    Ok(file_path.map(|p| p.as_path().unwrap().to_string_lossy().to_string()))
+    Ok(file_path.and_then(|p| p.into_path().ok()).map(|p| p.to_string_lossy().to_string()))
 }
 
-// Command to start watching a file for changes
+// Command to open file dialog allowing several files to be selected. Takes the
+// same filters and uses the same starting directory as `open_file_dialog`, and
+// returns an empty list on cancel.
 #[tauri::command]
-async fn watch_file(
-    path: String,
+async fn open_files_dialog(
+    filters: Option<DialogFilters>,
     app: tauri::AppHandle,
-    watched_file: State<'_, WatchedFile>,
-    file_watcher: State<'_, FileWatcher>,
-) -> std::result::Result<(), String> {
-    let path_buf = PathBuf::from(&path);
-
-    // Update the watched file state
-    {
-        let mut watched = watched_file.0.lock().unwrap();
-        *watched = Some(path_buf.clone());
-    }
+) -> std::result::Result<Vec<String>, AppError> {
+    use tauri_plugin_dialog::DialogExt;
 
-    // Create a debounced file watcher (500ms debounce)
-    let app_handle = app.clone();
-    let debouncer = new_debouncer(
-        Duration::from_millis(500),
-        None,
-        move |result: DebounceEventResult| {
-            match result {
-                Ok(events) => {
-                    for event in events {
-                        if event.kind.is_modify() {
-                            // Emit event to frontend
-                            let _ = app_handle.emit("file-changed", ());
-                        }
-                    }
-                }
-                Err(errors) => {
-                    for error in errors {
-                        log::error!("File watch error: {:?}", error);
-                        // Emit error event to frontend so users have visibility
-                        let error_msg = format!("File watch error: {}", error);
-                        let _ = app_handle.emit("file-watch-error", error_msg);
-                    }
-                }
-            }
-        },
-    ).map_err(|e| format!("Failed to create file watcher: {}", e))?;
+    let filters = filters.unwrap_or_else(default_open_filters);
+    let file_paths = run_dialog(move || {
+        let mut dialog = add_filters(app.dialog().file(), &filters);
+        if let Some(dir) = last_directory(&app) {
+            dialog = dialog.set_directory(dir);
+        }
+        dialog.blocking_pick_files()
+    })
+    .await?;
 
-    // Store the debouncer before watching to ensure proper cleanup
-    {
-        let mut watcher = file_watcher.0.lock().unwrap();
-        *watcher = Some(debouncer);
-    }
+    Ok(file_paths
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|p| p.into_path().ok())
+        .map(|p| p.to_string_lossy().to_string())
+        .collect())
+}
 
-    // Watch the file
-    {
-        let mut watcher = file_watcher.0.lock().unwrap();
-        if let Some(ref mut debouncer) = *watcher {
-            debouncer
-                .watcher()
-                .watch(&path_buf, RecursiveMode::NonRecursive)
-                .map_err(|e| format!("Failed to watch file: {}", e))?;
+// Command to open a save dialog for exports and return the chosen destination.
+// `filters` are (name, extensions) pairs, by default one per exportable format.
+// The dialog starts in the directory of the last opened file when one is
+// recorded.
+#[tauri::command]
+async fn save_file_dialog(
+    default_name: Option<String>,
+    filters: Option<DialogFilters>,
+    app: tauri::AppHandle,
+) -> std::result::Result<Option<String>, AppError> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let filters = filters.unwrap_or_else(default_save_filters);
+    let file_path = run_dialog(move || {
+        let mut dialog = add_filters(app.dialog().file(), &filters);
+        if let Some(default_name) = default_name {
+            dialog = dialog.set_file_name(default_name);
         }
-    }
+        if let Some(dir) = last_directory(&app) {
+            dialog = dialog.set_directory(dir);
+        }
+        dialog.blocking_save_file()
+    })
+    .await?;
 
-    Ok(())
+    Ok(file_path.and_then(|p| p.into_path().ok()).map(|p| p.to_string_lossy().to_string()))
 }
 
-// Command to stop watching the current file
-#[tauri::command]
-async fn unwatch_file(
-    watched_file: State<'_, WatchedFile>,
-    file_watcher: State<'_, FileWatcher>,
-) -> std::result::Result<(), String> {
-    // Clear the watched file state
-    {
-        let mut watched = watched_file.0.lock().unwrap();
-        *watched = None;
+// The recorded directory of the last opened file, if it still exists
+fn read_last_dir(settings: &Settings) -> Option<PathBuf> {
+    let dir = PathBuf::from(settings.get_str(settings::LAST_DIR_KEY)?);
+    dir.is_dir().then_some(dir)
+}
+
+// Directory the file dialogs start in, or `None` for the OS default
+fn last_directory(app: &tauri::AppHandle) -> Option<PathBuf> {
+    read_last_dir(&app.state::<Settings>())
+}
+
+// The recorded last file path, returning `None` (and forgetting the record) if
+// the file it points to no longer exists
+fn read_last_file_path(settings: &Settings) -> Option<String> {
+    let recorded = settings.get_str(settings::LAST_FILE_KEY)?;
+    if recorded.is_empty() || !Path::new(&recorded).exists() {
+        settings.set(settings::LAST_FILE_KEY, serde_json::Value::Null);
+        return None;
     }
+    Some(recorded)
+}
 
-    // Drop the debouncer to stop watching
-    {
-        let mut watcher = file_watcher.0.lock().unwrap();
-        *watcher = None;
+// Answer to `ping`: the crate version and the optional features compiled in
+#[derive(Debug, Clone, PartialEq, serde::Serialize, schemars::JsonSchema)]
+struct BackendInfo {
+    version: String,
+    features: Vec<String>,
+}
+
+// Optional capabilities of this build, for the frontend to enable UI by.
+// OASIS, DXF and gzip support are always built; memory-mapping depends on the
+// `mmap` feature and the platform.
+pub(crate) fn compiled_features() -> Vec<String> {
+    let mut features = vec!["oasis", "dxf", "gzip"];
+    if cfg!(all(unix, feature = "mmap")) {
+        features.push("mmap");
     }
+    features.into_iter().map(str::to_string).collect()
+}
 
-    Ok(())
+// Command for the frontend to check that the backend is up and what it supports
+#[tauri::command]
+async fn ping() -> std::result::Result<BackendInfo, AppError> {
+    Ok(BackendInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        features: compiled_features(),
+    })
 }
 
-// Command to get the last opened file path from app data
+// Command to get the last opened file path from the settings
 #[tauri::command]
-async fn get_last_file_path(app: tauri::AppHandle) -> std::result::Result<Option<String>, String> {
-    let app_dir = app.path().app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-
-    let last_file_path = app_dir.join("last_file.txt");
-
-    if last_file_path.exists() {
-        std::fs::read_to_string(&last_file_path)
-            .map(|s| Some(s.trim().to_string()))
-            .map_err(|e| format!("Failed to read last file path: {}", e))
-    } else {
-        Ok(None)
+async fn get_last_file_path(
+    settings: tauri::State<'_, Settings>,
+) -> std::result::Result<Option<String>, AppError> {
+    Ok(read_last_file_path(&settings))
+}
+
+// Command to save the last opened file path to the settings
+#[tauri::command]
+async fn save_last_file_path(
+    path: String,
+    settings: tauri::State<'_, Settings>,
+) -> std::result::Result<(), AppError> {
+    write_last_file_path(&settings, &path);
+    Ok(())
+}
+
+// Remember `path` as the last opened file, unless it is open read-only
+fn write_last_file_path(settings: &Settings, path: &str) {
+    if read_only::is_read_only(settings, path) {
+        return;
+    }
+    settings.set(settings::LAST_FILE_KEY, serde_json::Value::String(path.to_string()));
+
+    // Remember the directory separately so it outlives the file itself
+    if let Some(dir) = Path::new(path).parent().filter(|dir| dir.is_dir()) {
+        let dir = dir.to_string_lossy().to_string();
+        settings.set(settings::LAST_DIR_KEY, serde_json::Value::String(dir));
     }
 }
 
-// Command to save the last opened file path to app data
+// Answer to `open_and_prepare`: the canonicalized path of the opened file and
+// what the frontend needs to show it
+#[derive(Debug, Clone, PartialEq, serde::Serialize, schemars::JsonSchema)]
+struct OpenResult {
+    path: String,
+    stats: gds::stats::GdsStats,
+    top_cells: Vec<String>,
+    layers: Vec<gds::layers::LayerInfo>,
+}
+
+// Command to open a layout in one round trip: show the open dialog if `path` is
+// `None`, parse the file, record it as the last and most recent file, and start
+// watching it with the default options. Returns `None` if the dialog was
+// cancelled. The file is only recorded and watched once it parsed.
 #[tauri::command]
-async fn save_last_file_path(path: String, app: tauri::AppHandle) -> std::result::Result<(), String> {
-    let app_dir = app.path().app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+async fn open_and_prepare(
+    path: Option<String>,
+    app: tauri::AppHandle,
+    cache: tauri::State<'_, ParseCache>,
+    settings: tauri::State<'_, Settings>,
+) -> std::result::Result<Option<OpenResult>, AppError> {
+    let path = match path {
+        Some(path) => path,
+        None => match pick_file(default_open_filters(), app.clone()).await? {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+    let path = paths::layout_file(&path)?;
+    let library = cache.library(&path)?;
+    let file_size = std::fs::metadata(&path).map_err(|e| AppError::for_path(&path, e))?.len();
 
-    // Create app data directory if it doesn't exist
-    std::fs::create_dir_all(&app_dir)
-        .map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    write_last_file_path(&settings, &path);
+    recent_files::remember_file(&app, &path, recent_files::DEFAULT_RECENT_FILES_LIMIT)?;
+    let path = watcher::watch_with_defaults(&path, &app)?;
+    Ok(Some(OpenResult {
+        path,
+        stats: gds::stats::library_stats(&library, file_size),
+        top_cells: gds::hierarchy::top_cells(&library),
+        layers: gds::layers::collect_layers(&library),
+    }))
+}
 
-    let last_file_path = app_dir.join("last_file.txt");
+// Command to stop every running parse job, export job and geometry stream,
+// e.g. when the user closes a file or navigates away, so no worker reports
+// against a view that is gone. Returns how many were running.
+#[tauri::command]
+async fn cancel_all_jobs(
+    parse_jobs: tauri::State<'_, ParseJobs>,
+    export_jobs: tauri::State<'_, ExportJobs>,
+    streams: tauri::State<'_, GeometryStreams>,
+) -> std::result::Result<usize, AppError> {
+    let cancelled = parse_jobs.cancel_all() + export_jobs.cancel_all() + streams.cancel_all();
+    if cancelled > 0 {
+        log::info!("Cancelled {} running jobs", cancelled);
+    }
+    Ok(cancelled)
+}
 
-    std::fs::write(&last_file_path, path)
-        .map_err(|e| format!("Failed to save last file path: {}", e))
+// Release what the app holds before the process exits: every file watch is
+// stopped, so no inotify/FSEvents handle lingers, and settings and recent files
+// changes still waiting to be written are written now
+fn shutdown(app: &tauri::AppHandle) {
+    app.state::<WatchedFiles>().stop_all();
+    if let Some(settings) = app.try_state::<Settings>() {
+        if let Err(e) = settings.flush() {
+            log::warn!("Failed to save settings on exit: {}", e);
+        }
+    }
+    if let Err(e) = app.state::<RecentFiles>().flush() {
+        log::warn!("Failed to save recent files on exit: {}", e);
+    }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -146,25 +366,226 @@ pub fn run() {
   tauri::Builder::default()
     .plugin(tauri_plugin_dialog::init())
     .plugin(tauri_plugin_fs::init())
-    .manage(WatchedFile(Arc::new(Mutex::new(None))))
-    .manage(FileWatcher(Arc::new(Mutex::new(None))))
+    .manage(WatchedFiles::default())
+    .manage(ParseJobs::default())
+    .manage(ExportJobs::default())
+    .manage(GeometryStreams::default())
+    .manage(ParseCache::default())
+    .manage(HitIndexCache::default())
+    .manage(LaunchFiles::default())
+    .manage(RecentFiles::default())
+    .menu(menu::initial_menu)
+    .on_menu_event(menu::handle_menu_event)
     .setup(|app| {
-      if cfg!(debug_assertions) {
-        app.handle().plugin(
-          tauri_plugin_log::Builder::default()
-            .level(log::LevelFilter::Info)
-            .build(),
-        )?;
+      app.handle().plugin(logging::plugin())?;
+      let settings = Settings::load(&app.path().app_data_dir()?);
+      paths::set_follow_symlinks(settings.follow_symlinks());
+      gds::flatten::set_max_hierarchy_depth(settings.max_hierarchy_depth());
+      app.manage(settings);
+      if let Some(window) = app.get_webview_window("main") {
+        if let Err(e) = window_state::restore(&window) {
+          log::warn!("Failed to restore window state: {}", e);
+        }
       }
+      launch::open_from_args(app.handle());
       Ok(())
     })
+    .on_window_event(|window, event| match event {
+      tauri::WindowEvent::CloseRequested { .. } => {
+        if let Err(e) = window_state::save(window) {
+          log::warn!("Failed to save window state: {}", e);
+        }
+      }
+      tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) => {
+        open_dropped_files(window, paths);
+      }
+      _ => {}
+    })
     .invoke_handler(tauri::generate_handler![
       open_file_dialog,
-      watch_file,
-      unwatch_file,
+      open_files_dialog,
+      save_file_dialog,
+      launch::frontend_ready,
+      logging::get_log_path,
+      diagnostics::create_diagnostic_bundle,
+      logging::log_frontend,
+      watcher::watch_file,
+      watcher::watch_files,
+      watcher::watch_directory,
+      watcher::unwatch_file,
+      watcher::get_watched_files,
+      ping,
       get_last_file_path,
       save_last_file_path,
+      open_and_prepare,
+      settings::get_setting,
+      settings::set_setting,
+      settings::set_project_root,
+      settings::resolve_in_project,
+      read_only::set_read_only,
+      read_only::get_read_only_status,
+      recent_files::add_recent_file,
+      recent_files::get_recent_files,
+      recent_files::clear_recent_files,
+      reveal::reveal_in_file_manager,
+      schema::get_api_schema,
+      layer_state::save_layer_state,
+      layer_state::load_layer_state,
+      layer_state::get_default_layer_color,
+      layer_state::set_default_layer_color,
+      layer_state::get_layer_stack,
+      layer_state::set_layer_order,
+      session::save_session,
+      session::load_session,
+      view_state::save_view_state,
+      view_state::load_view_state,
+      gds::jobs::parse_gds,
+      gds::jobs::parse_gds_batch,
+      gds::jobs::cancel_parse,
+      cancel_all_jobs,
+      gds::jobs::reload_file,
+      gds::cache::clear_parse_cache,
+      gds::changes::diff_since_reload,
+      gds::changes::get_changed_layer_buffers,
+      gds::layers::get_gds_layers,
+      gds::layers::compare_cell_layers,
+      gds::bbox::get_cell_bbox,
+      gds::flatten::get_layer_polygons,
+      gds::streaming::stream_layer_polygons,
+      gds::streaming::cancel_layer_polygons,
+      gds::rects::get_layer_rects,
+      gds::buffer::get_layer_buffer,
+      gds::instancing::get_instanced_geometry,
+      gds::hit_test::hit_test,
+      gds::hit_test::point_in_cell,
+      gds::cross_section::cross_section,
+      gds::problems::find_problem_polygons,
+      gds::duplicates::find_duplicate_polygons,
+      gds::snap::snap_point,
+      gds::interaction::layer_interaction_report,
+      gds::dump::dump_gds_records,
+      gds::validate::validate_gds,
+      gds::validate::get_gds_version,
+      gds::region::query_region,
+      gds::region::layers_in_region,
+      gds::render_plan::get_render_plan,
+      gds::tiles::get_cell_tiles,
+      gds::tiles::get_tile_geometry,
+      gds::hierarchy::get_top_cells,
+      gds::hierarchy::get_cell_graph,
+      gds::hierarchy::export_hierarchy,
+      gds::hierarchy::get_cell_summaries,
+      gds::hierarchy::find_cells,
+      gds::hierarchy::get_orphan_cells,
+      gds::hierarchy::get_empty_cells,
+      gds::hierarchy::check_references,
+      gds::aref::expand_aref,
+      gds::locate::locate_cell,
+      gds::units::get_gds_units,
+      gds::units::transform_coords,
+      gds::stats::get_gds_stats,
+      gds::stats::get_geometry_histogram,
+      gds::outline::get_cell_outline,
+      gds::path_lengths::get_path_lengths,
+      gds::offgrid::find_offgrid,
+      gds::angles::classify_geometry,
+      gds::elements::get_layer_elements,
+      gds::elements::get_element_geometry,
+      gds::freshness::check_file_freshness,
+      gds::centroid::get_cell_centroid,
+      gds::diff::diff_gds,
+      gds::boolean::boolean_layers,
+      gds::boolean::union_layer,
+      gds::boolean::get_layer_coverage,
+      gds::labels::get_labels,
+      gds::labels::get_text_strings,
+      gds::properties::get_element_properties,
+      geometry::measure::measure_polygon,
+      geometry::measure::measure_distance,
+      geometry::measure::min_bounding_box,
+      dxf::parse_dxf,
+      format::detect_format,
+      format::get_supported_formats,
+      oasis::parse_oasis,
+      export::svg::export_svg,
+      export::measurements::export_measurements,
+      export::layer_map::export_layer_map,
+      export::dxf::convert_gds_to_dxf,
+      export::gds::export_gds_subset,
+      export::gds::rename_cell,
+      export::gds::crop_to_region,
+      export::gds::save_minimized,
+      export::gds::export_cells,
+      export::gds::transform_cell,
+      export::gds::remap_layers,
+      export::thumbnail::render_thumbnail,
+      export::contact_sheet::render_contact_sheet,
+      export::jobs::cancel_export,
+      export::clipboard::copy_cell_to_clipboard,
     ])
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+    .build(tauri::generate_context!())
+    .expect("error while building tauri application")
+    .run(|app, event| match event {
+      tauri::RunEvent::Exit => shutdown(app),
+      #[cfg(any(target_os = "macos", target_os = "ios"))]
+      tauri::RunEvent::Opened { urls } => app.state::<LaunchFiles>().open_urls(app, &urls),
+      _ => {}
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_file_path_pointing_at_missing_file_is_none() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = Settings::load(dir.path());
+        let missing = dir.path().join("deleted.gds");
+        let missing = serde_json::Value::String(missing.to_string_lossy().to_string());
+        settings.set(settings::LAST_FILE_KEY, missing);
+
+        assert_eq!(read_last_file_path(&settings), None);
+        assert_eq!(settings.get(settings::LAST_FILE_KEY), None);
+    }
+
+    #[test]
+    fn legacy_last_file_path_is_trimmed() {
+        let dir = tempfile::tempdir().unwrap();
+        let layout = dir.path().join("layout.gds");
+        std::fs::write(&layout, b"").unwrap();
+        std::fs::write(dir.path().join("last_file.txt"), format!("  {}\n", layout.display()))
+            .unwrap();
+
+        assert_eq!(
+            read_last_file_path(&Settings::load(dir.path())),
+            Some(layout.to_string_lossy().to_string())
+        );
+    }
+
+    #[test]
+    fn dropped_files_are_filtered_by_extension() {
+        assert!(has_layout_extension(Path::new("/chips/top.gds")));
+        assert!(has_layout_extension(Path::new("C:\\chips\\TOP.OAS")));
+        assert!(has_layout_extension(Path::new("outline.dxf")));
+        assert!(!has_layout_extension(Path::new("notes.txt")));
+        assert!(!has_layout_extension(Path::new("gds")));
+        assert!(has_layout_extension(Path::new("/archive/top.GDS.gz")));
+        assert!(!has_layout_extension(Path::new("logs.tar.gz")));
+        assert!(!has_layout_extension(Path::new("top.gz")));
+    }
+
+    #[test]
+    fn last_dir_must_still_exist() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = Settings::load(dir.path());
+        assert_eq!(read_last_dir(&settings), None);
+
+        let recorded = |path: &Path| serde_json::Value::String(path.to_string_lossy().to_string());
+        settings.set(settings::LAST_DIR_KEY, recorded(dir.path()));
+        assert_eq!(read_last_dir(&settings), Some(dir.path().to_path_buf()));
+
+        settings.set(settings::LAST_DIR_KEY, recorded(&dir.path().join("gone")));
+        assert_eq!(read_last_dir(&settings), None);
+    }
 }
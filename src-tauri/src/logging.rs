@@ -0,0 +1,88 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::path::PathBuf;
+use tauri::plugin::TauriPlugin;
+use tauri::{Manager, Runtime};
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind};
+
+use crate::error::AppError;
+
+// Base name of the log file in the OS log dir; the plugin adds `.log`
+const LOG_FILE_NAME: &str = "gdsjam";
+
+// Size at which the log file is rotated, and how many rotated files are kept
+const MAX_LOG_FILE_BYTES: u128 = 5 * 1024 * 1024;
+const KEPT_LOG_FILES: usize = 3;
+
+// Log target used for messages forwarded by `log_frontend`
+const FRONTEND_TARGET: &str = "frontend";
+
+// Logger writing to stdout and to a rotating file in the app log dir, in debug
+// and release builds alike, so users can attach the file to bug reports
+pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+    tauri_plugin_log::Builder::default()
+        .level(log::LevelFilter::Info)
+        .clear_targets()
+        .targets([
+            Target::new(TargetKind::Stdout),
+            Target::new(TargetKind::LogDir { file_name: Some(LOG_FILE_NAME.to_string()) }),
+        ])
+        .max_file_size(MAX_LOG_FILE_BYTES)
+        .rotation_strategy(RotationStrategy::KeepSome(KEPT_LOG_FILES))
+        .build()
+}
+
+// Severity of a frontend log message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FrontendLogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<FrontendLogLevel> for log::Level {
+    fn from(level: FrontendLogLevel) -> Self {
+        match level {
+            FrontendLogLevel::Error => log::Level::Error,
+            FrontendLogLevel::Warn => log::Level::Warn,
+            FrontendLogLevel::Info => log::Level::Info,
+            FrontendLogLevel::Debug => log::Level::Debug,
+            FrontendLogLevel::Trace => log::Level::Trace,
+        }
+    }
+}
+
+// Path of the current log file
+pub(crate) fn log_file<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, AppError> {
+    let log_dir = app.path().app_log_dir()
+        .map_err(|e| AppError::io("Failed to get app log dir", e))?;
+    Ok(log_dir.join(format!("{}.log", LOG_FILE_NAME)))
+}
+
+// Command to get the path of the current log file
+#[tauri::command]
+pub async fn get_log_path(app: tauri::AppHandle) -> Result<String, AppError> {
+    Ok(log_file(&app)?.to_string_lossy().to_string())
+}
+
+// Command for the frontend to write a message into the backend log
+#[tauri::command]
+pub async fn log_frontend(level: FrontendLogLevel, message: String) -> Result<(), AppError> {
+    log::log!(target: FRONTEND_TARGET, level.into(), "{}", message);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frontend_levels_map_to_log_levels() {
+        let level: FrontendLogLevel = serde_json::from_str("\"warn\"").unwrap();
+        assert_eq!(log::Level::from(level), log::Level::Warn);
+        assert!(serde_json::from_str::<FrontendLogLevel>("\"fatal\"").is_err());
+    }
+}
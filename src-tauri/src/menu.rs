@@ -0,0 +1,108 @@
+use tauri::menu::{Menu, MenuBuilder, MenuEvent, MenuItemBuilder, SubmenuBuilder};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+use crate::recent_files;
+
+const OPEN_ID: &str = "open";
+const RELOAD_ID: &str = "reload";
+const QUIT_ID: &str = "quit";
+const NO_RECENT_ID: &str = "no-recent";
+// Recent file items are identified by this prefix followed by the file path
+const RECENT_PREFIX: &str = "open-recent:";
+
+// What clicking a menu item asks for
+#[derive(Debug, PartialEq)]
+enum MenuAction {
+    Open,
+    OpenRecent(String),
+    Reload,
+    Quit,
+}
+
+fn menu_action(id: &str) -> Option<MenuAction> {
+    match id {
+        OPEN_ID => Some(MenuAction::Open),
+        RELOAD_ID => Some(MenuAction::Reload),
+        QUIT_ID => Some(MenuAction::Quit),
+        _ => id.strip_prefix(RECENT_PREFIX).map(|path| MenuAction::OpenRecent(path.to_string())),
+    }
+}
+
+// Build the application menu, listing `recent` under File → Open Recent
+pub fn build_menu(app: &AppHandle, recent: &[String]) -> tauri::Result<Menu<Wry>> {
+    let mut open_recent = SubmenuBuilder::new(app, "Open Recent");
+    if recent.is_empty() {
+        open_recent = open_recent.item(
+            &MenuItemBuilder::with_id(NO_RECENT_ID, "No Recent Files").enabled(false).build(app)?,
+        );
+    }
+    for path in recent {
+        open_recent = open_recent.text(format!("{}{}", RECENT_PREFIX, path), path);
+    }
+
+    let file = SubmenuBuilder::new(app, "File")
+        .item(&MenuItemBuilder::with_id(OPEN_ID, "Open…").accelerator("CmdOrCtrl+O").build(app)?)
+        .item(&open_recent.build()?)
+        .separator()
+        .item(&MenuItemBuilder::with_id(RELOAD_ID, "Reload").accelerator("CmdOrCtrl+R").build(app)?)
+        .separator()
+        .item(&MenuItemBuilder::with_id(QUIT_ID, "Quit").accelerator("CmdOrCtrl+Q").build(app)?)
+        .build()?;
+    MenuBuilder::new(app).item(&file).build()
+}
+
+// Build the application menu from the stored recent files list
+pub fn initial_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let recent = recent_files::recent_files(app).unwrap_or_else(|e| {
+        log::warn!("Failed to read recent files for the menu: {}", e);
+        Vec::new()
+    });
+    build_menu(app, &recent)
+}
+
+// Replace the application menu after the recent files list changed
+pub fn refresh_recent_files(app: &AppHandle, recent: &[String]) {
+    if let Err(e) = build_menu(app, recent).and_then(|menu| app.set_menu(menu)) {
+        log::warn!("Failed to rebuild the menu: {}", e);
+    }
+}
+
+// Forward a menu click to the frontend: `menu-open` and `menu-reload` events for
+// Open and Reload, and the same `open-file` event as dropping a file for recent
+// files. Quit closes the windows so their state is saved on the way out.
+pub fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    let result = match menu_action(event.id().as_ref()) {
+        Some(MenuAction::Open) => app.emit("menu-open", ()),
+        Some(MenuAction::Reload) => app.emit("menu-reload", ()),
+        Some(MenuAction::OpenRecent(path)) => app.emit("open-file", path),
+        Some(MenuAction::Quit) => {
+            for window in app.webview_windows().values() {
+                if let Err(e) = window.close() {
+                    log::warn!("Failed to close window: {}", e);
+                }
+            }
+            Ok(())
+        }
+        None => Ok(()),
+    };
+    if let Err(e) = result {
+        log::warn!("Failed to emit menu event: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn menu_ids_map_to_actions() {
+        assert_eq!(menu_action(OPEN_ID), Some(MenuAction::Open));
+        assert_eq!(menu_action(RELOAD_ID), Some(MenuAction::Reload));
+        assert_eq!(menu_action(QUIT_ID), Some(MenuAction::Quit));
+        assert_eq!(
+            menu_action("open-recent:/chips/a:b.gds"),
+            Some(MenuAction::OpenRecent("/chips/a:b.gds".to_string()))
+        );
+        assert_eq!(menu_action(NO_RECENT_ID), None);
+    }
+}
@@ -0,0 +1,32 @@
+//! OASIS stream parsing into the same [`GdsLibrary`](crate::gds::GdsLibrary)
+//! structure the GDSII path produces.
+//!
+//! `reader` decodes the primitive value encodings, `parser` the records.
+
+pub mod parser;
+pub mod reader;
+
+use std::path::Path;
+
+use crate::error::AppError;
+use crate::gds::{GdsError, GdsLibrary};
+
+pub use parser::parse_oasis as parse_oasis_bytes;
+
+// Read and parse an OASIS file from disk, naming the library after the file
+pub fn read_oasis(path: &str) -> Result<GdsLibrary, GdsError> {
+    let bytes = std::fs::read(path)?;
+    parse_oasis_bytes(&bytes, &library_name(path))
+}
+
+// OASIS files carry no library name, so the file name stands in for it
+fn library_name(path: &str) -> String {
+    Path::new(path).file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+// Command to parse an OASIS file into its cell hierarchy
+#[tauri::command]
+pub async fn parse_oasis(path: String) -> Result<GdsLibrary, AppError> {
+    let bytes = std::fs::read(&path).map_err(|e| AppError::for_path(&path, e))?;
+    parse_oasis_bytes(&bytes, &library_name(&path)).map_err(|e| AppError::from_stream(e, "OASIS"))
+}
@@ -0,0 +1,1144 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+use super::reader::ByteReader;
+use crate::gds::{
+    ArrayParams, CellReference, GdsCell, GdsDates, GdsElement, GdsError, GdsLibrary, GdsUnits,
+    Strans,
+};
+use crate::gds::writer::GDS_VERSION;
+
+pub const MAGIC: &[u8] = b"%SEMI-OASIS\r\n";
+
+// Record IDs
+const PAD: u64 = 0;
+const START: u64 = 1;
+const END: u64 = 2;
+const CELLNAME: u64 = 3;
+const CELLNAME_REF: u64 = 4;
+const TEXTSTRING: u64 = 5;
+const TEXTSTRING_REF: u64 = 6;
+const PROPNAME: u64 = 7;
+const PROPNAME_REF: u64 = 8;
+const PROPSTRING: u64 = 9;
+const PROPSTRING_REF: u64 = 10;
+const LAYERNAME: u64 = 11;
+const LAYERNAME_TEXT: u64 = 12;
+const CELL_REF: u64 = 13;
+const CELL: u64 = 14;
+const XYABSOLUTE: u64 = 15;
+const XYRELATIVE: u64 = 16;
+const PLACEMENT: u64 = 17;
+const PLACEMENT_MAG: u64 = 18;
+const TEXT: u64 = 19;
+const RECTANGLE: u64 = 20;
+const POLYGON: u64 = 21;
+const PATH: u64 = 22;
+const TRAPEZOID: u64 = 23;
+const TRAPEZOID_A: u64 = 24;
+const TRAPEZOID_B: u64 = 25;
+const CTRAPEZOID: u64 = 26;
+const CIRCLE: u64 = 27;
+const PROPERTY: u64 = 28;
+const PROPERTY_REPEAT: u64 = 29;
+const XNAME: u64 = 30;
+const XNAME_REF: u64 = 31;
+const XELEMENT: u64 = 32;
+const XGEOMETRY: u64 = 33;
+const CBLOCK: u64 = 34;
+
+// Segments used to approximate a CIRCLE, which GDSII has no element for
+const CIRCLE_SEGMENTS: usize = 64;
+
+// A cell or text string given directly or by reference number. Name tables may
+// follow the records that use them, so references are resolved at END.
+#[derive(Debug, Clone)]
+enum Name {
+    Literal(String),
+    Ref(u64),
+}
+
+// Instance offsets of a REPETITION, kept as a lattice when it is one so arrayed
+// placements can become AREFs
+#[derive(Debug, Clone)]
+enum Repetition {
+    Grid { columns: u64, rows: u64, column_step: [i64; 2], row_step: [i64; 2] },
+    List(Vec<[i64; 2]>),
+}
+
+impl Repetition {
+    fn offsets(&self) -> Vec<[i64; 2]> {
+        match self {
+            Repetition::Grid { columns, rows, column_step, row_step } => {
+                let mut offsets = Vec::with_capacity((columns * rows) as usize);
+                for j in 0..*rows as i64 {
+                    for i in 0..*columns as i64 {
+                        offsets.push([
+                            i * column_step[0] + j * row_step[0],
+                            i * column_step[1] + j * row_step[1],
+                        ]);
+                    }
+                }
+                offsets
+            }
+            Repetition::List(offsets) => offsets.clone(),
+        }
+    }
+}
+
+// Modal variables, reset at every CELL record
+#[derive(Default)]
+struct Modal {
+    relative: bool,
+    placement_x: i64,
+    placement_y: i64,
+    placement_cell: Option<Name>,
+    geometry_x: i64,
+    geometry_y: i64,
+    text_x: i64,
+    text_y: i64,
+    text_string: Option<Name>,
+    layer: Option<u64>,
+    datatype: Option<u64>,
+    text_layer: Option<u64>,
+    texttype: Option<u64>,
+    geometry_w: Option<u64>,
+    geometry_h: Option<u64>,
+    polygon_points: Option<Vec<[i64; 2]>>,
+    path_halfwidth: Option<u64>,
+    path_points: Option<Vec<[i64; 2]>>,
+    path_start_extension: Option<i64>,
+    path_end_extension: Option<i64>,
+    circle_radius: Option<u64>,
+    repetition: Option<Repetition>,
+}
+
+// What an error expects in place of an undefined modal variable
+const MODAL_SET: &str = "a value set by an earlier record";
+
+// Value read from the record if present, otherwise the modal variable, which
+// must have been set by an earlier record
+fn modal_unsigned(
+    reader: &mut ByteReader,
+    present: bool,
+    modal: &mut Option<u64>,
+    name: &str,
+) -> Result<u64, GdsError> {
+    if present {
+        *modal = Some(reader.unsigned()?);
+    }
+    modal.ok_or_else(|| {
+        reader.invalid(MODAL_SET, format!("modal variable {} is undefined", name))
+    })
+}
+
+struct PendingText {
+    element: GdsElement,
+    text: Name,
+}
+
+struct PendingCell {
+    name: Name,
+    references: Vec<(Name, CellReference)>,
+    elements: Vec<GdsElement>,
+    texts: Vec<PendingText>,
+}
+
+struct Parser {
+    modal: Modal,
+    cell_names: HashMap<u64, String>,
+    text_strings: HashMap<u64, String>,
+    next_cell_name: u64,
+    next_text_string: u64,
+    cells: Vec<PendingCell>,
+    // Set once the END record is read
+    ended: bool,
+}
+
+fn to_i32(reader: &ByteReader, value: i64) -> Result<i32, GdsError> {
+    i32::try_from(value)
+        .map_err(|_| {
+            reader.invalid("a 32-bit coordinate", format!("coordinate {} is out of range", value))
+        })
+}
+
+fn to_u16(reader: &ByteReader, value: u64, what: &str) -> Result<u16, GdsError> {
+    u16::try_from(value).map_err(|_| {
+        reader.invalid("a value from 0 to 65535", format!("{} {} is out of range", what, value))
+    })
+}
+
+fn point(reader: &ByteReader, x: i64, y: i64) -> Result<[i32; 2], GdsError> {
+    Ok([to_i32(reader, x)?, to_i32(reader, y)?])
+}
+
+impl Parser {
+    fn new() -> Self {
+        Parser {
+            modal: Modal::default(),
+            cell_names: HashMap::new(),
+            text_strings: HashMap::new(),
+            next_cell_name: 0,
+            next_text_string: 0,
+            cells: Vec::new(),
+            ended: false,
+        }
+    }
+
+    fn current_cell(&mut self, reader: &ByteReader) -> Result<&mut PendingCell, GdsError> {
+        let outside = || reader.invalid("a CELL record first", "element record outside a cell");
+        self.cells.last_mut().ok_or_else(outside)
+    }
+
+    // Read records until END or the end of the data (for CBLOCK contents)
+    fn records(&mut self, reader: &mut ByteReader) -> Result<(), GdsError> {
+        while !self.ended && !reader.is_empty() {
+            let id = reader.unsigned()?;
+            match id {
+                PAD => {}
+                START => {
+                    let message = "unexpected second START record";
+                    return Err(reader.invalid("any record but START", message));
+                }
+                END => self.ended = true,
+                CELLNAME | CELLNAME_REF => {
+                    let name = reader.string()?;
+                    let number =
+                        self.table_number(reader, id == CELLNAME_REF, |p| &mut p.next_cell_name)?;
+                    self.cell_names.insert(number, name);
+                }
+                TEXTSTRING | TEXTSTRING_REF => {
+                    let text = reader.string()?;
+                    let number = self
+                        .table_number(reader, id == TEXTSTRING_REF, |p| &mut p.next_text_string)?;
+                    self.text_strings.insert(number, text);
+                }
+                PROPNAME | PROPNAME_REF => {
+                    reader.skip_string()?;
+                    if id == PROPNAME_REF {
+                        reader.unsigned()?;
+                    }
+                }
+                PROPSTRING | PROPSTRING_REF => {
+                    reader.skip_string()?;
+                    if id == PROPSTRING_REF {
+                        reader.unsigned()?;
+                    }
+                }
+                LAYERNAME | LAYERNAME_TEXT => {
+                    reader.skip_string()?;
+                    skip_interval(reader)?;
+                    skip_interval(reader)?;
+                }
+                CELL_REF | CELL => {
+                    let name = if id == CELL_REF {
+                        Name::Ref(reader.unsigned()?)
+                    } else {
+                        Name::Literal(reader.string()?)
+                    };
+                    self.modal = Modal::default();
+                    self.cells.push(PendingCell {
+                        name,
+                        references: Vec::new(),
+                        elements: Vec::new(),
+                        texts: Vec::new(),
+                    });
+                }
+                XYABSOLUTE => self.modal.relative = false,
+                XYRELATIVE => self.modal.relative = true,
+                PLACEMENT | PLACEMENT_MAG => self.placement(reader, id == PLACEMENT_MAG)?,
+                TEXT => self.text(reader)?,
+                RECTANGLE => self.rectangle(reader)?,
+                POLYGON => self.polygon(reader)?,
+                PATH => self.path(reader)?,
+                TRAPEZOID | TRAPEZOID_A | TRAPEZOID_B => self.trapezoid(reader, id)?,
+                CTRAPEZOID => self.ctrapezoid(reader)?,
+                CIRCLE => self.circle(reader)?,
+                PROPERTY => skip_property(reader)?,
+                PROPERTY_REPEAT => {}
+                XNAME | XNAME_REF => {
+                    reader.unsigned()?;
+                    reader.skip_string()?;
+                    if id == XNAME_REF {
+                        reader.unsigned()?;
+                    }
+                }
+                XELEMENT => {
+                    reader.unsigned()?;
+                    reader.skip_string()?;
+                }
+                XGEOMETRY => self.xgeometry(reader)?,
+                CBLOCK => self.cblock(reader)?,
+                _ => {
+                    let message = format!("unknown record type {}", id);
+                    return Err(reader.invalid("an OASIS record type", message));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Reference number of a name table entry: explicit, or the next implicit one
+    fn table_number(
+        &mut self,
+        reader: &mut ByteReader,
+        explicit: bool,
+        counter: fn(&mut Parser) -> &mut u64,
+    ) -> Result<u64, GdsError> {
+        if explicit {
+            return reader.unsigned();
+        }
+        let next = counter(self);
+        let number = *next;
+        *next += 1;
+        Ok(number)
+    }
+
+    fn cblock(&mut self, reader: &mut ByteReader) -> Result<(), GdsError> {
+        let offset = reader.offset();
+        let scheme = reader.unsigned()?;
+        if scheme != 0 {
+            let message = format!("unsupported CBLOCK compression type {}", scheme);
+            return Err(reader.invalid("compression type 0", message));
+        }
+        let uncompressed_len = reader.length()?;
+        let compressed_len = reader.length()?;
+        let compressed = reader.bytes(compressed_len)?;
+        let mut data = Vec::with_capacity(uncompressed_len);
+        flate2::read::DeflateDecoder::new(compressed)
+            .read_to_end(&mut data)
+            .map_err(|e| {
+                reader.invalid("deflate data", format!("Failed to decompress CBLOCK: {}", e))
+            })?;
+        if data.len() != uncompressed_len {
+            return Err(reader.invalid(
+                "the declared uncompressed length",
+                format!(
+                    "CBLOCK decompressed to {} bytes, expected {}",
+                    data.len(),
+                    uncompressed_len
+                ),
+            ));
+        }
+        self.records(&mut ByteReader::decompressed(&data, offset))
+    }
+
+    // Apply XYRELATIVE mode to a coordinate read from a record
+    fn coordinate(
+        &self,
+        reader: &mut ByteReader,
+        present: bool,
+        modal: i64,
+    ) -> Result<i64, GdsError> {
+        if !present {
+            return Ok(modal);
+        }
+        let value = reader.signed()?;
+        Ok(if self.modal.relative { modal + value } else { value })
+    }
+
+    fn repetition(
+        &mut self,
+        reader: &mut ByteReader,
+        present: bool,
+    ) -> Result<Option<Repetition>, GdsError> {
+        if !present {
+            return Ok(None);
+        }
+        let repetition = read_repetition(reader, &self.modal.repetition)?;
+        self.modal.repetition = Some(repetition.clone());
+        Ok(Some(repetition))
+    }
+
+    // x, y and repetition fields shared by every geometry record
+    fn geometry_position(
+        &mut self,
+        reader: &mut ByteReader,
+        info: u8,
+    ) -> Result<Vec<[i64; 2]>, GdsError> {
+        let x = self.coordinate(reader, info & 0x10 != 0, self.modal.geometry_x)?;
+        self.modal.geometry_x = x;
+        let y = self.coordinate(reader, info & 0x08 != 0, self.modal.geometry_y)?;
+        self.modal.geometry_y = y;
+        let repetition = self.repetition(reader, info & 0x04 != 0)?;
+        Ok(match repetition {
+            None => vec![[x, y]],
+            Some(repetition) => {
+                repetition.offsets().into_iter().map(|[dx, dy]| [x + dx, y + dy]).collect()
+            }
+        })
+    }
+
+    fn layer_and_datatype(
+        &mut self,
+        reader: &mut ByteReader,
+        info: u8,
+    ) -> Result<(u16, u16), GdsError> {
+        let layer = modal_unsigned(reader, info & 0x01 != 0, &mut self.modal.layer, "layer")?;
+        let datatype =
+            modal_unsigned(reader, info & 0x02 != 0, &mut self.modal.datatype, "datatype")?;
+        Ok((to_u16(reader, layer, "layer")?, to_u16(reader, datatype, "datatype")?))
+    }
+
+    // Add a polygon (relative to each position) to the current cell, closing it
+    // the way GDSII boundaries are closed
+    fn push_polygons(
+        &mut self,
+        reader: &ByteReader,
+        layer: u16,
+        datatype: u16,
+        outline: &[[i64; 2]],
+        positions: &[[i64; 2]],
+    ) -> Result<(), GdsError> {
+        let mut elements = Vec::with_capacity(positions.len());
+        for [x, y] in positions {
+            let mut points = outline
+                .iter()
+                .map(|[px, py]| point(reader, x + px, y + py))
+                .collect::<Result<Vec<_>, _>>()?;
+            if let Some(&first) = points.first() {
+                if points.last() != Some(&first) {
+                    points.push(first);
+                }
+            }
+            elements.push(GdsElement::boundary(layer, datatype, points, Vec::new()));
+        }
+        self.current_cell(reader)?.elements.extend(elements);
+        Ok(())
+    }
+
+    fn placement(
+        &mut self,
+        reader: &mut ByteReader,
+        with_magnification: bool,
+    ) -> Result<(), GdsError> {
+        let info = reader.byte()?;
+        if info & 0x80 != 0 {
+            self.modal.placement_cell = Some(if info & 0x40 != 0 {
+                Name::Ref(reader.unsigned()?)
+            } else {
+                Name::Literal(reader.string()?)
+            });
+        }
+        let cell = self
+            .modal
+            .placement_cell
+            .clone()
+            .ok_or_else(|| {
+                reader.invalid(MODAL_SET, "modal variable placement-cell is undefined")
+            })?;
+
+        let (mag, angle) = if with_magnification {
+            let mag = if info & 0x04 != 0 { reader.real()? } else { 1.0 };
+            let angle = if info & 0x02 != 0 { reader.real()? } else { 0.0 };
+            (mag, angle)
+        } else {
+            (1.0, ((info >> 1) & 0x3) as f64 * 90.0)
+        };
+        let x = self.coordinate(reader, info & 0x20 != 0, self.modal.placement_x)?;
+        self.modal.placement_x = x;
+        let y = self.coordinate(reader, info & 0x10 != 0, self.modal.placement_y)?;
+        self.modal.placement_y = y;
+        let repetition = self.repetition(reader, info & 0x08 != 0)?;
+
+        let strans = Strans { reflect_x: info & 0x01 != 0, mag, angle, ..Strans::default() };
+        let origin = point(reader, x, y)?;
+        let mut references = Vec::new();
+        match repetition {
+            None => {
+                references.push(CellReference {
+                    cell: String::new(),
+                    origin,
+                    strans,
+                    array: None,
+                    properties: Vec::new(),
+                })
+            }
+            Some(Repetition::Grid { columns, rows, column_step, row_step })
+                if columns <= u16::MAX as u64 && rows <= u16::MAX as u64 =>
+            {
+                let column_point = point(
+                    reader,
+                    x + columns as i64 * column_step[0],
+                    y + columns as i64 * column_step[1],
+                )?;
+                let row_point =
+                    point(reader, x + rows as i64 * row_step[0], y + rows as i64 * row_step[1])?;
+                let array = ArrayParams {
+                    columns: columns as u16,
+                    rows: rows as u16,
+                    column_point,
+                    row_point,
+                };
+                references.push(CellReference {
+                    cell: String::new(),
+                    origin,
+                    strans,
+                    array: Some(array),
+                    properties: Vec::new(),
+                });
+            }
+            Some(repetition) => {
+                for [dx, dy] in repetition.offsets() {
+                    let origin = point(reader, x + dx, y + dy)?;
+                    references.push(CellReference {
+                        cell: String::new(),
+                        origin,
+                        strans,
+                        array: None,
+                        properties: Vec::new(),
+                    });
+                }
+            }
+        }
+        let current = self.current_cell(reader)?;
+        current
+            .references
+            .extend(references.into_iter().map(|reference| (cell.clone(), reference)));
+        Ok(())
+    }
+
+    fn text(&mut self, reader: &mut ByteReader) -> Result<(), GdsError> {
+        let info = reader.byte()?;
+        if info & 0x40 != 0 {
+            self.modal.text_string = Some(if info & 0x20 != 0 {
+                Name::Ref(reader.unsigned()?)
+            } else {
+                Name::Literal(reader.string()?)
+            });
+        }
+        let text = self
+            .modal
+            .text_string
+            .clone()
+            .ok_or_else(|| reader.invalid(MODAL_SET, "modal variable text-string is undefined"))?;
+        let layer =
+            modal_unsigned(reader, info & 0x01 != 0, &mut self.modal.text_layer, "textlayer")?;
+        let texttype =
+            modal_unsigned(reader, info & 0x02 != 0, &mut self.modal.texttype, "texttype")?;
+        let (layer, texttype) =
+            (to_u16(reader, layer, "layer")?, to_u16(reader, texttype, "texttype")?);
+
+        let x = self.coordinate(reader, info & 0x10 != 0, self.modal.text_x)?;
+        self.modal.text_x = x;
+        let y = self.coordinate(reader, info & 0x08 != 0, self.modal.text_y)?;
+        self.modal.text_y = y;
+        let repetition = self.repetition(reader, info & 0x04 != 0)?;
+        let offsets = repetition.map(|r| r.offsets()).unwrap_or_else(|| vec![[0, 0]]);
+
+        let mut texts = Vec::with_capacity(offsets.len());
+        for [dx, dy] in offsets {
+            let element = GdsElement::Text {
+                layer,
+                texttype,
+                presentation: 0,
+                strans: Strans::default(),
+                position: point(reader, x + dx, y + dy)?,
+                text: String::new(),
+                properties: Vec::new(),
+            };
+            texts.push(PendingText { element, text: text.clone() });
+        }
+        self.current_cell(reader)?.texts.extend(texts);
+        Ok(())
+    }
+
+    fn rectangle(&mut self, reader: &mut ByteReader) -> Result<(), GdsError> {
+        let info = reader.byte()?;
+        let (layer, datatype) = self.layer_and_datatype(reader, info)?;
+        let square = info & 0x80 != 0;
+        let width =
+            modal_unsigned(reader, info & 0x40 != 0, &mut self.modal.geometry_w, "geometry-w")?;
+        let height = if square {
+            self.modal.geometry_h = Some(width);
+            width
+        } else {
+            modal_unsigned(reader, info & 0x20 != 0, &mut self.modal.geometry_h, "geometry-h")?
+        };
+        let positions = self.geometry_position(reader, info)?;
+        let (w, h) = (width as i64, height as i64);
+        self.push_polygons(reader, layer, datatype, &[[0, 0], [w, 0], [w, h], [0, h]], &positions)
+    }
+
+    fn polygon(&mut self, reader: &mut ByteReader) -> Result<(), GdsError> {
+        let info = reader.byte()?;
+        let (layer, datatype) = self.layer_and_datatype(reader, info)?;
+        if info & 0x20 != 0 {
+            self.modal.polygon_points = Some(read_point_list(reader, true)?);
+        }
+        let outline = self
+            .modal
+            .polygon_points
+            .clone()
+            .ok_or_else(|| {
+                reader.invalid(MODAL_SET, "modal variable polygon-point-list is undefined")
+            })?;
+        let positions = self.geometry_position(reader, info)?;
+        self.push_polygons(reader, layer, datatype, &outline, &positions)
+    }
+
+    fn path(&mut self, reader: &mut ByteReader) -> Result<(), GdsError> {
+        let info = reader.byte()?;
+        let (layer, datatype) = self.layer_and_datatype(reader, info)?;
+        let halfwidth = modal_unsigned(
+            reader,
+            info & 0x40 != 0,
+            &mut self.modal.path_halfwidth,
+            "path-halfwidth",
+        )?;
+        let halfwidth = halfwidth as i64;
+        if info & 0x80 != 0 {
+            let scheme = reader.unsigned()?;
+            for (bits, modal) in [(scheme >> 2, 0), (scheme, 1)] {
+                let extension = match bits & 0x3 {
+                    0 => None,
+                    1 => Some(0),
+                    2 => Some(halfwidth),
+                    _ => Some(reader.signed()?),
+                };
+                if let Some(extension) = extension {
+                    if modal == 0 {
+                        self.modal.path_start_extension = Some(extension);
+                    } else {
+                        self.modal.path_end_extension = Some(extension);
+                    }
+                }
+            }
+        }
+        if info & 0x20 != 0 {
+            self.modal.path_points = Some(read_point_list(reader, false)?);
+        }
+        let centerline = self
+            .modal
+            .path_points
+            .clone()
+            .ok_or_else(|| {
+                reader.invalid(MODAL_SET, "modal variable path-point-list is undefined")
+            })?;
+        let positions = self.geometry_position(reader, info)?;
+
+        // GDSII paths only distinguish flush (0), half-width (2) and custom (4) ends
+        let start = self.modal.path_start_extension.unwrap_or(0);
+        let end = self.modal.path_end_extension.unwrap_or(0);
+        let pathtype = match (start, end) {
+            (0, 0) => 0,
+            (s, e) if s == halfwidth && e == halfwidth => 2,
+            _ => 4,
+        };
+        let width = to_i32(reader, halfwidth * 2)?;
+        let (begin_extension, end_extension) = match pathtype {
+            4 => (to_i32(reader, start)?, to_i32(reader, end)?),
+            _ => (0, 0),
+        };
+        let mut elements = Vec::with_capacity(positions.len());
+        for [x, y] in &positions {
+            let points = centerline
+                .iter()
+                .map(|[px, py]| point(reader, x + px, y + py))
+                .collect::<Result<Vec<_>, _>>()?;
+            elements.push(GdsElement::Path {
+                layer,
+                datatype,
+                pathtype,
+                width,
+                begin_extension,
+                end_extension,
+                points,
+                properties: Vec::new(),
+            });
+        }
+        self.current_cell(reader)?.elements.extend(elements);
+        Ok(())
+    }
+
+    fn trapezoid(&mut self, reader: &mut ByteReader, id: u64) -> Result<(), GdsError> {
+        let info = reader.byte()?;
+        let (layer, datatype) = self.layer_and_datatype(reader, info)?;
+        let w = modal_unsigned(reader, info & 0x40 != 0, &mut self.modal.geometry_w, "geometry-w")?
+            as i64;
+        let h = modal_unsigned(reader, info & 0x20 != 0, &mut self.modal.geometry_h, "geometry-h")?
+            as i64;
+        let delta_a = if id != TRAPEZOID_B { reader.signed()? } else { 0 };
+        let delta_b = if id != TRAPEZOID_A { reader.signed()? } else { 0 };
+        let positions = self.geometry_position(reader, info)?;
+        let outline = if info & 0x80 != 0 {
+            [
+                [0, delta_a.max(0)],
+                [0, h + delta_b.min(0)],
+                [w, h - delta_b.max(0)],
+                [w, -delta_a.min(0)],
+            ]
+        } else {
+            [
+                [-delta_a.min(0), h],
+                [w - delta_b.max(0), h],
+                [w + delta_b.min(0), 0],
+                [delta_a.max(0), 0],
+            ]
+        };
+        self.push_polygons(reader, layer, datatype, &outline, &positions)
+    }
+
+    // Compact trapezoids are read so the stream stays in sync but not drawn
+    fn ctrapezoid(&mut self, reader: &mut ByteReader) -> Result<(), GdsError> {
+        let info = reader.byte()?;
+        self.layer_and_datatype(reader, info)?;
+        if info & 0x80 != 0 {
+            reader.unsigned()?;
+        }
+        if info & 0x40 != 0 {
+            self.modal.geometry_w = Some(reader.unsigned()?);
+        }
+        if info & 0x20 != 0 {
+            self.modal.geometry_h = Some(reader.unsigned()?);
+        }
+        self.geometry_position(reader, info)?;
+        log::warn!("Skipping unsupported OASIS CTRAPEZOID record at byte {}", reader.offset());
+        Ok(())
+    }
+
+    fn circle(&mut self, reader: &mut ByteReader) -> Result<(), GdsError> {
+        let info = reader.byte()?;
+        let (layer, datatype) = self.layer_and_datatype(reader, info)?;
+        let radius = modal_unsigned(
+            reader,
+            info & 0x20 != 0,
+            &mut self.modal.circle_radius,
+            "circle-radius",
+        )? as f64;
+        let positions = self.geometry_position(reader, info)?;
+        let outline: Vec<[i64; 2]> = (0..CIRCLE_SEGMENTS)
+            .map(|i| {
+                let angle = std::f64::consts::TAU * i as f64 / CIRCLE_SEGMENTS as f64;
+                [(radius * angle.cos()).round() as i64, (radius * angle.sin()).round() as i64]
+            })
+            .collect();
+        self.push_polygons(reader, layer, datatype, &outline, &positions)
+    }
+
+    fn xgeometry(&mut self, reader: &mut ByteReader) -> Result<(), GdsError> {
+        let info = reader.byte()?;
+        reader.unsigned()?;
+        if info & 0x01 != 0 {
+            self.modal.layer = Some(reader.unsigned()?);
+        }
+        if info & 0x02 != 0 {
+            self.modal.datatype = Some(reader.unsigned()?);
+        }
+        reader.skip_string()?;
+        self.geometry_position(reader, info)?;
+        Ok(())
+    }
+
+    fn finish(self, name: String, units: GdsUnits) -> Result<GdsLibrary, GdsError> {
+        let resolve = |name: &Name, table: &HashMap<u64, String>, what: &str| match name {
+            Name::Literal(name) => Ok(name.clone()),
+            Name::Ref(number) => {
+                table.get(number).cloned().ok_or_else(|| {
+                    GdsError::invalid_record(
+                        0,
+                        format!("a defined {} reference number", what),
+                        format!("{} reference number {} is not defined", what, number),
+                    )
+                })
+            }
+        };
+
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for pending in self.cells {
+            let cell_name = resolve(&pending.name, &self.cell_names, "cell name")?;
+            let mut references = Vec::with_capacity(pending.references.len());
+            for (target, mut reference) in pending.references {
+                reference.cell = resolve(&target, &self.cell_names, "cell name")?;
+                references.push(reference);
+            }
+            let mut elements = pending.elements;
+            for PendingText { mut element, text } in pending.texts {
+                if let GdsElement::Text { text: value, .. } = &mut element {
+                    *value = resolve(&text, &self.text_strings, "text string")?;
+                }
+                elements.push(element);
+            }
+            cells.push(GdsCell {
+                name: cell_name,
+                dates: GdsDates::default(),
+                references,
+                elements,
+            });
+        }
+        Ok(GdsLibrary {
+            name,
+            // OASIS has no GDSII version; report the one exporting it would write
+            version: GDS_VERSION,
+            units,
+            dates: GdsDates::default(),
+            cells,
+            warnings: Vec::new(),
+        })
+    }
+}
+
+fn skip_interval(reader: &mut ByteReader) -> Result<(), GdsError> {
+    match reader.unsigned()? {
+        0 => {}
+        1..=3 => {
+            reader.unsigned()?;
+        }
+        4 => {
+            reader.unsigned()?;
+            reader.unsigned()?;
+        }
+        kind => {
+            let message = format!("unknown interval type {}", kind);
+            return Err(reader.invalid("an interval type from 0 to 4", message));
+        }
+    }
+    Ok(())
+}
+
+fn skip_property(reader: &mut ByteReader) -> Result<(), GdsError> {
+    let info = reader.byte()?;
+    if info & 0x04 != 0 {
+        if info & 0x02 != 0 {
+            reader.unsigned()?;
+        } else {
+            reader.skip_string()?;
+        }
+    }
+    if info & 0x08 != 0 {
+        return Ok(());
+    }
+    let mut count = (info >> 4) as u64;
+    if count == 15 {
+        count = reader.unsigned()?;
+    }
+    for _ in 0..count {
+        match reader.unsigned()? {
+            kind @ 0..=7 => {
+                reader.real_of_type(kind)?;
+            }
+            8 | 9 | 13..=15 => {
+                reader.unsigned()?;
+            }
+            10..=12 => reader.skip_string()?,
+            kind => {
+                let message = format!("unknown property value type {}", kind);
+                return Err(reader.invalid("a property value type from 0 to 15", message));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_repetition(
+    reader: &mut ByteReader,
+    previous: &Option<Repetition>,
+) -> Result<Repetition, GdsError> {
+    let kind = reader.unsigned()?;
+    let dimension =
+        |reader: &mut ByteReader| -> Result<u64, GdsError> { Ok(reader.unsigned()? + 2) };
+    let spaced = |reader: &mut ByteReader,
+                  count: u64,
+                  grid: i64,
+                  vertical: bool|
+     -> Result<Repetition, GdsError> {
+        let mut offsets = vec![[0, 0]];
+        let mut position = 0i64;
+        for _ in 1..count {
+            position += reader.unsigned()? as i64 * grid;
+            offsets.push(if vertical { [0, position] } else { [position, 0] });
+        }
+        Ok(Repetition::List(offsets))
+    };
+    Ok(match kind {
+        0 => previous
+            .clone()
+            .ok_or_else(|| reader.invalid(MODAL_SET, "modal variable repetition is undefined"))?,
+        1 => {
+            let columns = dimension(reader)?;
+            let rows = dimension(reader)?;
+            let dx = reader.unsigned()? as i64;
+            let dy = reader.unsigned()? as i64;
+            Repetition::Grid { columns, rows, column_step: [dx, 0], row_step: [0, dy] }
+        }
+        2 => {
+            let columns = dimension(reader)?;
+            let dx = reader.unsigned()? as i64;
+            Repetition::Grid { columns, rows: 1, column_step: [dx, 0], row_step: [0, 0] }
+        }
+        3 => {
+            let rows = dimension(reader)?;
+            let dy = reader.unsigned()? as i64;
+            Repetition::Grid { columns: 1, rows, column_step: [0, 0], row_step: [0, dy] }
+        }
+        4 | 6 => {
+            let count = dimension(reader)?;
+            spaced(reader, count, 1, kind == 6)?
+        }
+        5 | 7 => {
+            let count = dimension(reader)?;
+            let grid = reader.unsigned()? as i64;
+            spaced(reader, count, grid, kind == 7)?
+        }
+        8 => {
+            let columns = dimension(reader)?;
+            let rows = dimension(reader)?;
+            let column_step = reader.delta_g()?;
+            let row_step = reader.delta_g()?;
+            Repetition::Grid { columns, rows, column_step, row_step }
+        }
+        9 => {
+            let columns = dimension(reader)?;
+            let column_step = reader.delta_g()?;
+            Repetition::Grid { columns, rows: 1, column_step, row_step: [0, 0] }
+        }
+        10 | 11 => {
+            let count = dimension(reader)?;
+            let grid = if kind == 11 { reader.unsigned()? as i64 } else { 1 };
+            let mut offsets = vec![[0, 0]];
+            let mut position = [0i64, 0i64];
+            for _ in 1..count {
+                let [dx, dy] = reader.delta_g()?;
+                position = [position[0] + dx * grid, position[1] + dy * grid];
+                offsets.push(position);
+            }
+            Repetition::List(offsets)
+        }
+        _ => {
+            let message = format!("unknown repetition type {}", kind);
+            return Err(reader.invalid("a repetition type from 0 to 11", message));
+        }
+    })
+}
+
+// Vertices of a point list relative to the element position, starting at the
+// origin. Manhattan lists (types 0 and 1) of polygons imply a closing vertex.
+fn read_point_list(reader: &mut ByteReader, polygon: bool) -> Result<Vec<[i64; 2]>, GdsError> {
+    let kind = reader.unsigned()?;
+    let count = reader.length()?;
+    let mut points = Vec::with_capacity(count + 2);
+    points.push([0i64, 0i64]);
+    let mut current = [0i64, 0i64];
+    let mut previous_delta = [0i64, 0i64];
+    for i in 0..count {
+        let delta = match kind {
+            0 | 1 => {
+                let value = reader.signed()?;
+                let horizontal = (i % 2 == 0) == (kind == 0);
+                if horizontal {
+                    [value, 0]
+                } else {
+                    [0, value]
+                }
+            }
+            2 => reader.delta_2()?,
+            3 => reader.delta_3()?,
+            4 => reader.delta_g()?,
+            5 => {
+                let [dx, dy] = reader.delta_g()?;
+                previous_delta = [previous_delta[0] + dx, previous_delta[1] + dy];
+                previous_delta
+            }
+            _ => {
+                let message = format!("unknown point list type {}", kind);
+                return Err(reader.invalid("a point list type from 0 to 5", message));
+            }
+        };
+        current = [current[0] + delta[0], current[1] + delta[1]];
+        points.push(current);
+    }
+    if polygon && (kind == 0 || kind == 1) {
+        let next_horizontal = (count % 2 == 0) == (kind == 0);
+        let implied = if next_horizontal { [0, current[1]] } else { [current[0], 0] };
+        if implied != current && implied != [0, 0] {
+            points.push(implied);
+        }
+    }
+    Ok(points)
+}
+
+// Parse an OASIS file into the same library structure as GDSII. The library is
+// named `name`, since OASIS files don't carry one.
+pub fn parse_oasis(bytes: &[u8], name: &str) -> Result<GdsLibrary, GdsError> {
+    read_oasis(bytes, name).map_err(|error| error.with_context(bytes))
+}
+
+fn read_oasis(bytes: &[u8], name: &str) -> Result<GdsLibrary, GdsError> {
+    if !bytes.starts_with(MAGIC) {
+        return Err(GdsError::InvalidHeader {
+            message: "file does not start with the OASIS magic bytes".to_string(),
+        });
+    }
+    let mut reader = ByteReader::new(bytes);
+    reader.seek(MAGIC.len());
+    if reader.unsigned()? != START {
+        return Err(GdsError::InvalidHeader { message: "first record is not START".to_string() });
+    }
+    let version = reader.string()?;
+    if version != "1.0" {
+        log::warn!("Reading OASIS version {} as 1.0", version);
+    }
+    let unit = reader.real()?;
+    if unit.is_nan() || unit <= 0.0 {
+        return Err(GdsError::InvalidUnits {
+            message: format!("START unit must be positive, got {}", unit),
+        });
+    }
+    let offset_flag = reader.unsigned()?;
+    if offset_flag == 0 {
+        for _ in 0..12 {
+            reader.unsigned()?;
+        }
+    }
+
+    let mut parser = Parser::new();
+    parser.records(&mut reader)?;
+    if !parser.ended {
+        return Err(GdsError::truncated(
+            reader.position() as u64,
+            "an END record",
+            "missing END record",
+        ));
+    }
+    // `unit` is database units per micron
+    let units = GdsUnits { user_unit: 1.0 / unit, db_unit_meters: 1e-6 / unit };
+    parser.finish(name.to_string(), units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gds::Rect;
+    use std::io::Write;
+
+    // Minimal OASIS byte writer
+    #[derive(Default)]
+    struct Oasis(Vec<u8>);
+
+    impl Oasis {
+        fn start() -> Self {
+            let mut oasis = Oasis(MAGIC.to_vec());
+            oasis.uint(START).string("1.0").uint(0).uint(1000).uint(0);
+            for _ in 0..12 {
+                oasis.uint(0);
+            }
+            oasis
+        }
+
+        fn uint(&mut self, mut value: u64) -> &mut Self {
+            loop {
+                let byte = (value & 0x7f) as u8;
+                value >>= 7;
+                if value == 0 {
+                    self.0.push(byte);
+                    return self;
+                }
+                self.0.push(byte | 0x80);
+            }
+        }
+
+        fn sint(&mut self, value: i64) -> &mut Self {
+            self.uint((value.unsigned_abs() << 1) | (value < 0) as u64)
+        }
+
+        fn byte(&mut self, value: u8) -> &mut Self {
+            self.0.push(value);
+            self
+        }
+
+        fn string(&mut self, value: &str) -> &mut Self {
+            self.uint(value.len() as u64);
+            self.0.extend_from_slice(value.as_bytes());
+            self
+        }
+    }
+
+    #[test]
+    fn decodes_geometry_placements_and_compressed_blocks() {
+        let mut oasis = Oasis::start();
+        // TOP is named by reference; its CELLNAME comes at the end
+        oasis.uint(CELL_REF).uint(0);
+        // Three 10x20 rectangles at 30 pitch
+        oasis.uint(RECTANGLE).byte(0x7f).uint(1).uint(0).uint(10).uint(20).sint(0).sint(0);
+        oasis.uint(2).uint(1).uint(30);
+        // Manhattan polygon reusing the modal layer and datatype
+        oasis.uint(POLYGON).byte(0x38).uint(0).uint(3).sint(10).sint(10).sint(-10);
+        oasis.sint(100).sint(0);
+        // Path with half-width extensions
+        oasis.uint(PATH).byte(0xf8).uint(2).uint(0b1010).uint(2).uint(2).uint(20).uint(21);
+        oasis.sint(0).sint(50);
+        // CHILD placed by name, reflected and rotated by 90 degrees
+        oasis.uint(PLACEMENT).byte(0xb3).string("CHILD").sint(5).sint(7);
+
+        let mut child = Oasis::default();
+        child.uint(RECTANGLE).byte(0x7b).uint(2).uint(0).uint(1).uint(1).sint(0).sint(0);
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&child.0).unwrap();
+        let compressed = encoder.finish().unwrap();
+        oasis.uint(CELL).string("CHILD");
+        oasis.uint(CBLOCK).uint(0).uint(child.0.len() as u64).uint(compressed.len() as u64);
+        oasis.0.extend_from_slice(&compressed);
+        oasis.uint(CELLNAME).string("TOP").uint(END);
+
+        let library = parse_oasis(&oasis.0, "design").unwrap();
+        assert!((library.units.user_unit - 0.001).abs() < 1e-15);
+        let top = library.cell("TOP").unwrap();
+        assert_eq!(top.elements.len(), 5);
+        assert_eq!(
+            top.elements[2],
+            GdsElement::Rect {
+                layer: 1,
+                datatype: 0,
+                rect: Rect { x: 60, y: 0, w: 10, h: 20 },
+                properties: Vec::new(),
+            }
+        );
+        assert_eq!(
+            top.elements[3],
+            GdsElement::Rect {
+                layer: 1,
+                datatype: 0,
+                rect: Rect { x: 100, y: 0, w: 10, h: 10 },
+                properties: Vec::new(),
+            }
+        );
+        assert_eq!(
+            top.elements[4],
+            GdsElement::Path {
+                layer: 1,
+                datatype: 0,
+                pathtype: 2,
+                width: 4,
+                begin_extension: 0,
+                end_extension: 0,
+                points: vec![[0, 50], [5, 50], [5, 55]],
+                properties: Vec::new(),
+            }
+        );
+        let reference = &top.references[0];
+        assert_eq!((reference.cell.as_str(), reference.origin), ("CHILD", [5, 7]));
+        assert!(reference.strans.reflect_x);
+        assert_eq!(reference.strans.angle, 90.0);
+        assert_eq!(library.cell("CHILD").unwrap().elements[0].layer_key(), (2, 0));
+    }
+
+    #[test]
+    fn grid_placements_become_arrays_and_unknown_names_fail() {
+        let mut oasis = Oasis::start();
+        oasis.uint(CELL).string("TOP");
+        // 2 columns at 10 pitch, 3 rows at 20 pitch
+        oasis.uint(PLACEMENT).byte(0xb8).string("LEAF").sint(1).sint(2);
+        oasis.uint(1).uint(0).uint(1).uint(10).uint(20);
+        oasis.uint(CELL).string("LEAF").uint(END);
+        let library = parse_oasis(&oasis.0, "design").unwrap();
+        assert_eq!(
+            library.cell("TOP").unwrap().references[0].array,
+            Some(ArrayParams { columns: 2, rows: 3, column_point: [21, 2], row_point: [1, 62] })
+        );
+
+        let mut dangling = Oasis::start();
+        dangling.uint(CELL_REF).uint(7).uint(END);
+        assert!(matches!(parse_oasis(&dangling.0, "x"), Err(GdsError::InvalidRecord { .. })));
+    }
+}
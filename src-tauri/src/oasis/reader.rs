@@ -0,0 +1,172 @@
+use crate::gds::GdsError;
+
+// Reads OASIS primitive values: variable-length integers, reals, strings and
+// the delta encodings used by point lists and repetitions
+pub struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    // Offset reported in errors for data decompressed from a CBLOCK, which has
+    // no position of its own in the file
+    fixed_offset: Option<u64>,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        ByteReader { data, pos: 0, fixed_offset: None }
+    }
+
+    // Reader over decompressed CBLOCK contents, reporting errors at `offset`
+    pub fn decompressed(data: &'a [u8], offset: u64) -> Self {
+        ByteReader { data, pos: 0, fixed_offset: Some(offset) }
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.fixed_offset.unwrap_or(self.pos as u64)
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn seek(&mut self, pos: usize) {
+        self.pos = pos.min(self.data.len());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    pub fn invalid(&self, expected: &str, message: impl Into<String>) -> GdsError {
+        GdsError::invalid_record(self.offset(), expected, message)
+    }
+
+    fn truncated(&self, what: &str) -> GdsError {
+        let message = format!("file ends inside {}", what);
+        GdsError::truncated(self.offset(), format!("the rest of {}", what), message)
+    }
+
+    pub fn byte(&mut self) -> Result<u8, GdsError> {
+        let byte = *self.data.get(self.pos).ok_or_else(|| self.truncated("a record"))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], GdsError> {
+        if self.data.len() - self.pos < len {
+            return Err(self.truncated("a string or block"));
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    // Unsigned integer: 7 bits per byte, least significant group first
+    pub fn unsigned(&mut self) -> Result<u64, GdsError> {
+        let mut value: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            if shift >= 64 || (shift == 63 && byte & 0x7e != 0) {
+                return Err(self.invalid(
+                    "an integer of at most 64 bits",
+                    "unsigned integer does not fit in 64 bits",
+                ));
+            }
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    // Signed integer: sign in the lowest bit, magnitude above it
+    pub fn signed(&mut self) -> Result<i64, GdsError> {
+        let value = self.unsigned()?;
+        let magnitude = (value >> 1) as i64;
+        Ok(if value & 1 != 0 { -magnitude } else { magnitude })
+    }
+
+    pub fn real(&mut self) -> Result<f64, GdsError> {
+        let kind = self.unsigned()?;
+        self.real_of_type(kind)
+    }
+
+    // Real whose type tag has already been read (property values share the tags)
+    pub fn real_of_type(&mut self, kind: u64) -> Result<f64, GdsError> {
+        let value = match kind {
+            0 => self.unsigned()? as f64,
+            1 => -(self.unsigned()? as f64),
+            2 => 1.0 / self.unsigned()? as f64,
+            3 => -1.0 / self.unsigned()? as f64,
+            4 => self.unsigned()? as f64 / self.unsigned()? as f64,
+            5 => -(self.unsigned()? as f64) / self.unsigned()? as f64,
+            6 => f32::from_le_bytes(self.bytes(4)?.try_into().unwrap()) as f64,
+            7 => f64::from_le_bytes(self.bytes(8)?.try_into().unwrap()),
+            _ => {
+                let message = format!("unknown real type {}", kind);
+                return Err(self.invalid("a real type from 0 to 7", message));
+            }
+        };
+        Ok(value)
+    }
+
+    pub fn string(&mut self) -> Result<String, GdsError> {
+        let len = self.length()?;
+        Ok(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+    }
+
+    pub fn skip_string(&mut self) -> Result<(), GdsError> {
+        let len = self.length()?;
+        self.bytes(len).map(|_| ())
+    }
+
+    pub fn length(&mut self) -> Result<usize, GdsError> {
+        let len = self.unsigned()?;
+        usize::try_from(len).map_err(|_| {
+            self.invalid("a length that fits in memory", format!("length {} is too large", len))
+        })
+    }
+
+    // 2-delta: a Manhattan step, direction in the low 2 bits
+    pub fn delta_2(&mut self) -> Result<[i64; 2], GdsError> {
+        let value = self.unsigned()?;
+        let magnitude = (value >> 2) as i64;
+        Ok(match value & 0x3 {
+            0 => [magnitude, 0],
+            1 => [0, magnitude],
+            2 => [-magnitude, 0],
+            _ => [0, -magnitude],
+        })
+    }
+
+    // 3-delta: an octangular step, direction in the low 3 bits
+    pub fn delta_3(&mut self) -> Result<[i64; 2], GdsError> {
+        let value = self.unsigned()?;
+        Ok(octangular(value & 0x7, (value >> 3) as i64))
+    }
+
+    // g-delta: either an octangular step or an arbitrary (x, y) displacement
+    pub fn delta_g(&mut self) -> Result<[i64; 2], GdsError> {
+        let value = self.unsigned()?;
+        if value & 1 == 0 {
+            return Ok(octangular((value >> 1) & 0x7, (value >> 4) as i64));
+        }
+        let magnitude = (value >> 2) as i64;
+        let x = if value & 0x2 != 0 { -magnitude } else { magnitude };
+        Ok([x, self.signed()?])
+    }
+}
+
+fn octangular(direction: u64, magnitude: i64) -> [i64; 2] {
+    match direction {
+        0 => [magnitude, 0],
+        1 => [0, magnitude],
+        2 => [-magnitude, 0],
+        3 => [0, -magnitude],
+        4 => [magnitude, magnitude],
+        5 => [-magnitude, magnitude],
+        6 => [-magnitude, -magnitude],
+        _ => [magnitude, -magnitude],
+    }
+}
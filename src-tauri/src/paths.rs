@@ -0,0 +1,240 @@
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use tauri::Manager;
+
+use crate::error::AppError;
+
+// Whether user files that are symlinks are read, from the `follow_symlinks`
+// setting. On until the settings are loaded.
+static FOLLOW_SYMLINKS: AtomicBool = AtomicBool::new(true);
+
+pub(crate) fn set_follow_symlinks(follow: bool) {
+    FOLLOW_SYMLINKS.store(follow, Ordering::Relaxed);
+}
+
+// Whether `path` itself is a symlink that must not be followed under `follow`.
+// Only the last component counts: directories further up may be links set up
+// by the system, such as /var on macOS.
+fn is_refused_symlink(path: &Path, follow: bool) -> bool {
+    !follow && std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_symlink())
+}
+
+// Whether reading the user file `path` must be refused because it is a
+// symlink and the `follow_symlinks` setting is off
+pub(crate) fn refuses_symlink(path: &Path) -> bool {
+    is_refused_symlink(path, FOLLOW_SYMLINKS.load(Ordering::Relaxed))
+}
+
+// Resolve a layout file path given by the frontend to its canonical form:
+// `..` components are resolved and symlinks followed, so a file stored under
+// this path is always the real file it points at. Relative paths, which would
+// depend on the working directory, and anything that isn't an existing regular
+// file are rejected, and so is a symlink while `follow_symlinks` is off.
+pub(crate) fn layout_file(path: &str) -> Result<String, AppError> {
+    if !Path::new(path).is_absolute() {
+        return Err(AppError::invalid_argument(format!(
+            "Expected an absolute file path, got {:?}",
+            path
+        )));
+    }
+    if refuses_symlink(Path::new(path)) {
+        return Err(AppError::SymlinkRefused { path: path.to_string() });
+    }
+    let canonical = std::fs::canonicalize(path).map_err(|e| AppError::for_path(path, e))?;
+    if !canonical.is_file() {
+        return Err(AppError::invalid_argument(format!("{} is not a file", canonical.display())));
+    }
+    Ok(canonical.to_string_lossy().to_string())
+}
+
+// Resolve a path stored relative to the project root `root`. Absolute paths,
+// and any path when no root is set, are returned as given.
+pub(crate) fn resolve_in_root(root: Option<&Path>, path: &str) -> PathBuf {
+    match root {
+        Some(root) if Path::new(path).is_relative() => root.join(path),
+        _ => PathBuf::from(path),
+    }
+}
+
+// The form of `path` to store: relative to `root` when it lies inside it, so
+// the entry still resolves after the project folder moves, else unchanged
+pub(crate) fn relative_to_root(root: Option<&Path>, path: &str) -> String {
+    root.and_then(|root| Path::new(path).strip_prefix(root).ok())
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .map(|relative| relative.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+// Path of the data file `name` in `app_dir`. `name` must be a plain file name.
+// An existing symlink is only accepted if it resolves to somewhere inside
+// `app_dir`, so writing the file can't touch anything outside it.
+pub(crate) fn data_file(app_dir: &Path, name: &str) -> Result<PathBuf, AppError> {
+    let mut components = Path::new(name).components();
+    if !matches!((components.next(), components.next()), (Some(Component::Normal(_)), None)) {
+        return Err(AppError::invalid_argument(format!("Invalid data file name {:?}", name)));
+    }
+    let file = app_dir.join(name);
+    let is_symlink =
+        std::fs::symlink_metadata(&file).is_ok_and(|meta| meta.file_type().is_symlink());
+    if is_symlink {
+        let escapes = match (std::fs::canonicalize(app_dir), std::fs::canonicalize(&file)) {
+            (Ok(root), Ok(target)) => !target.starts_with(root),
+            // A dangling link would be followed on write, wherever it points
+            _ => true,
+        };
+        if escapes {
+            return Err(AppError::invalid_argument(format!(
+                "{} is a symlink leading outside the app data dir",
+                file.display()
+            )));
+        }
+    }
+    Ok(file)
+}
+
+// `data_file` in the app data dir
+pub(crate) fn app_data_file(app: &tauri::AppHandle, name: &str) -> Result<PathBuf, AppError> {
+    let app_dir = app.path().app_data_dir()
+        .map_err(|e| AppError::io("Failed to get app data dir", e))?;
+    data_file(&app_dir, name)
+}
+
+// Numbers the temp files of `write_atomic`, so concurrent writes never share one
+static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+
+// Write `bytes` to `dst` through a temp file that is renamed over it once
+// complete, so a write that fails or is cut short leaves any existing file
+// intact. The temp file sits next to `dst`, since a rename can't cross file
+// systems. A symlink at `dst` is left in place and its target replaced.
+pub(crate) fn write_atomic(dst: impl AsRef<Path>, bytes: &[u8]) -> std::io::Result<()> {
+    let dst = dst.as_ref();
+    let is_symlink = std::fs::symlink_metadata(dst).is_ok_and(|meta| meta.file_type().is_symlink());
+    let dst = if is_symlink { std::fs::canonicalize(dst)? } else { dst.to_path_buf() };
+    let name = dst.file_name().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "destination has no file name")
+    })?;
+    let temp = dst.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        name.to_string_lossy(),
+        std::process::id(),
+        NEXT_TEMP.fetch_add(1, Ordering::Relaxed)
+    ));
+    let written = (|| {
+        let mut file = std::fs::File::options().write(true).create_new(true).open(&temp)?;
+        file.write_all(bytes)?;
+        // An existing file keeps its permissions
+        if let Ok(metadata) = std::fs::metadata(&dst) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()?;
+        std::fs::rename(&temp, &dst)
+    })();
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_files_are_canonicalized_and_must_exist() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        let layout = dir.path().join("top.gds");
+        std::fs::write(&layout, b"").unwrap();
+        let canonical = std::fs::canonicalize(&layout).unwrap().to_string_lossy().to_string();
+
+        let dotted = dir.path().join("sub").join("..").join("top.gds");
+        assert_eq!(layout_file(&dotted.to_string_lossy()), Ok(canonical.clone()));
+        assert!(matches!(layout_file("top.gds"), Err(AppError::InvalidArgument { .. })));
+        assert!(matches!(
+            layout_file(&dir.path().join("sub").to_string_lossy()),
+            Err(AppError::InvalidArgument { .. })
+        ));
+        let missing = dir.path().join("sub").join("..").join("..").join("missing.gds");
+        assert!(matches!(layout_file(&missing.to_string_lossy()), Err(AppError::NotFound { .. })));
+
+        #[cfg(unix)]
+        {
+            let link = dir.path().join("link.gds");
+            std::os::unix::fs::symlink(&layout, &link).unwrap();
+            assert_eq!(layout_file(&link.to_string_lossy()), Ok(canonical));
+            // The setting is process-wide, so check the policy without it
+            assert!(is_refused_symlink(&link, false));
+            assert!(!is_refused_symlink(&link, true));
+            assert!(!is_refused_symlink(&layout, false));
+        }
+    }
+
+    #[test]
+    fn data_files_stay_inside_the_app_dir() {
+        let app_dir = tempfile::tempdir().unwrap();
+        let recent = data_file(app_dir.path(), "recent_files.json");
+        assert_eq!(recent, Ok(app_dir.path().join("recent_files.json")));
+        for name in ["../recent_files.json", "sub/recent_files.json", "/etc/passwd", ""] {
+            let rejected = data_file(app_dir.path(), name);
+            assert!(matches!(rejected, Err(AppError::InvalidArgument { .. })), "{:?}", name);
+        }
+
+        #[cfg(unix)]
+        {
+            let outside = tempfile::tempdir().unwrap();
+            let escaping = app_dir.path().join("layer_states.json");
+            std::os::unix::fs::symlink(outside.path().join("stolen.json"), &escaping).unwrap();
+            assert!(matches!(
+                data_file(app_dir.path(), "layer_states.json"),
+                Err(AppError::InvalidArgument { .. })
+            ));
+
+            std::fs::write(app_dir.path().join("real.json"), b"[]").unwrap();
+            std::os::unix::fs::symlink("real.json", app_dir.path().join("alias.json")).unwrap();
+            assert!(data_file(app_dir.path(), "alias.json").is_ok());
+        }
+    }
+
+    #[test]
+    fn atomic_writes_replace_the_file_or_leave_it_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let dst = dir.path().join("out.gds");
+        write_atomic(&dst, b"first").unwrap();
+        write_atomic(&dst, b"second").unwrap();
+        assert_eq!(std::fs::read(&dst).unwrap(), b"second");
+
+        // A failed write (here: into a missing directory) leaves nothing behind
+        assert!(write_atomic(dir.path().join("missing").join("out.gds"), b"x").is_err());
+        let names: Vec<_> =
+            std::fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(names, ["out.gds"]);
+
+        #[cfg(unix)]
+        {
+            let link = dir.path().join("link.gds");
+            std::os::unix::fs::symlink(&dst, &link).unwrap();
+            write_atomic(&link, b"third").unwrap();
+            assert!(std::fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+            assert_eq!(std::fs::read(&dst).unwrap(), b"third");
+        }
+    }
+
+    #[test]
+    fn paths_inside_the_project_root_are_stored_relative() {
+        let root = Path::new("/work/layouts");
+        let inside = root.join("chips").join("top.gds").to_string_lossy().to_string();
+        let stored = relative_to_root(Some(root), &inside);
+        assert_eq!(Path::new(&stored), Path::new("chips").join("top.gds"));
+        assert_eq!(resolve_in_root(Some(root), &stored), Path::new(&inside));
+        // Moving the project only changes the root the entry resolves against
+        let moved = Path::new("/archive/layouts");
+        assert_eq!(resolve_in_root(Some(moved), &stored), moved.join("chips").join("top.gds"));
+
+        assert_eq!(relative_to_root(Some(root), "/elsewhere/top.gds"), "/elsewhere/top.gds");
+        assert_eq!(relative_to_root(None, &inside), inside);
+        assert_eq!(resolve_in_root(None, "chips/top.gds"), Path::new("chips/top.gds"));
+        let outside = resolve_in_root(Some(root), "/elsewhere/top.gds");
+        assert_eq!(outside, Path::new("/elsewhere/top.gds"));
+    }
+}
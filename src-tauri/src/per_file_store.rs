@@ -0,0 +1,111 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use crate::error::AppError;
+use crate::paths::{app_data_file, layout_file, write_atomic};
+use crate::read_only::is_read_only;
+use crate::recent_files::normalize_path;
+use crate::settings::Settings;
+
+// Something remembered for each layout file, such as its layer styles or the
+// last view of it, in one JSON file in app data keyed by canonicalized path.
+// `what` names the contents in error messages, e.g. "layer states".
+pub(crate) struct PerFileStore<T> {
+    file: PathBuf,
+    what: &'static str,
+    entries: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned> PerFileStore<T> {
+    pub(crate) fn new(file: PathBuf, what: &'static str) -> Self {
+        PerFileStore { file, what, entries: PhantomData }
+    }
+
+    // The store named `name` in the app data dir
+    pub(crate) fn in_app_data(
+        app: &tauri::AppHandle,
+        name: &str,
+        what: &'static str,
+    ) -> Result<Self, AppError> {
+        Ok(Self::new(app_data_file(app, name)?, what))
+    }
+
+    // Every stored entry, treating a missing file as empty
+    pub(crate) fn read(&self) -> Result<BTreeMap<String, T>, AppError> {
+        if !self.file.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let contents = std::fs::read_to_string(&self.file)
+            .map_err(|e| AppError::io(&format!("Failed to read {}", self.what), e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| AppError::parse_error(format!("Failed to parse {}: {}", self.what, e)))
+    }
+
+    fn write(&self, entries: &BTreeMap<String, T>) -> Result<(), AppError> {
+        if let Some(dir) = self.file.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| AppError::io("Failed to create app data dir", e))?;
+        }
+        let contents = serde_json::to_string_pretty(entries)
+            .map_err(|e| AppError::io(&format!("Failed to serialize {}", self.what), e))?;
+        write_atomic(&self.file, contents.as_bytes())
+            .map_err(|e| AppError::io(&format!("Failed to save {}", self.what), e))
+    }
+
+    // Remember `entry` for the file at `path`, which must exist. Nothing is
+    // remembered for a file open read-only.
+    pub(crate) fn save(&self, settings: &Settings, path: &str, entry: T) -> Result<(), AppError> {
+        if is_read_only(settings, path) {
+            return Ok(());
+        }
+        let path = layout_file(path)?;
+        let mut entries = self.read()?;
+        entries.insert(path, entry);
+        self.write(&entries)
+    }
+
+    // Look up the entry for `path`, first dropping (and persisting the removal
+    // of) entries for files that no longer exist or aren't stored canonically
+    pub(crate) fn load(&self, path: &str) -> Result<Option<T>, AppError> {
+        let mut entries = self.read()?;
+        let count = entries.len();
+        entries.retain(|file, _| layout_file(file).as_ref() == Ok(file));
+        if entries.len() != count {
+            self.write(&entries)?;
+        }
+        Ok(entries.remove(&normalize_path(path)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::READ_ONLY_FILES_KEY;
+    use serde_json::Value;
+
+    #[test]
+    fn read_only_files_are_not_remembered() {
+        let dir = tempfile::tempdir().unwrap();
+        let store: PerFileStore<u32> = PerFileStore::new(dir.path().join("counts.json"), "counts");
+        let settings = Settings::load(dir.path());
+        let (open, locked) = (dir.path().join("open.gds"), dir.path().join("locked.gds"));
+        std::fs::write(&open, b"").unwrap();
+        std::fs::write(&locked, b"").unwrap();
+        let (open, locked) =
+            (open.to_string_lossy().to_string(), locked.to_string_lossy().to_string());
+        settings.set(READ_ONLY_FILES_KEY, Value::from(vec![normalize_path(&locked)]));
+
+        store.save(&settings, &open, 1).unwrap();
+        store.save(&settings, &locked, 2).unwrap();
+        assert_eq!(store.load(&open).unwrap(), Some(1));
+        assert_eq!(store.load(&locked).unwrap(), None);
+        assert_eq!(store.read().unwrap().len(), 1);
+        assert!(matches!(
+            store.save(&settings, "open.gds", 3),
+            Err(AppError::InvalidArgument { .. })
+        ));
+    }
+}
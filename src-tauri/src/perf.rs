@@ -0,0 +1,55 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::settings::{Settings, PERF_EVENTS_KEY};
+
+// Sent as `perf` each time a heavy command finishes successfully, while the
+// `perf_events` setting is `true`
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct PerfPayload {
+    pub command: &'static str,
+    pub path: String,
+    pub duration_ms: f64,
+}
+
+// Whether `perf` events were switched on; they are off by default
+fn enabled(settings: &Settings) -> bool {
+    settings.get(PERF_EVENTS_KEY) == Some(Value::Bool(true))
+}
+
+// Emit a `perf` event for `command` on `path`, which started at `started`.
+// Called once per command rather than from inside its loops, and checks the
+// setting only then, so timing costs nothing while it is off.
+pub fn report(app: &AppHandle, command: &'static str, path: &str, started: Instant) {
+    let Some(settings) = app.try_state::<Settings>() else {
+        return;
+    };
+    if !enabled(&settings) {
+        return;
+    }
+    let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+    let payload = PerfPayload { command, path: path.to_string(), duration_ms };
+    if let Err(e) = app.emit("perf", payload) {
+        log::warn!("Failed to emit perf event: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perf_events_are_off_unless_switched_on() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = Settings::load(dir.path());
+        assert!(!enabled(&settings));
+
+        settings.set(PERF_EVENTS_KEY, Value::String("yes".to_string()));
+        assert!(!enabled(&settings));
+        settings.set(PERF_EVENTS_KEY, Value::Bool(true));
+        assert!(enabled(&settings));
+    }
+}
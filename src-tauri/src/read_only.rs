@@ -0,0 +1,115 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::AppError;
+use crate::recent_files::normalize_path;
+use crate::settings::{Settings, READ_ONLY_FILES_KEY, READ_ONLY_KEY};
+
+// Whether writes are refused for a file, and whether that is because read-only
+// mode is on for every file rather than for this one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct ReadOnlyStatus {
+    pub read_only: bool,
+    pub global: bool,
+}
+
+// The canonicalized paths of the files opened read-only one by one
+fn read_only_files(settings: &Settings) -> Vec<String> {
+    match settings.get(READ_ONLY_FILES_KEY) {
+        Some(Value::Array(files)) => {
+            files.iter().filter_map(|file| file.as_str().map(str::to_string)).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+// Whether `path` may not be written to or have state remembered for it, either
+// on its own or because read-only mode is on for every file
+pub fn is_read_only(settings: &Settings, path: &str) -> bool {
+    settings.read_only() || read_only_files(settings).contains(&normalize_path(path))
+}
+
+// Refuse a write that reads or writes any of `paths` while one of them is
+// read-only, or any write at all while read-only mode is on for every file
+pub fn ensure_writable(settings: &Settings, paths: &[&str]) -> Result<(), AppError> {
+    if settings.read_only() {
+        return Err(AppError::ReadOnly { path: None });
+    }
+    let files = read_only_files(settings);
+    match paths.iter().find(|path| files.contains(&normalize_path(path))) {
+        Some(path) => Err(AppError::ReadOnly { path: Some(path.to_string()) }),
+        None => Ok(()),
+    }
+}
+
+fn status(settings: &Settings, path: Option<&str>) -> ReadOnlyStatus {
+    let global = settings.read_only();
+    let read_only = global || path.is_some_and(|path| is_read_only(settings, path));
+    ReadOnlyStatus { read_only, global }
+}
+
+// Command to open a file read-only, or with `None` every file, so exports,
+// edits and transforms touching it are refused and nothing is remembered for
+// it, or to allow writes again with `read_only: false`
+#[tauri::command]
+pub async fn set_read_only(
+    path: Option<String>,
+    read_only: bool,
+    settings: tauri::State<'_, Settings>,
+) -> Result<ReadOnlyStatus, AppError> {
+    let Some(path) = path else {
+        settings.set(READ_ONLY_KEY, Value::Bool(read_only));
+        return Ok(status(&settings, None));
+    };
+    let normalized = normalize_path(&path);
+    let mut files = read_only_files(&settings);
+    files.retain(|file| *file != normalized);
+    if read_only {
+        files.push(normalized);
+    }
+    let value = if files.is_empty() { Value::Null } else { Value::from(files) };
+    settings.set(READ_ONLY_FILES_KEY, value);
+    Ok(status(&settings, Some(&path)))
+}
+
+// Command to tell whether a file, or with `None` every file, is open
+// read-only, so the UI can disable its edit and export actions
+#[tauri::command]
+pub async fn get_read_only_status(
+    path: Option<String>,
+    settings: tauri::State<'_, Settings>,
+) -> Result<ReadOnlyStatus, AppError> {
+    Ok(status(&settings, path.as_deref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_are_refused_for_read_only_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let layout = dir.path().join("top.gds");
+        std::fs::write(&layout, b"").unwrap();
+        let layout = layout.to_string_lossy().to_string();
+        let settings = Settings::load(dir.path());
+        assert!(ensure_writable(&settings, &[&layout, "/out.gds"]).is_ok());
+
+        settings.set(READ_ONLY_FILES_KEY, Value::from(vec![normalize_path(&layout)]));
+        assert!(is_read_only(&settings, &layout));
+        assert!(!is_read_only(&settings, "/other.gds"));
+        assert_eq!(
+            ensure_writable(&settings, &["/other.gds", &layout]),
+            Err(AppError::ReadOnly { path: Some(layout.clone()) })
+        );
+        assert!(ensure_writable(&settings, &["/other.gds"]).is_ok());
+
+        settings.set(READ_ONLY_KEY, Value::Bool(true));
+        assert_eq!(
+            ensure_writable(&settings, &["/other.gds"]),
+            Err(AppError::ReadOnly { path: None })
+        );
+        assert_eq!(status(&settings, None), ReadOnlyStatus { read_only: true, global: true });
+    }
+}
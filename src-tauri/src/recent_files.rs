@@ -0,0 +1,312 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+
+use crate::debounce::DebouncedWrite;
+use crate::error::AppError;
+use crate::paths::{
+    app_data_file, layout_file, relative_to_root, resolve_in_root, write_atomic,
+};
+use crate::read_only::is_read_only;
+use crate::settings::Settings;
+use crate::sync::lock;
+
+// Number of entries kept when the caller doesn't supply a limit
+pub(crate) const DEFAULT_RECENT_FILES_LIMIT: usize = 10;
+
+const RECENT_FILES_NAME: &str = "recent_files.json";
+
+fn recent_files_path(app: &tauri::AppHandle) -> std::result::Result<PathBuf, AppError> {
+    app_data_file(app, RECENT_FILES_NAME)
+}
+
+fn project_root(app: &tauri::AppHandle) -> Option<PathBuf> {
+    app.try_state::<Settings>()?.project_root()
+}
+
+// Canonicalize a path for deduplication, keeping it as given if it can't be resolved
+pub(crate) fn normalize_path(path: &str) -> String {
+    std::fs::canonicalize(path)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+// Read the stored list, treating a missing file as empty
+fn read_list(list_path: &Path) -> std::result::Result<Vec<String>, AppError> {
+    if !list_path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = std::fs::read_to_string(list_path)
+        .map_err(|e| AppError::io("Failed to read recent files", e))?;
+    serde_json::from_str(&contents)
+        .map_err(|e| AppError::parse_error(format!("Failed to parse recent files: {}", e)))
+}
+
+fn write_list(list_path: &Path, files: &[String]) -> std::result::Result<(), AppError> {
+    if let Some(dir) = list_path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| AppError::io("Failed to create app data dir", e))?;
+    }
+    let contents = serde_json::to_string_pretty(files)
+        .map_err(|e| AppError::io("Failed to serialize recent files", e))?;
+    write_atomic(list_path, contents.as_bytes())
+        .map_err(|e| AppError::io("Failed to save recent files", e))
+}
+
+// Move `path` to the front of the list, deduplicating by canonicalized path and
+// keeping at most `limit` entries
+fn push_recent(files: &mut Vec<String>, path: &str, limit: usize) {
+    let normalized = normalize_path(path);
+    files.retain(|existing| normalize_path(existing) != normalized);
+    files.insert(0, normalized);
+    files.truncate(limit);
+}
+
+// Add `path`, which must be an existing file (see `layout_file`), to the
+// stored list. Entries inside the project `root` are stored relative to it;
+// returns the list with every entry that can be resolved, as absolute paths.
+fn add_to_list(
+    stored: &mut Vec<String>,
+    path: &str,
+    limit: usize,
+    root: Option<&Path>,
+) -> std::result::Result<Vec<String>, AppError> {
+    let path = layout_file(&resolve_in_root(root, path).to_string_lossy())?;
+    let mut files: Vec<String> = stored
+        .iter()
+        .map(|entry| resolve_in_root(root, entry).to_string_lossy().to_string())
+        .collect();
+    push_recent(&mut files, &path, limit);
+    *stored = files.iter().map(|file| relative_to_root(root, file)).collect();
+    files.retain(|file| Path::new(file).is_absolute());
+    Ok(files)
+}
+
+// Drop entries of the stored list that are no longer (or never were) valid
+// layout files, resolving the rest against the project `root` to their
+// canonical paths. Relative entries are kept, but not listed, while no root is
+// set, so they come back once the moved project's root is set again.
+fn prune(stored: &mut Vec<String>, root: Option<&Path>) -> Vec<String> {
+    let mut existing: Vec<String> = Vec::new();
+    let mut kept = Vec::new();
+    for entry in stored.iter() {
+        let resolved = resolve_in_root(root, entry);
+        if resolved.is_relative() {
+            kept.push(entry.clone());
+            continue;
+        }
+        let Ok(file) = layout_file(&resolved.to_string_lossy()) else {
+            continue;
+        };
+        if !existing.contains(&file) {
+            kept.push(relative_to_root(root, &file));
+            existing.push(file);
+        }
+    }
+    *stored = kept;
+    existing
+}
+
+// The list as read from its file, in stored form
+struct StoredList {
+    file: PathBuf,
+    entries: Vec<String>,
+}
+
+// State to hold the recent files list in memory. It is read from
+// `recent_files.json` on first use, and changes are written back shortly after
+// they are made (see `DebouncedWrite`).
+pub struct RecentFiles {
+    list: Arc<Mutex<Option<StoredList>>>,
+    writes: DebouncedWrite,
+}
+
+impl Default for RecentFiles {
+    fn default() -> Self {
+        let list: Arc<Mutex<Option<StoredList>>> = Arc::default();
+        let stored = Arc::clone(&list);
+        let writes = DebouncedWrite::new(move || match &*lock(&stored, "Recent files") {
+            Some(list) => write_list(&list.file, &list.entries),
+            None => Ok(()),
+        });
+        RecentFiles { list, writes }
+    }
+}
+
+impl RecentFiles {
+    // Run `update` on the stored list, reading it from `file` if this is its
+    // first use, and schedule a write if the list changed
+    fn update<R>(
+        &self,
+        file: &Path,
+        update: impl FnOnce(&mut Vec<String>) -> std::result::Result<R, AppError>,
+    ) -> std::result::Result<R, AppError> {
+        let mut list = lock(&self.list, "Recent files");
+        let loaded = match list.take() {
+            Some(loaded) => loaded,
+            None => StoredList { file: file.to_path_buf(), entries: read_list(file)? },
+        };
+        let stored = list.insert(loaded);
+        let before = stored.entries.clone();
+        let result = update(&mut stored.entries);
+        let changed = stored.entries != before;
+        drop(list);
+        if changed {
+            self.writes.changed();
+        }
+        result
+    }
+
+    fn add(
+        &self,
+        file: &Path,
+        path: &str,
+        limit: usize,
+        root: Option<&Path>,
+    ) -> std::result::Result<Vec<String>, AppError> {
+        self.update(file, |stored| add_to_list(stored, path, limit, root))
+    }
+
+    fn list(&self, file: &Path, root: Option<&Path>) -> std::result::Result<Vec<String>, AppError> {
+        self.update(file, |stored| Ok(prune(stored, root)))
+    }
+
+    // Empty the list, without reading a file that may be unreadable
+    fn clear(&self, file: &Path) {
+        let cleared = StoredList { file: file.to_path_buf(), entries: Vec::new() };
+        *lock(&self.list, "Recent files") = Some(cleared);
+        self.writes.changed();
+    }
+
+    // Write any changes not yet on disk, e.g. on exit
+    pub fn flush(&self) -> std::result::Result<(), AppError> {
+        self.writes.flush()
+    }
+}
+
+// The stored recent files list, pruned of files that no longer exist
+pub(crate) fn recent_files(app: &tauri::AppHandle) -> std::result::Result<Vec<String>, AppError> {
+    let file = recent_files_path(app)?;
+    app.state::<RecentFiles>().list(&file, project_root(app).as_deref())
+}
+
+// Add `path` to the front of the recent files list and refresh the menu,
+// returning the updated list. A file open read-only leaves the list as it is.
+pub(crate) fn remember_file(
+    app: &tauri::AppHandle,
+    path: &str,
+    limit: usize,
+) -> std::result::Result<Vec<String>, AppError> {
+    if app.try_state::<Settings>().is_some_and(|settings| is_read_only(&settings, path)) {
+        return recent_files(app);
+    }
+    let file = recent_files_path(app)?;
+    let root = project_root(app);
+    let files = app.state::<RecentFiles>().add(&file, path, limit, root.as_deref())?;
+    crate::menu::refresh_recent_files(app, &files);
+    Ok(files)
+}
+
+// Command to add a file to the front of the recent files list.
+// `limit` defaults to 10 entries. The path must be absolute, or relative to the
+// project root, and is stored canonicalized, with symlinks resolved, and
+// relative to the project root if it lies inside it.
+#[tauri::command]
+pub async fn add_recent_file(
+    path: String,
+    limit: Option<usize>,
+    app: tauri::AppHandle,
+) -> std::result::Result<Vec<String>, AppError> {
+    remember_file(&app, &path, limit.unwrap_or(DEFAULT_RECENT_FILES_LIMIT).max(1))
+}
+
+// Command to get the recent files list, most recent first. Entries whose files
+// no longer exist are pruned.
+#[tauri::command]
+pub async fn get_recent_files(app: tauri::AppHandle) -> std::result::Result<Vec<String>, AppError> {
+    recent_files(&app)
+}
+
+// Command to clear the recent files list
+#[tauri::command]
+pub async fn clear_recent_files(app: tauri::AppHandle) -> std::result::Result<(), AppError> {
+    app.state::<RecentFiles>().clear(&recent_files_path(&app)?);
+    crate::menu::refresh_recent_files(&app, &[]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn re_adding_moves_to_front_and_respects_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let list_path = dir.path().join(RECENT_FILES_NAME);
+        let files: Vec<String> = (0..3)
+            .map(|i| {
+                let path = dir.path().join(format!("cell{}.gds", i));
+                std::fs::write(&path, b"").unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+
+        let recent = RecentFiles::default();
+        for file in &files {
+            recent.add(&list_path, file, 2, None).unwrap();
+        }
+        let list = recent.add(&list_path, &files[1], 2, None).unwrap();
+
+        let expected = vec![normalize_path(&files[1]), normalize_path(&files[2])];
+        assert_eq!(list, expected);
+        // The adds are written together, on the timer or the next flush
+        recent.flush().unwrap();
+        assert_eq!(read_list(&list_path).unwrap(), expected);
+    }
+
+    #[test]
+    fn listing_prunes_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let list_path = dir.path().join(RECENT_FILES_NAME);
+        let kept = dir.path().join("kept.gds");
+        let gone = dir.path().join("gone.gds");
+        std::fs::write(&kept, b"").unwrap();
+        std::fs::write(&gone, b"").unwrap();
+
+        let recent = RecentFiles::default();
+        recent.add(&list_path, &kept.to_string_lossy(), 10, None).unwrap();
+        recent.add(&list_path, &gone.to_string_lossy(), 10, None).unwrap();
+        std::fs::remove_file(&gone).unwrap();
+
+        let expected = vec![normalize_path(&kept.to_string_lossy())];
+        assert_eq!(recent.list(&list_path, None).unwrap(), expected);
+        recent.flush().unwrap();
+        assert_eq!(read_list(&list_path).unwrap(), expected);
+
+        recent.clear(&list_path);
+        recent.flush().unwrap();
+        assert!(RecentFiles::default().list(&list_path, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn entries_inside_the_project_root_survive_a_move() {
+        let dir = tempfile::tempdir().unwrap();
+        let list_path = dir.path().join(RECENT_FILES_NAME);
+        let root = std::fs::canonicalize(dir.path()).unwrap().join("layouts");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(root.join("top.gds"), b"").unwrap();
+
+        let recent = RecentFiles::default();
+        let list = recent.add(&list_path, "top.gds", 10, Some(&root)).unwrap();
+        assert_eq!(list, vec![root.join("top.gds").to_string_lossy().to_string()]);
+        recent.flush().unwrap();
+        assert_eq!(read_list(&list_path).unwrap(), vec!["top.gds".to_string()]);
+
+        let moved = root.with_file_name("moved");
+        std::fs::rename(&root, &moved).unwrap();
+        // Without a root the entry can't be resolved, but isn't pruned either
+        assert!(recent.list(&list_path, None).unwrap().is_empty());
+        let listed = recent.list(&list_path, Some(&moved)).unwrap();
+        assert_eq!(listed, vec![moved.join("top.gds").to_string_lossy().to_string()]);
+    }
+}
@@ -0,0 +1,131 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::error::AppError;
+
+// A command that shows a file in the system file manager. In its arguments
+// `{path}` stands for the file, `{uri}` for its `file://` URI and `{dir}` for
+// the folder holding it.
+struct RevealTool {
+    program: &'static str,
+    args: &'static [&'static str],
+    // Explorer exits with 1 even when it opened the window, so its status is
+    // meaningless
+    check_status: bool,
+}
+
+// Tools tried in order until one is installed and succeeds
+#[cfg(target_os = "macos")]
+const REVEAL_TOOLS: &[RevealTool] =
+    &[RevealTool { program: "open", args: &["-R", "{path}"], check_status: true }];
+
+#[cfg(windows)]
+const REVEAL_TOOLS: &[RevealTool] =
+    &[RevealTool { program: "explorer", args: &["/select,{path}"], check_status: false }];
+
+// File managers that implement the freedesktop FileManager1 interface select
+// the file; otherwise the folder is opened without a selection
+#[cfg(not(any(target_os = "macos", windows)))]
+const REVEAL_TOOLS: &[RevealTool] = &[
+    RevealTool {
+        program: "dbus-send",
+        args: &[
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.FileManager1",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+            "array:string:{uri}",
+            "string:",
+        ],
+        check_status: true,
+    },
+    RevealTool { program: "xdg-open", args: &["{dir}"], check_status: true },
+];
+
+// `file://` URI of an absolute path, percent-encoding every byte outside the
+// unreserved set
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+// Run the first of `tools` that is installed on `file`
+fn run_reveal_tool(tools: &[RevealTool], file: &Path) -> Result<(), AppError> {
+    let path = file.to_string_lossy();
+    let uri = file_uri(file);
+    let dir = file.parent().unwrap_or(file).to_string_lossy();
+    let mut failures = Vec::new();
+    for tool in tools {
+        let args = tool.args.iter().map(|arg| {
+            arg.replace("{path}", &path).replace("{uri}", &uri).replace("{dir}", &dir)
+        });
+        let status = Command::new(tool.program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        match status {
+            Ok(status) if status.success() || !tool.check_status => return Ok(()),
+            Ok(status) => failures.push(format!("{} exited with {}", tool.program, status)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                failures.push(format!("{} is not installed", tool.program))
+            }
+            Err(e) => failures.push(format!("{} failed to start: {}", tool.program, e)),
+        }
+    }
+    Err(AppError::io("Failed to open the file manager", failures.join("; ")))
+}
+
+// Command to show a file in the system file manager, selected in its folder
+// where the platform allows it
+#[tauri::command]
+pub async fn reveal_in_file_manager(path: String) -> Result<(), AppError> {
+    if !Path::new(&path).is_absolute() {
+        return Err(AppError::invalid_argument(format!(
+            "Expected an absolute file path, got {:?}",
+            path
+        )));
+    }
+    let file = std::fs::canonicalize(&path).map_err(|e| AppError::for_path(&path, e))?;
+    run_reveal_tool(REVEAL_TOOLS, &file)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    const MISSING: RevealTool =
+        RevealTool { program: "gdsjam-no-such-tool", args: &[], check_status: true };
+    const FAILING: RevealTool = RevealTool { program: "false", args: &[], check_status: true };
+    // Succeeds only if the file and its folder were filled in
+    const WORKING: RevealTool = RevealTool {
+        program: "test",
+        args: &["-f", "{path}", "-a", "-d", "{dir}"],
+        check_status: true,
+    };
+
+    #[test]
+    fn tools_are_tried_in_order_with_the_file_filled_in() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("my top.gds");
+        std::fs::write(&file, b"gds").unwrap();
+
+        assert_eq!(run_reveal_tool(&[MISSING, WORKING], &file), Ok(()));
+        let Err(AppError::Io { message }) = run_reveal_tool(&[MISSING, FAILING], &file) else {
+            panic!("expected an I/O error");
+        };
+        assert!(message.contains("gdsjam-no-such-tool is not installed"), "{}", message);
+        assert!(message.contains("false exited with"), "{}", message);
+        assert_eq!(file_uri(Path::new("/tmp/my top#1.gds")), "file:///tmp/my%20top%231.gds");
+    }
+}
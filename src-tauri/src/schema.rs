@@ -0,0 +1,217 @@
+use schemars::gen::SchemaSettings;
+use serde_json::{json, Value};
+
+use crate::dxf::{DxfDrawing, DxfError};
+use crate::error::AppError;
+use crate::export::contact_sheet::ContactSheet;
+use crate::export::jobs::{ExportCompletePayload, ExportProgressPayload};
+use crate::export::layer_map::LayerMapFormat;
+use crate::export::measurements::Measurement;
+use crate::format::{FileFormat, SupportedFormat};
+use crate::gds::angles::{ClassifiedElement, GeometryClass, GeometryClasses};
+use crate::gds::aref::InstancePlacement;
+use crate::gds::bbox::Bbox;
+use crate::gds::boolean::LayerCoverage;
+use crate::gds::buffer::LayerBuffer;
+use crate::gds::changes::{ChangedLayerBuffer, ChangedRegions};
+use crate::gds::cross_section::LayerSection;
+use crate::gds::diff::GdsDiff;
+use crate::gds::dump::RecordSummary;
+use crate::gds::duplicates::DuplicatePair;
+use crate::gds::elements::ElementSummary;
+use crate::gds::flatten::{LayerPolygon, PolygonKind};
+use crate::gds::freshness::FileFreshness;
+use crate::gds::hierarchy::{
+    CellGraph, CellSummary, EmptyCell, HierarchyFormat, HierarchyNode, MissingReference, OrphanCell,
+};
+use crate::gds::hit_test::ElementRef;
+use crate::gds::instancing::InstancedGeometry;
+use crate::gds::interaction::LayerPair;
+use crate::gds::jobs::{
+    BatchProgressPayload, ParseCompletePayload, ParseOutcome, ParseProgressPayload,
+    WarningEmittedPayload,
+};
+use crate::gds::labels::Label;
+use crate::gds::layers::{LayerComparison, LayerInfo};
+use crate::gds::locate::CellLocation;
+use crate::gds::offgrid::{OffGridReport, OffGridVertex};
+use crate::gds::parser::ParseMode;
+use crate::gds::path_lengths::PathLengths;
+use crate::gds::problems::ProblemPolygon;
+use crate::gds::properties::ElementProperties;
+use crate::gds::rects::LayerRects;
+use crate::gds::region::Polygon;
+use crate::gds::render_plan::RenderPlan;
+use crate::gds::snap::SnapResult;
+use crate::gds::stats::{GdsStats, LayerHistogram};
+use crate::gds::streaming::{GeometryChunkPayload, GeometryDonePayload};
+use crate::gds::tiles::{CellTile, TilePolygon};
+use crate::gds::transform::Transform;
+use crate::gds::units::{CoordConvention, LengthUnit};
+use crate::gds::validate::ValidationReport;
+use crate::gds::{GdsError, GdsLibrary};
+use crate::geometry::boolean::BooleanOp;
+use crate::geometry::measure::{OrientedBox, PolygonMeasurement};
+use crate::layer_state::{LayerStackEntry, LayerStyle};
+use crate::logging::FrontendLogLevel;
+use crate::perf::PerfPayload;
+use crate::read_only::ReadOnlyStatus;
+use crate::session::{RestoredSession, Session};
+use crate::view_state::ViewState;
+use crate::watcher::{
+    FileChangedPayload, FileCreatedPayload, FileDeletedPayload, WatchErrorPayload, WatchOutcome,
+};
+use crate::window_state::WindowGeometry;
+use crate::{BackendInfo, OpenResult};
+
+// JSON Schema (draft 7) of every type a command takes or returns and every
+// event payload, under `definitions` by type name. The types they contain come
+// along. Definitions and properties are sorted by name, so the output only
+// changes when a type does.
+pub fn api_schema() -> Value {
+    let mut generator = SchemaSettings::draft07().into_generator();
+    macro_rules! add {
+        ($($ty:ty),* $(,)?) => {
+            $(generator.subschema_for::<$ty>();)*
+        };
+    }
+    add!(
+        // Command results
+        BackendInfo,
+        OpenResult,
+        GdsLibrary,
+        GdsStats,
+        LayerHistogram,
+        LayerInfo,
+        LayerComparison,
+        Bbox,
+        CellGraph,
+        HierarchyNode,
+        OrphanCell,
+        CellSummary,
+        EmptyCell,
+        MissingReference,
+        LayerPolygon,
+        LayerRects,
+        LayerBuffer,
+        Label,
+        ElementProperties,
+        ElementSummary,
+        ElementRef,
+        FileFreshness,
+        ProblemPolygon,
+        DuplicatePair,
+        CellTile,
+        TilePolygon,
+        InstancePlacement,
+        CellLocation,
+        InstancedGeometry,
+        RenderPlan,
+        Transform,
+        LayerCoverage,
+        LayerPair,
+        Polygon,
+        ChangedRegions,
+        ChangedLayerBuffer,
+        GdsDiff,
+        RecordSummary,
+        ValidationReport,
+        SnapResult,
+        LayerSection,
+        PolygonMeasurement,
+        PathLengths,
+        OffGridReport,
+        OffGridVertex,
+        GeometryClasses,
+        GeometryClass,
+        ClassifiedElement,
+        OrientedBox,
+        DxfDrawing,
+        FileFormat,
+        SupportedFormat,
+        WatchOutcome,
+        ParseOutcome,
+        LayerStackEntry,
+        LayerStyle,
+        WindowGeometry,
+        RestoredSession,
+        ViewState,
+        ReadOnlyStatus,
+        // Command arguments
+        Session,
+        Measurement,
+        LayerMapFormat,
+        ContactSheet,
+        ParseMode,
+        PolygonKind,
+        HierarchyFormat,
+        LengthUnit,
+        CoordConvention,
+        BooleanOp,
+        FrontendLogLevel,
+        // Event payloads
+        ParseProgressPayload,
+        ParseCompletePayload,
+        WarningEmittedPayload,
+        BatchProgressPayload,
+        ExportProgressPayload,
+        ExportCompletePayload,
+        GeometryChunkPayload,
+        GeometryDonePayload,
+        FileChangedPayload,
+        FileCreatedPayload,
+        FileDeletedPayload,
+        WatchErrorPayload,
+        PerfPayload,
+        // Errors
+        AppError,
+        GdsError,
+        DxfError,
+    );
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "gdsjam command API",
+        "definitions": generator.take_definitions(),
+    })
+}
+
+// Command to get `api_schema`, the same as the checked-in `schema/api.json`
+#[tauri::command]
+pub async fn get_api_schema() -> Result<Value, AppError> {
+    Ok(api_schema())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    // Where the schema is checked in, relative to the crate root, for the
+    // frontend to generate its types from
+    const SCHEMA_PATH: &str = "schema/api.json";
+
+    // Fails when a type changed without the checked-in schema; running the
+    // tests with GDSJAM_UPDATE_SCHEMA=1 rewrites it
+    #[test]
+    fn checked_in_schema_is_up_to_date() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(SCHEMA_PATH);
+        let mut generated = serde_json::to_string_pretty(&api_schema()).unwrap();
+        generated.push('\n');
+        if std::env::var_os("GDSJAM_UPDATE_SCHEMA").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, &generated).unwrap();
+        }
+        let checked_in = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            checked_in == generated,
+            "{} is out of date; run the tests with GDSJAM_UPDATE_SCHEMA=1 to regenerate it",
+            SCHEMA_PATH
+        );
+
+        let schema = api_schema();
+        let definitions = schema["definitions"].as_object().unwrap();
+        for name in ["GdsLibrary", "GdsElement", "LayerInfo", "AppError"] {
+            assert!(definitions.contains_key(name), "{} is missing", name);
+        }
+    }
+}
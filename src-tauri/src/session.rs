@@ -0,0 +1,209 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+use crate::error::AppError;
+use crate::layer_state::{check_layer_keys, LayerState};
+use crate::paths::{app_data_file, layout_file, relative_to_root, resolve_in_root, write_atomic};
+use crate::settings::Settings;
+
+const SESSION_NAME: &str = "session.json";
+
+// Where a tab is looking: the layout point at the center of the canvas, in user
+// units, and the zoom factor
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SessionView {
+    pub center_x: f64,
+    pub center_y: f64,
+    pub zoom: f64,
+}
+
+// One open file with the cell it shows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SessionTab {
+    pub path: String,
+    pub cell: Option<String>,
+    pub view: Option<SessionView>,
+    pub layers: Option<LayerState>,
+}
+
+// The open files in tab order, and the index of the active one
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Session {
+    pub tabs: Vec<SessionTab>,
+    pub active: Option<usize>,
+}
+
+// A session as restored, and the paths of the tabs left out of it because
+// their files no longer exist
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct RestoredSession {
+    pub session: Session,
+    pub dropped: Vec<String>,
+}
+
+fn session_path(app: &tauri::AppHandle) -> std::result::Result<PathBuf, AppError> {
+    app_data_file(app, SESSION_NAME)
+}
+
+fn project_root(app: &tauri::AppHandle) -> Option<PathBuf> {
+    app.try_state::<Settings>()?.project_root()
+}
+
+// Fail with `InvalidArgument` for a view of the file at `path` that can't be
+// shown: coordinates that aren't finite or a zoom that isn't positive
+pub(crate) fn check_view(path: &str, view: &SessionView) -> std::result::Result<(), AppError> {
+    let finite = view.center_x.is_finite() && view.center_y.is_finite();
+    if !(finite && view.zoom.is_finite() && view.zoom > 0.0) {
+        return Err(AppError::invalid_argument(format!("Invalid view for {}: {:?}", path, view)));
+    }
+    Ok(())
+}
+
+// Fail with `InvalidArgument` for an active tab that doesn't exist, a view that
+// can't be shown or a layer state with bad keys
+fn check_session(session: &Session) -> std::result::Result<(), AppError> {
+    if let Some(active) = session.active.filter(|active| *active >= session.tabs.len()) {
+        return Err(AppError::invalid_argument(format!(
+            "Active tab {} is out of range for {} tabs",
+            active,
+            session.tabs.len()
+        )));
+    }
+    for tab in &session.tabs {
+        if let Some(view) = &tab.view {
+            check_view(&tab.path, view)?;
+        }
+        if let Some(layers) = &tab.layers {
+            check_layer_keys(layers)?;
+        }
+    }
+    Ok(())
+}
+
+// Read the stored session, `None` if there is none
+fn read_session(path: &Path) -> std::result::Result<Option<Session>, AppError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| AppError::io("Failed to read session", e))?;
+    serde_json::from_str(&contents)
+        .map(Some)
+        .map_err(|e| AppError::parse_error(format!("Failed to parse session: {}", e)))
+}
+
+// Store `session` with each file canonicalized, and relative to the project
+// `root` when inside it, so a moved project's session still resolves
+fn save(
+    path: &Path,
+    root: Option<&Path>,
+    mut session: Session,
+) -> std::result::Result<(), AppError> {
+    check_session(&session)?;
+    for tab in &mut session.tabs {
+        let file = layout_file(&resolve_in_root(root, &tab.path).to_string_lossy())?;
+        tab.path = relative_to_root(root, &file);
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| AppError::io("Failed to create app data dir", e))?;
+    }
+    let contents = serde_json::to_string_pretty(&session)
+        .map_err(|e| AppError::io("Failed to serialize session", e))?;
+    write_atomic(path, contents.as_bytes()).map_err(|e| AppError::io("Failed to save session", e))
+}
+
+// The stored session with its paths resolved against the project `root`,
+// leaving out tabs whose files no longer exist. The active tab keeps being
+// active if it was kept. The stored session is left as it is, so files on a
+// drive that is only unmounted come back with it.
+fn load(
+    path: &Path,
+    root: Option<&Path>,
+) -> std::result::Result<Option<RestoredSession>, AppError> {
+    let Some(stored) = read_session(path)? else {
+        return Ok(None);
+    };
+    let mut session = Session::default();
+    let mut dropped = Vec::new();
+    for (index, mut tab) in stored.tabs.into_iter().enumerate() {
+        let resolved = resolve_in_root(root, &tab.path).to_string_lossy().to_string();
+        let Ok(file) = layout_file(&resolved) else {
+            dropped.push(resolved);
+            continue;
+        };
+        if stored.active == Some(index) {
+            session.active = Some(session.tabs.len());
+        }
+        tab.path = file;
+        session.tabs.push(tab);
+    }
+    Ok(Some(RestoredSession { session, dropped }))
+}
+
+// Command to remember the open files, with the cell, view and layer setup of
+// each, in `session.json` in app data, replacing the previous session
+#[tauri::command]
+pub async fn save_session(
+    session: Session,
+    app: tauri::AppHandle,
+) -> std::result::Result<(), AppError> {
+    save(&session_path(&app)?, project_root(&app).as_deref(), session)
+}
+
+// Command to get the session saved last, without the files that are gone;
+// `None` if none was saved
+#[tauri::command]
+pub async fn load_session(
+    app: tauri::AppHandle,
+) -> std::result::Result<Option<RestoredSession>, AppError> {
+    load(&session_path(&app)?, project_root(&app).as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer_state::LayerStyle;
+
+    fn tab(path: &Path, cell: &str) -> SessionTab {
+        SessionTab {
+            path: path.to_string_lossy().to_string(),
+            cell: Some(cell.to_string()),
+            view: Some(SessionView { center_x: 1.5, center_y: -2.0, zoom: 4.0 }),
+            layers: Some(LayerState::from([(
+                "1/0".to_string(),
+                LayerStyle { visible: false, color: "#ff0000".to_string(), name: None },
+            )])),
+        }
+    }
+
+    #[test]
+    fn sessions_round_trip_without_the_files_that_are_gone() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let session_file = root.join(SESSION_NAME);
+        let (gone, kept) = (root.join("gone.gds"), root.join("kept.gds"));
+        std::fs::write(&gone, b"").unwrap();
+        std::fs::write(&kept, b"").unwrap();
+        let session = Session { tabs: vec![tab(&gone, "A"), tab(&kept, "B")], active: Some(1) };
+
+        assert_eq!(load(&session_file, Some(&root)).unwrap(), None);
+        save(&session_file, Some(&root), session.clone()).unwrap();
+        // Files inside the project root are stored relative to it
+        let stored = read_session(&session_file).unwrap().unwrap();
+        assert_eq!(stored.tabs[1].path, "kept.gds");
+
+        std::fs::remove_file(&gone).unwrap();
+        let restored = load(&session_file, Some(&root)).unwrap().unwrap();
+        assert_eq!(restored.dropped, [gone.to_string_lossy()]);
+        assert_eq!(restored.session, Session { tabs: vec![tab(&kept, "B")], active: Some(0) });
+
+        let out_of_range = Session { tabs: vec![tab(&kept, "B")], active: Some(1) };
+        assert!(save(&session_file, None, out_of_range).is_err());
+        let mut flat = tab(&kept, "B");
+        flat.view = Some(SessionView { center_x: 0.0, center_y: 0.0, zoom: 0.0 });
+        assert!(save(&session_file, None, Session { tabs: vec![flat], active: None }).is_err());
+    }
+}
@@ -0,0 +1,307 @@
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::debounce::DebouncedWrite;
+use crate::error::AppError;
+use crate::gds::flatten::{set_max_hierarchy_depth, HIERARCHY_DEPTH_LIMIT, MAX_HIERARCHY_DEPTH};
+use crate::paths::{data_file, resolve_in_root, set_follow_symlinks, write_atomic};
+use crate::sync::lock;
+
+const SETTINGS_NAME: &str = "settings.json";
+
+// Keys used by the backend itself
+pub const LAST_FILE_KEY: &str = "last_file";
+pub const LAST_DIR_KEY: &str = "last_dir";
+pub const PROJECT_ROOT_KEY: &str = "project_root";
+pub const LAYER_COLORS_KEY: &str = "layer_colors";
+pub const LAYER_ORDER_KEY: &str = "layer_order";
+pub const PERF_EVENTS_KEY: &str = "perf_events";
+pub const FOLLOW_SYMLINKS_KEY: &str = "follow_symlinks";
+pub const REDACT_PATHS_KEY: &str = "redact_paths";
+pub const READ_ONLY_KEY: &str = "read_only";
+pub const READ_ONLY_FILES_KEY: &str = "read_only_files";
+pub const MAX_HIERARCHY_DEPTH_KEY: &str = "max_hierarchy_depth";
+
+// Settings that only take `true`, `false` or `null`
+const BOOLEAN_KEYS: &[&str] = &[FOLLOW_SYMLINKS_KEY, REDACT_PATHS_KEY, READ_ONLY_KEY];
+
+// Plain-text files that held single settings before `settings.json`, moved
+// into it on first load
+const LEGACY_FILES: &[(&str, &str)] =
+    &[(LAST_FILE_KEY, "last_file.txt"), (LAST_DIR_KEY, "last_dir.txt")];
+
+// `value` as a `max_hierarchy_depth`: a whole number from 1 to
+// `HIERARCHY_DEPTH_LIMIT`
+fn hierarchy_depth(value: &Value) -> Option<usize> {
+    let depth = value.as_u64()?;
+    (1..=HIERARCHY_DEPTH_LIMIT as u64).contains(&depth).then_some(depth as usize)
+}
+
+// Settings loaded once from `settings.json` in the app data dir. Changes take
+// effect in memory at once and are written back shortly after (see
+// `DebouncedWrite`). Without a usable file they are only kept in memory.
+pub struct Settings {
+    values: Arc<Mutex<Map<String, Value>>>,
+    writes: Option<DebouncedWrite>,
+}
+
+fn read_values(file: &Path) -> Result<Map<String, Value>, AppError> {
+    if !file.exists() {
+        return Ok(Map::new());
+    }
+    let contents =
+        std::fs::read_to_string(file).map_err(|e| AppError::io("Failed to read settings", e))?;
+    serde_json::from_str(&contents)
+        .map_err(|e| AppError::parse_error(format!("Failed to parse settings: {}", e)))
+}
+
+fn write_values(file: &Path, values: &Map<String, Value>) -> Result<(), AppError> {
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| AppError::io("Failed to create app data dir", e))?;
+    }
+    let contents = serde_json::to_string_pretty(values)
+        .map_err(|e| AppError::io("Failed to serialize settings", e))?;
+    write_atomic(file, contents.as_bytes()).map_err(|e| AppError::io("Failed to save settings", e))
+}
+
+// Move the values of legacy files in `app_dir` into `values`, keeping values
+// already present. Returns whether anything was moved.
+fn migrate_legacy_files(app_dir: &Path, values: &mut Map<String, Value>) -> bool {
+    let mut migrated = false;
+    for (key, name) in LEGACY_FILES {
+        let legacy = app_dir.join(name);
+        let Ok(contents) = std::fs::read_to_string(&legacy) else {
+            continue;
+        };
+        let recorded = contents.trim();
+        if !recorded.is_empty() && !values.contains_key(*key) {
+            values.insert(key.to_string(), Value::String(recorded.to_string()));
+            migrated = true;
+        }
+        if let Err(e) = std::fs::remove_file(&legacy) {
+            log::warn!("Failed to remove migrated {}: {}", name, e);
+        }
+    }
+    migrated
+}
+
+impl Settings {
+    // Load the settings stored in `app_dir`. A file that can't be read or parsed
+    // is logged and replaced by empty settings on the next change; one that is
+    // a symlink leading out of `app_dir` is left alone.
+    pub fn load(app_dir: &Path) -> Self {
+        let file = match data_file(app_dir, SETTINGS_NAME) {
+            Ok(file) => file,
+            Err(e) => {
+                log::warn!("Keeping settings in memory only: {}", e);
+                return Settings { values: Arc::new(Mutex::new(Map::new())), writes: None };
+            }
+        };
+        let mut values = read_values(&file).unwrap_or_else(|e| {
+            log::warn!("Ignoring unreadable settings: {}", e);
+            Map::new()
+        });
+        let migrated = migrate_legacy_files(app_dir, &mut values);
+        let values = Arc::new(Mutex::new(values));
+        let stored = Arc::clone(&values);
+        let writes = DebouncedWrite::new(move || write_values(&file, &lock(&stored, "Settings")));
+        // The legacy files are gone, so their values are written right away
+        if migrated {
+            writes.changed();
+            if let Err(e) = writes.flush() {
+                log::warn!("Failed to save migrated settings: {}", e);
+            }
+        }
+        Settings { values, writes: Some(writes) }
+    }
+
+    pub fn get(&self, key: &str) -> Option<Value> {
+        lock(&self.values, "Settings").get(key).cloned()
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<String> {
+        self.get(key).and_then(|value| value.as_str().map(str::to_string))
+    }
+
+    // The project root paths are stored relative to, if one is set and it
+    // still exists
+    pub fn project_root(&self) -> Option<PathBuf> {
+        let root = PathBuf::from(self.get_str(PROJECT_ROOT_KEY)?);
+        root.is_dir().then_some(root)
+    }
+
+    // Whether user files that are symlinks are read; only an explicit `false`
+    // turns this off
+    pub fn follow_symlinks(&self) -> bool {
+        self.get(FOLLOW_SYMLINKS_KEY) != Some(Value::Bool(false))
+    }
+
+    // Whether diagnostics leave out the directories of paths; off unless set
+    // to `true`
+    pub fn redact_paths(&self) -> bool {
+        self.get(REDACT_PATHS_KEY) == Some(Value::Bool(true))
+    }
+
+    // Whether every file is open read-only; off unless set to `true`
+    pub fn read_only(&self) -> bool {
+        self.get(READ_ONLY_KEY) == Some(Value::Bool(true))
+    }
+
+    // How deep hierarchy walks follow references, `MAX_HIERARCHY_DEPTH`
+    // unless set to a valid depth
+    pub fn max_hierarchy_depth(&self) -> usize {
+        let depth = self.get(MAX_HIERARCHY_DEPTH_KEY);
+        depth.as_ref().and_then(hierarchy_depth).unwrap_or(MAX_HIERARCHY_DEPTH)
+    }
+
+    // Every setting, as currently in memory
+    pub fn values(&self) -> Map<String, Value> {
+        lock(&self.values, "Settings").clone()
+    }
+
+    // Store `value` under `key`, to be written to disk within `FLUSH_DELAY`.
+    // `null` removes the key.
+    pub fn set(&self, key: &str, value: Value) {
+        let mut values = lock(&self.values, "Settings");
+        if value.is_null() {
+            values.remove(key);
+        } else {
+            values.insert(key.to_string(), value);
+        }
+        drop(values);
+        if let Some(writes) = &self.writes {
+            writes.changed();
+        }
+    }
+
+    // Write any changes not yet on disk, e.g. on exit
+    pub fn flush(&self) -> Result<(), AppError> {
+        self.writes.as_ref().map_or(Ok(()), DebouncedWrite::flush)
+    }
+}
+
+// Command to read a setting, or `None` if it was never set
+#[tauri::command]
+pub async fn get_setting(
+    key: String,
+    settings: tauri::State<'_, Settings>,
+) -> Result<Option<Value>, AppError> {
+    Ok(settings.get(&key))
+}
+
+// Command to store a setting, removing it when `value` is null
+#[tauri::command]
+pub async fn set_setting(
+    key: String,
+    value: Value,
+    settings: tauri::State<'_, Settings>,
+) -> Result<(), AppError> {
+    if key.is_empty() {
+        return Err(AppError::invalid_argument("Setting key must not be empty"));
+    }
+    if BOOLEAN_KEYS.contains(&key.as_str()) && !(value.is_boolean() || value.is_null()) {
+        return Err(AppError::invalid_argument(format!(
+            "{} must be true, false or null, got {}",
+            key, value
+        )));
+    }
+    if key == MAX_HIERARCHY_DEPTH_KEY && !(value.is_null() || hierarchy_depth(&value).is_some()) {
+        return Err(AppError::invalid_argument(format!(
+            "{} must be a whole number from 1 to {} or null, got {}",
+            key, HIERARCHY_DEPTH_LIMIT, value
+        )));
+    }
+    settings.set(&key, value);
+    if key == FOLLOW_SYMLINKS_KEY {
+        set_follow_symlinks(settings.follow_symlinks());
+    }
+    if key == MAX_HIERARCHY_DEPTH_KEY {
+        set_max_hierarchy_depth(settings.max_hierarchy_depth());
+    }
+    Ok(())
+}
+
+// Command to set the project directory that recent files are stored relative
+// to and relative paths are resolved against, or to clear it with `None`.
+// Returns the canonicalized root.
+#[tauri::command]
+pub async fn set_project_root(
+    path: Option<String>,
+    settings: tauri::State<'_, Settings>,
+) -> Result<Option<String>, AppError> {
+    let Some(path) = path else {
+        settings.set(PROJECT_ROOT_KEY, Value::Null);
+        return Ok(None);
+    };
+    if !Path::new(&path).is_absolute() {
+        return Err(AppError::invalid_argument(format!(
+            "Expected an absolute directory path, got {:?}",
+            path
+        )));
+    }
+    let root = std::fs::canonicalize(&path).map_err(|e| AppError::for_path(&path, e))?;
+    if !root.is_dir() {
+        return Err(AppError::invalid_argument(format!("{} is not a directory", root.display())));
+    }
+    let root = root.to_string_lossy().to_string();
+    settings.set(PROJECT_ROOT_KEY, Value::String(root.clone()));
+    Ok(Some(root))
+}
+
+// Command to resolve a path relative to the project root to its canonical
+// absolute form. Absolute paths are only canonicalized; a relative path
+// without a project root is rejected.
+#[tauri::command]
+pub async fn resolve_in_project(
+    relative: String,
+    settings: tauri::State<'_, Settings>,
+) -> Result<String, AppError> {
+    let root = settings.project_root();
+    let path = resolve_in_root(root.as_deref(), &relative);
+    if path.is_relative() {
+        return Err(AppError::invalid_argument(format!(
+            "No project root set to resolve {:?} against",
+            relative
+        )));
+    }
+    let canonical = std::fs::canonicalize(&path).map_err(|e| AppError::for_path(&path, e))?;
+    Ok(canonical.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn settings_persist_and_legacy_files_are_migrated() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("last_file.txt"), "/chips/top.gds\n").unwrap();
+
+        let settings = Settings::load(dir.path());
+        assert_eq!(settings.get_str(LAST_FILE_KEY).as_deref(), Some("/chips/top.gds"));
+        assert!(!dir.path().join("last_file.txt").exists());
+
+        settings.set("theme", json!("dark"));
+        settings.set("debounce_ms", json!(250));
+        settings.set("debounce_ms", Value::Null);
+        settings.flush().unwrap();
+
+        let reloaded = Settings::load(dir.path());
+        assert_eq!(reloaded.get("theme"), Some(json!("dark")));
+        assert_eq!(reloaded.get("debounce_ms"), None);
+        assert_eq!(reloaded.get_str(LAST_FILE_KEY).as_deref(), Some("/chips/top.gds"));
+
+        assert!(reloaded.follow_symlinks());
+        reloaded.set(FOLLOW_SYMLINKS_KEY, json!(false));
+        assert!(!reloaded.follow_symlinks());
+
+        assert_eq!(reloaded.max_hierarchy_depth(), MAX_HIERARCHY_DEPTH);
+        reloaded.set(MAX_HIERARCHY_DEPTH_KEY, json!(500));
+        assert_eq!(reloaded.max_hierarchy_depth(), 500);
+        reloaded.set(MAX_HIERARCHY_DEPTH_KEY, json!(0));
+        assert_eq!(reloaded.max_hierarchy_depth(), MAX_HIERARCHY_DEPTH);
+    }
+}
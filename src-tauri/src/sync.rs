@@ -0,0 +1,34 @@
+use std::sync::{Mutex, MutexGuard};
+
+// Lock `mutex`, recovering the data if a thread panicked while holding it. The
+// state behind these locks is only changed in single steps (insert, remove,
+// take), so it is still usable, and carrying on beats failing every later
+// command that needs the lock. The poison is cleared so the warning about `what`
+// is logged once.
+pub fn lock<'a, T>(mutex: &'a Mutex<T>, what: &str) -> MutexGuard<'a, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        log::warn!("{} lock was poisoned by a panic; recovering", what);
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poisoned_locks_are_recovered() {
+        let mutex = Mutex::new(vec![1]);
+        let _ = std::panic::catch_unwind(|| {
+            let mut guard = mutex.lock().unwrap();
+            guard.push(2);
+            panic!("while holding the lock");
+        });
+        assert!(mutex.is_poisoned());
+
+        lock(&mutex, "Test").push(3);
+        assert_eq!(*lock(&mutex, "Test"), vec![1, 2, 3]);
+        assert!(!mutex.is_poisoned());
+    }
+}
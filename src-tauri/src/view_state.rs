@@ -0,0 +1,88 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::per_file_store::PerFileStore;
+use crate::session::{check_view, SessionView};
+use crate::settings::Settings;
+
+const VIEW_STATES_NAME: &str = "view_states.json";
+
+// Where the user last was in one file: the cell shown and the view of it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ViewState {
+    pub cell: Option<String>,
+    pub view: Option<SessionView>,
+}
+
+fn view_states(app: &tauri::AppHandle) -> std::result::Result<PerFileStore<ViewState>, AppError> {
+    PerFileStore::in_app_data(app, VIEW_STATES_NAME, "view states")
+}
+
+fn save_state(
+    store: &PerFileStore<ViewState>,
+    settings: &Settings,
+    path: &str,
+    state: ViewState,
+) -> std::result::Result<(), AppError> {
+    if let Some(view) = &state.view {
+        check_view(path, view)?;
+    }
+    store.save(settings, path, state)
+}
+
+// Command to remember the cell and view last shown for a file. Nothing is
+// remembered for a file open read-only.
+#[tauri::command]
+pub async fn save_view_state(
+    path: String,
+    state: ViewState,
+    app: tauri::AppHandle,
+    settings: tauri::State<'_, Settings>,
+) -> std::result::Result<(), AppError> {
+    save_state(&view_states(&app)?, &settings, &path, state)
+}
+
+// Command to get the cell and view last shown for a file, `None` if none was
+// saved
+#[tauri::command]
+pub async fn load_view_state(
+    path: String,
+    app: tauri::AppHandle,
+) -> std::result::Result<Option<ViewState>, AppError> {
+    view_states(&app)?.load(&path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_states_round_trip_per_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PerFileStore::new(dir.path().join(VIEW_STATES_NAME), "view states");
+        let settings = Settings::load(dir.path());
+        let (top, gone) = (dir.path().join("top.gds"), dir.path().join("gone.gds"));
+        std::fs::write(&top, b"").unwrap();
+        std::fs::write(&gone, b"").unwrap();
+        let (top, gone) = (top.to_string_lossy().to_string(), gone.to_string_lossy().to_string());
+
+        let view = SessionView { center_x: 12.5, center_y: -3.0, zoom: 8.0 };
+        let state = ViewState { cell: Some("CORE".to_string()), view: Some(view) };
+        assert_eq!(store.load(&top).unwrap(), None);
+        save_state(&store, &settings, &top, state.clone()).unwrap();
+        save_state(&store, &settings, &gone, ViewState { cell: None, view: None }).unwrap();
+        std::fs::remove_file(&gone).unwrap();
+
+        assert_eq!(store.load(&top).unwrap(), Some(state));
+        assert_eq!(store.load(&gone).unwrap(), None);
+        assert_eq!(store.read().unwrap().len(), 1);
+
+        let flat = SessionView { zoom: -1.0, ..view };
+        let bad = ViewState { cell: None, view: Some(flat) };
+        assert!(matches!(
+            save_state(&store, &settings, &top, bad),
+            Err(AppError::InvalidArgument { .. })
+        ));
+    }
+}
@@ -0,0 +1,52 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::fmt;
+
+// What kind of problem a warning is about, serialized as a fixed snake_case
+// code the frontend can filter on and translate, e.g. `missing_endstr`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningCode {
+    // The HEADER holds a stream version no known tool writes, or none at all
+    UnrecognizedVersion,
+    // A record that couldn't be decoded was skipped up to the next one
+    DamagedRecord,
+    // An element with a bad or missing field was dropped
+    InvalidElement,
+    MissingEndel,
+    MissingEndstr,
+    MissingEndlib,
+    // A known record where it doesn't belong, e.g. an element outside a structure
+    UnexpectedRecord,
+    // A record of a type the format doesn't define, skipped
+    UnknownRecord,
+    // A layer or datatype above 255, the most the GDSII spec allows
+    LayerOutOfRange,
+    // A structure without any elements or references
+    EmptyCell,
+    // DXF entities of a type the importer doesn't read
+    UnsupportedEntity,
+    // The last warning of a parse that had more than it keeps, counting the rest
+    WarningsTruncated,
+}
+
+// A problem in a file that could still be read. `offset` is the byte offset of
+// the record concerned, for the formats that have one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub message: String,
+    pub offset: Option<u64>,
+}
+
+impl Warning {
+    pub fn new(code: WarningCode, offset: Option<u64>, message: impl Into<String>) -> Self {
+        Warning { code, message: message.into(), offset }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
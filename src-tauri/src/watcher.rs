@@ -0,0 +1,1101 @@
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant, SystemTime};
+use schemars::JsonSchema;
+use serde::Serialize;
+use tauri::{Emitter, Manager, State};
+
+use crate::error::AppError;
+use crate::gds::cache::ParseCache;
+use crate::paths::resolve_in_root;
+use crate::settings::Settings;
+use crate::sync::lock;
+use notify_debouncer_full::{new_debouncer, notify::{event::ModifyKind, EventKind, RecursiveMode, Watcher}, DebounceEventResult, Debouncer, FileIdMap};
+
+type DebouncerType = Debouncer<notify_debouncer_full::notify::RecommendedWatcher, FileIdMap>;
+
+// Debounce interval used when the caller doesn't supply one, and the accepted range
+const DEFAULT_DEBOUNCE_MS: u64 = 500;
+const MIN_DEBOUNCE_MS: u64 = 50;
+const MAX_DEBOUNCE_MS: u64 = 10_000;
+
+// Quiet period used when the caller doesn't supply one, and its upper bound. A
+// change is only reported once the file's size has held still this long.
+const DEFAULT_QUIET_MS: u64 = 300;
+const MAX_QUIET_MS: u64 = 10_000;
+
+// Subdirectories a directory watch skips when the caller names none: version
+// control metadata and installed packages, which can be large and hold no layouts
+const DEFAULT_IGNORED_DIRS: &[&str] = &[".git", ".hg", ".svn", "node_modules"];
+
+// Longest a change waits for a file to stop growing before it is reported anyway,
+// and the shortest and longest intervals between size checks while waiting. The
+// longest also bounds how long stopping the watch waits for the check to notice.
+const MAX_SETTLE_WAIT: Duration = Duration::from_secs(30);
+const MIN_SETTLE_POLL: Duration = Duration::from_millis(10);
+const MAX_SETTLE_POLL: Duration = Duration::from_millis(100);
+
+// How often and how long to retry re-establishing a watch after the watched
+// file was replaced (e.g. by an atomic write-to-temp-then-rename save)
+const REWATCH_ATTEMPTS: u32 = 10;
+const REWATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+// A live watch on a single file or a directory tree. The debouncer sits in a shared slot so the
+// event callback can re-establish the watch after an atomic save replaced the
+// file; the callback only holds a weak reference, so stopping the watch frees it.
+// `stopped` is set first when stopping, so a callback waiting for the file to
+// settle gives up rather than holding up the stop.
+struct FileWatch {
+    debouncer: Arc<Mutex<Option<DebouncerType>>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl FileWatch {
+    // Stop the debouncer and wait for its event thread to exit. The slot lock is
+    // released before joining so an in-flight callback can finish.
+    fn stop(self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        let debouncer = lock(&self.debouncer, "Watch").take();
+        if let Some(debouncer) = debouncer {
+            debouncer.stop();
+        }
+    }
+}
+
+// State to hold one live watch per watched file or directory, keyed by
+// canonicalized path.
+// Removing an entry stops its debouncer, which tears down the OS-level watch.
+#[derive(Default)]
+pub struct WatchedFiles(Arc<Mutex<HashMap<PathBuf, FileWatch>>>);
+
+impl WatchedFiles {
+    pub fn is_watched(&self, path: &str) -> bool {
+        let path_buf = canonical_watch_path(path, None).unwrap_or_else(|_| PathBuf::from(path));
+        lock(&self.0, "Watcher state").contains_key(&path_buf)
+    }
+
+    // The watched paths, sorted
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = lock(&self.0, "Watcher state").keys().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    // Stop every watch, e.g. on exit, so no OS-level watch outlives the app.
+    // The watches are stopped after the state lock is released.
+    pub fn stop_all(&self) {
+        let watches: Vec<FileWatch> =
+            lock(&self.0, "Watcher state").drain().map(|(_, watch)| watch).collect();
+        for watch in watches {
+            watch.stop();
+        }
+    }
+}
+
+// Coarse classification of a file system event, as reported to the frontend
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FileChangeKind {
+    Modify,
+    Create,
+    Remove,
+}
+
+impl FileChangeKind {
+    fn from_event_kind(kind: &EventKind) -> Option<Self> {
+        match kind {
+            EventKind::Modify(_) => Some(FileChangeKind::Modify),
+            EventKind::Create(_) => Some(FileChangeKind::Create),
+            EventKind::Remove(_) => Some(FileChangeKind::Remove),
+            _ => None,
+        }
+    }
+}
+
+// What the OS-level watch is placed on. Watching the file itself is lighter, but
+// a tool that deletes the file and only later writes it again leaves nothing to
+// re-watch; watching the parent directory sees the new file being created.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchMode {
+    File,
+    ParentDir,
+}
+
+// Payload of the `file-changed` event
+#[derive(Clone, Debug, PartialEq, Serialize, JsonSchema)]
+pub struct FileChangedPayload {
+    pub path: String,
+    pub kind: FileChangeKind,
+}
+
+// Payload of the `watch-error` event: the watch on `path` reported an error
+// and may no longer see changes
+#[derive(Clone, Debug, PartialEq, Serialize, JsonSchema)]
+pub struct WatchErrorPayload {
+    pub path: String,
+    pub message: String,
+}
+
+// Payload of the `file-deleted` event, emitted alongside a `remove` change
+#[derive(Clone, Debug, PartialEq, Serialize, JsonSchema)]
+pub struct FileDeletedPayload {
+    pub path: String,
+}
+
+// Payload of the `file-created` event, emitted alongside a `create` change
+#[derive(Clone, Debug, PartialEq, Serialize, JsonSchema)]
+pub struct FileCreatedPayload {
+    pub path: String,
+}
+
+// The last-seen version of a watched file. Size and modification time are
+// compared first; the content hash only settles a changed time at the same size,
+// which is what a tool touching or rewriting an unchanged file leaves behind. It
+// is only computed once such a change comes along, so `hash` is `None` until then.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct FileVersion {
+    len: u64,
+    modified: Option<SystemTime>,
+    hash: Option<u64>,
+}
+
+fn content_hash(path: &Path) -> std::io::Result<u64> {
+    let mut file = File::open(path)?;
+    let mut hasher = DefaultHasher::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(hasher.finish());
+        }
+        hasher.write(&buffer[..read]);
+    }
+}
+
+impl FileVersion {
+    // The size and modification time of `path`, without reading its contents
+    fn read(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(FileVersion { len: metadata.len(), modified: metadata.modified().ok(), hash: None })
+    }
+}
+
+// Whether `path` differs from the `last` version seen, which is updated to the
+// current one. A file that can't be read counts as changed, as does a new size.
+// The file is only hashed when its time changed at the same size, and then
+// counts as changed unless the hash of the last version is known and matches.
+fn content_changed(last: &mut Option<FileVersion>, path: &Path) -> bool {
+    let Some(current) = FileVersion::read(path) else {
+        *last = None;
+        return true;
+    };
+    match last {
+        Some(version) if version.len != current.len => {}
+        Some(version) if version.modified == current.modified => return false,
+        Some(version) => {
+            let hash = content_hash(path).ok();
+            let changed = hash.is_none() || version.hash.is_none() || hash != version.hash;
+            *last = Some(FileVersion { hash, ..current });
+            return changed;
+        }
+        None => {}
+    }
+    *last = Some(current);
+    true
+}
+
+// Whether an event means the watched inode went away, as happens when a tool
+// deletes the file or renames a freshly written temp file over it
+fn is_replacement_event(kind: &EventKind) -> bool {
+    matches!(kind, EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_)))
+}
+
+// Re-establish the watch on `path` after the watched file was replaced, retrying
+// briefly in case the new file doesn't exist yet. Returns whether it succeeded.
+fn rewatch(slot: &Weak<Mutex<Option<DebouncerType>>>, path: &Path) -> bool {
+    for _ in 0..REWATCH_ATTEMPTS {
+        let Some(slot) = slot.upgrade() else {
+            return false;
+        };
+        let mut guard = lock(&slot, "Watch");
+        let Some(debouncer) = guard.as_mut() else {
+            // The watch was stopped while the event was in flight
+            return false;
+        };
+        if path.exists() {
+            let watcher = debouncer.watcher();
+            let _ = watcher.unwatch(path);
+            match watcher.watch(path, RecursiveMode::NonRecursive) {
+                Ok(()) => return true,
+                Err(e) => log::warn!("Failed to re-watch {}: {}", path.display(), e),
+            }
+        }
+        drop(guard);
+        std::thread::sleep(REWATCH_INTERVAL);
+    }
+    false
+}
+
+// Resolve the debounce interval for a watch, defaulting to 500ms
+fn debounce_interval(debounce_ms: Option<u64>) -> std::result::Result<Duration, AppError> {
+    let ms = debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MS);
+    if !(MIN_DEBOUNCE_MS..=MAX_DEBOUNCE_MS).contains(&ms) {
+        return Err(AppError::invalid_argument(format!(
+            "Debounce interval must be between {} and {} ms, got {}",
+            MIN_DEBOUNCE_MS, MAX_DEBOUNCE_MS, ms
+        )));
+    }
+    Ok(Duration::from_millis(ms))
+}
+
+// Resolve the quiet period for a watch, defaulting to 300ms. Zero reports changes
+// as soon as the debouncer does.
+fn quiet_period(quiet_ms: Option<u64>) -> std::result::Result<Duration, AppError> {
+    let ms = quiet_ms.unwrap_or(DEFAULT_QUIET_MS);
+    if ms > MAX_QUIET_MS {
+        return Err(AppError::invalid_argument(format!(
+            "Quiet period must be at most {} ms, got {}",
+            MAX_QUIET_MS, ms
+        )));
+    }
+    Ok(Duration::from_millis(ms))
+}
+
+// Wait until the size of `path` has held still for `quiet`, so a tool writing
+// the file in bursts has it reported once, when it's complete, rather than
+// half-written. Events arriving meanwhile are held up behind the wait and then
+// find the contents unchanged. Returns false if the file kept changing for
+// `MAX_SETTLE_WAIT` or went away, or as soon as `stopped` is set.
+fn wait_until_settled(path: &Path, quiet: Duration, stopped: &AtomicBool) -> bool {
+    if quiet.is_zero() {
+        return true;
+    }
+    let poll = (quiet / 5).clamp(MIN_SETTLE_POLL, MAX_SETTLE_POLL);
+    let started = Instant::now();
+    let mut len = std::fs::metadata(path).map(|metadata| metadata.len()).ok();
+    let mut unchanged_since = started;
+    while started.elapsed() < MAX_SETTLE_WAIT {
+        std::thread::sleep(poll);
+        if stopped.load(Ordering::SeqCst) {
+            return false;
+        }
+        let current = std::fs::metadata(path).map(|metadata| metadata.len()).ok();
+        if current.is_none() {
+            return false;
+        }
+        if current != len {
+            len = current;
+            unchanged_since = Instant::now();
+        } else if unchanged_since.elapsed() >= quiet {
+            return true;
+        }
+    }
+    false
+}
+
+// Resolve a user-supplied path, which may be relative to the project `root`, to
+// the key used in the watcher state
+fn canonical_watch_path(
+    path: &str,
+    root: Option<&Path>,
+) -> std::result::Result<PathBuf, AppError> {
+    let path = resolve_in_root(root, path);
+    std::fs::canonicalize(&path).map_err(|e| AppError::for_path(&path, e))
+}
+
+fn project_root(app: &tauri::AppHandle) -> Option<PathBuf> {
+    app.try_state::<Settings>()?.project_root()
+}
+
+// Create a debounced watch on `path` that calls `on_change` once per distinct
+// (path, kind) change in each debounced batch and `on_error` for every watch error.
+// In `WatchMode::File`, if the file is removed or renamed over, the watch is
+// re-established on the same path and the replacement is reported as a
+// modification; a removal is only reported if the file doesn't come back. In
+// `WatchMode::ParentDir` the directory is watched and only events for `path` are
+// reported, so a removal is reported right away and a later re-creation arrives
+// as a `create` change. The OS-level watch lives until the returned watch is
+// stopped. `on_change` is passed the watch's stop flag, and isn't called once the
+// watch is being stopped.
+fn create_file_watch<C, E>(
+    path: &Path,
+    mode: WatchMode,
+    timeout: Duration,
+    on_change: C,
+    on_error: E,
+) -> std::result::Result<FileWatch, AppError>
+where
+    C: Fn(FileChangedPayload, &AtomicBool) + Send + 'static,
+    E: Fn(WatchErrorPayload) + Send + 'static,
+{
+    let stopped = Arc::new(AtomicBool::new(false));
+    let callback_stopped = stopped.clone();
+    let slot: Arc<Mutex<Option<DebouncerType>>> = Arc::new(Mutex::new(None));
+    let callback_slot = Arc::downgrade(&slot);
+    let watched_path = path.to_path_buf();
+    let mut debouncer = new_debouncer(
+        timeout,
+        None,
+        move |result: DebounceEventResult| {
+            match result {
+                Ok(events) => {
+                    let mut changes: Vec<FileChangedPayload> = Vec::new();
+                    let mut replaced = false;
+                    for event in events {
+                        let in_dir = mode == WatchMode::ParentDir;
+                        if in_dir && !event.paths.contains(&watched_path) {
+                            continue;
+                        }
+                        let kind = if !is_replacement_event(&event.kind) {
+                            FileChangeKind::from_event_kind(&event.kind)
+                        } else if !in_dir {
+                            replaced = true;
+                            continue;
+                        } else if watched_path.exists() {
+                            // Renamed over the target, or removed and already back
+                            Some(FileChangeKind::Modify)
+                        } else {
+                            Some(FileChangeKind::Remove)
+                        };
+                        let Some(kind) = kind else {
+                            continue;
+                        };
+                        let path = event
+                            .paths
+                            .first()
+                            .filter(|_| !in_dir)
+                            .map(|p| p.to_string_lossy().to_string())
+                            .unwrap_or_else(|| watched_path.to_string_lossy().to_string());
+                        let change = FileChangedPayload { path, kind };
+                        if !changes.contains(&change) {
+                            changes.push(change);
+                        }
+                    }
+                    if replaced {
+                        let kind = if rewatch(&callback_slot, &watched_path) {
+                            FileChangeKind::Modify
+                        } else {
+                            FileChangeKind::Remove
+                        };
+                        let change = FileChangedPayload {
+                            path: watched_path.to_string_lossy().to_string(),
+                            kind,
+                        };
+                        if !changes.contains(&change) {
+                            changes.push(change);
+                        }
+                    }
+                    for change in changes {
+                        if callback_stopped.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        on_change(change, &callback_stopped);
+                    }
+                }
+                Err(errors) => {
+                    for error in errors {
+                        log::error!("File watch error on {}: {:?}", watched_path.display(), error);
+                        on_error(WatchErrorPayload {
+                            path: watched_path.to_string_lossy().to_string(),
+                            message: format!("File watch error: {}", error),
+                        });
+                    }
+                }
+            }
+        },
+    ).map_err(|e| AppError::Watch { message: format!("Failed to create file watcher: {}", e) })?;
+
+    let target = match mode {
+        WatchMode::File => path,
+        WatchMode::ParentDir => path.parent().ok_or_else(|| AppError::Watch {
+            message: format!("{} has no parent directory to watch", path.display()),
+        })?,
+    };
+    debouncer
+        .watcher()
+        .watch(target, RecursiveMode::NonRecursive)
+        .map_err(|e| AppError::Watch { message: format!("Failed to watch file: {}", e) })?;
+
+    *lock(&slot, "Watch") = Some(debouncer);
+
+    Ok(FileWatch { debouncer: slot, stopped })
+}
+
+// Which files below a watched directory are reported
+#[derive(Clone, Debug, PartialEq)]
+struct DirectoryFilter {
+    // Lowercase and without a leading dot, e.g. `gds` or `gds.gz`. No extensions
+    // match every file.
+    extensions: Vec<String>,
+    // Names of subdirectories skipped at any depth
+    ignored: Vec<String>,
+}
+
+impl DirectoryFilter {
+    fn new(extensions: Vec<String>, ignore: Option<Vec<String>>) -> Result<Self, AppError> {
+        let extensions: Vec<String> = extensions
+            .iter()
+            .map(|extension| extension.trim_start_matches('.').to_lowercase())
+            .collect();
+        if extensions.iter().any(|extension| extension.is_empty()) {
+            return Err(AppError::invalid_argument("File extensions must not be empty"));
+        }
+        let ignored = ignore.unwrap_or_else(|| {
+            DEFAULT_IGNORED_DIRS.iter().map(|name| name.to_string()).collect()
+        });
+        Ok(DirectoryFilter { extensions, ignored })
+    }
+
+    // Whether `path`, somewhere below the watched `root`, is reported. The
+    // extension is matched case-insensitively against the end of the file name,
+    // so `gds.gz` matches `top.gds.gz`.
+    fn matches(&self, root: &Path, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(root) else {
+            return false;
+        };
+        let mut components: Vec<String> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect();
+        let Some(name) = components.pop() else {
+            return false;
+        };
+        if components.iter().any(|dir| self.ignored.contains(dir)) {
+            return false;
+        }
+        let name = name.to_lowercase();
+        self.extensions.is_empty()
+            || self.extensions.iter().any(|extension| {
+                name.strip_suffix(extension.as_str())
+                    .and_then(|stem| stem.strip_suffix('.'))
+                    .is_some_and(|stem| !stem.is_empty())
+            })
+    }
+}
+
+// Create a debounced recursive watch on the directory `root` that calls
+// `on_change` once per distinct (path, kind) change of a file `filter` matches
+// in each debounced batch, and `on_error` for every watch error. A file removed
+// or renamed away is reported as a removal, one renamed into place, as an
+// atomic save does, as a modification. Ignored subdirectories are still covered
+// by the OS-level watch; only their events are dropped. `on_change` gets the stop
+// flag as in `create_file_watch`.
+fn create_directory_watch<C, E>(
+    root: &Path,
+    filter: DirectoryFilter,
+    timeout: Duration,
+    on_change: C,
+    on_error: E,
+) -> std::result::Result<FileWatch, AppError>
+where
+    C: Fn(FileChangedPayload, &AtomicBool) + Send + 'static,
+    E: Fn(WatchErrorPayload) + Send + 'static,
+{
+    let stopped = Arc::new(AtomicBool::new(false));
+    let callback_stopped = stopped.clone();
+    let watched_root = root.to_path_buf();
+    let mut debouncer = new_debouncer(
+        timeout,
+        None,
+        move |result: DebounceEventResult| {
+            match result {
+                Ok(events) => {
+                    let mut changes: Vec<FileChangedPayload> = Vec::new();
+                    for event in events {
+                        for path in &event.paths {
+                            if !filter.matches(&watched_root, path) || path.is_dir() {
+                                continue;
+                            }
+                            let kind = if !is_replacement_event(&event.kind) {
+                                FileChangeKind::from_event_kind(&event.kind)
+                            } else if path.exists() {
+                                Some(FileChangeKind::Modify)
+                            } else {
+                                Some(FileChangeKind::Remove)
+                            };
+                            let Some(kind) = kind else {
+                                continue;
+                            };
+                            let path = path.to_string_lossy().to_string();
+                            let change = FileChangedPayload { path, kind };
+                            if !changes.contains(&change) {
+                                changes.push(change);
+                            }
+                        }
+                    }
+                    for change in changes {
+                        if callback_stopped.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        on_change(change, &callback_stopped);
+                    }
+                }
+                Err(errors) => {
+                    for error in errors {
+                        let root = watched_root.display();
+                        log::error!("Directory watch error on {}: {:?}", root, error);
+                        on_error(WatchErrorPayload {
+                            path: watched_root.to_string_lossy().to_string(),
+                            message: format!("Directory watch error: {}", error),
+                        });
+                    }
+                }
+            }
+        },
+    ).map_err(|e| AppError::Watch { message: format!("Failed to create file watcher: {}", e) })?;
+
+    debouncer
+        .watcher()
+        .watch(root, RecursiveMode::Recursive)
+        .map_err(|e| AppError::Watch { message: format!("Failed to watch directory: {}", e) })?;
+
+    Ok(FileWatch { debouncer: Arc::new(Mutex::new(Some(debouncer))), stopped })
+}
+
+// Emit `change` as `file-changed`, along with `file-created` or `file-deleted`
+// for a creation or removal
+fn emit_change(app: &tauri::AppHandle, change: FileChangedPayload) {
+    let path = change.path.clone();
+    let _ = match change.kind {
+        FileChangeKind::Create => app.emit("file-created", FileCreatedPayload { path }),
+        FileChangeKind::Remove => app.emit("file-deleted", FileDeletedPayload { path }),
+        FileChangeKind::Modify => Ok(()),
+    };
+    let _ = app.emit("file-changed", change);
+}
+
+// Payload of the per-path result of `watch_files`: the canonicalized path
+// that events will carry, or why the path couldn't be watched
+#[derive(Clone, Debug, PartialEq, Serialize, JsonSchema)]
+pub struct WatchOutcome {
+    pub path: String,
+    pub watched_path: Option<String>,
+    pub error: Option<AppError>,
+}
+
+// Watch `path` unless it is already watched, returning its canonicalized path
+fn install_watch(
+    path: &str,
+    timeout: Duration,
+    quiet: Duration,
+    mode: WatchMode,
+    app: &tauri::AppHandle,
+    watched_files: &WatchedFiles,
+) -> std::result::Result<String, AppError> {
+    let path_buf = canonical_watch_path(path, project_root(app).as_deref())?;
+    let path_str = path_buf.to_string_lossy().to_string();
+    if lock(&watched_files.0, "Watcher state").contains_key(&path_buf) {
+        return Ok(path_str);
+    }
+
+    // Only the size and time for now; the contents are hashed when a change at
+    // the same size first needs telling apart from a touch
+    let last_version = Mutex::new(FileVersion::read(&path_buf));
+
+    // Create a debounced file watcher, outside the state lock so other watch
+    // commands aren't held up while the OS-level watch is set up
+    let change_handle = app.clone();
+    let error_handle = app.clone();
+    let cache = ParseCache::clone(&app.state::<ParseCache>());
+    let watch = create_file_watch(
+        &path_buf,
+        mode,
+        timeout,
+        move |change, stopped| {
+            if change.kind != FileChangeKind::Remove
+                && !wait_until_settled(Path::new(&change.path), quiet, stopped)
+            {
+                if stopped.load(Ordering::SeqCst) {
+                    return;
+                }
+                log::debug!("{} is still changing; reporting it anyway", change.path);
+            }
+            // Tools that touch or rewrite the file without changing it don't
+            // trigger a reload
+            let changed = content_changed(
+                &mut lock(&last_version, "Watched file version"),
+                Path::new(&change.path),
+            );
+            if !changed && change.kind != FileChangeKind::Remove {
+                log::debug!("Ignoring change to {}: contents are unchanged", change.path);
+                return;
+            }
+            // A cached parse of the old contents must not be served again
+            cache.invalidate(Path::new(&change.path));
+            // A removal is only reported once an atomic save had the chance to
+            // put the file back, so it means the file is really gone
+            emit_change(&change_handle, change);
+        },
+        move |error| {
+            // Emit error event to frontend so users know live reload may have stopped
+            let _ = error_handle.emit("watch-error", error);
+        },
+    )?;
+
+    let duplicate = match lock(&watched_files.0, "Watcher state").entry(path_buf) {
+        Entry::Occupied(_) => Some(watch),
+        Entry::Vacant(entry) => {
+            entry.insert(watch);
+            None
+        }
+    };
+    // Watched meanwhile by a concurrent call; stopped outside the state lock
+    if let Some(watch) = duplicate {
+        watch.stop();
+    }
+    Ok(path_str)
+}
+
+// Watch `path` with the default debounce interval and quiet period, watching
+// the file itself. Returns its canonicalized path.
+pub(crate) fn watch_with_defaults(
+    path: &str,
+    app: &tauri::AppHandle,
+) -> std::result::Result<String, AppError> {
+    let timeout = debounce_interval(None)?;
+    let quiet = quiet_period(None)?;
+    install_watch(path, timeout, quiet, WatchMode::File, app, &app.state::<WatchedFiles>())
+}
+
+fn watch_mode(watch_parent_dir: Option<bool>) -> WatchMode {
+    if watch_parent_dir.unwrap_or(false) {
+        WatchMode::ParentDir
+    } else {
+        WatchMode::File
+    }
+}
+
+// Command to start watching a file for changes. Watches are additive: each file
+// gets its own watcher, and watching an already-watched file is a no-op. A
+// relative path is resolved against the project root.
+// `debounce_ms` defaults to 500ms and must be within 50-10000ms. A change is
+// reported once the file's size has stopped changing for `quiet_ms` (300ms by
+// default, at most 10000ms, 0 to report right away). With
+// `watch_parent_dir`, the file's directory is watched instead of the file, so
+// the file being deleted and later re-created still reports changes.
+// Returns the canonicalized path that `file-changed` and `file-deleted` events
+// will carry.
+#[tauri::command]
+pub async fn watch_file(
+    path: String,
+    debounce_ms: Option<u64>,
+    quiet_ms: Option<u64>,
+    watch_parent_dir: Option<bool>,
+    app: tauri::AppHandle,
+    watched_files: State<'_, WatchedFiles>,
+) -> std::result::Result<String, AppError> {
+    let timeout = debounce_interval(debounce_ms)?;
+    let quiet = quiet_period(quiet_ms)?;
+    install_watch(&path, timeout, quiet, watch_mode(watch_parent_dir), &app, &watched_files)
+}
+
+// Command to watch several files at once, e.g. when restoring a session. A path
+// that can't be watched is reported in its outcome and doesn't stop the rest;
+// outcomes come back in the order of `paths`.
+#[tauri::command]
+pub async fn watch_files(
+    paths: Vec<String>,
+    debounce_ms: Option<u64>,
+    quiet_ms: Option<u64>,
+    watch_parent_dir: Option<bool>,
+    app: tauri::AppHandle,
+    watched_files: State<'_, WatchedFiles>,
+) -> std::result::Result<Vec<WatchOutcome>, AppError> {
+    let timeout = debounce_interval(debounce_ms)?;
+    let quiet = quiet_period(quiet_ms)?;
+    let mode = watch_mode(watch_parent_dir);
+    Ok(paths
+        .into_iter()
+        .map(|path| match install_watch(&path, timeout, quiet, mode, &app, &watched_files) {
+            Ok(watched) => WatchOutcome { path, watched_path: Some(watched), error: None },
+            Err(error) => WatchOutcome { path, watched_path: None, error: Some(error) },
+        })
+        .collect())
+}
+
+// Command to watch a project folder and everything below it, reporting changes
+// to files with one of `extensions`, or to every file if none are given.
+// Subdirectories named in `ignore` are skipped at any depth, `.git`, `.hg`,
+// `.svn` and `node_modules` if it isn't given. `debounce_ms` and `quiet_ms` work
+// as for `watch_file`, and a relative path is resolved against the project
+// root. Watching an already-watched directory is a no-op. Returns the
+// canonicalized directory path, which `unwatch_file` stops the watch by.
+#[tauri::command]
+pub async fn watch_directory(
+    path: String,
+    extensions: Vec<String>,
+    ignore: Option<Vec<String>>,
+    debounce_ms: Option<u64>,
+    quiet_ms: Option<u64>,
+    app: tauri::AppHandle,
+    watched_files: State<'_, WatchedFiles>,
+) -> std::result::Result<String, AppError> {
+    let timeout = debounce_interval(debounce_ms)?;
+    let quiet = quiet_period(quiet_ms)?;
+    let filter = DirectoryFilter::new(extensions, ignore)?;
+    let root = canonical_watch_path(&path, project_root(&app).as_deref())?;
+    if !root.is_dir() {
+        return Err(AppError::invalid_argument(format!("{} is not a directory", root.display())));
+    }
+    let root_str = root.to_string_lossy().to_string();
+    if lock(&watched_files.0, "Watcher state").contains_key(&root) {
+        return Ok(root_str);
+    }
+
+    // Versions of the files seen changing so far; the tree isn't hashed up front
+    let versions: Mutex<HashMap<PathBuf, Option<FileVersion>>> = Mutex::default();
+    let change_handle = app.clone();
+    let error_handle = app.clone();
+    let cache = ParseCache::clone(&app.state::<ParseCache>());
+    // Set up outside the state lock, as the debouncer scans the whole tree
+    let watch = create_directory_watch(
+        &root,
+        filter,
+        timeout,
+        move |change, stopped| {
+            let path = Path::new(&change.path);
+            if change.kind != FileChangeKind::Remove && !wait_until_settled(path, quiet, stopped) {
+                if stopped.load(Ordering::SeqCst) {
+                    return;
+                }
+                log::debug!("{} is still changing; reporting it anyway", change.path);
+            }
+            let changed = {
+                let mut versions = lock(&versions, "Watched file versions");
+                let last = versions.entry(path.to_path_buf()).or_default();
+                let changed = content_changed(last, path);
+                if change.kind == FileChangeKind::Remove {
+                    versions.remove(path);
+                }
+                changed
+            };
+            if !changed && change.kind != FileChangeKind::Remove {
+                log::debug!("Ignoring change to {}: contents are unchanged", change.path);
+                return;
+            }
+            cache.invalidate(path);
+            emit_change(&change_handle, change);
+        },
+        move |error| {
+            let _ = error_handle.emit("watch-error", error);
+        },
+    )?;
+
+    let duplicate = match lock(&watched_files.0, "Watcher state").entry(root) {
+        Entry::Occupied(_) => Some(watch),
+        Entry::Vacant(entry) => {
+            entry.insert(watch);
+            None
+        }
+    };
+    // Watched meanwhile by a concurrent call; stopped outside the state lock
+    if let Some(watch) = duplicate {
+        watch.stop();
+    }
+    Ok(root_str)
+}
+
+// Command to stop watching a single file
+#[tauri::command]
+pub async fn unwatch_file(
+    path: String,
+    settings: State<'_, Settings>,
+    watched_files: State<'_, WatchedFiles>,
+    cache: State<'_, ParseCache>,
+) -> std::result::Result<(), AppError> {
+    // Fall back to the raw path so a file that was deleted since it was
+    // watched can still be unwatched
+    let root = settings.project_root();
+    let path_buf = canonical_watch_path(&path, root.as_deref())
+        .unwrap_or_else(|_| resolve_in_root(root.as_deref(), &path));
+
+    // Stop the debouncer and wait for its event thread to exit, so no
+    // `file-changed` event can be emitted for this file once this returns
+    let removed = lock(&watched_files.0, "Watcher state").remove(&path_buf);
+    if let Some(watch) = removed {
+        watch.stop();
+    }
+    // The file is done with, so it isn't held open either
+    cache.files().close(&path_buf);
+
+    Ok(())
+}
+
+// Command to list the canonicalized paths of all currently watched files
+#[tauri::command]
+pub async fn get_watched_files(
+    watched_files: State<'_, WatchedFiles>,
+) -> std::result::Result<Vec<String>, AppError> {
+    let watched = lock(&watched_files.0, "Watcher state");
+
+    let mut paths: Vec<String> = watched
+        .keys()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    paths.sort();
+
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    const TEST_DEBOUNCE: Duration = Duration::from_millis(100);
+    const TEST_WAIT: Duration = Duration::from_millis(1500);
+
+    // A watch on `path` sending every change it reports into the returned channel
+    fn watch_into_channel(
+        path: &Path,
+        mode: WatchMode,
+    ) -> (FileWatch, mpsc::Receiver<FileChangedPayload>) {
+        let (tx, rx) = mpsc::channel();
+        let on_change = move |change, _: &AtomicBool| {
+            let _ = tx.send(change);
+        };
+        (create_file_watch(path, mode, TEST_DEBOUNCE, on_change, |_| {}).unwrap(), rx)
+    }
+
+    #[test]
+    fn debounce_interval_defaults_and_validates() {
+        assert_eq!(debounce_interval(None).unwrap(), Duration::from_millis(500));
+        assert_eq!(debounce_interval(Some(50)).unwrap(), Duration::from_millis(50));
+        assert_eq!(debounce_interval(Some(10_000)).unwrap(), Duration::from_secs(10));
+        assert!(debounce_interval(Some(49)).is_err());
+        assert!(debounce_interval(Some(10_001)).is_err());
+        assert_eq!(quiet_period(None).unwrap(), Duration::from_millis(300));
+        assert_eq!(quiet_period(Some(0)).unwrap(), Duration::ZERO);
+        assert!(quiet_period(Some(10_001)).is_err());
+    }
+
+    #[test]
+    fn changes_wait_for_the_file_to_stop_growing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("layout.gds");
+        std::fs::write(&path, b"").unwrap();
+
+        // A writer appending in bursts closer together than the quiet period
+        let mut file = File::options().append(true).open(&path).unwrap();
+        std::io::Write::write_all(&mut file, &[0; 1024]).unwrap();
+        let writer = std::thread::spawn(move || {
+            for _ in 0..3 {
+                std::thread::sleep(Duration::from_millis(40));
+                std::io::Write::write_all(&mut file, &[0; 1024]).unwrap();
+            }
+        });
+        let running = AtomicBool::new(false);
+        assert!(wait_until_settled(&path, Duration::from_millis(200), &running));
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 4096);
+        writer.join().unwrap();
+
+        // Stopping the watch doesn't wait out the quiet period
+        let started = Instant::now();
+        assert!(!wait_until_settled(&path, Duration::from_secs(10), &AtomicBool::new(true)));
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        assert!(wait_until_settled(&path, Duration::ZERO, &running));
+        std::fs::remove_file(&path).unwrap();
+        assert!(!wait_until_settled(&path, Duration::from_millis(50), &running));
+    }
+
+    #[test]
+    fn touching_without_changing_contents_is_not_a_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("layout.gds");
+        std::fs::write(&path, b"v1").unwrap();
+        let touch = |seconds: u64| {
+            let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+            File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        };
+        let mut last = FileVersion::read(&path);
+        assert_eq!(last.as_ref().and_then(|version| version.hash), None);
+
+        assert!(!content_changed(&mut last, &path));
+        // Nothing to compare the first hash against yet
+        touch(1_000_000);
+        assert!(content_changed(&mut last, &path));
+        assert!(last.as_ref().is_some_and(|version| version.hash.is_some()));
+        touch(1_500_000);
+        assert!(!content_changed(&mut last, &path));
+        // Same size, new contents: only the hash tells
+        std::fs::write(&path, b"v2").unwrap();
+        touch(2_000_000);
+        assert!(content_changed(&mut last, &path));
+        // A new size is a change without hashing
+        std::fs::write(&path, b"v10").unwrap();
+        assert!(content_changed(&mut last, &path));
+        assert_eq!(last.as_ref().and_then(|version| version.hash), None);
+        assert!(!content_changed(&mut last, &path));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(content_changed(&mut last, &path));
+        assert_eq!(last, None);
+    }
+
+    #[test]
+    fn stopped_watcher_no_longer_reports_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("layout.gds");
+        std::fs::write(&path, b"v1").unwrap();
+
+        let (watch, rx) = watch_into_channel(&path, WatchMode::File);
+
+        std::fs::write(&path, b"v2").unwrap();
+        let change = rx.recv_timeout(TEST_WAIT).expect("expected a change while watching");
+        assert_eq!(change.kind, FileChangeKind::Modify);
+        assert_eq!(change.path, path.to_string_lossy());
+
+        watch.stop();
+        while rx.try_recv().is_ok() {}
+
+        std::fs::write(&path, b"v3").unwrap();
+        assert!(
+            rx.recv_timeout(TEST_WAIT).is_err(),
+            "no change should be reported after the watcher is stopped"
+        );
+    }
+
+    #[test]
+    fn stop_all_drains_every_watch() {
+        let dir = tempfile::tempdir().unwrap();
+        let watched = WatchedFiles::default();
+        let mut receivers = Vec::new();
+        for name in ["a.gds", "b.gds"] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, b"v1").unwrap();
+            let (watch, rx) = watch_into_channel(&path, WatchMode::File);
+            lock(&watched.0, "Watcher state").insert(path, watch);
+            receivers.push(rx);
+        }
+
+        watched.stop_all();
+        assert!(lock(&watched.0, "Watcher state").is_empty());
+        std::fs::write(dir.path().join("a.gds"), b"v2").unwrap();
+        for rx in &receivers {
+            assert!(rx.recv_timeout(TEST_WAIT).is_err(), "no change after stopping all watches");
+        }
+    }
+
+    #[test]
+    fn atomic_save_keeps_the_watch_alive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("layout.gds");
+        std::fs::write(&path, b"v1").unwrap();
+
+        let (watch, rx) = watch_into_channel(&path, WatchMode::File);
+
+        // Save the way layout tools do: write a temp file, rename it over the target
+        let temp = dir.path().join("layout.gds.tmp");
+        std::fs::write(&temp, b"v2").unwrap();
+        std::fs::rename(&temp, &path).unwrap();
+
+        let change = rx.recv_timeout(TEST_WAIT).expect("expected a change after the atomic save");
+        assert_eq!(change.kind, FileChangeKind::Modify);
+        assert_eq!(change.path, path.to_string_lossy());
+        std::thread::sleep(TEST_DEBOUNCE * 2);
+        while rx.try_recv().is_ok() {}
+
+        // The re-established watch still sees in-place writes to the new file
+        std::fs::write(&path, b"v3").unwrap();
+        let change = rx.recv_timeout(TEST_WAIT).expect("expected a change after re-watching");
+        assert_eq!(change.kind, FileChangeKind::Modify);
+
+        watch.stop();
+    }
+
+    #[test]
+    fn deleting_the_watched_file_reports_a_removal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("layout.gds");
+        std::fs::write(&path, b"v1").unwrap();
+
+        let (watch, rx) = watch_into_channel(&path, WatchMode::File);
+
+        // Reported only after the re-watch attempts gave up on the file coming back
+        std::fs::remove_file(&path).unwrap();
+        let change = rx.recv_timeout(TEST_WAIT).expect("expected the removal");
+        assert_eq!(change.kind, FileChangeKind::Remove);
+        assert_eq!(change.path, path.to_string_lossy());
+        assert!(rx.recv_timeout(TEST_DEBOUNCE * 3).is_err(), "removal reported once");
+
+        watch.stop();
+    }
+
+    #[test]
+    fn directory_watch_reports_matching_files_anywhere_below() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("cells/sub")).unwrap();
+        std::fs::create_dir_all(root.join("build")).unwrap();
+        let filter = DirectoryFilter::new(vec![".GDS".to_string()], Some(vec!["build".into()]));
+        let filter = filter.unwrap();
+        assert!(filter.matches(&root, &root.join("cells/TOP.Gds")));
+        assert!(!filter.matches(&root, &root.join("build/top.gds")));
+        assert!(!filter.matches(&root, &root.join("top.gds.bak")));
+        assert!(DirectoryFilter::new(vec![".".to_string()], None).is_err());
+
+        let (tx, rx) = mpsc::channel();
+        let watch = create_directory_watch(
+            &root,
+            filter,
+            TEST_DEBOUNCE,
+            move |change, _| {
+                let _ = tx.send(change);
+            },
+            |_| {},
+        )
+        .unwrap();
+
+        // Only the new layout in the nested directory is reported
+        let layout = root.join("cells/sub/new.gds");
+        std::fs::write(root.join("build/out.gds"), b"v1").unwrap();
+        std::fs::write(root.join("notes.txt"), b"v1").unwrap();
+        std::fs::write(&layout, b"v1").unwrap();
+        let change = rx.recv_timeout(TEST_WAIT).expect("expected the new layout");
+        let mut changes = vec![change];
+        while let Ok(change) = rx.recv_timeout(TEST_DEBOUNCE * 3) {
+            changes.push(change);
+        }
+        assert!(changes.iter().all(|change| change.path == layout.to_string_lossy()));
+        assert!(changes.iter().any(|change| change.kind == FileChangeKind::Create));
+
+        std::fs::remove_file(&layout).unwrap();
+        let change = rx.recv_timeout(TEST_WAIT).expect("expected the removal");
+        assert_eq!(change.kind, FileChangeKind::Remove);
+
+        watch.stop();
+    }
+
+    #[test]
+    fn parent_dir_watch_sees_the_file_coming_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().canonicalize().unwrap().join("layout.gds");
+        std::fs::write(&path, b"v1").unwrap();
+        std::fs::write(dir.path().join("other.gds"), b"v1").unwrap();
+
+        let (watch, rx) = watch_into_channel(&path, WatchMode::ParentDir);
+
+        // Other files in the directory are not reported
+        std::fs::write(dir.path().join("other.gds"), b"v2").unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let change = rx.recv_timeout(TEST_WAIT).expect("expected the removal");
+        assert_eq!(change.kind, FileChangeKind::Remove);
+        assert_eq!(change.path, path.to_string_lossy());
+
+        // Written again well after the removal was reported
+        std::thread::sleep(TEST_DEBOUNCE * 2);
+        std::fs::write(&path, b"v2").unwrap();
+        let change = rx.recv_timeout(TEST_WAIT).expect("expected the re-creation");
+        assert_eq!(change.kind, FileChangeKind::Create);
+        assert_eq!(change.path, path.to_string_lossy());
+
+        watch.stop();
+    }
+}
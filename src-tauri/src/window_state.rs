@@ -0,0 +1,202 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{Manager, PhysicalPosition, PhysicalSize};
+
+use crate::error::AppError;
+use crate::paths::write_atomic;
+
+const WINDOW_STATE_NAME: &str = "window_state.json";
+
+// How much of the window, in pixels along each axis, must overlap a monitor for
+// a restored position to count as reachable
+const MIN_VISIBLE_PIXELS: i64 = 100;
+
+// Outer position and inner size of the main window, in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub maximized: bool,
+}
+
+// Area of one monitor, in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonitorArea {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl MonitorArea {
+    fn overlap(&self, geometry: &WindowGeometry) -> (i64, i64) {
+        let span = |start: i32, length: u32, other_start: i32, other_length: u32| {
+            let end = (start as i64 + length as i64).min(other_start as i64 + other_length as i64);
+            end - (start as i64).max(other_start as i64)
+        };
+        (
+            span(self.x, self.width, geometry.x, geometry.width),
+            span(self.y, self.height, geometry.y, geometry.height),
+        )
+    }
+
+    // Squared distance from the window's center to the nearest point of this area
+    fn distance_squared(&self, geometry: &WindowGeometry) -> i64 {
+        let center_x = geometry.x as i64 + geometry.width as i64 / 2;
+        let center_y = geometry.y as i64 + geometry.height as i64 / 2;
+        let dx = center_x - center_x.clamp(self.x as i64, self.x as i64 + self.width as i64);
+        let dy = center_y - center_y.clamp(self.y as i64, self.y as i64 + self.height as i64);
+        dx * dx + dy * dy
+    }
+}
+
+// Move a saved geometry back onto a monitor if it is no longer visible enough on
+// any of them (e.g. the monitor it was on has been unplugged). The window is
+// moved onto the nearest monitor and shrunk to fit it. With no monitors known
+// the geometry is returned unchanged.
+pub fn clamp_to_monitors(geometry: WindowGeometry, monitors: &[MonitorArea]) -> WindowGeometry {
+    let visible = monitors.iter().any(|monitor| {
+        let (overlap_x, overlap_y) = monitor.overlap(&geometry);
+        overlap_x >= MIN_VISIBLE_PIXELS.min(geometry.width as i64)
+            && overlap_y >= MIN_VISIBLE_PIXELS.min(geometry.height as i64)
+    });
+    let Some(nearest) = monitors.iter().min_by_key(|monitor| monitor.distance_squared(&geometry))
+    else {
+        return geometry;
+    };
+    if visible {
+        return geometry;
+    }
+
+    let width = geometry.width.min(nearest.width);
+    let height = geometry.height.min(nearest.height);
+    let clamp = |start: i32, length: u32, area_start: i32, area_length: u32| {
+        let last = area_start as i64 + (area_length - length) as i64;
+        (start as i64).clamp(area_start as i64, last) as i32
+    };
+    WindowGeometry {
+        x: clamp(geometry.x, width, nearest.x, nearest.width),
+        y: clamp(geometry.y, height, nearest.y, nearest.height),
+        width,
+        height,
+        maximized: geometry.maximized,
+    }
+}
+
+fn window_state_path(app: &tauri::AppHandle) -> std::result::Result<PathBuf, AppError> {
+    crate::paths::app_data_file(app, WINDOW_STATE_NAME)
+}
+
+fn read_geometry(state_path: &Path) -> std::result::Result<Option<WindowGeometry>, AppError> {
+    if !state_path.exists() {
+        return Ok(None);
+    }
+    let contents = std::fs::read_to_string(state_path)
+        .map_err(|e| AppError::io("Failed to read window state", e))?;
+    serde_json::from_str(&contents)
+        .map(Some)
+        .map_err(|e| AppError::parse_error(format!("Failed to parse window state: {}", e)))
+}
+
+fn write_geometry(
+    state_path: &Path,
+    geometry: &WindowGeometry,
+) -> std::result::Result<(), AppError> {
+    if let Some(dir) = state_path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| AppError::io("Failed to create app data dir", e))?;
+    }
+    let contents = serde_json::to_string_pretty(geometry)
+        .map_err(|e| AppError::io("Failed to serialize window state", e))?;
+    write_atomic(state_path, contents.as_bytes())
+        .map_err(|e| AppError::io("Failed to save window state", e))
+}
+
+// Record the geometry of `window`. While maximized, the previously saved size and
+// position are kept so unmaximizing after a restart returns to them.
+pub fn save(window: &tauri::Window) -> std::result::Result<(), AppError> {
+    let state_path = window_state_path(window.app_handle())?;
+    let maximized = window.is_maximized()
+        .map_err(|e| AppError::io("Failed to read window state", e))?;
+    let geometry = match read_geometry(&state_path).ok().flatten() {
+        Some(previous) if maximized => WindowGeometry { maximized, ..previous },
+        _ => {
+            let position = window.outer_position()
+                .map_err(|e| AppError::io("Failed to read window position", e))?;
+            let size = window.inner_size()
+                .map_err(|e| AppError::io("Failed to read window size", e))?;
+            WindowGeometry {
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+                maximized,
+            }
+        }
+    };
+    write_geometry(&state_path, &geometry)
+}
+
+// Apply the saved geometry, if any, to `window`, clamped onto the current monitors
+pub fn restore(window: &tauri::WebviewWindow) -> std::result::Result<(), AppError> {
+    let Some(geometry) = read_geometry(&window_state_path(window.app_handle())?)? else {
+        return Ok(());
+    };
+    let monitors: Vec<MonitorArea> = window.available_monitors()
+        .map_err(|e| AppError::io("Failed to list monitors", e))?
+        .iter()
+        .map(|monitor| MonitorArea {
+            x: monitor.position().x,
+            y: monitor.position().y,
+            width: monitor.size().width,
+            height: monitor.size().height,
+        })
+        .collect();
+    let geometry = clamp_to_monitors(geometry, &monitors);
+
+    window.set_size(PhysicalSize::new(geometry.width, geometry.height))
+        .map_err(|e| AppError::io("Failed to restore window size", e))?;
+    window.set_position(PhysicalPosition::new(geometry.x, geometry.y))
+        .map_err(|e| AppError::io("Failed to restore window position", e))?;
+    if geometry.maximized {
+        window.maximize()
+            .map_err(|e| AppError::io("Failed to restore maximized window", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEFT: MonitorArea = MonitorArea { x: 0, y: 0, width: 1920, height: 1080 };
+    const RIGHT: MonitorArea = MonitorArea { x: 1920, y: 0, width: 1280, height: 720 };
+
+    fn geometry(x: i32, y: i32, width: u32, height: u32) -> WindowGeometry {
+        WindowGeometry { x, y, width, height, maximized: false }
+    }
+
+    #[test]
+    fn visible_windows_are_left_alone() {
+        let on_right = geometry(2000, 100, 1400, 900);
+        assert_eq!(clamp_to_monitors(on_right, &[LEFT, RIGHT]), on_right);
+        // Hanging partly off the edge is still reachable
+        let overhanging = geometry(-500, 50, 800, 600);
+        assert_eq!(clamp_to_monitors(overhanging, &[LEFT]), overhanging);
+        assert_eq!(clamp_to_monitors(overhanging, &[]), overhanging);
+    }
+
+    #[test]
+    fn offscreen_windows_move_to_the_nearest_monitor() {
+        // Saved on the right monitor, which is gone now
+        let clamped = clamp_to_monitors(geometry(2500, 200, 1400, 900), &[LEFT]);
+        assert_eq!(clamped, geometry(520, 180, 1400, 900));
+
+        // Too large for the only monitor left, so it shrinks to fit
+        let clamped = clamp_to_monitors(geometry(4000, -2000, 1400, 900), &[RIGHT]);
+        assert_eq!(clamped, geometry(1920, 0, 1280, 720));
+    }
+}
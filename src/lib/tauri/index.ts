@@ -8,6 +8,30 @@
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 
+/**
+ * Structured error returned by backend commands, discriminated by `kind`
+ */
+export type AppError =
+	| { kind: "Io"; message: string }
+	| { kind: "NotFound"; path: string }
+	| {
+			kind: "ParseError";
+			offset: number | null;
+			message: string;
+			expected: string | null;
+			found_bytes: number[];
+	  }
+	| { kind: "InvalidFormat"; expected: string; found: string }
+	| { kind: "InvalidArgument"; message: string }
+	| { kind: "CellNotFound"; cell: string }
+	| { kind: "InvalidHierarchy"; cell: string; message: string }
+	| { kind: "Watch"; message: string }
+	| { kind: "Clipboard"; message: string }
+	| { kind: "Cancelled" }
+	| { kind: "TooLarge"; count: number; limit: number }
+	| { kind: "SymlinkRefused"; path: string }
+	| { kind: "ReadOnly"; path: string | null };
+
 /**
  * Check if the app is running in Tauri (desktop mode)
  */
@@ -15,17 +39,23 @@ export function isTauri(): boolean {
 	return typeof window !== "undefined" && "__TAURI__" in window;
 }
 
+/**
+ * File type filters for the dialogs, as [name, extensions] pairs
+ */
+export type DialogFilters = [string, string[]][];
+
 /**
  * Open a file dialog and return the selected file path
  * Returns null if no file was selected or if running in web mode
+ * @param filters - Optional filters replacing the default layout file filter; [] shows all files
  */
-export async function openFileDialog(): Promise<string | null> {
+export async function openFileDialog(filters?: DialogFilters): Promise<string | null> {
 	if (!isTauri()) {
 		return null;
 	}
 
 	try {
-		const result = await invoke<string | null>("open_file_dialog");
+		const result = await invoke<string | null>("open_file_dialog", { filters });
 		return result;
 	} catch (error) {
 		console.error("[Tauri] Failed to open file dialog:", error);
@@ -33,44 +63,71 @@ export async function openFileDialog(): Promise<string | null> {
 	}
 }
 
+/**
+ * Payload of the backend `file-changed` event
+ */
+export interface FileChangedPayload {
+	path: string;
+	kind: "modify" | "create" | "remove";
+}
+
 /**
  * Start watching a file for changes
  * @param path - The file path to watch
  * @param onChange - Callback function to call when the file changes
  * @param onError - Optional callback function to call when a watch error occurs
+ * @param onDeleted - Optional callback function to call when the file is deleted
  * @returns A function to stop watching the file
  */
 export async function watchFile(
 	path: string,
 	onChange: () => void,
 	onError?: (error: string) => void,
+	onDeleted?: () => void,
 ): Promise<() => void> {
 	if (!isTauri()) {
 		return () => {}; // No-op unwatch function
 	}
 
 	try {
-		// Start watching the file
-		await invoke("watch_file", { path });
+		// Start watching the file; the backend reports changes using the canonical path
+		const watchedPath = await invoke<string>("watch_file", { path });
 
-		// Listen for file change events
-		const unlistenChange = await listen("file-changed", () => {
-			onChange();
+		// Listen for file change events for this file only
+		const unlistenChange = await listen<FileChangedPayload>("file-changed", (event) => {
+			if (event.payload.path === watchedPath && event.payload.kind !== "remove") {
+				onChange();
+			}
 		});
 
-		// Listen for file watch error events
-		const unlistenError = await listen<string>("file-watch-error", (event) => {
-			console.error("[Tauri] File watch error:", event.payload);
-			if (onError) {
-				onError(event.payload);
+		// Listen for the file being deleted, as opposed to replaced by a save
+		const unlistenDeleted = await listen<{ path: string }>("file-deleted", (event) => {
+			if (event.payload.path === watchedPath && onDeleted) {
+				onDeleted();
 			}
 		});
 
+		// Listen for errors of this file's watch, after which live reload may
+		// have stopped (e.g. the drive was unmounted)
+		const unlistenError = await listen<{ path: string; message: string }>(
+			"watch-error",
+			(event) => {
+				if (event.payload.path !== watchedPath) {
+					return;
+				}
+				console.error("[Tauri] File watch error:", event.payload.message);
+				if (onError) {
+					onError(event.payload.message);
+				}
+			},
+		);
+
 		// Return a function to stop watching
 		return async () => {
 			unlistenChange();
+			unlistenDeleted();
 			unlistenError();
-			await invoke("unwatch_file");
+			await invoke("unwatch_file", { path: watchedPath });
 		};
 	} catch (error) {
 		console.error("[Tauri] Failed to watch file:", error);
@@ -129,3 +186,85 @@ export async function readFile(path: string): Promise<ArrayBuffer> {
 		throw error;
 	}
 }
+
+/**
+ * Listen for files the OS, the menu or drag and drop ask to open, then tell the
+ * backend to deliver the ones queued since launch (e.g. `gdsjam chip.gds`)
+ * @param onOpen - Callback receiving the path of each file to open
+ * @returns A function to stop listening
+ */
+export async function onOpenFile(onOpen: (path: string) => void): Promise<() => void> {
+	if (!isTauri()) {
+		return () => {};
+	}
+
+	try {
+		const unlisten = await listen<string>("open-file", (event) => onOpen(event.payload));
+		await invoke("frontend_ready");
+		return unlisten;
+	} catch (error) {
+		console.error("[Tauri] Failed to listen for opened files:", error);
+		return () => {};
+	}
+}
+
+/**
+ * Write a message into the backend log file, next to the backend's own messages
+ * @param level - Severity of the message
+ * @param message - The message to log
+ */
+export async function logToBackend(
+	level: "error" | "warn" | "info" | "debug" | "trace",
+	message: string,
+): Promise<void> {
+	if (!isTauri()) {
+		return;
+	}
+
+	try {
+		await invoke("log_frontend", { level, message });
+	} catch (error) {
+		console.error("[Tauri] Failed to write to the backend log:", error);
+	}
+}
+
+/**
+ * Get the path of the backend log file, e.g. to show it in a bug report dialog
+ */
+export async function getLogPath(): Promise<string | null> {
+	if (!isTauri()) {
+		return null;
+	}
+
+	try {
+		return await invoke<string>("get_log_path");
+	} catch (error) {
+		console.error("[Tauri] Failed to get log path:", error);
+		return null;
+	}
+}
+
+/**
+ * Version of the backend and the optional features compiled into it
+ */
+export interface BackendInfo {
+	version: string;
+	features: string[];
+}
+
+/**
+ * Check that the backend is up, e.g. to enable UI that depends on optional features
+ * Returns null if the backend doesn't answer or if running in web mode
+ */
+export async function ping(): Promise<BackendInfo | null> {
+	if (!isTauri()) {
+		return null;
+	}
+
+	try {
+		return await invoke<BackendInfo>("ping");
+	} catch (error) {
+		console.error("[Tauri] Backend did not answer ping:", error);
+		return null;
+	}
+}